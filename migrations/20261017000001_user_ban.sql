@@ -0,0 +1,4 @@
+-- Блокировка пользователей администратором
+ALTER TABLE users
+    ADD COLUMN is_banned BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN disabled_at TIMESTAMPTZ;
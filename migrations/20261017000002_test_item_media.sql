@@ -0,0 +1,13 @@
+-- Медиафайлы (изображения и аудио), хранящиеся на диске сервера
+CREATE TABLE media (
+    id SERIAL PRIMARY KEY,
+    file_name TEXT NOT NULL UNIQUE,
+    mime_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Вложения к вопросам тестов
+ALTER TABLE test_items
+    ADD COLUMN image_media_id INTEGER REFERENCES media(id) ON DELETE SET NULL,
+    ADD COLUMN audio_media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
@@ -0,0 +1,13 @@
+-- Дата прохождения нужна для публичной карточки результата
+ALTER TABLE test_results ADD COLUMN IF NOT EXISTS submitted_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Публичные ссылки на результаты тестов
+CREATE TABLE result_shares (
+    token TEXT PRIMARY KEY,
+    result_id INTEGER NOT NULL REFERENCES test_results(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX result_shares_result_id_idx ON result_shares (result_id);
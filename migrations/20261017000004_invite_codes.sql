@@ -0,0 +1,9 @@
+-- Коды приглашений для закрытых инсталляций
+CREATE TABLE invite_codes (
+    code TEXT PRIMARY KEY,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    used_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    used_at TIMESTAMPTZ
+);
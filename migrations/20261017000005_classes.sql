@@ -0,0 +1,26 @@
+-- Учебные классы: учитель и его ученики
+CREATE TABLE classes (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    teacher_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Если включено, ученики класса видят только разрешенный учителем глобальный контент
+    restrict_content BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE class_members (
+    class_id INTEGER NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (class_id, user_id)
+);
+
+-- Глобальные тесты, разрешенные классу с ограниченным контентом
+CREATE TABLE class_tests (
+    class_id INTEGER NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    test_id INTEGER NOT NULL REFERENCES tests(id) ON DELETE CASCADE,
+    PRIMARY KEY (class_id, test_id)
+);
+
+-- Собственные тесты класса не попадают в общий каталог
+ALTER TABLE tests ADD COLUMN class_id INTEGER REFERENCES classes(id) ON DELETE CASCADE;
@@ -0,0 +1,21 @@
+-- Предложения с озвучкой для упражнений
+CREATE TABLE sentences (
+    id SERIAL PRIMARY KEY,
+    text TEXT NOT NULL,
+    pinyin TEXT NOT NULL,
+    translation TEXT NOT NULL,
+    audio_media_id INTEGER REFERENCES media(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Попытки повторения предложения за диктором (shadowing)
+CREATE TABLE shadowing_attempts (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    sentence_id INTEGER NOT NULL REFERENCES sentences(id) ON DELETE CASCADE,
+    transcript TEXT NOT NULL,
+    score REAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX shadowing_attempts_user_sentence_idx ON shadowing_attempts (user_id, sentence_id, created_at);
@@ -0,0 +1,12 @@
+-- Предложения тоже учитываются в прогрессе
+ALTER TYPE content_type_enum ADD VALUE IF NOT EXISTS 'sentence';
+
+-- Слабые места пользователя: элементы, в которых он ошибался
+CREATE TABLE weak_items (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    misses INTEGER NOT NULL DEFAULT 1,
+    last_missed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, content_type, content_id)
+);
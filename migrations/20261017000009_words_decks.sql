@@ -0,0 +1,26 @@
+-- Слова (из одного или нескольких иероглифов)
+CREATE TABLE words (
+    id SERIAL PRIMARY KEY,
+    simplified TEXT NOT NULL UNIQUE,
+    pinyin TEXT NOT NULL,
+    translation TEXT NOT NULL,
+    hsk_level SMALLINT,
+    hsk_version TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Колоды: упорядоченные наборы контента для изучения
+CREATE TABLE decks (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE deck_items (
+    deck_id INTEGER NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (deck_id, content_type, content_id)
+);
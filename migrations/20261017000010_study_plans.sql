@@ -0,0 +1,14 @@
+-- Учебные планы: выучить колоду к заданной дате
+CREATE TABLE study_plans (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    deck_id INTEGER NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+    start_date DATE NOT NULL DEFAULT CURRENT_DATE,
+    target_date DATE NOT NULL,
+    -- Сколько элементов оставалось выучить на момент создания плана
+    initial_remaining INTEGER NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX study_plans_user_id_idx ON study_plans (user_id) WHERE is_active;
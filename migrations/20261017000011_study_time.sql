@@ -0,0 +1,8 @@
+-- Время занятий по дням и видам активности
+CREATE TABLE study_time (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    activity TEXT NOT NULL,
+    seconds INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, day, activity)
+);
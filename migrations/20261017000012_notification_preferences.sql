@@ -0,0 +1,12 @@
+-- Настройки уведомлений: канал доставки для каждого вида событий.
+-- Отсутствие строки означает значение по умолчанию (канал включен).
+CREATE TABLE notification_preferences (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    PRIMARY KEY (user_id, event, channel)
+);
+
+-- Нужно ли клиенту показать уведомление на рабочем столе
+ALTER TABLE notifications ADD COLUMN desktop BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Показывать ли другим, что пользователь сейчас занимается
+ALTER TABLE users ADD COLUMN presence_visible BOOLEAN NOT NULL DEFAULT TRUE;
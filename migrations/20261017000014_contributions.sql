@@ -0,0 +1,19 @@
+-- Предложения нового контента от пользователей (очередь модерации)
+CREATE TABLE contributions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    reviewed_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    -- id созданного слова или предложения после принятия
+    content_id INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX contributions_status_idx ON contributions (status, created_at);
+
+-- Авторство принятого контента
+ALTER TABLE words ADD COLUMN contributor_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE sentences ADD COLUMN contributor_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,6 @@
+-- Метаданные иероглифов из базы Unihan
+ALTER TABLE hieroglyphs
+    ADD COLUMN definition TEXT,
+    ADD COLUMN readings TEXT,
+    ADD COLUMN total_strokes SMALLINT,
+    ADD COLUMN radical_index SMALLINT;
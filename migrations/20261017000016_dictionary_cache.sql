@@ -0,0 +1,6 @@
+-- Кеш ответов внешнего словаря, чтобы повторные запросы работали без сети
+CREATE TABLE dictionary_cache (
+    query TEXT PRIMARY KEY,
+    entries JSONB NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
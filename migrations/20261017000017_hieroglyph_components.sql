@@ -0,0 +1,10 @@
+-- Граф разложения иероглифов на компоненты
+CREATE TABLE hieroglyph_components (
+    hieroglyph_id INTEGER NOT NULL REFERENCES hieroglyphs(id) ON DELETE CASCADE,
+    component_id INTEGER NOT NULL REFERENCES hieroglyphs(id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    PRIMARY KEY (hieroglyph_id, component_id),
+    CHECK (hieroglyph_id <> component_id)
+);
+
+CREATE INDEX hieroglyph_components_component_idx ON hieroglyph_components (component_id);
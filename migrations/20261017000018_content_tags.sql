@@ -0,0 +1,8 @@
+-- Теги контента для группировки и массовых операций
+ALTER TABLE hieroglyphs ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE words ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE sentences ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX hieroglyphs_tags_idx ON hieroglyphs USING GIN (tags);
+CREATE INDEX words_tags_idx ON words USING GIN (tags);
+CREATE INDEX sentences_tags_idx ON sentences USING GIN (tags);
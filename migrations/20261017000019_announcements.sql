@@ -0,0 +1,22 @@
+-- Объявления администрации: техработы, новый контент и т.п.
+CREATE TABLE announcements (
+    id SERIAL PRIMARY KEY,
+    author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    -- Аудитория: все пользователи, ученики и учитель класса или только администраторы
+    audience TEXT NOT NULL CHECK (audience IN ('all', 'class', 'admins')),
+    class_id INTEGER REFERENCES classes(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    CHECK ((audience = 'class') = (class_id IS NOT NULL))
+);
+
+CREATE INDEX announcements_created_at_idx ON announcements (created_at DESC);
+
+CREATE TABLE announcement_reads (
+    announcement_id INTEGER NOT NULL REFERENCES announcements(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    read_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (announcement_id, user_id)
+);
@@ -0,0 +1,12 @@
+-- Пользовательские настройки, задаваемые при первом входе и позже в профиле
+CREATE TABLE user_settings (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    interface_language TEXT NOT NULL DEFAULT 'ru' CHECK (interface_language IN ('ru', 'en')),
+    script TEXT NOT NULL DEFAULT 'simplified' CHECK (script IN ('simplified', 'traditional')),
+    -- Время ежедневного напоминания о занятиях (UTC)
+    reminder_time TIME,
+    last_reminded_on DATE,
+    -- Когда пользователь прошел или пропустил вводный сценарий
+    onboarding_finished_at TIMESTAMPTZ,
+    onboarding_skipped BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,25 @@
+-- Интервальное повторение (SM-2) выученного контента
+ALTER TABLE user_progress
+    ADD COLUMN next_review_at TIMESTAMPTZ,
+    ADD COLUMN ease_factor REAL NOT NULL DEFAULT 2.5,
+    ADD COLUMN interval_days INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN repetitions INTEGER NOT NULL DEFAULT 0;
+
+-- Уже выученное ставится в очередь повторения сразу
+UPDATE user_progress SET next_review_at = NOW() WHERE is_learned;
+
+CREATE INDEX user_progress_due_idx ON user_progress (user_id, next_review_at) WHERE is_learned;
+
+-- Журнал повторений
+CREATE TABLE review_log (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    quality SMALLINT NOT NULL CHECK (quality BETWEEN 0 AND 5),
+    ease_factor REAL NOT NULL,
+    interval_days INTEGER NOT NULL,
+    reviewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX review_log_user_id_idx ON review_log (user_id, reviewed_at DESC);
@@ -0,0 +1,14 @@
+-- Устойчивые выражения и фразы
+CREATE TABLE phrases (
+    id SERIAL PRIMARY KEY,
+    text TEXT NOT NULL UNIQUE,
+    pinyin TEXT NOT NULL,
+    translation TEXT NOT NULL,
+    -- Пояснение по употреблению
+    usage_note TEXT,
+    hsk_level SMALLINT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX phrases_tags_idx ON phrases USING GIN (tags);
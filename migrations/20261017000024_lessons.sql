@@ -0,0 +1,19 @@
+-- Уроки: упорядоченные наборы иероглифов, слов и правил грамматики
+CREATE TABLE lessons (
+    id SERIAL PRIMARY KEY,
+    title TEXT NOT NULL,
+    description TEXT,
+    -- Порядок уроков в курсе
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE lesson_items (
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (lesson_id, content_type, content_id)
+);
+
+CREATE INDEX lesson_items_order_idx ON lesson_items (lesson_id, position);
@@ -0,0 +1,15 @@
+-- Правила грамматики с примерами
+CREATE TABLE grammar_rules (
+    id SERIAL PRIMARY KEY,
+    title TEXT NOT NULL,
+    explanation TEXT NOT NULL,
+    -- Схема конструкции, например «A 比 B + прил.»
+    pattern TEXT NOT NULL,
+    -- Примеры: [{"text": ..., "pinyin": ..., "translation": ...}]
+    examples JSONB NOT NULL DEFAULT '[]',
+    hsk_level SMALLINT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX grammar_rules_tags_idx ON grammar_rules USING GIN (tags);
@@ -0,0 +1,43 @@
+-- Синхронизация между устройствами: у каждой синхронизируемой записи есть версия
+-- из общей последовательности. Клиент забирает изменения с версией больше своего курсора.
+CREATE SEQUENCE sync_version_seq;
+
+CREATE FUNCTION bump_sync_version() RETURNS trigger AS $$
+BEGIN
+    -- Пустые обновления (например, upsert без изменений) версию не двигают
+    IF TG_OP = 'INSERT' OR NEW IS DISTINCT FROM OLD THEN
+        NEW.version := nextval('sync_version_seq');
+        NEW.updated_at := NOW();
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE user_progress
+    ADD COLUMN version BIGINT NOT NULL DEFAULT nextval('sync_version_seq'),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+CREATE INDEX user_progress_sync_idx ON user_progress (user_id, version);
+CREATE TRIGGER user_progress_sync_version BEFORE INSERT OR UPDATE ON user_progress
+    FOR EACH ROW EXECUTE FUNCTION bump_sync_version();
+
+ALTER TABLE user_settings
+    ADD COLUMN version BIGINT NOT NULL DEFAULT nextval('sync_version_seq'),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+CREATE TRIGGER user_settings_sync_version BEFORE INSERT OR UPDATE ON user_settings
+    FOR EACH ROW EXECUTE FUNCTION bump_sync_version();
+
+-- Заметки пользователя к элементам контента. Удаление — мягкое, чтобы его
+-- можно было передать на другие устройства.
+CREATE TABLE user_notes (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    body TEXT NOT NULL DEFAULT '',
+    deleted BOOLEAN NOT NULL DEFAULT FALSE,
+    version BIGINT NOT NULL DEFAULT nextval('sync_version_seq'),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, content_type, content_id)
+);
+CREATE INDEX user_notes_sync_idx ON user_notes (user_id, version);
+CREATE TRIGGER user_notes_sync_version BEFORE INSERT OR UPDATE ON user_notes
+    FOR EACH ROW EXECUTE FUNCTION bump_sync_version();
@@ -0,0 +1,5 @@
+-- Уровень HSK иероглифа для фильтрации списка
+ALTER TABLE hieroglyphs ADD COLUMN hsk_level SMALLINT;
+
+CREATE INDEX hieroglyphs_hsk_level_idx ON hieroglyphs (hsk_level);
+CREATE INDEX hieroglyphs_pinyin_idx ON hieroglyphs (lower(pinyin) text_pattern_ops);
@@ -0,0 +1,28 @@
+-- Поиск по словарю: триграммы и полнотекстовые индексы
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Пиньинь без тонов и пробелов, как в matching::normalize_pinyin: «nǐ hǎo» и «ni3 hao3» → «nihao»
+CREATE FUNCTION plain_pinyin(pinyin TEXT) RETURNS TEXT AS $$
+    SELECT regexp_replace(
+        translate(lower(pinyin), 'āáǎàēéěèīíǐìōóǒòūúǔùüǖǘǚǜ', 'aaaaeeeeiiiioooouuuuvvvvv'),
+        '[^a-z]', '', 'g'
+    )
+$$ LANGUAGE SQL IMMUTABLE;
+
+ALTER TABLE hieroglyphs ADD COLUMN pinyin_plain TEXT GENERATED ALWAYS AS (plain_pinyin(pinyin)) STORED;
+ALTER TABLE words ADD COLUMN pinyin_plain TEXT GENERATED ALWAYS AS (plain_pinyin(pinyin)) STORED;
+ALTER TABLE phrases ADD COLUMN pinyin_plain TEXT GENERATED ALWAYS AS (plain_pinyin(pinyin)) STORED;
+
+CREATE INDEX hieroglyphs_pinyin_plain_trgm_idx ON hieroglyphs USING GIN (pinyin_plain gin_trgm_ops);
+CREATE INDEX hieroglyphs_translation_trgm_idx ON hieroglyphs USING GIN (translation gin_trgm_ops);
+CREATE INDEX hieroglyphs_translation_fts_idx ON hieroglyphs USING GIN (to_tsvector('simple', translation));
+
+CREATE INDEX words_simplified_trgm_idx ON words USING GIN (simplified gin_trgm_ops);
+CREATE INDEX words_pinyin_plain_trgm_idx ON words USING GIN (pinyin_plain gin_trgm_ops);
+CREATE INDEX words_translation_trgm_idx ON words USING GIN (translation gin_trgm_ops);
+CREATE INDEX words_translation_fts_idx ON words USING GIN (to_tsvector('simple', translation));
+
+CREATE INDEX phrases_text_trgm_idx ON phrases USING GIN (text gin_trgm_ops);
+CREATE INDEX phrases_pinyin_plain_trgm_idx ON phrases USING GIN (pinyin_plain gin_trgm_ops);
+CREATE INDEX phrases_translation_trgm_idx ON phrases USING GIN (translation gin_trgm_ops);
+CREATE INDEX phrases_translation_fts_idx ON phrases USING GIN (to_tsvector('simple', translation));
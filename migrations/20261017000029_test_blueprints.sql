@@ -0,0 +1,40 @@
+-- Теги вопросов: навык, уровень HSK и грамматическая тема
+CREATE TYPE question_skill_enum AS ENUM ('listening', 'reading', 'writing', 'vocabulary', 'grammar');
+
+ALTER TABLE test_items
+    ADD COLUMN skill question_skill_enum,
+    ADD COLUMN hsk_level SMALLINT,
+    ADD COLUMN grammar_rule_id INTEGER REFERENCES grammar_rules(id) ON DELETE SET NULL,
+    -- Для вопросов сгенерированных тестов — исходный вопрос из банка
+    ADD COLUMN source_item_id INTEGER REFERENCES test_items(id) ON DELETE SET NULL;
+
+CREATE INDEX test_items_bank_idx ON test_items (skill, hsk_level) WHERE source_item_id IS NULL;
+CREATE INDEX test_items_source_idx ON test_items (source_item_id) WHERE source_item_id IS NOT NULL;
+
+-- Шаблоны тестов: «10 вопросов на аудирование + 10 на чтение уровня HSK 2»
+CREATE TABLE test_blueprints (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE blueprint_sections (
+    id SERIAL PRIMARY KEY,
+    blueprint_id INTEGER NOT NULL REFERENCES test_blueprints(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    question_count INTEGER NOT NULL CHECK (question_count > 0),
+    -- Пустой фильтр означает «любой»
+    skill question_skill_enum,
+    hsk_level SMALLINT,
+    grammar_rule_id INTEGER REFERENCES grammar_rules(id) ON DELETE CASCADE
+);
+
+CREATE INDEX blueprint_sections_blueprint_idx ON blueprint_sections (blueprint_id, position);
+
+-- Сгенерированный по шаблону тест принадлежит одному пользователю
+ALTER TABLE tests
+    ADD COLUMN generated_for INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    ADD COLUMN blueprint_id INTEGER REFERENCES test_blueprints(id) ON DELETE SET NULL;
+
+CREATE INDEX tests_generated_for_idx ON tests (generated_for, created_at) WHERE generated_for IS NOT NULL;
@@ -0,0 +1,52 @@
+-- Связь слов с иероглифами, из которых они состоят
+CREATE TABLE word_characters (
+    word_id INTEGER NOT NULL REFERENCES words(id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    hieroglyph_id INTEGER NOT NULL REFERENCES hieroglyphs(id) ON DELETE CASCADE,
+    PRIMARY KEY (word_id, position)
+);
+
+CREATE INDEX word_characters_hieroglyph_idx ON word_characters (hieroglyph_id);
+
+-- Слова добавляются из многих мест (импорт, заявки, админка), поэтому связи
+-- поддерживаются триггерами, а не кодом сервера
+CREATE FUNCTION link_word_characters() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM word_characters WHERE word_id = NEW.id;
+    INSERT INTO word_characters (word_id, position, hieroglyph_id)
+    SELECT NEW.id, c.position::smallint, h.id
+    FROM regexp_split_to_table(NEW.simplified, '') WITH ORDINALITY AS c(ch, position)
+    JOIN hieroglyphs h ON h.character = c.ch
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER words_link_characters AFTER INSERT OR UPDATE OF simplified ON words
+    FOR EACH ROW EXECUTE FUNCTION link_word_characters();
+
+-- Новый иероглиф связывается с уже существующими словами
+CREATE FUNCTION link_character_words() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        DELETE FROM word_characters WHERE hieroglyph_id = NEW.id;
+    END IF;
+    INSERT INTO word_characters (word_id, position, hieroglyph_id)
+    SELECT w.id, c.position::smallint, NEW.id
+    FROM words w,
+         regexp_split_to_table(w.simplified, '') WITH ORDINALITY AS c(ch, position)
+    WHERE strpos(w.simplified, NEW.character) > 0 AND c.ch = NEW.character
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER hieroglyphs_link_words AFTER INSERT OR UPDATE OF character ON hieroglyphs
+    FOR EACH ROW EXECUTE FUNCTION link_character_words();
+
+INSERT INTO word_characters (word_id, position, hieroglyph_id)
+SELECT w.id, c.position::smallint, h.id
+FROM words w,
+     regexp_split_to_table(w.simplified, '') WITH ORDINALITY AS c(ch, position)
+JOIN hieroglyphs h ON h.character = c.ch
+ON CONFLICT DO NOTHING;
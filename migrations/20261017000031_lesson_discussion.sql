@@ -0,0 +1,24 @@
+-- Заметки учителя к уроку для своего класса
+CREATE TABLE lesson_notes (
+    id SERIAL PRIMARY KEY,
+    class_id INTEGER NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX lesson_notes_class_lesson_idx ON lesson_notes (class_id, lesson_id, created_at);
+
+-- Обсуждение урока внутри класса: вопросы (parent_id IS NULL) и ответы на них
+CREATE TABLE lesson_posts (
+    id SERIAL PRIMARY KEY,
+    class_id INTEGER NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES lesson_posts(id) ON DELETE CASCADE,
+    author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX lesson_posts_class_lesson_idx ON lesson_posts (class_id, lesson_id, created_at);
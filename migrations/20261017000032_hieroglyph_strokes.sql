@@ -0,0 +1,9 @@
+-- Порядок черт иероглифов в формате Make Me A Hanzi
+CREATE TABLE hieroglyph_strokes (
+    hieroglyph_id INTEGER PRIMARY KEY REFERENCES hieroglyphs(id) ON DELETE CASCADE,
+    -- Контуры черт в порядке написания (SVG path, система координат Make Me A Hanzi)
+    strokes JSONB NOT NULL,
+    -- Осевые линии черт: по ним клиент анимирует написание
+    medians JSONB NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+-- Озвучка иероглифов и слов; файлы лежат на диске или в S3, здесь только описание
+CREATE TABLE audio (
+    id SERIAL PRIMARY KEY,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    mime_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audio_content_idx ON audio (content_type, content_id);
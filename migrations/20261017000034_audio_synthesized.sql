@@ -0,0 +1,2 @@
+-- Озвучка, синтезированная TTS для иероглифов без записи; заменяется загруженной записью
+ALTER TABLE audio ADD COLUMN synthesized BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Иллюстрация к иероглифу, загружается из редактора контента
+ALTER TABLE hieroglyphs ADD COLUMN image_media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
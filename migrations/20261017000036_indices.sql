@@ -0,0 +1,25 @@
+-- Недостающие индексы для горячих запросов. Уникальные ограничения могли быть
+-- созданы исходной схемой под стандартными именами PostgreSQL, поэтому
+-- здесь те же имена и IF NOT EXISTS: повторный индекс не появится.
+CREATE UNIQUE INDEX IF NOT EXISTS users_nickname_key ON users (nickname);
+CREATE UNIQUE INDEX IF NOT EXISTS user_progress_user_id_content_type_content_id_key
+    ON user_progress (user_id, content_type, content_id);
+
+-- Обновление токена ищет сессию по самому токену, выход из всех сессий — по пользователю
+CREATE UNIQUE INDEX IF NOT EXISTS refresh_sessions_refresh_token_idx ON refresh_sessions (refresh_token);
+CREATE INDEX IF NOT EXISTS refresh_sessions_user_id_idx ON refresh_sessions (user_id);
+
+-- Достижения и статистика считают выученное пользователем за период
+CREATE INDEX IF NOT EXISTS user_progress_learned_idx ON user_progress (user_id, learned_at) WHERE is_learned;
+
+CREATE INDEX IF NOT EXISTS test_items_test_id_idx ON test_items (test_id, id);
+CREATE INDEX IF NOT EXISTS test_results_user_test_idx ON test_results (user_id, test_id);
+
+-- Точные совпадения при проверке дубликатов и поиске в словаре
+CREATE INDEX IF NOT EXISTS hieroglyphs_character_idx ON hieroglyphs (character);
+CREATE INDEX IF NOT EXISTS phrases_text_idx ON phrases (text);
+CREATE INDEX IF NOT EXISTS sentences_text_idx ON sentences (text);
+
+-- Поиск пользователей по части никнейма в панели администратора
+CREATE INDEX IF NOT EXISTS users_nickname_trgm_idx ON users USING GIN (lower(nickname) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS sentences_text_trgm_idx ON sentences USING GIN (text gin_trgm_ops);
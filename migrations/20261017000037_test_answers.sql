@@ -0,0 +1,12 @@
+-- Ответы пользователя на каждый вопрос, чтобы после теста можно было разобрать ошибки
+CREATE TABLE test_answers (
+    result_id INTEGER NOT NULL REFERENCES test_results(id) ON DELETE CASCADE,
+    test_item_id INTEGER NOT NULL REFERENCES test_items(id) ON DELETE CASCADE,
+    -- NULL, если на вопрос не ответили
+    answer TEXT,
+    is_correct BOOLEAN NOT NULL,
+    PRIMARY KEY (result_id, test_item_id)
+);
+
+-- Пояснение к вопросу показывается только в разборе после отправки теста
+ALTER TABLE test_items ADD COLUMN explanation TEXT;
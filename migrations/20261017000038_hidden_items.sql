@@ -0,0 +1,8 @@
+-- Элементы, которые пользователь попросил больше не показывать
+CREATE TABLE hidden_items (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_type content_type_enum NOT NULL,
+    content_id INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, content_type, content_id)
+);
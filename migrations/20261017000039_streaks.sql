@@ -0,0 +1,11 @@
+-- Дни занятий для серии: день засчитывается за выученный элемент, повторение или тест.
+-- `frozen` — пропущенный день, закрытый заморозкой серии
+CREATE TABLE study_days (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    frozen BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (user_id, day)
+);
+
+-- Запас заморозок: выдаются за каждую неделю серии
+ALTER TABLE users ADD COLUMN streak_freezes INTEGER NOT NULL DEFAULT 0 CHECK (streak_freezes >= 0);
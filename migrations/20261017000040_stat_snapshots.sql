@@ -0,0 +1,12 @@
+-- Итоги пользователя на конец каждого дня для графиков за длинный период
+CREATE TABLE stat_snapshots (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    learned_total INTEGER NOT NULL,
+    reviews_total INTEGER NOT NULL,
+    streak_days INTEGER NOT NULL,
+    PRIMARY KEY (user_id, day)
+);
+
+-- Задача снимков ищет последний обработанный день
+CREATE INDEX stat_snapshots_day_idx ON stat_snapshots (day);
@@ -0,0 +1,15 @@
+-- Опыт и уровень пользователя
+ALTER TABLE users ADD COLUMN xp BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN level INTEGER NOT NULL DEFAULT 1;
+
+-- Начисления опыта: каждый источник (выученный элемент, тест, урок) дает опыт один раз
+CREATE TABLE xp_awards (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    awarded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, source, source_id)
+);
+
+ALTER TABLE stat_snapshots ADD COLUMN xp BIGINT NOT NULL DEFAULT 0;
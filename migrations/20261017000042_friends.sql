@@ -0,0 +1,24 @@
+-- Заявки в друзья
+CREATE TABLE friend_requests (
+    id SERIAL PRIMARY KEY,
+    from_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    to_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'accepted', 'declined')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMPTZ,
+    CHECK (from_user_id <> to_user_id)
+);
+
+-- Между двумя пользователями не больше одной ожидающей заявки, в какую бы сторону она ни шла
+CREATE UNIQUE INDEX friend_requests_pending_pair ON friend_requests
+    (LEAST(from_user_id, to_user_id), GREATEST(from_user_id, to_user_id)) WHERE status = 'pending';
+CREATE INDEX friend_requests_to_user ON friend_requests (to_user_id) WHERE status = 'pending';
+
+-- Дружба хранится двумя строками, по одной на каждого из друзей
+CREATE TABLE friendships (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    friend_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, friend_id),
+    CHECK (user_id <> friend_id)
+);
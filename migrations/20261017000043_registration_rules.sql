@@ -0,0 +1,10 @@
+-- Почта пользователя; при правилах доменов регистрации она обязательна
+ALTER TABLE users ADD COLUMN email TEXT;
+CREATE UNIQUE INDEX users_email_unique ON users (lower(email)) WHERE email IS NOT NULL;
+
+-- Публичные регистрации по IP для суточного ограничения
+CREATE TABLE registrations_by_ip (
+    ip TEXT NOT NULL,
+    registered_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX registrations_by_ip_ip ON registrations_by_ip (ip, registered_at);
@@ -0,0 +1,18 @@
+-- Тексты для чтения: предложения подряд и общая запись диктора
+CREATE TABLE passages (
+    id SERIAL PRIMARY KEY,
+    title TEXT NOT NULL,
+    audio_media_id INTEGER REFERENCES media(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Предложения текста и их место в записи, в миллисекундах от начала
+CREATE TABLE passage_sentences (
+    passage_id INTEGER NOT NULL REFERENCES passages(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    sentence_id INTEGER NOT NULL REFERENCES sentences(id) ON DELETE CASCADE,
+    start_ms INTEGER NOT NULL CHECK (start_ms >= 0),
+    end_ms INTEGER NOT NULL,
+    PRIMARY KEY (passage_id, position),
+    CHECK (end_ms > start_ms)
+);
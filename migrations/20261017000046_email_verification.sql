@@ -0,0 +1,13 @@
+-- Подтверждение почты: когда пользователь подтвердил текущий адрес
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- Коды подтверждения почты; хранится только SHA-256 кода.
+-- Адрес запоминается, чтобы код не подтвердил почту, которую уже сменили
+CREATE TABLE email_verification_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX email_verification_tokens_user ON email_verification_tokens (user_id);
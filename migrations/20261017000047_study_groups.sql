@@ -0,0 +1,21 @@
+-- Учебные группы: несколько друзей с общей целью на неделю, отдельно от классов
+CREATE TABLE study_groups (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    join_code TEXT NOT NULL UNIQUE,
+    -- Цель группы на неделю в очках опыта
+    weekly_goal INTEGER NOT NULL CHECK (weekly_goal > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Последние неделя и день, о которых участникам уже сообщили
+    goal_notified_week DATE,
+    streak_notified_day DATE
+);
+
+CREATE TABLE study_group_members (
+    group_id INTEGER NOT NULL REFERENCES study_groups(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, user_id)
+);
+CREATE INDEX study_group_members_user ON study_group_members (user_id);
@@ -0,0 +1,13 @@
+-- Ограничение нагрузки повторений: новые элементы не добавляются, пока прогноз
+-- повторений на ближайшие дни выше порога
+CREATE TABLE review_load_settings (
+    -- Единственная строка с настройками по умолчанию, которые меняет админ
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    daily_limit INTEGER NOT NULL DEFAULT 150 CHECK (daily_limit > 0),
+    -- off — не проверять, warn — предупреждать, block — не давать отметить «выучено»
+    mode TEXT NOT NULL DEFAULT 'warn' CHECK (mode IN ('off', 'warn', 'block'))
+);
+INSERT INTO review_load_settings DEFAULT VALUES;
+
+-- Собственный порог пользователя вместо общего
+ALTER TABLE user_settings ADD COLUMN daily_review_limit INTEGER CHECK (daily_review_limit > 0);
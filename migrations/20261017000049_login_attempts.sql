@@ -0,0 +1,15 @@
+-- Журнал попыток входа и временная блокировка после серии неудач
+CREATE TABLE login_attempts (
+    id SERIAL PRIMARY KEY,
+    -- NULL, если такого никнейма нет
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    nickname TEXT NOT NULL,
+    ip TEXT,
+    outcome TEXT NOT NULL CHECK (outcome IN ('success', 'bad_password', 'unknown_user', 'locked')),
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX login_attempts_user_idx ON login_attempts (user_id, id DESC);
+CREATE INDEX login_attempts_attempted_at_idx ON login_attempts (attempted_at);
+
+ALTER TABLE users ADD COLUMN locked_until TIMESTAMPTZ;
@@ -0,0 +1,17 @@
+-- Привязка refresh-сессий к устройству и журнал событий безопасности
+ALTER TABLE refresh_sessions
+    ADD COLUMN device_id TEXT,
+    -- Когда сессию последний раз обновляли с чужого устройства
+    ADD COLUMN device_mismatch_at TIMESTAMPTZ;
+
+CREATE TABLE security_events (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('device_mismatch')),
+    ip TEXT,
+    details TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX security_events_user_idx ON security_events (user_id, id DESC);
+CREATE INDEX security_events_created_at_idx ON security_events (created_at);
@@ -0,0 +1,12 @@
+-- Семейства refresh-сессий: при ротации старый токен не удаляется, а помечается,
+-- и его повторное предъявление отзывает все семейство
+ALTER TABLE refresh_sessions
+    ADD COLUMN family_id TEXT NOT NULL DEFAULT md5(random()::text || clock_timestamp()::text),
+    ADD COLUMN rotated_at TIMESTAMPTZ;
+ALTER TABLE refresh_sessions ALTER COLUMN family_id DROP DEFAULT;
+
+CREATE INDEX refresh_sessions_family_idx ON refresh_sessions (family_id);
+
+ALTER TABLE security_events
+    DROP CONSTRAINT security_events_kind_check,
+    ADD CONSTRAINT security_events_kind_check CHECK (kind IN ('device_mismatch', 'refresh_reuse'));
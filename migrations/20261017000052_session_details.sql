@@ -0,0 +1,7 @@
+-- Сведения об устройстве для списка активных сессий. created_at — время входа,
+-- переходит от токена к токену при ротации; last_used_at — время выдачи токена
+ALTER TABLE refresh_sessions
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip TEXT,
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,17 @@
+-- Личные колоды, собранные из текста пользователя, с карточками-пропусками
+CREATE TABLE mined_decks (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE mined_cards (
+    deck_id INTEGER NOT NULL REFERENCES mined_decks(id) ON DELETE CASCADE,
+    word_id INTEGER NOT NULL REFERENCES words(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    sentence TEXT NOT NULL,
+    cloze TEXT NOT NULL,
+    PRIMARY KEY (deck_id, word_id)
+);
@@ -0,0 +1,14 @@
+-- Заходы тренажера набора иероглифов через IME
+CREATE TABLE typing_runs (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_count INTEGER NOT NULL,
+    correct_count INTEGER NOT NULL,
+    char_count INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    chars_per_minute REAL NOT NULL,
+    accuracy REAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX typing_runs_user_idx ON typing_runs (user_id, created_at);
@@ -0,0 +1,2 @@
+-- Время ответа на повторение, мс; NULL — клиент не прислал или пользователь отвлекся
+ALTER TABLE review_log ADD COLUMN latency_ms INTEGER CHECK (latency_ms >= 0);
@@ -0,0 +1,6 @@
+-- Расписание до ответа: по нему отменяется ошибочная оценка. NULL у записей до отмены ответов
+ALTER TABLE review_log
+    ADD COLUMN prev_ease_factor REAL,
+    ADD COLUMN prev_interval_days INTEGER,
+    ADD COLUMN prev_repetitions INTEGER,
+    ADD COLUMN prev_next_review_at TIMESTAMPTZ;
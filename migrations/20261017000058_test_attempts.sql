@@ -0,0 +1,51 @@
+-- Ограничение времени теста; NULL — тест без ограничения
+ALTER TABLE tests ADD COLUMN time_limit_secs INTEGER CHECK (time_limit_secs > 0);
+-- Сколько секунд пауз из-за потери связи не идет в зачет времени каждой попытки
+ALTER TABLE tests ADD COLUMN pause_allowance_secs INTEGER NOT NULL DEFAULT 0 CHECK (pause_allowance_secs >= 0);
+
+-- Попытки тестов с ограничением времени
+CREATE TABLE test_attempts (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    test_id INTEGER NOT NULL REFERENCES tests(id) ON DELETE CASCADE,
+    -- Копируются из теста при старте: изменение теста не влияет на начатые попытки
+    time_limit_secs INTEGER NOT NULL,
+    -- Запас пауз; учитель может его увеличить
+    pause_allowance_secs INTEGER NOT NULL,
+    -- Сколько секунд пауз уже засчитано
+    paused_secs INTEGER NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Последний запрос клиента (ответ или heartbeat): по нему обнаруживаются паузы
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+    result_id INTEGER REFERENCES test_results(id) ON DELETE SET NULL
+);
+
+-- Не больше одной незавершенной попытки теста у пользователя
+CREATE UNIQUE INDEX test_attempts_open_idx ON test_attempts (user_id, test_id) WHERE finished_at IS NULL;
+
+-- Ответы попытки с моментом ответа
+CREATE TABLE test_attempt_answers (
+    attempt_id INTEGER NOT NULL REFERENCES test_attempts(id) ON DELETE CASCADE,
+    test_item_id INTEGER NOT NULL REFERENCES test_items(id) ON DELETE CASCADE,
+    answer TEXT NOT NULL,
+    answered_at TIMESTAMPTZ NOT NULL,
+    -- Секунд с начала попытки без учета засчитанных пауз
+    elapsed_secs INTEGER NOT NULL,
+    PRIMARY KEY (attempt_id, test_item_id)
+);
+
+-- Журнал попытки: старт, паузы, продления запаса пауз, отправка
+CREATE TABLE test_attempt_events (
+    id SERIAL PRIMARY KEY,
+    attempt_id INTEGER NOT NULL REFERENCES test_attempts(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    -- Для паузы — засчитанные секунды, для продления — добавленные
+    seconds INTEGER,
+    details TEXT,
+    -- Кто выполнил действие, если не сам ученик (учитель, продливший запас)
+    actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX test_attempt_events_attempt_idx ON test_attempt_events (attempt_id, id);
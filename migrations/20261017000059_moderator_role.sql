@@ -0,0 +1,2 @@
+-- Модераторы разбирают очередь предложений пользователей
+ALTER TYPE user_role_enum ADD VALUE IF NOT EXISTS 'moderator';
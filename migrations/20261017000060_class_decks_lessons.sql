@@ -0,0 +1,12 @@
+-- Глобальные колоды и уроки, разрешенные классу с ограниченным контентом
+CREATE TABLE class_decks (
+    class_id INTEGER NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    deck_id INTEGER NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+    PRIMARY KEY (class_id, deck_id)
+);
+
+CREATE TABLE class_lessons (
+    class_id INTEGER NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    PRIMARY KEY (class_id, lesson_id)
+);
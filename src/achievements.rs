@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::errors::AppError;
+use crate::events::{self, Event};
+use crate::jobs;
+use crate::models::{Achievement, ContentType, UserAchievementDetails};
+
+/// Временное окно для счетных условий.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimeWindow {
+    /// В пределах одного календарного дня (любого): «100 слов за день».
+    SingleDay,
+    /// За последние `days` дней.
+    LastDays { days: i32 },
+}
+
+/// Условие получения достижения, хранящееся в `achievements.criteria`.
+///
+/// Условия можно комбинировать через `all`/`any`, например:
+/// `{"type":"all","conditions":[{"type":"learned_count","count":50},{"type":"study_streak","days":7}]}`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Criteria {
+    /// Выучено не меньше `count` элементов (опционально — только заданного типа и в окне времени).
+    LearnedCount {
+        content_type: Option<ContentType>,
+        count: i64,
+        window: Option<TimeWindow>,
+    },
+    /// Выполнено не меньше `count` повторений (опционально — только заданного типа).
+    ReviewsCount {
+        content_type: Option<ContentType>,
+        count: i64,
+    },
+    /// Пройдено не меньше `count` тестов с результатом от `min_score_percent`.
+    TestsPassed {
+        count: i64,
+        #[serde(default)]
+        min_score_percent: f64,
+    },
+    /// Принято не меньше `count` предложений контента от пользователя.
+    ContributionsAccepted { count: i64 },
+    /// Самая длинная серия дней подряд с занятиями — не меньше `days`.
+    StudyStreak { days: i64 },
+    /// Выполнены все вложенные условия.
+    All { conditions: Vec<Criteria> },
+    /// Выполнено хотя бы одно вложенное условие.
+    Any { conditions: Vec<Criteria> },
+}
+
+/// Проверяет, выполнено ли условие достижения для пользователя.
+pub fn is_satisfied<'a>(
+    user_id: i32,
+    criteria: &'a Criteria,
+    pool: &'a PgPool,
+) -> Pin<Box<dyn Future<Output = Result<bool, AppError>> + Send + 'a>> {
+    // Условия вложенные, поэтому рекурсивный future упаковывается в Box
+    Box::pin(async move {
+        match criteria {
+            Criteria::LearnedCount { content_type, count, window } => {
+                let learned = learned_count(user_id, content_type.as_ref(), window.as_ref(), pool).await?;
+                Ok(learned >= *count)
+            }
+            Criteria::ReviewsCount { content_type, count } => {
+                let reviews: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM review_log
+                     WHERE user_id = $1 AND ($2::content_type_enum IS NULL OR content_type = $2)",
+                )
+                    .bind(user_id)
+                    .bind(content_type)
+                    .fetch_one(pool)
+                    .await?;
+                Ok(reviews >= *count)
+            }
+            Criteria::TestsPassed { count, min_score_percent } => {
+                let passed: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(DISTINCT tr.test_id) FROM test_results tr
+                     WHERE tr.user_id = $1 AND tr.score * 100.0 >= $2 * GREATEST(
+                         (SELECT COUNT(*) FROM test_items ti WHERE ti.test_id = tr.test_id), 1)",
+                )
+                    .bind(user_id)
+                    .bind(min_score_percent)
+                    .fetch_one(pool)
+                    .await?;
+                Ok(passed >= *count)
+            }
+            Criteria::ContributionsAccepted { count } => {
+                let accepted: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM contributions WHERE user_id = $1 AND status = 'accepted'",
+                )
+                    .bind(user_id)
+                    .fetch_one(pool)
+                    .await?;
+                Ok(accepted >= *count)
+            }
+            Criteria::StudyStreak { days } => Ok(longest_study_streak(user_id, pool).await? >= *days),
+            Criteria::All { conditions } => {
+                for condition in conditions {
+                    if !is_satisfied(user_id, condition, pool).await? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Criteria::Any { conditions } => {
+                for condition in conditions {
+                    if is_satisfied(user_id, condition, pool).await? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    })
+}
+
+async fn learned_count(
+    user_id: i32,
+    content_type: Option<&ContentType>,
+    window: Option<&TimeWindow>,
+    pool: &PgPool,
+) -> Result<i64, AppError> {
+    const FILTER: &str = "user_id = $1 AND is_learned AND ($2::content_type_enum IS NULL OR content_type = $2)";
+
+    let sql = match window {
+        None => format!("SELECT COUNT(*) FROM user_progress WHERE {}", FILTER),
+        Some(TimeWindow::LastDays { .. }) => format!(
+            "SELECT COUNT(*) FROM user_progress WHERE {} AND learned_at > NOW() - make_interval(days => $3)",
+            FILTER
+        ),
+        Some(TimeWindow::SingleDay) => format!(
+            "SELECT COALESCE(MAX(cnt), 0) FROM (
+                 SELECT COUNT(*) AS cnt FROM user_progress WHERE {} AND learned_at IS NOT NULL
+                 GROUP BY learned_at::date
+             ) per_day",
+            FILTER
+        ),
+    };
+
+    let days = match window {
+        Some(TimeWindow::LastDays { days }) => *days,
+        _ => 0,
+    };
+
+    let mut query = sqlx::query_scalar::<_, i64>(&sql).bind(user_id).bind(content_type);
+    if matches!(window, Some(TimeWindow::LastDays { .. })) {
+        query = query.bind(days);
+    }
+
+    Ok(query.fetch_one(pool).await?)
+}
+
+/// Самая длинная серия дней подряд, в которые пользователь занимался.
+async fn longest_study_streak(user_id: i32, pool: &PgPool) -> Result<i64, AppError> {
+    let streak = sqlx::query_scalar(
+        "WITH days AS (
+             SELECT DISTINCT day FROM study_time WHERE user_id = $1 AND seconds > 0
+         ), groups AS (
+             SELECT day - (ROW_NUMBER() OVER (ORDER BY day))::int AS grp FROM days
+         )
+         SELECT COALESCE(MAX(cnt), 0) FROM (SELECT COUNT(*) AS cnt FROM groups GROUP BY grp) streaks",
+    )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(streak)
+}
+
+/// Выдает пользователю все достижения, условия которых уже выполнены,
+/// и возвращает те из них, что он получил только что.
+pub async fn award_for_user(user_id: i32, achievements: &[Achievement], pool: &PgPool) -> Result<Vec<Achievement>, AppError> {
+    let mut unlocked = Vec::new();
+    for achievement in achievements {
+        let criteria: Criteria = match serde_json::from_value(achievement.criteria.clone()) {
+            Ok(criteria) => criteria,
+            Err(e) => {
+                tracing::warn!("Неизвестный критерий у достижения {}: {}", achievement.id, e);
+                continue;
+            }
+        };
+
+        if is_satisfied(user_id, &criteria, pool).await? {
+            let result = sqlx::query(
+                "INSERT INTO user_achievements (user_id, achievement_id, achieved_at)
+                 VALUES ($1, $2, NOW())
+                 ON CONFLICT (user_id, achievement_id) DO NOTHING",
+            )
+                .bind(user_id)
+                .bind(achievement.id)
+                .execute(pool)
+                .await?;
+
+            if result.rows_affected() > 0 {
+                events::publish(
+                    Event::AchievementUnlocked { user_id, achievement_id: achievement.id, name: achievement.name.clone() },
+                    pool,
+                );
+                unlocked.push(achievement.clone());
+            }
+        }
+    }
+
+    Ok(unlocked)
+}
+
+/// Проверяет только еще не полученные пользователем достижения.
+/// Вызывается после прогресса и тестов, чтобы сразу вернуть новые достижения в ответе.
+pub async fn unlock_new(user_id: i32, pool: &PgPool) -> Result<Vec<Achievement>, AppError> {
+    let pending = sqlx::query_as::<_, Achievement>(
+        "SELECT a.* FROM achievements a
+         WHERE NOT EXISTS (
+             SELECT 1 FROM user_achievements ua WHERE ua.user_id = $1 AND ua.achievement_id = a.id
+         )
+         ORDER BY a.id",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    award_for_user(user_id, &pending, pool).await
+}
+
+/// Полученные пользователем достижения.
+pub async fn for_user(user_id: i32, pool: &PgPool) -> Result<Vec<UserAchievementDetails>, AppError> {
+    let achievements = sqlx::query_as::<_, UserAchievementDetails>(
+        "SELECT a.id, a.name, a.description, a.icon, ua.achieved_at
+         FROM achievements a
+         JOIN user_achievements ua ON a.id = ua.achievement_id
+         WHERE ua.user_id = $1",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(achievements)
+}
+
+/// Пересчитывает достижения всех пользователей (фоновая задача).
+pub async fn recompute_all(pool: PgPool, job_id: u64) -> Result<(), AppError> {
+    let achievements = sqlx::query_as::<_, Achievement>("SELECT * FROM achievements")
+        .fetch_all(&pool)
+        .await?;
+    let user_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM users ORDER BY id")
+        .fetch_all(&pool)
+        .await?;
+
+    jobs::set_total(job_id, user_ids.len());
+    for user_id in user_ids {
+        award_for_user(user_id, &achievements, &pool).await?;
+        jobs::advance(job_id);
+    }
+
+    Ok(())
+}
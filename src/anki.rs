@@ -0,0 +1,109 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::hsk;
+use crate::models::AnkiSelection;
+
+/// Иероглиф для карточки Anki вместе с состоянием изучения.
+#[derive(Debug, sqlx::FromRow)]
+pub struct AnkiCard {
+    pub character: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub example: Option<String>,
+    pub hsk_level: Option<i16>,
+    pub is_learned: bool,
+}
+
+/// Разбирает название колоды: `all` (или пусто) — все иероглифы, `hsk1`…`hsk9` — уровень.
+pub fn parse_deck(deck: Option<&str>) -> Result<Option<i16>, AppError> {
+    let deck = deck.map(|d| d.trim().to_lowercase()).unwrap_or_default();
+    if deck.is_empty() || deck == "all" {
+        return Ok(None);
+    }
+    let level = deck
+        .strip_prefix("hsk")
+        .and_then(|level| level.parse::<i16>().ok())
+        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Колода должна быть all или hsk1…hsk9"))?;
+    hsk::ensure_level(level)?;
+    Ok(Some(level))
+}
+
+/// Название колоды в Anki; `::` задает вложенность.
+pub fn deck_name(level: Option<i16>) -> String {
+    match level {
+        Some(level) => format!("Mandarin::HSK {}", level),
+        None => "Mandarin".to_string(),
+    }
+}
+
+/// Иероглифы пользователя для выгрузки.
+pub async fn cards(
+    user_id: i32,
+    level: Option<i16>,
+    selection: AnkiSelection,
+    pool: &PgPool,
+) -> Result<Vec<AnkiCard>, AppError> {
+    let condition = match selection {
+        AnkiSelection::All => "TRUE",
+        AnkiSelection::Learned => "COALESCE(up.is_learned, FALSE)",
+        AnkiSelection::Unlearned => "NOT COALESCE(up.is_learned, FALSE)",
+        AnkiSelection::Due => "up.is_learned AND up.next_review_at <= NOW()",
+    };
+    let cards = sqlx::query_as::<_, AnkiCard>(&format!(
+        "SELECT h.character, h.pinyin, h.translation, h.example, h.hsk_level,
+                COALESCE(up.is_learned, FALSE) AS is_learned
+         FROM hieroglyphs h
+         LEFT JOIN user_progress up
+             ON up.user_id = $1 AND up.content_type = 'hieroglyph' AND up.content_id = h.id
+         WHERE ($2::smallint IS NULL OR h.hsk_level = $2) AND {}
+         ORDER BY h.hsk_level NULLS LAST, h.id",
+        condition
+    ))
+        .bind(user_id)
+        .bind(level)
+        .fetch_all(pool)
+        .await?;
+    Ok(cards)
+}
+
+/// Экранирует поле: табуляция и перевод строки разделяют поля и записи, а поля идут как HTML.
+fn field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Текстовый файл для импорта в Anki (File → Import): заголовки `#` задают
+/// разделитель, тип записи «Basic» и колоду, так что настраивать импорт не нужно.
+/// Лицевая сторона — иероглиф, оборотная — пиньинь, перевод и пример; в третьей
+/// колонке теги (уровень HSK и выучен ли иероглиф).
+pub fn to_tsv(deck: &str, cards: &[AnkiCard]) -> String {
+    let mut lines = vec![
+        "#separator:tab".to_string(),
+        "#html:true".to_string(),
+        "#notetype:Basic".to_string(),
+        format!("#deck:{}", deck),
+        "#tags column:3".to_string(),
+    ];
+    for card in cards {
+        let mut back = format!("{}<br>{}", field(&card.pinyin), field(&card.translation));
+        if let Some(example) = &card.example {
+            back.push_str(&format!("<br><br>{}", field(example)));
+        }
+        let mut tags = vec!["mandarin".to_string()];
+        if let Some(level) = card.hsk_level {
+            tags.push(format!("hsk{}", level));
+        }
+        if card.is_learned {
+            tags.push("learned".to_string());
+        }
+        lines.push(format!("{}\t{}\t{}", field(&card.character), back, tags.join(" ")));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
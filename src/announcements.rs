@@ -0,0 +1,73 @@
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::{Announcement, AnnouncementAudience};
+
+impl AnnouncementAudience {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnnouncementAudience::All => "all",
+            AnnouncementAudience::Class => "class",
+            AnnouncementAudience::Admins => "admins",
+        }
+    }
+}
+
+/// SQL-условие видимости объявления `a` для пользователя `$1` (NULL для гостя);
+/// `$2` — является ли пользователь администратором.
+const VISIBILITY_CONDITION: &str = "(
+    a.audience = 'all'
+    OR (a.audience = 'admins' AND $2)
+    OR (a.audience = 'class' AND (
+        a.class_id IN (SELECT class_id FROM class_members WHERE user_id = $1)
+        OR a.class_id IN (SELECT id FROM classes WHERE teacher_id = $1)
+    ))
+)";
+
+/// Действующие объявления, видимые пользователю, от новых к старым.
+pub async fn visible(user_id: Option<i32>, is_admin: bool, pool: &PgPool) -> Result<Vec<Announcement>, AppError> {
+    let announcements = sqlx::query_as::<_, Announcement>(&format!(
+        "SELECT a.*, EXISTS (
+             SELECT 1 FROM announcement_reads r WHERE r.announcement_id = a.id AND r.user_id = $1
+         ) AS read
+         FROM announcements a
+         WHERE {} AND (a.expires_at IS NULL OR a.expires_at > NOW())
+         ORDER BY a.created_at DESC",
+        VISIBILITY_CONDITION
+    ))
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(announcements)
+}
+
+/// Отмечает объявление прочитанным. Возвращает `false`, если объявление пользователю не видно.
+pub async fn mark_read(id: i32, user_id: i32, is_admin: bool, pool: &PgPool) -> Result<bool, AppError> {
+    let result = sqlx::query(&format!(
+        "INSERT INTO announcement_reads (announcement_id, user_id)
+         SELECT a.id, $1 FROM announcements a WHERE a.id = $3 AND {}
+         ON CONFLICT DO NOTHING",
+        VISIBILITY_CONDITION
+    ))
+        .bind(user_id)
+        .bind(is_admin)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() > 0 {
+        return Ok(true);
+    }
+
+    // Повторная отметка уже прочитанного объявления — не ошибка
+    let already_read: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM announcement_reads WHERE announcement_id = $1 AND user_id = $2)",
+    )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(already_read)
+}
@@ -1,47 +1,256 @@
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName, Method},
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
 use sqlx::postgres::PgPoolOptions;
 use std::env;
 use std::net::SocketAddr;
 use dotenv::dotenv;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::services::ServeDir;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-// Подключаем наши модули
-mod auth;
-mod handlers;
-mod models;
-mod errors;
-
-// Подключаем тестовый модуль, только когда запускаем `cargo test`
-#[cfg(test)]
-mod tests;
+// Модули объявлены в main.rs: сервер собирается в тот же бинарник, что и клиент
+use crate::{
+    assignments, caching, config, consent, doctor, ext, handlers, hygiene, public, rate_limit, settings,
+    snapshots, startup, AppState,
+};
+use crate::authz::{require_auth, require_permission, Admin, Moderator, Permission, RequireRole};
 
-// Структура для хранения состояния приложения (например, пула подключений к БД)
-#[derive(Clone)]
-pub struct AppState {
-    db_pool: sqlx::PgPool,
-}
+// Лимит тела запроса для импорта больших словарей
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
 // Логика создания роутера вынесена в отдельную функцию для тестируемости
 pub fn app(app_state: AppState) -> Router {
-    Router::new()
-        // --- Роуты аутентификации ---
+    // Проверка прав подключается к группе роутов целиком, а не в каждом обработчике,
+    // поэтому новый роут в группе не может остаться без проверки.
+    let user_management = Router::new()
+        .route("/api/admin/users", get(handlers::search_users_handler))
+        .route("/api/admin/users/:id/ban", post(handlers::ban_user_handler))
+        .route("/api/admin/users/:id/unban", post(handlers::unban_user_handler))
+        .route("/api/admin/users/:id/login-attempts", get(handlers::get_login_attempts_handler))
+        .route("/api/admin/users/:id/security-events", get(handlers::get_security_events_handler))
+        .route("/api/admin/users/import", post(handlers::import_roster_handler))
+        .route("/api/admin/invites", post(handlers::create_invites_handler))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_permission(Permission::ManageUsers),
+        ));
+
+    let content_management = Router::new()
+        .route("/api/hieroglyphs", post(handlers::create_hieroglyph_handler))
+        .route("/api/hieroglyphs/export", get(handlers::export_hieroglyphs_handler))
+        .route("/api/hieroglyphs/:id/components", put(handlers::set_hieroglyph_components_handler))
+        .route("/api/hieroglyphs/:id/strokes", put(handlers::set_hieroglyph_strokes_handler))
+        .route("/api/hieroglyphs/:id/audio", post(handlers::upload_hieroglyph_audio_handler))
+        .route("/api/hieroglyphs/:id/image", post(handlers::upload_hieroglyph_image_handler))
+        .route("/api/admin/content-assist", get(handlers::content_assist_handler))
+        .route("/api/words", post(handlers::create_word_handler))
+        .route("/api/words/:id", put(handlers::update_word_handler))
+        .route("/api/words/:id", delete(handlers::delete_word_handler))
+        .route("/api/words/:id/audio", post(handlers::upload_word_audio_handler))
+        .route("/api/audio/:id", delete(handlers::delete_audio_handler))
+        .route("/api/phrases", post(handlers::create_phrase_handler))
+        .route("/api/phrases/:id", put(handlers::update_phrase_handler))
+        .route("/api/phrases/:id", delete(handlers::delete_phrase_handler))
+        .route("/api/grammar", post(handlers::create_grammar_rule_handler))
+        .route("/api/grammar/:id", put(handlers::update_grammar_rule_handler))
+        .route("/api/grammar/:id", delete(handlers::delete_grammar_rule_handler))
+        .route("/api/lessons", post(handlers::create_lesson_handler))
+        .route("/api/lessons/:id", put(handlers::update_lesson_handler))
+        .route("/api/lessons/:id", delete(handlers::delete_lesson_handler))
+        .route("/api/lessons/:id/items", put(handlers::set_lesson_items_handler))
+        .route("/api/admin/achievements/dry-run", post(handlers::dry_run_criteria_handler))
+        .route("/api/tests", post(handlers::create_test_handler))
+        .route("/api/tests/:id", put(handlers::update_test_handler))
+        .route("/api/tests/:id", delete(handlers::delete_test_handler))
+        .route("/api/tests/:id/items", post(handlers::add_test_items_handler))
+        .route("/api/test-items/:id/media", post(handlers::upload_test_item_media_handler))
+        .route("/api/test-items/:id/tags", put(handlers::tag_test_item_handler))
+        .route("/api/blueprints", post(handlers::create_blueprint_handler))
+        .route("/api/blueprints/:id", delete(handlers::delete_blueprint_handler))
+        .route("/api/classes", post(handlers::create_class_handler))
+        .route("/api/announcements", post(handlers::create_announcement_handler))
+        .route("/api/announcements/:id", delete(handlers::delete_announcement_handler))
+        .route("/api/sentences", post(handlers::create_sentence_handler))
+        .route("/api/sentences/:id/audio", post(handlers::upload_sentence_audio_handler))
+        .route("/api/passages", post(handlers::create_passage_handler))
+        .route("/api/passages/:id/audio", post(handlers::upload_passage_audio_handler))
+        .route("/api/admin/hsk/levels", put(handlers::set_hsk_levels_handler))
+        .route(
+            "/api/admin/import",
+            // Словари CC-CEDICT и Unihan весят десятки мегабайт
+            post(handlers::start_import_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/api/admin/import/:id", get(handlers::get_import_handler))
+        .route("/api/admin/import/:id", delete(handlers::discard_import_handler))
+        .route("/api/admin/import/:id/apply", post(handlers::apply_import_handler))
+        .route(
+            "/api/admin/import/strokes",
+            // graphics.txt из Make Me A Hanzi весит около 30 МБ
+            post(handlers::import_strokes_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/api/admin/bulk", post(handlers::bulk_handler))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_permission(Permission::ManageContent),
+        ));
+
+    // Очередь предложений разбирают модераторы, не получая прав на остальной контент
+    let moderation = Router::new()
+        .route("/api/admin/contributions", get(handlers::get_contributions_handler))
+        .route("/api/admin/contributions/:id/accept", post(handlers::accept_contribution_handler))
+        .route("/api/admin/contributions/:id/reject", post(handlers::reject_contribution_handler))
+        .route_layer(middleware::from_extractor_with_state::<RequireRole<Moderator>, _>(app_state.clone()));
+
+    let system = Router::new()
+        .route("/api/admin/recompute", post(handlers::recompute_handler))
+        .route("/api/admin/jobs", get(handlers::get_jobs_handler))
+        .route("/api/admin/jobs/:id", get(handlers::get_job_handler))
+        .route("/api/admin/hygiene", get(handlers::get_hygiene_stats_handler))
+        .route("/api/admin/hygiene/run", post(handlers::run_hygiene_handler))
+        .route("/api/admin/selfcheck", get(handlers::selfcheck_handler))
+        .route("/api/admin/review-load", get(handlers::get_review_load_settings_handler))
+        .route("/api/admin/review-load", put(handlers::update_review_load_settings_handler))
+        .route("/api/admin/legal", post(handlers::publish_legal_document_handler))
+        .route_layer(middleware::from_extractor_with_state::<RequireRole<Admin>, _>(app_state.clone()));
+
+    // Чтение учебного контента. В публичном режиме (`PUBLIC_CONTENT`) доступно
+    // без токена и кешируется, иначе требует авторизации, как и остальные роуты.
+    let content_read = Router::new()
+        .route("/api/hieroglyphs", get(handlers::get_hieroglyphs_handler))
+        .route("/api/hieroglyphs/:id", get(handlers::get_hieroglyph_by_id_handler))
+        .route("/api/hieroglyphs/:id/strokes", get(handlers::get_hieroglyph_strokes_handler))
+        .route("/api/hieroglyphs/:id/audio", get(handlers::get_hieroglyph_audio_handler))
+        .route("/api/words", get(handlers::get_words_handler))
+        .route("/api/words/:id", get(handlers::get_word_by_id_handler))
+        .route("/api/words/:id/audio", get(handlers::get_word_audio_handler))
+        .route("/api/phrases", get(handlers::get_phrases_handler))
+        .route("/api/phrases/:id", get(handlers::get_phrase_by_id_handler))
+        .route("/api/grammar", get(handlers::get_grammar_rules_handler))
+        .route("/api/grammar/:id", get(handlers::get_grammar_rule_by_id_handler))
+        .route("/api/sentences", get(handlers::get_sentences_handler))
+        .route("/api/sentences/:id", get(handlers::get_sentence_by_id_handler))
+        .route("/api/passages", get(handlers::get_passages_handler))
+        .route("/api/passages/:id/timing", get(handlers::get_passage_timing_handler))
+        .route("/api/audio/:id", get(handlers::stream_audio_handler))
+        .route("/api/lookup", get(handlers::lookup_handler))
+        .route("/api/search", get(handlers::search_handler))
+        .route("/api/hsk/:level/hieroglyphs", get(handlers::get_hsk_hieroglyphs_handler))
+        .route_layer(middleware::from_fn(caching::revalidate));
+    let content_read = if config::public_content() {
+        content_read
+            .route_layer(middleware::from_fn(public::cache_headers))
+            .layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET]))
+    } else {
+        content_read.route_layer(middleware::from_fn_with_state(app_state.clone(), require_auth))
+    };
+
+    // Роуты расширения браузера вызываются со страниц любых сайтов,
+    // поэтому CORS открыт только для них, а доступ проверяется ключом API.
+    let extension = Router::new()
+        .route("/api/ext/lookup", get(handlers::ext_lookup_handler))
+        .route("/api/ext/save", post(handlers::ext_save_handler))
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods([Method::GET, Method::POST])
+                .allow_headers([header::CONTENT_TYPE, HeaderName::from_static(ext::API_KEY_HEADER)]),
+        );
+
+    // Подбор пароля и массовая регистрация ограничены по IP и никнейму
+    let auth_limited = Router::new()
         .route("/api/register", post(handlers::register_handler))
         .route("/api/login", post(handlers::login_handler))
         .route("/api/refresh", post(handlers::refresh_handler))
+        .layer(middleware::from_fn(rate_limit::limit_auth));
+
+    Router::new()
+        // --- Роуты аутентификации ---
+        .merge(auth_limited)
         .route("/api/logout", post(handlers::logout_handler))
+        .route("/.well-known/jwks.json", get(handlers::jwks_handler))
+        .route("/api/password/forgot", post(handlers::forgot_password_handler))
+        .route("/api/password/reset", post(handlers::reset_password_handler))
+        .route("/api/verify-email", post(handlers::verify_email_handler))
+        .route("/api/verify-email/resend", post(handlers::resend_verification_handler))
+        .route("/api/legal", get(handlers::get_legal_documents_handler))
+        .route("/api/consent", post(handlers::accept_consent_handler))
+        .route("/api/consent/me", get(handlers::get_my_consent_handler))
         .route("/api/protected", get(handlers::protected_handler))
 
         // --- Роуты для иероглифов ---
-        .route("/api/hieroglyphs", get(handlers::get_hieroglyphs_handler))
-        .route("/api/hieroglyphs", post(handlers::create_hieroglyph_handler))
-        .route("/api/hieroglyphs/:id", get(handlers::get_hieroglyph_by_id_handler))
+        .route("/api/study/prerequisites/:id", get(handlers::get_prerequisites_handler))
+        .route("/api/graph/:type/:id", get(handlers::get_content_graph_handler))
+        .route("/api/export/worksheets", get(handlers::get_worksheets_handler))
+        .route("/api/sentences/grade", post(handlers::grade_sentence_handler))
 
         // --- Роуты для прогресса пользователя ---
+        .route("/api/me", get(handlers::get_me_handler))
+        .route("/api/me/export", get(handlers::export_my_data_handler))
+        .route("/api/export/anki", get(handlers::export_anki_handler))
+        .route(
+            "/api/client-config",
+            get(handlers::get_client_config_handler).layer(middleware::from_fn(caching::revalidate)),
+        )
+        .route("/api/status", get(handlers::get_instance_status_handler))
+        .route("/healthz", get(handlers::healthz_handler))
+        .route("/readyz", get(handlers::readyz_handler))
+        .route("/api/sessions", delete(handlers::revoke_all_sessions_handler))
+        .route("/api/sessions/me", get(handlers::get_my_sessions_handler))
+        .route("/api/sessions/:id", delete(handlers::revoke_session_handler))
+        .route("/api/settings", get(handlers::get_settings_handler))
+        .route("/api/settings", put(handlers::update_settings_handler))
+        .route("/api/onboarding", get(handlers::get_onboarding_handler))
+        .route("/api/onboarding/finish", post(handlers::finish_onboarding_handler))
         .route("/api/progress/me", get(handlers::get_my_progress_handler))
         .route("/api/progress/learn", post(handlers::mark_learned_handler))
+        .route("/api/review/queue", get(handlers::get_review_queue_handler))
+        .route("/api/review/answer", post(handlers::answer_review_handler))
+        .route("/api/reviews/undo", post(handlers::undo_review_handler))
+        .route("/api/review/forecast", get(handlers::get_review_forecast_handler))
+        .route("/api/hsk/:level/progress", get(handlers::get_hsk_progress_handler))
+        .route("/api/study/known", get(handlers::get_known_status_handler))
+        .route("/api/study/list", post(handlers::add_to_study_list_handler))
+        .route("/api/mine", post(handlers::mine_text_handler))
+        .route("/api/mine/decks", post(handlers::create_mined_deck_handler))
+        .route("/api/mine/decks", get(handlers::get_mined_decks_handler))
+        .route("/api/mine/decks/:id", get(handlers::get_mined_deck_handler))
+        .route("/api/mine/decks/:id", delete(handlers::delete_mined_deck_handler))
+        .route("/api/hidden", get(handlers::get_hidden_items_handler))
+        .route("/api/hidden", post(handlers::hide_item_handler))
+        .route("/api/hidden", delete(handlers::unhide_item_handler))
+        .route("/api/sync/pull", get(handlers::sync_pull_handler))
+        .route("/api/sync/push", post(handlers::sync_push_handler))
+        .route("/api/ext/keys", post(handlers::create_api_key_handler))
+        .route("/api/ext/keys", get(handlers::get_api_keys_handler))
+        .route("/api/ext/keys/:id", delete(handlers::revoke_api_key_handler))
+
+        // --- Роуты колод ---
+        .route("/api/decks", get(handlers::get_decks_handler).layer(middleware::from_fn(caching::revalidate)))
+        .route("/api/decks/:id/manifest", get(handlers::get_deck_manifest_handler))
+        .route("/api/decks/:id/items", get(handlers::get_deck_items_handler))
+
+        // --- Роуты уроков ---
+        .route("/api/lessons", get(handlers::get_lessons_handler))
+        .route("/api/lessons/:id", get(handlers::get_lesson_handler))
+        .route("/api/lessons/:id/complete", post(handlers::complete_lesson_handler))
+
+        // --- Роуты учебных планов ---
+        .route("/api/plans", post(handlers::create_plan_handler))
+        .route("/api/plans/me", get(handlers::get_my_plans_handler))
+        .route("/api/plans/:id", delete(handlers::delete_plan_handler))
+
+        // --- Роуты статистики ---
+        .route("/api/stats/time", post(handlers::record_study_time_handler))
+        .route("/api/stats/time", get(handlers::get_study_time_handler))
+        .route("/api/stats/history", get(handlers::get_stats_history_handler))
+        .route("/api/stats/breakdown", get(handlers::get_stats_breakdown_handler))
+        .route("/api/streak/me", get(handlers::get_my_streak_handler))
+        .route("/api/profile/me", get(handlers::get_my_profile_handler))
 
         // --- Роуты для достижений ---
         .route("/api/achievements", get(handlers::get_all_achievements_handler))
@@ -49,8 +258,163 @@ pub fn app(app_state: AppState) -> Router {
 
         // --- Роуты для тестов ---
         .route("/api/tests", get(handlers::get_all_tests_handler))
+        .route("/api/tests/generate", post(handlers::generate_quiz_handler))
         .route("/api/tests/:id", get(handlers::get_test_details_handler))
         .route("/api/tests/:id/submit", post(handlers::submit_test_handler))
+        .route("/api/tests/:id/results/:result_id", get(handlers::get_test_result_handler))
+        .route("/api/tests/:id/attempts", post(handlers::start_test_attempt_handler))
+        .route("/api/test-attempts/:id/heartbeat", post(handlers::test_attempt_heartbeat_handler))
+        .route("/api/test-attempts/:id/answers", put(handlers::answer_test_attempt_handler))
+        .route("/api/test-attempts/:id/submit", post(handlers::submit_test_attempt_handler))
+        .route("/api/test-attempts/:id/pause-allowance", post(handlers::grant_pause_allowance_handler))
+        .route("/api/test-attempts/:id/events", get(handlers::get_test_attempt_events_handler))
+        .route("/api/results/me", get(handlers::get_my_results_handler))
+        .route("/api/blueprints", get(handlers::get_blueprints_handler))
+        .route("/api/blueprints/:id", get(handlers::get_blueprint_handler))
+        .route("/api/blueprints/:id/generate", post(handlers::generate_blueprint_test_handler))
+
+        // --- Роуты учебных классов ---
+        .route("/api/classes/:id/members", post(handlers::add_class_member_handler))
+        .route("/api/classes/:id/content", put(handlers::set_class_content_handler))
+        .route("/api/classes/:id/tests", post(handlers::create_class_test_handler))
+        .route("/api/classes/:id/assignments", post(handlers::create_assignment_handler))
+        .route("/api/classes/:id/gradebook.csv", get(handlers::get_gradebook_handler))
+        .route("/api/classes/:id/presence", get(handlers::get_class_presence_handler))
+        .route("/api/classes/:id/board", get(handlers::class_board_ws_handler))
+        .route("/api/classes/:id/lessons/:lesson_id/notes", get(handlers::get_lesson_notes_handler))
+        .route("/api/classes/:id/lessons/:lesson_id/notes", post(handlers::create_lesson_note_handler))
+        .route("/api/classes/:id/lessons/:lesson_id/notes/:note_id", delete(handlers::delete_lesson_note_handler))
+        .route("/api/classes/:id/lessons/:lesson_id/thread", get(handlers::get_lesson_thread_handler))
+        .route("/api/classes/:id/lessons/:lesson_id/thread", post(handlers::create_lesson_post_handler))
+        .route("/api/classes/:id/lessons/:lesson_id/thread/:post_id", delete(handlers::delete_lesson_post_handler))
+        .route("/api/assignments/me", get(handlers::get_my_assignments_handler))
+
+        // --- Роуты уведомлений ---
+        .route("/api/announcements", get(handlers::get_announcements_handler))
+        .route("/api/announcements/:id/read", post(handlers::read_announcement_handler))
+        .route("/api/notifications/me", get(handlers::get_my_notifications_handler))
+        .route("/api/notifications/:id/read", post(handlers::mark_notification_read_handler))
+        .route("/api/notifications/preferences", get(handlers::get_notification_preferences_handler))
+        .route("/api/notifications/preferences", put(handlers::update_notification_preferences_handler))
+
+        // --- Роуты предложений и shadowing ---
+        .route("/api/shadowing/next", get(handlers::get_next_shadowing_handler))
+        .route("/api/shadowing/:id/attempts", post(handlers::submit_shadowing_handler))
+        .route("/api/shadowing/:id/history", get(handlers::get_shadowing_history_handler))
+        .route("/api/typing/drill", get(handlers::get_typing_drill_handler))
+        .route("/api/typing/runs", post(handlers::submit_typing_run_handler))
+        .route("/api/typing/stats", get(handlers::get_typing_stats_handler))
+        .route("/api/dictation/next", get(handlers::get_next_dictation_handler))
+        .route("/api/dictation/:id/answer", post(handlers::submit_dictation_handler))
+        .route("/api/compose/next", get(handlers::get_next_composition_handler))
+        .route("/api/compose/:id/answer", post(handlers::submit_composition_handler))
+
+        // --- Роуты предложений контента ---
+        .route("/api/contribute", post(handlers::contribute_handler))
+
+        // --- Роуты присутствия ---
+        .route("/api/ws", get(handlers::presence_ws_handler))
+        .route("/api/presence/settings", put(handlers::update_presence_settings_handler))
+
+        // --- Роуты друзей ---
+        .route("/api/friends", get(handlers::get_friends_handler))
+        .route("/api/friends/:id", delete(handlers::remove_friend_handler))
+        .route("/api/friends/:id/profile", get(handlers::get_friend_profile_handler))
+        .route("/api/friends/requests", get(handlers::get_friend_requests_handler))
+        .route("/api/friends/requests", post(handlers::send_friend_request_handler))
+        .route("/api/friends/requests/:id/accept", post(handlers::accept_friend_request_handler))
+        .route("/api/friends/requests/:id/decline", post(handlers::decline_friend_request_handler))
+
+        // --- Роуты учебных групп ---
+        .route("/api/groups", get(handlers::get_my_groups_handler))
+        .route("/api/groups", post(handlers::create_group_handler))
+        .route("/api/groups/join", post(handlers::join_group_handler))
+        .route("/api/groups/:id", get(handlers::get_group_handler))
+        .route("/api/groups/:id", put(handlers::update_group_handler))
+        .route("/api/groups/:id/leave", post(handlers::leave_group_handler))
+
+        // --- Роуты публикации результатов ---
+        .route("/api/results/:id/share", post(handlers::share_result_handler))
+        .route("/api/results/:id/share", delete(handlers::revoke_result_share_handler))
+        .route("/api/shared/:token", get(handlers::get_shared_result_handler))
+
+        // --- Роуты для медиафайлов ---
+        .route("/api/media/:id", get(handlers::get_media_handler))
+
+        // --- Роуты чтения контента ---
+        .merge(content_read)
+
+        // --- Роуты, доступные только администраторам ---
+        .merge(user_management)
+        .merge(content_management)
+        .merge(moderation)
+        .merge(system)
+
+        // --- Роуты расширения браузера ---
+        .merge(extension)
+
+        // Пока действующие условия не приняты, API с токеном пользователя недоступен
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), consent::enforce))
+
+        // Все остальные адреса — статическая стартовая страница (`WEB_DIR`)
+        .fallback_service(ServeDir::new(config::web_dir()))
 
         .with_state(app_state)
-}
\ No newline at end of file
+}
+
+// Запуск сервера. Порт начинает слушаться сразу, а подключение к базе и миграции
+// идут в фоне: до их завершения `/readyz` и роуты `/api` отвечают 503, и медленный
+// Postgres не выглядит как зависший сервер.
+pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let database_url = env::var("DATABASE_URL")?;
+    let app_state = AppState { db_pool: startup::lazy_pool(&database_url)? };
+
+    let background_state = app_state.clone();
+    tokio::spawn(async move {
+        if startup::prepare(&background_state.db_pool).await {
+            spawn_background_jobs(&background_state);
+        }
+    });
+
+    let port = env::var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(3000);
+    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+    tracing::info!("Сервер слушает порт {}", port);
+    let app = app(app_state).layer(middleware::from_fn(startup::require_ready));
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+// Периодические фоновые задачи сервера. Вызывается один раз при старте,
+// отдельно от `app`, чтобы тесты роутера не запускали планировщики.
+pub fn spawn_background_jobs(app_state: &AppState) {
+    assignments::spawn_reminder_loop(app_state.db_pool.clone());
+    settings::spawn_study_reminder_loop(app_state.db_pool.clone());
+    hygiene::spawn_hygiene_loop(app_state.db_pool.clone());
+    doctor::spawn_seq_scan_watch(app_state.db_pool.clone());
+    snapshots::spawn_snapshot_loop(app_state.db_pool.clone());
+}
+
+// Команда `doctor`: проверяет конфигурацию без запуска сервера и завершается
+// с ненулевым кодом, если есть ошибки.
+pub async fn run_doctor() -> std::process::ExitCode {
+    dotenv().ok();
+    let pool = match env::var("DATABASE_URL") {
+        Ok(url) => PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_secs(5))
+            .connect(&url)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(_) => Err("DATABASE_URL не задан".to_string()),
+    };
+
+    let report = doctor::run(pool.as_ref().map_err(String::clone)).await;
+    println!("{}", doctor::format_report(&report));
+    if report.ok { std::process::ExitCode::SUCCESS } else { std::process::ExitCode::FAILURE }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::errors::AppError;
+use crate::notifications::{self, NotificationKind};
+
+/// Как часто проверять сроки заданий.
+const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Напоминания перед сроком: вид и за сколько часов до срока.
+const REMINDERS: &[(&str, i32)] = &[("24h", 24), ("1h", 1)];
+
+/// SQL-условие: ученик `cm.user_id` выполнил задание `a`.
+const COMPLETED_CONDITION: &str = "EXISTS (
+    SELECT 1 FROM test_results tr
+    WHERE tr.user_id = cm.user_id AND tr.test_id = a.test_id AND tr.submitted_at >= a.created_at
+)";
+
+/// Запускает периодическую рассылку напоминаний о сроках заданий.
+pub fn spawn_reminder_loop(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REMINDER_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = send_due_reminders(&pool).await {
+                tracing::error!("Ошибка рассылки напоминаний о заданиях: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Отправляет напоминания ученикам, не выполнившим задание, и итоги учителям
+/// по заданиям с истекшим сроком.
+pub async fn send_due_reminders(pool: &PgPool) -> Result<(), AppError> {
+    for (kind, hours) in REMINDERS {
+        let pending = sqlx::query_as::<_, (i32, i32, String, DateTime<Utc>)>(&format!(
+            "SELECT a.id, cm.user_id, a.title, a.due_at
+             FROM assignments a
+             JOIN class_members cm ON cm.class_id = a.class_id
+             WHERE a.due_at > NOW() AND a.due_at <= NOW() + make_interval(hours => $1)
+               AND NOT {}
+               AND NOT EXISTS (
+                   SELECT 1 FROM assignment_reminders r
+                   WHERE r.assignment_id = a.id AND r.user_id = cm.user_id AND r.kind = $2
+               )",
+            COMPLETED_CONDITION
+        ))
+            .bind(hours)
+            .bind(kind)
+            .fetch_all(pool)
+            .await?;
+
+        for (assignment_id, user_id, title, due_at) in pending {
+            let body = format!("Срок выполнения задания «{}»: {}", title, due_at.format("%d.%m.%Y %H:%M UTC"));
+            notifications::notify(user_id, NotificationKind::AssignmentReminder, "Напоминание о задании", &body, pool).await?;
+
+            sqlx::query("INSERT INTO assignment_reminders (assignment_id, user_id, kind) VALUES ($1, $2, $3)")
+                .bind(assignment_id)
+                .bind(user_id)
+                .bind(kind)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    send_deadline_summaries(pool).await
+}
+
+/// Отправляет учителям сводку по выполнению заданий после истечения срока.
+async fn send_deadline_summaries(pool: &PgPool) -> Result<(), AppError> {
+    let finished = sqlx::query_as::<_, (i32, i32, String, i64, i64)>(&format!(
+        "SELECT a.id, c.teacher_id, a.title,
+                COUNT(cm.user_id),
+                COUNT(cm.user_id) FILTER (WHERE {})
+         FROM assignments a
+         JOIN classes c ON c.id = a.class_id
+         LEFT JOIN class_members cm ON cm.class_id = a.class_id
+         WHERE a.due_at <= NOW() AND a.summary_sent_at IS NULL
+         GROUP BY a.id, c.teacher_id, a.title",
+        COMPLETED_CONDITION
+    ))
+        .fetch_all(pool)
+        .await?;
+
+    for (assignment_id, teacher_id, title, total, completed) in finished {
+        let body = format!("Задание «{}» выполнили {} из {} учеников", title, completed, total);
+        notifications::notify(teacher_id, NotificationKind::AssignmentSummary, "Итоги задания", &body, pool).await?;
+
+        sqlx::query("UPDATE assignments SET summary_sent_at = NOW() WHERE id = $1")
+            .bind(assignment_id)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
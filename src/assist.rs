@@ -0,0 +1,79 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+use crate::dictionary;
+use crate::difficulty::{candidates, segment};
+use crate::errors::AppError;
+use crate::matching::normalize_hanzi;
+use crate::models::{ContentAssist, ExistingContent, LookupEntry};
+
+/// Максимальная длина текста для подсказок: каждое слово ищется в словаре отдельно.
+pub const MAX_ASSIST_LEN: usize = 64;
+
+/// Пиньинь всего текста из пиньиня слов, если он известен для каждого слова.
+pub fn combine_pinyin(segments: &[LookupEntry]) -> Option<String> {
+    if segments.is_empty() || segments.iter().any(|segment| segment.pinyin.trim().is_empty()) {
+        return None;
+    }
+    Some(segments.iter().map(|segment| segment.pinyin.trim()).collect::<Vec<_>>().join(" "))
+}
+
+/// Подсказки для формы создания контента: разбиение текста на слова с пиньинем
+/// и переводом из словаря и уже существующие элементы с тем же текстом.
+pub async fn assist(text: &str, pool: &PgPool) -> Result<ContentAssist, AppError> {
+    let text = text.trim();
+    let normalized = normalize_hanzi(text);
+    if normalized.is_empty() || normalized.chars().count() > MAX_ASSIST_LEN {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Текст должен содержать от 1 до {} символов", MAX_ASSIST_LEN),
+        ));
+    }
+
+    let duplicates = sqlx::query_as::<_, ExistingContent>(
+        "SELECT 'hieroglyph'::content_type_enum AS content_type, id, pinyin, translation FROM hieroglyphs WHERE character = $1
+         UNION ALL
+         SELECT 'word'::content_type_enum, id, pinyin, translation FROM words WHERE simplified = $1
+         UNION ALL
+         SELECT 'phrase'::content_type_enum, id, pinyin, translation FROM phrases WHERE text = $1
+         UNION ALL
+         SELECT 'sentence'::content_type_enum, id, pinyin, translation FROM sentences WHERE text = $1",
+    )
+        .bind(text)
+        .fetch_all(pool)
+        .await?;
+
+    let chars: Vec<char> = normalized.chars().collect();
+    let words: HashSet<String> = sqlx::query_scalar("SELECT simplified FROM words WHERE simplified = ANY($1)")
+        .bind(candidates(&chars))
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut segments = Vec::new();
+    for token in segment(&normalized, &words) {
+        let entry = dictionary::lookup(&token, pool).await?.entries.into_iter().next();
+        segments.push(LookupEntry {
+            pinyin: entry.as_ref().map(|e| e.pinyin.clone()).unwrap_or_default(),
+            translation: entry.map(|e| e.translation).unwrap_or_default(),
+            text: token,
+        });
+    }
+
+    // Статья на весь текст точнее, чем склейка слов
+    let whole = match segments.as_slice() {
+        [single] if single.text == normalized => Some(single.clone()),
+        _ => dictionary::lookup(&normalized, pool).await?.entries.into_iter().next(),
+    };
+    let pinyin = whole.as_ref().map(|e| e.pinyin.clone()).or_else(|| combine_pinyin(&segments));
+
+    Ok(ContentAssist {
+        text: text.to_string(),
+        pinyin,
+        translation: whole.map(|e| e.translation),
+        segments,
+        duplicates,
+    })
+}
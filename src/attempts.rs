@@ -0,0 +1,369 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use crate::authz::{Admin, RoleRequirement};
+use crate::classes;
+use crate::config;
+use crate::errors::AppError;
+use crate::models::{AnswerPayload, AttemptAnswer, AttemptEvent, Claims, TestAttempt, TestAttemptState};
+
+/// Максимальное продление запаса пауз за раз, секунд.
+pub const MAX_ALLOWANCE_GRANT_SECS: i32 = 3600;
+
+const ATTEMPT_COLUMNS: &str = "id, user_id, test_id, time_limit_secs, pause_allowance_secs, paused_secs,
+    started_at, last_seen_at, finished_at, result_id";
+
+/// Вид события журнала попытки.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Started,
+    /// Клиент пропал дольше порога и вернулся; в `seconds` — засчитанная часть паузы.
+    Paused,
+    /// Учитель увеличил запас пауз.
+    AllowanceGranted,
+    Submitted,
+    /// Попытку бросили: время вышло даже с учетом всего запаса пауз, и она закрыта без результата.
+    Expired,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Started => "started",
+            EventKind::Paused => "paused",
+            EventKind::AllowanceGranted => "allowance_granted",
+            EventKind::Submitted => "submitted",
+            EventKind::Expired => "expired",
+        }
+    }
+}
+
+/// Пауза, обнаруженная по затишью клиента.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdlePause {
+    /// С какого момента клиент считается отключившимся.
+    pub from: DateTime<Utc>,
+    pub idle_secs: i64,
+    /// Сколько из них не идет в зачет времени: не больше оставшегося запаса пауз.
+    pub credited_secs: i64,
+}
+
+/// Пауза между последним запросом клиента и `now`. Первые `idle_after_secs` секунд
+/// затишья — обычное время на раздумье и паузой не считаются.
+pub fn idle_pause(last_seen_at: DateTime<Utc>, now: DateTime<Utc>, idle_after_secs: i64, allowance_left_secs: i64) -> Option<IdlePause> {
+    let from = last_seen_at + Duration::seconds(idle_after_secs);
+    let idle_secs = (now - from).num_seconds();
+    (idle_secs > 0).then(|| IdlePause { from, idle_secs, credited_secs: idle_secs.min(allowance_left_secs.max(0)) })
+}
+
+/// Секунд с начала попытки без учета засчитанных пауз.
+pub fn elapsed_secs(attempt: &TestAttempt, now: DateTime<Utc>) -> i64 {
+    (now - attempt.started_at).num_seconds() - attempt.paused_secs as i64
+}
+
+/// Сколько секунд осталось до конца попытки; отрицательное значение — время вышло.
+pub fn remaining_secs(attempt: &TestAttempt, now: DateTime<Utc>) -> i64 {
+    attempt.time_limit_secs as i64 - elapsed_secs(attempt, now)
+}
+
+async fn record(
+    attempt_id: i32,
+    kind: EventKind,
+    seconds: Option<i64>,
+    details: Option<&str>,
+    actor_id: Option<i32>,
+    conn: &mut PgConnection,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO test_attempt_events (attempt_id, kind, seconds, details, actor_id) VALUES ($1, $2, $3, $4, $5)",
+    )
+        .bind(attempt_id)
+        .bind(kind.as_str())
+        .bind(seconds.map(|s| s as i32))
+        .bind(details)
+        .bind(actor_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Блокирует незавершенную попытку пользователя, засчитывает паузу, если клиент
+/// пропадал, и отмечает его активность.
+async fn touch(user_id: i32, attempt_id: i32, now: DateTime<Utc>, conn: &mut PgConnection) -> Result<TestAttempt, AppError> {
+    let mut attempt = sqlx::query_as::<_, TestAttempt>(&format!(
+        "SELECT {} FROM test_attempts WHERE id = $1 AND user_id = $2 FOR UPDATE",
+        ATTEMPT_COLUMNS
+    ))
+        .bind(attempt_id)
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Попытка не найдена"))?;
+    if attempt.finished_at.is_some() {
+        return Err(AppError::new(StatusCode::CONFLICT, "Попытка уже завершена").with_code("attempt_finished"));
+    }
+
+    let allowance_left = (attempt.pause_allowance_secs - attempt.paused_secs) as i64;
+    if let Some(pause) = idle_pause(attempt.last_seen_at, now, config::test_idle_pause_secs(), allowance_left) {
+        attempt.paused_secs += pause.credited_secs as i32;
+        let details = format!(
+            "Нет связи с {} в течение {} с, засчитано {} с",
+            pause.from.format("%d.%m.%Y %H:%M:%S UTC"),
+            pause.idle_secs,
+            pause.credited_secs
+        );
+        record(attempt.id, EventKind::Paused, Some(pause.credited_secs), Some(&details), None, conn).await?;
+    }
+    attempt.last_seen_at = now;
+
+    sqlx::query("UPDATE test_attempts SET paused_secs = $2, last_seen_at = $3 WHERE id = $1")
+        .bind(attempt.id)
+        .bind(attempt.paused_secs)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+    Ok(attempt)
+}
+
+async fn state(attempt: TestAttempt, now: DateTime<Utc>, conn: &mut PgConnection) -> Result<TestAttemptState, AppError> {
+    let answers = sqlx::query_as::<_, AttemptAnswer>(
+        "SELECT test_item_id AS question_id, answer, answered_at, elapsed_secs
+         FROM test_attempt_answers WHERE attempt_id = $1 ORDER BY answered_at",
+    )
+        .bind(attempt.id)
+        .fetch_all(conn)
+        .await?;
+    Ok(TestAttemptState {
+        remaining_secs: remaining_secs(&attempt, now).max(0),
+        pause_remaining_secs: (attempt.pause_allowance_secs - attempt.paused_secs).max(0) as i64,
+        attempt,
+        answers,
+    })
+}
+
+/// Запрещает обычную отправку теста с ограничением времени: его ответы идут через попытку.
+pub async fn ensure_untimed(test_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let timed: bool = sqlx::query_scalar("SELECT time_limit_secs IS NOT NULL FROM tests WHERE id = $1")
+        .bind(test_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(false);
+    if timed {
+        return Err(AppError::new(StatusCode::CONFLICT, "Тест с ограничением времени проходится через попытку")
+            .with_code("attempt_required"));
+    }
+    Ok(())
+}
+
+/// Закрывает незавершенную попытку теста, время которой вышло даже с учетом всего
+/// запаса пауз: продолжать ее бессмысленно, а новую не дал бы начать уникальный индекс.
+/// Выполняется в транзакции вызывающего кода.
+async fn expire_stale(user_id: i32, test_id: i32, now: DateTime<Utc>, conn: &mut PgConnection) -> Result<(), AppError> {
+    let expired: Option<i32> = sqlx::query_scalar(
+        "UPDATE test_attempts SET finished_at = $3
+         WHERE user_id = $1 AND test_id = $2 AND finished_at IS NULL
+           AND started_at + make_interval(secs => time_limit_secs + pause_allowance_secs) <= $3
+         RETURNING id",
+    )
+        .bind(user_id)
+        .bind(test_id)
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(attempt_id) = expired {
+        record(attempt_id, EventKind::Expired, None, None, None, conn).await?;
+    }
+    Ok(())
+}
+
+/// Начинает попытку теста с ограничением времени. Если незавершенная попытка уже есть,
+/// продолжает ее: так клиент восстанавливается после обрыва связи. Брошенная попытка,
+/// время которой вышло, закрывается, и начинается новая.
+pub async fn start(user_id: i32, test_id: i32, pool: &PgPool) -> Result<TestAttemptState, AppError> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    let limits: Option<(Option<i32>, i32)> =
+        sqlx::query_as("SELECT time_limit_secs, pause_allowance_secs FROM tests WHERE id = $1")
+            .bind(test_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let (time_limit_secs, pause_allowance_secs) = limits.ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Тест не найден"))?;
+    let time_limit_secs = time_limit_secs
+        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "У теста нет ограничения времени"))?;
+    expire_stale(user_id, test_id, now, &mut tx).await?;
+
+    let created = sqlx::query_as::<_, TestAttempt>(&format!(
+        "INSERT INTO test_attempts (user_id, test_id, time_limit_secs, pause_allowance_secs, started_at, last_seen_at)
+         VALUES ($1, $2, $3, $4, $5, $5)
+         ON CONFLICT (user_id, test_id) WHERE finished_at IS NULL DO NOTHING
+         RETURNING {}",
+        ATTEMPT_COLUMNS
+    ))
+        .bind(user_id)
+        .bind(test_id)
+        .bind(time_limit_secs)
+        .bind(pause_allowance_secs)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let attempt = match created {
+        Some(attempt) => {
+            record(attempt.id, EventKind::Started, None, None, None, &mut tx).await?;
+            attempt
+        }
+        None => {
+            let attempt_id: i32 = sqlx::query_scalar(
+                "SELECT id FROM test_attempts WHERE user_id = $1 AND test_id = $2 AND finished_at IS NULL",
+            )
+                .bind(user_id)
+                .bind(test_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            touch(user_id, attempt_id, now, &mut tx).await?
+        }
+    };
+    let state = state(attempt, now, &mut tx).await?;
+    tx.commit().await?;
+    Ok(state)
+}
+
+/// Отмечает, что клиент на связи, и возвращает оставшееся время.
+pub async fn heartbeat(user_id: i32, attempt_id: i32, pool: &PgPool) -> Result<TestAttemptState, AppError> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    let attempt = touch(user_id, attempt_id, now, &mut tx).await?;
+    let state = state(attempt, now, &mut tx).await?;
+    tx.commit().await?;
+    Ok(state)
+}
+
+/// Сохраняет ответ с моментом ответа. Повторный ответ на вопрос заменяет прежний.
+pub async fn answer(user_id: i32, attempt_id: i32, payload: &AnswerPayload, pool: &PgPool) -> Result<TestAttemptState, AppError> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    let attempt = touch(user_id, attempt_id, now, &mut tx).await?;
+    if remaining_secs(&attempt, now) <= 0 {
+        // Паузу засчитываем и при отказе: она уже попала в журнал
+        tx.commit().await?;
+        return Err(AppError::new(StatusCode::CONFLICT, "Время попытки истекло").with_code("time_is_up"));
+    }
+
+    let saved = sqlx::query(
+        "INSERT INTO test_attempt_answers (attempt_id, test_item_id, answer, answered_at, elapsed_secs)
+         SELECT $1, id, $3, $4, $5 FROM test_items WHERE id = $2 AND test_id = $6
+         ON CONFLICT (attempt_id, test_item_id)
+         DO UPDATE SET answer = EXCLUDED.answer, answered_at = EXCLUDED.answered_at, elapsed_secs = EXCLUDED.elapsed_secs",
+    )
+        .bind(attempt.id)
+        .bind(payload.question_id)
+        .bind(&payload.answer)
+        .bind(now)
+        .bind(elapsed_secs(&attempt, now) as i32)
+        .bind(attempt.test_id)
+        .execute(&mut *tx)
+        .await?;
+    if saved.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Вопрос не относится к тесту"));
+    }
+
+    let state = state(attempt, now, &mut tx).await?;
+    tx.commit().await?;
+    Ok(state)
+}
+
+/// Завершает попытку и возвращает тест и ответы для проверки.
+/// Выполняется в транзакции вызывающего кода вместе с сохранением результата.
+pub async fn finish(user_id: i32, attempt_id: i32, conn: &mut PgConnection) -> Result<(i32, Vec<AnswerPayload>), AppError> {
+    let now = Utc::now();
+    let attempt = touch(user_id, attempt_id, now, conn).await?;
+    sqlx::query("UPDATE test_attempts SET finished_at = $2 WHERE id = $1")
+        .bind(attempt.id)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+    let elapsed = elapsed_secs(&attempt, now).min(attempt.time_limit_secs as i64);
+    record(attempt.id, EventKind::Submitted, Some(elapsed), None, None, conn).await?;
+
+    let answers: Vec<(i32, String)> = sqlx::query_as(
+        "SELECT test_item_id, answer FROM test_attempt_answers WHERE attempt_id = $1",
+    )
+        .bind(attempt.id)
+        .fetch_all(&mut *conn)
+        .await?;
+    let answers = answers.into_iter().map(|(question_id, answer)| AnswerPayload { question_id, answer }).collect();
+    Ok((attempt.test_id, answers))
+}
+
+/// Связывает завершенную попытку с результатом теста.
+pub async fn link_result(attempt_id: i32, result_id: i32, conn: &mut PgConnection) -> Result<(), AppError> {
+    sqlx::query("UPDATE test_attempts SET result_id = $2 WHERE id = $1")
+        .bind(attempt_id)
+        .bind(result_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Попытка, которую может просматривать пользователь: своя, своего класса для учителя,
+/// любая для администратора. `teacher_only` закрывает доступ самому ученику.
+async fn ensure_can_view(attempt_id: i32, claims: &Claims, teacher_only: bool, pool: &PgPool) -> Result<(), AppError> {
+    let (user_id, class_id): (i32, Option<i32>) = sqlx::query_as(
+        "SELECT a.user_id, t.class_id FROM test_attempts a JOIN tests t ON t.id = a.test_id WHERE a.id = $1",
+    )
+        .bind(attempt_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Попытка не найдена"))?;
+
+    if !teacher_only && user_id == claims.user_id {
+        return Ok(());
+    }
+    match class_id {
+        Some(class_id) => classes::ensure_teacher(class_id, claims, pool).await,
+        None if Admin::allows(&claims.role) => Ok(()),
+        None => Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен")),
+    }
+}
+
+/// Увеличивает запас пауз незавершенной попытки (учитель класса или администратор),
+/// например, когда у ученика надолго пропал интернет.
+pub async fn grant_allowance(attempt_id: i32, seconds: i32, claims: &Claims, pool: &PgPool) -> Result<TestAttempt, AppError> {
+    if !(1..=MAX_ALLOWANCE_GRANT_SECS).contains(&seconds) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Продление должно быть от 1 до {} секунд", MAX_ALLOWANCE_GRANT_SECS),
+        ));
+    }
+    ensure_can_view(attempt_id, claims, true, pool).await?;
+
+    let mut tx = pool.begin().await?;
+    let attempt = sqlx::query_as::<_, TestAttempt>(&format!(
+        "UPDATE test_attempts SET pause_allowance_secs = pause_allowance_secs + $2
+         WHERE id = $1 AND finished_at IS NULL
+         RETURNING {}",
+        ATTEMPT_COLUMNS
+    ))
+        .bind(attempt_id)
+        .bind(seconds)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::CONFLICT, "Попытка уже завершена").with_code("attempt_finished"))?;
+    record(attempt_id, EventKind::AllowanceGranted, Some(seconds as i64), None, Some(claims.user_id), &mut tx).await?;
+    tx.commit().await?;
+    Ok(attempt)
+}
+
+/// Журнал попытки по порядку событий.
+pub async fn events(attempt_id: i32, claims: &Claims, pool: &PgPool) -> Result<Vec<AttemptEvent>, AppError> {
+    ensure_can_view(attempt_id, claims, false, pool).await?;
+    let events = sqlx::query_as::<_, AttemptEvent>(
+        "SELECT id, kind, seconds, details, actor_id, created_at FROM test_attempt_events
+         WHERE attempt_id = $1 ORDER BY id",
+    )
+        .bind(attempt_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(events)
+}
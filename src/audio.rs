@@ -0,0 +1,472 @@
+use axum::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::config::{self, S3Settings, TtsSettings};
+use crate::errors::AppError;
+use crate::media;
+use crate::models::{AudioFile, ContentType};
+
+/// Хранилище файлов озвучки. Ключ — путь файла внутри хранилища.
+#[async_trait]
+pub trait AudioStorage: Send + Sync {
+    async fn put(&self, key: &str, mime_type: &str, bytes: Vec<u8>) -> Result<(), AppError>;
+    /// Читает байты с `start` по `end` включительно.
+    async fn read(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError>;
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+/// Файлы в подкаталоге `audio` каталога медиафайлов.
+pub struct DiskStorage {
+    dir: PathBuf,
+}
+
+#[async_trait]
+impl AudioStorage for DiskStorage {
+    async fn put(&self, key: &str, _mime_type: &str, bytes: Vec<u8>) -> Result<(), AppError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.dir.join(key), bytes).await?;
+        Ok(())
+    }
+
+    async fn read(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+        let mut file = tokio::fs::File::open(self.dir.join(key)).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        let mut bytes = vec![0u8; (end - start + 1) as usize];
+        file.read_exact(&mut bytes).await?;
+        Ok(bytes)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match tokio::fs::remove_file(self.dir.join(key)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// S3-совместимое хранилище (AWS S3, MinIO и т.п.), адресация path-style.
+pub struct S3Storage {
+    settings: S3Settings,
+    client: Client,
+}
+
+fn storage_error(e: impl std::fmt::Display) -> AppError {
+    tracing::error!("Ошибка хранилища аудио: {}", e);
+    AppError::new(StatusCode::BAD_GATEWAY, "Хранилище аудио недоступно")
+}
+
+/// HMAC-SHA256 (RFC 2104).
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+impl S3Storage {
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}/{}", self.settings.endpoint, self.settings.bucket, key)
+    }
+
+    /// Запрос, подписанный по AWS Signature Version 4.
+    fn request(&self, method: reqwest::Method, key: &str, payload_hash: &str) -> Result<reqwest::RequestBuilder, AppError> {
+        let url = reqwest::Url::parse(&self.url(key)).map_err(storage_error)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(storage_error("в AUDIO_S3_ENDPOINT нет хоста")),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.settings.region);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key_date = hmac_sha256(format!("AWS4{}", self.settings.secret_key).as_bytes(), date.as_bytes());
+        let key_region = hmac_sha256(&key_date, self.settings.region.as_bytes());
+        let key_service = hmac_sha256(&key_region, b"s3");
+        let key_signing = hmac_sha256(&key_service, b"aws4_request");
+        let signature = hex::encode(hmac_sha256(&key_signing, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.settings.access_key, scope, signed_headers, signature
+        );
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(reqwest::header::AUTHORIZATION, authorization))
+    }
+}
+
+/// Хеш пустого тела запроса.
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[async_trait]
+impl AudioStorage for S3Storage {
+    async fn put(&self, key: &str, mime_type: &str, bytes: Vec<u8>) -> Result<(), AppError> {
+        let payload_hash = hex::encode(Sha256::digest(&bytes));
+        self.request(reqwest::Method::PUT, key, &payload_hash)?
+            .header(reqwest::header::CONTENT_TYPE, mime_type)
+            .body(bytes)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn read(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+        let bytes = self
+            .request(reqwest::Method::GET, key, EMPTY_PAYLOAD_HASH)?
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(storage_error)?
+            .bytes()
+            .await
+            .map_err(storage_error)?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.request(reqwest::Method::DELETE, key, EMPTY_PAYLOAD_HASH)?
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+// Хранилище выбирается один раз при первом обращении по переменным окружения.
+static STORAGE: Lazy<Box<dyn AudioStorage>> = Lazy::new(|| match config::audio_s3() {
+    Some(settings) => Box::new(S3Storage { settings, client: Client::new() }),
+    None => Box::new(DiskStorage { dir: media::media_dir().join("audio") }),
+});
+
+fn storage() -> &'static dyn AudioStorage {
+    STORAGE.as_ref()
+}
+
+/// Приводит MIME-тип к одному из допустимых форматов — MP3 или OGG
+/// (`audio/mp3` и `application/ogg` присылают некоторые браузеры).
+fn normalize_mime_type(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "audio/mpeg" | "audio/mp3" => Some("audio/mpeg"),
+        "audio/ogg" | "audio/vorbis" | "application/ogg" => Some("audio/ogg"),
+        _ => None,
+    }
+}
+
+/// Сохраняет озвучку иероглифа или слова. Загруженная запись заменяет
+/// синтезированную, если она была.
+pub async fn store(
+    content_type: ContentType,
+    content_id: i32,
+    mime_type: &str,
+    bytes: Vec<u8>,
+    pool: &PgPool,
+) -> Result<AudioFile, AppError> {
+    let table = match content_type {
+        ContentType::Hieroglyph => "hieroglyphs",
+        ContentType::Word => "words",
+        _ => return Err(AppError::new(StatusCode::BAD_REQUEST, "Озвучка бывает только у иероглифов и слов")),
+    };
+    let exists: bool = sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)", table))
+        .bind(content_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Элемент не найден"));
+    }
+
+    let audio = insert(content_type, content_id, mime_type, bytes, false, pool).await?;
+    remove_synthesized(content_type, content_id, pool).await?;
+    Ok(audio)
+}
+
+/// Кладет файл в хранилище и записывает его описание.
+async fn insert(
+    content_type: ContentType,
+    content_id: i32,
+    mime_type: &str,
+    bytes: Vec<u8>,
+    synthesized: bool,
+    pool: &PgPool,
+) -> Result<AudioFile, AppError> {
+    let mime_type = normalize_mime_type(mime_type)
+        .ok_or_else(|| AppError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Поддерживаются только MP3 и OGG"))?;
+    if bytes.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Файл пустой"));
+    }
+
+    // Ключ случайный, чтобы не зависеть от имени, присланного клиентом
+    let mut name_bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut name_bytes);
+    let extension = if mime_type == "audio/ogg" { "ogg" } else { "mp3" };
+    let key = format!("{}.{}", hex::encode(name_bytes), extension);
+
+    let size = bytes.len() as i64;
+    storage().put(&key, mime_type, bytes).await?;
+
+    let audio = sqlx::query_as::<_, AudioFile>(
+        "INSERT INTO audio (content_type, content_id, storage_key, mime_type, size_bytes, synthesized)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING id, content_type, content_id, mime_type, size_bytes, synthesized, created_at",
+    )
+        .bind(content_type)
+        .bind(content_id)
+        .bind(&key)
+        .bind(mime_type)
+        .bind(size)
+        .bind(synthesized)
+        .fetch_one(pool)
+        .await?;
+    Ok(audio)
+}
+
+/// Удаляет синтезированные озвучки элемента вместе с файлами.
+async fn remove_synthesized(content_type: ContentType, content_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let keys: Vec<String> = sqlx::query_scalar(
+        "DELETE FROM audio WHERE content_type = $1 AND content_id = $2 AND synthesized
+         RETURNING storage_key",
+    )
+        .bind(content_type)
+        .bind(content_id)
+        .fetch_all(pool)
+        .await?;
+    for key in keys {
+        // Запись в базе уже удалена; оставшийся файл ни на что не влияет
+        if let Err(e) = storage().delete(&key).await {
+            tracing::warn!("Не удалось удалить синтезированную озвучку {}: {}", key, e.message());
+        }
+    }
+    Ok(())
+}
+
+/// Озвучки элемента контента, от новых к старым.
+pub async fn list(content_type: ContentType, content_id: i32, pool: &PgPool) -> Result<Vec<AudioFile>, AppError> {
+    let files = sqlx::query_as::<_, AudioFile>(
+        "SELECT id, content_type, content_id, mime_type, size_bytes, synthesized, created_at FROM audio
+         WHERE content_type = $1 AND content_id = $2
+         ORDER BY created_at DESC, id DESC",
+    )
+        .bind(content_type)
+        .bind(content_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(files)
+}
+
+/// Запрошенный диапазон байтов.
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    /// Заголовка нет или он не в формате `bytes=`: отдается весь файл.
+    Full,
+    /// Диапазон с `start` по `end` включительно.
+    Partial(u64, u64),
+    /// Диапазон за пределами файла (ответ 416).
+    Unsatisfiable,
+}
+
+/// Разбирает заголовок `Range` (RFC 7233). Поддерживается один диапазон:
+/// `bytes=0-99`, `bytes=100-` и `bytes=-100` (последние 100 байт).
+pub fn parse_range(header: Option<&str>, size: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    // Несколько диапазонов сразу плееры не запрашивают; отдаем весь файл
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) | Err(_) => None,
+            Ok(length) => Some((size.saturating_sub(length), size.saturating_sub(1))),
+        },
+        (start, "") => start.parse::<u64>().ok().map(|start| (start, size.saturating_sub(1))),
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => Some((start, end.min(size.saturating_sub(1)))),
+            _ => None,
+        },
+    };
+
+    match range {
+        Some((start, end)) if size > 0 && start < size => ByteRange::Partial(start, end),
+        _ => ByteRange::Unsatisfiable,
+    }
+}
+
+/// Описание файла и ключ в хранилище.
+pub async fn find(id: i32, pool: &PgPool) -> Result<(String, String, u64, DateTime<Utc>), AppError> {
+    let (key, mime_type, size, created_at): (String, String, i64, DateTime<Utc>) =
+        sqlx::query_as("SELECT storage_key, mime_type, size_bytes, created_at FROM audio WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Файл не найден"))?;
+    Ok((key, mime_type, size as u64, created_at))
+}
+
+/// Читает диапазон байтов файла.
+pub async fn read(key: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+    storage().read(key, start, end).await
+}
+
+/// Удаляет озвучку вместе с файлом.
+pub async fn delete(id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let key: String = sqlx::query_scalar("DELETE FROM audio WHERE id = $1 RETURNING storage_key")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Файл не найден"))?;
+    storage().delete(&key).await
+}
+
+// --- Синтез речи ---
+
+/// Сервис синтеза речи для иероглифов без записанной озвучки.
+#[async_trait]
+pub trait TtsProvider: Send + Sync {
+    /// Озвучивает китайский текст и возвращает MIME-тип и аудио.
+    /// Пиньинь подсказывает чтение иероглифов с несколькими произношениями.
+    async fn synthesize(&self, text: &str, pinyin: &str) -> Result<(String, Vec<u8>), AppError>;
+}
+
+/// Внешний HTTP-сервис (`TTS_URL`): принимает `{"text", "pinyin", "lang", "voice"}`
+/// и возвращает аудио в теле ответа с соответствующим `Content-Type`.
+pub struct HttpTts {
+    settings: TtsSettings,
+    client: Client,
+}
+
+fn tts_error(e: impl std::fmt::Display) -> AppError {
+    tracing::error!("Ошибка сервиса синтеза речи: {}", e);
+    AppError::new(StatusCode::BAD_GATEWAY, "Сервис синтеза речи недоступен")
+}
+
+#[async_trait]
+impl TtsProvider for HttpTts {
+    async fn synthesize(&self, text: &str, pinyin: &str) -> Result<(String, Vec<u8>), AppError> {
+        let mut request = self.client.post(&self.settings.url).json(&serde_json::json!({
+            "text": text,
+            "pinyin": pinyin,
+            "lang": "zh",
+            "voice": self.settings.voice,
+        }));
+        if let Some(api_key) = &self.settings.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(tts_error)?;
+        // `audio/mpeg; charset=...` -> `audio/mpeg`
+        let mime_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let bytes = response.bytes().await.map_err(tts_error)?;
+        Ok((mime_type, bytes.to_vec()))
+    }
+}
+
+// Провайдер выбирается один раз при первом обращении; без настроек синтез отключен.
+static TTS: Lazy<Option<Box<dyn TtsProvider>>> = Lazy::new(|| {
+    config::tts().map(|settings| Box::new(HttpTts { settings, client: Client::new() }) as Box<dyn TtsProvider>)
+});
+
+// Синтез идет по одному, чтобы параллельные запросы не озвучивали иероглиф дважды.
+static SYNTHESIS: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Озвучки иероглифа. Если записей нет и настроен синтез речи, озвучка
+/// синтезируется и сохраняется, так что сервис вызывается один раз на иероглиф.
+/// Ошибка синтеза не ломает ответ: возвращается пустой список.
+pub async fn hieroglyph_audio(id: i32, pool: &PgPool) -> Result<Vec<AudioFile>, AppError> {
+    let files = list(ContentType::Hieroglyph, id, pool).await?;
+    let Some(tts) = TTS.as_ref() else {
+        return Ok(files);
+    };
+    if !files.is_empty() {
+        return Ok(files);
+    }
+
+    let _guard = SYNTHESIS.lock().await;
+    // Пока ждали очереди, озвучку мог синтезировать другой запрос
+    let files = list(ContentType::Hieroglyph, id, pool).await?;
+    if !files.is_empty() {
+        return Ok(files);
+    }
+
+    let hieroglyph: Option<(String, String)> = sqlx::query_as("SELECT character, pinyin FROM hieroglyphs WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    let Some((character, pinyin)) = hieroglyph else {
+        return Ok(files);
+    };
+
+    let result = match tts.synthesize(&character, &pinyin).await {
+        Ok((mime_type, bytes)) => insert(ContentType::Hieroglyph, id, &mime_type, bytes, true, pool).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(audio) => Ok(vec![audio]),
+        Err(e) => {
+            tracing::warn!("Не удалось синтезировать озвучку иероглифа {}: {}", id, e.message());
+            Ok(files)
+        }
+    }
+}
@@ -1,49 +1,226 @@
 use axum::{
     async_trait,
-    extract::{FromRequestParts},
-    http::{request::Parts},
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, header, HeaderMap},
     response::{IntoResponse, Response},
 };
 use axum_extra::headers::{authorization::Bearer, Authorization};
+use argon2::password_hash::{self, rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
 use axum_extra::TypedHeader;
-use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use chrono::{DateTime, Duration, Utc};
 use rand::RngCore;
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
-use std::env;
+use std::net::IpAddr;
 
+use crate::config::{self, DeviceBinding};
 use crate::models::{AuthResponse, Claims, User};
 use crate::errors::AppError;
+use crate::jwt_keys;
+use crate::security_log::{self, EventKind};
+use crate::AppState;
 use axum::http::StatusCode;
 
 // --- Константы для времени жизни токенов ---
 const ACCESS_TOKEN_EXPIRATION_MINUTES: i64 = 15;
 const REFRESH_TOKEN_EXPIRATION_DAYS: i64 = 30;
+/// Более длинный идентификатор устройства считается мусором и не сохраняется.
+const MAX_DEVICE_ID_LENGTH: usize = 128;
+/// User-Agent длиннее этого обрезается.
+const MAX_USER_AGENT_LENGTH: usize = 256;
 
-/// Хеширует пароль с использованием bcrypt.
+/// Хешер Argon2id с параметрами из конфигурации.
+fn argon2() -> Result<Argon2<'static>, AppError> {
+    let config = config::password_hashing();
+    let params = Params::new(config.memory_kib, config.iterations, config.parallelism, None).map_err(|e| {
+        tracing::error!("Некорректные параметры Argon2 {:?}: {}", config, e);
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Сервер настроен некорректно").with_code("misconfigured")
+    })?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+/// Хеширует пароль с использованием Argon2id.
 pub fn hash_password(password: &str) -> Result<String, AppError> {
-    hash(password, DEFAULT_COST).map_err(|_| {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2()?.hash_password(password.as_bytes(), &salt).map_err(|_| {
         AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Не удалось хешировать пароль")
-    })
+    })?;
+    Ok(hash.to_string())
 }
 
-/// Проверяет пароль на соответствие хешу.
+/// Проверяет пароль на соответствие хешу. Кроме Argon2 принимает старые хеши bcrypt.
 pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    verify(password, hash).map_err(|_| {
-        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Ошибка при проверке пароля")
-    })
+    let error = || AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Ошибка при проверке пароля");
+    if !hash.starts_with("$argon2") {
+        return bcrypt::verify(password, hash).map_err(|_| error());
+    }
+
+    let parsed = PasswordHash::new(hash).map_err(|_| error())?;
+    // Параметры берутся из самого хеша, поэтому старые хеши проверяются и после смены настроек
+    match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(_) => Err(error()),
+    }
+}
+
+/// Нужно ли пересчитать хеш: это bcrypt или Argon2 с параметрами, отличными от настроенных.
+pub fn needs_rehash(hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return true;
+    };
+    if parsed.algorithm != Algorithm::Argon2id.ident() {
+        return true;
+    }
+    let Ok(params) = Params::try_from(&parsed) else {
+        return true;
+    };
+    let config = config::password_hashing();
+    params.m_cost() != config.memory_kib || params.t_cost() != config.iterations || params.p_cost() != config.parallelism
+}
+
+/// Пересчитывает хеш пароля после успешного входа. Ошибка только логируется:
+/// вход не должен срываться из-за того, что хеш не удалось обновить.
+/// Хеш не перезаписывается, если пароль успели сменить параллельно.
+pub async fn rehash_password(user_id: i32, password: &str, old_hash: &str, pool: &PgPool) {
+    let result = async {
+        let new_hash = hash_password(password)?;
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3")
+            .bind(&new_hash)
+            .bind(user_id)
+            .bind(old_hash)
+            .execute(pool)
+            .await?;
+        Ok::<_, AppError>(())
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("Не удалось пересчитать хеш пароля пользователя {}: {:?}", user_id, e);
+    }
+}
+
+/// Пользователь текущего запроса. Загружается из БД один раз и кешируется
+/// в расширениях запроса, чтобы повторные обращения не ходили в базу.
+#[derive(Debug, Clone)]
+pub struct CurrentUser(pub User);
+
+/// Возвращает пользователя текущего запроса, используя кеш в `parts.extensions`.
+async fn load_current_user(parts: &mut Parts, user_id: i32, pool: &PgPool) -> Result<User, AppError> {
+    if let Some(CurrentUser(user)) = parts.extensions.get::<CurrentUser>() {
+        return Ok(user.clone());
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Пользователь не найден"))?;
+
+    parts.extensions.insert(CurrentUser(user.clone()));
+    Ok(user)
+}
+
+/// Проверяет, что аккаунт не заблокирован и не отключен.
+pub fn ensure_not_banned(user: &User) -> Result<(), AppError> {
+    if user.is_banned || user.disabled_at.is_some() {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Аккаунт заблокирован")
+            .with_code("account_suspended"));
+    }
+    Ok(())
+}
+
+/// Удаляет все refresh-сессии пользователя.
+pub async fn revoke_all_sessions<'e, E>(user_id: i32, executor: E) -> Result<(), AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query("DELETE FROM refresh_sessions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Генерирует случайный непредсказуемый токен (32 байта в hex).
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Идентификатор устройства для привязки сессии: присланный клиентом, а если его
+/// нет — отпечаток заголовка `User-Agent`. Без того и другого сессия не привязывается.
+pub fn device_id(explicit: Option<&str>, headers: &HeaderMap) -> Option<String> {
+    if let Some(id) = explicit.map(str::trim).filter(|id| !id.is_empty() && id.len() <= MAX_DEVICE_ID_LENGTH) {
+        return Some(id.to_string());
+    }
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())?;
+    Some(format!("ua:{}", hex::encode(&Sha256::digest(user_agent.as_bytes())[..16])))
+}
+
+/// Клиент, который входит или обновляет сессию.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    /// Устройство, к которому привязана сессия.
+    pub device_id: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<IpAddr>,
+}
+
+impl ClientInfo {
+    /// Сведения о клиенте из запроса; `device_id` — идентификатор, присланный в теле.
+    pub fn from_request(device_id: Option<&str>, headers: &HeaderMap, ip: Option<IpAddr>) -> Self {
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+        ClientInfo { device_id: self::device_id(device_id, headers), user_agent, ip }
+    }
+}
+
+/// Параметры новой refresh-сессии.
+#[derive(Debug, Clone, Default)]
+pub struct NewSession {
+    pub client: ClientInfo,
+    /// Семейство при ротации; `None` при входе — начинается новое.
+    /// Id семейства служит id сессии в списке устройств.
+    pub family_id: Option<String>,
+    /// Время входа, переходит от прежнего токена; `None` — сейчас.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Отметка об обновлении с чужого устройства, переходит от прежней сессии.
+    pub device_mismatch_at: Option<DateTime<Utc>>,
+}
+
+/// Отзывает семейство сессий: все токены, полученные ротацией от одного входа.
+pub async fn revoke_family<'e, E>(family_id: &str, executor: E) -> Result<u64, AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let result = sqlx::query("DELETE FROM refresh_sessions WHERE family_id = $1")
+        .bind(family_id)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
 }
 
 /// Генерирует пару access и refresh токенов.
-pub async fn generate_tokens(user_id: &i32, pool: &PgPool) -> Result<AuthResponse, AppError> {
+pub async fn generate_tokens(user_id: &i32, session: &NewSession, pool: &PgPool) -> Result<AuthResponse, AppError> {
     // Получаем пользователя целиком, чтобы иметь доступ к роли.
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_id)
         .fetch_one(pool)
         .await?;
 
-    // 1. Создание Access Token
+    // Заблокированным пользователям новые токены не выдаем
+    ensure_not_banned(&user)?;
+
+    // 1. Создание Access Token. В нем id сессии, чтобы в списке устройств отметить текущее.
+    let family_id = session.family_id.clone().unwrap_or_else(random_token);
     let now = Utc::now();
     let access_token_exp = (now + Duration::minutes(ACCESS_TOKEN_EXPIRATION_MINUTES)).timestamp();
     let access_claims = Claims {
@@ -51,59 +228,136 @@ pub async fn generate_tokens(user_id: &i32, pool: &PgPool) -> Result<AuthRespons
         iat: now.timestamp() as usize,
         user_id: *user_id,
         role: user.role,
+        session_id: Some(family_id.clone()),
     };
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET должен быть установлен");
-    let access_token = encode(
-        &Header::default(),
-        &access_claims,
-        &EncodingKey::from_secret(jwt_secret.as_ref()),
-    )?;
+    let access_token = jwt_keys::keys()?.sign(&access_claims)?;
 
     // 2. Создание Refresh Token
-    let mut refresh_token_bytes = [0u8; 32];
-    rand::thread_rng().fill_bytes(&mut refresh_token_bytes);
-    let refresh_token = hex::encode(refresh_token_bytes);
+    let refresh_token = random_token();
     let refresh_token_exp = now + Duration::days(REFRESH_TOKEN_EXPIRATION_DAYS);
 
     // 3. Сохранение Refresh Token в БД
-    sqlx::query("INSERT INTO refresh_sessions (user_id, refresh_token, expires_at) VALUES ($1, $2, $3)")
+    sqlx::query(
+        "INSERT INTO refresh_sessions
+             (user_id, refresh_token, expires_at, device_id, family_id, device_mismatch_at, user_agent, ip, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, NOW()))",
+    )
         .bind(user_id)
         .bind(&refresh_token)
         .bind(refresh_token_exp)
+        .bind(&session.client.device_id)
+        .bind(&family_id)
+        .bind(session.device_mismatch_at)
+        .bind(&session.client.user_agent)
+        .bind(session.client.ip.map(|ip| ip.to_string()))
+        .bind(session.created_at)
         .execute(pool)
         .await?;
 
     Ok(AuthResponse { access_token, refresh_token })
 }
 
+/// Refresh-сессия, как она хранится в БД.
+#[derive(sqlx::FromRow)]
+struct StoredSession {
+    user_id: i32,
+    expires_at: DateTime<Utc>,
+    device_id: Option<String>,
+    device_mismatch_at: Option<DateTime<Utc>>,
+    family_id: String,
+    rotated_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+/// Ошибка повторного предъявления замененного токена: семейство уже отозвано.
+async fn reuse_detected(user_id: i32, family_id: &str, ip: Option<IpAddr>, pool: &PgPool) -> Result<AppError, AppError> {
+    let revoked = revoke_family(family_id, pool).await?;
+    let details = format!("Повторно предъявлен замененный refresh-токен, отозвано токенов семейства: {}", revoked);
+    security_log::record(user_id, EventKind::RefreshReuse, ip, &details, pool).await?;
+    Ok(AppError::new(StatusCode::UNAUTHORIZED, "Сессия отозвана из соображений безопасности, войдите заново")
+        .with_code("refresh_reused"))
+}
+
 /// Обновляет access token, используя refresh token (без транзакции).
-pub async fn refresh_access_token(refresh_token: &str, pool: &PgPool) -> Result<AuthResponse, AppError> {
+///
+/// Токены ротируются внутри семейства: замененный токен не удаляется, а
+/// помечается, и если его предъявят еще раз (токен украден и им уже
+/// воспользовались), отзывается все семейство, а событие пишется в журнал
+/// безопасности.
+///
+/// Если сессия привязана к устройству, а токен предъявлен с другого, событие
+/// тоже пишется в журнал. В режиме `strict` семейство при этом отзывается,
+/// в режиме `warn` сессия обновляется, но остается привязанной к исходному
+/// устройству и помечается в списке сессий.
+pub async fn refresh_access_token(refresh_token: &str, client: ClientInfo, pool: &PgPool) -> Result<AuthResponse, AppError> {
     // 1. Найти сессию по refresh token в БД
-    let session: (i32, chrono::DateTime<Utc>) = sqlx::query_as(
-        "SELECT user_id, expires_at FROM refresh_sessions WHERE refresh_token = $1",
+    let session = sqlx::query_as::<_, StoredSession>(
+        "SELECT user_id, expires_at, device_id, device_mismatch_at, family_id, rotated_at, created_at
+         FROM refresh_sessions WHERE refresh_token = $1",
     )
         .bind(refresh_token)
         .fetch_optional(pool) // Используем пул напрямую
         .await?
         .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Невалидный refresh токен"))?;
 
-    let (user_id, expires_at) = session;
+    let StoredSession {
+        user_id,
+        expires_at,
+        device_id: bound_device,
+        device_mismatch_at: mismatch_at,
+        family_id,
+        rotated_at,
+        created_at,
+    } = session;
+
+    // 2. Замененный токен предъявлен повторно
+    if rotated_at.is_some() {
+        return Err(reuse_detected(user_id, &family_id, client.ip, pool).await?);
+    }
 
-    // 2. Проверить, не истек ли срок действия
+    // 3. Проверить, не истек ли срок действия
     if Utc::now() > expires_at {
         // Удаляем просроченный токен из БД
         sqlx::query("DELETE FROM refresh_sessions WHERE refresh_token = $1").bind(refresh_token).execute(pool).await?;
         return Err(AppError::new(StatusCode::UNAUTHORIZED, "Сессия истекла"));
     }
 
-    // 3. Удалить старый refresh token (рискованная часть, но так было запрошено)
-    sqlx::query("DELETE FROM refresh_sessions WHERE refresh_token = $1")
+    // 4. Сверить устройство
+    let binding = config::device_binding();
+    let mismatch = binding != DeviceBinding::Off && bound_device.is_some() && bound_device != client.device_id;
+    if mismatch {
+        let details = format!(
+            "Сессия устройства {} обновлена с устройства {}",
+            bound_device.as_deref().unwrap_or_default(),
+            client.device_id.as_deref().unwrap_or("без идентификатора"),
+        );
+        security_log::record(user_id, EventKind::DeviceMismatch, client.ip, &details, pool).await?;
+        if binding == DeviceBinding::Strict {
+            revoke_family(&family_id, pool).await?;
+            return Err(AppError::new(StatusCode::UNAUTHORIZED, "Сессия открыта на другом устройстве, войдите заново")
+                .with_code("device_mismatch"));
+        }
+    }
+
+    // 5. Пометить старый токен замененным. Условие в запросе не дает двум
+    // одновременным запросам с одним токеном оба получить новую пару.
+    let rotated = sqlx::query("UPDATE refresh_sessions SET rotated_at = NOW() WHERE refresh_token = $1 AND rotated_at IS NULL")
         .bind(refresh_token)
-        .execute(pool) // Используем пул напрямую
+        .execute(pool)
         .await?;
+    if rotated.rows_affected() == 0 {
+        return Err(reuse_detected(user_id, &family_id, client.ip, pool).await?);
+    }
 
-    // 4. Сгенерировать новую пару токенов (ротация)
-    let tokens = generate_tokens(&user_id, pool).await?;
+    // 6. Сгенерировать новую пару токенов в том же семействе. Сессия остается
+    // за устройством входа, отметка о чужом устройстве переходит на новую.
+    let session = NewSession {
+        client: ClientInfo { device_id: bound_device.or(client.device_id), ..client },
+        family_id: Some(family_id),
+        created_at: Some(created_at),
+        device_mismatch_at: if mismatch { Some(Utc::now()) } else { mismatch_at },
+    };
+    let tokens = generate_tokens(&user_id, &session, pool).await?;
 
     Ok(tokens)
 }
@@ -112,27 +366,27 @@ pub async fn refresh_access_token(refresh_token: &str, pool: &PgPool) -> Result<
 #[async_trait]
 impl<S> FromRequestParts<S> for Claims
 where
+    AppState: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = Response;
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let TypedHeader(Authorization(bearer)) =
-            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, _state)
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
                 .await
                 .map_err(|_| AppError::new(StatusCode::UNAUTHORIZED, "Требуется токен авторизации").into_response())?;
 
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET должен быть установлен");
-        let token_data = decode::<Claims>(
-            bearer.token(),
-            &DecodingKey::from_secret(jwt_secret.as_ref()),
-            &Validation::default(),
-        )
-            .map_err(|e| {
-                let error_message = format!("Невалидный токен: {}", e);
-                AppError::new(StatusCode::UNAUTHORIZED, &error_message).into_response()
-            })?;
-
-        Ok(token_data.claims)
+        let claims = jwt_keys::verify_access_token(bearer.token()).map_err(IntoResponse::into_response)?;
+
+        // Токен может быть еще действителен, а пользователь уже заблокирован,
+        // поэтому состояние аккаунта проверяем по БД на каждый запрос.
+        let app_state = AppState::from_ref(state);
+        let user = load_current_user(parts, claims.user_id, &app_state.db_pool)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        ensure_not_banned(&user).map_err(IntoResponse::into_response)?;
+
+        Ok(claims)
     }
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Request},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use crate::errors::AppError;
+use crate::models::{Claims, UserRole};
+use crate::AppState;
+
+/// Права доступа, которые выдаются ролями.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Блокировка, импорт и приглашение пользователей.
+    ManageUsers,
+    /// Создание и редактирование учебного контента, импорт словарей.
+    ManageContent,
+    /// Рассмотрение предложений пользователей.
+    ModerateContributions,
+    /// Обслуживание сервера (задачи, ключи, аудит) и доступ к чужим данным.
+    ManageSystem,
+}
+
+impl UserRole {
+    /// Единственная таблица «роль — права»: и группы роутов, и [`RequireRole`]
+    /// проверяют доступ только через нее.
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        match self {
+            UserRole::Admin => true,
+            UserRole::Moderator => permission == Permission::ModerateContributions,
+            UserRole::User => false,
+        }
+    }
+}
+
+type MiddlewareFuture = Pin<Box<dyn Future<Output = Result<Response, AppError>> + Send>>;
+
+fn forbidden() -> AppError {
+    AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен")
+}
+
+/// Middleware для группы роутов: пропускает любого авторизованного пользователя.
+pub async fn require_auth(_claims: Claims, request: Request, next: Next) -> Response {
+    next.run(request).await
+}
+
+/// Middleware для группы роутов: пропускает только пользователей, чья роль дает `permission`.
+pub fn require_permission(permission: Permission) -> impl Fn(Claims, Request, Next) -> MiddlewareFuture + Clone + Send + 'static {
+    move |claims, request, next| {
+        let allowed = claims.role.has_permission(permission);
+        Box::pin(async move {
+            if !allowed {
+                return Err(forbidden());
+            }
+            Ok(next.run(request).await)
+        })
+    }
+}
+
+/// Требование к роли для [`RequireRole`]: право, которое роль должна давать.
+pub trait RoleRequirement {
+    const PERMISSION: Permission;
+
+    fn allows(role: &UserRole) -> bool {
+        role.has_permission(Self::PERMISSION)
+    }
+}
+
+/// Администраторы: обслуживание сервера.
+pub struct Admin;
+
+/// Модераторы предложений пользователей; администраторы тоже проходят.
+pub struct Moderator;
+
+impl RoleRequirement for Admin {
+    const PERMISSION: Permission = Permission::ManageSystem;
+}
+
+impl RoleRequirement for Moderator {
+    const PERMISSION: Permission = Permission::ModerateContributions;
+}
+
+/// Экстрактор claims пользователя, чья роль удовлетворяет `R`; остальным — 403.
+/// Группе роутов подключается слоем `middleware::from_extractor_with_state::<RequireRole<Admin>, _>`,
+/// отдельному обработчику — аргументом, если ему нужны claims.
+pub struct RequireRole<R>(pub Claims, PhantomData<R>);
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+    R: RoleRequirement,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        if !R::allows(&claims.role) {
+            return Err(forbidden().into_response());
+        }
+        Ok(RequireRole(claims, PhantomData))
+    }
+}
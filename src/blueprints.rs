@@ -0,0 +1,270 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::hsk;
+use crate::models::{BlueprintPayload, BlueprintSection, QuestionSkill, TagTestItemPayload, TestBlueprint};
+
+/// Максимум вопросов в тесте, собранном по шаблону.
+pub const MAX_BLUEPRINT_QUESTIONS: i32 = 100;
+/// Вопросы, которые пользователь видел за это число дней, в новый тест не попадают.
+pub const RECENT_DAYS: i32 = 30;
+
+/// Банк вопросов: вопросы общих тестов, не принадлежащие классам и не собранные по шаблонам.
+const BANK_CONDITION: &str = "ti.source_item_id IS NULL AND t.class_id IS NULL AND t.generated_for IS NULL";
+
+#[derive(sqlx::FromRow)]
+struct BlueprintRow {
+    id: i32,
+    name: String,
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Проставляет теги вопросу теста.
+pub async fn tag_item(item_id: i32, payload: &TagTestItemPayload, pool: &PgPool) -> Result<(), AppError> {
+    if let Some(level) = payload.hsk_level {
+        hsk::ensure_level(level)?;
+    }
+    let result = sqlx::query(
+        "UPDATE test_items SET skill = $2, hsk_level = $3, grammar_rule_id = $4 WHERE id = $1",
+    )
+        .bind(item_id)
+        .bind(payload.skill)
+        .bind(payload.hsk_level)
+        .bind(payload.grammar_rule_id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Вопрос не найден"));
+    }
+    Ok(())
+}
+
+/// Проверяет шаблон: название, разделы и общий размер теста.
+pub fn validate(payload: &BlueprintPayload) -> Result<(), AppError> {
+    if payload.name.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название шаблона не может быть пустым"));
+    }
+    if payload.sections.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "В шаблоне должен быть хотя бы один раздел"));
+    }
+    let mut total = 0;
+    for section in &payload.sections {
+        if section.question_count <= 0 {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Число вопросов в разделе должно быть положительным"));
+        }
+        if let Some(level) = section.hsk_level {
+            hsk::ensure_level(level)?;
+        }
+        total += section.question_count;
+    }
+    if total > MAX_BLUEPRINT_QUESTIONS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("В тесте может быть не больше {} вопросов", MAX_BLUEPRINT_QUESTIONS),
+        ));
+    }
+    Ok(())
+}
+
+async fn sections(blueprint_id: i32, pool: &PgPool) -> Result<Vec<BlueprintSection>, AppError> {
+    let sections = sqlx::query_as::<_, BlueprintSection>(
+        "SELECT question_count, skill, hsk_level, grammar_rule_id
+         FROM blueprint_sections WHERE blueprint_id = $1 ORDER BY position",
+    )
+        .bind(blueprint_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(sections)
+}
+
+fn with_sections(row: BlueprintRow, sections: Vec<BlueprintSection>) -> TestBlueprint {
+    TestBlueprint { id: row.id, name: row.name, description: row.description, created_at: row.created_at, sections }
+}
+
+/// Создает шаблон теста.
+pub async fn create(payload: &BlueprintPayload, pool: &PgPool) -> Result<TestBlueprint, AppError> {
+    validate(payload)?;
+
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query_as::<_, BlueprintRow>(
+        "INSERT INTO test_blueprints (name, description) VALUES ($1, $2) RETURNING id, name, description, created_at",
+    )
+        .bind(payload.name.trim())
+        .bind(&payload.description)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    for (position, section) in payload.sections.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO blueprint_sections (blueprint_id, position, question_count, skill, hsk_level, grammar_rule_id)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+            .bind(row.id)
+            .bind(position as i32)
+            .bind(section.question_count)
+            .bind(section.skill)
+            .bind(section.hsk_level)
+            .bind(section.grammar_rule_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(with_sections(row, payload.sections.clone()))
+}
+
+/// Все шаблоны с разделами.
+pub async fn list(pool: &PgPool) -> Result<Vec<TestBlueprint>, AppError> {
+    let rows = sqlx::query_as::<_, BlueprintRow>(
+        "SELECT id, name, description, created_at FROM test_blueprints ORDER BY id",
+    )
+        .fetch_all(pool)
+        .await?;
+
+    let mut blueprints = Vec::with_capacity(rows.len());
+    for row in rows {
+        let sections = sections(row.id, pool).await?;
+        blueprints.push(with_sections(row, sections));
+    }
+    Ok(blueprints)
+}
+
+/// Шаблон по id.
+pub async fn get(blueprint_id: i32, pool: &PgPool) -> Result<TestBlueprint, AppError> {
+    let row = sqlx::query_as::<_, BlueprintRow>(
+        "SELECT id, name, description, created_at FROM test_blueprints WHERE id = $1",
+    )
+        .bind(blueprint_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Шаблон не найден"))?;
+
+    let sections = sections(row.id, pool).await?;
+    Ok(with_sections(row, sections))
+}
+
+/// Удаляет шаблон. Уже собранные по нему тесты остаются.
+pub async fn delete(blueprint_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let result = sqlx::query("DELETE FROM test_blueprints WHERE id = $1")
+        .bind(blueprint_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Шаблон не найден"));
+    }
+    Ok(())
+}
+
+fn describe(section: &BlueprintSection) -> String {
+    let mut filters = Vec::new();
+    if let Some(skill) = section.skill {
+        let skill = match skill {
+            QuestionSkill::Listening => "аудирование",
+            QuestionSkill::Reading => "чтение",
+            QuestionSkill::Writing => "письмо",
+            QuestionSkill::Vocabulary => "лексика",
+            QuestionSkill::Grammar => "грамматика",
+        };
+        filters.push(skill.to_string());
+    }
+    if let Some(level) = section.hsk_level {
+        filters.push(format!("HSK {}", level));
+    }
+    if let Some(rule) = section.grammar_rule_id {
+        filters.push(format!("грамматическая тема {}", rule));
+    }
+    if filters.is_empty() { "любые вопросы".to_string() } else { filters.join(", ") }
+}
+
+/// Собирает по шаблону новый тест для пользователя и возвращает его id.
+///
+/// Вопросы берутся из банка случайно. Вопросы, которые пользователь видел за
+/// последние `RECENT_DAYS` дней (в собранных для него тестах или в пройденных
+/// общих тестах), исключаются; если для раздела не хватает новых вопросов,
+/// тест не собирается.
+pub async fn generate(user_id: i32, blueprint_id: i32, pool: &PgPool) -> Result<i32, AppError> {
+    let blueprint = get(blueprint_id, pool).await?;
+
+    let mut excluded: Vec<i32> = sqlx::query_scalar(
+        "SELECT ti.source_item_id FROM test_items ti JOIN tests t ON t.id = ti.test_id
+         WHERE t.generated_for = $1 AND t.created_at > NOW() - make_interval(days => $2)
+           AND ti.source_item_id IS NOT NULL
+         UNION
+         SELECT ti.id FROM test_items ti JOIN test_results r ON r.test_id = ti.test_id
+         WHERE r.user_id = $1 AND r.submitted_at > NOW() - make_interval(days => $2)",
+    )
+        .bind(user_id)
+        .bind(RECENT_DAYS)
+        .fetch_all(pool)
+        .await?;
+
+    let mut picked: Vec<i32> = Vec::new();
+    for section in &blueprint.sections {
+        let ids: Vec<i32> = sqlx::query_scalar(&format!(
+            "SELECT ti.id FROM test_items ti JOIN tests t ON t.id = ti.test_id
+             WHERE {}
+               AND ($1::question_skill_enum IS NULL OR ti.skill = $1)
+               AND ($2::smallint IS NULL OR ti.hsk_level = $2)
+               AND ($3::int IS NULL OR ti.grammar_rule_id = $3)
+               AND NOT (ti.id = ANY($4))
+             ORDER BY random()
+             LIMIT $5",
+            BANK_CONDITION
+        ))
+            .bind(section.skill)
+            .bind(section.hsk_level)
+            .bind(section.grammar_rule_id)
+            .bind(&excluded)
+            .bind(section.question_count as i64)
+            .fetch_all(pool)
+            .await?;
+
+        if ids.len() < section.question_count as usize {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                &format!(
+                    "Недостаточно новых вопросов ({}): нужно {}, доступно {}",
+                    describe(section),
+                    section.question_count,
+                    ids.len()
+                ),
+            )
+            .with_code("question_bank_exhausted"));
+        }
+        // Разделы могут пересекаться по тегам: один вопрос не попадает в тест дважды
+        excluded.extend(&ids);
+        picked.extend(ids);
+    }
+
+    let mut tx = pool.begin().await?;
+    let test_id: i32 = sqlx::query_scalar(
+        "INSERT INTO tests (name, description, generated_for, blueprint_id) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+        .bind(&blueprint.name)
+        .bind(&blueprint.description)
+        .bind(user_id)
+        .bind(blueprint.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO test_items (test_id, question, options, correct_answer, image_media_id, audio_media_id,
+                                 skill, hsk_level, grammar_rule_id, explanation, source_item_id)
+         SELECT $1, ti.question, ti.options, ti.correct_answer, ti.image_media_id, ti.audio_media_id,
+                ti.skill, ti.hsk_level, ti.grammar_rule_id, ti.explanation, ti.id
+         FROM UNNEST($2::int[]) WITH ORDINALITY AS p(item_id, position)
+         JOIN test_items ti ON ti.id = p.item_id
+         ORDER BY p.position",
+    )
+        .bind(test_id)
+        .bind(&picked)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(test_id)
+}
@@ -0,0 +1,136 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::contributions;
+use crate::errors::AppError;
+use crate::models::{BulkChange, BulkOperation, BulkPayload, BulkReport, BulkTarget};
+
+/// Максимальное количество элементов во всех операциях одного запроса.
+pub const MAX_BULK_ITEMS: usize = 1000;
+
+impl BulkTarget {
+    fn table(self) -> &'static str {
+        match self {
+            BulkTarget::Hieroglyphs => "hieroglyphs",
+            BulkTarget::Words => "words",
+            BulkTarget::Sentences => "sentences",
+        }
+    }
+
+    fn key_prefix(self) -> &'static str {
+        match self {
+            BulkTarget::Hieroglyphs => "hieroglyph",
+            BulkTarget::Words => "word",
+            BulkTarget::Sentences => "sentence",
+        }
+    }
+}
+
+impl BulkOperation {
+    fn item_count(&self) -> usize {
+        match self {
+            BulkOperation::Retag { ids, .. } => ids.len(),
+            BulkOperation::SetHskLevel { ids, .. } => ids.len(),
+            BulkOperation::PublishContributions { ids } => ids.len(),
+        }
+    }
+}
+
+/// Выполняет пакет операций в одной транзакции. В режиме `dry_run` транзакция
+/// откатывается, а в отчете остаются изменения, которые были бы внесены.
+/// Возвращает отчет и авторов принятых предложений (для начисления достижений).
+pub async fn execute(payload: BulkPayload, reviewer_id: i32, pool: &PgPool) -> Result<(BulkReport, Vec<i32>), AppError> {
+    let total: usize = payload.operations.iter().map(BulkOperation::item_count).sum();
+    if total == 0 || total > MAX_BULK_ITEMS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Пакет должен затрагивать от 1 до {} элементов", MAX_BULK_ITEMS),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut changes = Vec::new();
+    let mut contributors = Vec::new();
+
+    for (index, operation) in payload.operations.into_iter().enumerate() {
+        match operation {
+            BulkOperation::Retag { target, ids, add, remove } => {
+                let rows = sqlx::query_as::<_, (i32, Vec<String>, Vec<String>)>(&format!(
+                    "WITH old AS (SELECT id, tags FROM {table} WHERE id = ANY($1) FOR UPDATE)
+                     UPDATE {table} t
+                     SET tags = ARRAY(SELECT unnest(array_cat(old.tags, $2::TEXT[])) EXCEPT SELECT unnest($3::TEXT[]) ORDER BY 1)
+                     FROM old WHERE t.id = old.id
+                     RETURNING t.id, old.tags, t.tags",
+                    table = target.table(),
+                ))
+                    .bind(&ids)
+                    .bind(&add)
+                    .bind(&remove)
+                    .fetch_all(&mut *tx)
+                    .await?;
+
+                changes.extend(rows.into_iter().filter(|(_, before, after)| before != after).map(
+                    |(id, before, after)| BulkChange {
+                        operation: index,
+                        item: format!("{}:{}", target.key_prefix(), id),
+                        before: json!(before),
+                        after: json!(after),
+                    },
+                ));
+            }
+            BulkOperation::SetHskLevel { ids, level } => {
+                if !(1..=9).contains(&level) {
+                    return Err(AppError::new(
+                        StatusCode::BAD_REQUEST,
+                        &format!("Операция {}: некорректный уровень HSK", index),
+                    ));
+                }
+
+                let rows = sqlx::query_as::<_, (i32, Option<i16>)>(
+                    "WITH old AS (SELECT id, hsk_level FROM words WHERE id = ANY($1) FOR UPDATE)
+                     UPDATE words w SET hsk_level = $2
+                     FROM old WHERE w.id = old.id AND old.hsk_level IS DISTINCT FROM $2
+                     RETURNING w.id, old.hsk_level",
+                )
+                    .bind(&ids)
+                    .bind(level)
+                    .fetch_all(&mut *tx)
+                    .await?;
+
+                changes.extend(rows.into_iter().map(|(id, before)| BulkChange {
+                    operation: index,
+                    item: format!("word:{}", id),
+                    before: json!(before),
+                    after: json!(level),
+                }));
+            }
+            BulkOperation::PublishContributions { ids } => {
+                for id in ids {
+                    let contribution = contributions::accept(id, reviewer_id, &mut tx).await.map_err(|e| {
+                        AppError::new(e.status_code(), &format!("Операция {}, предложение {}: {}", index, id, e.message()))
+                    })?;
+                    contributors.push(contribution.user_id);
+                    changes.push(BulkChange {
+                        operation: index,
+                        item: format!("contribution:{}", id),
+                        before: json!("pending"),
+                        after: json!({ "status": "accepted", "content_id": contribution.content_id }),
+                    });
+                }
+            }
+        }
+    }
+
+    if payload.dry_run {
+        tx.rollback().await?;
+        contributors.clear();
+    } else {
+        tx.commit().await?;
+    }
+
+    contributors.sort_unstable();
+    contributors.dedup();
+
+    Ok((BulkReport { dry_run: payload.dry_run, changes }, contributors))
+}
@@ -0,0 +1,109 @@
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// Медиафайлы и озвучки не меняются: новая загрузка получает новый ID.
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// То же для файлов, доступных не всем: общие кеши их не хранят.
+pub const PRIVATE_IMMUTABLE: &str = "private, max-age=31536000, immutable";
+/// Контент может измениться в любой момент, поэтому клиент перепроверяет его по `ETag`.
+pub const REVALIDATE: &str = "private, no-cache";
+/// Ответы крупнее не хешируются и уходят без `ETag`.
+const MAX_ETAG_BODY: usize = 2 * 1024 * 1024;
+
+/// Дата в формате HTTP (`Sun, 06 Nov 1994 08:49:37 GMT`).
+pub fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Клиент уже получал версию не старше `modified` (точность заголовка — секунда).
+pub fn not_modified_since(headers: &HeaderMap, modified: DateTime<Utc>) -> bool {
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| modified.timestamp() <= since.timestamp())
+}
+
+/// Заголовки неизменяемого файла.
+pub fn immutable_headers(modified: DateTime<Utc>) -> [(header::HeaderName, String); 2] {
+    [
+        (header::CACHE_CONTROL, IMMUTABLE.to_string()),
+        (header::LAST_MODIFIED, http_date(modified)),
+    ]
+}
+
+/// Ответ 304 для неизменяемого файла.
+pub fn not_modified(modified: DateTime<Utc>) -> Response {
+    (StatusCode::NOT_MODIFIED, immutable_headers(modified)).into_response()
+}
+
+/// `ETag` по содержимому ответа.
+pub fn etag(body: &[u8]) -> String {
+    format!("\"{}\"", &hex::encode(Sha256::digest(body))[..32])
+}
+
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }))
+}
+
+/// Middleware для чтения контента: проставляет `ETag` и `Cache-Control`
+/// и отвечает 304, если у клиента та же версия. У контента нет даты изменения,
+/// поэтому версия определяется хешем тела ответа.
+pub async fn revalidate(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let request_headers = request.headers().clone();
+    let response = next.run(request).await;
+    // Неизменяемые файлы уже помечены обработчиком, частичные ответы не хешируются
+    if response.status() != StatusCode::OK || response.headers().contains_key(header::LAST_MODIFIED) {
+        return response;
+    }
+
+    let size = response.body().size_hint().upper();
+    if size.is_none_or(|size| size > MAX_ETAG_BODY as u64) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_ETAG_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Не удалось прочитать ответ для ETag: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = etag(&bytes);
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    parts.headers.insert(header::ETAG, etag_value.clone());
+    parts
+        .headers
+        .entry(header::CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static(REVALIDATE));
+
+    if etag_matches(&request_headers, &etag) {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        response.headers_mut().insert(header::ETAG, etag_value);
+        if let Some(cache_control) = parts.headers.get(header::CACHE_CONTROL) {
+            response.headers_mut().insert(header::CACHE_CONTROL, cache_control.clone());
+        }
+        return response;
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
@@ -0,0 +1,229 @@
+use axum::async_trait;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+use crate::errors::AppError;
+use crate::importer::{ImportPlan, Importer, Prepared};
+use crate::jobs;
+use crate::models::{ImportAction, ImportFormat, ImportPreview, ImportResult};
+
+/// Сколько записей вставляется одним запросом.
+const BATCH_SIZE: usize = 500;
+
+/// Статья CC-CEDICT: `繁體 简体 [pin1 yin1] /перевод 1/перевод 2/`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CedictEntry {
+    pub simplified: String,
+    /// Пиньинь с цифрами тонов, как в файле.
+    pub pinyin: String,
+    pub glosses: Vec<String>,
+}
+
+impl CedictEntry {
+    /// Имена собственные (фамилии, топонимы) записаны с заглавной буквы.
+    fn is_proper_noun(&self) -> bool {
+        self.pinyin.starts_with(|c: char| c.is_ascii_uppercase())
+    }
+
+    /// Статья только отсылает к другому написанию.
+    fn is_variant(&self) -> bool {
+        self.glosses.iter().all(|g| {
+            g.starts_with("variant of") || g.starts_with("old variant of") || g.starts_with("see ")
+        })
+    }
+}
+
+/// Разбирает строку файла. Комментарии и некорректные строки дают `None`.
+pub fn parse_line(line: &str) -> Option<CedictEntry> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (headwords, rest) = line.split_once(" [")?;
+    let (pinyin, glosses) = rest.split_once("] /")?;
+    let (_traditional, simplified) = headwords.split_once(' ')?;
+    let glosses: Vec<String> = glosses
+        .trim_end_matches('/')
+        .split('/')
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(str::to_string)
+        .collect();
+    if simplified.is_empty() || glosses.is_empty() {
+        return None;
+    }
+    Some(CedictEntry { simplified: simplified.to_string(), pinyin: pinyin.trim().to_string(), glosses })
+}
+
+fn tone_mark(vowel: char, tone: usize) -> char {
+    let marks: [char; 4] = match vowel {
+        'a' => ['ā', 'á', 'ǎ', 'à'],
+        'e' => ['ē', 'é', 'ě', 'è'],
+        'i' => ['ī', 'í', 'ǐ', 'ì'],
+        'o' => ['ō', 'ó', 'ǒ', 'ò'],
+        'u' => ['ū', 'ú', 'ǔ', 'ù'],
+        'ü' => ['ǖ', 'ǘ', 'ǚ', 'ǜ'],
+        'A' => ['Ā', 'Á', 'Ǎ', 'À'],
+        'E' => ['Ē', 'É', 'Ě', 'È'],
+        'O' => ['Ō', 'Ó', 'Ǒ', 'Ò'],
+        _ => return vowel,
+    };
+    marks[tone - 1]
+}
+
+/// Переводит слог с цифрой тона в запись со знаком: `lu:4` → `lǜ`, `zi5` → `zi`.
+fn mark_syllable(syllable: &str) -> String {
+    let syllable = syllable.replace("u:", "ü").replace('v', "ü");
+    let Some(tone) = syllable.chars().last().and_then(|c| c.to_digit(10)) else {
+        return syllable;
+    };
+    let base = &syllable[..syllable.len() - 1];
+    if !(1..=4).contains(&tone) {
+        return base.to_string();
+    }
+
+    // Знак ставится на a или e, в сочетании ou — на o, иначе на последнюю гласную
+    let lower = base.to_lowercase();
+    let position = lower
+        .find('a')
+        .or_else(|| lower.find('e'))
+        .or_else(|| lower.find("ou"))
+        .or_else(|| lower.char_indices().filter(|(_, c)| "iouü".contains(*c)).map(|(i, _)| i).last());
+    match position {
+        Some(position) => base
+            .char_indices()
+            .map(|(i, c)| if i == position { tone_mark(c, tone as usize) } else { c })
+            .collect(),
+        None => base.to_string(),
+    }
+}
+
+/// Пиньинь CEDICT с цифрами тонов в привычной записи со знаками тонов.
+pub fn to_tone_marks(pinyin: &str) -> String {
+    pinyin.split_whitespace().map(mark_syllable).collect::<Vec<_>>().join(" ")
+}
+
+/// Импорт CC-CEDICT. Однознаковые статьи становятся иероглифами, остальные — словами.
+///
+/// Повторные статьи одного слова (другие чтения) пропускаются — остается первая,
+/// как и имена собственные и статьи-отсылки к вариантам написания. Записи, уже
+/// существующие в базе, не перезаписываются.
+pub struct CedictImporter;
+
+#[async_trait]
+impl Importer for CedictImporter {
+    async fn prepare(&self, data: &str, pool: &PgPool, job_id: u64) -> Result<Prepared, AppError> {
+        let mut preview = ImportPreview::new(ImportFormat::Cedict);
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut entries: Vec<(usize, CedictEntry)> = Vec::new();
+
+        jobs::set_total(job_id, data.lines().count());
+        for (index, line) in data.lines().enumerate() {
+            jobs::advance(job_id);
+            let line_number = index + 1;
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            let Some(entry) = parse_line(line) else {
+                preview.push(Some(line_number), None, ImportAction::Error, Some("Строка не разобрана".to_string()));
+                continue;
+            };
+            let reason = if entry.is_proper_noun() {
+                Some("имя собственное")
+            } else if entry.is_variant() {
+                Some("отсылка к варианту написания")
+            } else if !seen.insert(entry.simplified.clone()) {
+                Some("повторная статья, остается первая")
+            } else {
+                None
+            };
+            match reason {
+                Some(reason) => {
+                    preview.push(Some(line_number), Some(&entry.simplified), ImportAction::Skip, Some(reason.to_string()))
+                }
+                None => entries.push((line_number, entry)),
+            }
+        }
+
+        let mut plan = CedictPlan { hieroglyphs: Vec::new(), words: Vec::new() };
+        for batch in entries.chunks(BATCH_SIZE) {
+            let texts: Vec<&str> = batch.iter().map(|(_, e)| e.simplified.as_str()).collect();
+            let existing: HashSet<String> = sqlx::query_scalar(
+                "SELECT character FROM hieroglyphs WHERE character = ANY($1)
+                 UNION ALL
+                 SELECT simplified FROM words WHERE simplified = ANY($1)",
+            )
+                .bind(&texts)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .collect();
+
+            for (line_number, entry) in batch {
+                if existing.contains(&entry.simplified) {
+                    preview.push(Some(*line_number), Some(&entry.simplified), ImportAction::Unchanged, None);
+                    continue;
+                }
+                let details = format!("{} — {}", to_tone_marks(&entry.pinyin), entry.glosses.join("; "));
+                preview.push(Some(*line_number), Some(&entry.simplified), ImportAction::Create, Some(details));
+                if entry.simplified.chars().count() == 1 {
+                    plan.hieroglyphs.push(entry.clone());
+                } else {
+                    plan.words.push(entry.clone());
+                }
+            }
+        }
+
+        Ok(Prepared { preview, plan: Box::new(plan) })
+    }
+}
+
+struct CedictPlan {
+    hieroglyphs: Vec<CedictEntry>,
+    words: Vec<CedictEntry>,
+}
+
+#[async_trait]
+impl ImportPlan for CedictPlan {
+    async fn apply(self: Box<Self>, pool: &PgPool, job_id: u64) -> Result<ImportResult, AppError> {
+        jobs::set_total(job_id, self.hieroglyphs.len() + self.words.len());
+        let mut result = ImportResult::default();
+        for batch in self.hieroglyphs.chunks(BATCH_SIZE) {
+            result.created += insert(batch, true, pool).await?;
+            jobs::advance_by(job_id, batch.len());
+        }
+        for batch in self.words.chunks(BATCH_SIZE) {
+            result.created += insert(batch, false, pool).await?;
+            jobs::advance_by(job_id, batch.len());
+        }
+        Ok(result)
+    }
+}
+
+/// Вставляет иероглифы или слова и возвращает число новых. Записи, появившиеся
+/// в базе после предпросмотра, не меняются.
+async fn insert(batch: &[CedictEntry], single_characters: bool, pool: &PgPool) -> Result<usize, AppError> {
+    let texts: Vec<&str> = batch.iter().map(|e| e.simplified.as_str()).collect();
+    let pinyin: Vec<String> = batch.iter().map(|e| to_tone_marks(&e.pinyin)).collect();
+    let translations: Vec<String> = batch.iter().map(|e| e.glosses.join("; ")).collect();
+
+    let query = if single_characters {
+        "INSERT INTO hieroglyphs (character, pinyin, translation)
+         SELECT n.text, n.pinyin, n.translation
+         FROM UNNEST($1::text[], $2::text[], $3::text[]) AS n(text, pinyin, translation)
+         WHERE NOT EXISTS (SELECT 1 FROM hieroglyphs h WHERE h.character = n.text)"
+    } else {
+        "INSERT INTO words (simplified, pinyin, translation)
+         SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+         ON CONFLICT (simplified) DO NOTHING"
+    };
+    let created = sqlx::query(query)
+        .bind(&texts)
+        .bind(&pinyin)
+        .bind(&translations)
+        .execute(pool)
+        .await?
+        .rows_affected() as usize;
+    Ok(created)
+}
@@ -0,0 +1,90 @@
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::authz::{Admin, RoleRequirement};
+use crate::models::Claims;
+use axum::http::StatusCode;
+
+/// SQL-условие видимости теста `t` для пользователя `$1`.
+///
+/// Собственные тесты класса видны только его ученикам и учителю. Глобальные тесты
+/// видны всем, кроме учеников классов с ограниченным контентом: им доступны
+/// только тесты, разрешенные хотя бы одним из их классов. Тест, собранный
+/// по шаблону, виден только тому, для кого он собран.
+pub const TEST_VISIBILITY_CONDITION: &str = "(
+    t.generated_for = $1
+    OR (t.class_id IS NULL AND t.generated_for IS NULL AND (
+        NOT EXISTS (
+            SELECT 1 FROM class_members cm JOIN classes c ON c.id = cm.class_id
+            WHERE cm.user_id = $1 AND c.restrict_content
+        )
+        OR EXISTS (
+            SELECT 1 FROM class_members cm JOIN class_tests ct ON ct.class_id = cm.class_id
+            WHERE cm.user_id = $1 AND ct.test_id = t.id
+        )
+    ))
+    OR t.class_id IN (SELECT class_id FROM class_members WHERE user_id = $1)
+    OR t.class_id IN (SELECT id FROM classes WHERE teacher_id = $1)
+)";
+
+/// SQL-условие видимости колоды `d` для пользователя `$1`: ученикам классов
+/// с ограниченным контентом видны только колоды, разрешенные хотя бы одним из их классов.
+pub const DECK_VISIBILITY_CONDITION: &str = "(
+    NOT EXISTS (
+        SELECT 1 FROM class_members cm JOIN classes c ON c.id = cm.class_id
+        WHERE cm.user_id = $1 AND c.restrict_content
+    )
+    OR EXISTS (
+        SELECT 1 FROM class_members cm JOIN class_decks cd ON cd.class_id = cm.class_id
+        WHERE cm.user_id = $1 AND cd.deck_id = d.id
+    )
+)";
+
+/// SQL-условие видимости урока `l` для пользователя `$1`, как у колод.
+pub const LESSON_VISIBILITY_CONDITION: &str = "(
+    NOT EXISTS (
+        SELECT 1 FROM class_members cm JOIN classes c ON c.id = cm.class_id
+        WHERE cm.user_id = $1 AND c.restrict_content
+    )
+    OR EXISTS (
+        SELECT 1 FROM class_members cm JOIN class_lessons cl ON cl.class_id = cm.class_id
+        WHERE cm.user_id = $1 AND cl.lesson_id = l.id
+    )
+)";
+
+/// Проверяет, что пользователь — учитель класса или администратор.
+pub async fn ensure_teacher(class_id: i32, claims: &Claims, pool: &PgPool) -> Result<(), AppError> {
+    let teacher_id: i32 = sqlx::query_scalar("SELECT teacher_id FROM classes WHERE id = $1")
+        .bind(class_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Класс не найден"))?;
+
+    if teacher_id != claims.user_id && !Admin::allows(&claims.role) {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
+    }
+
+    Ok(())
+}
+
+/// Проверяет, виден ли тест пользователю (администраторам видно все).
+pub async fn ensure_test_visible(test_id: i32, claims: Option<&Claims>, pool: &PgPool) -> Result<(), AppError> {
+    if claims.is_some_and(|c| Admin::allows(&c.role)) {
+        return Ok(());
+    }
+
+    let visible: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS (SELECT 1 FROM tests t WHERE t.id = $2 AND {})",
+        TEST_VISIBILITY_CONDITION
+    ))
+        .bind(claims.map(|c| c.user_id))
+        .bind(test_id)
+        .fetch_one(pool)
+        .await?;
+
+    if !visible {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Тест не найден"));
+    }
+
+    Ok(())
+}
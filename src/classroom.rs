@@ -0,0 +1,198 @@
+use axum::extract::ws::{Message, WebSocket};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+use crate::errors::AppError;
+use crate::events::Event;
+use crate::models::BoardEntry;
+use crate::presence;
+
+/// Сколько обновлений может отстать медленный зритель, прежде чем получит снимок заново.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Сообщение живой доски класса.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BoardMessage {
+    /// Вся доска: отправляется при подключении и после отставания.
+    Snapshot { students: Vec<BoardEntry> },
+    /// Ученик что-то сделал: его обновленная строка и само событие.
+    Activity { student: BoardEntry, event: Event },
+}
+
+// Каналы классов, которые сейчас кто-то смотрит. Как и присутствие,
+// живут только в памяти процесса.
+static CHANNELS: Lazy<Mutex<HashMap<i32, broadcast::Sender<BoardMessage>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn subscribe(class_id: i32) -> broadcast::Receiver<BoardMessage> {
+    CHANNELS
+        .lock()
+        .unwrap()
+        .entry(class_id)
+        .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+        .subscribe()
+}
+
+fn unsubscribe(class_id: i32) {
+    let mut channels = CHANNELS.lock().unwrap();
+    if channels.get(&class_id).is_some_and(|sender| sender.receiver_count() == 0) {
+        channels.remove(&class_id);
+    }
+}
+
+/// Классы, у доски которых сейчас есть зрители.
+fn watched_classes() -> Vec<i32> {
+    CHANNELS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, sender)| sender.receiver_count() > 0)
+        .map(|(class_id, _)| *class_id)
+        .collect()
+}
+
+#[derive(sqlx::FromRow)]
+struct BoardRow {
+    user_id: i32,
+    nickname: String,
+    presence_visible: bool,
+    reviews_today: i64,
+    learned_today: i64,
+    tests_today: i64,
+    last_active_at: Option<DateTime<Utc>>,
+}
+
+// Счетчики считаются с начала текущих суток
+const BOARD_COLUMNS: &str = "
+    u.id AS user_id, u.nickname, u.presence_visible,
+    (SELECT COUNT(*) FROM review_log r
+     WHERE r.user_id = u.id AND r.reviewed_at >= date_trunc('day', NOW())) AS reviews_today,
+    (SELECT COUNT(*) FROM user_progress up
+     WHERE up.user_id = u.id AND up.is_learned AND up.learned_at >= date_trunc('day', NOW())) AS learned_today,
+    (SELECT COUNT(*) FROM test_results tr
+     WHERE tr.user_id = u.id AND tr.submitted_at >= date_trunc('day', NOW())) AS tests_today,
+    GREATEST(
+        (SELECT MAX(r.reviewed_at) FROM review_log r WHERE r.user_id = u.id),
+        (SELECT MAX(up.learned_at) FROM user_progress up WHERE up.user_id = u.id),
+        (SELECT MAX(tr.submitted_at) FROM test_results tr WHERE tr.user_id = u.id)
+    ) AS last_active_at";
+
+fn to_entries(rows: Vec<BoardRow>) -> Vec<BoardEntry> {
+    let users = rows.iter().map(|row| (row.user_id, row.nickname.clone(), row.presence_visible)).collect();
+    presence::snapshot(users)
+        .into_iter()
+        .zip(rows)
+        .map(|(presence, row)| BoardEntry {
+            presence,
+            reviews_today: row.reviews_today,
+            learned_today: row.learned_today,
+            tests_today: row.tests_today,
+            last_active_at: row.last_active_at,
+        })
+        .collect()
+}
+
+/// Доска класса целиком: все ученики по алфавиту.
+pub async fn board(class_id: i32, pool: &PgPool) -> Result<Vec<BoardEntry>, AppError> {
+    let rows = sqlx::query_as::<_, BoardRow>(&format!(
+        "SELECT {} FROM class_members cm JOIN users u ON u.id = cm.user_id
+         WHERE cm.class_id = $1
+         ORDER BY u.nickname",
+        BOARD_COLUMNS
+    ))
+        .bind(class_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(to_entries(rows))
+}
+
+/// Рассылает событие ученика на доски его классов, которые сейчас открыты.
+pub async fn broadcast(user_id: i32, event: &Event, pool: &PgPool) -> Result<(), AppError> {
+    let watched = watched_classes();
+    if watched.is_empty() {
+        return Ok(());
+    }
+
+    let class_ids: Vec<i32> = sqlx::query_scalar(
+        "SELECT class_id FROM class_members WHERE user_id = $1 AND class_id = ANY($2)",
+    )
+        .bind(user_id)
+        .bind(&watched)
+        .fetch_all(pool)
+        .await?;
+    if class_ids.is_empty() {
+        return Ok(());
+    }
+
+    let rows = sqlx::query_as::<_, BoardRow>(&format!("SELECT {} FROM users u WHERE u.id = $1", BOARD_COLUMNS))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    let Some(student) = to_entries(rows).pop() else {
+        return Ok(());
+    };
+
+    let channels = CHANNELS.lock().unwrap();
+    for class_id in class_ids {
+        if let Some(sender) = channels.get(&class_id) {
+            // Ошибка означает, что зрители только что ушли
+            let _ = sender.send(BoardMessage::Activity { student: student.clone(), event: event.clone() });
+        }
+    }
+    Ok(())
+}
+
+async fn send(socket: &mut WebSocket, message: &BoardMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(e) => {
+            tracing::warn!("Сообщение доски не сериализуется: {}", e);
+            true
+        }
+    }
+}
+
+async fn send_snapshot(socket: &mut WebSocket, class_id: i32, pool: &PgPool) -> bool {
+    match board(class_id, pool).await {
+        Ok(students) => send(socket, &BoardMessage::Snapshot { students }).await,
+        Err(e) => {
+            tracing::warn!("Не удалось собрать доску класса {}: {}", class_id, e.message());
+            false
+        }
+    }
+}
+
+/// Обслуживает WebSocket учителя: снимок доски, затем поток событий учеников.
+pub async fn handle_board(mut socket: WebSocket, class_id: i32, pool: PgPool) {
+    // Подписка до снимка, чтобы не потерять события между ними
+    let mut updates = subscribe(class_id);
+
+    if send_snapshot(&mut socket, class_id, &pool).await {
+        loop {
+            tokio::select! {
+                update = updates.recv() => {
+                    let sent = match update {
+                        Ok(message) => send(&mut socket, &message).await,
+                        Err(broadcast::error::RecvError::Lagged(_)) => send_snapshot(&mut socket, class_id, &pool).await,
+                        Err(broadcast::error::RecvError::Closed) => false,
+                    };
+                    if !sent {
+                        break;
+                    }
+                }
+                incoming = socket.recv() => match incoming {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    }
+
+    drop(updates);
+    unsubscribe(class_id);
+}
@@ -0,0 +1,79 @@
+use axum::http::StatusCode;
+use rand::seq::SliceRandom;
+use sqlx::PgPool;
+
+use crate::distractors;
+use crate::errors::AppError;
+use crate::hidden;
+use crate::models::{CompositionPrompt, CompositionResult, ContentType};
+use crate::progress;
+
+/// Сколько лишних иероглифов добавляется к буквам слова.
+pub const DISTRACTOR_COUNT: usize = 4;
+
+#[derive(sqlx::FromRow)]
+struct TargetWord {
+    id: i32,
+    simplified: String,
+    pinyin: String,
+    translation: String,
+}
+
+/// Следующее слово для сборки: все его иероглифы пользователь уже выучил,
+/// а само слово — еще нет. Чаще попадаются слова, в которых он ошибался.
+pub async fn next_prompt(user_id: i32, pool: &PgPool) -> Result<CompositionPrompt, AppError> {
+    let word = sqlx::query_as::<_, TargetWord>(&format!(
+        "SELECT w.id, w.simplified, w.pinyin, w.translation
+         FROM words w
+         JOIN word_characters wc ON wc.word_id = w.id
+         LEFT JOIN user_progress hp
+             ON hp.user_id = $1 AND hp.content_type = 'hieroglyph' AND hp.content_id = wc.hieroglyph_id
+         LEFT JOIN user_progress wp
+             ON wp.user_id = $1 AND wp.content_type = 'word' AND wp.content_id = w.id
+         LEFT JOIN weak_items wi
+             ON wi.user_id = $1 AND wi.content_type = 'word' AND wi.content_id = w.id
+         WHERE NOT COALESCE(wp.is_learned, FALSE) AND {}
+         GROUP BY w.id, wi.misses
+         HAVING COUNT(*) = char_length(w.simplified) AND bool_and(COALESCE(hp.is_learned, FALSE))
+         ORDER BY COALESCE(wi.misses, 0) DESC, random()
+         LIMIT 1",
+        hidden::not_hidden("$1", "'word'", "w.id")
+    ))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::NOT_FOUND, "Нет слов, которые можно собрать из выученных иероглифов")
+                .with_code("no_composable_words")
+        })?;
+
+    // Лишние иероглифы — тоже выученные, чтобы ответ нельзя было угадать по знакомым
+    let distractors = distractors::hieroglyph_tiles(user_id, &word.simplified, DISTRACTOR_COUNT, pool).await?;
+
+    let mut tiles: Vec<String> = word.simplified.chars().map(String::from).collect();
+    let length = tiles.len();
+    tiles.extend(distractors);
+    tiles.shuffle(&mut rand::thread_rng());
+
+    Ok(CompositionPrompt { word_id: word.id, pinyin: word.pinyin, translation: word.translation, length, tiles })
+}
+
+/// Проверяет собранное слово; ошибка записывается в слабые места.
+pub async fn check(user_id: i32, word_id: i32, answer: &str, pool: &PgPool) -> Result<CompositionResult, AppError> {
+    let word = sqlx::query_as::<_, TargetWord>("SELECT id, simplified, pinyin, translation FROM words WHERE id = $1")
+        .bind(word_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Слово не найдено"))?;
+
+    let correct = is_correct(&word.simplified, answer);
+    if !correct {
+        progress::record_miss(user_id, ContentType::Word, word.id, pool).await?;
+    }
+    Ok(CompositionResult { correct, correct_text: word.simplified, pinyin: word.pinyin, translation: word.translation })
+}
+
+/// Ответ засчитывается, если иероглифы совпадают без учета пробелов между ними.
+pub fn is_correct(word: &str, answer: &str) -> bool {
+    answer.chars().filter(|c| !c.is_whitespace()).eq(word.chars())
+}
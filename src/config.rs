@@ -0,0 +1,383 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::models::ClientConfig;
+
+/// Режим регистрации новых пользователей (`REGISTRATION_MODE`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    /// Регистрация открыта для всех (по умолчанию).
+    Open,
+    /// Регистрация только по коду приглашения.
+    Invite,
+    /// Публичная регистрация отключена, аккаунты создает администратор.
+    Closed,
+}
+
+/// Возвращает текущий режим регистрации.
+pub fn registration_mode() -> RegistrationMode {
+    match env::var("REGISTRATION_MODE").as_deref() {
+        Ok("invite") => RegistrationMode::Invite,
+        Ok("closed") => RegistrationMode::Closed,
+        _ => RegistrationMode::Open,
+    }
+}
+
+/// Правила доменов почты для публичной регистрации. Домен подходит под правило,
+/// если совпадает с ним или является его поддоменом.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmailDomainRules {
+    /// Разрешенные домены (`REGISTRATION_EMAIL_DOMAINS`); если список не пуст, почта обязательна.
+    pub allow: Vec<String>,
+    /// Запрещенные домены (`REGISTRATION_EMAIL_DENY_DOMAINS`), проверяются первыми.
+    pub deny: Vec<String>,
+}
+
+fn domain_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+/// Правила доменов почты, списки через запятую: `REGISTRATION_EMAIL_DOMAINS=school.edu`.
+pub fn email_domain_rules() -> EmailDomainRules {
+    EmailDomainRules {
+        allow: domain_list("REGISTRATION_EMAIL_DOMAINS"),
+        deny: domain_list("REGISTRATION_EMAIL_DENY_DOMAINS"),
+    }
+}
+
+/// Сколько аккаунтов можно зарегистрировать с одного IP за сутки (`REGISTRATION_DAILY_IP_LIMIT`).
+/// Без значения ограничения нет.
+pub fn registration_daily_ip_limit() -> Option<i64> {
+    env::var("REGISTRATION_DAILY_IP_LIMIT").ok().and_then(|v| v.parse().ok()).filter(|limit| *limit > 0)
+}
+
+/// Лимит запросов: не больше `max` за скользящее окно `window`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub max: u32,
+    pub window: Duration,
+}
+
+/// Лимиты входа, регистрации и обновления токена.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthRateLimits {
+    pub ip: Option<RateLimit>,
+    pub nickname: Option<RateLimit>,
+}
+
+/// Разбирает лимит вида `10/60` — запросов за столько секунд. `off` отключает лимит.
+pub fn parse_rate_limit(value: &str) -> Option<RateLimit> {
+    let (max, window) = value.trim().split_once('/')?;
+    let max: u32 = max.trim().parse().ok().filter(|max| *max > 0)?;
+    let window: u64 = window.trim().parse().ok().filter(|window| *window > 0)?;
+    Some(RateLimit { max, window: Duration::from_secs(window) })
+}
+
+fn rate_limit_var(name: &str, default: &str) -> Option<RateLimit> {
+    let value = env::var(name).unwrap_or_else(|_| default.to_string());
+    let limit = parse_rate_limit(&value);
+    if limit.is_none() && value.trim() != "off" {
+        tracing::warn!("{} не распознан: {}", name, value);
+    }
+    limit
+}
+
+/// Лимиты `/api/login`, `/api/register` и `/api/refresh`: с одного IP (`AUTH_RATE_LIMIT_IP`,
+/// по умолчанию `30/60`) и на один никнейм с любых адресов (`AUTH_RATE_LIMIT_NICKNAME`, `5/60`).
+pub fn auth_rate_limits() -> AuthRateLimits {
+    AuthRateLimits {
+        ip: rate_limit_var("AUTH_RATE_LIMIT_IP", "30/60"),
+        nickname: rate_limit_var("AUTH_RATE_LIMIT_NICKNAME", "5/60"),
+    }
+}
+
+/// Временная блокировка входа: после `max_failures` неверных паролей подряд
+/// (за время блокировки) аккаунт закрывается на `duration`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoginLockout {
+    pub max_failures: i64,
+    pub duration: Duration,
+}
+
+/// Блокировка входа: `LOGIN_LOCKOUT_THRESHOLD` неудач (по умолчанию 5, `0` отключает)
+/// закрывают вход на `LOGIN_LOCKOUT_MINUTES` минут (по умолчанию 15).
+pub fn login_lockout() -> Option<LoginLockout> {
+    let max_failures: i64 = env::var("LOGIN_LOCKOUT_THRESHOLD").ok().and_then(|v| v.parse().ok()).unwrap_or(5);
+    let minutes: u64 = env::var("LOGIN_LOCKOUT_MINUTES").ok().and_then(|v| v.parse().ok()).filter(|m| *m > 0).unwrap_or(15);
+    (max_failures > 0).then(|| LoginLockout { max_failures, duration: Duration::from_secs(minutes * 60) })
+}
+
+/// Требования к паролю.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    /// Сколько разных классов символов (строчные, заглавные, цифры, прочие) нужно.
+    pub min_classes: usize,
+    /// Отклонять пароли из встроенного списка распространенных.
+    pub reject_common: bool,
+}
+
+/// Требования к паролю: `PASSWORD_MIN_LENGTH` (по умолчанию 8), `PASSWORD_MIN_CLASSES`
+/// (по умолчанию 2, от 1 до 4) и `PASSWORD_REJECT_COMMON` (по умолчанию включено, `0` отключает).
+pub fn password_policy() -> PasswordPolicy {
+    let min_length = env::var("PASSWORD_MIN_LENGTH").ok().and_then(|v| v.parse().ok()).unwrap_or(8);
+    let min_classes: usize = env::var("PASSWORD_MIN_CLASSES").ok().and_then(|v| v.parse().ok()).unwrap_or(2);
+    let reject_common = !matches!(env::var("PASSWORD_REJECT_COMMON").as_deref(), Ok("0") | Ok("false"));
+    PasswordPolicy { min_length, min_classes: min_classes.clamp(1, 4), reject_common }
+}
+
+/// Параметры Argon2id для хеширования паролей.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordHashing {
+    /// Объем памяти в КиБ.
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Параметры Argon2id: `ARGON2_MEMORY_KIB` (по умолчанию 19456), `ARGON2_ITERATIONS`
+/// (по умолчанию 2) и `ARGON2_PARALLELISM` (по умолчанию 1) — минимум, рекомендованный OWASP.
+/// Хеши с другими параметрами пересчитываются при следующем входе пользователя.
+pub fn password_hashing() -> PasswordHashing {
+    let memory_kib = env::var("ARGON2_MEMORY_KIB").ok().and_then(|v| v.parse().ok()).unwrap_or(19456);
+    let iterations = env::var("ARGON2_ITERATIONS").ok().and_then(|v| v.parse().ok()).unwrap_or(2);
+    let parallelism = env::var("ARGON2_PARALLELISM").ok().and_then(|v| v.parse().ok()).unwrap_or(1);
+    PasswordHashing { memory_kib, iterations, parallelism }
+}
+
+/// Привязка refresh-сессии к устройству, на котором был выполнен вход (`REFRESH_DEVICE_BINDING`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceBinding {
+    /// Устройство не сверяется.
+    Off,
+    /// Обновление с другого устройства проходит, но попадает в журнал безопасности (по умолчанию).
+    Warn,
+    /// Обновление с другого устройства отклоняется, а сессия отзывается.
+    Strict,
+}
+
+/// Возвращает режим привязки сессий к устройству.
+pub fn device_binding() -> DeviceBinding {
+    match env::var("REFRESH_DEVICE_BINDING").as_deref() {
+        Ok("off") => DeviceBinding::Off,
+        Ok("strict") => DeviceBinding::Strict,
+        _ => DeviceBinding::Warn,
+    }
+}
+
+/// Сколько доверенных прокси стоит перед сервером: адрес клиента берется из
+/// `X-Forwarded-For` (`TRUST_FORWARDED_FOR=1`) на столько записей от правого края
+/// (`TRUSTED_PROXY_HOPS`, по умолчанию 1). Ноль — заголовок не используется: без
+/// обратного прокси его подделывает сам клиент.
+pub fn trusted_proxy_hops() -> usize {
+    if !matches!(env::var("TRUST_FORWARDED_FOR").as_deref(), Ok("1") | Ok("true")) {
+        return 0;
+    }
+    env::var("TRUSTED_PROXY_HOPS").ok().and_then(|v| v.parse().ok()).filter(|hops| *hops > 0).unwrap_or(1)
+}
+
+/// Общий секрет HS256 (`JWT_SECRET`). Становится ключом `default`: так работают
+/// установки без каталога ключей, и им проверяются токены, выданные до ротации.
+pub fn jwt_secret() -> Option<String> {
+    env::var("JWT_SECRET").ok().filter(|secret| !secret.is_empty())
+}
+
+/// Каталог ключей JWT (`JWT_KEYS_DIR`); формат файлов описан в `jwt_keys::load_dir`.
+pub fn jwt_keys_dir() -> Option<PathBuf> {
+    env::var("JWT_KEYS_DIR").ok().filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+/// Ключ, которым подписываются новые токены (`JWT_ACTIVE_KID`). Если не задан,
+/// берется ключ с наибольшим именем из тех, что могут подписывать.
+pub fn jwt_active_kid() -> Option<String> {
+    env::var("JWT_ACTIVE_KID").ok().filter(|kid| !kid.is_empty())
+}
+
+/// Адрес SMTP-сервера `хост:порт` (`SMTP_HOST`, `SMTP_PORT`, по умолчанию 587), если почта настроена.
+pub fn smtp_address() -> Option<String> {
+    let host = env::var("SMTP_HOST").ok().filter(|host| !host.is_empty())?;
+    let port = env::var("SMTP_PORT").ok().and_then(|port| port.parse::<u16>().ok()).unwrap_or(587);
+    Some(format!("{}:{}", host, port))
+}
+
+/// Адрес отправителя писем (`SMTP_FROM`).
+pub fn smtp_from() -> String {
+    env::var("SMTP_FROM").ok().filter(|from| !from.is_empty()).unwrap_or_else(|| "noreply@localhost".to_string())
+}
+
+/// Параметры S3-совместимого хранилища аудио.
+#[derive(Debug, Clone)]
+pub struct S3Settings {
+    /// Адрес сервиса, например `https://s3.eu-central-1.amazonaws.com` или адрес MinIO.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// Хранилище аудио в S3 (`AUDIO_S3_ENDPOINT`, `AUDIO_S3_BUCKET`, `AUDIO_S3_ACCESS_KEY`,
+/// `AUDIO_S3_SECRET_KEY`, `AUDIO_S3_REGION` — по умолчанию `us-east-1`).
+/// Без этих переменных аудио хранится на диске рядом с остальными медиафайлами.
+pub fn audio_s3() -> Option<S3Settings> {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    Some(S3Settings {
+        endpoint: var("AUDIO_S3_ENDPOINT")?.trim_end_matches('/').to_string(),
+        bucket: var("AUDIO_S3_BUCKET")?,
+        region: var("AUDIO_S3_REGION").unwrap_or_else(|| "us-east-1".to_string()),
+        access_key: var("AUDIO_S3_ACCESS_KEY")?,
+        secret_key: var("AUDIO_S3_SECRET_KEY")?,
+    })
+}
+
+/// Параметры внешнего сервиса синтеза речи.
+#[derive(Debug, Clone)]
+pub struct TtsSettings {
+    pub url: String,
+    /// Передается как `Authorization: Bearer`, если сервис его требует.
+    pub api_key: Option<String>,
+    pub voice: Option<String>,
+}
+
+/// Сервис синтеза речи для иероглифов без записанной озвучки (`TTS_URL`,
+/// необязательные `TTS_API_KEY` и `TTS_VOICE`). Без `TTS_URL` синтез отключен.
+pub fn tts() -> Option<TtsSettings> {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    Some(TtsSettings {
+        url: var("TTS_URL")?,
+        api_key: var("TTS_API_KEY"),
+        voice: var("TTS_VOICE"),
+    })
+}
+
+/// Кривая уровней: переход с уровня `n` на `n + 1` стоит `base * growth^(n - 1)` опыта.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelCurve {
+    pub base: i64,
+    pub growth: f64,
+}
+
+/// Кривая уровней (`XP_LEVEL_BASE`, по умолчанию 100, и `XP_LEVEL_GROWTH`, по умолчанию 1.5).
+pub fn level_curve() -> LevelCurve {
+    let base: i64 = env::var("XP_LEVEL_BASE").ok().and_then(|v| v.parse().ok()).filter(|base| *base > 0).unwrap_or(100);
+    let growth: f64 = env::var("XP_LEVEL_GROWTH").ok().and_then(|v| v.parse().ok()).filter(|growth| *growth >= 1.0).unwrap_or(1.5);
+    LevelCurve { base, growth }
+}
+
+/// Внешний приемник событий обучения.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventSink {
+    /// Файл JSON Lines, в который дописывается по событию на строку.
+    File(PathBuf),
+    /// Адрес, на который каждое событие отправляется POST-запросом.
+    Webhook(String),
+}
+
+/// Выгрузка событий (`EVENT_SINK`): `file:/var/log/mandarin/events.jsonl` или URL webhook.
+/// Без переменной события обрабатываются только внутри сервера.
+pub fn event_sink() -> Option<EventSink> {
+    parse_event_sink(&env::var("EVENT_SINK").ok()?)
+}
+
+pub fn parse_event_sink(value: &str) -> Option<EventSink> {
+    let value = value.trim();
+    if let Some(path) = value.strip_prefix("file:") {
+        return (!path.is_empty()).then(|| EventSink::File(PathBuf::from(path)));
+    }
+    if value.starts_with("http://") || value.starts_with("https://") {
+        return Some(EventSink::Webhook(value.to_string()));
+    }
+    if !value.is_empty() {
+        tracing::warn!("EVENT_SINK не распознан: {}", value);
+    }
+    None
+}
+
+/// Требовать ли изучения компонентов перед составным иероглифом (`ENFORCE_COMPONENTS_FIRST`).
+pub fn components_first_enforced() -> bool {
+    matches!(env::var("ENFORCE_COMPONENTS_FIRST").as_deref(), Ok("1") | Ok("true"))
+}
+
+/// Тест для определения уровня при первом входе (`PLACEMENT_TEST_ID`).
+pub fn placement_test_id() -> Option<i32> {
+    env::var("PLACEMENT_TEST_ID").ok()?.parse().ok()
+}
+
+/// Через сколько секунд без запросов клиента попытка теста с ограничением времени
+/// считается приостановленной (`TEST_IDLE_PAUSE_SECS`, по умолчанию 60). Клиент
+/// шлет heartbeat чаще, так что затишье дольше порога означает потерю связи.
+pub fn test_idle_pause_secs() -> i64 {
+    env::var("TEST_IDLE_PAUSE_SECS").ok().and_then(|v| v.parse().ok()).filter(|s| *s > 0).unwrap_or(60)
+}
+
+/// Требуется ли подтвержденная почта для друзей, публикации результатов
+/// и предложений контента (`REQUIRE_VERIFIED_EMAIL=1`). Тогда почта обязательна и при регистрации.
+pub fn require_verified_email() -> bool {
+    matches!(env::var("REQUIRE_VERIFIED_EMAIL").as_deref(), Ok("1") | Ok("true"))
+}
+
+/// Каталог статической страницы сервера (`WEB_DIR`, по умолчанию `web`).
+pub fn web_dir() -> String {
+    env::var("WEB_DIR").unwrap_or_else(|_| "web".to_string())
+}
+
+/// Ссылка для регистрации на стартовой странице (`REGISTRATION_URL`), например страница загрузки клиента.
+pub fn registration_url() -> Option<String> {
+    env::var("REGISTRATION_URL").ok().filter(|url| !url.is_empty())
+}
+
+/// Открыт ли учебный контент для чтения без авторизации (`PUBLIC_CONTENT`).
+/// Нужен, чтобы сайт-компаньон мог встраивать словарь; данные пользователей остаются закрытыми.
+pub fn public_content() -> bool {
+    matches!(env::var("PUBLIC_CONTENT").as_deref(), Ok("1") | Ok("true"))
+}
+
+/// Время кеширования публичного контента в секундах (`PUBLIC_CACHE_MAX_AGE`, по умолчанию час).
+pub fn public_cache_max_age() -> u64 {
+    env::var("PUBLIC_CACHE_MAX_AGE").ok().and_then(|v| v.parse().ok()).unwrap_or(3600)
+}
+
+/// Конфигурация для десктопного клиента, запрашиваемая им при старте.
+///
+/// Флаги функций по умолчанию выводятся из настроек сервера и могут быть
+/// переопределены через `FEATURE_FLAGS` в виде `имя=on,имя=off`.
+pub fn client_config() -> ClientConfig {
+    let mut features = BTreeMap::from([
+        ("shadowing".to_string(), env::var("ASR_URL").is_ok()),
+        ("dictation".to_string(), true),
+        ("presence".to_string(), true),
+        ("external_dictionary".to_string(), env::var("DICTIONARY_API_URL").is_ok()),
+        ("registration".to_string(), registration_mode() != RegistrationMode::Closed),
+        ("announcements".to_string(), true),
+        ("onboarding".to_string(), true),
+        ("export".to_string(), true),
+        ("media_prefetch".to_string(), true),
+        ("offline_decks".to_string(), true),
+    ]);
+    if let Ok(flags) = env::var("FEATURE_FLAGS") {
+        for flag in flags.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (name, enabled) = match flag.split_once('=') {
+                Some((name, value)) => (name.trim(), matches!(value.trim(), "on" | "true" | "1")),
+                None => (flag, true),
+            };
+            features.insert(name.to_string(), enabled);
+        }
+    }
+
+    ClientConfig {
+        min_client_version: env::var("MIN_CLIENT_VERSION").unwrap_or_else(|_| "0.1.0".to_string()),
+        features,
+        // Пустое значение означает, что медиа раздаются с того же адреса, что и API
+        media_base_url: env::var("MEDIA_BASE_URL").unwrap_or_default(),
+        tts_available: tts().is_some(),
+    }
+}
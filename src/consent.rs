@@ -0,0 +1,161 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::errors::AppError;
+use crate::models::{Claims, ConsentStatus, LegalDocument, LegalDocumentKind, PublishLegalDocumentPayload};
+use crate::AppState;
+
+/// Самая длинная допустимая строка версии.
+const MAX_VERSION_LENGTH: usize = 64;
+
+/// Адреса, доступные без принятия условий: без них нельзя прочитать и принять
+/// документы, выйти, управлять сессиями или выгрузить свои данные.
+const EXEMPT_PATHS: &[&str] = &[
+    "/api/consent",
+    "/api/legal",
+    "/api/login",
+    "/api/register",
+    "/api/refresh",
+    "/api/logout",
+    "/api/password/",
+    "/api/verify-email",
+    "/api/me",
+    "/api/sessions",
+    "/api/status",
+    "/api/client-config",
+    "/healthz",
+    "/readyz",
+    "/.well-known/jwks.json",
+];
+
+impl LegalDocumentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LegalDocumentKind::Terms => "terms",
+            LegalDocumentKind::Privacy => "privacy",
+        }
+    }
+}
+
+/// Не требует ли адрес принятых условий. `/api/me` покрывает и `/api/me/export`.
+pub fn is_exempt(path: &str) -> bool {
+    EXEMPT_PATHS.iter().any(|exempt| {
+        path.strip_prefix(exempt)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || exempt.ends_with('/'))
+    })
+}
+
+/// Действующие версии документов: последняя опубликованная каждого вида.
+pub async fn current_documents(pool: &PgPool) -> Result<Vec<LegalDocument>, AppError> {
+    let documents = sqlx::query_as::<_, LegalDocument>(
+        "SELECT DISTINCT ON (kind) id, kind, version, content, published_at FROM legal_documents
+         ORDER BY kind, published_at DESC, id DESC",
+    )
+        .fetch_all(pool)
+        .await?;
+    Ok(documents)
+}
+
+/// Какие действующие документы пользователь еще не принял.
+pub async fn status(user_id: i32, pool: &PgPool) -> Result<ConsentStatus, AppError> {
+    let documents = current_documents(pool).await?;
+    let ids: Vec<i32> = documents.iter().map(|document| document.id).collect();
+    let accepted: Vec<i32> = sqlx::query_scalar(
+        "SELECT document_id FROM user_consents WHERE user_id = $1 AND document_id = ANY($2)",
+    )
+        .bind(user_id)
+        .bind(&ids)
+        .fetch_all(pool)
+        .await?;
+    let pending = ids.into_iter().filter(|id| !accepted.contains(id)).collect();
+    Ok(ConsentStatus { documents, pending })
+}
+
+/// Есть ли действующие документы, которые пользователь еще не принял.
+pub async fn has_pending(user_id: i32, pool: &PgPool) -> Result<bool, AppError> {
+    let pending: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM (
+                 SELECT DISTINCT ON (kind) id FROM legal_documents ORDER BY kind, published_at DESC, id DESC
+             ) d
+             WHERE NOT EXISTS (SELECT 1 FROM user_consents c WHERE c.user_id = $1 AND c.document_id = d.id)
+         )",
+    )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(pending)
+}
+
+/// Записывает принятие документов. Принять можно только действующие версии:
+/// если пока пользователь читал, вышла новая, он получит `consent_outdated`.
+pub async fn accept(user_id: i32, document_ids: &[i32], ip: Option<IpAddr>, pool: &PgPool) -> Result<ConsentStatus, AppError> {
+    let current = current_documents(pool).await?;
+    if document_ids.iter().any(|id| !current.iter().any(|document| document.id == *id)) {
+        return Err(AppError::new(StatusCode::CONFLICT, "Документ устарел или не найден, обновите страницу")
+            .with_code("consent_outdated"));
+    }
+
+    sqlx::query(
+        "INSERT INTO user_consents (user_id, document_id, ip)
+         SELECT $1, UNNEST($2::int[]), $3
+         ON CONFLICT (user_id, document_id) DO NOTHING",
+    )
+        .bind(user_id)
+        .bind(document_ids)
+        .bind(ip.map(|ip| ip.to_string()))
+        .execute(pool)
+        .await?;
+    status(user_id, pool).await
+}
+
+/// Публикует новую версию документа. С этого момента пользователи, не принявшие
+/// ее, не могут пользоваться API, пока не примут.
+pub async fn publish(payload: &PublishLegalDocumentPayload, pool: &PgPool) -> Result<LegalDocument, AppError> {
+    let version = payload.version.trim();
+    if version.is_empty() || version.chars().count() > MAX_VERSION_LENGTH {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Версия должна быть непустой и не длиннее {} символов", MAX_VERSION_LENGTH),
+        ));
+    }
+    if payload.content.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Текст документа не может быть пустым"));
+    }
+
+    sqlx::query_as::<_, LegalDocument>(
+        "INSERT INTO legal_documents (kind, version, content) VALUES ($1, $2, $3)
+         ON CONFLICT (kind, version) DO NOTHING
+         RETURNING id, kind, version, content, published_at",
+    )
+        .bind(payload.kind.as_str())
+        .bind(version)
+        .bind(&payload.content)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::CONFLICT, "Такая версия документа уже опубликована"))
+}
+
+/// Middleware всего API: пока пользователь не принял действующие версии
+/// документов, запросы с его токеном отклоняются с кодом `consent_required`.
+/// Запросы без токена проходят дальше, их отклонит сам обработчик.
+pub async fn enforce(
+    State(state): State<AppState>,
+    claims: Option<Claims>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(claims) = claims.filter(|_| !is_exempt(request.uri().path())) {
+        if has_pending(claims.user_id, &state.db_pool).await? {
+            return Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "Обновились условия использования или политика конфиденциальности: примите их, чтобы продолжить",
+            ).with_code("consent_required"));
+        }
+    }
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,61 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::ContentType;
+
+/// Таблица, в которой хранится контент данного типа.
+/// `None` для типов, у которых нет собственной таблицы.
+pub fn table_for(content_type: &ContentType) -> Option<&'static str> {
+    match content_type {
+        ContentType::Hieroglyph => Some("hieroglyphs"),
+        ContentType::Word => Some("words"),
+        ContentType::Phrase => Some("phrases"),
+        ContentType::Sentence => Some("sentences"),
+        ContentType::GrammarRule => Some("grammar_rules"),
+        ContentType::Lesson => Some("lessons"),
+    }
+}
+
+/// Проверяет, что элемент контента существует, чтобы прогресс не ссылался в пустоту.
+pub async fn ensure_exists(content_type: &ContentType, id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let Some(table) = table_for(content_type) else {
+        return Ok(());
+    };
+
+    let exists: bool = sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)", table))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Элемент контента не найден"));
+    }
+    Ok(())
+}
+
+/// Удаляет элемент контента вместе с прогрессом пользователей и записями в колодах и уроках.
+/// У этих таблиц нет внешнего ключа на контент, поэтому чистим их вручную.
+pub async fn delete(content_type: ContentType, id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let table = table_for(&content_type)
+        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Этот тип контента нельзя удалить"))?;
+
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(&format!("DELETE FROM {} WHERE id = $1", table))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Элемент контента не найден"));
+    }
+
+    for dependent in ["user_progress", "deck_items", "lesson_items", "review_log", "hidden_items"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE content_type = $1 AND content_id = $2", dependent))
+            .bind(&content_type)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use axum::http::StatusCode;
+use sqlx::PgConnection;
+
+use crate::errors::AppError;
+use crate::models::{Contribution, ContributionPayload};
+
+/// Принимает предложение: создает контент с указанием автора и помечает предложение принятым.
+/// Выполняется в транзакции вызывающего кода.
+pub async fn accept(id: i32, reviewer_id: i32, conn: &mut PgConnection) -> Result<Contribution, AppError> {
+    let contribution = sqlx::query_as::<_, Contribution>(
+        "SELECT * FROM contributions WHERE id = $1 AND status = 'pending' FOR UPDATE",
+    )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено или уже рассмотрено"))?;
+
+    let payload: ContributionPayload = serde_json::from_value(contribution.payload.clone())
+        .map_err(|_| AppError::new(StatusCode::UNPROCESSABLE_ENTITY, "Некорректное предложение"))?;
+
+    let content_id: i32 = match payload {
+        ContributionPayload::Word { simplified, pinyin, translation } => {
+            sqlx::query_scalar(
+                "INSERT INTO words (simplified, pinyin, translation, contributor_id) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (simplified) DO NOTHING RETURNING id",
+            )
+                .bind(simplified)
+                .bind(pinyin)
+                .bind(translation)
+                .bind(contribution.user_id)
+                .fetch_optional(&mut *conn)
+                .await?
+                .ok_or_else(|| AppError::new(StatusCode::CONFLICT, "Такое слово уже есть"))?
+        }
+        ContributionPayload::Sentence { text, pinyin, translation } => {
+            sqlx::query_scalar(
+                "INSERT INTO sentences (text, pinyin, translation, contributor_id) VALUES ($1, $2, $3, $4) RETURNING id",
+            )
+                .bind(text)
+                .bind(pinyin)
+                .bind(translation)
+                .bind(contribution.user_id)
+                .fetch_one(&mut *conn)
+                .await?
+        }
+    };
+
+    let contribution = sqlx::query_as::<_, Contribution>(
+        "UPDATE contributions SET status = 'accepted', reviewed_by = $1, reviewed_at = NOW(), content_id = $2
+         WHERE id = $3 RETURNING *",
+    )
+        .bind(reviewer_id)
+        .bind(content_id)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(contribution)
+}
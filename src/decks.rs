@@ -0,0 +1,95 @@
+use axum::http::StatusCode;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::classes::DECK_VISIBILITY_CONDITION;
+use crate::errors::AppError;
+use crate::models::{Deck, DeckItem, DeckManifest, MediaRef};
+
+/// Максимум элементов колоды в одной порции загрузки.
+pub const MAX_BATCH_SIZE: i64 = 200;
+
+// Элементы колоды с подставленным содержимым в порядке изучения
+const DECK_ITEMS_SELECT: &str = "
+    SELECT di.position, di.content_type, di.content_id,
+           COALESCE(h.character, w.simplified, p.text, s.text, g.pattern) AS text,
+           COALESCE(h.pinyin, w.pinyin, p.pinyin, s.pinyin) AS pinyin,
+           COALESCE(h.translation, w.translation, p.translation, s.translation, g.title) AS translation,
+           CASE WHEN s.audio_media_id IS NULL THEN NULL ELSE '/api/media/' || s.audio_media_id END AS audio_url
+    FROM deck_items di
+    LEFT JOIN hieroglyphs h ON di.content_type = 'hieroglyph' AND h.id = di.content_id
+    LEFT JOIN words w ON di.content_type = 'word' AND w.id = di.content_id
+    LEFT JOIN phrases p ON di.content_type = 'phrase' AND p.id = di.content_id
+    LEFT JOIN sentences s ON di.content_type = 'sentence' AND s.id = di.content_id
+    LEFT JOIN grammar_rules g ON di.content_type = 'grammar_rule' AND g.id = di.content_id
+    WHERE di.deck_id = $1 AND COALESCE(h.id, w.id, p.id, s.id, g.id) IS NOT NULL
+    ORDER BY di.position, di.content_type, di.content_id";
+
+/// Колоды, видимые пользователю, с числом элементов.
+pub async fn list(user_id: Option<i32>, pool: &PgPool) -> Result<Vec<Deck>, AppError> {
+    let decks = sqlx::query_as::<_, Deck>(&format!(
+        "SELECT d.id, d.name, d.description,
+                (SELECT COUNT(*) FROM deck_items di WHERE di.deck_id = d.id) AS item_count
+         FROM decks d WHERE {} ORDER BY d.name",
+        DECK_VISIBILITY_CONDITION
+    ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(decks)
+}
+
+/// Порция элементов колоды для постраничной загрузки.
+pub async fn items(deck_id: i32, offset: i64, limit: i64, pool: &PgPool) -> Result<Vec<DeckItem>, AppError> {
+    let items = sqlx::query_as::<_, DeckItem>(&format!("{} OFFSET $2 LIMIT $3", DECK_ITEMS_SELECT))
+        .bind(deck_id)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
+/// Контрольная сумма содержимого колоды. Клиент считает ее той же функцией
+/// по скачанным элементам, чтобы убедиться, что загрузка полная и не устарела.
+pub fn checksum(items: &[DeckItem]) -> String {
+    let mut hasher = Sha256::new();
+    for item in items {
+        hasher.update(serde_json::to_vec(item).unwrap_or_default());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Описание колоды для офлайн-загрузки: число элементов, медиафайлы и контрольная сумма.
+pub async fn manifest(deck_id: i32, pool: &PgPool) -> Result<DeckManifest, AppError> {
+    let name: String = sqlx::query_scalar("SELECT name FROM decks WHERE id = $1")
+        .bind(deck_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Колода не найдена"))?;
+
+    let items = sqlx::query_as::<_, DeckItem>(DECK_ITEMS_SELECT)
+        .bind(deck_id)
+        .fetch_all(pool)
+        .await?;
+
+    let media = sqlx::query_as::<_, MediaRef>(
+        "SELECT DISTINCT '/api/media/' || m.id AS url, m.size_bytes
+         FROM deck_items di
+         JOIN sentences s ON di.content_type = 'sentence' AND s.id = di.content_id
+         JOIN media m ON m.id = s.audio_media_id
+         WHERE di.deck_id = $1",
+    )
+        .bind(deck_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(DeckManifest {
+        deck_id,
+        name,
+        item_count: items.len() as i64,
+        checksum: checksum(&items),
+        media,
+    })
+}
@@ -0,0 +1,101 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::{ContentType, Hieroglyph};
+
+/// Ограничение глубины обхода графа разложения.
+const MAX_DEPTH: i32 = 10;
+
+/// Задает компоненты иероглифа (в порядке написания), заменяя прежние.
+/// Компоненты должны уже существовать как иероглифы; циклы не допускаются.
+pub async fn set_components(hieroglyph_id: i32, components: &[String], pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let mut component_ids = Vec::with_capacity(components.len());
+    for character in components {
+        let id: Option<i32> = sqlx::query_scalar("SELECT id FROM hieroglyphs WHERE character = $1")
+            .bind(character)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let id = id.ok_or_else(|| {
+            AppError::new(StatusCode::BAD_REQUEST, &format!("Компонент {} не найден", character))
+        })?;
+
+        // Иероглиф не может быть компонентом самого себя, в том числе через другие компоненты
+        let creates_cycle: bool = sqlx::query_scalar(
+            "WITH RECURSIVE descendants AS (
+                 SELECT $1::INTEGER AS id, 0 AS depth
+                 UNION
+                 SELECT hc.component_id, d.depth + 1 FROM hieroglyph_components hc
+                 JOIN descendants d ON hc.hieroglyph_id = d.id
+                 WHERE d.depth < $3
+             )
+             SELECT EXISTS (SELECT 1 FROM descendants WHERE id = $2)",
+        )
+            .bind(id)
+            .bind(hieroglyph_id)
+            .bind(MAX_DEPTH)
+            .fetch_one(&mut *tx)
+            .await?;
+        if creates_cycle {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Компонент {} образует цикл в разложении", character),
+            ));
+        }
+
+        component_ids.push(id);
+    }
+
+    sqlx::query("DELETE FROM hieroglyph_components WHERE hieroglyph_id = $1")
+        .bind(hieroglyph_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (position, component_id) in component_ids.into_iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO hieroglyph_components (hieroglyph_id, component_id, position) VALUES ($1, $2, $3)
+             ON CONFLICT DO NOTHING",
+        )
+            .bind(hieroglyph_id)
+            .bind(component_id)
+            .bind(position as i16)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Невыученные компоненты иероглифа (включая компоненты компонентов)
+/// в порядке изучения: сначала самые глубокие.
+pub async fn unknown_prerequisites(user_id: i32, hieroglyph_id: i32, pool: &PgPool) -> Result<Vec<Hieroglyph>, AppError> {
+    let prerequisites = sqlx::query_as::<_, Hieroglyph>(
+        "WITH RECURSIVE tree AS (
+             SELECT component_id, 1 AS depth FROM hieroglyph_components WHERE hieroglyph_id = $1
+             UNION
+             SELECT hc.component_id, t.depth + 1 FROM hieroglyph_components hc
+             JOIN tree t ON hc.hieroglyph_id = t.component_id
+             WHERE t.depth < $4
+         )
+         SELECT h.* FROM (
+             SELECT component_id, MAX(depth) AS depth FROM tree GROUP BY component_id
+         ) d
+         JOIN hieroglyphs h ON h.id = d.component_id
+         WHERE NOT EXISTS (
+             SELECT 1 FROM user_progress up
+             WHERE up.user_id = $2 AND up.content_type = $3 AND up.content_id = h.id AND up.is_learned
+         )
+         ORDER BY d.depth DESC, h.id",
+    )
+        .bind(hieroglyph_id)
+        .bind(user_id)
+        .bind(ContentType::Hieroglyph)
+        .bind(MAX_DEPTH)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(prerequisites)
+}
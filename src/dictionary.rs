@@ -0,0 +1,86 @@
+use reqwest::Client;
+use sqlx::PgPool;
+use std::env;
+use std::time::Duration;
+
+use crate::errors::AppError;
+use crate::models::{LookupEntry, LookupResponse, LookupSource};
+
+/// Таймаут запроса к внешнему словарю: поиск не должен подвисать из-за чужого сервиса.
+const EXTERNAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ищет слово в локальном словаре (иероглифы и слова).
+async fn lookup_local(query: &str, pool: &PgPool) -> Result<Vec<LookupEntry>, AppError> {
+    let entries = sqlx::query_as::<_, LookupEntry>(
+        "SELECT character AS text, pinyin, translation FROM hieroglyphs WHERE character = $1
+         UNION ALL
+         SELECT simplified AS text, pinyin, translation FROM words WHERE simplified = $1",
+    )
+        .bind(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Запрашивает внешний словарь (`DICTIONARY_API_URL`), если он настроен.
+/// Сервис должен отвечать на `GET {url}?q=...` массивом `[{"text","pinyin","translation"}]`.
+async fn lookup_external(query: &str) -> Option<Vec<LookupEntry>> {
+    let url = env::var("DICTIONARY_API_URL").ok()?;
+
+    let response = Client::new()
+        .get(url)
+        .query(&[("q", query)])
+        .timeout(EXTERNAL_TIMEOUT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+
+    match response {
+        Ok(response) => match response.json::<Vec<LookupEntry>>().await {
+            Ok(entries) => Some(entries),
+            Err(e) => {
+                tracing::warn!("Некорректный ответ внешнего словаря: {:?}", e);
+                None
+            }
+        },
+        Err(e) => {
+            tracing::warn!("Внешний словарь недоступен: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Поиск слова: сначала локальный словарь, затем кеш, затем внешний сервис.
+/// Ответы внешнего сервиса сохраняются в кеш.
+pub async fn lookup(query: &str, pool: &PgPool) -> Result<LookupResponse, AppError> {
+    let local = lookup_local(query, pool).await?;
+    if !local.is_empty() {
+        return Ok(LookupResponse { query: query.to_string(), source: LookupSource::Local, entries: local });
+    }
+
+    let cached: Option<sqlx::types::Json<Vec<LookupEntry>>> =
+        sqlx::query_scalar("SELECT entries FROM dictionary_cache WHERE query = $1")
+            .bind(query)
+            .fetch_optional(pool)
+            .await?;
+    if let Some(sqlx::types::Json(entries)) = cached {
+        return Ok(LookupResponse { query: query.to_string(), source: LookupSource::Cache, entries });
+    }
+
+    let Some(entries) = lookup_external(query).await else {
+        return Ok(LookupResponse { query: query.to_string(), source: LookupSource::Local, entries: Vec::new() });
+    };
+
+    // Пустые ответы тоже кешируются, чтобы не дергать сервис повторно
+    sqlx::query(
+        "INSERT INTO dictionary_cache (query, entries) VALUES ($1, $2)
+         ON CONFLICT (query) DO UPDATE SET entries = EXCLUDED.entries, fetched_at = NOW()",
+    )
+        .bind(query)
+        .bind(sqlx::types::Json(&entries))
+        .execute(pool)
+        .await?;
+
+    Ok(LookupResponse { query: query.to_string(), source: LookupSource::External, entries })
+}
@@ -0,0 +1,132 @@
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+
+use crate::errors::AppError;
+use crate::matching::normalize_hanzi;
+use crate::models::{ContentType, GradedToken, SentenceGrade};
+
+/// Максимальная длина слова в символах при сегментации.
+const MAX_WORD_LEN: usize = 4;
+
+/// Доля слов, которую должен покрывать уровень HSK, чтобы считаться уровнем предложения.
+const HSK_COVERAGE: f32 = 0.9;
+
+/// Слово словаря, найденное в предложении.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DictionaryWord {
+    pub id: i32,
+    pub simplified: String,
+    pub hsk_level: Option<i16>,
+}
+
+/// Все подстроки текста длиной до `MAX_WORD_LEN` — кандидаты для поиска в словаре.
+pub fn candidates(chars: &[char]) -> Vec<String> {
+    let mut result = HashSet::new();
+    for start in 0..chars.len() {
+        for len in 2..=MAX_WORD_LEN.min(chars.len() - start) {
+            result.insert(chars[start..start + len].iter().collect::<String>());
+        }
+        result.insert(chars[start].to_string());
+    }
+    result.into_iter().collect()
+}
+
+/// Делит текст на слова методом прямого максимального соответствия.
+/// Символы, которых нет в словаре, становятся отдельными токенами.
+pub fn segment(text: &str, dictionary: &HashSet<String>) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let len = (2..=MAX_WORD_LEN.min(chars.len() - start))
+            .rev()
+            .find(|&len| dictionary.contains(&chars[start..start + len].iter().collect::<String>()))
+            .unwrap_or(1);
+        tokens.push(chars[start..start + len].iter().collect());
+        start += len;
+    }
+
+    tokens
+}
+
+/// Оценивает уровень HSK: минимальный уровень, покрывающий `HSK_COVERAGE` слов.
+/// Слова без уровня считаются сложнее любого уровня.
+pub fn estimate_hsk_level(levels: &[Option<i16>]) -> Option<i16> {
+    if levels.is_empty() {
+        return None;
+    }
+    let mut known: Vec<i16> = levels.iter().flatten().copied().collect();
+    known.sort_unstable();
+
+    let needed = (levels.len() as f32 * HSK_COVERAGE).ceil() as usize;
+    known.get(needed.max(1) - 1).copied()
+}
+
+/// Слова словаря, которые встречаются среди подстрок текста.
+pub async fn dictionary_words(chars: &[char], pool: &PgPool) -> Result<HashMap<String, DictionaryWord>, AppError> {
+    let words = sqlx::query_as::<_, DictionaryWord>(
+        "SELECT id, simplified, hsk_level FROM words WHERE simplified = ANY($1)",
+    )
+        .bind(candidates(chars))
+        .fetch_all(pool)
+        .await?;
+    Ok(words.into_iter().map(|w| (w.simplified.clone(), w)).collect())
+}
+
+/// Выученные слова и иероглифы пользователя
+/// (односимвольное слово считается известным, если выучен иероглиф).
+pub async fn known_words(user_id: i32, pool: &PgPool) -> Result<HashSet<String>, AppError> {
+    let known: Vec<String> = sqlx::query_scalar(
+        "SELECT w.simplified FROM user_progress up
+         JOIN words w ON w.id = up.content_id
+         WHERE up.user_id = $1 AND up.content_type = $2 AND up.is_learned
+         UNION
+         SELECT h.character FROM user_progress up
+         JOIN hieroglyphs h ON h.id = up.content_id
+         WHERE up.user_id = $1 AND up.content_type = $3 AND up.is_learned",
+    )
+        .bind(user_id)
+        .bind(ContentType::Word)
+        .bind(ContentType::Hieroglyph)
+        .fetch_all(pool)
+        .await?;
+    Ok(known.into_iter().collect())
+}
+
+/// Оценивает сложность предложения для пользователя.
+pub async fn grade(user_id: i32, text: &str, pool: &PgPool) -> Result<SentenceGrade, AppError> {
+    let normalized = normalize_hanzi(text);
+    let chars: Vec<char> = normalized.chars().collect();
+
+    let words = dictionary_words(&chars, pool).await?;
+    let dictionary: HashSet<String> = words.keys().cloned().collect();
+    let known_words = known_words(user_id, pool).await?;
+
+    let tokens: Vec<GradedToken> = segment(&normalized, &dictionary)
+        .into_iter()
+        .map(|token| {
+            let word = words.get(&token);
+            GradedToken {
+                known: known_words.contains(&token),
+                word_id: word.map(|w| w.id),
+                hsk_level: word.and_then(|w| w.hsk_level),
+                text: token,
+            }
+        })
+        .collect();
+
+    let known_count = tokens.iter().filter(|t| t.known).count();
+    let known_percent = if tokens.is_empty() {
+        0.0
+    } else {
+        known_count as f32 / tokens.len() as f32 * 100.0
+    };
+    let levels: Vec<Option<i16>> = tokens.iter().map(|t| t.hsk_level).collect();
+
+    Ok(SentenceGrade {
+        hsk_level: estimate_hsk_level(&levels),
+        known_percent,
+        tokens,
+    })
+}
@@ -0,0 +1,267 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::classes;
+use crate::errors::AppError;
+use crate::models::{Claims, LessonNote, LessonPost, LessonPostPayload};
+use crate::notifications::{self, NotificationKind};
+
+/// Максимальная длина заметки или сообщения.
+pub const MAX_BODY_LEN: usize = 4000;
+
+/// Участник обсуждения: учитель класса (или админ) либо ученик.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Participant {
+    Teacher,
+    Student,
+}
+
+/// Проверяет доступ к обсуждению урока в классе и что урок существует.
+pub async fn participant(class_id: i32, lesson_id: i32, claims: &Claims, pool: &PgPool) -> Result<Participant, AppError> {
+    let lesson_exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM lessons WHERE id = $1)")
+        .bind(lesson_id)
+        .fetch_one(pool)
+        .await?;
+    if !lesson_exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Урок не найден"));
+    }
+
+    let is_member: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM class_members WHERE class_id = $1 AND user_id = $2)",
+    )
+        .bind(class_id)
+        .bind(claims.user_id)
+        .fetch_one(pool)
+        .await?;
+    if is_member {
+        return Ok(Participant::Student);
+    }
+
+    classes::ensure_teacher(class_id, claims, pool).await?;
+    Ok(Participant::Teacher)
+}
+
+/// Проверяет текст заметки или сообщения и возвращает его без лишних пробелов.
+pub fn validate_body(body: &str) -> Result<&str, AppError> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Текст не может быть пустым"));
+    }
+    if body.chars().count() > MAX_BODY_LEN {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Текст длиннее {} символов", MAX_BODY_LEN),
+        ));
+    }
+    Ok(body)
+}
+
+/// Начало текста для уведомления.
+fn preview(body: &str) -> String {
+    const PREVIEW_LEN: usize = 140;
+    if body.chars().count() <= PREVIEW_LEN {
+        body.to_string()
+    } else {
+        format!("{}…", body.chars().take(PREVIEW_LEN).collect::<String>())
+    }
+}
+
+async fn lesson_title(lesson_id: i32, pool: &PgPool) -> Result<String, AppError> {
+    let title = sqlx::query_scalar("SELECT title FROM lessons WHERE id = $1")
+        .bind(lesson_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(title)
+}
+
+/// Заметки учителя к уроку, от старых к новым.
+pub async fn notes(class_id: i32, lesson_id: i32, pool: &PgPool) -> Result<Vec<LessonNote>, AppError> {
+    let notes = sqlx::query_as::<_, LessonNote>(
+        "SELECT n.id, n.class_id, n.lesson_id, n.author_id, u.nickname AS author_nickname, n.body, n.created_at
+         FROM lesson_notes n LEFT JOIN users u ON u.id = n.author_id
+         WHERE n.class_id = $1 AND n.lesson_id = $2
+         ORDER BY n.created_at, n.id",
+    )
+        .bind(class_id)
+        .bind(lesson_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(notes)
+}
+
+/// Добавляет заметку к уроку и уведомляет учеников класса.
+pub async fn add_note(
+    class_id: i32,
+    lesson_id: i32,
+    author_id: i32,
+    body: &str,
+    pool: &PgPool,
+) -> Result<LessonNote, AppError> {
+    let body = validate_body(body)?;
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO lesson_notes (class_id, lesson_id, author_id, body) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+        .bind(class_id)
+        .bind(lesson_id)
+        .bind(author_id)
+        .bind(body)
+        .fetch_one(pool)
+        .await?;
+
+    let title = format!("Заметка к уроку «{}»", lesson_title(lesson_id, pool).await?);
+    let members: Vec<i32> = sqlx::query_scalar("SELECT user_id FROM class_members WHERE class_id = $1")
+        .bind(class_id)
+        .fetch_all(pool)
+        .await?;
+    for user_id in members {
+        notifications::notify(user_id, NotificationKind::LessonNote, &title, &preview(body), pool).await?;
+    }
+
+    let note = sqlx::query_as::<_, LessonNote>(
+        "SELECT n.id, n.class_id, n.lesson_id, n.author_id, u.nickname AS author_nickname, n.body, n.created_at
+         FROM lesson_notes n LEFT JOIN users u ON u.id = n.author_id
+         WHERE n.id = $1",
+    )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    Ok(note)
+}
+
+/// Удаляет заметку урока.
+pub async fn delete_note(class_id: i32, lesson_id: i32, note_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let result = sqlx::query("DELETE FROM lesson_notes WHERE id = $1 AND class_id = $2 AND lesson_id = $3")
+        .bind(note_id)
+        .bind(class_id)
+        .bind(lesson_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Заметка не найдена"));
+    }
+    Ok(())
+}
+
+const POST_COLUMNS: &str =
+    "p.id, p.class_id, p.lesson_id, p.parent_id, p.author_id, u.nickname AS author_nickname, p.body, p.created_at";
+
+/// Обсуждение урока в классе, от старых сообщений к новым; ответы ссылаются на вопрос через `parent_id`.
+pub async fn thread(class_id: i32, lesson_id: i32, pool: &PgPool) -> Result<Vec<LessonPost>, AppError> {
+    let posts = sqlx::query_as::<_, LessonPost>(&format!(
+        "SELECT {} FROM lesson_posts p LEFT JOIN users u ON u.id = p.author_id
+         WHERE p.class_id = $1 AND p.lesson_id = $2
+         ORDER BY p.created_at, p.id",
+        POST_COLUMNS
+    ))
+        .bind(class_id)
+        .bind(lesson_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(posts)
+}
+
+/// Добавляет вопрос или ответ в обсуждение урока.
+///
+/// Вопрос ученика уведомляет учителя класса. Ответ уведомляет автора вопроса
+/// и, если отвечает не учитель, самого учителя.
+pub async fn add_post(
+    class_id: i32,
+    lesson_id: i32,
+    author_id: i32,
+    payload: &LessonPostPayload,
+    pool: &PgPool,
+) -> Result<LessonPost, AppError> {
+    let body = validate_body(&payload.body)?;
+
+    // Ответ привязывается к вопросу того же обсуждения; ответы на ответы не вкладываются
+    let question_author = match payload.parent_id {
+        Some(parent_id) => {
+            let author: Option<Option<i32>> = sqlx::query_scalar(
+                "SELECT author_id FROM lesson_posts
+                 WHERE id = $1 AND class_id = $2 AND lesson_id = $3 AND parent_id IS NULL",
+            )
+                .bind(parent_id)
+                .bind(class_id)
+                .bind(lesson_id)
+                .fetch_optional(pool)
+                .await?;
+            Some(author.ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Вопрос не найден"))?)
+        }
+        None => None,
+    };
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO lesson_posts (class_id, lesson_id, parent_id, author_id, body)
+         VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+        .bind(class_id)
+        .bind(lesson_id)
+        .bind(payload.parent_id)
+        .bind(author_id)
+        .bind(body)
+        .fetch_one(pool)
+        .await?;
+
+    let lesson = lesson_title(lesson_id, pool).await?;
+    let teacher_id: i32 = sqlx::query_scalar("SELECT teacher_id FROM classes WHERE id = $1")
+        .bind(class_id)
+        .fetch_one(pool)
+        .await?;
+
+    match question_author {
+        None => {
+            if teacher_id != author_id {
+                let title = format!("Вопрос по уроку «{}»", lesson);
+                notifications::notify(teacher_id, NotificationKind::LessonQuestion, &title, &preview(body), pool).await?;
+            }
+        }
+        Some(question_author) => {
+            let title = format!("Ответ в обсуждении урока «{}»", lesson);
+            if let Some(question_author) = question_author.filter(|id| *id != author_id) {
+                notifications::notify(question_author, NotificationKind::LessonReply, &title, &preview(body), pool).await?;
+            }
+            if teacher_id != author_id && question_author != Some(teacher_id) {
+                notifications::notify(teacher_id, NotificationKind::LessonReply, &title, &preview(body), pool).await?;
+            }
+        }
+    }
+
+    let post = sqlx::query_as::<_, LessonPost>(&format!(
+        "SELECT {} FROM lesson_posts p LEFT JOIN users u ON u.id = p.author_id WHERE p.id = $1",
+        POST_COLUMNS
+    ))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    Ok(post)
+}
+
+/// Удаляет сообщение: автор может удалить свое, учитель — любое.
+pub async fn delete_post(
+    class_id: i32,
+    lesson_id: i32,
+    post_id: i32,
+    user_id: i32,
+    participant: Participant,
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    let author_id: Option<i32> = sqlx::query_scalar(
+        "SELECT author_id FROM lesson_posts WHERE id = $1 AND class_id = $2 AND lesson_id = $3",
+    )
+        .bind(post_id)
+        .bind(class_id)
+        .bind(lesson_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Сообщение не найдено"))?;
+
+    if participant != Participant::Teacher && author_id != Some(user_id) {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
+    }
+
+    sqlx::query("DELETE FROM lesson_posts WHERE id = $1")
+        .bind(post_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use rand::seq::SliceRandom;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::ContentType;
+
+/// Сколько кандидатов выбирается из базы перед ранжированием.
+const CANDIDATE_POOL: i64 = 40;
+
+/// Неверный вариант ответа и признаки, по которым он похож на правильный.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Candidate {
+    /// Текст варианта: перевод или иероглиф, в зависимости от вопроса.
+    pub text: String,
+    /// Пиньинь без тонов и пробелов, как в `matching::normalize_pinyin`.
+    pub pinyin_plain: String,
+    pub radical_index: Option<i16>,
+    pub hsk_level: Option<i16>,
+}
+
+/// Столбцы таблицы, из которых строятся кандидаты. У слов, фраз и предложений
+/// нет ключа, а у предложений — уровня HSK и сохраненного пиньиня без тонов.
+struct Source {
+    table: &'static str,
+    pinyin_plain: &'static str,
+    radical_index: &'static str,
+    hsk_level: &'static str,
+}
+
+fn source_for(content_type: &ContentType) -> Option<Source> {
+    match content_type {
+        ContentType::Hieroglyph => Some(Source {
+            table: "hieroglyphs",
+            pinyin_plain: "pinyin_plain",
+            radical_index: "radical_index",
+            hsk_level: "hsk_level",
+        }),
+        ContentType::Word => Some(Source {
+            table: "words",
+            pinyin_plain: "pinyin_plain",
+            radical_index: "NULL::smallint",
+            hsk_level: "hsk_level",
+        }),
+        ContentType::Phrase => Some(Source {
+            table: "phrases",
+            pinyin_plain: "pinyin_plain",
+            radical_index: "NULL::smallint",
+            hsk_level: "hsk_level",
+        }),
+        ContentType::Sentence => Some(Source {
+            table: "sentences",
+            pinyin_plain: "plain_pinyin(pinyin)",
+            radical_index: "NULL::smallint",
+            hsk_level: "NULL::smallint",
+        }),
+        ContentType::GrammarRule | ContentType::Lesson => None,
+    }
+}
+
+/// Расстояние Левенштейна по символам.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Насколько похоже звучание: омофоны, отличие в одну букву, общее начало.
+pub fn pinyin_similarity(a: &str, b: &str) -> u32 {
+    if a.is_empty() || b.is_empty() {
+        return 0;
+    }
+    if a == b {
+        return 3;
+    }
+    if edit_distance(a, b) <= 1 {
+        return 2;
+    }
+    let common_prefix = a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+    u32::from(common_prefix >= 2)
+}
+
+/// Правдоподобие неверного варианта: чем выше, тем легче его спутать с правильным.
+pub fn plausibility(target: &Candidate, candidate: &Candidate) -> u32 {
+    let radical = match (target.radical_index, candidate.radical_index) {
+        (Some(a), Some(b)) if a == b => 3,
+        _ => 0,
+    };
+    let level = match (target.hsk_level, candidate.hsk_level) {
+        (Some(a), Some(b)) if a == b => 2,
+        (Some(a), Some(b)) if (a - b).abs() == 1 => 1,
+        _ => 0,
+    };
+    radical + level + pinyin_similarity(&target.pinyin_plain, &candidate.pinyin_plain)
+}
+
+/// Выбирает `count` самых правдоподобных вариантов. Варианты, совпадающие
+/// с правильным ответом или друг с другом, отбрасываются; среди равных
+/// по правдоподобию выбор случайный.
+pub fn pick(target: &Candidate, mut candidates: Vec<Candidate>, count: usize) -> Vec<String> {
+    candidates.shuffle(&mut rand::thread_rng());
+    // Сортировка устойчивая, поэтому перемешивание решает судьбу равных
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(plausibility(target, candidate)));
+
+    let mut picked: Vec<String> = Vec::with_capacity(count);
+    for candidate in candidates {
+        if picked.len() == count {
+            break;
+        }
+        if candidate.text != target.text && !picked.contains(&candidate.text) {
+            picked.push(candidate.text);
+        }
+    }
+    picked
+}
+
+/// Неверные переводы для вопроса «что означает ...» по элементу контента.
+pub async fn translations(
+    content_type: &ContentType,
+    content_id: i32,
+    count: usize,
+    pool: &PgPool,
+) -> Result<Vec<String>, AppError> {
+    let Some(source) = source_for(content_type) else {
+        return Ok(Vec::new());
+    };
+    let columns = format!(
+        "translation AS text, COALESCE({}, '') AS pinyin_plain, {} AS radical_index, {} AS hsk_level",
+        source.pinyin_plain, source.radical_index, source.hsk_level
+    );
+
+    let Some(target) = sqlx::query_as::<_, Candidate>(&format!(
+        "SELECT {} FROM {} WHERE id = $1",
+        columns, source.table
+    ))
+        .bind(content_id)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Ok(Vec::new());
+    };
+
+    // Грубый отбор в базе по тем же признакам, точное ранжирование — в `pick`
+    let candidates = sqlx::query_as::<_, Candidate>(&format!(
+        "SELECT {columns} FROM {table}
+         WHERE id <> $1 AND translation <> $2
+         ORDER BY COALESCE({radical} = $3, FALSE)::int * 3 + COALESCE({hsk} = $4, FALSE)::int * 2
+                  + COALESCE(left({pinyin}, 2) = left($5, 2), FALSE)::int DESC,
+                  random()
+         LIMIT $6",
+        columns = columns,
+        table = source.table,
+        radical = source.radical_index,
+        hsk = source.hsk_level,
+        pinyin = source.pinyin_plain,
+    ))
+        .bind(content_id)
+        .bind(&target.text)
+        .bind(target.radical_index)
+        .bind(target.hsk_level)
+        .bind(&target.pinyin_plain)
+        .bind(CANDIDATE_POOL)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(pick(&target, candidates, count))
+}
+
+/// Лишние иероглифы для сборки слова: выученные пользователем, не из самого слова
+/// и похожие на иероглифы слова по ключу, звучанию или уровню.
+pub async fn hieroglyph_tiles(user_id: i32, word: &str, count: usize, pool: &PgPool) -> Result<Vec<String>, AppError> {
+    let parts = sqlx::query_as::<_, Candidate>(
+        "SELECT character AS text, pinyin_plain, radical_index, hsk_level
+         FROM hieroglyphs WHERE strpos($1, character) > 0",
+    )
+        .bind(word)
+        .fetch_all(pool)
+        .await?;
+
+    let candidates = sqlx::query_as::<_, Candidate>(
+        "SELECT h.character AS text, h.pinyin_plain, h.radical_index, h.hsk_level FROM hieroglyphs h
+         JOIN user_progress up
+             ON up.user_id = $1 AND up.content_type = 'hieroglyph' AND up.content_id = h.id
+         WHERE up.is_learned AND strpos($2, h.character) = 0
+         ORDER BY random()
+         LIMIT $3",
+    )
+        .bind(user_id)
+        .bind(word)
+        .bind(CANDIDATE_POOL)
+        .fetch_all(pool)
+        .await?;
+
+    // Каждый иероглиф слова получает свою долю похожих на него вариантов
+    let mut tiles: Vec<String> = Vec::with_capacity(count);
+    let mut remaining = candidates;
+    for (i, part) in parts.iter().enumerate() {
+        let share = (count - tiles.len()).div_ceil(parts.len() - i);
+        for text in pick(part, remaining.clone(), share) {
+            remaining.retain(|candidate| candidate.text != text);
+            tiles.push(text);
+        }
+    }
+    if tiles.len() < count {
+        tiles.extend(remaining.into_iter().map(|candidate| candidate.text).take(count - tiles.len()));
+    }
+    Ok(tiles)
+}
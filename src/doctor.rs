@@ -0,0 +1,385 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::BTreeSet;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use crate::config;
+use crate::jwt_keys;
+use crate::media;
+use crate::models::{SelfCheck, SelfCheckReport, SelfCheckStatus};
+
+/// Минимальная длина секрета JWT в байтах (256 бит для HS256).
+pub const MIN_JWT_SECRET_LEN: usize = 32;
+/// Расхождение часов, начиная с которого выдается предупреждение.
+const CLOCK_SKEW_WARNING_SECS: i64 = 5;
+/// Расхождение часов, при котором токены начинают отклоняться.
+const CLOCK_SKEW_FAILURE_SECS: i64 = 60;
+/// Рекомендованный OWASP минимум памяти для Argon2id, КиБ.
+const MIN_ARGON2_MEMORY_KIB: u32 = 19456;
+/// Таймаут сетевых проверок.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Таблицы горячих запросов: последовательное чтение здесь означает пропущенный индекс.
+pub const HOT_TABLES: &[&str] = &[
+    "users", "refresh_sessions", "user_progress", "review_log", "test_items", "test_results",
+    "hieroglyphs", "words", "phrases", "sentences",
+];
+/// Таблицы меньше этого размера PostgreSQL вправе читать целиком.
+pub const SEQ_SCAN_MIN_ROWS: i64 = 10_000;
+/// Доля последовательных чтений, начиная с которой выдается предупреждение.
+pub const SEQ_SCAN_MAX_SHARE: f64 = 0.5;
+/// Как часто сервер перепроверяет статистику чтений.
+const SEQ_SCAN_WATCH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Значения из примеров конфигурации, которые нельзя оставлять в работе.
+const WEAK_JWT_SECRETS: &[&str] = &["secret", "changeme", "change-me", "jwt_secret", "your-secret-key", "password"];
+
+impl SelfCheck {
+    fn ok(name: &str, message: impl Into<String>) -> Self {
+        SelfCheck { name: name.to_string(), status: SelfCheckStatus::Ok, message: message.into(), hint: None }
+    }
+
+    fn warning(name: &str, message: impl Into<String>, hint: &str) -> Self {
+        SelfCheck {
+            name: name.to_string(),
+            status: SelfCheckStatus::Warning,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn failed(name: &str, message: impl Into<String>, hint: &str) -> Self {
+        SelfCheck {
+            name: name.to_string(),
+            status: SelfCheckStatus::Failed,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn skipped(name: &str, message: impl Into<String>) -> Self {
+        SelfCheck { name: name.to_string(), status: SelfCheckStatus::Skipped, message: message.into(), hint: None }
+    }
+}
+
+/// Проверяет секрет JWT: задан, достаточно длинный и не взят из примера.
+pub fn check_jwt_secret(secret: Option<&str>) -> SelfCheck {
+    const NAME: &str = "jwt_secret";
+    let Some(secret) = secret.filter(|s| !s.is_empty()) else {
+        return SelfCheck::failed(
+            NAME,
+            "JWT_SECRET не задан",
+            "Задайте JWT_SECRET, например результат `openssl rand -hex 32`",
+        );
+    };
+    if WEAK_JWT_SECRETS.contains(&secret.to_lowercase().as_str()) {
+        return SelfCheck::failed(NAME, "JWT_SECRET взят из примера конфигурации", "Сгенерируйте случайный секрет");
+    }
+    if secret.len() < MIN_JWT_SECRET_LEN {
+        return SelfCheck::warning(
+            NAME,
+            format!("JWT_SECRET короче {} байт", MIN_JWT_SECRET_LEN),
+            "Используйте случайный секрет длиной не меньше 32 байт",
+        );
+    }
+    if secret.chars().collect::<BTreeSet<_>>().len() < 8 {
+        return SelfCheck::warning(NAME, "JWT_SECRET слишком однообразен", "Сгенерируйте случайный секрет");
+    }
+    SelfCheck::ok(NAME, "Секрет задан")
+}
+
+/// Проверяет каталог ключей JWT: ключи читаются, пары совпадают, есть ключ подписи.
+pub fn check_jwt_keys() -> SelfCheck {
+    const NAME: &str = "jwt_keys";
+    match jwt_keys::load() {
+        Ok(keys) => {
+            let active = keys.active();
+            SelfCheck::ok(
+                NAME,
+                format!("Ключей: {}, новые токены подписываются ключом {} ({:?})", keys.key_count(), active.kid, active.algorithm),
+            )
+        }
+        Err(e) => SelfCheck::failed(NAME, e, "Проверьте JWT_KEYS_DIR, JWT_ACTIVE_KID и JWT_SECRET"),
+    }
+}
+
+/// Проверяет параметры Argon2id: допустимы и не слабее рекомендованных.
+pub fn check_password_hashing(hashing: config::PasswordHashing) -> SelfCheck {
+    const NAME: &str = "password_hashing";
+    if let Err(e) = argon2::Params::new(hashing.memory_kib, hashing.iterations, hashing.parallelism, None) {
+        return SelfCheck::failed(
+            NAME,
+            format!("Некорректные параметры Argon2: {}", e),
+            "Проверьте ARGON2_MEMORY_KIB, ARGON2_ITERATIONS и ARGON2_PARALLELISM",
+        );
+    }
+    if hashing.memory_kib < MIN_ARGON2_MEMORY_KIB || hashing.iterations < 2 {
+        return SelfCheck::warning(
+            NAME,
+            format!("Argon2id: {} КиБ, итераций {}", hashing.memory_kib, hashing.iterations),
+            "Рекомендуется не меньше 19456 КиБ памяти и 2 итераций",
+        );
+    }
+    SelfCheck::ok(NAME, format!("Argon2id: {} КиБ, итераций {}", hashing.memory_kib, hashing.iterations))
+}
+
+/// Оценивает расхождение часов сервера и базы данных.
+pub fn check_clock_skew(server_now: DateTime<Utc>, database_now: DateTime<Utc>) -> SelfCheck {
+    const NAME: &str = "clock_skew";
+    let skew = (server_now - database_now).num_seconds().abs();
+    let message = format!("Расхождение с часами базы данных: {} с", skew);
+    if skew >= CLOCK_SKEW_FAILURE_SECS {
+        SelfCheck::failed(NAME, message, "Включите синхронизацию времени (NTP) на сервере и на хосте базы данных")
+    } else if skew >= CLOCK_SKEW_WARNING_SECS {
+        SelfCheck::warning(NAME, message, "Проверьте синхронизацию времени (NTP)")
+    } else {
+        SelfCheck::ok(NAME, message)
+    }
+}
+
+async fn check_database(pool: &PgPool) -> (SelfCheck, Option<DateTime<Utc>>) {
+    match sqlx::query_scalar::<_, DateTime<Utc>>("SELECT NOW()").fetch_one(pool).await {
+        Ok(now) => (SelfCheck::ok("database", "Соединение с базой данных установлено"), Some(now)),
+        Err(e) => (
+            SelfCheck::failed("database", format!("База данных недоступна: {}", e), "Проверьте DATABASE_URL и что PostgreSQL запущен"),
+            None,
+        ),
+    }
+}
+
+fn migrations_dir() -> PathBuf {
+    PathBuf::from(env::var("MIGRATIONS_DIR").unwrap_or_else(|_| "migrations".to_string()))
+}
+
+/// Версии миграций из имен файлов вида `20261017000001_name.sql`.
+fn known_migrations() -> std::io::Result<BTreeSet<i64>> {
+    let mut versions = BTreeSet::new();
+    for entry in std::fs::read_dir(migrations_dir())? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(version) = name
+            .strip_suffix(".sql")
+            .and_then(|stem| stem.split('_').next())
+            .and_then(|v| v.parse::<i64>().ok())
+        {
+            versions.insert(version);
+        }
+    }
+    Ok(versions)
+}
+
+async fn check_migrations(pool: &PgPool) -> SelfCheck {
+    const NAME: &str = "migrations";
+    let known = match known_migrations() {
+        Ok(known) => known,
+        Err(e) => {
+            return SelfCheck::skipped(NAME, format!("Каталог миграций не найден ({}), проверка пропущена", e));
+        }
+    };
+    let applied: BTreeSet<i64> =
+        match sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success").fetch_all(pool).await {
+            Ok(applied) => applied.into_iter().collect(),
+            Err(_) => {
+                return SelfCheck::failed(NAME, "Миграции ни разу не применялись", "Выполните `sqlx migrate run`");
+            }
+        };
+
+    let pending: Vec<String> = known.difference(&applied).map(i64::to_string).collect();
+    if pending.is_empty() {
+        SelfCheck::ok(NAME, format!("Применено миграций: {}", applied.len()))
+    } else {
+        SelfCheck::failed(
+            NAME,
+            format!("Не применены миграции: {}", pending.join(", ")),
+            "Выполните `sqlx migrate run` перед запуском сервера",
+        )
+    }
+}
+
+/// Счетчики чтений таблицы из `pg_stat_user_tables` (с последнего сброса статистики).
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScanStats {
+    pub table_name: String,
+    pub seq_scan: i64,
+    pub idx_scan: i64,
+    pub live_rows: i64,
+}
+
+/// Последовательные чтения преобладают над индексными на таблице, где это заметно.
+pub fn seq_scans_dominate(stats: &ScanStats) -> bool {
+    let total = stats.seq_scan + stats.idx_scan;
+    stats.live_rows >= SEQ_SCAN_MIN_ROWS && total > 0 && stats.seq_scan as f64 / total as f64 > SEQ_SCAN_MAX_SHARE
+}
+
+async fn scan_stats(pool: &PgPool) -> Result<Vec<ScanStats>, sqlx::Error> {
+    sqlx::query_as::<_, ScanStats>(
+        "SELECT relname::text AS table_name, seq_scan, COALESCE(idx_scan, 0) AS idx_scan, n_live_tup AS live_rows
+         FROM pg_stat_user_tables
+         WHERE relname = ANY($1)
+         ORDER BY relname",
+    )
+        .bind(HOT_TABLES)
+        .fetch_all(pool)
+        .await
+}
+
+/// Таблицы, которые читаются в основном целиком.
+async fn seq_scan_tables(pool: &PgPool) -> Result<Vec<ScanStats>, sqlx::Error> {
+    Ok(scan_stats(pool).await?.into_iter().filter(seq_scans_dominate).collect())
+}
+
+async fn check_sequential_scans(pool: &PgPool) -> SelfCheck {
+    const NAME: &str = "sequential_scans";
+    match seq_scan_tables(pool).await {
+        Ok(tables) if tables.is_empty() => SelfCheck::ok(NAME, "Горячие запросы используют индексы"),
+        Ok(tables) => {
+            let tables: Vec<String> = tables
+                .iter()
+                .map(|t| format!("{} ({} последовательных из {})", t.table_name, t.seq_scan, t.seq_scan + t.idx_scan))
+                .collect();
+            SelfCheck::warning(
+                NAME,
+                format!("Таблицы читаются в основном целиком: {}", tables.join(", ")),
+                "Проверьте, что применены все миграции, и посмотрите планы запросов через EXPLAIN",
+            )
+        }
+        Err(e) => SelfCheck::skipped(NAME, format!("Статистика таблиц недоступна: {}", e)),
+    }
+}
+
+/// Периодически пишет в лог предупреждение, если горячие таблицы читаются без индексов.
+pub fn spawn_seq_scan_watch(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SEQ_SCAN_WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            match seq_scan_tables(&pool).await {
+                Ok(tables) => {
+                    for t in tables {
+                        tracing::warn!(
+                            "Таблица {} читается в основном целиком: {} последовательных чтений против {} по индексу",
+                            t.table_name,
+                            t.seq_scan,
+                            t.idx_scan
+                        );
+                    }
+                }
+                Err(e) => tracing::debug!("Статистика таблиц недоступна: {:?}", e),
+            }
+        }
+    });
+}
+
+async fn check_media_storage() -> SelfCheck {
+    const NAME: &str = "media_storage";
+    let dir = media::media_dir();
+    let probe = dir.join(".doctor-probe");
+    let result = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await
+    }
+    .await;
+    match result {
+        Ok(()) => SelfCheck::ok(NAME, format!("Каталог {} доступен для записи", dir.display())),
+        Err(e) => SelfCheck::failed(
+            NAME,
+            format!("Нельзя записать в {}: {}", dir.display(), e),
+            "Проверьте MEDIA_DIR и права пользователя, от которого запущен сервер",
+        ),
+    }
+}
+
+async fn check_smtp() -> SelfCheck {
+    const NAME: &str = "smtp";
+    let Some(address) = config::smtp_address() else {
+        return SelfCheck::skipped(NAME, "Почта не настроена (SMTP_HOST)");
+    };
+
+    // Сервер должен ответить приветствием с кодом 220
+    let result = tokio::time::timeout(NETWORK_TIMEOUT, async {
+        let mut stream = TcpStream::connect(&address).await?;
+        let mut greeting = [0u8; 64];
+        let read = stream.read(&mut greeting).await?;
+        Ok::<_, std::io::Error>(String::from_utf8_lossy(&greeting[..read]).to_string())
+    })
+    .await;
+    match result {
+        Ok(Ok(greeting)) if greeting.starts_with("220") => SelfCheck::ok(NAME, format!("{} отвечает", address)),
+        Ok(Ok(greeting)) => SelfCheck::warning(
+            NAME,
+            format!("{} ответил неожиданно: {}", address, greeting.trim()),
+            "Проверьте, что SMTP_PORT указывает на SMTP-сервер",
+        ),
+        Ok(Err(e)) => SelfCheck::failed(NAME, format!("{} недоступен: {}", address, e), "Проверьте SMTP_HOST, SMTP_PORT и сетевой доступ"),
+        Err(_) => SelfCheck::failed(NAME, format!("{} не ответил за 5 с", address), "Проверьте SMTP_HOST, SMTP_PORT и сетевой доступ"),
+    }
+}
+
+/// Выполняет все проверки. `database` — пул или текст ошибки подключения
+/// (команда `doctor` подключается сама и может не достучаться до базы).
+pub async fn run(database: Result<&PgPool, String>) -> SelfCheckReport {
+    let mut checks = Vec::new();
+
+    match database {
+        Ok(pool) => {
+            let (check, database_now) = check_database(pool).await;
+            checks.push(check);
+            match database_now {
+                Some(database_now) => {
+                    checks.push(check_migrations(pool).await);
+                    checks.push(check_clock_skew(Utc::now(), database_now));
+                    checks.push(check_sequential_scans(pool).await);
+                }
+                None => {
+                    checks.push(SelfCheck::skipped("migrations", "База данных недоступна"));
+                    checks.push(SelfCheck::skipped("clock_skew", "База данных недоступна"));
+                    checks.push(SelfCheck::skipped("sequential_scans", "База данных недоступна"));
+                }
+            }
+        }
+        Err(e) => {
+            checks.push(SelfCheck::failed(
+                "database",
+                format!("Не удалось подключиться: {}", e),
+                "Проверьте DATABASE_URL и что PostgreSQL запущен",
+            ));
+            checks.push(SelfCheck::skipped("migrations", "База данных недоступна"));
+            checks.push(SelfCheck::skipped("clock_skew", "База данных недоступна"));
+            checks.push(SelfCheck::skipped("sequential_scans", "База данных недоступна"));
+        }
+    }
+
+    // С каталогом ключей JWT_SECRET необязателен: он нужен только для старых токенов
+    match config::jwt_keys_dir() {
+        Some(_) => checks.push(check_jwt_keys()),
+        None => checks.push(check_jwt_secret(env::var("JWT_SECRET").ok().as_deref())),
+    }
+    checks.push(check_password_hashing(config::password_hashing()));
+    checks.push(check_media_storage().await);
+    checks.push(check_smtp().await);
+
+    SelfCheckReport::new(checks)
+}
+
+/// Текстовый отчет для команды `doctor`.
+pub fn format_report(report: &SelfCheckReport) -> String {
+    let mut lines = Vec::new();
+    for check in &report.checks {
+        let mark = match check.status {
+            SelfCheckStatus::Ok => "[ OK ]",
+            SelfCheckStatus::Warning => "[WARN]",
+            SelfCheckStatus::Failed => "[FAIL]",
+            SelfCheckStatus::Skipped => "[SKIP]",
+        };
+        lines.push(format!("{} {}: {}", mark, check.name, check.message));
+        if let Some(hint) = &check.hint {
+            lines.push(format!("       → {}", hint));
+        }
+    }
+    lines.push(if report.ok { "Все проверки пройдены".to_string() } else { "Есть ошибки конфигурации".to_string() });
+    lines.join("\n")
+}
@@ -0,0 +1,117 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::auth;
+use crate::config;
+use crate::errors::AppError;
+use crate::mailer::{self, Email};
+use crate::password_reset::token_hash;
+
+/// Сколько действует код подтверждения почты.
+pub const TOKEN_TTL_HOURS: i64 = 48;
+/// Не чаще одного письма за столько секунд на пользователя.
+const RESEND_INTERVAL_SECS: i64 = 60;
+
+/// Отправляет код подтверждения на почту пользователя. Уже подтвержденная
+/// почта и частые повторы молча пропускаются.
+pub async fn send(user_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let user: Option<(String, Option<String>, bool)> = sqlx::query_as(
+        "SELECT nickname, email, email_verified_at IS NOT NULL FROM users WHERE id = $1",
+    )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some((nickname, Some(email), false)) = user else {
+        return Ok(());
+    };
+
+    let recent: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM email_verification_tokens
+                        WHERE user_id = $1 AND created_at > NOW() - make_interval(secs => $2))",
+    )
+        .bind(user_id)
+        .bind(RESEND_INTERVAL_SECS as f64)
+        .fetch_one(pool)
+        .await?;
+    if recent {
+        return Ok(());
+    }
+
+    let token = auth::random_token();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM email_verification_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO email_verification_tokens (token_hash, user_id, email, expires_at) VALUES ($1, $2, $3, $4)",
+    )
+        .bind(token_hash(&token))
+        .bind(user_id)
+        .bind(&email)
+        .bind(Utc::now() + Duration::hours(TOKEN_TTL_HOURS))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    mailer::send_later(Email {
+        to: email,
+        subject: "Подтверждение почты".to_string(),
+        body: format!(
+            "Здравствуйте, {}!\n\nКод для подтверждения почты:\n\n{}\n\nКод действует {} часов. \
+             Если вы не регистрировались, просто проигнорируйте это письмо.",
+            nickname, token, TOKEN_TTL_HOURS
+        ),
+    });
+    Ok(())
+}
+
+/// Подтверждает почту по коду из письма. Код одноразовый и подходит,
+/// только пока у пользователя та же почта, на которую он был отправлен.
+pub async fn verify(token: &str, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let pending: Option<(i32, String)> = sqlx::query_as(
+        "DELETE FROM email_verification_tokens WHERE token_hash = $1 AND expires_at > NOW()
+         RETURNING user_id, email",
+    )
+        .bind(token_hash(token.trim()))
+        .fetch_optional(&mut *tx)
+        .await?;
+    let invalid = || {
+        AppError::new(StatusCode::BAD_REQUEST, "Код подтверждения недействителен или устарел")
+            .with_code("invalid_verification_token")
+    };
+    let (user_id, email) = pending.ok_or_else(invalid)?;
+
+    let result = sqlx::query(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW())
+         WHERE id = $1 AND lower(email) = lower($2)",
+    )
+        .bind(user_id)
+        .bind(&email)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(invalid());
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Проверяет, что почта пользователя подтверждена, если сервер этого требует
+/// (`REQUIRE_VERIFIED_EMAIL`). Вызывается обработчиками социальных функций.
+pub async fn ensure_verified(user_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    if !config::require_verified_email() {
+        return Ok(());
+    }
+    let verified: bool = sqlx::query_scalar("SELECT email_verified_at IS NOT NULL FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(false);
+    if !verified {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Сначала подтвердите почту").with_code("email_not_verified"));
+    }
+    Ok(())
+}
@@ -3,13 +3,25 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 
+/// Ошибка в одном поле формы: клиент показывает ее рядом с полем.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    /// Машиночитаемая причина, например `too_short`.
+    pub code: &'static str,
+    pub message: String,
+}
+
 /// Наша кастомная структура ошибок.
 #[derive(Debug)]
 pub struct AppError {
     status_code: StatusCode,
     message: String,
+    code: Option<&'static str>,
+    fields: Vec<FieldError>,
 }
 
 impl AppError {
@@ -17,18 +29,57 @@ impl AppError {
         Self {
             status_code,
             message: message.to_string(),
+            code: None,
+            fields: Vec::new(),
         }
     }
+
+    /// Добавляет машиночитаемый код ошибки, по которому клиент различает ситуации
+    /// с одинаковым HTTP-статусом (например, `account_suspended`).
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Добавляет ошибки отдельных полей; в ответе они идут списком `fields`.
+    pub fn with_fields(mut self, fields: Vec<FieldError>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Текст ошибки (нужен, например, для статуса фоновых задач).
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// HTTP-статус ошибки.
+    pub fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    /// Машиночитаемый код ошибки, если он задан.
+    pub fn code(&self) -> Option<&'static str> {
+        self.code
+    }
+
+    /// Ошибки отдельных полей.
+    pub fn fields(&self) -> &[FieldError] {
+        &self.fields
+    }
 }
 
 /// Преобразуем нашу ошибку в HTTP ответ.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (
-            self.status_code,
-            Json(json!({ "error": self.message })),
-        )
-            .into_response()
+        let mut body = match self.code {
+            Some(code) => json!({ "error": self.message, "code": code }),
+            None => json!({ "error": self.message }),
+        };
+        if !self.fields.is_empty() {
+            body["fields"] = json!(self.fields);
+        }
+
+        (self.status_code, Json(body)).into_response()
     }
 }
 
@@ -57,4 +108,12 @@ impl From<bcrypt::BcryptError> for AppError {
         tracing::error!("Ошибка Bcrypt: {:?}", err);
         AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Ошибка хеширования")
     }
-}
\ No newline at end of file
+}
+
+/// Позволяем использовать `?` для ошибок ввода-вывода.
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        tracing::error!("Ошибка ввода-вывода: {:?}", err);
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Ошибка работы с файлами")
+    }
+}
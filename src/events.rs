@@ -0,0 +1,249 @@
+use axum::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::classroom;
+use crate::config::{self, EventSink};
+use crate::errors::AppError;
+use crate::gamification::{self, XpSource};
+use crate::groups;
+use crate::models::ContentType;
+use crate::notifications::{self, NotificationKind};
+use crate::streak;
+
+/// Таймаут доставки события во внешний webhook.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Событие обучения. Обработчики публикуют факт, а реакции на него
+/// (уведомления, опыт, серия дней, учебные группы, доска класса, выгрузка) живут в подписчиках. Достижения проверяются
+/// прямо в обработчике, чтобы вернуть новые в том же ответе.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Learned { user_id: i32, content_type: ContentType, content_id: i32 },
+    Reviewed { user_id: i32, content_type: ContentType, content_id: i32, quality: u8 },
+    TestSubmitted { user_id: i32, test_id: i32, result_id: i32, score: i32, total_questions: i32 },
+    LessonCompleted { user_id: i32, lesson_id: i32, newly_learned: u64 },
+    Login { user_id: i32 },
+    AchievementUnlocked { user_id: i32, achievement_id: i32, name: String },
+}
+
+/// Событие с временем публикации; в таком виде оно уходит во внешний приемник.
+#[derive(Debug, Clone, Serialize)]
+pub struct Envelope {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Подписчик шины событий. Ошибка подписчика пишется в лог и не мешает остальным.
+#[async_trait]
+pub trait Subscriber: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn handle(&self, envelope: &Envelope, pool: &PgPool) -> Result<(), AppError>;
+}
+
+/// Сообщает пользователю о новых достижениях.
+struct NotificationsSubscriber;
+
+#[async_trait]
+impl Subscriber for NotificationsSubscriber {
+    fn name(&self) -> &'static str {
+        "notifications"
+    }
+
+    async fn handle(&self, envelope: &Envelope, pool: &PgPool) -> Result<(), AppError> {
+        if let Event::AchievementUnlocked { user_id, name, .. } = &envelope.event {
+            notifications::notify(*user_id, NotificationKind::Achievement, "Новое достижение", name, pool).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Начисляет опыт за выученное, тесты и уроки.
+struct XpSubscriber;
+
+#[async_trait]
+impl Subscriber for XpSubscriber {
+    fn name(&self) -> &'static str {
+        "xp"
+    }
+
+    async fn handle(&self, envelope: &Envelope, pool: &PgPool) -> Result<(), AppError> {
+        let (user_id, source, source_id, amount) = match &envelope.event {
+            Event::Learned { user_id, content_type, content_id } => (
+                *user_id,
+                XpSource::Learned,
+                gamification::learned_key(content_type, *content_id),
+                gamification::XP_PER_LEARNED,
+            ),
+            // Опыт дает только первая сдача теста
+            Event::TestSubmitted { user_id, test_id, score, .. } => {
+                (*user_id, XpSource::Test, test_id.to_string(), score * gamification::XP_PER_CORRECT_ANSWER)
+            }
+            // Элементы урока выучиваются без отдельных событий, поэтому оцениваются здесь же
+            Event::LessonCompleted { user_id, lesson_id, newly_learned } => (
+                *user_id,
+                XpSource::Lesson,
+                lesson_id.to_string(),
+                gamification::XP_PER_LESSON + *newly_learned as i32 * gamification::XP_PER_LEARNED,
+            ),
+            Event::Reviewed { .. } | Event::Login { .. } | Event::AchievementUnlocked { .. } => return Ok(()),
+        };
+        gamification::award(user_id, source, &source_id, amount, pool).await?;
+        Ok(())
+    }
+}
+
+/// Засчитывает день в серию занятий.
+struct StreakSubscriber;
+
+#[async_trait]
+impl Subscriber for StreakSubscriber {
+    fn name(&self) -> &'static str {
+        "streak"
+    }
+
+    async fn handle(&self, envelope: &Envelope, pool: &PgPool) -> Result<(), AppError> {
+        match envelope.event {
+            Event::Learned { user_id, .. }
+            | Event::Reviewed { user_id, .. }
+            | Event::TestSubmitted { user_id, .. }
+            | Event::LessonCompleted { user_id, .. } => streak::record(user_id, pool).await,
+            Event::Login { .. } | Event::AchievementUnlocked { .. } => Ok(()),
+        }
+    }
+}
+
+/// Сообщает учебным группам о выполненной цели недели и дне, когда позанимались все.
+/// Стоит после опыта и серии: читает то, что они только что записали.
+struct GroupsSubscriber;
+
+#[async_trait]
+impl Subscriber for GroupsSubscriber {
+    fn name(&self) -> &'static str {
+        "groups"
+    }
+
+    async fn handle(&self, envelope: &Envelope, pool: &PgPool) -> Result<(), AppError> {
+        match envelope.event {
+            Event::Learned { user_id, .. }
+            | Event::Reviewed { user_id, .. }
+            | Event::TestSubmitted { user_id, .. }
+            | Event::LessonCompleted { user_id, .. } => groups::on_activity(user_id, pool).await,
+            Event::Login { .. } | Event::AchievementUnlocked { .. } => Ok(()),
+        }
+    }
+}
+
+/// Показывает действия учеников на открытых досках их классов.
+struct ClassroomSubscriber;
+
+#[async_trait]
+impl Subscriber for ClassroomSubscriber {
+    fn name(&self) -> &'static str {
+        "classroom"
+    }
+
+    async fn handle(&self, envelope: &Envelope, pool: &PgPool) -> Result<(), AppError> {
+        match envelope.event {
+            Event::Learned { user_id, .. }
+            | Event::Reviewed { user_id, .. }
+            | Event::TestSubmitted { user_id, .. }
+            | Event::LessonCompleted { user_id, .. } => classroom::broadcast(user_id, &envelope.event, pool).await,
+            Event::Login { .. } | Event::AchievementUnlocked { .. } => Ok(()),
+        }
+    }
+}
+
+/// Дописывает события в файл JSON Lines (`EVENT_SINK=file:путь`).
+struct FileSink {
+    path: PathBuf,
+}
+
+#[async_trait]
+impl Subscriber for FileSink {
+    fn name(&self) -> &'static str {
+        "file_sink"
+    }
+
+    async fn handle(&self, envelope: &Envelope, _pool: &PgPool) -> Result<(), AppError> {
+        let mut line = serde_json::to_vec(envelope).map_err(|e| {
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, &format!("Событие не сериализуется: {}", e))
+        })?;
+        line.push(b'\n');
+        // Одна запись в режиме дозаписи не перемешивается с соседними строками
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&self.path).await?;
+        file.write_all(&line).await?;
+        Ok(())
+    }
+}
+
+/// Отправляет события POST-запросом (`EVENT_SINK=https://...`).
+struct WebhookSink {
+    url: String,
+    client: Client,
+}
+
+#[async_trait]
+impl Subscriber for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook_sink"
+    }
+
+    async fn handle(&self, envelope: &Envelope, _pool: &PgPool) -> Result<(), AppError> {
+        self.client
+            .post(&self.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(envelope)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                AppError::new(StatusCode::BAD_GATEWAY, &format!("Webhook событий недоступен: {}", e))
+            })?;
+        Ok(())
+    }
+}
+
+// Подписчики собираются один раз при первой публикации.
+static SUBSCRIBERS: Lazy<Vec<Box<dyn Subscriber>>> = Lazy::new(|| {
+    let mut subscribers: Vec<Box<dyn Subscriber>> = vec![
+        Box::new(NotificationsSubscriber),
+        Box::new(XpSubscriber),
+        Box::new(StreakSubscriber),
+        Box::new(GroupsSubscriber),
+        Box::new(ClassroomSubscriber),
+    ];
+    match config::event_sink() {
+        Some(EventSink::File(path)) => subscribers.push(Box::new(FileSink { path })),
+        Some(EventSink::Webhook(url)) => subscribers.push(Box::new(WebhookSink { url, client: Client::new() })),
+        None => {}
+    }
+    subscribers
+});
+
+/// Публикует событие. Подписчики работают в фоне, поэтому обработчик запроса
+/// не ждет ни уведомлений, ни внешнего приемника.
+pub fn publish(event: Event, pool: &PgPool) {
+    let envelope = Envelope { at: Utc::now(), event };
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        dispatch(&envelope, &pool).await;
+    });
+}
+
+async fn dispatch(envelope: &Envelope, pool: &PgPool) {
+    for subscriber in SUBSCRIBERS.iter() {
+        if let Err(e) = subscriber.handle(envelope, pool).await {
+            tracing::warn!("Подписчик {} не обработал событие {:?}: {}", subscriber.name(), envelope.event, e.message());
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::gradebook::{csv_error, csv_error_message};
+use crate::models::{ExportProgressRow, ExportTestResult, StudyTimeEntry, UserExport};
+
+/// Собирает все учебные данные пользователя для выгрузки.
+pub async fn collect(user_id: i32, pool: &PgPool) -> Result<UserExport, AppError> {
+    let nickname: String = sqlx::query_scalar("SELECT nickname FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    // Прогресс вместе со статистикой ошибок: элемент может быть только в одной из таблиц
+    let progress = sqlx::query_as::<_, ExportProgressRow>(
+        "SELECT COALESCE(up.content_type, wi.content_type) AS content_type,
+                COALESCE(up.content_id, wi.content_id) AS content_id,
+                COALESCE(up.is_learned, FALSE) AS is_learned,
+                up.learned_at,
+                COALESCE(wi.misses, 0) AS misses,
+                wi.last_missed_at,
+                up.ease_factor,
+                up.interval_days,
+                up.repetitions,
+                up.next_review_at
+         FROM (SELECT * FROM user_progress WHERE user_id = $1) up
+         FULL OUTER JOIN (SELECT * FROM weak_items WHERE user_id = $1) wi
+             ON wi.content_type = up.content_type AND wi.content_id = up.content_id
+         ORDER BY 1, 2",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let test_results = sqlx::query_as::<_, ExportTestResult>(
+        "SELECT tr.test_id, t.name AS test_name, tr.score, tr.submitted_at
+         FROM test_results tr JOIN tests t ON t.id = tr.test_id
+         WHERE tr.user_id = $1
+         ORDER BY tr.submitted_at",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let study_time = sqlx::query_as::<_, StudyTimeEntry>(
+        "SELECT day, activity, seconds / 60.0::float8 AS minutes FROM study_time
+         WHERE user_id = $1 ORDER BY day, activity",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(UserExport { exported_at: Utc::now(), nickname, progress, test_results, study_time })
+}
+
+/// Прогресс пользователя в CSV: по строке на элемент контента.
+pub fn progress_csv(export: &UserExport) -> Result<Vec<u8>, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in &export.progress {
+        writer.serialize(row).map_err(csv_error)?;
+    }
+    writer.into_inner().map_err(|_| csv_error_message())
+}
@@ -0,0 +1,165 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::auth::{self, ensure_not_banned};
+use crate::errors::AppError;
+use crate::models::{ApiKey, CreatedApiKey, User};
+use crate::AppState;
+
+/// Заголовок, в котором расширение браузера передает ключ.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// Лимит запросов на один ключ в окно
+const RATE_LIMIT_REQUESTS: u32 = 60;
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+// Максимум активных ключей у одного пользователя
+const MAX_KEYS_PER_USER: i64 = 10;
+
+// Счетчики запросов по ключам: начало текущего окна и число запросов в нем
+static RATE_WINDOWS: Lazy<Mutex<HashMap<i32, (Instant, u32)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Владелец ключа API, которым подписан запрос.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiKeyUser {
+    pub user_id: i32,
+    pub key_id: i32,
+}
+
+/// SHA-256 ключа в hex. В БД хранится только он.
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Учитывает запрос в окне ключа. Возвращает ошибку, если лимит исчерпан.
+fn check_rate_limit(key_id: i32) -> Result<(), AppError> {
+    let mut windows = RATE_WINDOWS.lock().unwrap();
+    let now = Instant::now();
+    let window = windows.entry(key_id).or_insert((now, 0));
+    if now.duration_since(window.0) >= RATE_LIMIT_WINDOW {
+        *window = (now, 0);
+    }
+    if window.1 >= RATE_LIMIT_REQUESTS {
+        return Err(AppError::new(StatusCode::TOO_MANY_REQUESTS, "Слишком много запросов, попробуйте позже")
+            .with_code("rate_limited"));
+    }
+    window.1 += 1;
+    Ok(())
+}
+
+/// Создает новый ключ. Сам ключ возвращается только в этом ответе.
+pub async fn create_key(user_id: i32, name: &str, pool: &PgPool) -> Result<CreatedApiKey, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 64 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название ключа должно содержать от 1 до 64 символов"));
+    }
+
+    let active: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM api_keys WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    if active >= MAX_KEYS_PER_USER {
+        return Err(AppError::new(StatusCode::CONFLICT, "Достигнут лимит активных ключей"));
+    }
+
+    let key = auth::random_token();
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO api_keys (user_id, name, key_hash) VALUES ($1, $2, $3) RETURNING id",
+    )
+        .bind(user_id)
+        .bind(name)
+        .bind(hash_key(&key))
+        .fetch_one(pool)
+        .await?;
+
+    Ok(CreatedApiKey { id, name: name.to_string(), key })
+}
+
+/// Ключи пользователя без самих значений.
+pub async fn list_keys(user_id: i32, pool: &PgPool) -> Result<Vec<ApiKey>, AppError> {
+    let keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT id, name, created_at, last_used_at, revoked_at FROM api_keys
+         WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(keys)
+}
+
+/// Отзывает ключ пользователя.
+pub async fn revoke_key(user_id: i32, key_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+        .bind(key_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Ключ не найден"));
+    }
+    RATE_WINDOWS.lock().unwrap().remove(&key_id);
+    Ok(())
+}
+
+/// Проверяет ключ из заголовка: ключ не отозван, владелец не заблокирован, лимит не исчерпан.
+async fn authenticate(parts: &Parts, pool: &PgPool) -> Result<ApiKeyUser, AppError> {
+    let key = parts
+        .headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Требуется ключ API"))?;
+
+    let (key_id, user_id): (i32, i32) = sqlx::query_as(
+        "SELECT id, user_id FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+    )
+        .bind(hash_key(key))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Невалидный ключ API"))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    ensure_not_banned(&user)?;
+
+    check_rate_limit(key_id)?;
+
+    sqlx::query("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1")
+        .bind(key_id)
+        .execute(pool)
+        .await?;
+
+    Ok(ApiKeyUser { user_id, key_id })
+}
+
+// Экстрактор для роутов расширения: вместо JWT запрос подписан ключом API
+#[async_trait]
+impl<S> FromRequestParts<S> for ApiKeyUser
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = AppState::from_ref(state);
+        authenticate(parts, &app_state.db_pool)
+            .await
+            .map_err(IntoResponse::into_response)
+    }
+}
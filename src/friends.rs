@@ -0,0 +1,202 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::achievements;
+use crate::errors::AppError;
+use crate::gamification;
+use crate::models::{Friend, FriendProfile, FriendRequest, FriendRequests};
+use crate::notifications::{self, NotificationKind};
+use crate::streak;
+
+const REQUEST_COLUMNS: &str = "
+    fr.id, fr.from_user_id, uf.nickname AS from_nickname, fr.to_user_id, ut.nickname AS to_nickname,
+    fr.status, fr.created_at, fr.responded_at";
+
+const REQUEST_JOINS: &str = "
+    JOIN users uf ON uf.id = fr.from_user_id
+    JOIN users ut ON ut.id = fr.to_user_id";
+
+async fn load_request(request_id: i32, pool: &PgPool) -> Result<FriendRequest, AppError> {
+    let request = sqlx::query_as::<_, FriendRequest>(&format!(
+        "SELECT {} FROM friend_requests fr {} WHERE fr.id = $1",
+        REQUEST_COLUMNS, REQUEST_JOINS
+    ))
+        .bind(request_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(request)
+}
+
+/// Друзья ли пользователи.
+pub async fn are_friends(user_id: i32, other_id: i32, pool: &PgPool) -> Result<bool, AppError> {
+    let friends: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM friendships WHERE user_id = $1 AND friend_id = $2)",
+    )
+        .bind(user_id)
+        .bind(other_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(friends)
+}
+
+/// Отправляет заявку пользователю с ником `nickname`. Если он сам уже позвал
+/// отправителя в друзья, встречная заявка просто принимается.
+pub async fn send_request(user_id: i32, nickname: &str, pool: &PgPool) -> Result<FriendRequest, AppError> {
+    let to_user_id: i32 = sqlx::query_scalar("SELECT id FROM users WHERE nickname = $1 AND disabled_at IS NULL")
+        .bind(nickname.trim())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"))?;
+    if to_user_id == user_id {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Нельзя добавить в друзья самого себя"));
+    }
+    if are_friends(user_id, to_user_id, pool).await? {
+        return Err(AppError::new(StatusCode::CONFLICT, "Пользователь уже в друзьях").with_code("already_friends"));
+    }
+
+    let counter: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM friend_requests WHERE from_user_id = $1 AND to_user_id = $2 AND status = 'pending'",
+    )
+        .bind(to_user_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    if let Some(request_id) = counter {
+        return respond(request_id, user_id, true, pool).await;
+    }
+
+    let request_id: i32 = sqlx::query_scalar(
+        "INSERT INTO friend_requests (from_user_id, to_user_id) VALUES ($1, $2)
+         ON CONFLICT ((LEAST(from_user_id, to_user_id)), (GREATEST(from_user_id, to_user_id)))
+             WHERE status = 'pending' DO NOTHING
+         RETURNING id",
+    )
+        .bind(user_id)
+        .bind(to_user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::CONFLICT, "Заявка уже отправлена").with_code("request_pending")
+        })?;
+
+    let request = load_request(request_id, pool).await?;
+    notifications::notify(
+        to_user_id,
+        NotificationKind::FriendRequest,
+        "Заявка в друзья",
+        &format!("{} хочет добавить вас в друзья", request.from_nickname),
+        pool,
+    )
+        .await?;
+    Ok(request)
+}
+
+/// Принимает или отклоняет заявку. Отвечать может только тот, кому она адресована.
+pub async fn respond(request_id: i32, user_id: i32, accept: bool, pool: &PgPool) -> Result<FriendRequest, AppError> {
+    let mut tx = pool.begin().await?;
+    let (from_user_id, status): (i32, String) = sqlx::query_as(
+        "SELECT from_user_id, status FROM friend_requests WHERE id = $1 AND to_user_id = $2 FOR UPDATE",
+    )
+        .bind(request_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Заявка не найдена"))?;
+    if status != "pending" {
+        return Err(AppError::new(StatusCode::CONFLICT, "На заявку уже ответили").with_code("request_answered"));
+    }
+
+    sqlx::query("UPDATE friend_requests SET status = $2, responded_at = NOW() WHERE id = $1")
+        .bind(request_id)
+        .bind(if accept { "accepted" } else { "declined" })
+        .execute(&mut *tx)
+        .await?;
+    if accept {
+        sqlx::query(
+            "INSERT INTO friendships (user_id, friend_id) VALUES ($1, $2), ($2, $1)
+             ON CONFLICT DO NOTHING",
+        )
+            .bind(from_user_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let request = load_request(request_id, pool).await?;
+    // Об отказе отправитель не узнает: заявка просто перестает ждать ответа
+    if accept {
+        notifications::notify(
+            from_user_id,
+            NotificationKind::FriendAccepted,
+            "Новый друг",
+            &format!("{} принял(а) вашу заявку в друзья", request.to_nickname),
+            pool,
+        )
+            .await?;
+    }
+    Ok(request)
+}
+
+/// Входящие и исходящие заявки, ожидающие ответа, новые первыми.
+pub async fn pending(user_id: i32, pool: &PgPool) -> Result<FriendRequests, AppError> {
+    let requests = sqlx::query_as::<_, FriendRequest>(&format!(
+        "SELECT {} FROM friend_requests fr {}
+         WHERE (fr.from_user_id = $1 OR fr.to_user_id = $1) AND fr.status = 'pending'
+         ORDER BY fr.created_at DESC",
+        REQUEST_COLUMNS, REQUEST_JOINS
+    ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let (incoming, outgoing) = requests.into_iter().partition(|request| request.to_user_id == user_id);
+    Ok(FriendRequests { incoming, outgoing })
+}
+
+/// Друзья пользователя по алфавиту.
+pub async fn list(user_id: i32, pool: &PgPool) -> Result<Vec<Friend>, AppError> {
+    let friends = sqlx::query_as::<_, Friend>(
+        "SELECT u.id AS user_id, u.nickname, f.created_at AS since
+         FROM friendships f JOIN users u ON u.id = f.friend_id
+         WHERE f.user_id = $1
+         ORDER BY u.nickname",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(friends)
+}
+
+/// Удаляет дружбу у обоих пользователей.
+pub async fn remove(user_id: i32, friend_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let result = sqlx::query(
+        "DELETE FROM friendships
+         WHERE (user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1)",
+    )
+        .bind(user_id)
+        .bind(friend_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Пользователь не в друзьях"));
+    }
+    Ok(())
+}
+
+/// Профиль друга. Чужой профиль не отличается от несуществующего, чтобы
+/// по ответу нельзя было узнать, есть ли такой пользователь.
+pub async fn profile(viewer_id: i32, friend_id: i32, pool: &PgPool) -> Result<FriendProfile, AppError> {
+    if !are_friends(viewer_id, friend_id, pool).await? {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Профиль не найден"));
+    }
+
+    let profile = gamification::profile(friend_id, pool).await?;
+    Ok(FriendProfile {
+        user_id: profile.id,
+        nickname: profile.nickname,
+        level: profile.level,
+        streak: streak::summary(friend_id, pool).await?,
+        achievements: achievements::for_user(friend_id, pool).await?,
+    })
+}
@@ -0,0 +1,148 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::config::{self, LevelCurve};
+use crate::errors::AppError;
+use crate::jobs;
+use crate::models::{ContentType, LevelProgress, ProfileResponse};
+
+/// Опыт за выученный элемент.
+pub const XP_PER_LEARNED: i32 = 10;
+/// Опыт за каждый верный ответ в тесте.
+pub const XP_PER_CORRECT_ANSWER: i32 = 5;
+/// Опыт за пройденный урок (элементы урока оцениваются отдельно).
+pub const XP_PER_LESSON: i32 = 50;
+
+/// За что начислен опыт; вместе с id источника не дает получить опыт дважды.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XpSource {
+    Learned,
+    Test,
+    Lesson,
+}
+
+impl XpSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            XpSource::Learned => "learned",
+            XpSource::Test => "test",
+            XpSource::Lesson => "lesson",
+        }
+    }
+}
+
+/// Ключ выученного элемента в журнале начислений.
+pub fn learned_key(content_type: &ContentType, content_id: i32) -> String {
+    format!("{:?}:{}", content_type, content_id)
+}
+
+/// Сколько опыта стоит переход с уровня `level` на следующий.
+pub fn level_cost(curve: LevelCurve, level: i32) -> i64 {
+    ((curve.base as f64) * curve.growth.powi(level - 1)).round().max(1.0) as i64
+}
+
+/// Уровень и прогресс до следующего уровня при `xp` опыта.
+pub fn level_progress(xp: i64, curve: LevelCurve) -> LevelProgress {
+    let mut level = 1;
+    let mut level_start = 0;
+    let mut cost = level_cost(curve, level);
+    while xp >= level_start + cost {
+        level_start += cost;
+        level += 1;
+        cost = level_cost(curve, level);
+    }
+
+    let xp_into_level = xp.max(0) - level_start;
+    LevelProgress {
+        xp,
+        level,
+        xp_into_level,
+        xp_for_next_level: cost,
+        progress: xp_into_level as f64 / cost as f64,
+    }
+}
+
+/// Начисляет опыт и пересчитывает уровень. Повторное начисление за тот же
+/// источник игнорируется; возвращает новый прогресс, если опыт начислен.
+pub async fn award(
+    user_id: i32,
+    source: XpSource,
+    source_id: &str,
+    amount: i32,
+    pool: &PgPool,
+) -> Result<Option<LevelProgress>, AppError> {
+    if amount <= 0 {
+        return Ok(None);
+    }
+
+    let mut tx = pool.begin().await?;
+    let inserted = sqlx::query(
+        "INSERT INTO xp_awards (user_id, source, source_id, amount) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (user_id, source, source_id) DO NOTHING",
+    )
+        .bind(user_id)
+        .bind(source.as_str())
+        .bind(source_id)
+        .bind(amount)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if inserted == 0 {
+        return Ok(None);
+    }
+
+    // UPDATE блокирует строку пользователя до конца транзакции, поэтому
+    // параллельные начисления не перезапишут уровень устаревшим значением
+    let xp: i64 = sqlx::query_scalar("UPDATE users SET xp = xp + $2 WHERE id = $1 RETURNING xp")
+        .bind(user_id)
+        .bind(amount as i64)
+        .fetch_one(&mut *tx)
+        .await?;
+    let progress = level_progress(xp, config::level_curve());
+    sqlx::query("UPDATE users SET level = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(progress.level)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(Some(progress))
+}
+
+/// Профиль пользователя с опытом и уровнем. Уровень считается по текущей кривой,
+/// так что ее изменение сразу видно в профиле.
+pub async fn profile(user_id: i32, pool: &PgPool) -> Result<ProfileResponse, AppError> {
+    let (id, nickname, xp): (i32, String, i64) = sqlx::query_as("SELECT id, nickname, xp FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"))?;
+
+    Ok(ProfileResponse { id, nickname, level: level_progress(xp, config::level_curve()) })
+}
+
+/// Пересчитывает опыт и уровень всех пользователей по журналу начислений и
+/// текущей кривой: рейтинги групп и друзей читают именно их.
+pub async fn recompute_all(pool: PgPool, job_id: u64) -> Result<(), AppError> {
+    let totals: Vec<(i32, i64)> = sqlx::query_as(
+        "SELECT u.id, COALESCE(SUM(x.amount), 0)::bigint
+         FROM users u LEFT JOIN xp_awards x ON x.user_id = u.id
+         GROUP BY u.id ORDER BY u.id",
+    )
+        .fetch_all(&pool)
+        .await?;
+
+    let curve = config::level_curve();
+    jobs::set_total(job_id, totals.len());
+    for (user_id, xp) in totals {
+        sqlx::query("UPDATE users SET xp = $2, level = $3 WHERE id = $1")
+            .bind(user_id)
+            .bind(xp)
+            .bind(level_progress(xp, curve).level)
+            .execute(&pool)
+            .await?;
+        jobs::advance(job_id);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::errors::AppError;
+use axum::http::StatusCode;
+
+/// Постоянные колонки журнала. Колонки заданий идут после них
+/// в порядке срока сдачи, чтобы импорт в школьные системы не ломался.
+const FIXED_COLUMNS: &[&str] = &[
+    "user_id",
+    "nickname",
+    "assignments_total",
+    "assignments_completed",
+    "average_score_percent",
+    "study_minutes",
+];
+
+/// Формирует CSV-журнал класса: по строке на ученика.
+pub async fn build_csv(class_id: i32, pool: &PgPool) -> Result<Vec<u8>, AppError> {
+    let students = sqlx::query_as::<_, (i32, String)>(
+        "SELECT u.id, u.nickname FROM class_members cm
+         JOIN users u ON u.id = cm.user_id
+         WHERE cm.class_id = $1
+         ORDER BY u.nickname, u.id",
+    )
+        .bind(class_id)
+        .fetch_all(pool)
+        .await?;
+
+    let assignments = sqlx::query_as::<_, (i32, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, title, due_at FROM assignments WHERE class_id = $1 ORDER BY due_at, id",
+    )
+        .bind(class_id)
+        .fetch_all(pool)
+        .await?;
+
+    // Лучший результат ученика по каждому заданию в процентах
+    let scores = sqlx::query_as::<_, (i32, i32, f64)>(
+        "SELECT a.id, tr.user_id,
+                MAX(tr.score)::float8 * 100 / NULLIF((SELECT COUNT(*) FROM test_items ti WHERE ti.test_id = a.test_id), 0)
+         FROM assignments a
+         JOIN test_results tr ON tr.test_id = a.test_id AND tr.submitted_at >= a.created_at
+         JOIN class_members cm ON cm.class_id = a.class_id AND cm.user_id = tr.user_id
+         WHERE a.class_id = $1
+         GROUP BY a.id, tr.user_id",
+    )
+        .bind(class_id)
+        .fetch_all(pool)
+        .await?;
+
+    let study_minutes: HashMap<i32, i64> = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT st.user_id, SUM(st.seconds)::bigint / 60 FROM study_time st
+         JOIN class_members cm ON cm.user_id = st.user_id AND cm.class_id = $1
+         WHERE st.day >= cm.joined_at::date
+         GROUP BY st.user_id",
+    )
+        .bind(class_id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let scores: HashMap<(i32, i32), f64> = scores
+        .into_iter()
+        .map(|(assignment_id, user_id, percent)| ((assignment_id, user_id), percent))
+        .collect();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let mut header: Vec<String> = FIXED_COLUMNS.iter().map(|c| c.to_string()).collect();
+    header.extend(
+        assignments
+            .iter()
+            .map(|(_, title, due_at)| format!("{} ({})", title, due_at.format("%Y-%m-%d"))),
+    );
+    writer.write_record(&header).map_err(csv_error)?;
+
+    for (user_id, nickname) in students {
+        let student_scores: Vec<Option<f64>> = assignments
+            .iter()
+            .map(|(assignment_id, _, _)| scores.get(&(*assignment_id, user_id)).copied())
+            .collect();
+
+        let completed: Vec<f64> = student_scores.iter().flatten().copied().collect();
+        let average = if completed.is_empty() {
+            String::new()
+        } else {
+            format!("{:.1}", completed.iter().sum::<f64>() / completed.len() as f64)
+        };
+
+        let mut record = vec![
+            user_id.to_string(),
+            nickname,
+            assignments.len().to_string(),
+            completed.len().to_string(),
+            average,
+            study_minutes.get(&user_id).copied().unwrap_or(0).to_string(),
+        ];
+        record.extend(
+            student_scores
+                .iter()
+                .map(|score| score.map(|s| format!("{:.1}", s)).unwrap_or_default()),
+        );
+        writer.write_record(&record).map_err(csv_error)?;
+    }
+
+    writer.into_inner().map_err(|_| csv_error_message())
+}
+
+pub(crate) fn csv_error(err: csv::Error) -> AppError {
+    tracing::error!("Ошибка формирования CSV: {:?}", err);
+    csv_error_message()
+}
+
+pub(crate) fn csv_error_message() -> AppError {
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Не удалось сформировать CSV")
+}
@@ -0,0 +1,168 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+use crate::difficulty::segment;
+use crate::errors::AppError;
+use crate::matching::normalize_hanzi;
+use crate::models::{ContentGraph, ContentType, GraphEdge, GraphNode, GraphRelation};
+use crate::search::contains_pattern;
+
+/// Максимальное количество соседей одного типа, чтобы граф оставался компактным.
+const MAX_NEIGHBOURS: i64 = 30;
+
+/// Разбирает тип узла из пути запроса.
+pub fn parse_node_type(value: &str) -> Option<ContentType> {
+    match value {
+        "hieroglyph" => Some(ContentType::Hieroglyph),
+        "word" => Some(ContentType::Word),
+        "sentence" => Some(ContentType::Sentence),
+        _ => None,
+    }
+}
+
+fn node_key(content_type: &ContentType, id: i32) -> String {
+    let prefix = match content_type {
+        ContentType::Hieroglyph => "hieroglyph",
+        ContentType::Word => "word",
+        ContentType::Sentence => "sentence",
+        ContentType::Phrase => "phrase",
+        ContentType::GrammarRule => "grammar_rule",
+        ContentType::Lesson => "lesson",
+    };
+    format!("{}:{}", prefix, id)
+}
+
+/// Накопитель узлов и ребер без дубликатов.
+#[derive(Default)]
+struct GraphBuilder {
+    graph: ContentGraph,
+    seen: HashSet<String>,
+}
+
+impl GraphBuilder {
+    fn node(&mut self, content_type: ContentType, id: i32, label: String) -> String {
+        let key = node_key(&content_type, id);
+        if self.seen.insert(key.clone()) {
+            self.graph.nodes.push(GraphNode { key: key.clone(), content_type, id, label });
+        }
+        key
+    }
+
+    /// Добавляет соседей и ребра; `outgoing` — ребро направлено от центрального узла.
+    fn neighbours(&mut self, center: &str, content_type: ContentType, rows: Vec<(i32, String)>, relation: GraphRelation, outgoing: bool) {
+        for (id, label) in rows {
+            let key = self.node(content_type.clone(), id, label);
+            let (from, to) = if outgoing { (center.to_string(), key) } else { (key, center.to_string()) };
+            self.graph.edges.push(GraphEdge { from, to, relation });
+        }
+    }
+}
+
+/// Строит граф связей вокруг элемента контента: компоненты, составные иероглифы,
+/// слова и предложения. Ребра направлены от целого к части.
+pub async fn build(content_type: ContentType, id: i32, pool: &PgPool) -> Result<ContentGraph, AppError> {
+    let mut builder = GraphBuilder::default();
+
+    match content_type {
+        ContentType::Hieroglyph => {
+            let character: String = sqlx::query_scalar("SELECT character FROM hieroglyphs WHERE id = $1")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Иероглиф не найден"))?;
+            let center = builder.node(ContentType::Hieroglyph, id, character.clone());
+
+            let components = sqlx::query_as::<_, (i32, String)>(
+                "SELECT h.id, h.character FROM hieroglyph_components hc
+                 JOIN hieroglyphs h ON h.id = hc.component_id
+                 WHERE hc.hieroglyph_id = $1 ORDER BY hc.position",
+            )
+                .bind(id)
+                .fetch_all(pool)
+                .await?;
+            builder.neighbours(&center, ContentType::Hieroglyph, components, GraphRelation::Component, true);
+
+            let compounds = sqlx::query_as::<_, (i32, String)>(
+                "SELECT h.id, h.character FROM hieroglyph_components hc
+                 JOIN hieroglyphs h ON h.id = hc.hieroglyph_id
+                 WHERE hc.component_id = $1 ORDER BY h.id LIMIT $2",
+            )
+                .bind(id)
+                .bind(MAX_NEIGHBOURS)
+                .fetch_all(pool)
+                .await?;
+            builder.neighbours(&center, ContentType::Hieroglyph, compounds, GraphRelation::Component, false);
+
+            let words = sqlx::query_as::<_, (i32, String)>(
+                "SELECT id, simplified FROM words WHERE simplified LIKE $1 ORDER BY id LIMIT $2",
+            )
+                .bind(contains_pattern(&character))
+                .bind(MAX_NEIGHBOURS)
+                .fetch_all(pool)
+                .await?;
+            builder.neighbours(&center, ContentType::Word, words, GraphRelation::Contains, false);
+
+            let sentences = sqlx::query_as::<_, (i32, String)>(
+                "SELECT id, text FROM sentences WHERE text LIKE $1 ORDER BY id LIMIT $2",
+            )
+                .bind(contains_pattern(&character))
+                .bind(MAX_NEIGHBOURS)
+                .fetch_all(pool)
+                .await?;
+            builder.neighbours(&center, ContentType::Sentence, sentences, GraphRelation::Example, false);
+        }
+        ContentType::Word => {
+            let simplified: String = sqlx::query_scalar("SELECT simplified FROM words WHERE id = $1")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Слово не найдено"))?;
+            let center = builder.node(ContentType::Word, id, simplified.clone());
+
+            let characters: Vec<String> = simplified.chars().map(|c| c.to_string()).collect();
+            let hieroglyphs = sqlx::query_as::<_, (i32, String)>(
+                "SELECT id, character FROM hieroglyphs WHERE character = ANY($1) ORDER BY id",
+            )
+                .bind(&characters)
+                .fetch_all(pool)
+                .await?;
+            builder.neighbours(&center, ContentType::Hieroglyph, hieroglyphs, GraphRelation::Contains, true);
+
+            let sentences = sqlx::query_as::<_, (i32, String)>(
+                "SELECT id, text FROM sentences WHERE text LIKE $1 ORDER BY id LIMIT $2",
+            )
+                .bind(contains_pattern(&simplified))
+                .bind(MAX_NEIGHBOURS)
+                .fetch_all(pool)
+                .await?;
+            builder.neighbours(&center, ContentType::Sentence, sentences, GraphRelation::Example, false);
+        }
+        ContentType::Sentence => {
+            let text: String = sqlx::query_scalar("SELECT text FROM sentences WHERE id = $1")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено"))?;
+            let center = builder.node(ContentType::Sentence, id, text.clone());
+
+            // Слова предложения определяются той же сегментацией, что и при оценке сложности
+            let normalized = normalize_hanzi(&text);
+            let words = sqlx::query_as::<_, (i32, String)>(
+                "SELECT id, simplified FROM words WHERE strpos($1, simplified) > 0",
+            )
+                .bind(&normalized)
+                .fetch_all(pool)
+                .await?;
+            let dictionary: HashSet<String> = words.iter().map(|(_, w)| w.clone()).collect();
+            let tokens: HashSet<String> = segment(&normalized, &dictionary).into_iter().collect();
+            let words = words.into_iter().filter(|(_, w)| tokens.contains(w)).collect();
+            builder.neighbours(&center, ContentType::Word, words, GraphRelation::Example, true);
+        }
+        _ => {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Граф для этого типа контента не поддерживается"));
+        }
+    }
+
+    Ok(builder.graph)
+}
@@ -0,0 +1,361 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::auth;
+use crate::errors::AppError;
+use crate::models::{GroupMember, StudyGroup, StudyGroupPayload};
+use crate::notifications::{self, NotificationKind};
+use crate::streak::{self, StudyDay};
+
+/// Группа небольшая: в ней все знают друг друга.
+pub const MAX_MEMBERS: i64 = 8;
+/// Верхняя граница цели на неделю в очках опыта.
+pub const MAX_WEEKLY_GOAL: i32 = 100_000;
+
+// Неделя начинается в понедельник
+const WEEK_START: &str = "date_trunc('week', NOW())";
+
+#[derive(sqlx::FromRow)]
+struct GroupRow {
+    id: i32,
+    name: String,
+    owner_id: i32,
+    join_code: String,
+    weekly_goal: i32,
+}
+
+/// Проверяет название и цель группы и возвращает название без лишних пробелов.
+pub fn validate(payload: &StudyGroupPayload) -> Result<String, AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название группы не может быть пустым"));
+    }
+    if payload.weekly_goal <= 0 || payload.weekly_goal > MAX_WEEKLY_GOAL {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Цель на неделю должна быть от 1 до {} очков опыта", MAX_WEEKLY_GOAL),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Дни, которые засчитываются группе: в такой день занимались все, кто уже
+/// состоял в группе. День, закрытый чьей-то заморозкой, серию не прерывает,
+/// но и не удлиняет — как в личной серии. На вход — дата вступления
+/// и дни занятий каждого участника.
+pub fn group_days(members: &[(NaiveDate, Vec<StudyDay>)]) -> Vec<StudyDay> {
+    let by_member: Vec<(NaiveDate, BTreeMap<NaiveDate, bool>)> = members
+        .iter()
+        .map(|(joined, days)| (*joined, days.iter().map(|day| (day.day, day.frozen)).collect()))
+        .collect();
+    let candidates: BTreeSet<NaiveDate> = by_member.iter().flat_map(|(_, days)| days.keys().copied()).collect();
+
+    let mut result = Vec::new();
+    for day in candidates {
+        let mut frozen = false;
+        let mut everyone = true;
+        let mut anyone = false;
+        for (joined, days) in &by_member {
+            if *joined > day {
+                continue;
+            }
+            anyone = true;
+            match days.get(&day) {
+                Some(member_frozen) => frozen |= *member_frozen,
+                None => everyone = false,
+            }
+        }
+        if anyone && everyone {
+            result.push(StudyDay { day, frozen });
+        }
+    }
+    result
+}
+
+async fn load(group_id: i32, pool: &PgPool) -> Result<StudyGroup, AppError> {
+    let group = sqlx::query_as::<_, GroupRow>(
+        "SELECT id, name, owner_id, join_code, weekly_goal FROM study_groups WHERE id = $1",
+    )
+        .bind(group_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Группа не найдена"))?;
+
+    let members = sqlx::query_as::<_, GroupMember>(&format!(
+        "SELECT u.id AS user_id, u.nickname,
+                (SELECT COALESCE(SUM(x.amount), 0) FROM xp_awards x
+                 WHERE x.user_id = u.id AND x.awarded_at >= {week})::bigint AS weekly_xp,
+                EXISTS (SELECT 1 FROM study_days s
+                        WHERE s.user_id = u.id AND s.day = CURRENT_DATE AND NOT s.frozen) AS studied_today
+         FROM study_group_members m JOIN users u ON u.id = m.user_id
+         WHERE m.group_id = $1
+         ORDER BY m.joined_at, u.nickname",
+        week = WEEK_START
+    ))
+        .bind(group_id)
+        .fetch_all(pool)
+        .await?;
+
+    let rows: Vec<(i32, NaiveDate, Option<NaiveDate>, Option<bool>)> = sqlx::query_as(
+        "SELECT m.user_id, m.joined_at::date, s.day, s.frozen
+         FROM study_group_members m
+         LEFT JOIN study_days s ON s.user_id = m.user_id
+         WHERE m.group_id = $1
+         ORDER BY m.user_id, s.day",
+    )
+        .bind(group_id)
+        .fetch_all(pool)
+        .await?;
+    let mut member_days: Vec<(i32, NaiveDate, Vec<StudyDay>)> = Vec::new();
+    for (user_id, joined, day, frozen) in rows {
+        if member_days.last().is_none_or(|(last_id, _, _)| *last_id != user_id) {
+            member_days.push((user_id, joined, Vec::new()));
+        }
+        if let (Some(day), Some(frozen), Some((_, _, days))) = (day, frozen, member_days.last_mut()) {
+            days.push(StudyDay { day, frozen });
+        }
+    }
+    let member_days: Vec<(NaiveDate, Vec<StudyDay>)> =
+        member_days.into_iter().map(|(_, joined, days)| (joined, days)).collect();
+
+    let today: NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE").fetch_one(pool).await?;
+    // Заморозок у группы нет: пропуск любого участника прерывает серию
+    let streak = streak::summarize(&group_days(&member_days), today, 0);
+
+    let weekly_xp = members.iter().map(|member| member.weekly_xp).sum();
+    Ok(StudyGroup {
+        id: group.id,
+        name: group.name,
+        owner_id: group.owner_id,
+        join_code: group.join_code,
+        weekly_goal: group.weekly_goal,
+        weekly_xp,
+        goal_met: weekly_xp >= group.weekly_goal as i64,
+        members,
+        streak,
+    })
+}
+
+async fn is_member(group_id: i32, user_id: i32, pool: &PgPool) -> Result<bool, AppError> {
+    let member: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM study_group_members WHERE group_id = $1 AND user_id = $2)",
+    )
+        .bind(group_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(member)
+}
+
+/// Создает группу; создатель становится ее первым участником и владельцем.
+pub async fn create(user_id: i32, payload: &StudyGroupPayload, pool: &PgPool) -> Result<StudyGroup, AppError> {
+    let name = validate(payload)?;
+
+    // Код короче токена сессии, чтобы его было удобно передать друзьям
+    let join_code = auth::random_token()[..12].to_string();
+
+    let mut tx = pool.begin().await?;
+    let group_id: i32 = sqlx::query_scalar(
+        "INSERT INTO study_groups (name, owner_id, join_code, weekly_goal) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+        .bind(&name)
+        .bind(user_id)
+        .bind(&join_code)
+        .bind(payload.weekly_goal)
+        .fetch_one(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO study_group_members (group_id, user_id) VALUES ($1, $2)")
+        .bind(group_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    load(group_id, pool).await
+}
+
+/// Вступление в группу по коду.
+pub async fn join(user_id: i32, code: &str, pool: &PgPool) -> Result<StudyGroup, AppError> {
+    let mut tx = pool.begin().await?;
+    // Блокировка группы не дает параллельным вступлениям превысить лимит
+    let group_id: i32 = sqlx::query_scalar("SELECT id FROM study_groups WHERE join_code = $1 FOR UPDATE")
+        .bind(code.trim())
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Группа с таким кодом не найдена"))?;
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM study_group_members WHERE group_id = $1")
+        .bind(group_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if count >= MAX_MEMBERS {
+        return Err(AppError::new(StatusCode::CONFLICT, &format!("В группе уже {} участников", MAX_MEMBERS))
+            .with_code("group_full"));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO study_group_members (group_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+        .bind(group_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::CONFLICT, "Вы уже в этой группе").with_code("already_member"));
+    }
+    tx.commit().await?;
+
+    load(group_id, pool).await
+}
+
+/// Выход из группы. Уходящий владелец передает группу самому давнему участнику;
+/// группа без участников удаляется.
+pub async fn leave(user_id: i32, group_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let owner_id: i32 = sqlx::query_scalar("SELECT owner_id FROM study_groups WHERE id = $1 FOR UPDATE")
+        .bind(group_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Группа не найдена"))?;
+
+    let result = sqlx::query("DELETE FROM study_group_members WHERE group_id = $1 AND user_id = $2")
+        .bind(group_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Группа не найдена"));
+    }
+
+    if owner_id == user_id {
+        let successor: Option<i32> = sqlx::query_scalar(
+            "SELECT user_id FROM study_group_members WHERE group_id = $1 ORDER BY joined_at LIMIT 1",
+        )
+            .bind(group_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        match successor {
+            Some(successor) => {
+                sqlx::query("UPDATE study_groups SET owner_id = $2 WHERE id = $1")
+                    .bind(group_id)
+                    .bind(successor)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM study_groups WHERE id = $1")
+                    .bind(group_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Меняет название и цель группы. Доступно только владельцу.
+pub async fn update(user_id: i32, group_id: i32, payload: &StudyGroupPayload, pool: &PgPool) -> Result<StudyGroup, AppError> {
+    let name = validate(payload)?;
+    if !is_member(group_id, user_id, pool).await? {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Группа не найдена"));
+    }
+
+    let result = sqlx::query("UPDATE study_groups SET name = $3, weekly_goal = $4 WHERE id = $1 AND owner_id = $2")
+        .bind(group_id)
+        .bind(user_id)
+        .bind(&name)
+        .bind(payload.weekly_goal)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Менять группу может только ее владелец"));
+    }
+    load(group_id, pool).await
+}
+
+/// Группа с прогрессом недели и серией. Чужая группа не отличается от несуществующей.
+pub async fn get(user_id: i32, group_id: i32, pool: &PgPool) -> Result<StudyGroup, AppError> {
+    if !is_member(group_id, user_id, pool).await? {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Группа не найдена"));
+    }
+    load(group_id, pool).await
+}
+
+async fn group_ids(user_id: i32, pool: &PgPool) -> Result<Vec<i32>, AppError> {
+    let ids = sqlx::query_scalar(
+        "SELECT group_id FROM study_group_members WHERE user_id = $1 ORDER BY joined_at",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(ids)
+}
+
+/// Группы пользователя в порядке вступления.
+pub async fn list(user_id: i32, pool: &PgPool) -> Result<Vec<StudyGroup>, AppError> {
+    let mut groups = Vec::new();
+    for group_id in group_ids(user_id, pool).await? {
+        groups.push(load(group_id, pool).await?);
+    }
+    Ok(groups)
+}
+
+async fn notify_members(group: &StudyGroup, kind: NotificationKind, title: &str, body: &str, pool: &PgPool) -> Result<(), AppError> {
+    for member in &group.members {
+        notifications::notify(member.user_id, kind, title, body, pool).await?;
+    }
+    Ok(())
+}
+
+/// Проверяет группы пользователя после его занятия: сообщает участникам, что
+/// цель недели выполнена или что сегодня позанимались все. Каждое сообщение
+/// уходит один раз за неделю или день, даже если занятий потом будет больше.
+pub async fn on_activity(user_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    for group_id in group_ids(user_id, pool).await? {
+        let group = load(group_id, pool).await?;
+
+        if group.goal_met {
+            let first = sqlx::query(&format!(
+                "UPDATE study_groups SET goal_notified_week = {week}::date
+                 WHERE id = $1 AND goal_notified_week IS DISTINCT FROM {week}::date",
+                week = WEEK_START
+            ))
+                .bind(group_id)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0;
+            if first {
+                let body = format!(
+                    "Группа «{}» набрала {} из {} очков опыта за неделю",
+                    group.name, group.weekly_xp, group.weekly_goal
+                );
+                notify_members(&group, NotificationKind::GroupGoal, "Цель группы выполнена", &body, pool).await?;
+            }
+        }
+
+        // В группе из одного человека это сообщение ничего не добавляет к личной серии
+        if group.streak.studied_today && group.members.len() > 1 {
+            let first = sqlx::query(
+                "UPDATE study_groups SET streak_notified_day = CURRENT_DATE
+                 WHERE id = $1 AND streak_notified_day IS DISTINCT FROM CURRENT_DATE",
+            )
+                .bind(group_id)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0;
+            if first {
+                let body = format!(
+                    "Сегодня в группе «{}» позанимались все. Серия группы: {} дн.",
+                    group.name, group.streak.current_days
+                );
+                notify_members(&group, NotificationKind::GroupStreak, "Вся группа в строю", &body, pool).await?;
+            }
+        }
+    }
+    Ok(())
+}
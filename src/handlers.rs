@@ -1,23 +1,87 @@
-use axum::{extract::{State, Path}, http::StatusCode, Json, response::IntoResponse};
+use axum::{
+    extract::{ConnectInfo, Multipart, Request, State, Path, Query, WebSocketUpgrade},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Json,
+    response::{IntoResponse, Response},
+};
 
-use crate::auth;
+use crate::{achievements, anki, announcements, assist, attempts, audio, auth, blueprints, bulk, caching, classes, classroom, compose, config, consent, content, contributions, decks, decomposition, dictionary, difficulty, discussion, doctor, email_verification, events, export, ext, friends, gamification, gradebook, groups, graph, hidden, hieroglyph_csv, hsk, hygiene, importer, jobs, jwt_keys, lessons, lockout, matching, media, mining, notifications, password_policy, password_reset, plans, presence, progress, quiz, reader, registration, review_load, search, security_log, sessions, settings, shadowing, snapshots, srs, startup, stats, streak, strokes, study_list, sync, test_builder, typing, worksheets};
+use crate::authz::{Admin, RoleRequirement};
+use crate::config::RegistrationMode;
+use crate::events::Event;
+use crate::discussion::Participant;
+use crate::media::MediaKind;
 use crate::models::{
     RegisterPayload, LoginPayload, AuthResponse, RefreshPayload, Claims, User,
     Hieroglyph, CreateHieroglyphPayload, UserRole, UserProgress, MarkLearnedPayload,
-    Achievement, UserAchievementDetails, Test, TestItem, TestDetails, TestSubmissionPayload, TestResultResponse
+    Achievement, UserAchievementDetails, Test, TestItem, TestDetails, TestSubmissionPayload, TestResultResponse,
+    TestPayload, TestItemsPayload, QuestionReview, TestResultDetails,
+    RecomputePayload, RecomputeKind, JobStatus, ShareLinkResponse, SharedResult,
+    CreateInvitesPayload, InviteCode, RosterRow, RosterRowResult,
+    Class, CreateClassPayload, AddClassMemberPayload, ClassContentPayload, CreateClassTestPayload,
+    Assignment, CreateAssignmentPayload, Notification,
+    Sentence, CreateSentencePayload, ShadowingAttempt, ShadowingResult,
+    ContentType, DictationPrompt, DictationAnswerPayload, DictationResult, WorksheetQuery,
+    StudyPlan, CreatePlanPayload, StudyPlanResponse,
+    StudyTimePayload, StudyTimeQuery, StudyTimeEntry, CriteriaDryRunPayload, CriteriaDryRunResponse,
+    NotificationPreference, PresenceInfo, PresenceSettingsPayload,
+    Contribution, ContributionPayload, ContributionsQuery,
+    LookupQuery, LookupResponse, GradeSentencePayload, SentenceGrade, SetComponentsPayload,
+    PrerequisitesResponse, ContentGraph, BulkPayload, BulkReport, Announcement, AnnouncementAudience,
+    CreateAnnouncementPayload, ExportFormat, ExportQuery, UserSettings, UpdateSettingsPayload,
+    FinishOnboardingPayload, OnboardingState, ClientConfig, HygieneStats, MessageResponse, ReviewItem,
+    ReviewQueueQuery, ReviewAnswerPayload, ReviewUndoResponse, KnownStatus, StudyTextQuery, ApiKey,
+    CreateApiKeyPayload, CreatedApiKey, ExtLookupResponse, Word, WordPayload, Phrase, PhrasePayload,
+    Lesson, LessonDetails, LessonPayload, LessonCompletion, SetLessonItemsPayload, Deck, DeckItem,
+    DeckItemsQuery, DeckManifest, GrammarRule, GrammarRulePayload, SyncPullQuery, SyncPullResponse,
+    SyncPushPayload, SyncPushResponse, Pagination, Paginated, HieroglyphListQuery, HieroglyphSort, SearchQuery,
+    SearchResponse, ContentCounts, InstanceStatus, SetHskLevelsPayload, HskLevelProgress,
+    SelfCheckReport, TagTestItemPayload, BlueprintPayload, TestBlueprint, GenerateQuizPayload,
+    HiddenItem, HiddenItemPayload, AchievementUnlocks, ReviewAnswerResponse, StreakSummary,
+    StatsHistoryQuery, StatSnapshot, ProfileResponse, FriendRequestPayload, FriendRequest, FriendRequests,
+    Friend, FriendProfile, ForgotPasswordPayload, ResetPasswordPayload, PassagePayload, PassageSummary,
+    PassageTiming, VerifyEmailPayload, StudyGroupPayload, JoinGroupPayload, StudyGroup, CursorPagination, CursorPage,
+    ResultHistoryItem, ReviewForecast, ReviewLoadSettings, ForecastQuery, LoginAttempt,
+    ImportQuery, ImportStatus, SecurityEvent, SessionInfo,
+    LegalDocument, PublishLegalDocumentPayload, ConsentPayload, ConsentStatus,
+    MinePayload, MiningResult, CreateMinedDeckPayload, MinedDeck, MinedDeckDetails,
+    TypingPrompt, TypingDrillQuery, TypingRunPayload, TypingRunResult, TypingStats,
+    StatsBreakdownQuery, StatsBreakdown, JwkSet,
+    AnswerPayload, TestAttempt, TestAttemptState, GrantPauseAllowancePayload, AttemptEvent,
+    AnkiExportQuery, CompositionPrompt, CompositionAnswerPayload, CompositionResult,
+    LessonNote, LessonNotePayload, LessonPost, LessonPostPayload,
+    StrokeDataPayload, HieroglyphStrokes, AudioFile, UserSummary, UserSearchQuery,
+    ContentAssistQuery, ContentAssist
 };
 use crate::errors::AppError;
+use crate::ext::ApiKeyUser;
 use crate::AppState;
+use std::net::SocketAddr;
 
 
 // --- Обработчики аутентификации ---
 
 /// Обработчик регистрации нового пользователя.
+/// В зависимости от `REGISTRATION_MODE` регистрация открыта, требует код приглашения или отключена.
+/// Дополнительно проверяются домен почты и суточный лимит регистраций с одного IP.
 #[axum::debug_handler]
 pub async fn register_handler(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterPayload>,
 ) -> Result<impl IntoResponse, AppError> {
+    let mode = config::registration_mode();
+    if mode == RegistrationMode::Closed {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Регистрация отключена, обратитесь к администратору")
+            .with_code("registration_closed"));
+    }
+    let email = registration::check_email(payload.email.as_deref(), &config::email_domain_rules())?;
+    if email.is_none() && config::require_verified_email() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Для регистрации нужна почта").with_code("email_required"));
+    }
+    password_policy::ensure_valid(&payload.password, Some(&payload.nickname))?;
+
     // Проверяем, существует ли пользователь с таким никнеймом
     let existing_user = sqlx::query("SELECT id FROM users WHERE nickname = $1")
         .bind(&payload.nickname)
@@ -28,39 +92,113 @@ pub async fn register_handler(
         return Err(AppError::new(StatusCode::CONFLICT, "Пользователь с таким никнеймом уже существует"));
     }
 
+    if let Some(email) = &email {
+        let taken: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE lower(email) = $1)")
+            .bind(email)
+            .fetch_one(&state.db_pool)
+            .await?;
+        if taken {
+            return Err(AppError::new(StatusCode::CONFLICT, "Эта почта уже используется").with_code("email_taken"));
+        }
+    }
+
     // Хешируем пароль
     let hashed_password = auth::hash_password(&payload.password)?;
 
+    let mut tx = state.db_pool.begin().await?;
+    let ip = registration::client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    registration::count_ip(ip, &mut tx).await?;
+
     // Сохраняем нового пользователя в БД
-    sqlx::query("INSERT INTO users (nickname, password_hash) VALUES ($1, $2)")
+    let user_id: i32 = sqlx::query_scalar(
+        "INSERT INTO users (nickname, password_hash, email) VALUES ($1, $2, $3) RETURNING id",
+    )
         .bind(&payload.nickname)
         .bind(&hashed_password)
-        .execute(&state.db_pool)
+        .bind(&email)
+        .fetch_one(&mut *tx)
         .await?;
 
-    Ok((StatusCode::CREATED, "Пользователь успешно зарегистрирован"))
+    // В режиме приглашений код помечается использованным в той же транзакции
+    if mode == RegistrationMode::Invite {
+        let code = payload.invite_code.as_deref().unwrap_or_default();
+        let result = sqlx::query(
+            "UPDATE invite_codes SET used_by = $1, used_at = NOW()
+             WHERE code = $2 AND used_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())",
+        )
+            .bind(user_id)
+            .bind(code)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::new(StatusCode::FORBIDDEN, "Недействительный код приглашения")
+                .with_code("invalid_invite_code"));
+        }
+    }
+
+    tx.commit().await?;
+
+    if email.is_none() {
+        return Ok((StatusCode::CREATED, Json(MessageResponse::new("Пользователь успешно зарегистрирован"))));
+    }
+    // Аккаунт уже создан: если письмо не ушло, код можно запросить повторно
+    if let Err(e) = email_verification::send(user_id, &state.db_pool).await {
+        tracing::warn!("Не удалось отправить код подтверждения пользователю {}: {}", user_id, e.message());
+    }
+    Ok((
+        StatusCode::CREATED,
+        Json(MessageResponse::new("Пользователь зарегистрирован, на почту отправлен код подтверждения")),
+    ))
 }
 
 /// Обработчик входа пользователя.
 #[axum::debug_handler]
 pub async fn login_handler(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginPayload>,
 ) -> Result<Json<AuthResponse>, AppError> {
+    let ip = registration::client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+
     // Ищем пользователя по никнейму
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE nickname = $1")
         .bind(&payload.nickname)
         .fetch_optional(&state.db_pool)
-        .await?
-        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Неверный никнейм или пароль"))?;
+        .await?;
+    let Some(user) = user else {
+        lockout::record(None, &payload.nickname, ip, lockout::Outcome::UnknownUser, &state.db_pool).await?;
+        return Err(AppError::new(StatusCode::UNAUTHORIZED, "Неверный никнейм или пароль"));
+    };
+
+    // Во время блокировки пароль не проверяется вовсе
+    if let Err(e) = lockout::ensure_unlocked(&user, chrono::Utc::now()) {
+        lockout::record(Some(user.id), &user.nickname, ip, lockout::Outcome::Locked, &state.db_pool).await?;
+        return Err(e);
+    }
 
     // Проверяем пароль
     if !auth::verify_password(&payload.password, &user.password_hash)? {
+        if let Some(until) = lockout::register_failure(&user, ip, &state.db_pool).await? {
+            return Err(lockout::locked_error(until, chrono::Utc::now()));
+        }
         return Err(AppError::new(StatusCode::UNAUTHORIZED, "Неверный никнейм или пароль"));
     }
+    lockout::record(Some(user.id), &user.nickname, ip, lockout::Outcome::Success, &state.db_pool).await?;
+
+    // Старые хеши (bcrypt или прежние параметры Argon2) заменяются, пока известен пароль
+    if auth::needs_rehash(&user.password_hash) {
+        auth::rehash_password(user.id, &payload.password, &user.password_hash, &state.db_pool).await;
+    }
 
     // Генерируем access и refresh токены, используя пул соединений
-    let tokens = auth::generate_tokens(&user.id, &state.db_pool).await?;
+    let session = auth::NewSession {
+        client: auth::ClientInfo::from_request(payload.device_id.as_deref(), &headers, ip),
+        ..Default::default()
+    };
+    let tokens = auth::generate_tokens(&user.id, &session, &state.db_pool).await?;
+    events::publish(Event::Login { user_id: user.id }, &state.db_pool);
 
     Ok(Json(tokens))
 }
@@ -68,29 +206,352 @@ pub async fn login_handler(
 /// Обработчик обновления токенов.
 pub async fn refresh_handler(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<RefreshPayload>,
 ) -> Result<Json<AuthResponse>, AppError> {
-    let tokens = auth::refresh_access_token(&payload.refresh_token, &state.db_pool).await?;
+    let ip = registration::client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    let client = auth::ClientInfo::from_request(payload.device_id.as_deref(), &headers, ip);
+    let tokens = auth::refresh_access_token(&payload.refresh_token, client, &state.db_pool).await?;
     Ok(Json(tokens))
 }
 
+/// Открытые ключи проверки access-токенов для других сервисов.
+/// Симметричные ключи сюда не попадают.
+pub async fn jwks_handler() -> Result<Json<JwkSet>, AppError> {
+    Ok(Json(jwt_keys::keys()?.jwks()))
+}
+
 /// Обработчик выхода из системы.
 pub async fn logout_handler(
     State(state): State<AppState>,
     Json(payload): Json<RefreshPayload>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Удаляем refresh токен из базы
-    sqlx::query("DELETE FROM refresh_sessions WHERE refresh_token = $1")
+    // Удаляем refresh токен из базы вместе с замененными токенами того же семейства
+    sqlx::query(
+        "DELETE FROM refresh_sessions
+         WHERE family_id = (SELECT family_id FROM refresh_sessions WHERE refresh_token = $1)",
+    )
         .bind(&payload.refresh_token)
         .execute(&state.db_pool)
         .await?;
 
-    Ok((StatusCode::OK, "Вы успешно вышли из системы"))
+    Ok(Json(MessageResponse::new("Вы успешно вышли из системы")))
+}
+
+/// Активные сессии текущего пользователя.
+pub async fn get_my_sessions_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<SessionInfo>>, AppError> {
+    let sessions = sessions::list(claims.user_id, claims.session_id.as_deref(), &state.db_pool).await?;
+    Ok(Json(sessions))
+}
+
+/// Завершение одной сессии текущего пользователя.
+pub async fn revoke_session_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    sessions::revoke(claims.user_id, &id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Выход на всех устройствах.
+pub async fn revoke_all_sessions_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, AppError> {
+    sessions::revoke_all(claims.user_id, &state.db_pool).await?;
+    Ok(Json(MessageResponse::new("Вы вышли на всех устройствах")))
+}
+
+/// Действующие версии условий использования и политики конфиденциальности.
+pub async fn get_legal_documents_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LegalDocument>>, AppError> {
+    Ok(Json(consent::current_documents(&state.db_pool).await?))
+}
+
+/// Какие документы текущий пользователь еще не принял.
+pub async fn get_my_consent_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<ConsentStatus>, AppError> {
+    Ok(Json(consent::status(claims.user_id, &state.db_pool).await?))
+}
+
+/// Принятие документов текущим пользователем.
+pub async fn accept_consent_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(payload): Json<ConsentPayload>,
+) -> Result<Json<ConsentStatus>, AppError> {
+    let ip = registration::client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    let status = consent::accept(claims.user_id, &payload.document_ids, ip, &state.db_pool).await?;
+    Ok(Json(status))
+}
+
+/// Публикация новой версии документа (только для админов).
+pub async fn publish_legal_document_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<PublishLegalDocumentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let document = consent::publish(&payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(document)))
+}
+
+/// Запрос сброса пароля: на почту уходит одноразовый код.
+/// Ответ одинаковый, даже если такой почты нет.
+pub async fn forgot_password_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    password_reset::request(&payload.email, &state.db_pool).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(MessageResponse::new("Если эта почта зарегистрирована, на нее отправлен код для сброса пароля")),
+    ))
+}
+
+/// Смена пароля по коду из письма. Все сессии пользователя завершаются.
+pub async fn reset_password_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordPayload>,
+) -> Result<Json<MessageResponse>, AppError> {
+    password_reset::reset(&payload.token, &payload.new_password, &state.db_pool).await?;
+    Ok(Json(MessageResponse::new("Пароль изменен, войдите с новым паролем")))
+}
+
+/// Подтверждение почты кодом из письма.
+pub async fn verify_email_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailPayload>,
+) -> Result<Json<MessageResponse>, AppError> {
+    email_verification::verify(&payload.token, &state.db_pool).await?;
+    Ok(Json(MessageResponse::new("Почта подтверждена")))
+}
+
+/// Повторная отправка кода подтверждения на почту текущего пользователя.
+pub async fn resend_verification_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, AppError> {
+    email_verification::send(claims.user_id, &state.db_pool).await?;
+    Ok((StatusCode::ACCEPTED, Json(MessageResponse::new("Если почта еще не подтверждена, на нее отправлен новый код"))))
+}
+
+/// Максимум пользователей в ответе поиска.
+const MAX_USER_SEARCH: i64 = 100;
+
+/// Поиск пользователей по части никнейма (только для админов).
+/// Без `q` возвращает первых пользователей по алфавиту.
+pub async fn search_users_handler(
+    State(state): State<AppState>,
+    Query(query): Query<UserSearchQuery>,
+) -> Result<Json<Vec<UserSummary>>, AppError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_USER_SEARCH);
+    let q = query.q.unwrap_or_default().trim().to_string();
+
+    let users = sqlx::query_as::<_, UserSummary>(
+        "SELECT id, nickname, role, is_banned, disabled_at, email, email_verified_at FROM users
+         WHERE lower(nickname) LIKE $1
+         ORDER BY nickname
+         LIMIT $2",
+    )
+        .bind(search::contains_pattern(&q.to_lowercase()))
+        .bind(limit)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(users))
+}
+
+/// Блокировка пользователя (только для админов).
+/// Все refresh-сессии пользователя отзываются сразу, а действующие access-токены
+/// перестают приниматься экстрактором `Claims`.
+pub async fn ban_user_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = state.db_pool.begin().await?;
+
+    let result = sqlx::query("UPDATE users SET is_banned = TRUE, disabled_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"));
+    }
+
+    auth::revoke_all_sessions(id, &mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Журнал попыток входа пользователя, новые первыми (только для админов).
+pub async fn get_login_attempts_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    pagination: CursorPagination,
+) -> Result<Json<CursorPage<LoginAttempt>>, AppError> {
+    Ok(Json(lockout::attempts(id, pagination, &state.db_pool).await?))
+}
+
+/// Журнал безопасности пользователя, новые первыми (только для админов).
+pub async fn get_security_events_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    pagination: CursorPagination,
+) -> Result<Json<CursorPage<SecurityEvent>>, AppError> {
+    Ok(Json(security_log::events(id, pagination, &state.db_pool).await?))
+}
+
+/// Снятие блокировки с пользователя (только для админов). Снимает и временную блокировку входа.
+pub async fn unban_user_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = sqlx::query("UPDATE users SET is_banned = FALSE, disabled_at = NULL, locked_until = NULL WHERE id = $1")
+        .bind(id)
+        .execute(&state.db_pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Создание кодов приглашений (только для админов).
+pub async fn create_invites_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateInvitesPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.count == 0 || payload.count > 500 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Количество кодов должно быть от 1 до 500"));
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+    let mut invites = Vec::with_capacity(payload.count as usize);
+
+    for _ in 0..payload.count {
+        // Код короче токена сессии, чтобы его было удобно передать ученику
+        let code = auth::random_token()[..12].to_string();
+        let invite = sqlx::query_as::<_, InviteCode>(
+            "INSERT INTO invite_codes (code, created_by, expires_at)
+             VALUES ($1, $2, NOW() + make_interval(days => $3::int))
+             RETURNING code, created_at, expires_at",
+        )
+            .bind(code)
+            .bind(claims.user_id)
+            .bind(payload.expires_in_days)
+            .fetch_one(&mut *tx)
+            .await?;
+        invites.push(invite);
+    }
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(invites)))
+}
+
+/// Массовое создание пользователей из CSV-списка (только для админов).
+/// Ожидает CSV с заголовком `nickname,password[,role]` в теле запроса.
+pub async fn import_roster_handler(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<Json<Vec<RosterRowResult>>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+    let mut results = Vec::new();
+
+    for (index, record) in reader.deserialize::<RosterRow>().enumerate() {
+        // Строка 1 — заголовок
+        let line = index + 2;
+        let row = match record {
+            Ok(row) => row,
+            Err(e) => {
+                results.push(RosterRowResult { line, nickname: None, created: false, error: Some(e.to_string()) });
+                continue;
+            }
+        };
+
+        if row.nickname.is_empty() || row.password.is_empty() {
+            results.push(RosterRowResult {
+                line,
+                nickname: Some(row.nickname),
+                created: false,
+                error: Some("Пустой никнейм или пароль".to_string()),
+            });
+            continue;
+        }
+
+        let weaknesses = password_policy::check(&row.password, Some(&row.nickname), &config::password_policy());
+        if !weaknesses.is_empty() {
+            let messages: Vec<String> = weaknesses.into_iter().map(|error| error.message).collect();
+            results.push(RosterRowResult {
+                line,
+                nickname: Some(row.nickname),
+                created: false,
+                error: Some(messages.join("; ")),
+            });
+            continue;
+        }
+
+        let hashed_password = auth::hash_password(&row.password)?;
+        let inserted = sqlx::query(
+            "INSERT INTO users (nickname, password_hash, role) VALUES ($1, $2, $3)
+             ON CONFLICT (nickname) DO NOTHING",
+        )
+            .bind(&row.nickname)
+            .bind(&hashed_password)
+            .bind(row.role.unwrap_or(UserRole::User))
+            .execute(&state.db_pool)
+            .await?;
+
+        let created = inserted.rows_affected() == 1;
+        results.push(RosterRowResult {
+            line,
+            nickname: Some(row.nickname),
+            created,
+            error: (!created).then(|| "Пользователь с таким никнеймом уже существует".to_string()),
+        });
+    }
+
+    Ok(Json(results))
 }
 
 /// Пример защищенного обработчика.
-pub async fn protected_handler(claims: Claims) -> String {
-    format!("Привет, user_id: {}. Твоя роль: {}. Это защищенный ресурс.", claims.user_id, claims.role)
+pub async fn protected_handler(claims: Claims) -> Json<MessageResponse> {
+    Json(MessageResponse::new(&format!(
+        "Привет, user_id: {}. Твоя роль: {}. Это защищенный ресурс.",
+        claims.user_id, claims.role
+    )))
+}
+
+/// Текущий пользователь; по роли клиент решает, показывать ли инструменты администратора.
+pub async fn get_me_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<UserSummary>, AppError> {
+    let user = sqlx::query_as::<_, UserSummary>(
+        "SELECT id, nickname, role, is_banned, disabled_at, email, email_verified_at FROM users WHERE id = $1",
+    )
+        .bind(claims.user_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"))?;
+
+    Ok(Json(user))
 }
 
 // --- Обработчики для иероглифов ---
@@ -98,14 +559,8 @@ pub async fn protected_handler(claims: Claims) -> String {
 /// Создание нового иероглифа (только для админов).
 pub async fn create_hieroglyph_handler(
     State(state): State<AppState>,
-    claims: Claims, // Экстрактор для проверки аутентификации и роли
     Json(payload): Json<CreateHieroglyphPayload>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Проверяем, что у пользователя роль админа
-    if claims.role != UserRole::Admin {
-        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
-    }
-
     // Вставляем новый иероглиф в базу данных
     let hieroglyph = sqlx::query_as::<_, Hieroglyph>(
         "INSERT INTO hieroglyphs (character, pinyin, translation, example) VALUES ($1, $2, $3, $4) RETURNING *",
@@ -120,15 +575,134 @@ pub async fn create_hieroglyph_handler(
     Ok((StatusCode::CREATED, Json(hieroglyph)))
 }
 
-/// Получение списка всех иероглифов.
+/// Загрузка иллюстрации к иероглифу (только для админов, multipart-поле `image`).
+pub async fn upload_hieroglyph_image_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<Json<Hieroglyph>, AppError> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Некорректные данные формы"))?
+    {
+        if field.name() != Some("image") {
+            continue;
+        }
+        let mime_type = field.content_type().unwrap_or_default().to_string();
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Не удалось прочитать файл"))?;
+
+        let pool = &state.db_pool;
+        let hieroglyph = media::store_attached(MediaKind::Image, &mime_type, &bytes, pool, |media_id| async move {
+            sqlx::query_as::<_, Hieroglyph>("UPDATE hieroglyphs SET image_media_id = $1 WHERE id = $2 RETURNING *")
+                .bind(media_id)
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Иероглиф не найден"))
+        })
+            .await?;
+        return Ok(Json(hieroglyph));
+    }
+
+    Err(AppError::new(StatusCode::BAD_REQUEST, "Не передано поле image"))
+}
+
+/// Подсказки для формы создания контента (только для админов): пиньинь и перевод
+/// из словаря, разбиение на слова и уже существующие элементы с тем же текстом.
+pub async fn content_assist_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ContentAssistQuery>,
+) -> Result<Json<ContentAssist>, AppError> {
+    let assist = assist::assist(&query.text, &state.db_pool).await?;
+    Ok(Json(assist))
+}
+
+/// Список иероглифов постранично, с сортировкой и фильтрами по пиньинь, уровню HSK и тегу.
 pub async fn get_hieroglyphs_handler(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Hieroglyph>>, AppError> {
-    let hieroglyphs = sqlx::query_as::<_, Hieroglyph>("SELECT * FROM hieroglyphs")
+    pagination: Pagination,
+    Query(query): Query<HieroglyphListQuery>,
+) -> Result<Json<Paginated<Hieroglyph>>, AppError> {
+    let sort_column = match query.sort_by {
+        HieroglyphSort::Id => "id",
+        HieroglyphSort::Character => "character",
+        HieroglyphSort::Pinyin => "pinyin",
+        HieroglyphSort::Strokes => "total_strokes",
+        HieroglyphSort::HskLevel => "hsk_level",
+    };
+    // Поиск по началу пиньинь: спецсимволы LIKE во вводе экранируем
+    let pinyin_prefix = query.pinyin.as_deref().map(str::trim).filter(|p| !p.is_empty()).map(|p| {
+        p.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+    });
+    let filter = "WHERE ($1::text IS NULL OR lower(pinyin) LIKE $1 || '%')
+                    AND ($2::smallint IS NULL OR hsk_level = $2)
+                    AND ($3::text IS NULL OR $3 = ANY(tags))";
+
+    let total_count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM hieroglyphs {}", filter))
+        .bind(&pinyin_prefix)
+        .bind(query.hsk_level)
+        .bind(&query.tag)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let hieroglyphs = sqlx::query_as::<_, Hieroglyph>(&format!(
+        "SELECT * FROM hieroglyphs {} ORDER BY {} {} NULLS LAST, id LIMIT $4 OFFSET $5",
+        filter,
+        sort_column,
+        query.order.as_sql(),
+    ))
+        .bind(&pinyin_prefix)
+        .bind(query.hsk_level)
+        .bind(&query.tag)
+        .bind(pagination.per_page)
+        .bind(pagination.offset())
         .fetch_all(&state.db_pool)
         .await?;
 
-    Ok(Json(hieroglyphs))
+    Ok(Json(Paginated::new(hieroglyphs, pagination, total_count)))
+}
+
+/// Выгрузка всех иероглифов в CSV (только для админов).
+pub async fn export_hieroglyphs_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let csv = hieroglyph_csv::export(&state.db_pool).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"hieroglyphs.csv\""),
+        ],
+        csv,
+    ))
+}
+
+/// Задать компоненты иероглифа (только для администраторов).
+pub async fn set_hieroglyph_components_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payload): Json<SetComponentsPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    decomposition::set_components(id, &payload.components, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Невыученные компоненты, которые стоит изучить перед иероглифом.
+pub async fn get_prerequisites_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<PrerequisitesResponse>, AppError> {
+    let prerequisites = decomposition::unknown_prerequisites(claims.user_id, id, &state.db_pool).await?;
+
+    Ok(Json(PrerequisitesResponse {
+        hieroglyph_id: id,
+        prerequisites,
+        enforced: config::components_first_enforced(),
+    }))
 }
 
 /// Получение одного иероглифа по ID.
@@ -145,163 +719,3050 @@ pub async fn get_hieroglyph_by_id_handler(
     Ok(Json(hieroglyph))
 }
 
-// --- Обработчики прогресса пользователя ---
+/// Порядок черт иероглифа для анимации написания.
+pub async fn get_hieroglyph_strokes_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<HieroglyphStrokes>, AppError> {
+    let strokes = strokes::get(id, &state.db_pool).await?;
+    Ok(Json(strokes))
+}
 
-/// Отметить элемент контента как выученный.
-pub async fn mark_learned_handler(
+/// Сохранение порядка черт иероглифа (только для администраторов).
+pub async fn set_hieroglyph_strokes_handler(
     State(state): State<AppState>,
-    claims: Claims,
-    Json(payload): Json<MarkLearnedPayload>,
+    Path(id): Path<i32>,
+    Json(payload): Json<StrokeDataPayload>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Используем INSERT ... ON CONFLICT DO UPDATE для атомарного добавления/обновления прогресса
-    // Это гарантирует, что не будет дубликатов, и триггер сработает корректно
-    let query = "
-        INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at)
-        VALUES ($1, $2, $3, TRUE, NOW())
-        ON CONFLICT (user_id, content_type, content_id) DO UPDATE
-        SET is_learned = TRUE, learned_at = NOW()
-    ";
-
-    sqlx::query(query)
-        .bind(claims.user_id)
-        .bind(payload.content_type)
-        .bind(payload.content_id)
-        .execute(&state.db_pool)
-        .await?;
-
-    Ok(StatusCode::OK)
+    strokes::save(id, &payload, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
-/// Получить прогресс текущего пользователя.
-pub async fn get_my_progress_handler(
+// --- Обработчики для слов и фраз ---
+
+/// Список всех слов.
+pub async fn get_words_handler(
     State(state): State<AppState>,
-    claims: Claims,
-) -> Result<Json<Vec<UserProgress>>, AppError> {
-    let progress = sqlx::query_as::<_, UserProgress>("SELECT * FROM user_progress WHERE user_id = $1")
-        .bind(claims.user_id)
+) -> Result<Json<Vec<Word>>, AppError> {
+    let words = sqlx::query_as::<_, Word>("SELECT * FROM words ORDER BY hsk_level NULLS LAST, id")
         .fetch_all(&state.db_pool)
         .await?;
 
-    Ok(Json(progress))
+    Ok(Json(words))
 }
 
-// --- Обработчики достижений ---
+/// Получение одного слова по ID.
+pub async fn get_word_by_id_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Word>, AppError> {
+    let word = sqlx::query_as::<_, Word>("SELECT * FROM words WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Слово не найдено"))?;
 
-/// Получить список всех возможных достижений
-pub async fn get_all_achievements_handler(
+    Ok(Json(word))
+}
+
+/// Создание слова (только для администраторов).
+pub async fn create_word_handler(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Achievement>>, AppError> {
-    let achievements = sqlx::query_as::<_, Achievement>("SELECT * FROM achievements")
-        .fetch_all(&state.db_pool)
-        .await?;
+    Json(payload): Json<WordPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let word = sqlx::query_as::<_, Word>(
+        "INSERT INTO words (simplified, pinyin, translation, hsk_level, tags) VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (simplified) DO NOTHING RETURNING *",
+    )
+        .bind(payload.simplified.trim())
+        .bind(payload.pinyin)
+        .bind(payload.translation)
+        .bind(payload.hsk_level)
+        .bind(payload.tags)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::CONFLICT, "Такое слово уже существует"))?;
 
-    Ok(Json(achievements))
+    Ok((StatusCode::CREATED, Json(word)))
 }
 
-/// Получить список достижений текущего пользователя
-pub async fn get_my_achievements_handler(
+/// Изменение слова (только для администраторов).
+pub async fn update_word_handler(
     State(state): State<AppState>,
-    claims: Claims,
-) -> Result<Json<Vec<UserAchievementDetails>>, AppError> {
-    let my_achievements = sqlx::query_as::<_, UserAchievementDetails>(
-        "SELECT a.id, a.name, a.description, a.icon, ua.achieved_at
-         FROM achievements a
-         JOIN user_achievements ua ON a.id = ua.achievement_id
-         WHERE ua.user_id = $1"
+    Path(id): Path<i32>,
+    Json(payload): Json<WordPayload>,
+) -> Result<Json<Word>, AppError> {
+    let word = sqlx::query_as::<_, Word>(
+        "UPDATE words SET simplified = $2, pinyin = $3, translation = $4, hsk_level = $5, tags = $6
+         WHERE id = $1 RETURNING *",
     )
-        .bind(claims.user_id)
-        .fetch_all(&state.db_pool)
-        .await?;
+        .bind(id)
+        .bind(payload.simplified.trim())
+        .bind(payload.pinyin)
+        .bind(payload.translation)
+        .bind(payload.hsk_level)
+        .bind(payload.tags)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Слово не найдено"))?;
 
-    Ok(Json(my_achievements))
+    Ok(Json(word))
 }
 
-// --- Обработчики тестов ---
+/// Удаление слова вместе с прогрессом по нему (только для администраторов).
+pub async fn delete_word_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    content::delete(ContentType::Word, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
 
-/// Получить список всех тестов
-pub async fn get_all_tests_handler(
+/// Список всех фраз.
+pub async fn get_phrases_handler(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Test>>, AppError> {
-    let tests = sqlx::query_as::<_, Test>("SELECT * FROM tests")
+) -> Result<Json<Vec<Phrase>>, AppError> {
+    let phrases = sqlx::query_as::<_, Phrase>("SELECT * FROM phrases ORDER BY hsk_level NULLS LAST, id")
         .fetch_all(&state.db_pool)
         .await?;
-    Ok(Json(tests))
+
+    Ok(Json(phrases))
 }
 
-/// Получить детальную информацию о тесте, включая вопросы
-pub async fn get_test_details_handler(
+/// Получение одной фразы по ID.
+pub async fn get_phrase_by_id_handler(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<TestDetails>, AppError> {
-    // Получаем основную информацию о тесте
-    let test = sqlx::query_as::<_, Test>("SELECT * FROM tests WHERE id = $1")
+) -> Result<Json<Phrase>, AppError> {
+    let phrase = sqlx::query_as::<_, Phrase>("SELECT * FROM phrases WHERE id = $1")
         .bind(id)
         .fetch_optional(&state.db_pool)
         .await?
-        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Тест не найден"))?;
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Фраза не найдена"))?;
 
-    // Получаем вопросы к этому тесту
-    // Важно: не отдаем `correct_answer` клиенту
-    let questions = sqlx::query_as::<_, TestItem>(
-        "SELECT id, test_id, question, options FROM test_items WHERE test_id = $1",
+    Ok(Json(phrase))
+}
+
+/// Создание фразы (только для администраторов).
+pub async fn create_phrase_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<PhrasePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let phrase = sqlx::query_as::<_, Phrase>(
+        "INSERT INTO phrases (text, pinyin, translation, usage_note, hsk_level, tags) VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (text) DO NOTHING RETURNING *",
+    )
+        .bind(payload.text.trim())
+        .bind(payload.pinyin)
+        .bind(payload.translation)
+        .bind(payload.usage_note)
+        .bind(payload.hsk_level)
+        .bind(payload.tags)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::CONFLICT, "Такая фраза уже существует"))?;
+
+    Ok((StatusCode::CREATED, Json(phrase)))
+}
+
+/// Изменение фразы (только для администраторов).
+pub async fn update_phrase_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payload): Json<PhrasePayload>,
+) -> Result<Json<Phrase>, AppError> {
+    let phrase = sqlx::query_as::<_, Phrase>(
+        "UPDATE phrases SET text = $2, pinyin = $3, translation = $4, usage_note = $5, hsk_level = $6, tags = $7
+         WHERE id = $1 RETURNING *",
     )
         .bind(id)
+        .bind(payload.text.trim())
+        .bind(payload.pinyin)
+        .bind(payload.translation)
+        .bind(payload.usage_note)
+        .bind(payload.hsk_level)
+        .bind(payload.tags)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Фраза не найдена"))?;
+
+    Ok(Json(phrase))
+}
+
+/// Удаление фразы вместе с прогрессом по ней (только для администраторов).
+pub async fn delete_phrase_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    content::delete(ContentType::Phrase, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики правил грамматики ---
+
+/// Проверяет обязательные поля правила грамматики.
+fn validate_grammar_rule(payload: &GrammarRulePayload) -> Result<(), AppError> {
+    if payload.title.trim().is_empty() || payload.pattern.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название и схема правила не могут быть пустыми"));
+    }
+    if payload.examples.iter().any(|e| e.text.trim().is_empty()) {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Пример не может быть пустым"));
+    }
+    Ok(())
+}
+
+/// Список правил грамматики.
+pub async fn get_grammar_rules_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<GrammarRule>>, AppError> {
+    let rules = sqlx::query_as::<_, GrammarRule>("SELECT * FROM grammar_rules ORDER BY hsk_level NULLS LAST, id")
         .fetch_all(&state.db_pool)
         .await?;
 
-    let test_details = TestDetails {
-        id: test.id,
-        name: test.name,
-        description: test.description,
-        created_at: test.created_at,
-        questions,
-    };
+    Ok(Json(rules))
+}
 
-    Ok(Json(test_details))
+/// Правило грамматики с примерами.
+pub async fn get_grammar_rule_by_id_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<GrammarRule>, AppError> {
+    let rule = sqlx::query_as::<_, GrammarRule>("SELECT * FROM grammar_rules WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Правило не найдено"))?;
+
+    Ok(Json(rule))
 }
 
-/// Принять ответы на тест, проверить и сохранить результат
-pub async fn submit_test_handler(
+/// Создание правила грамматики (только для администраторов).
+pub async fn create_grammar_rule_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<GrammarRulePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    validate_grammar_rule(&payload)?;
+
+    let rule = sqlx::query_as::<_, GrammarRule>(
+        "INSERT INTO grammar_rules (title, explanation, pattern, examples, hsk_level, tags)
+         VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+    )
+        .bind(payload.title.trim())
+        .bind(payload.explanation)
+        .bind(payload.pattern.trim())
+        .bind(sqlx::types::Json(payload.examples))
+        .bind(payload.hsk_level)
+        .bind(payload.tags)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// Изменение правила грамматики (только для администраторов).
+pub async fn update_grammar_rule_handler(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-    claims: Claims,
-    Json(payload): Json<TestSubmissionPayload>,
-) -> Result<Json<TestResultResponse>, AppError> {
-    // Получаем правильные ответы из БД
-    let correct_answers = sqlx::query_as::<_, (i32, String)>(
-        "SELECT id, correct_answer FROM test_items WHERE test_id = $1"
+    Json(payload): Json<GrammarRulePayload>,
+) -> Result<Json<GrammarRule>, AppError> {
+    validate_grammar_rule(&payload)?;
+
+    let rule = sqlx::query_as::<_, GrammarRule>(
+        "UPDATE grammar_rules SET title = $2, explanation = $3, pattern = $4, examples = $5, hsk_level = $6, tags = $7
+         WHERE id = $1 RETURNING *",
     )
         .bind(id)
+        .bind(payload.title.trim())
+        .bind(payload.explanation)
+        .bind(payload.pattern.trim())
+        .bind(sqlx::types::Json(payload.examples))
+        .bind(payload.hsk_level)
+        .bind(payload.tags)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Правило не найдено"))?;
+
+    Ok(Json(rule))
+}
+
+/// Удаление правила грамматики вместе с прогрессом по нему (только для администраторов).
+pub async fn delete_grammar_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    content::delete(ContentType::GrammarRule, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Поиск слова в словаре с обращением к внешнему сервису при необходимости.
+pub async fn lookup_handler(
+    State(state): State<AppState>,
+    Query(query): Query<LookupQuery>,
+) -> Result<Json<LookupResponse>, AppError> {
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > 32 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Запрос должен содержать от 1 до 32 символов"));
+    }
+
+    let response = dictionary::lookup(q, &state.db_pool).await?;
+    Ok(Json(response))
+}
+
+/// Поиск иероглифов, слов и фраз по иероглифам, пиньинь и переводу.
+pub async fn search_handler(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, AppError> {
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > 64 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Запрос должен содержать от 1 до 64 символов"));
+    }
+
+    let response = search::search(q, query.limit.unwrap_or(20), &state.db_pool).await?;
+    Ok(Json(response))
+}
+
+/// Граф связей элемента контента для карты связей в клиенте.
+pub async fn get_content_graph_handler(
+    State(state): State<AppState>,
+    Path((content_type, id)): Path<(String, i32)>,
+) -> Result<Json<ContentGraph>, AppError> {
+    let content_type = graph::parse_node_type(&content_type)
+        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Неизвестный тип контента"))?;
+
+    let graph = graph::build(content_type, id, &state.db_pool).await?;
+    Ok(Json(graph))
+}
+
+/// Максимальная длина текста для оценки сложности.
+const MAX_GRADE_TEXT_LEN: usize = 500;
+
+/// Оценить сложность произвольного предложения для текущего пользователя.
+pub async fn grade_sentence_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<GradeSentencePayload>,
+) -> Result<Json<SentenceGrade>, AppError> {
+    let length = payload.text.chars().count();
+    if length == 0 || length > MAX_GRADE_TEXT_LEN {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Текст должен содержать от 1 до {} символов", MAX_GRADE_TEXT_LEN),
+        ));
+    }
+
+    let grade = difficulty::grade(claims.user_id, &payload.text, &state.db_pool).await?;
+    Ok(Json(grade))
+}
+
+/// Максимальное количество иероглифов в одних прописях.
+const MAX_WORKSHEET_ITEMS: usize = 100;
+
+/// Генерация PDF-прописей для выбранных иероглифов.
+pub async fn get_worksheets_handler(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Query(query): Query<WorksheetQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let ids = query
+        .items
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Некорректный список иероглифов"))?;
+
+    if ids.is_empty() || ids.len() > MAX_WORKSHEET_ITEMS {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Выберите от 1 до 100 иероглифов"));
+    }
+
+    let mut hieroglyphs = sqlx::query_as::<_, Hieroglyph>("SELECT * FROM hieroglyphs WHERE id = ANY($1)")
+        .bind(&ids)
         .fetch_all(&state.db_pool)
         .await?;
 
-    let total_questions = correct_answers.len();
-    if total_questions == 0 {
-        return Err(AppError::new(StatusCode::NOT_FOUND, "Тест не найден или не содержит вопросов"));
+    if hieroglyphs.is_empty() {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Иероглифы не найдены"));
     }
 
-    // Считаем правильные ответы
-    let mut score = 0;
-    for (question_id, correct_answer) in correct_answers {
-        if let Some(user_answer) = payload.answers.iter().find(|a| a.question_id == question_id) {
-            if user_answer.answer == correct_answer {
-                score += 1;
-            }
+    // Сохраняем порядок, в котором иероглифы были выбраны
+    hieroglyphs.sort_by_key(|h| ids.iter().position(|id| *id == h.id));
+
+    let pdf = worksheets::render(hieroglyphs).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"worksheets.pdf\""),
+        ],
+        pdf.as_ref().clone(),
+    ))
+}
+
+// --- Обработчики синхронизации ---
+
+/// Изменения прогресса, заметок и настроек после курсора клиента.
+pub async fn sync_pull_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<SyncPullQuery>,
+) -> Result<Json<SyncPullResponse>, AppError> {
+    let response = sync::pull(claims.user_id, query.since.unwrap_or(0), &state.db_pool).await?;
+    Ok(Json(response))
+}
+
+/// Прием локальных изменений с устройства с объединением конфликтов.
+pub async fn sync_push_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<SyncPushPayload>,
+) -> Result<Json<SyncPushResponse>, AppError> {
+    let report = sync::push(claims.user_id, &payload, &state.db_pool).await?;
+    Ok(Json(report))
+}
+
+// --- Обработчики колод ---
+
+/// Список колод, видимых текущему пользователю.
+pub async fn get_decks_handler(
+    State(state): State<AppState>,
+    claims: Option<Claims>,
+) -> Result<Json<Vec<Deck>>, AppError> {
+    let decks = decks::list(claims.map(|c| c.user_id), &state.db_pool).await?;
+    Ok(Json(decks))
+}
+
+/// Описание колоды для офлайн-загрузки: медиафайлы и контрольная сумма содержимого.
+pub async fn get_deck_manifest_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<DeckManifest>, AppError> {
+    let manifest = decks::manifest(id, &state.db_pool).await?;
+    Ok(Json(manifest))
+}
+
+/// Порция элементов колоды для постраничной загрузки.
+pub async fn get_deck_items_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(query): Query<DeckItemsQuery>,
+) -> Result<Json<Vec<DeckItem>>, AppError> {
+    let offset = query.offset.unwrap_or(0).max(0);
+    let limit = query.limit.unwrap_or(decks::MAX_BATCH_SIZE).clamp(1, decks::MAX_BATCH_SIZE);
+    let items = decks::items(id, offset, limit, &state.db_pool).await?;
+    Ok(Json(items))
+}
+
+// --- Обработчики уроков ---
+
+/// Список уроков с отметкой о прохождении.
+pub async fn get_lessons_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<Lesson>>, AppError> {
+    let lessons = lessons::list(claims.user_id, &state.db_pool).await?;
+    Ok(Json(lessons))
+}
+
+/// Урок с содержимым в порядке изучения.
+pub async fn get_lesson_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<LessonDetails>, AppError> {
+    let lesson = lessons::details(claims.user_id, id, &state.db_pool).await?;
+    Ok(Json(lesson))
+}
+
+/// Отметить урок пройденным вместе со всеми его элементами.
+pub async fn complete_lesson_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<LessonCompletion>, AppError> {
+    let completion = lessons::complete(claims.user_id, id, &state.db_pool).await?;
+    events::publish(
+        Event::LessonCompleted { user_id: claims.user_id, lesson_id: id, newly_learned: completion.newly_learned },
+        &state.db_pool,
+    );
+    Ok(Json(completion))
+}
+
+/// Создание урока (только для администраторов).
+pub async fn create_lesson_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<LessonPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.title.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название урока не может быть пустым"));
+    }
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO lessons (title, description, position) VALUES ($1, $2, $3) RETURNING id",
+    )
+        .bind(payload.title.trim())
+        .bind(payload.description)
+        .bind(payload.position)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let lesson = lessons::details(claims.user_id, id, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(lesson)))
+}
+
+/// Изменение названия, описания и места урока в курсе (только для администраторов).
+pub async fn update_lesson_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payload): Json<LessonPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.title.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название урока не может быть пустым"));
+    }
+
+    let result = sqlx::query("UPDATE lessons SET title = $2, description = $3, position = $4 WHERE id = $1")
+        .bind(id)
+        .bind(payload.title.trim())
+        .bind(payload.description)
+        .bind(payload.position)
+        .execute(&state.db_pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Урок не найден"));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Задать состав урока (только для администраторов).
+pub async fn set_lesson_items_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payload): Json<SetLessonItemsPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    lessons::set_items(id, &payload.items, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Удаление урока (только для администраторов).
+pub async fn delete_lesson_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    content::delete(ContentType::Lesson, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики прогресса пользователя ---
+
+/// Отметить элемент контента как выученный.
+pub async fn mark_learned_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<MarkLearnedPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    // Составной иероглиф можно отметить только после его компонентов, если это включено
+    if payload.content_type == ContentType::Hieroglyph && config::components_first_enforced() {
+        let missing = decomposition::unknown_prerequisites(claims.user_id, payload.content_id, &state.db_pool).await?;
+        if !missing.is_empty() {
+            let characters: Vec<&str> = missing.iter().map(|h| h.character.as_str()).collect();
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                &format!("Сначала выучите компоненты: {}", characters.join(", ")),
+            ).with_code("prerequisites_unknown"));
         }
     }
 
-    // Сохраняем результат в БД
-    sqlx::query("INSERT INTO test_results (user_id, test_id, score) VALUES ($1, $2, $3)")
+    content::ensure_exists(&payload.content_type, payload.content_id, &state.db_pool).await?;
+    let review_load =
+        review_load::check_new_item(claims.user_id, &payload.content_type, payload.content_id, &state.db_pool).await?;
+
+    // Используем INSERT ... ON CONFLICT DO UPDATE для атомарного добавления/обновления прогресса
+    // Это гарантирует, что не будет дубликатов, и триггер сработает корректно
+    let query = "
+        INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at, next_review_at)
+        VALUES ($1, $2, $3, TRUE, NOW(), NOW() + INTERVAL '1 day')
+        ON CONFLICT (user_id, content_type, content_id) DO UPDATE
+        SET is_learned = TRUE, learned_at = NOW(),
+            next_review_at = COALESCE(user_progress.next_review_at, NOW() + INTERVAL '1 day')
+    ";
+
+    sqlx::query(query)
         .bind(claims.user_id)
-        .bind(id)
-        .bind(score as i32)
+        .bind(&payload.content_type)
+        .bind(payload.content_id)
         .execute(&state.db_pool)
         .await?;
 
-    let response = TestResultResponse {
-        score,
-        total_questions,
-    };
+    events::publish(
+        Event::Learned { user_id: claims.user_id, content_type: payload.content_type, content_id: payload.content_id },
+        &state.db_pool,
+    );
+    let new_achievements = achievements::unlock_new(claims.user_id, &state.db_pool).await?;
+    Ok(Json(AchievementUnlocks { new_achievements, review_load }))
+}
 
-    Ok(Json(response))
-}
\ No newline at end of file
+/// Очередь повторения: выученные элементы, срок повторения которых наступил.
+pub async fn get_review_queue_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<ReviewQueueQuery>,
+) -> Result<Json<Vec<ReviewItem>>, AppError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, srs::MAX_QUEUE_SIZE);
+    let queue = srs::due_queue(claims.user_id, limit, &state.db_pool).await?;
+
+    Ok(Json(queue))
+}
+
+/// Прогноз повторений на ближайшие дни и сравнение с порогом нагрузки.
+pub async fn get_review_forecast_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<ForecastQuery>,
+) -> Result<Json<ReviewForecast>, AppError> {
+    let days = query.days.unwrap_or(review_load::DEFAULT_FORECAST_DAYS).clamp(1, review_load::MAX_FORECAST_DAYS);
+    let forecast = review_load::forecast(claims.user_id, days, &state.db_pool).await?;
+    Ok(Json(forecast))
+}
+
+/// Ответ на повторение: пересчитывает расписание по SM-2.
+pub async fn answer_review_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<ReviewAnswerPayload>,
+) -> Result<Json<ReviewAnswerResponse>, AppError> {
+    if payload.quality > 5 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Оценка должна быть от 0 до 5"));
+    }
+
+    let (next, quality) = srs::answer(
+        claims.user_id,
+        payload.content_type.clone(),
+        payload.content_id,
+        payload.quality,
+        payload.latency_ms,
+        &state.db_pool,
+    ).await?;
+
+    events::publish(
+        Event::Reviewed {
+            user_id: claims.user_id,
+            content_type: payload.content_type,
+            content_id: payload.content_id,
+            quality,
+        },
+        &state.db_pool,
+    );
+    let new_achievements = achievements::unlock_new(claims.user_id, &state.db_pool).await?;
+    Ok(Json(ReviewAnswerResponse { state: next, new_achievements }))
+}
+
+/// Отмена последнего ответа на повторение, если он дан недавно (например, по ошибке).
+pub async fn undo_review_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<ReviewUndoResponse>, AppError> {
+    let undone = srs::undo_last(claims.user_id, &state.db_pool).await?;
+    Ok(Json(undone))
+}
+
+/// Знает ли текущий пользователь слово.
+pub async fn get_known_status_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<StudyTextQuery>,
+) -> Result<Json<KnownStatus>, AppError> {
+    let status = study_list::status(claims.user_id, &query.text, &state.db_pool).await?;
+    Ok(Json(status))
+}
+
+/// Добавить слово в список изучения текущего пользователя.
+pub async fn add_to_study_list_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<StudyTextQuery>,
+) -> Result<Json<KnownStatus>, AppError> {
+    let status = study_list::add(claims.user_id, &payload.text, &state.db_pool).await?;
+    Ok(Json(status))
+}
+
+// --- Обработчики разбора текста ---
+
+/// Найти в тексте незнакомые слова уровня пользователя с предложениями-пропусками.
+pub async fn mine_text_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<MinePayload>,
+) -> Result<Json<MiningResult>, AppError> {
+    let result = mining::mine(claims.user_id, &payload.text, payload.max_level, &state.db_pool).await?;
+    Ok(Json(result))
+}
+
+/// Создать личную колоду из незнакомых слов текста.
+pub async fn create_mined_deck_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateMinedDeckPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let deck = mining::create_deck(claims.user_id, &payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(deck)))
+}
+
+/// Личные колоды текущего пользователя.
+pub async fn get_mined_decks_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<MinedDeck>>, AppError> {
+    let decks = mining::list_decks(claims.user_id, &state.db_pool).await?;
+    Ok(Json(decks))
+}
+
+/// Личная колода с карточками-пропусками.
+pub async fn get_mined_deck_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<MinedDeckDetails>, AppError> {
+    let deck = mining::deck(claims.user_id, id, &state.db_pool).await?;
+    Ok(Json(deck))
+}
+
+/// Удалить личную колоду.
+pub async fn delete_mined_deck_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    mining::delete_deck(claims.user_id, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Элементы, скрытые текущим пользователем из занятий.
+pub async fn get_hidden_items_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<HiddenItem>>, AppError> {
+    let items = hidden::list(claims.user_id, &state.db_pool).await?;
+    Ok(Json(items))
+}
+
+/// Больше не показывать элемент в повторениях, тестах и упражнениях.
+pub async fn hide_item_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<HiddenItemPayload>,
+) -> Result<StatusCode, AppError> {
+    hidden::hide(claims.user_id, &payload.content_type, payload.content_id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Вернуть скрытый элемент в занятия.
+pub async fn unhide_item_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<HiddenItemPayload>,
+) -> Result<StatusCode, AppError> {
+    hidden::unhide(claims.user_id, &payload.content_type, payload.content_id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики для расширения браузера ---
+
+/// Поиск слова из всплывающего окна расширения: перевод и статус изучения одним ответом.
+pub async fn ext_lookup_handler(
+    State(state): State<AppState>,
+    key_user: ApiKeyUser,
+    Query(query): Query<LookupQuery>,
+) -> Result<Json<ExtLookupResponse>, AppError> {
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > 32 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Запрос должен содержать от 1 до 32 символов"));
+    }
+
+    let lookup = dictionary::lookup(q, &state.db_pool).await?;
+    let status = study_list::status(key_user.user_id, q, &state.db_pool).await?;
+    let entry = lookup.entries.into_iter().next();
+
+    Ok(Json(ExtLookupResponse {
+        text: status.text,
+        pinyin: entry.as_ref().map(|e| e.pinyin.clone()),
+        translation: entry.map(|e| e.translation),
+        known: status.known,
+        saved: status.in_study_list,
+    }))
+}
+
+/// Сохранить слово в список изучения из расширения.
+pub async fn ext_save_handler(
+    State(state): State<AppState>,
+    key_user: ApiKeyUser,
+    Json(payload): Json<StudyTextQuery>,
+) -> Result<StatusCode, AppError> {
+    study_list::add(key_user.user_id, &payload.text, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Создать ключ API для расширения. Значение ключа возвращается один раз.
+pub async fn create_api_key_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateApiKeyPayload>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), AppError> {
+    let key = ext::create_key(claims.user_id, &payload.name, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+/// Список ключей API текущего пользователя.
+pub async fn get_api_keys_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<ApiKey>>, AppError> {
+    let keys = ext::list_keys(claims.user_id, &state.db_pool).await?;
+    Ok(Json(keys))
+}
+
+/// Отозвать ключ API.
+pub async fn revoke_api_key_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    ext::revoke_key(claims.user_id, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Выгрузить все учебные данные текущего пользователя в JSON или CSV.
+pub async fn export_my_data_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let data = export::collect(claims.user_id, &state.db_pool).await?;
+
+    let (content_type, filename, body) = match query.format {
+        ExportFormat::Json => (
+            "application/json",
+            "mandarin-export.json",
+            serde_json::to_vec_pretty(&data).map_err(|_| {
+                AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Не удалось сформировать выгрузку")
+            })?,
+        ),
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "mandarin-progress.csv", export::progress_csv(&data)?),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ))
+}
+
+/// Колода Anki из иероглифов: `deck=hsk1` ограничивает уровень, `select` — выбор по прогрессу.
+pub async fn export_anki_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<AnkiExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let level = anki::parse_deck(query.deck.as_deref())?;
+    let cards = anki::cards(claims.user_id, level, query.select, &state.db_pool).await?;
+    let tsv = anki::to_tsv(&anki::deck_name(level), &cards);
+
+    let filename = match level {
+        Some(level) => format!("mandarin-hsk{}.txt", level),
+        None => "mandarin.txt".to_string(),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/tab-separated-values; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        tsv,
+    ))
+}
+
+/// Получить прогресс текущего пользователя.
+pub async fn get_my_progress_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<UserProgress>>, AppError> {
+    let progress = sqlx::query_as::<_, UserProgress>("SELECT * FROM user_progress WHERE user_id = $1")
+        .bind(claims.user_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(progress))
+}
+
+// --- Обработчики учебных планов ---
+
+/// Создание учебного плана: выучить колоду к заданной дате.
+pub async fn create_plan_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreatePlanPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.target_date <= chrono::Utc::now().date_naive() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Дата цели должна быть в будущем"));
+    }
+
+    let deck_exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM decks WHERE id = $1)")
+        .bind(payload.deck_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+    if !deck_exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Колода не найдена"));
+    }
+
+    let remaining = plans::remaining_in_deck(claims.user_id, payload.deck_id, &state.db_pool).await?;
+
+    let plan = sqlx::query_as::<_, StudyPlan>(
+        "INSERT INTO study_plans (user_id, deck_id, target_date, initial_remaining)
+         VALUES ($1, $2, $3, $4) RETURNING *",
+    )
+        .bind(claims.user_id)
+        .bind(payload.deck_id)
+        .bind(payload.target_date)
+        .bind(remaining as i32)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let (plan, progress) = plans::with_progress(plan, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(StudyPlanResponse { plan, progress })))
+}
+
+/// Получить активные планы текущего пользователя с прогрессом.
+pub async fn get_my_plans_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<StudyPlanResponse>>, AppError> {
+    let user_plans = sqlx::query_as::<_, StudyPlan>(
+        "SELECT * FROM study_plans WHERE user_id = $1 AND is_active ORDER BY target_date",
+    )
+        .bind(claims.user_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    let mut response = Vec::with_capacity(user_plans.len());
+    for plan in user_plans {
+        let (plan, progress) = plans::with_progress(plan, &state.db_pool).await?;
+        response.push(StudyPlanResponse { plan, progress });
+    }
+
+    Ok(Json(response))
+}
+
+/// Отменить свой учебный план.
+pub async fn delete_plan_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("UPDATE study_plans SET is_active = FALSE WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(claims.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики статистики ---
+
+/// Максимум секунд в одном heartbeat: клиент шлет события часто,
+/// а большие значения означают забытое открытое окно.
+const MAX_HEARTBEAT_SECONDS: i32 = 300;
+
+/// Прием heartbeat-события о времени занятий.
+pub async fn record_study_time_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<StudyTimePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.seconds <= 0 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Длительность должна быть положительной"));
+    }
+    let seconds = payload.seconds.min(MAX_HEARTBEAT_SECONDS);
+
+    sqlx::query(
+        "INSERT INTO study_time (user_id, day, activity, seconds)
+         VALUES ($1, CURRENT_DATE, $2, $3)
+         ON CONFLICT (user_id, day, activity) DO UPDATE
+         SET seconds = study_time.seconds + EXCLUDED.seconds",
+    )
+        .bind(claims.user_id)
+        .bind(payload.activity.as_str())
+        .bind(seconds)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// История итогов текущего пользователя по дням (`from`/`to` включительно).
+pub async fn get_stats_history_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<StatsHistoryQuery>,
+) -> Result<Json<Vec<StatSnapshot>>, AppError> {
+    let (from, to) = snapshots::history_range(query.from, query.to, chrono::Utc::now().date_naive())?;
+    let history = snapshots::history(claims.user_id, from, to, &state.db_pool).await?;
+    Ok(Json(history))
+}
+
+/// Разбивка статистики повторений по уровню HSK, тегу и типу карточки.
+pub async fn get_stats_breakdown_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<StatsBreakdownQuery>,
+) -> Result<Json<StatsBreakdown>, AppError> {
+    let breakdown = stats::breakdown(claims.user_id, query.days, &state.db_pool).await?;
+    Ok(Json(breakdown))
+}
+
+/// Серия дней занятий текущего пользователя.
+pub async fn get_my_streak_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<StreakSummary>, AppError> {
+    let summary = streak::summary(claims.user_id, &state.db_pool).await?;
+    Ok(Json(summary))
+}
+
+/// Профиль текущего пользователя: опыт, уровень и прогресс до следующего уровня.
+pub async fn get_my_profile_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<ProfileResponse>, AppError> {
+    let profile = gamification::profile(claims.user_id, &state.db_pool).await?;
+    Ok(Json(profile))
+}
+
+/// Время занятий текущего пользователя по дням и видам активности.
+pub async fn get_study_time_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<StudyTimeQuery>,
+) -> Result<Json<Vec<StudyTimeEntry>>, AppError> {
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+
+    let entries = sqlx::query_as::<_, StudyTimeEntry>(
+        "SELECT day, activity, seconds / 60.0::float8 AS minutes FROM study_time
+         WHERE user_id = $1 AND day > CURRENT_DATE - $2
+         ORDER BY day, activity",
+    )
+        .bind(claims.user_id)
+        .bind(days)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(entries))
+}
+
+// --- Обработчики достижений ---
+
+/// Получить список всех возможных достижений
+pub async fn get_all_achievements_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Achievement>>, AppError> {
+    let achievements = sqlx::query_as::<_, Achievement>("SELECT * FROM achievements")
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(achievements))
+}
+
+/// Получить список достижений текущего пользователя
+pub async fn get_my_achievements_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<UserAchievementDetails>>, AppError> {
+    let my_achievements = achievements::for_user(claims.user_id, &state.db_pool).await?;
+    Ok(Json(my_achievements))
+}
+
+/// Пробная проверка условия достижения для пользователя без выдачи награды (только для админов).
+/// Позволяет отладить новое условие до сохранения его в базе.
+pub async fn dry_run_criteria_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<CriteriaDryRunPayload>,
+) -> Result<Json<CriteriaDryRunResponse>, AppError> {
+    let criteria: achievements::Criteria = serde_json::from_value(payload.criteria).map_err(|e| {
+        AppError::new(StatusCode::BAD_REQUEST, &format!("Некорректное условие: {}", e))
+    })?;
+
+    let satisfied = achievements::is_satisfied(payload.user_id, &criteria, &state.db_pool).await?;
+    Ok(Json(CriteriaDryRunResponse { satisfied }))
+}
+
+// --- Обработчики тестов ---
+
+/// Публичные колонки вопроса теста (без `correct_answer`), вложения отдаются ссылками.
+const TEST_ITEM_COLUMNS: &str = "id, test_id, question, options,
+    CASE WHEN image_media_id IS NULL THEN NULL ELSE '/api/media/' || image_media_id END AS image_url,
+    CASE WHEN audio_media_id IS NULL THEN NULL ELSE '/api/media/' || audio_media_id END AS audio_url";
+
+/// Получить список тестов, видимых текущему пользователю
+pub async fn get_all_tests_handler(
+    State(state): State<AppState>,
+    claims: Option<Claims>,
+) -> Result<Json<Vec<Test>>, AppError> {
+    if claims.as_ref().is_some_and(|c| Admin::allows(&c.role)) {
+        let tests = sqlx::query_as::<_, Test>("SELECT * FROM tests")
+            .fetch_all(&state.db_pool)
+            .await?;
+        return Ok(Json(tests));
+    }
+
+    let tests = sqlx::query_as::<_, Test>(&format!(
+        "SELECT t.* FROM tests t WHERE {}",
+        classes::TEST_VISIBILITY_CONDITION
+    ))
+        .bind(claims.map(|c| c.user_id))
+        .fetch_all(&state.db_pool)
+        .await?;
+    Ok(Json(tests))
+}
+
+/// Получить детальную информацию о тесте, включая вопросы
+pub async fn get_test_details_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Option<Claims>,
+) -> Result<Json<TestDetails>, AppError> {
+    classes::ensure_test_visible(id, claims.as_ref(), &state.db_pool).await?;
+    let test_details = load_test_details(id, &state.db_pool).await?;
+    Ok(Json(test_details))
+}
+
+/// Тест с вопросами (без правильных ответов).
+async fn load_test_details(id: i32, pool: &sqlx::PgPool) -> Result<TestDetails, AppError> {
+    // Получаем основную информацию о тесте
+    let test = sqlx::query_as::<_, Test>("SELECT * FROM tests WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Тест не найден"))?;
+
+    // Получаем вопросы к этому тесту
+    // Важно: не отдаем `correct_answer` клиенту
+    let questions = sqlx::query_as::<_, TestItem>(&format!(
+        "SELECT {} FROM test_items WHERE test_id = $1 ORDER BY id",
+        TEST_ITEM_COLUMNS
+    ))
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(TestDetails {
+        id: test.id,
+        name: test.name,
+        description: test.description,
+        created_at: test.created_at,
+        time_limit_secs: test.time_limit_secs,
+        questions,
+    })
+}
+
+/// Принять ответы на тест, проверить и сохранить результат
+pub async fn submit_test_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+    Json(payload): Json<TestSubmissionPayload>,
+) -> Result<Json<TestResultResponse>, AppError> {
+    classes::ensure_test_visible(id, Some(&claims), &state.db_pool).await?;
+    attempts::ensure_untimed(id, &state.db_pool).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    let graded = save_test_result(claims.user_id, id, &payload.answers, &mut tx).await?;
+    tx.commit().await?;
+
+    let response = test_submitted(claims.user_id, id, graded, &state.db_pool).await?;
+    Ok(Json(response))
+}
+
+/// Проверенный тест: ID результата, набранные баллы и число вопросов.
+struct GradedTest {
+    result_id: i32,
+    score: usize,
+    total_questions: usize,
+}
+
+/// Проверяет ответы и сохраняет результат с ответами на каждый вопрос.
+/// Выполняется в транзакции вызывающего кода.
+async fn save_test_result(
+    user_id: i32,
+    test_id: i32,
+    submitted: &[AnswerPayload],
+    conn: &mut sqlx::PgConnection,
+) -> Result<GradedTest, AppError> {
+    // Получаем правильные ответы из БД
+    let correct_answers = sqlx::query_as::<_, (i32, String)>(
+        "SELECT id, correct_answer FROM test_items WHERE test_id = $1"
+    )
+        .bind(test_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let total_questions = correct_answers.len();
+    if total_questions == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Тест не найден или не содержит вопросов"));
+    }
+
+    // Проверяем ответы; пропущенные вопросы тоже сохраняются, чтобы попасть в разбор
+    let mut score = 0;
+    let mut answers = Vec::with_capacity(total_questions);
+    for (question_id, correct_answer) in correct_answers {
+        let answer = submitted.iter().find(|a| a.question_id == question_id).map(|a| a.answer.clone());
+        let is_correct = answer.as_ref() == Some(&correct_answer);
+        if is_correct {
+            score += 1;
+        }
+        answers.push((question_id, answer, is_correct));
+    }
+
+    // Сохраняем результат и ответы в БД
+    let result_id: i32 = sqlx::query_scalar(
+        "INSERT INTO test_results (user_id, test_id, score) VALUES ($1, $2, $3) RETURNING id",
+    )
+        .bind(user_id)
+        .bind(test_id)
+        .bind(score as i32)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let (question_ids, answers, correct): (Vec<i32>, Vec<Option<String>>, Vec<bool>) =
+        answers.into_iter().fold((Vec::new(), Vec::new(), Vec::new()), |mut acc, (question_id, answer, is_correct)| {
+            acc.0.push(question_id);
+            acc.1.push(answer);
+            acc.2.push(is_correct);
+            acc
+        });
+    sqlx::query(
+        "INSERT INTO test_answers (result_id, test_item_id, answer, is_correct)
+         SELECT $1, * FROM UNNEST($2::int[], $3::text[], $4::bool[])",
+    )
+        .bind(result_id)
+        .bind(&question_ids)
+        .bind(&answers)
+        .bind(&correct)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(GradedTest { result_id, score, total_questions })
+}
+
+/// Событие об отправке теста и новые достижения — после сохранения результата.
+async fn test_submitted(user_id: i32, test_id: i32, graded: GradedTest, pool: &sqlx::PgPool) -> Result<TestResultResponse, AppError> {
+    events::publish(
+        Event::TestSubmitted {
+            user_id,
+            test_id,
+            result_id: graded.result_id,
+            score: graded.score as i32,
+            total_questions: graded.total_questions as i32,
+        },
+        pool,
+    );
+    let new_achievements = achievements::unlock_new(user_id, pool).await?;
+    Ok(TestResultResponse {
+        result_id: graded.result_id,
+        score: graded.score,
+        total_questions: graded.total_questions,
+        new_achievements,
+    })
+}
+
+// --- Обработчики попыток тестов с ограничением времени ---
+
+/// Начать попытку теста с ограничением времени или продолжить незавершенную.
+pub async fn start_test_attempt_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+) -> Result<Json<TestAttemptState>, AppError> {
+    classes::ensure_test_visible(id, Some(&claims), &state.db_pool).await?;
+    let attempt = attempts::start(claims.user_id, id, &state.db_pool).await?;
+    Ok(Json(attempt))
+}
+
+/// Heartbeat клиента; после обрыва связи засчитывает паузу и возвращает оставшееся время.
+pub async fn test_attempt_heartbeat_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+) -> Result<Json<TestAttemptState>, AppError> {
+    let attempt = attempts::heartbeat(claims.user_id, id, &state.db_pool).await?;
+    Ok(Json(attempt))
+}
+
+/// Сохранить ответ на вопрос попытки.
+pub async fn answer_test_attempt_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+    Json(payload): Json<AnswerPayload>,
+) -> Result<Json<TestAttemptState>, AppError> {
+    let attempt = attempts::answer(claims.user_id, id, &payload, &state.db_pool).await?;
+    Ok(Json(attempt))
+}
+
+/// Завершить попытку: проверить сохраненные ответы и записать результат.
+pub async fn submit_test_attempt_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+) -> Result<Json<TestResultResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await?;
+    let (test_id, answers) = attempts::finish(claims.user_id, id, &mut tx).await?;
+    let graded = save_test_result(claims.user_id, test_id, &answers, &mut tx).await?;
+    attempts::link_result(id, graded.result_id, &mut tx).await?;
+    tx.commit().await?;
+
+    let response = test_submitted(claims.user_id, test_id, graded, &state.db_pool).await?;
+    Ok(Json(response))
+}
+
+/// Увеличить запас пауз попытки (учитель класса или админ).
+pub async fn grant_pause_allowance_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+    Json(payload): Json<GrantPauseAllowancePayload>,
+) -> Result<Json<TestAttempt>, AppError> {
+    let attempt = attempts::grant_allowance(id, payload.seconds, &claims, &state.db_pool).await?;
+    Ok(Json(attempt))
+}
+
+/// Журнал попытки: старт, паузы, продления и отправка (ученик, учитель класса или админ).
+pub async fn get_test_attempt_events_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Claims,
+) -> Result<Json<Vec<AttemptEvent>>, AppError> {
+    let events = attempts::events(id, &claims, &state.db_pool).await?;
+    Ok(Json(events))
+}
+
+/// История результатов текущего пользователя, новые первыми, по курсору.
+pub async fn get_my_results_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    pagination: CursorPagination,
+) -> Result<Json<CursorPage<ResultHistoryItem>>, AppError> {
+    let results = sqlx::query_as::<_, ResultHistoryItem>(
+        "SELECT tr.id AS result_id, tr.test_id, t.name AS test_name, tr.score,
+                (SELECT COUNT(*) FROM test_answers ta WHERE ta.result_id = tr.id) AS total_questions,
+                tr.submitted_at
+         FROM test_results tr JOIN tests t ON t.id = tr.test_id
+         WHERE tr.user_id = $1 AND ($2::int IS NULL OR tr.id < $2)
+         ORDER BY tr.id DESC
+         LIMIT $3",
+    )
+        .bind(claims.user_id)
+        .bind(pagination.cursor)
+        .bind(pagination.fetch_limit())
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(CursorPage::new(results, pagination, |result| result.result_id)))
+}
+
+/// Разбор результата теста: ответы пользователя, правильные ответы и пояснения.
+/// Доступен владельцу результата и администраторам.
+pub async fn get_test_result_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((test_id, result_id)): Path<(i32, i32)>,
+) -> Result<Json<TestResultDetails>, AppError> {
+    let (user_id, test_name, score, submitted_at): (i32, String, i32, chrono::DateTime<chrono::Utc>) = sqlx::query_as(
+        "SELECT tr.user_id, t.name, tr.score, tr.submitted_at
+         FROM test_results tr JOIN tests t ON t.id = tr.test_id
+         WHERE tr.id = $1 AND tr.test_id = $2",
+    )
+        .bind(result_id)
+        .bind(test_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Результат не найден"))?;
+
+    if user_id != claims.user_id && !Admin::allows(&claims.role) {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
+    }
+
+    let questions = sqlx::query_as::<_, QuestionReview>(
+        "SELECT ti.id AS question_id, ti.question, ti.options, ta.answer, ti.correct_answer,
+                ta.is_correct, ti.explanation
+         FROM test_answers ta JOIN test_items ti ON ti.id = ta.test_item_id
+         WHERE ta.result_id = $1
+         ORDER BY ti.id",
+    )
+        .bind(result_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(TestResultDetails {
+        result_id,
+        test_id,
+        test_name,
+        score,
+        total_questions: questions.len(),
+        submitted_at,
+        questions,
+    }))
+}
+
+// --- Конструктор тестов ---
+
+/// Создание общего теста (только для админов).
+pub async fn create_test_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<TestPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let test = test_builder::create(&payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(test)))
+}
+
+/// Изменение названия и описания теста (только для админов).
+pub async fn update_test_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payload): Json<TestPayload>,
+) -> Result<Json<Test>, AppError> {
+    let test = test_builder::update(id, &payload, &state.db_pool).await?;
+    Ok(Json(test))
+}
+
+/// Удаление теста вместе с вопросами (только для админов).
+pub async fn delete_test_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    test_builder::delete(id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Пакетное добавление вопросов в тест (только для админов); возвращает тест целиком.
+pub async fn add_test_items_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payload): Json<TestItemsPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    test_builder::add_items(id, &payload.items, &state.db_pool).await?;
+    let test = load_test_details(id, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(test)))
+}
+
+// --- Обработчики шаблонов тестов ---
+
+/// Теги вопроса для сборки тестов по шаблонам (только для админов).
+pub async fn tag_test_item_handler(
+    State(state): State<AppState>,
+    Path(item_id): Path<i32>,
+    Json(payload): Json<TagTestItemPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    blueprints::tag_item(item_id, &payload, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Создание шаблона теста (только для админов).
+pub async fn create_blueprint_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<BlueprintPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let blueprint = blueprints::create(&payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(blueprint)))
+}
+
+/// Список шаблонов тестов.
+pub async fn get_blueprints_handler(
+    State(state): State<AppState>,
+    _claims: Claims,
+) -> Result<Json<Vec<TestBlueprint>>, AppError> {
+    let blueprints = blueprints::list(&state.db_pool).await?;
+    Ok(Json(blueprints))
+}
+
+/// Шаблон теста по ID.
+pub async fn get_blueprint_handler(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<TestBlueprint>, AppError> {
+    let blueprint = blueprints::get(id, &state.db_pool).await?;
+    Ok(Json(blueprint))
+}
+
+/// Удаление шаблона теста (только для админов).
+pub async fn delete_blueprint_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    blueprints::delete(id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Собрать по шаблону новый тест для текущего пользователя.
+pub async fn generate_blueprint_test_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let test_id = blueprints::generate(claims.user_id, id, &state.db_pool).await?;
+    let test = load_test_details(test_id, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(test)))
+}
+
+/// Собирает персональный тест по выученному и тому, что пора повторить.
+pub async fn generate_quiz_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<GenerateQuizPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let test_id = quiz::generate(claims.user_id, &payload, &state.db_pool).await?;
+    let test = load_test_details(test_id, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(test)))
+}
+
+// --- Обработчики учебных классов ---
+
+/// Создание класса с назначенным учителем (только для админов).
+pub async fn create_class_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateClassPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let class = sqlx::query_as::<_, Class>(
+        "INSERT INTO classes (name, teacher_id) VALUES ($1, $2) RETURNING *",
+    )
+        .bind(payload.name)
+        .bind(payload.teacher_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(class)))
+}
+
+/// Добавление ученика в класс (учитель класса или админ).
+pub async fn add_class_member_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+    Json(payload): Json<AddClassMemberPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+
+    sqlx::query(
+        "INSERT INTO class_members (class_id, user_id) VALUES ($1, $2)
+         ON CONFLICT (class_id, user_id) DO NOTHING",
+    )
+        .bind(class_id)
+        .bind(payload.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Настройка глобального контента, доступного классу (учитель класса или админ).
+pub async fn set_class_content_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+    Json(payload): Json<ClassContentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+
+    sqlx::query("UPDATE classes SET restrict_content = $1 WHERE id = $2")
+        .bind(payload.restrict_content)
+        .bind(class_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM class_tests WHERE class_id = $1")
+        .bind(class_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO class_tests (class_id, test_id)
+         SELECT $1, id FROM tests WHERE id = ANY($2) AND class_id IS NULL",
+    )
+        .bind(class_id)
+        .bind(&payload.test_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM class_decks WHERE class_id = $1")
+        .bind(class_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO class_decks (class_id, deck_id)
+         SELECT $1, id FROM decks WHERE id = ANY($2)",
+    )
+        .bind(class_id)
+        .bind(&payload.deck_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM class_lessons WHERE class_id = $1")
+        .bind(class_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO class_lessons (class_id, lesson_id)
+         SELECT $1, id FROM lessons WHERE id = ANY($2)",
+    )
+        .bind(class_id)
+        .bind(&payload.lesson_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Создание собственного теста класса, не попадающего в общий каталог.
+pub async fn create_class_test_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+    Json(payload): Json<CreateClassTestPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+    if payload.time_limit_secs.is_some_and(|limit| limit <= 0) || payload.pause_allowance_secs < 0 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Ограничение времени и запас пауз не могут быть отрицательными"));
+    }
+
+    let test = sqlx::query_as::<_, Test>(
+        "INSERT INTO tests (name, description, class_id, time_limit_secs, pause_allowance_secs)
+         VALUES ($1, $2, $3, $4, $5) RETURNING *",
+    )
+        .bind(payload.name)
+        .bind(payload.description)
+        .bind(class_id)
+        .bind(payload.time_limit_secs)
+        .bind(payload.pause_allowance_secs)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(test)))
+}
+
+/// Выгрузка журнала класса в CSV (учитель класса или админ).
+pub async fn get_gradebook_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+
+    let csv = gradebook::build_csv(class_id, &state.db_pool).await?;
+    let disposition = format!("attachment; filename=\"gradebook-{}.csv\"", class_id);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        csv,
+    ))
+}
+
+/// Кто из класса сейчас в сети и чем занят (учитель или ученик класса).
+pub async fn get_class_presence_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+) -> Result<Json<Vec<PresenceInfo>>, AppError> {
+    let is_member: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM class_members WHERE class_id = $1 AND user_id = $2)",
+    )
+        .bind(class_id)
+        .bind(claims.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+    if !is_member {
+        classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+    }
+
+    let members = sqlx::query_as::<_, (i32, String, bool)>(
+        "SELECT u.id, u.nickname, u.presence_visible FROM class_members cm
+         JOIN users u ON u.id = cm.user_id
+         WHERE cm.class_id = $1
+         ORDER BY u.nickname",
+    )
+        .bind(class_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(presence::snapshot(members)))
+}
+
+/// Живая доска класса для учителя (WebSocket): снимок, затем действия учеников по мере их появления.
+pub async fn class_board_ws_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, AppError> {
+    classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+    Ok(ws.on_upgrade(move |socket| classroom::handle_board(socket, class_id, state.db_pool)))
+}
+
+// --- Обработчики обсуждения уроков ---
+
+/// Заметки учителя к уроку (учитель или ученик класса).
+pub async fn get_lesson_notes_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((class_id, lesson_id)): Path<(i32, i32)>,
+) -> Result<Json<Vec<LessonNote>>, AppError> {
+    discussion::participant(class_id, lesson_id, &claims, &state.db_pool).await?;
+    let notes = discussion::notes(class_id, lesson_id, &state.db_pool).await?;
+    Ok(Json(notes))
+}
+
+/// Добавление заметки к уроку (учитель класса или админ).
+pub async fn create_lesson_note_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((class_id, lesson_id)): Path<(i32, i32)>,
+    Json(payload): Json<LessonNotePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if discussion::participant(class_id, lesson_id, &claims, &state.db_pool).await? != Participant::Teacher {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
+    }
+    let note = discussion::add_note(class_id, lesson_id, claims.user_id, &payload.body, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(note)))
+}
+
+/// Удаление заметки урока (учитель класса или админ).
+pub async fn delete_lesson_note_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((class_id, lesson_id, note_id)): Path<(i32, i32, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    if discussion::participant(class_id, lesson_id, &claims, &state.db_pool).await? != Participant::Teacher {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
+    }
+    discussion::delete_note(class_id, lesson_id, note_id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Обсуждение урока в классе.
+pub async fn get_lesson_thread_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((class_id, lesson_id)): Path<(i32, i32)>,
+) -> Result<Json<Vec<LessonPost>>, AppError> {
+    discussion::participant(class_id, lesson_id, &claims, &state.db_pool).await?;
+    let posts = discussion::thread(class_id, lesson_id, &state.db_pool).await?;
+    Ok(Json(posts))
+}
+
+/// Вопрос или ответ в обсуждении урока.
+pub async fn create_lesson_post_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((class_id, lesson_id)): Path<(i32, i32)>,
+    Json(payload): Json<LessonPostPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    discussion::participant(class_id, lesson_id, &claims, &state.db_pool).await?;
+    let post = discussion::add_post(class_id, lesson_id, claims.user_id, &payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(post)))
+}
+
+/// Удаление сообщения из обсуждения (автор или учитель класса).
+pub async fn delete_lesson_post_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((class_id, lesson_id, post_id)): Path<(i32, i32, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    let participant = discussion::participant(class_id, lesson_id, &claims, &state.db_pool).await?;
+    discussion::delete_post(class_id, lesson_id, post_id, claims.user_id, participant, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики заданий ---
+
+/// Создание задания для класса (учитель класса или админ).
+pub async fn create_assignment_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(class_id): Path<i32>,
+    Json(payload): Json<CreateAssignmentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    classes::ensure_teacher(class_id, &claims, &state.db_pool).await?;
+
+    let assignment = sqlx::query_as::<_, Assignment>(
+        "INSERT INTO assignments (class_id, test_id, title, due_at) VALUES ($1, $2, $3, $4)
+         RETURNING id, class_id, test_id, title, due_at, created_at",
+    )
+        .bind(class_id)
+        .bind(payload.test_id)
+        .bind(payload.title)
+        .bind(payload.due_at)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(assignment)))
+}
+
+/// Получить задания классов текущего пользователя.
+pub async fn get_my_assignments_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<Assignment>>, AppError> {
+    let assignments = sqlx::query_as::<_, Assignment>(
+        "SELECT a.id, a.class_id, a.test_id, a.title, a.due_at, a.created_at
+         FROM assignments a
+         JOIN class_members cm ON cm.class_id = a.class_id
+         WHERE cm.user_id = $1
+         ORDER BY a.due_at",
+    )
+        .bind(claims.user_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(assignments))
+}
+
+// --- Обработчики уведомлений ---
+
+/// Уведомления текущего пользователя, новые первыми, по курсору.
+pub async fn get_my_notifications_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    pagination: CursorPagination,
+) -> Result<Json<CursorPage<Notification>>, AppError> {
+    let notifications = sqlx::query_as::<_, Notification>(
+        "SELECT id, kind, title, body, created_at, read_at, desktop FROM notifications
+         WHERE user_id = $1 AND ($2::int IS NULL OR id < $2)
+         ORDER BY id DESC
+         LIMIT $3",
+    )
+        .bind(claims.user_id)
+        .bind(pagination.cursor)
+        .bind(pagination.fetch_limit())
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(CursorPage::new(notifications, pagination, |notification| notification.id)))
+}
+
+/// Отметить уведомление как прочитанное.
+pub async fn mark_notification_read_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("UPDATE notifications SET read_at = NOW() WHERE id = $1 AND user_id = $2 AND read_at IS NULL")
+        .bind(id)
+        .bind(claims.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики предложений и shadowing ---
+
+/// Колонки предложения, озвучка отдается ссылкой.
+const SENTENCE_COLUMNS: &str = "id, text, pinyin, translation,
+    CASE WHEN audio_media_id IS NULL THEN NULL ELSE '/api/media/' || audio_media_id END AS audio_url";
+
+/// Создание предложения (только для админов).
+pub async fn create_sentence_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateSentencePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let sentence = sqlx::query_as::<_, Sentence>(&format!(
+        "INSERT INTO sentences (text, pinyin, translation) VALUES ($1, $2, $3) RETURNING {}",
+        SENTENCE_COLUMNS
+    ))
+        .bind(payload.text)
+        .bind(payload.pinyin)
+        .bind(payload.translation)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(sentence)))
+}
+
+/// Список предложений постранично.
+pub async fn get_sentences_handler(
+    State(state): State<AppState>,
+    pagination: Pagination,
+) -> Result<Json<Paginated<Sentence>>, AppError> {
+    let total_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sentences")
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let sentences = sqlx::query_as::<_, Sentence>(&format!(
+        "SELECT {} FROM sentences ORDER BY id LIMIT $1 OFFSET $2",
+        SENTENCE_COLUMNS
+    ))
+        .bind(pagination.per_page)
+        .bind(pagination.offset())
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(Paginated::new(sentences, pagination, total_count)))
+}
+
+/// Получение предложения по ID.
+pub async fn get_sentence_by_id_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Sentence>, AppError> {
+    let sentence = sqlx::query_as::<_, Sentence>(&format!("SELECT {} FROM sentences WHERE id = $1", SENTENCE_COLUMNS))
+        .bind(id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено"))?;
+
+    Ok(Json(sentence))
+}
+
+/// Загрузка озвучки предложения (только для админов, multipart-поле `audio`).
+pub async fn upload_sentence_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<Json<Sentence>, AppError> {
+    let (mime_type, bytes) = read_audio_field(multipart).await?;
+    let pool = &state.db_pool;
+    let sentence = media::store_attached(MediaKind::Audio, &mime_type, &bytes, pool, |media_id| async move {
+        sqlx::query_as::<_, Sentence>(&format!(
+            "UPDATE sentences SET audio_media_id = $1 WHERE id = $2 RETURNING {}",
+            SENTENCE_COLUMNS
+        ))
+            .bind(media_id)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено"))
+    })
+        .await?;
+
+    Ok(Json(sentence))
+}
+
+// --- Обработчики режима чтения ---
+
+/// Создание текста для чтения с разметкой предложений (только для админов).
+pub async fn create_passage_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<PassagePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let passage = reader::create(&payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(passage)))
+}
+
+/// Загрузка записи текста (только для админов): multipart-поле `audio`, MP3 или OGG.
+pub async fn upload_passage_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<Json<PassageSummary>, AppError> {
+    let (mime_type, bytes) = read_audio_field(multipart).await?;
+    let passage = media::store_attached(MediaKind::Audio, &mime_type, &bytes, &state.db_pool, |media_id| {
+        reader::set_audio(id, media_id, &state.db_pool)
+    })
+        .await?;
+    Ok(Json(passage))
+}
+
+/// Тексты с записью для режима чтения.
+pub async fn get_passages_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<PassageSummary>>, AppError> {
+    let passages = reader::list(&state.db_pool).await?;
+    Ok(Json(passages))
+}
+
+/// Разметка текста: запись и время начала и конца каждого предложения.
+pub async fn get_passage_timing_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<PassageTiming>, AppError> {
+    let timing = reader::timing(id, &state.db_pool).await?;
+    Ok(Json(timing))
+}
+
+/// Следующее предложение для shadowing: с озвучкой и наименее отработанное пользователем.
+pub async fn get_next_shadowing_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Sentence>, AppError> {
+    let sentence = sqlx::query_as::<_, Sentence>(&format!(
+        "SELECT {} FROM sentences s
+         WHERE s.audio_media_id IS NOT NULL AND {}
+         ORDER BY (SELECT COUNT(*) FROM shadowing_attempts a WHERE a.sentence_id = s.id AND a.user_id = $1), random()
+         LIMIT 1",
+        SENTENCE_COLUMNS,
+        hidden::not_hidden("$1", "'sentence'", "s.id")
+    ))
+        .bind(claims.user_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Нет предложений с озвучкой"))?;
+
+    Ok(Json(sentence))
+}
+
+/// Прием записи пользователя (multipart-поле `audio`): распознавание, сравнение с эталоном и оценка.
+pub async fn submit_shadowing_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(sentence_id): Path<i32>,
+    multipart: Multipart,
+) -> Result<Json<ShadowingResult>, AppError> {
+    let text: String = sqlx::query_scalar("SELECT text FROM sentences WHERE id = $1")
+        .bind(sentence_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено"))?;
+
+    let (mime_type, bytes) = read_audio_field(multipart).await?;
+    let transcript = shadowing::recognize(bytes, &mime_type).await?;
+    let alignment = shadowing::align(&text, &transcript);
+    let score = shadowing::fluency_score(&alignment);
+
+    let previous_best: Option<f32> = sqlx::query_scalar(
+        "SELECT MAX(score) FROM shadowing_attempts WHERE user_id = $1 AND sentence_id = $2",
+    )
+        .bind(claims.user_id)
+        .bind(sentence_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    sqlx::query("INSERT INTO shadowing_attempts (user_id, sentence_id, transcript, score) VALUES ($1, $2, $3, $4)")
+        .bind(claims.user_id)
+        .bind(sentence_id)
+        .bind(&transcript)
+        .bind(score)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(Json(ShadowingResult { transcript, alignment, score, previous_best }))
+}
+
+/// История попыток пользователя по предложению (для графика прогресса).
+pub async fn get_shadowing_history_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(sentence_id): Path<i32>,
+) -> Result<Json<Vec<ShadowingAttempt>>, AppError> {
+    let attempts = sqlx::query_as::<_, ShadowingAttempt>(
+        "SELECT id, transcript, score, created_at FROM shadowing_attempts
+         WHERE user_id = $1 AND sentence_id = $2 ORDER BY created_at",
+    )
+        .bind(claims.user_id)
+        .bind(sentence_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(attempts))
+}
+
+// --- Обработчики тренажера набора ---
+
+/// Задания для захода тренажера набора иероглифов.
+pub async fn get_typing_drill_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<TypingDrillQuery>,
+) -> Result<Json<Vec<TypingPrompt>>, AppError> {
+    let prompts = typing::drill(claims.user_id, query.size, &state.db_pool).await?;
+    Ok(Json(prompts))
+}
+
+/// Сохранить заход тренажера набора.
+pub async fn submit_typing_run_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<TypingRunPayload>,
+) -> Result<Json<TypingRunResult>, AppError> {
+    let result = typing::submit(claims.user_id, &payload, &state.db_pool).await?;
+    Ok(Json(result))
+}
+
+/// Личные рекорды тренажера набора.
+pub async fn get_typing_stats_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<TypingStats>, AppError> {
+    let stats = typing::stats(claims.user_id, &state.db_pool).await?;
+    Ok(Json(stats))
+}
+
+// --- Обработчики диктанта ---
+
+/// Следующее задание диктанта: сначала слабые места пользователя, затем случайные предложения.
+pub async fn get_next_dictation_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<DictationPrompt>, AppError> {
+    let prompt = sqlx::query_as::<_, DictationPrompt>(&format!(
+        "SELECT s.id AS sentence_id, '/api/media/' || s.audio_media_id AS audio_url
+         FROM sentences s
+         LEFT JOIN weak_items w
+             ON w.user_id = $1 AND w.content_type = 'sentence' AND w.content_id = s.id
+         WHERE s.audio_media_id IS NOT NULL AND {}
+         ORDER BY COALESCE(w.misses, 0) DESC, random()
+         LIMIT 1",
+        hidden::not_hidden("$1", "'sentence'", "s.id")
+    ))
+        .bind(claims.user_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Нет предложений с озвучкой"))?;
+
+    Ok(Json(prompt))
+}
+
+/// Проверка ответа диктанта. Ответ пиньинем засчитывается частично,
+/// ошибки записываются в слабые места.
+pub async fn submit_dictation_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(sentence_id): Path<i32>,
+    Json(payload): Json<DictationAnswerPayload>,
+) -> Result<Json<DictationResult>, AppError> {
+    let sentence = sqlx::query_as::<_, Sentence>(&format!(
+        "SELECT {} FROM sentences WHERE id = $1",
+        SENTENCE_COLUMNS
+    ))
+        .bind(sentence_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено"))?;
+
+    let result = matching::grade(&sentence.text, &sentence.pinyin, &payload.answer);
+    if result != matching::MatchKind::Exact {
+        progress::record_miss(claims.user_id, ContentType::Sentence, sentence_id, &state.db_pool).await?;
+    }
+
+    Ok(Json(DictationResult {
+        result,
+        credit: result.credit(),
+        correct_text: sentence.text,
+        correct_pinyin: sentence.pinyin,
+        translation: sentence.translation,
+    }))
+}
+
+/// Следующее слово для сборки из выученных иероглифов.
+pub async fn get_next_composition_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<CompositionPrompt>, AppError> {
+    let prompt = compose::next_prompt(claims.user_id, &state.db_pool).await?;
+    Ok(Json(prompt))
+}
+
+/// Проверка собранного слова.
+pub async fn submit_composition_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(word_id): Path<i32>,
+    Json(payload): Json<CompositionAnswerPayload>,
+) -> Result<Json<CompositionResult>, AppError> {
+    let result = compose::check(claims.user_id, word_id, &payload.answer, &state.db_pool).await?;
+    Ok(Json(result))
+}
+
+/// Читает из формы поле `audio` и возвращает его MIME-тип и содержимое.
+async fn read_audio_field(mut multipart: Multipart) -> Result<(String, Vec<u8>), AppError> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Некорректные данные формы"))?
+    {
+        if field.name() != Some("audio") {
+            continue;
+        }
+        let mime_type = field.content_type().unwrap_or_default().to_string();
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Не удалось прочитать файл"))?;
+        return Ok((mime_type, bytes.to_vec()));
+    }
+
+    Err(AppError::new(StatusCode::BAD_REQUEST, "Не передано поле audio"))
+}
+
+/// Получить настройки уведомлений текущего пользователя.
+pub async fn get_notification_preferences_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<NotificationPreference>>, AppError> {
+    let preferences = notifications::preferences(claims.user_id, &state.db_pool).await?;
+    Ok(Json(preferences))
+}
+
+/// Изменить настройки уведомлений текущего пользователя.
+pub async fn update_notification_preferences_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<Vec<NotificationPreference>>,
+) -> Result<Json<Vec<NotificationPreference>>, AppError> {
+    notifications::save_preferences(claims.user_id, &payload, &state.db_pool).await?;
+
+    let preferences = notifications::preferences(claims.user_id, &state.db_pool).await?;
+    Ok(Json(preferences))
+}
+
+// --- Обработчики присутствия ---
+
+/// WebSocket-подключение клиента: пока оно открыто, пользователь считается в сети.
+/// Клиент присылает `{"activity": "flashcards"}` при смене занятия.
+pub async fn presence_ws_handler(ws: WebSocketUpgrade, claims: Claims) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| presence::handle_socket(socket, claims.user_id))
+}
+
+/// Изменить видимость своего присутствия для других.
+pub async fn update_presence_settings_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<PresenceSettingsPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("UPDATE users SET presence_visible = $1 WHERE id = $2")
+        .bind(payload.visible)
+        .bind(claims.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики друзей ---
+
+/// Отправить заявку в друзья по нику.
+pub async fn send_friend_request_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<FriendRequestPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    email_verification::ensure_verified(claims.user_id, &state.db_pool).await?;
+    let request = friends::send_request(claims.user_id, &payload.nickname, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+/// Заявки в друзья, ожидающие ответа.
+pub async fn get_friend_requests_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<FriendRequests>, AppError> {
+    let requests = friends::pending(claims.user_id, &state.db_pool).await?;
+    Ok(Json(requests))
+}
+
+/// Принять заявку в друзья.
+pub async fn accept_friend_request_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<FriendRequest>, AppError> {
+    let request = friends::respond(id, claims.user_id, true, &state.db_pool).await?;
+    Ok(Json(request))
+}
+
+/// Отклонить заявку в друзья.
+pub async fn decline_friend_request_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<FriendRequest>, AppError> {
+    let request = friends::respond(id, claims.user_id, false, &state.db_pool).await?;
+    Ok(Json(request))
+}
+
+/// Список друзей.
+pub async fn get_friends_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<Friend>>, AppError> {
+    let friends = friends::list(claims.user_id, &state.db_pool).await?;
+    Ok(Json(friends))
+}
+
+/// Удалить пользователя из друзей.
+pub async fn remove_friend_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    friends::remove(claims.user_id, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Профиль друга: уровень, серия и достижения. Доступен только друзьям.
+pub async fn get_friend_profile_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<FriendProfile>, AppError> {
+    let profile = friends::profile(claims.user_id, id, &state.db_pool).await?;
+    Ok(Json(profile))
+}
+
+// --- Обработчики учебных групп ---
+
+/// Создать учебную группу с целью на неделю.
+pub async fn create_group_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<StudyGroupPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let group = groups::create(claims.user_id, &payload, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+/// Группы текущего пользователя с прогрессом недели.
+pub async fn get_my_groups_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<StudyGroup>>, AppError> {
+    let groups = groups::list(claims.user_id, &state.db_pool).await?;
+    Ok(Json(groups))
+}
+
+/// Вступить в группу по коду.
+pub async fn join_group_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<JoinGroupPayload>,
+) -> Result<Json<StudyGroup>, AppError> {
+    let group = groups::join(claims.user_id, &payload.code, &state.db_pool).await?;
+    Ok(Json(group))
+}
+
+/// Группа с участниками, прогрессом недели и серией (только для участников).
+pub async fn get_group_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<StudyGroup>, AppError> {
+    let group = groups::get(claims.user_id, id, &state.db_pool).await?;
+    Ok(Json(group))
+}
+
+/// Изменить название и цель группы (только владелец).
+pub async fn update_group_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<StudyGroupPayload>,
+) -> Result<Json<StudyGroup>, AppError> {
+    let group = groups::update(claims.user_id, id, &payload, &state.db_pool).await?;
+    Ok(Json(group))
+}
+
+/// Выйти из группы.
+pub async fn leave_group_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    groups::leave(claims.user_id, id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики публикации результатов ---
+
+/// Создать публичную ссылку на свой результат теста.
+/// Если активная ссылка уже есть, возвращается она.
+pub async fn share_result_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(result_id): Path<i32>,
+) -> Result<Json<ShareLinkResponse>, AppError> {
+    email_verification::ensure_verified(claims.user_id, &state.db_pool).await?;
+    let owner_id: i32 = sqlx::query_scalar("SELECT user_id FROM test_results WHERE id = $1")
+        .bind(result_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Результат не найден"))?;
+
+    if owner_id != claims.user_id {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Доступ запрещен"));
+    }
+
+    let existing: Option<String> = sqlx::query_scalar(
+        "SELECT token FROM result_shares WHERE result_id = $1 AND revoked_at IS NULL",
+    )
+        .bind(result_id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+    let token = match existing {
+        Some(token) => token,
+        None => {
+            let token = auth::random_token();
+            sqlx::query("INSERT INTO result_shares (token, result_id, user_id) VALUES ($1, $2, $3)")
+                .bind(&token)
+                .bind(result_id)
+                .bind(claims.user_id)
+                .execute(&state.db_pool)
+                .await?;
+            token
+        }
+    };
+
+    let url = format!("/api/shared/{}", token);
+    Ok(Json(ShareLinkResponse { token, url }))
+}
+
+/// Отозвать публичную ссылку на свой результат теста.
+pub async fn revoke_result_share_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(result_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query(
+        "UPDATE result_shares SET revoked_at = NOW()
+         WHERE result_id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+        .bind(result_id)
+        .bind(claims.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Просмотр опубликованного результата по ссылке (без авторизации).
+pub async fn get_shared_result_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedResult>, AppError> {
+    let result = sqlx::query_as::<_, SharedResult>(
+        "SELECT t.name AS test_name, tr.score, tr.submitted_at,
+                (SELECT COUNT(*) FROM test_items ti WHERE ti.test_id = t.id) AS total_questions
+         FROM result_shares rs
+         JOIN test_results tr ON tr.id = rs.result_id
+         JOIN tests t ON t.id = tr.test_id
+         WHERE rs.token = $1 AND rs.revoked_at IS NULL",
+    )
+        .bind(token)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Ссылка не найдена или отозвана"))?;
+
+    Ok(Json(result))
+}
+
+// --- Обработчики предложений контента ---
+
+/// Предложить новое слово или предложение. Попадает в очередь модерации.
+pub async fn contribute_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<ContributionPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    email_verification::ensure_verified(claims.user_id, &state.db_pool).await?;
+    let contribution = sqlx::query_as::<_, Contribution>(
+        "INSERT INTO contributions (user_id, kind, payload) VALUES ($1, $2, $3) RETURNING *",
+    )
+        .bind(claims.user_id)
+        .bind(payload.kind())
+        .bind(sqlx::types::Json(&payload))
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(contribution)))
+}
+
+/// Очередь модерации (модераторы и админы), по умолчанию — ожидающие проверки.
+pub async fn get_contributions_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ContributionsQuery>,
+) -> Result<Json<Vec<Contribution>>, AppError> {
+    let contributions = sqlx::query_as::<_, Contribution>(
+        "SELECT * FROM contributions WHERE status = $1 ORDER BY created_at",
+    )
+        .bind(query.status.unwrap_or_else(|| "pending".to_string()))
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    Ok(Json(contributions))
+}
+
+/// Принять предложение: контент создается с указанием автора (модераторы и админы).
+pub async fn accept_contribution_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<Contribution>, AppError> {
+    let mut tx = state.db_pool.begin().await?;
+    let contribution = contributions::accept(id, claims.user_id, &mut tx).await?;
+    tx.commit().await?;
+
+    // Достижения за вклад в контент
+    let all_achievements = sqlx::query_as::<_, Achievement>("SELECT * FROM achievements")
+        .fetch_all(&state.db_pool)
+        .await?;
+    achievements::award_for_user(contribution.user_id, &all_achievements, &state.db_pool).await?;
+
+    Ok(Json(contribution))
+}
+
+/// Отклонить предложение (модераторы и админы).
+pub async fn reject_contribution_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<Json<Contribution>, AppError> {
+    let contribution = sqlx::query_as::<_, Contribution>(
+        "UPDATE contributions SET status = 'rejected', reviewed_by = $1, reviewed_at = NOW()
+         WHERE id = $2 AND status = 'pending' RETURNING *",
+    )
+        .bind(claims.user_id)
+        .bind(id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Предложение не найдено или уже рассмотрено"))?;
+
+    Ok(Json(contribution))
+}
+
+// --- Обработчики медиафайлов ---
+
+/// Загрузка изображения или аудио к вопросу теста (только для админов).
+/// Ожидает multipart-поле `image` и/или `audio`.
+pub async fn upload_test_item_media_handler(
+    State(state): State<AppState>,
+    Path(item_id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<Json<TestItem>, AppError> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Некорректные данные формы"))?
+    {
+        let (kind, column) = match field.name() {
+            Some("image") => (MediaKind::Image, "image_media_id"),
+            Some("audio") => (MediaKind::Audio, "audio_media_id"),
+            _ => continue,
+        };
+        let mime_type = field.content_type().unwrap_or_default().to_string();
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Не удалось прочитать файл"))?;
+
+        let pool = &state.db_pool;
+        media::store_attached(kind, &mime_type, &bytes, pool, |media_id| async move {
+            let result = sqlx::query(&format!("UPDATE test_items SET {} = $1 WHERE id = $2", column))
+                .bind(media_id)
+                .bind(item_id)
+                .execute(pool)
+                .await?;
+            if result.rows_affected() == 0 {
+                return Err(AppError::new(StatusCode::NOT_FOUND, "Вопрос не найден"));
+            }
+            Ok(())
+        })
+            .await?;
+    }
+
+    let item = sqlx::query_as::<_, TestItem>(&format!(
+        "SELECT {} FROM test_items WHERE id = $1",
+        TEST_ITEM_COLUMNS
+    ))
+        .bind(item_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Вопрос не найден"))?;
+
+    Ok(Json(item))
+}
+
+/// Получение медиафайла по ID, если виден тест или контент, к которому он приложен.
+/// Файлы неизменяемы, поэтому кешируются надолго; закрытые — только у клиента.
+pub async fn get_media_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    claims: Option<Claims>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let public = media::ensure_visible(id, claims.as_ref(), &state.db_pool).await?;
+    let (media, bytes) = media::load(id, &state.db_pool).await?;
+    let mut response = if caching::not_modified_since(&headers, media.created_at) {
+        caching::not_modified(media.created_at)
+    } else {
+        ([(header::CONTENT_TYPE, media.mime_type)], caching::immutable_headers(media.created_at), bytes).into_response()
+    };
+    if !public {
+        response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static(caching::PRIVATE_IMMUTABLE));
+    }
+    Ok(response)
+}
+
+// --- Обработчики озвучки ---
+
+/// Загрузка озвучки иероглифа (только для админов): multipart-поле `audio`, MP3 или OGG.
+pub async fn upload_hieroglyph_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    let (mime_type, bytes) = read_audio_field(multipart).await?;
+    let audio = audio::store(ContentType::Hieroglyph, id, &mime_type, bytes, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(audio)))
+}
+
+/// Загрузка озвучки слова (только для админов): multipart-поле `audio`, MP3 или OGG.
+pub async fn upload_word_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    let (mime_type, bytes) = read_audio_field(multipart).await?;
+    let audio = audio::store(ContentType::Word, id, &mime_type, bytes, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(audio)))
+}
+
+/// Озвучки иероглифа. Без записанной озвучки отдается синтезированная, если настроен TTS.
+pub async fn get_hieroglyph_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<AudioFile>>, AppError> {
+    let files = audio::hieroglyph_audio(id, &state.db_pool).await?;
+    Ok(Json(files))
+}
+
+/// Озвучки слова.
+pub async fn get_word_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<AudioFile>>, AppError> {
+    let files = audio::list(ContentType::Word, id, &state.db_pool).await?;
+    Ok(Json(files))
+}
+
+/// Файл озвучки. Поддерживает заголовок `Range`, чтобы плееры могли
+/// начинать воспроизведение до загрузки всего файла и перематывать.
+pub async fn stream_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let (key, mime_type, size, created_at) = audio::find(id, &state.db_pool).await?;
+    if caching::not_modified_since(&headers, created_at) {
+        return Ok(caching::not_modified(created_at));
+    }
+    let range = headers.get(header::RANGE).and_then(|value| value.to_str().ok());
+
+    let (status, start, end) = match audio::parse_range(range, size) {
+        audio::ByteRange::Full => (StatusCode::OK, 0, size.saturating_sub(1)),
+        audio::ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end),
+        audio::ByteRange::Unsatisfiable => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+            )
+                .into_response());
+        }
+    };
+    let body = if size == 0 { Vec::new() } else { audio::read(&key, start, end).await? };
+
+    let mut response = (
+        status,
+        [
+            (header::CONTENT_TYPE, mime_type),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        caching::immutable_headers(created_at),
+        body,
+    )
+        .into_response();
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {}-{}/{}", start, end, size);
+        if let Ok(value) = HeaderValue::from_str(&content_range) {
+            response.headers_mut().insert(header::CONTENT_RANGE, value);
+        }
+    }
+    Ok(response)
+}
+
+/// Удаление озвучки (только для админов).
+pub async fn delete_audio_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    audio::delete(id, &state.db_pool).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Обработчики администрирования ---
+
+/// Запуск фонового пересчета производных данных (только для админов).
+/// Нужен после импортов и исправления ошибок вместо ручных правок в БД.
+pub async fn recompute_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<RecomputePayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let pool = state.db_pool.clone();
+    let job_id = match payload.kind {
+        RecomputeKind::Achievements => {
+            jobs::spawn("achievements", move |job_id| achievements::recompute_all(pool, job_id))
+        }
+        RecomputeKind::Streaks => jobs::spawn("streaks", move |job_id| streak::recompute_all(pool, job_id)),
+        RecomputeKind::Leaderboards => {
+            jobs::spawn("leaderboards", move |job_id| gamification::recompute_all(pool, job_id))
+        }
+        RecomputeKind::LessonCompletion => {
+            jobs::spawn("lesson_completion", move |job_id| lessons::recompute_completions(pool, job_id))
+        }
+    };
+
+    Ok((StatusCode::ACCEPTED, Json(jobs::get(job_id))))
+}
+
+/// Разметка уровней HSK иероглифов или слов (только для админов).
+pub async fn set_hsk_levels_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<SetHskLevelsPayload>,
+) -> Result<Json<MessageResponse>, AppError> {
+    let updated = hsk::set_levels(&payload.content_type, &payload.ids, payload.level, &state.db_pool).await?;
+    Ok(Json(MessageResponse::new(&format!("Обновлено записей: {}", updated))))
+}
+
+/// Иероглифы уровня HSK постранично.
+pub async fn get_hsk_hieroglyphs_handler(
+    State(state): State<AppState>,
+    Path(level): Path<i16>,
+    pagination: Pagination,
+) -> Result<Json<Paginated<Hieroglyph>>, AppError> {
+    let page = hsk::hieroglyphs(level, pagination, &state.db_pool).await?;
+    Ok(Json(page))
+}
+
+/// Прогресс текущего пользователя по уровню HSK.
+pub async fn get_hsk_progress_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(level): Path<i16>,
+) -> Result<Json<HskLevelProgress>, AppError> {
+    let progress = hsk::progress(claims.user_id, level, &state.db_pool).await?;
+    Ok(Json(progress))
+}
+
+/// Импорт порядка черт из `graphics.txt` Make Me A Hanzi (только для админов).
+pub async fn import_strokes_handler(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    let pool = state.db_pool.clone();
+    let job_id = jobs::spawn("strokes_import", move |job_id| strokes::import(body, pool, job_id));
+
+    Ok((StatusCode::ACCEPTED, Json(jobs::get(job_id))))
+}
+
+/// Запуск импорта (только для админов). Формат — параметр `format`; файл передается
+/// телом запроса или multipart-полем `file`, либо полем `path` с путем в каталоге
+/// импорта на сервере. В фоне файл разбирается и сравнивается с базой; изменения
+/// применяются только после просмотра, запросом `/api/admin/import/:id/apply`.
+pub async fn start_import_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    request: Request,
+) -> Result<impl IntoResponse, AppError> {
+    let source = importer::read_source(request).await?;
+    let status = importer::start(&query, source, state.db_pool.clone())?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// Ход импорта, предпросмотр изменений и итог применения (только для админов).
+pub async fn get_import_handler(
+    Path(id): Path<u64>,
+) -> Result<Json<ImportStatus>, AppError> {
+    let status = importer::status(id).ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Импорт не найден"))?;
+    Ok(Json(status))
+}
+
+/// Применение подготовленного импорта (только для админов).
+pub async fn apply_import_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = importer::apply(id, state.db_pool.clone())?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// Отмена неподтвержденного импорта (только для админов).
+pub async fn discard_import_handler(
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, AppError> {
+    importer::discard(id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Получить список фоновых задач (только для админов).
+pub async fn get_jobs_handler() -> Result<Json<Vec<JobStatus>>, AppError> {
+    Ok(Json(jobs::list()))
+}
+
+/// Получить статус фоновой задачи (только для админов).
+pub async fn get_job_handler(
+    Path(id): Path<u64>,
+) -> Result<Json<JobStatus>, AppError> {
+    let job = jobs::get(id).ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Задача не найдена"))?;
+    Ok(Json(job))
+}
+
+// --- Массовые операции ---
+
+/// Выполнить пакет операций над контентом в одной транзакции (только для админов).
+pub async fn bulk_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<BulkPayload>,
+) -> Result<Json<BulkReport>, AppError> {
+    let (report, contributors) = bulk::execute(payload, claims.user_id, &state.db_pool).await?;
+
+    // Достижения за принятые предложения
+    if !contributors.is_empty() {
+        let all_achievements = sqlx::query_as::<_, Achievement>("SELECT * FROM achievements")
+            .fetch_all(&state.db_pool)
+            .await?;
+        for user_id in contributors {
+            achievements::award_for_user(user_id, &all_achievements, &state.db_pool).await?;
+        }
+    }
+
+    Ok(Json(report))
+}
+
+// --- Объявления ---
+
+/// Действующие объявления для текущего пользователя; гостям видны только общие.
+pub async fn get_announcements_handler(
+    State(state): State<AppState>,
+    claims: Option<Claims>,
+) -> Result<Json<Vec<Announcement>>, AppError> {
+    let is_admin = claims.as_ref().is_some_and(|c| Admin::allows(&c.role));
+    let announcements = announcements::visible(claims.map(|c| c.user_id), is_admin, &state.db_pool).await?;
+
+    Ok(Json(announcements))
+}
+
+/// Создать объявление (только для админов).
+pub async fn create_announcement_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateAnnouncementPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.title.trim().is_empty() || payload.body.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Заголовок и текст не могут быть пустыми"));
+    }
+    if (payload.audience == AnnouncementAudience::Class) != payload.class_id.is_some() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Класс указывается только для аудитории class"));
+    }
+
+    let announcement = sqlx::query_as::<_, Announcement>(
+        "INSERT INTO announcements (author_id, title, body, audience, class_id, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *, FALSE AS read",
+    )
+        .bind(claims.user_id)
+        .bind(payload.title.trim())
+        .bind(payload.body.trim())
+        .bind(payload.audience.as_str())
+        .bind(payload.class_id)
+        .bind(payload.expires_at)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(announcement)))
+}
+
+/// Удалить объявление (только для админов).
+pub async fn delete_announcement_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = sqlx::query("DELETE FROM announcements WHERE id = $1")
+        .bind(id)
+        .execute(&state.db_pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Объявление не найдено"));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Отметить объявление прочитанным.
+pub async fn read_announcement_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let is_admin = Admin::allows(&claims.role);
+    if !announcements::mark_read(id, claims.user_id, is_admin, &state.db_pool).await? {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Объявление не найдено"));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Настройки и вводный сценарий ---
+
+/// Конфигурация для клиента: минимальная версия, флаги функций, адреса медиа.
+pub async fn get_client_config_handler() -> Json<ClientConfig> {
+    Json(config::client_config())
+}
+
+/// Проверка, что процесс сервера жив (не обращается к базе).
+pub async fn healthz_handler() -> &'static str {
+    "ok"
+}
+
+/// Готовность сервера: 200 после подключения к базе и применения миграций, до этого 503.
+pub async fn readyz_handler() -> impl IntoResponse {
+    let status = startup::status();
+    let code = if status.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(status))
+}
+
+/// Состояние сервера для стартовой страницы: версия, режим регистрации и объем контента.
+pub async fn get_instance_status_handler(State(state): State<AppState>) -> Result<Json<InstanceStatus>, AppError> {
+    let content = sqlx::query_as::<_, ContentCounts>(
+        "SELECT (SELECT COUNT(*) FROM hieroglyphs) AS hieroglyphs,
+                (SELECT COUNT(*) FROM words) AS words,
+                (SELECT COUNT(*) FROM phrases) AS phrases,
+                (SELECT COUNT(*) FROM sentences) AS sentences,
+                (SELECT COUNT(*) FROM grammar_rules) AS grammar_rules,
+                (SELECT COUNT(*) FROM lessons) AS lessons",
+    )
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok(Json(InstanceStatus {
+        version: env!("CARGO_PKG_VERSION"),
+        registration: config::registration_mode(),
+        registration_url: config::registration_url(),
+        public_content: config::public_content(),
+        content,
+    }))
+}
+
+/// Получить настройки текущего пользователя.
+pub async fn get_settings_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<UserSettings>, AppError> {
+    let settings = settings::load(claims.user_id, &state.db_pool).await?;
+    Ok(Json(settings))
+}
+
+/// Изменить настройки текущего пользователя.
+pub async fn update_settings_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<UpdateSettingsPayload>,
+) -> Result<Json<UserSettings>, AppError> {
+    let settings = settings::update(claims.user_id, &payload, &state.db_pool).await?;
+    Ok(Json(settings))
+}
+
+/// Состояние вводного сценария: какие шаги уже пройдены.
+pub async fn get_onboarding_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<OnboardingState>, AppError> {
+    let settings = settings::load(claims.user_id, &state.db_pool).await?;
+    let placement_test_id = config::placement_test_id();
+
+    let placement_test_taken: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM test_results WHERE user_id = $1 AND test_id = $2)",
+    )
+        .bind(claims.user_id)
+        .bind(placement_test_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let has_study_plan: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM study_plans WHERE user_id = $1 AND is_active)",
+    )
+        .bind(claims.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok(Json(OnboardingState {
+        required: settings.onboarding_finished_at.is_none(),
+        settings,
+        placement_test_id,
+        placement_test_taken,
+        has_study_plan,
+    }))
+}
+
+/// Завершить или пропустить вводный сценарий.
+pub async fn finish_onboarding_handler(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<FinishOnboardingPayload>,
+) -> Result<Json<UserSettings>, AppError> {
+    settings::load(claims.user_id, &state.db_pool).await?;
+
+    let settings = sqlx::query_as::<_, UserSettings>(
+        "UPDATE user_settings SET onboarding_finished_at = NOW(), onboarding_skipped = $2
+         WHERE user_id = $1
+         RETURNING interface_language, script, reminder_time, onboarding_finished_at, onboarding_skipped",
+    )
+        .bind(claims.user_id)
+        .bind(payload.skipped)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+// --- Обслуживание ---
+
+/// Метрики очистки устаревших записей (только для админов).
+pub async fn get_hygiene_stats_handler() -> Result<Json<HygieneStats>, AppError> {
+    Ok(Json(hygiene::stats()))
+}
+
+/// Самодиагностика сервера (только для админов).
+pub async fn selfcheck_handler(State(state): State<AppState>) -> Json<SelfCheckReport> {
+    Json(doctor::run(Ok(&state.db_pool)).await)
+}
+
+/// Общий порог нагрузки повторений (только для админов).
+pub async fn get_review_load_settings_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ReviewLoadSettings>, AppError> {
+    Ok(Json(review_load::settings(&state.db_pool).await?))
+}
+
+/// Изменить общий порог нагрузки повторений (только для админов).
+pub async fn update_review_load_settings_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<ReviewLoadSettings>,
+) -> Result<Json<ReviewLoadSettings>, AppError> {
+    Ok(Json(review_load::update_settings(&payload, &state.db_pool).await?))
+}
+
+/// Запустить очистку вне расписания (только для админов).
+pub async fn run_hygiene_handler(
+    State(state): State<AppState>,
+) -> Result<Json<HygieneStats>, AppError> {
+    hygiene::run(&state.db_pool).await?;
+    Ok(Json(hygiene::stats()))
+}
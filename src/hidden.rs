@@ -0,0 +1,63 @@
+use sqlx::PgPool;
+
+use crate::content;
+use crate::errors::AppError;
+use crate::models::{ContentType, HiddenItem};
+
+/// Условие «элемент не скрыт пользователем» для запросов, собирающих занятия.
+/// Аргументы — SQL-выражения с id пользователя, типом и id контента.
+pub fn not_hidden(user_id: &str, content_type: &str, content_id: &str) -> String {
+    format!(
+        "NOT EXISTS (SELECT 1 FROM hidden_items hi
+                     WHERE hi.user_id = {} AND hi.content_type = {} AND hi.content_id = {})",
+        user_id, content_type, content_id
+    )
+}
+
+/// Скрывает элемент из повторений, тестов и упражнений. Повторный вызов ничего не меняет.
+pub async fn hide(user_id: i32, content_type: &ContentType, content_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    content::ensure_exists(content_type, content_id, pool).await?;
+    sqlx::query(
+        "INSERT INTO hidden_items (user_id, content_type, content_id) VALUES ($1, $2, $3)
+         ON CONFLICT DO NOTHING",
+    )
+        .bind(user_id)
+        .bind(content_type)
+        .bind(content_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Возвращает элемент в занятия; прогресс по нему сохранялся все это время.
+pub async fn unhide(user_id: i32, content_type: &ContentType, content_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM hidden_items WHERE user_id = $1 AND content_type = $2 AND content_id = $3")
+        .bind(user_id)
+        .bind(content_type)
+        .bind(content_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Скрытые пользователем элементы, последние скрытые первыми.
+pub async fn list(user_id: i32, pool: &PgPool) -> Result<Vec<HiddenItem>, AppError> {
+    let items = sqlx::query_as::<_, HiddenItem>(
+        "SELECT hi.content_type, hi.content_id,
+                COALESCE(h.character, w.simplified, p.text, s.text, g.pattern, l.title) AS front,
+                hi.created_at
+         FROM hidden_items hi
+         LEFT JOIN hieroglyphs h ON hi.content_type = 'hieroglyph' AND h.id = hi.content_id
+         LEFT JOIN words w ON hi.content_type = 'word' AND w.id = hi.content_id
+         LEFT JOIN phrases p ON hi.content_type = 'phrase' AND p.id = hi.content_id
+         LEFT JOIN sentences s ON hi.content_type = 'sentence' AND s.id = hi.content_id
+         LEFT JOIN grammar_rules g ON hi.content_type = 'grammar_rule' AND g.id = hi.content_id
+         LEFT JOIN lessons l ON hi.content_type = 'lesson' AND l.id = hi.content_id
+         WHERE hi.user_id = $1
+         ORDER BY hi.created_at DESC",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
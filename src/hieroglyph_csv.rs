@@ -0,0 +1,202 @@
+use axum::async_trait;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+
+use crate::errors::AppError;
+use crate::gradebook::{csv_error, csv_error_message};
+use crate::importer::{ImportPlan, Importer, Prepared};
+use crate::jobs;
+use crate::models::{ImportAction, ImportFormat, ImportPreview, ImportResult};
+
+/// Максимум строк в одном файле.
+pub const MAX_IMPORT_ROWS: usize = 10_000;
+
+/// Строка таблицы иероглифов; тот же формат используется для выгрузки.
+#[derive(Debug, Deserialize, sqlx::FromRow)]
+struct HieroglyphCsvRow {
+    character: String,
+    pinyin: String,
+    translation: String,
+    #[serde(default)]
+    example: Option<String>,
+}
+
+/// Является ли символ китайским иероглифом (основной блок, расширение A, расширение B и совместимые).
+pub fn is_hanzi(c: char) -> bool {
+    matches!(c as u32, 0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0x20000..=0x2A6DF | 0xF900..=0xFAFF)
+}
+
+/// Проверяет строку, возвращая текст ошибки для отчета.
+fn validate(row: &HieroglyphCsvRow) -> Result<(), String> {
+    let mut chars = row.character.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if is_hanzi(c) => {}
+        _ => return Err("В колонке character должен быть ровно один иероглиф".to_string()),
+    }
+    if row.pinyin.is_empty() {
+        return Err("Не указан пиньинь".to_string());
+    }
+    if row.translation.is_empty() {
+        return Err("Не указан перевод".to_string());
+    }
+    Ok(())
+}
+
+/// Импорт таблицы иероглифов с заголовком `character,pinyin,translation,example`.
+/// Новые иероглифы создаются, у существующих обновляются пиньинь, перевод и пример,
+/// так что выгрузку можно отредактировать в таблице и загрузить обратно.
+/// Ошибка в строке не останавливает импорт и попадает в предпросмотр.
+pub struct CsvImporter;
+
+#[async_trait]
+impl Importer for CsvImporter {
+    async fn prepare(&self, data: &str, pool: &PgPool, job_id: u64) -> Result<Prepared, AppError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes());
+
+        let mut preview = ImportPreview::new(ImportFormat::HieroglyphCsv);
+        let mut rows = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (index, record) in reader.deserialize::<HieroglyphCsvRow>().enumerate() {
+            // Строка 1 — заголовок
+            let line = Some(index + 2);
+            if index >= MAX_IMPORT_ROWS {
+                let details = format!("Файл длиннее {} строк, остальные строки пропущены", MAX_IMPORT_ROWS);
+                preview.push(line, None, ImportAction::Error, Some(details));
+                break;
+            }
+
+            let mut row = match record {
+                Ok(row) => row,
+                Err(e) => {
+                    preview.push(line, None, ImportAction::Error, Some(e.to_string()));
+                    continue;
+                }
+            };
+            row.example = row.example.filter(|e| !e.is_empty());
+
+            if let Err(error) = validate(&row) {
+                preview.push(line, Some(&row.character), ImportAction::Error, Some(error));
+                continue;
+            }
+            if !seen.insert(row.character.clone()) {
+                let details = "Иероглиф уже встречался выше в файле".to_string();
+                preview.push(line, Some(&row.character), ImportAction::Error, Some(details));
+                continue;
+            }
+            rows.push((line, row));
+        }
+
+        let characters: Vec<&str> = rows.iter().map(|(_, row)| row.character.as_str()).collect();
+        let existing: HashMap<String, HieroglyphCsvRow> = sqlx::query_as::<_, HieroglyphCsvRow>(
+            "SELECT character, pinyin, translation, example FROM hieroglyphs WHERE character = ANY($1)",
+        )
+            .bind(&characters)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.character.clone(), row))
+            .collect();
+
+        jobs::set_total(job_id, rows.len());
+        let mut changed = Vec::new();
+        for (line, row) in rows {
+            jobs::advance(job_id);
+            match existing.get(&row.character) {
+                None => {
+                    preview.push(line, Some(&row.character), ImportAction::Create, Some(row.describe()));
+                    changed.push(row);
+                }
+                Some(current)
+                    if current.pinyin == row.pinyin
+                        && current.translation == row.translation
+                        && current.example == row.example =>
+                {
+                    preview.push(line, Some(&row.character), ImportAction::Unchanged, None);
+                }
+                Some(current) => {
+                    let details = format!("{} → {}", current.describe(), row.describe());
+                    preview.push(line, Some(&row.character), ImportAction::Update, Some(details));
+                    changed.push(row);
+                }
+            }
+        }
+
+        Ok(Prepared { preview, plan: Box::new(CsvPlan { rows: changed }) })
+    }
+}
+
+impl HieroglyphCsvRow {
+    fn describe(&self) -> String {
+        match &self.example {
+            Some(example) => format!("{} — {} ({})", self.pinyin, self.translation, example),
+            None => format!("{} — {}", self.pinyin, self.translation),
+        }
+    }
+}
+
+struct CsvPlan {
+    rows: Vec<HieroglyphCsvRow>,
+}
+
+#[async_trait]
+impl ImportPlan for CsvPlan {
+    async fn apply(self: Box<Self>, pool: &PgPool, job_id: u64) -> Result<ImportResult, AppError> {
+        jobs::set_total(job_id, self.rows.len());
+        let mut result = ImportResult::default();
+        let mut tx = pool.begin().await?;
+        for row in &self.rows {
+            // Уникального индекса по иероглифу нет, поэтому сначала обновление
+            let updated = sqlx::query("UPDATE hieroglyphs SET pinyin = $2, translation = $3, example = $4 WHERE character = $1")
+                .bind(&row.character)
+                .bind(&row.pinyin)
+                .bind(&row.translation)
+                .bind(&row.example)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            if updated > 0 {
+                result.updated += 1;
+            } else {
+                sqlx::query("INSERT INTO hieroglyphs (character, pinyin, translation, example) VALUES ($1, $2, $3, $4)")
+                    .bind(&row.character)
+                    .bind(&row.pinyin)
+                    .bind(&row.translation)
+                    .bind(&row.example)
+                    .execute(&mut *tx)
+                    .await?;
+                result.created += 1;
+            }
+            jobs::advance(job_id);
+        }
+        tx.commit().await?;
+        Ok(result)
+    }
+}
+
+/// Выгружает все иероглифы в CSV в формате, который принимает `CsvImporter`.
+pub async fn export(pool: &PgPool) -> Result<Vec<u8>, AppError> {
+    let rows = sqlx::query_as::<_, HieroglyphCsvRow>(
+        "SELECT character, pinyin, translation, example FROM hieroglyphs ORDER BY id",
+    )
+        .fetch_all(pool)
+        .await?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    // Заголовок пишем явно, чтобы он был и в пустой выгрузке
+    writer.write_record(["character", "pinyin", "translation", "example"]).map_err(csv_error)?;
+    for row in &rows {
+        writer
+            .write_record([
+                row.character.as_str(),
+                row.pinyin.as_str(),
+                row.translation.as_str(),
+                row.example.as_deref().unwrap_or(""),
+            ])
+            .map_err(csv_error)?;
+    }
+    writer.into_inner().map_err(|_| csv_error_message())
+}
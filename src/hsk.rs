@@ -0,0 +1,324 @@
+use axum::async_trait;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::errors::AppError;
+use crate::importer::{ImportPlan, Importer, Prepared};
+use crate::jobs;
+use crate::models::{
+    ContentType, Hieroglyph, HskLevelProgress, HskVersion, ImportAction, ImportFormat, ImportPreview, ImportResult,
+    Paginated, Pagination,
+};
+use axum::http::StatusCode;
+
+/// Максимальный уровень HSK (по стандарту 3.0).
+pub const MAX_LEVEL: i16 = 9;
+
+/// Строка списка слов HSK.
+#[derive(Debug, Deserialize)]
+struct HskRow {
+    word: String,
+    pinyin: String,
+    translation: String,
+    level: String,
+}
+
+impl HskVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HskVersion::V2 => "2.0",
+            HskVersion::V3 => "3.0",
+        }
+    }
+
+    /// Разбирает уровень. В HSK 3.0 уровни 7–9 сданы одним экзаменом и хранятся как 7.
+    fn parse_level(self, level: &str) -> Option<i16> {
+        let level = match (self, level.trim()) {
+            (HskVersion::V3, "7-9") => 7,
+            (_, level) => level.parse::<i16>().ok()?,
+        };
+        let max = match self {
+            HskVersion::V2 => 6,
+            HskVersion::V3 => 9,
+        };
+        (1..=max).contains(&level).then_some(level)
+    }
+}
+
+/// Импорт официального списка слов HSK: создает новые слова, проставляет уровень
+/// существующим и собирает колоды по уровням. Расхождения с уже имеющимися
+/// данными не перезаписываются, а показываются в предпросмотре.
+pub struct HskImporter {
+    pub version: HskVersion,
+}
+
+/// Строка списка, прошедшая проверку.
+struct HskListItem {
+    line: usize,
+    row: HskRow,
+    level: i16,
+}
+
+#[async_trait]
+impl Importer for HskImporter {
+    async fn prepare(&self, data: &str, pool: &PgPool, job_id: u64) -> Result<Prepared, AppError> {
+        let version = self.version;
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes());
+
+        let mut preview = ImportPreview::new(ImportFormat::Hsk);
+        let mut items = Vec::new();
+        for (index, record) in reader.deserialize::<HskRow>().enumerate() {
+            // Строка 1 — заголовок
+            let line = index + 2;
+            let row = match record {
+                Ok(row) => row,
+                Err(e) => {
+                    preview.push(Some(line), None, ImportAction::Error, Some(e.to_string()));
+                    continue;
+                }
+            };
+            match version.parse_level(&row.level) {
+                Some(level) => items.push(HskListItem { line, row, level }),
+                None => {
+                    let details = format!("некорректный уровень {}", row.level);
+                    preview.push(Some(line), Some(&row.word), ImportAction::Error, Some(details));
+                }
+            }
+        }
+
+        let words: Vec<&str> = items.iter().map(|item| item.row.word.as_str()).collect();
+        let existing: HashMap<String, (String, Option<i16>)> = sqlx::query_as::<_, (String, String, Option<i16>)>(
+            "SELECT simplified, pinyin, hsk_level FROM words WHERE simplified = ANY($1)",
+        )
+            .bind(&words)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|(word, pinyin, level)| (word, (pinyin, level)))
+            .collect();
+
+        jobs::set_total(job_id, items.len());
+        let mut seen = HashSet::new();
+        for item in &items {
+            jobs::advance(job_id);
+            let (line, word) = (Some(item.line), Some(item.row.word.as_str()));
+            if !seen.insert(item.row.word.as_str()) {
+                preview.push(line, word, ImportAction::Unchanged, None);
+                continue;
+            }
+            let Some((pinyin, level)) = existing.get(&item.row.word) else {
+                preview.push(line, word, ImportAction::Create, Some(format!("уровень {}", item.level)));
+                continue;
+            };
+
+            let mut conflicts = Vec::new();
+            if *pinyin != item.row.pinyin {
+                conflicts.push(format!("пиньинь в базе «{}», в списке «{}»", pinyin, item.row.pinyin));
+            }
+            match level {
+                Some(level) if *level != item.level => {
+                    conflicts.push(format!("уровень в базе {}, в списке {}", level, item.level));
+                }
+                Some(_) => {}
+                None => {
+                    conflicts.insert(0, format!("будет проставлен уровень {}", item.level));
+                    preview.push(line, word, ImportAction::Update, Some(conflicts.join("; ")));
+                    continue;
+                }
+            }
+            // Расхождения не перезаписываются, но о них нужно знать до применения
+            if conflicts.is_empty() {
+                preview.push(line, word, ImportAction::Unchanged, None);
+            } else {
+                preview.push(line, word, ImportAction::Skip, Some(conflicts.join("; ")));
+            }
+        }
+
+        Ok(Prepared { preview, plan: Box::new(HskPlan { version, items }) })
+    }
+}
+
+struct HskPlan {
+    version: HskVersion,
+    items: Vec<HskListItem>,
+}
+
+#[async_trait]
+impl ImportPlan for HskPlan {
+    async fn apply(self: Box<Self>, pool: &PgPool, job_id: u64) -> Result<ImportResult, AppError> {
+        let version = self.version;
+        let mut result = ImportResult::default();
+        let mut levels: BTreeMap<i16, Vec<i32>> = BTreeMap::new();
+        let mut tx = pool.begin().await?;
+        jobs::set_total(job_id, self.items.len());
+
+        for item in &self.items {
+            let existing = sqlx::query_as::<_, (i32, Option<i16>)>("SELECT id, hsk_level FROM words WHERE simplified = $1")
+                .bind(&item.row.word)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            let word_id = match existing {
+                None => {
+                    result.created += 1;
+                    sqlx::query_scalar(
+                        "INSERT INTO words (simplified, pinyin, translation, hsk_level, hsk_version)
+                         VALUES ($1, $2, $3, $4, $5) RETURNING id",
+                    )
+                        .bind(&item.row.word)
+                        .bind(&item.row.pinyin)
+                        .bind(&item.row.translation)
+                        .bind(item.level)
+                        .bind(version.as_str())
+                        .fetch_one(&mut *tx)
+                        .await?
+                }
+                Some((id, None)) => {
+                    result.updated += 1;
+                    sqlx::query("UPDATE words SET hsk_level = $1, hsk_version = $2 WHERE id = $3")
+                        .bind(item.level)
+                        .bind(version.as_str())
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                    id
+                }
+                Some((id, Some(_))) => id,
+            };
+
+            levels.entry(item.level).or_default().push(word_id);
+            jobs::advance(job_id);
+        }
+
+        for (level, word_ids) in levels {
+            let name = format!("HSK {} — уровень {}", version.as_str(), level);
+            let deck_id: i32 = sqlx::query_scalar(
+                "INSERT INTO decks (name, description) VALUES ($1, $2)
+                 ON CONFLICT (name) DO UPDATE SET description = EXCLUDED.description
+                 RETURNING id",
+            )
+                .bind(&name)
+                .bind(format!("Слова уровня {} из официального списка HSK {}", level, version.as_str()))
+                .fetch_one(&mut *tx)
+                .await?;
+
+            // Колода пересобирается целиком, чтобы повторный импорт не плодил дубликаты
+            sqlx::query("DELETE FROM deck_items WHERE deck_id = $1")
+                .bind(deck_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(
+                "INSERT INTO deck_items (deck_id, content_type, content_id, position)
+                 SELECT $1, $2, word_id, position::int
+                 FROM UNNEST($3::int[]) WITH ORDINALITY AS t(word_id, position)
+                 ON CONFLICT DO NOTHING",
+            )
+                .bind(deck_id)
+                .bind(ContentType::Word)
+                .bind(&word_ids)
+                .execute(&mut *tx)
+                .await?;
+
+            result.notes.push(format!("Колода «{}»: слов — {}", name, word_ids.len()));
+        }
+
+        tx.commit().await?;
+        Ok(result)
+    }
+}
+
+/// Проверяет, что уровень HSK в допустимых пределах.
+pub fn ensure_level(level: i16) -> Result<(), AppError> {
+    if !(1..=MAX_LEVEL).contains(&level) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Уровень HSK должен быть от 1 до {}", MAX_LEVEL),
+        ));
+    }
+    Ok(())
+}
+
+/// Проставляет (или снимает, если `level` пуст) уровень HSK иероглифам или словам.
+/// Возвращает число измененных записей.
+pub async fn set_levels(content_type: &ContentType, ids: &[i32], level: Option<i16>, pool: &PgPool) -> Result<u64, AppError> {
+    let table = match content_type {
+        ContentType::Hieroglyph => "hieroglyphs",
+        ContentType::Word => "words",
+        _ => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Уровень HSK задается только иероглифам и словам",
+            ));
+        }
+    };
+    if let Some(level) = level {
+        ensure_level(level)?;
+    }
+    if ids.is_empty() || ids.len() > crate::bulk::MAX_BULK_ITEMS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Можно изменить от 1 до {} элементов за раз", crate::bulk::MAX_BULK_ITEMS),
+        ));
+    }
+
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET hsk_level = $2 WHERE id = ANY($1) AND hsk_level IS DISTINCT FROM $2",
+        table
+    ))
+        .bind(ids)
+        .bind(level)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Иероглифы уровня по порядку изучения: от простых (меньше черт) к сложным.
+pub async fn hieroglyphs(level: i16, pagination: Pagination, pool: &PgPool) -> Result<Paginated<Hieroglyph>, AppError> {
+    ensure_level(level)?;
+
+    let total_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM hieroglyphs WHERE hsk_level = $1")
+        .bind(level)
+        .fetch_one(pool)
+        .await?;
+
+    let items = sqlx::query_as::<_, Hieroglyph>(
+        "SELECT * FROM hieroglyphs WHERE hsk_level = $1
+         ORDER BY total_strokes NULLS LAST, id
+         LIMIT $2 OFFSET $3",
+    )
+        .bind(level)
+        .bind(pagination.per_page)
+        .bind(pagination.offset())
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Paginated::new(items, pagination, total_count))
+}
+
+/// Сколько иероглифов и слов уровня выучил пользователь.
+pub async fn progress(user_id: i32, level: i16, pool: &PgPool) -> Result<HskLevelProgress, AppError> {
+    ensure_level(level)?;
+
+    let (hieroglyphs_total, hieroglyphs_learned, words_total, words_learned): (i64, i64, i64, i64) = sqlx::query_as(
+        "SELECT
+             (SELECT COUNT(*) FROM hieroglyphs WHERE hsk_level = $2),
+             (SELECT COUNT(*) FROM hieroglyphs h JOIN user_progress up
+                  ON up.content_type = 'hieroglyph' AND up.content_id = h.id AND up.user_id = $1 AND up.is_learned
+              WHERE h.hsk_level = $2),
+             (SELECT COUNT(*) FROM words WHERE hsk_level = $2),
+             (SELECT COUNT(*) FROM words w JOIN user_progress up
+                  ON up.content_type = 'word' AND up.content_id = w.id AND up.user_id = $1 AND up.is_learned
+              WHERE w.hsk_level = $2)",
+    )
+        .bind(user_id)
+        .bind(level)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(HskLevelProgress::new(level, hieroglyphs_total, hieroglyphs_learned, words_total, words_learned))
+}
@@ -0,0 +1,152 @@
+// http_cache.rs
+//
+// Disk cache for JSON responses of the server. Responses carrying an `ETag` or
+// `Last-Modified` are kept in `HTTP_CACHE_DIR` (default `http-cache`) and
+// revalidated with a conditional request, so unchanged content comes back as
+// an empty 304 instead of the full body. Responses that are `immutable` or still
+// within `max-age` are served without touching the network at all, and a cached
+// copy is used when the server can't be reached.
+// Responses are keyed by URL only, so use this for content that is the same for
+// every user; media files go through `media_cache` instead.
+
+use reqwest::header::{CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix seconds until which the body is used without asking the server.
+    fresh_until: u64,
+    body: String,
+}
+
+fn cache_dir() -> PathBuf {
+    PathBuf::from(env::var("HTTP_CACHE_DIR").unwrap_or_else(|_| "http-cache".to_string()))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn path_for(url: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    cache_dir().join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn load(url: &str) -> Option<CachedResponse> {
+    let bytes = std::fs::read(path_for(url)).ok()?;
+    let cached: CachedResponse = serde_json::from_slice(&bytes).ok()?;
+    // A hash collision would otherwise hand out another URL's body
+    (cached.url == url).then_some(cached)
+}
+
+fn save(cached: &CachedResponse) {
+    let result = std::fs::create_dir_all(cache_dir())
+        .and_then(|_| std::fs::write(path_for(&cached.url), serde_json::to_vec(cached).unwrap_or_default()));
+    if let Err(e) = result {
+        println!("Failed to save HTTP cache entry for {}: {:?}", cached.url, e);
+    }
+}
+
+/// How long a response may be used without revalidation, per its `Cache-Control`.
+/// `no-store` means it must not be cached at all.
+fn freshness(cache_control: &str) -> Option<u64> {
+    let mut max_age = 0;
+    for directive in cache_control.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+        if directive == "no-store" {
+            return None;
+        }
+        if directive == "no-cache" {
+            return Some(0);
+        }
+        if let Some(seconds) = directive.strip_prefix("max-age=") {
+            max_age = seconds.parse().unwrap_or(0);
+        }
+    }
+    Some(max_age)
+}
+
+/// GETs a JSON document through the cache. `Ok(None)` means the server answered 404.
+pub async fn get_json<T: DeserializeOwned>(client: &Client, url: &str, token: Option<&str>) -> Result<Option<T>, String> {
+    let cached = load(url);
+    if let Some(cached) = cached.as_ref().filter(|cached| cached.fresh_until > now()) {
+        return serde_json::from_str(&cached.body).map(Some).map_err(|e| e.to_string());
+    }
+
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            // Offline: a stale copy is better than nothing
+            return match cached {
+                Some(cached) => serde_json::from_str(&cached.body).map(Some).map_err(|e| e.to_string()),
+                None => Err(e.to_string()),
+            };
+        }
+    };
+
+    let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let fresh_for = freshness(&header(CACHE_CONTROL).unwrap_or_default());
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        let Some(mut cached) = cached else {
+            return Err("Сервер вернул 304 на запрос без кеша".to_string());
+        };
+        if let Some(fresh_for) = fresh_for {
+            cached.fresh_until = now() + fresh_for;
+            save(&cached);
+        }
+        return serde_json::from_str(&cached.body).map(Some).map_err(|e| e.to_string());
+    }
+    if response.status() == StatusCode::NOT_FOUND {
+        let _ = std::fs::remove_file(path_for(url));
+        return Ok(None);
+    }
+
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+    let body = response
+        .error_for_status()
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    if let Some(fresh_for) = fresh_for.filter(|_| etag.is_some() || last_modified.is_some()) {
+        save(&CachedResponse {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            fresh_until: now() + fresh_for,
+            body,
+        });
+    }
+    Ok(Some(value))
+}
+
+/// Removes all cached responses; called together with clearing the media cache.
+pub fn clear() {
+    let _ = std::fs::remove_dir_all(cache_dir());
+}
@@ -0,0 +1,96 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::AppError;
+use crate::models::HygieneStats;
+
+/// Как часто чистить устаревшие записи.
+const HYGIENE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Что чистить: имя (для метрик) и запрос удаления.
+/// Новые таблицы с истекающими записями добавляются сюда же.
+const PURGES: &[(&str, &str)] = &[
+    ("refresh_sessions", "DELETE FROM refresh_sessions WHERE expires_at < NOW()"),
+    (
+        "invite_codes",
+        "DELETE FROM invite_codes WHERE used_by IS NULL AND expires_at < NOW() - INTERVAL '30 days'",
+    ),
+    ("result_shares", "DELETE FROM result_shares WHERE revoked_at < NOW() - INTERVAL '30 days'"),
+    (
+        "password_reset_tokens",
+        "DELETE FROM password_reset_tokens WHERE expires_at < NOW() - INTERVAL '1 day'",
+    ),
+    (
+        "email_verification_tokens",
+        "DELETE FROM email_verification_tokens WHERE expires_at < NOW()",
+    ),
+    (
+        "registrations_by_ip",
+        "DELETE FROM registrations_by_ip WHERE registered_at < NOW() - INTERVAL '1 day'",
+    ),
+    (
+        "login_attempts",
+        "DELETE FROM login_attempts WHERE attempted_at < NOW() - INTERVAL '90 days'",
+    ),
+    (
+        "security_events",
+        "DELETE FROM security_events WHERE created_at < NOW() - INTERVAL '180 days'",
+    ),
+    (
+        "friend_requests",
+        "DELETE FROM friend_requests WHERE status <> 'pending' AND responded_at < NOW() - INTERVAL '30 days'",
+    ),
+    // Брошенные попытки: время с запасом пауз вышло, а отправки так и не было.
+    // Ответы и журнал попытки удаляются каскадом.
+    (
+        "test_attempts",
+        "DELETE FROM test_attempts WHERE finished_at IS NULL
+         AND started_at + make_interval(secs => time_limit_secs + pause_allowance_secs) < NOW() - INTERVAL '7 days'",
+    ),
+];
+
+// Метрики живут в памяти процесса, как и реестр задач.
+static STATS: Lazy<Mutex<HygieneStats>> = Lazy::new(|| Mutex::new(HygieneStats::default()));
+
+/// Запускает периодическую очистку.
+pub fn spawn_hygiene_loop(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HYGIENE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run(&pool).await {
+                tracing::error!("Ошибка очистки устаревших записей: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Удаляет устаревшие записи и обновляет метрики.
+pub async fn run(pool: &PgPool) -> Result<BTreeMap<String, u64>, AppError> {
+    let mut removed = BTreeMap::new();
+    for (name, sql) in PURGES {
+        let result = sqlx::query(sql).execute(pool).await?;
+        removed.insert(name.to_string(), result.rows_affected());
+    }
+
+    tracing::info!("Очистка устаревших записей: {:?}", removed);
+
+    let mut stats = STATS.lock().unwrap();
+    for (name, count) in &removed {
+        *stats.total_removed.entry(name.clone()).or_default() += count;
+    }
+    stats.runs += 1;
+    stats.last_run_at = Some(Utc::now());
+    stats.last_removed = removed.clone();
+
+    Ok(removed)
+}
+
+/// Метрики очистки с момента запуска сервера.
+pub fn stats() -> HygieneStats {
+    STATS.lock().unwrap().clone()
+}
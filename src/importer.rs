@@ -0,0 +1,286 @@
+use axum::async_trait;
+use axum::extract::multipart::Field;
+use axum::extract::{FromRequest, Multipart, Request};
+use axum::http::{header, StatusCode};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+
+use crate::errors::AppError;
+use crate::models::{ImportAction, ImportChange, ImportFormat, ImportPreview, ImportQuery, ImportResult, ImportStatus};
+use crate::{cedict, hieroglyph_csv, hsk, jobs, unihan};
+
+/// Больше записей в предпросмотре не показывается, счетчики при этом полные.
+pub const MAX_PREVIEW_CHANGES: usize = 1000;
+/// Сколько неподтвержденных импортов держать в памяти; самые старые вытесняются.
+const MAX_STAGED_IMPORTS: usize = 16;
+
+/// Формат импорта. Разбирает файл и сравнивает его с базой, ничего в ней не меняя;
+/// изменения применяются отдельно, после просмотра.
+#[async_trait]
+pub trait Importer: Send + Sync {
+    async fn prepare(&self, data: &str, pool: &PgPool, job_id: u64) -> Result<Prepared, AppError>;
+}
+
+/// Подготовленные изменения, которые ждут подтверждения.
+#[async_trait]
+pub trait ImportPlan: Send + Sync {
+    async fn apply(self: Box<Self>, pool: &PgPool, job_id: u64) -> Result<ImportResult, AppError>;
+}
+
+/// Результат разбора: что покажет предпросмотр и что будет применено.
+pub struct Prepared {
+    pub preview: ImportPreview,
+    pub plan: Box<dyn ImportPlan>,
+}
+
+/// Выбирает реализацию по формату из запроса.
+fn importer(query: &ImportQuery) -> Result<Box<dyn Importer>, AppError> {
+    Ok(match query.format {
+        ImportFormat::Cedict => Box::new(cedict::CedictImporter),
+        ImportFormat::Unihan => Box::new(unihan::UnihanImporter),
+        ImportFormat::Hsk => {
+            let version = query.hsk_version.ok_or_else(|| {
+                AppError::new(StatusCode::BAD_REQUEST, "Для списка HSK нужна версия стандарта (hsk_version)")
+            })?;
+            Box::new(hsk::HskImporter { version })
+        }
+        ImportFormat::HieroglyphCsv => Box::new(hieroglyph_csv::CsvImporter),
+    })
+}
+
+impl ImportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportFormat::Cedict => "cedict",
+            ImportFormat::Unihan => "unihan",
+            ImportFormat::Hsk => "hsk",
+            ImportFormat::HieroglyphCsv => "hieroglyph_csv",
+        }
+    }
+}
+
+impl ImportPreview {
+    pub fn new(format: ImportFormat) -> Self {
+        ImportPreview {
+            format,
+            create: 0,
+            update: 0,
+            unchanged: 0,
+            skip: 0,
+            error: 0,
+            changes: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    /// Учитывает запись. Неизмененные только считаются, остальные попадают
+    /// в список, пока он не длиннее `MAX_PREVIEW_CHANGES`.
+    pub fn push(&mut self, line: Option<usize>, key: Option<&str>, action: ImportAction, details: Option<String>) {
+        match action {
+            ImportAction::Create => self.create += 1,
+            ImportAction::Update => self.update += 1,
+            ImportAction::Unchanged => {
+                self.unchanged += 1;
+                return;
+            }
+            ImportAction::Skip => self.skip += 1,
+            ImportAction::Error => self.error += 1,
+        }
+        if self.changes.len() < MAX_PREVIEW_CHANGES {
+            self.changes.push(ImportChange { line, key: key.map(str::to_string), action, details });
+        } else {
+            self.truncated = true;
+        }
+    }
+}
+
+/// Откуда берется файл импорта.
+pub enum ImportSource {
+    /// Файл передан телом запроса.
+    Text(String),
+    /// Файл на диске; `temporary` — загруженный, удаляется после разбора.
+    File { path: PathBuf, temporary: bool },
+}
+
+impl ImportSource {
+    async fn read(self) -> Result<String, AppError> {
+        let (path, temporary) = match self {
+            ImportSource::Text(text) => return Ok(text),
+            ImportSource::File { path, temporary } => (path, temporary),
+        };
+        let bytes = tokio::fs::read(&path).await;
+        if temporary {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        String::from_utf8(bytes?)
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Файл импорта должен быть в кодировке UTF-8"))
+    }
+}
+
+/// Каталог, из которого можно импортировать файлы, уже лежащие на сервере (`IMPORT_DIR`).
+fn import_dir() -> PathBuf {
+    PathBuf::from(env::var("IMPORT_DIR").unwrap_or_else(|_| "imports".to_string()))
+}
+
+/// Путь к файлу на сервере. Допускаются только относительные пути внутри `IMPORT_DIR`.
+pub fn server_file(path: &str) -> Result<PathBuf, AppError> {
+    let relative = Path::new(path.trim());
+    if relative.as_os_str().is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Путь должен быть относительным и указывать внутрь каталога импорта"));
+    }
+    let path = import_dir().join(relative);
+    if !path.is_file() {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Файл не найден"));
+    }
+    Ok(path)
+}
+
+/// Сохраняет загружаемый файл во временный каталог по частям, не держа его целиком в памяти.
+async fn save_upload(mut field: Field<'_>) -> Result<PathBuf, AppError> {
+    let mut name_bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut name_bytes);
+    let path = env::temp_dir().join(format!("import-{}.txt", hex::encode(name_bytes)));
+
+    let mut file = tokio::fs::File::create(&path).await?;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Не удалось прочитать файл"))?
+    {
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(path)
+}
+
+/// Источник из запроса: multipart-поле `file` с файлом или `path` с путем
+/// в каталоге импорта на сервере, иначе — само тело запроса.
+pub async fn read_source(request: Request) -> Result<ImportSource, AppError> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    if !is_multipart {
+        let text = String::from_request(request, &())
+            .await
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Файл импорта должен быть в кодировке UTF-8"))?;
+        return Ok(ImportSource::Text(text));
+    }
+
+    let form_error = || AppError::new(StatusCode::BAD_REQUEST, "Некорректные данные формы");
+    let mut multipart = Multipart::from_request(request, &()).await.map_err(|_| form_error())?;
+    while let Some(field) = multipart.next_field().await.map_err(|_| form_error())? {
+        match field.name() {
+            Some("file") => return Ok(ImportSource::File { path: save_upload(field).await?, temporary: true }),
+            Some("path") => {
+                let path = field.text().await.map_err(|_| form_error())?;
+                return Ok(ImportSource::File { path: server_file(&path)?, temporary: false });
+            }
+            _ => continue,
+        }
+    }
+    Err(AppError::new(StatusCode::BAD_REQUEST, "Не передано поле file или path"))
+}
+
+struct StagedImport {
+    format: ImportFormat,
+    preview: Option<ImportPreview>,
+    plan: Option<Box<dyn ImportPlan>>,
+    apply_job: Option<u64>,
+    result: Option<ImportResult>,
+}
+
+// Импорты по id задачи разбора. Как и реестр задач, живут только в памяти:
+// неподтвержденный предпросмотр после перезапуска придется собрать заново.
+static IMPORTS: Lazy<Mutex<HashMap<u64, StagedImport>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Запускает разбор файла в фоне и возвращает состояние нового импорта.
+pub fn start(query: &ImportQuery, source: ImportSource, pool: PgPool) -> Result<ImportStatus, AppError> {
+    let importer = importer(query)?;
+    let format = query.format;
+    let id = jobs::spawn(&format!("import_{}", format.as_str()), move |job_id| {
+        let mut imports = IMPORTS.lock().unwrap();
+        imports.insert(job_id, StagedImport { format, preview: None, plan: None, apply_job: None, result: None });
+        while imports.len() > MAX_STAGED_IMPORTS {
+            let Some(oldest) = imports.keys().min().copied() else {
+                break;
+            };
+            imports.remove(&oldest);
+        }
+        prepare(importer, source, pool, job_id)
+    });
+    status(id).ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Импорт не найден"))
+}
+
+async fn prepare(importer: Box<dyn Importer>, source: ImportSource, pool: PgPool, job_id: u64) -> Result<(), AppError> {
+    let data = source.read().await?;
+    let prepared = importer.prepare(&data, &pool, job_id).await?;
+    if let Some(staged) = IMPORTS.lock().unwrap().get_mut(&job_id) {
+        staged.preview = Some(prepared.preview);
+        staged.plan = Some(prepared.plan);
+    }
+    Ok(())
+}
+
+/// Запускает применение подготовленного импорта. Применить его можно один раз.
+pub fn apply(id: u64, pool: PgPool) -> Result<ImportStatus, AppError> {
+    let plan = {
+        let mut imports = IMPORTS.lock().unwrap();
+        let staged = imports
+            .get_mut(&id)
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Импорт не найден"))?;
+        match staged.plan.take() {
+            Some(plan) => plan,
+            None if staged.preview.is_none() => {
+                return Err(AppError::new(StatusCode::CONFLICT, "Предпросмотр еще не готов или разбор завершился ошибкой")
+                    .with_code("import_not_ready"));
+            }
+            None => {
+                return Err(AppError::new(StatusCode::CONFLICT, "Импорт уже применен").with_code("import_applied"));
+            }
+        }
+    };
+
+    let apply_job = jobs::spawn("import_apply", move |job_id| async move {
+        let result = plan.apply(&pool, job_id).await?;
+        if let Some(staged) = IMPORTS.lock().unwrap().get_mut(&id) {
+            staged.result = Some(result);
+        }
+        Ok(())
+    });
+    if let Some(staged) = IMPORTS.lock().unwrap().get_mut(&id) {
+        staged.apply_job = Some(apply_job);
+    }
+    status(id).ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Импорт не найден"))
+}
+
+/// Отменяет неподтвержденный импорт. Уже запущенное применение доработает до конца.
+pub fn discard(id: u64) -> Result<(), AppError> {
+    IMPORTS
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .map(|_| ())
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Импорт не найден"))
+}
+
+/// Текущее состояние импорта.
+pub fn status(id: u64) -> Option<ImportStatus> {
+    let imports = IMPORTS.lock().unwrap();
+    let staged = imports.get(&id)?;
+    Some(ImportStatus {
+        id,
+        format: staged.format,
+        job: jobs::get(id),
+        apply_job: staged.apply_job.and_then(jobs::get),
+        preview: staged.preview.clone(),
+        result: staged.result.clone(),
+    })
+}
@@ -0,0 +1,88 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::errors::AppError;
+use crate::models::{JobState, JobStatus};
+
+// Реестр фоновых задач живет в памяти процесса: статусы нужны только
+// для отслеживания прогресса и после перезапуска сервера не важны.
+static JOBS: Lazy<Mutex<HashMap<u64, JobStatus>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Запускает фоновую задачу и возвращает ее идентификатор.
+/// Замыкание получает id задачи, чтобы сообщать о прогрессе через `set_total`/`advance`.
+pub fn spawn<F, Fut>(kind: &str, job: F) -> u64
+where
+    F: FnOnce(u64) -> Fut,
+    Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+{
+    let id = NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed);
+    let status = JobStatus {
+        id,
+        kind: kind.to_string(),
+        state: JobState::Running,
+        processed: 0,
+        total: 0,
+        error: None,
+        started_at: Utc::now(),
+        finished_at: None,
+    };
+    JOBS.lock().unwrap().insert(id, status);
+
+    let future = job(id);
+    tokio::spawn(async move {
+        let result = future.await;
+        finish(id, result);
+    });
+
+    id
+}
+
+/// Устанавливает общее количество шагов задачи.
+pub fn set_total(id: u64, total: usize) {
+    if let Some(job) = JOBS.lock().unwrap().get_mut(&id) {
+        job.total = total;
+    }
+}
+
+/// Отмечает выполнение одного шага задачи.
+pub fn advance(id: u64) {
+    advance_by(id, 1);
+}
+
+/// Отмечает выполнение нескольких шагов сразу (например, пачки записей).
+pub fn advance_by(id: u64, steps: usize) {
+    if let Some(job) = JOBS.lock().unwrap().get_mut(&id) {
+        job.processed += steps;
+    }
+}
+
+fn finish(id: u64, result: Result<(), AppError>) {
+    if let Some(job) = JOBS.lock().unwrap().get_mut(&id) {
+        job.finished_at = Some(Utc::now());
+        match result {
+            Ok(()) => job.state = JobState::Completed,
+            Err(e) => {
+                tracing::error!("Фоновая задача {} ({}) завершилась с ошибкой: {:?}", id, job.kind, e);
+                job.state = JobState::Failed;
+                job.error = Some(e.message().to_string());
+            }
+        }
+    }
+}
+
+/// Возвращает статус задачи по идентификатору.
+pub fn get(id: u64) -> Option<JobStatus> {
+    JOBS.lock().unwrap().get(&id).cloned()
+}
+
+/// Возвращает статусы всех задач, начиная с самых новых.
+pub fn list() -> Vec<JobStatus> {
+    let mut jobs: Vec<JobStatus> = JOBS.lock().unwrap().values().cloned().collect();
+    jobs.sort_by(|a, b| b.id.cmp(&a.id));
+    jobs
+}
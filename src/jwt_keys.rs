@@ -0,0 +1,256 @@
+use axum::http::StatusCode;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use once_cell::sync::Lazy;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::config;
+use crate::errors::AppError;
+use crate::models::{Claims, Jwk, JwkSet};
+
+/// Идентификатор ключа из `JWT_SECRET`. Им же проверяются токены без `kid`,
+/// выданные до появления ротации ключей.
+pub const LEGACY_KID: &str = "default";
+
+// Ключи читаются один раз при первом обращении; после ротации сервер перезапускается
+static KEYS: Lazy<Result<KeySet, String>> = Lazy::new(load);
+
+/// Ключ подписи JWT.
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    /// `None` — ключ только для проверки: закрытой части нет.
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    /// Открытая часть для JWKS; у симметричных ключей ее нет.
+    jwk: Option<Jwk>,
+}
+
+impl JwtKey {
+    /// Симметричный ключ HS256.
+    pub fn hs256(kid: &str, secret: &str) -> JwtKey {
+        JwtKey {
+            kid: kid.to_string(),
+            algorithm: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret.as_bytes())),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        }
+    }
+
+    /// Ключ RS256 из PEM: открытый ключ (SubjectPublicKeyInfo, `BEGIN PUBLIC KEY`)
+    /// обязателен, закрытый нужен только для подписи.
+    pub fn rs256(kid: &str, public_pem: &str, private_pem: Option<&str>) -> Result<JwtKey, String> {
+        let decoding = DecodingKey::from_rsa_pem(public_pem.as_bytes())
+            .map_err(|e| format!("ключ {}: некорректный открытый ключ: {}", kid, e))?;
+        let (n, e) = rsa_jwk_components(public_pem)
+            .map_err(|e| format!("ключ {}: ожидается открытый ключ RSA в формате BEGIN PUBLIC KEY: {}", kid, e))?;
+        let encoding = private_pem
+            .map(|pem| EncodingKey::from_rsa_pem(pem.as_bytes()))
+            .transpose()
+            .map_err(|e| format!("ключ {}: некорректный закрытый ключ: {}", kid, e))?;
+
+        Ok(JwtKey {
+            kid: kid.to_string(),
+            algorithm: Algorithm::RS256,
+            encoding,
+            decoding,
+            jwk: Some(Jwk {
+                kty: "RSA".to_string(),
+                kid: kid.to_string(),
+                alg: "RS256".to_string(),
+                key_use: "sig".to_string(),
+                n,
+                e,
+            }),
+        })
+    }
+
+    pub fn can_sign(&self) -> bool {
+        self.encoding.is_some()
+    }
+
+    /// Подписывает и сразу проверяет пробный токен: так несовпадающие
+    /// закрытый и открытый ключи обнаруживаются при загрузке, а не на входе пользователя.
+    fn self_check(&self) -> Result<(), String> {
+        let Some(encoding) = &self.encoding else {
+            return Ok(());
+        };
+        let token = encode(&Header::new(self.algorithm), &serde_json::json!({ "check": self.kid }), encoding)
+            .map_err(|e| format!("ключ {}: не удалось подписать: {}", self.kid, e))?;
+        let mut validation = Validation::new(self.algorithm);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        decode::<serde_json::Value>(&token, &self.decoding, &validation)
+            .map(|_| ())
+            .map_err(|_| format!("ключ {}: закрытый и открытый ключи не совпадают", self.kid))
+    }
+}
+
+/// Набор действующих ключей: одним подписываются новые токены, остальными
+/// еще проверяются выданные раньше.
+pub struct KeySet {
+    keys: Vec<JwtKey>,
+    active: usize,
+}
+
+impl KeySet {
+    /// Собирает набор и выбирает ключ подписи: `active_kid`, а если он не задан —
+    /// ключ с наибольшим `kid` из тех, что могут подписывать (ключи удобно называть по дате).
+    pub fn new(keys: Vec<JwtKey>, active_kid: Option<&str>) -> Result<KeySet, String> {
+        if keys.is_empty() {
+            return Err("Ключи JWT не настроены: задайте JWT_KEYS_DIR или JWT_SECRET".to_string());
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|other| other.kid == key.kid) {
+                return Err(format!("ключ {} указан дважды", key.kid));
+            }
+            key.self_check()?;
+        }
+
+        let active = match active_kid {
+            Some(kid) => keys
+                .iter()
+                .position(|key| key.kid == kid && key.can_sign())
+                .ok_or_else(|| format!("Ключ подписи {} не найден или у него нет закрытой части", kid))?,
+            None => keys
+                .iter()
+                .enumerate()
+                .filter(|(_, key)| key.can_sign())
+                .max_by(|(_, a), (_, b)| a.kid.cmp(&b.kid))
+                .map(|(i, _)| i)
+                .ok_or_else(|| "Нет ни одного ключа с закрытой частью для подписи".to_string())?,
+        };
+        Ok(KeySet { keys, active })
+    }
+
+    pub fn active(&self) -> &JwtKey {
+        &self.keys[self.active]
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Подписывает claims активным ключом, указывая его `kid` в заголовке.
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, AppError> {
+        let key = self.active();
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        let encoding = key.encoding.as_ref().expect("активный ключ всегда может подписывать");
+        Ok(encode(&header, claims, encoding)?)
+    }
+
+    /// Проверяет токен ключом из его заголовка `kid`. Алгоритм должен совпадать
+    /// с алгоритмом ключа, иначе открытый ключ RS256 можно было бы выдать за секрет HS256.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, AppError> {
+        let invalid = |reason: String| AppError::new(StatusCode::UNAUTHORIZED, &format!("Невалидный токен: {}", reason));
+
+        let header = decode_header(token).map_err(|e| invalid(e.to_string()))?;
+        let kid = header.kid.as_deref().unwrap_or(LEGACY_KID);
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == kid)
+            .ok_or_else(|| invalid(format!("неизвестный ключ подписи {}", kid)))?;
+        if header.alg != key.algorithm {
+            return Err(invalid("алгоритм не совпадает с ключом".to_string()));
+        }
+
+        let data = decode::<T>(token, &key.decoding, &Validation::new(key.algorithm)).map_err(|e| invalid(e.to_string()))?;
+        Ok(data.claims)
+    }
+
+    /// Открытые ключи для `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect() }
+    }
+}
+
+/// Действующие ключи. Ошибка конфигурации отдается запросу как `misconfigured`.
+pub fn keys() -> Result<&'static KeySet, AppError> {
+    KEYS.as_ref().map_err(|e| {
+        tracing::error!("Ключи JWT: {}, проверьте конфигурацию командой `doctor`", e);
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Сервер настроен некорректно").with_code("misconfigured")
+    })
+}
+
+/// Проверяет access-токен и возвращает его claims.
+pub fn verify_access_token(token: &str) -> Result<Claims, AppError> {
+    keys()?.verify(token)
+}
+
+/// Читает ключи из `JWT_KEYS_DIR` и `JWT_SECRET` и выбирает активный по `JWT_ACTIVE_KID`.
+pub fn load() -> Result<KeySet, String> {
+    let mut keys = match config::jwt_keys_dir() {
+        Some(dir) => load_dir(&dir)?,
+        None => Vec::new(),
+    };
+    if let Some(secret) = config::jwt_secret() {
+        if !keys.iter().any(|key| key.kid == LEGACY_KID) {
+            keys.push(JwtKey::hs256(LEGACY_KID, &secret));
+        }
+    }
+    KeySet::new(keys, config::jwt_active_kid().as_deref())
+}
+
+/// Ключи из каталога, по имени файла:
+/// `<kid>.secret` — секрет HS256; `<kid>.pub.pem` — открытый ключ RS256,
+/// рядом может лежать закрытый `<kid>.key.pem`. Остальные файлы пропускаются.
+pub fn load_dir(dir: &Path) -> Result<Vec<JwtKey>, String> {
+    let read = |path: &Path| fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e));
+
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    names.sort();
+
+    let mut keys = Vec::new();
+    for name in &names {
+        if let Some(kid) = name.strip_suffix(".secret") {
+            ensure_kid(kid)?;
+            let secret = read(&dir.join(name))?;
+            let secret = secret.trim();
+            if secret.is_empty() {
+                return Err(format!("ключ {}: пустой секрет", kid));
+            }
+            keys.push(JwtKey::hs256(kid, secret));
+        } else if let Some(kid) = name.strip_suffix(".pub.pem") {
+            ensure_kid(kid)?;
+            let private_path = dir.join(format!("{}.key.pem", kid));
+            let private_pem = if private_path.exists() { Some(read(&private_path)?) } else { None };
+            keys.push(JwtKey::rs256(kid, &read(&dir.join(name))?, private_pem.as_deref())?);
+        } else if let Some(kid) = name.strip_suffix(".key.pem") {
+            if !names.contains(&format!("{}.pub.pem", kid)) {
+                return Err(format!("ключ {}: нет открытого ключа {}.pub.pem", kid, kid));
+            }
+        }
+    }
+    Ok(keys)
+}
+
+// `kid` попадает в заголовок токена и в JWKS, поэтому ограничен простыми символами
+fn ensure_kid(kid: &str) -> Result<(), String> {
+    let valid = !kid.is_empty() && kid.len() <= 64
+        && kid.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("недопустимое имя ключа «{}»: разрешены латиница, цифры, «-», «_» и «.»", kid))
+    }
+}
+
+/// Модуль и экспонента открытого ключа RSA из PEM (`BEGIN PUBLIC KEY`)
+/// в base64url без выравнивания, как того требует JWK (RFC 7518).
+pub fn rsa_jwk_components(public_pem: &str) -> Result<(String, String), String> {
+    let key = RsaPublicKey::from_public_key_pem(public_pem).map_err(|e| e.to_string())?;
+    Ok((URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()), URL_SAFE_NO_PAD.encode(key.e().to_bytes_be())))
+}
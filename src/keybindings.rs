@@ -0,0 +1,154 @@
+// keybindings.rs
+//
+// Keyboard shortcuts of the flashcard review and test views. Out of the box a
+// card is flipped with Space or Enter, graded with 1-4 or Vim-style h/j/k/l
+// (again/hard/good/easy), `u` takes the last grade back and `s` skips the card
+// to the end of the queue. In a test 1-4 pick an answer choice, Enter or `n`
+// goes to the next question, `s` skips it and `f` submits the test.
+// `KEYBINDINGS_FILE` (default `keybindings.json`) rebinds actions per view,
+// e.g. `{"review": {"good": ["k", "3"], "undo": ["z"]}, "test": {"submit": ["g"]}}`;
+// keys are the text Slint reports for a key press, so Enter is "\n". Actions
+// not listed in the file keep their default keys.
+
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::hash::Hash;
+use std::path::PathBuf;
+
+/// Actions of one view together with their default keys.
+pub trait ViewAction: Copy + Eq + Hash + DeserializeOwned {
+    const DEFAULTS: &'static [(Self, &'static [&'static str])];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewAction {
+    Flip,
+    Again,
+    Hard,
+    Good,
+    Easy,
+    Undo,
+    Skip,
+}
+
+impl ReviewAction {
+    /// SM-2 quality sent for grading actions, same as the rate buttons.
+    pub fn quality(self) -> Option<i32> {
+        match self {
+            ReviewAction::Again => Some(1),
+            ReviewAction::Hard => Some(3),
+            ReviewAction::Good => Some(4),
+            ReviewAction::Easy => Some(5),
+            ReviewAction::Flip | ReviewAction::Undo | ReviewAction::Skip => None,
+        }
+    }
+}
+
+impl ViewAction for ReviewAction {
+    const DEFAULTS: &'static [(Self, &'static [&'static str])] = &[
+        (ReviewAction::Flip, &[" ", "\n"]),
+        (ReviewAction::Again, &["1", "h"]),
+        (ReviewAction::Hard, &["2", "j"]),
+        (ReviewAction::Good, &["3", "k"]),
+        (ReviewAction::Easy, &["4", "l"]),
+        (ReviewAction::Undo, &["u"]),
+        (ReviewAction::Skip, &["s"]),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestAction {
+    Choice1,
+    Choice2,
+    Choice3,
+    Choice4,
+    Next,
+    Skip,
+    Submit,
+}
+
+impl ViewAction for TestAction {
+    const DEFAULTS: &'static [(Self, &'static [&'static str])] = &[
+        (TestAction::Choice1, &["1"]),
+        (TestAction::Choice2, &["2"]),
+        (TestAction::Choice3, &["3"]),
+        (TestAction::Choice4, &["4"]),
+        (TestAction::Next, &["\n", "n"]),
+        (TestAction::Skip, &["s"]),
+        (TestAction::Submit, &["f"]),
+    ];
+}
+
+/// Overrides from the keybindings file, per view.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BindingsFile {
+    pub review: HashMap<ReviewAction, Vec<String>>,
+    pub test: HashMap<TestAction, Vec<String>>,
+}
+
+impl BindingsFile {
+    /// A malformed file is ignored as a whole, so every view keeps its defaults.
+    pub fn parse(bytes: &[u8]) -> Self {
+        serde_json::from_slice(bytes).unwrap_or_else(|e| {
+            println!("Ignoring malformed keybindings file: {}", e);
+            BindingsFile::default()
+        })
+    }
+
+    fn load() -> Self {
+        match std::fs::read(keybindings_file()) {
+            Ok(bytes) => BindingsFile::parse(&bytes),
+            Err(_) => BindingsFile::default(),
+        }
+    }
+}
+
+pub struct KeyBindings<A> {
+    keys: HashMap<String, A>,
+}
+
+impl<A: ViewAction> KeyBindings<A> {
+    /// Default keys with the actions from `overrides` rebound to the given keys only.
+    pub fn with_overrides(overrides: HashMap<A, Vec<String>>) -> Self {
+        let mut keys = HashMap::new();
+        for (action, defaults) in A::DEFAULTS {
+            if !overrides.contains_key(action) {
+                for key in *defaults {
+                    keys.insert(key.to_string(), *action);
+                }
+            }
+        }
+        for (action, bound) in overrides {
+            for key in bound {
+                keys.insert(key, action);
+            }
+        }
+        KeyBindings { keys }
+    }
+
+    pub fn action(&self, key: &str) -> Option<A> {
+        self.keys.get(key).copied()
+    }
+}
+
+fn keybindings_file() -> PathBuf {
+    PathBuf::from(env::var("KEYBINDINGS_FILE").unwrap_or_else(|_| "keybindings.json".to_string()))
+}
+
+static BINDINGS_FILE: Lazy<BindingsFile> = Lazy::new(BindingsFile::load);
+
+/// Bindings of the review screen, read once on first key press.
+pub static REVIEW_KEYS: Lazy<KeyBindings<ReviewAction>> =
+    Lazy::new(|| KeyBindings::with_overrides(BINDINGS_FILE.review.clone()));
+
+/// Bindings of the test screen. The client's tests page only lists past
+/// results so far, so nothing reads them until test taking moves into it.
+#[allow(dead_code)]
+pub static TEST_KEYS: Lazy<KeyBindings<TestAction>> =
+    Lazy::new(|| KeyBindings::with_overrides(BINDINGS_FILE.test.clone()));
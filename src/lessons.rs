@@ -0,0 +1,196 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+use crate::classes::LESSON_VISIBILITY_CONDITION;
+use crate::content;
+use crate::errors::AppError;
+use crate::gamification::{self, XpSource};
+use crate::jobs;
+use crate::models::{ContentType, Lesson, LessonCompletion, LessonDetails, LessonItem, LessonItemRef};
+
+/// Максимум элементов в одном уроке.
+pub const MAX_LESSON_ITEMS: usize = 200;
+
+// Урок с числом элементов и отметкой о прохождении для пользователя $1
+const LESSON_SELECT: &str = "
+    SELECT l.id, l.title, l.description, l.position,
+           (SELECT COUNT(*) FROM lesson_items li WHERE li.lesson_id = l.id) AS item_count,
+           EXISTS (
+               SELECT 1 FROM user_progress up
+               WHERE up.user_id = $1 AND up.content_type = 'lesson' AND up.content_id = l.id AND up.is_learned
+           ) AS completed
+    FROM lessons l";
+
+/// Уроки, видимые пользователю, в порядке курса.
+pub async fn list(user_id: i32, pool: &PgPool) -> Result<Vec<Lesson>, AppError> {
+    let lessons = sqlx::query_as::<_, Lesson>(&format!(
+        "{} WHERE {} ORDER BY l.position, l.id",
+        LESSON_SELECT, LESSON_VISIBILITY_CONDITION
+    ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(lessons)
+}
+
+/// Урок с содержимым элементов в заданном порядке.
+pub async fn details(user_id: i32, lesson_id: i32, pool: &PgPool) -> Result<LessonDetails, AppError> {
+    let lesson = sqlx::query_as::<_, Lesson>(&format!("{} WHERE l.id = $2", LESSON_SELECT))
+        .bind(user_id)
+        .bind(lesson_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Урок не найден"))?;
+
+    let items = sqlx::query_as::<_, LessonItem>(
+        "SELECT li.position, li.content_type, li.content_id,
+                COALESCE(h.character, w.simplified, p.text, s.text, g.pattern) AS text,
+                COALESCE(h.pinyin, w.pinyin, p.pinyin, s.pinyin) AS pinyin,
+                COALESCE(h.translation, w.translation, p.translation, s.translation, g.title) AS translation
+         FROM lesson_items li
+         LEFT JOIN hieroglyphs h ON li.content_type = 'hieroglyph' AND h.id = li.content_id
+         LEFT JOIN words w ON li.content_type = 'word' AND w.id = li.content_id
+         LEFT JOIN phrases p ON li.content_type = 'phrase' AND p.id = li.content_id
+         LEFT JOIN sentences s ON li.content_type = 'sentence' AND s.id = li.content_id
+         LEFT JOIN grammar_rules g ON li.content_type = 'grammar_rule' AND g.id = li.content_id
+         WHERE li.lesson_id = $1 AND COALESCE(h.id, w.id, p.id, s.id, g.id) IS NOT NULL
+         ORDER BY li.position",
+    )
+        .bind(lesson_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(LessonDetails { lesson, items })
+}
+
+/// Заменяет состав урока. Порядок элементов в запросе становится порядком изучения.
+pub async fn set_items(lesson_id: i32, items: &[LessonItemRef], pool: &PgPool) -> Result<(), AppError> {
+    if items.len() > MAX_LESSON_ITEMS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("В уроке может быть не больше {} элементов", MAX_LESSON_ITEMS),
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    for item in items {
+        // Уроки не вкладываются друг в друга
+        if item.content_type == ContentType::Lesson || content::table_for(&item.content_type).is_none() {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Этот тип контента нельзя добавить в урок"));
+        }
+        if !seen.insert((item.content_type.clone(), item.content_id)) {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Элемент повторяется в уроке"));
+        }
+        content::ensure_exists(&item.content_type, item.content_id, pool).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+    let exists: Option<i32> = sqlx::query_scalar("SELECT id FROM lessons WHERE id = $1 FOR UPDATE")
+        .bind(lesson_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if exists.is_none() {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Урок не найден"));
+    }
+
+    sqlx::query("DELETE FROM lesson_items WHERE lesson_id = $1")
+        .bind(lesson_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (position, item) in items.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO lesson_items (lesson_id, content_type, content_id, position) VALUES ($1, $2, $3, $4)",
+        )
+            .bind(lesson_id)
+            .bind(&item.content_type)
+            .bind(item.content_id)
+            .bind(position as i32)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Отмечает урок пройденным: все его элементы становятся выученными
+/// и попадают в очередь повторения, а сам урок получает запись прогресса.
+pub async fn complete(user_id: i32, lesson_id: i32, pool: &PgPool) -> Result<LessonCompletion, AppError> {
+    let mut tx = pool.begin().await?;
+    let exists: Option<i32> = sqlx::query_scalar("SELECT id FROM lessons WHERE id = $1")
+        .bind(lesson_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if exists.is_none() {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Урок не найден"));
+    }
+
+    // Уже выученные элементы не трогаем, чтобы не сбить дату изучения и расписание повторений
+    let result = sqlx::query(
+        "INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at, next_review_at)
+         SELECT $1, content_type, content_id, TRUE, NOW(), NOW() + INTERVAL '1 day'
+         FROM lesson_items WHERE lesson_id = $2
+         ON CONFLICT (user_id, content_type, content_id) DO UPDATE
+         SET is_learned = TRUE, learned_at = NOW(),
+             next_review_at = COALESCE(user_progress.next_review_at, NOW() + INTERVAL '1 day')
+         WHERE NOT user_progress.is_learned",
+    )
+        .bind(user_id)
+        .bind(lesson_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Сам урок в очередь повторения не ставится
+    sqlx::query(
+        "INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at)
+         VALUES ($1, $2, $3, TRUE, NOW())
+         ON CONFLICT (user_id, content_type, content_id) DO NOTHING",
+    )
+        .bind(user_id)
+        .bind(ContentType::Lesson)
+        .bind(lesson_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(LessonCompletion { lesson_id, newly_learned: result.rows_affected() })
+}
+
+/// Отмечает пройденными уроки, все элементы которых пользователь уже выучил
+/// по отдельности, и начисляет за них опыт урока. Элементы оценены при
+/// изучении, поэтому опыт за них не добавляется.
+pub async fn recompute_completions(pool: PgPool, job_id: u64) -> Result<(), AppError> {
+    let user_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM users ORDER BY id")
+        .fetch_all(&pool)
+        .await?;
+
+    jobs::set_total(job_id, user_ids.len());
+    for user_id in user_ids {
+        let completed: Vec<i32> = sqlx::query_scalar(
+            "INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at)
+             SELECT $1, $2, l.id, TRUE, NOW() FROM lessons l
+             WHERE EXISTS (SELECT 1 FROM lesson_items li WHERE li.lesson_id = l.id)
+               AND NOT EXISTS (
+                   SELECT 1 FROM lesson_items li
+                   LEFT JOIN user_progress up ON up.user_id = $1
+                        AND up.content_type = li.content_type AND up.content_id = li.content_id
+                   WHERE li.lesson_id = l.id AND up.is_learned IS NOT TRUE
+               )
+             ON CONFLICT (user_id, content_type, content_id) DO NOTHING
+             RETURNING content_id",
+        )
+            .bind(user_id)
+            .bind(ContentType::Lesson)
+            .fetch_all(&pool)
+            .await?;
+        for lesson_id in completed {
+            gamification::award(user_id, XpSource::Lesson, &lesson_id.to_string(), gamification::XP_PER_LESSON, &pool)
+                .await?;
+        }
+        jobs::advance(job_id);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// local_api.rs
+//
+// Opt-in REST surface on 127.0.0.1 for tools running on the same machine
+// (e.g. a browser extension): "is this word known to me?" and "add to study list".
+// Requests are authenticated with a random local token written to a file, and
+// forwarded to the server on behalf of the signed-in user.
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::env;
+use std::net::SocketAddr;
+
+use crate::models::StudyTextQuery;
+
+const TOKEN_HEADER: &str = "x-local-token";
+
+#[derive(Clone)]
+struct LocalApiState {
+    token: String,
+    client: Client,
+}
+
+type LocalResponse = (StatusCode, Json<Value>);
+
+fn error(status: StatusCode, message: &str) -> LocalResponse {
+    (status, Json(json!({ "error": message })))
+}
+
+/// Starts the local API if `LOCAL_API_PORT` is set. The token is written to
+/// `LOCAL_API_TOKEN_FILE` (default `mandarin-local-token`) for tools to read.
+pub fn spawn_if_enabled() {
+    let Ok(port) = env::var("LOCAL_API_PORT") else {
+        return;
+    };
+    let Ok(port) = port.parse::<u16>() else {
+        println!("Invalid LOCAL_API_PORT: {}", port);
+        return;
+    };
+
+    let token = crate::auth::random_token();
+    let token_path = env::var("LOCAL_API_TOKEN_FILE").unwrap_or_else(|_| "mandarin-local-token".to_string());
+    if let Err(e) = std::fs::write(&token_path, &token) {
+        println!("Failed to write local API token to {}: {:?}", token_path, e);
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(async move {
+            let app = Router::new()
+                .route("/known", get(known_handler))
+                .route("/study", post(study_handler))
+                .with_state(LocalApiState { token, client: Client::new() });
+
+            // Only the loopback interface: the API must not be reachable from the network
+            let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))).await?;
+            axum::serve(listener, app).await
+        });
+
+        if let Err(e) = result {
+            println!("Local API stopped: {:?}", e);
+        }
+    });
+}
+
+/// Checks the local token and returns the user's server token.
+fn authorize(state: &LocalApiState, headers: &HeaderMap) -> Result<String, LocalResponse> {
+    let provided = headers.get(TOKEN_HEADER).and_then(|v| v.to_str().ok());
+    if provided != Some(state.token.as_str()) {
+        return Err(error(StatusCode::UNAUTHORIZED, "Invalid local token"));
+    }
+
+    crate::AUTH_TOKEN
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| error(StatusCode::SERVICE_UNAVAILABLE, "Not signed in"))
+}
+
+/// Passes the server response through as is.
+async fn forward(request: reqwest::RequestBuilder) -> LocalResponse {
+    match request.send().await {
+        Ok(response) => {
+            let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            match response.json::<Value>().await {
+                Ok(body) => (status, Json(body)),
+                Err(_) => error(StatusCode::BAD_GATEWAY, "Invalid server response"),
+            }
+        }
+        Err(_) => error(StatusCode::BAD_GATEWAY, "Server unavailable"),
+    }
+}
+
+async fn known_handler(
+    State(state): State<LocalApiState>,
+    headers: HeaderMap,
+    Query(query): Query<StudyTextQuery>,
+) -> LocalResponse {
+    let server_token = match authorize(&state, &headers) {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+
+    forward(
+        state.client
+            .get(format!("{}/api/study/known", crate::api_base_url()))
+            .query(&query)
+            .bearer_auth(server_token),
+    ).await
+}
+
+async fn study_handler(
+    State(state): State<LocalApiState>,
+    headers: HeaderMap,
+    Json(payload): Json<StudyTextQuery>,
+) -> LocalResponse {
+    let server_token = match authorize(&state, &headers) {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+
+    forward(
+        state.client
+            .post(format!("{}/api/study/list", crate::api_base_url()))
+            .json(&payload)
+            .bearer_auth(server_token),
+    ).await
+}
@@ -0,0 +1,149 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::config;
+use crate::errors::AppError;
+use crate::models::{CursorPage, CursorPagination, LoginAttempt, User};
+
+/// Исход попытки входа, как он пишется в журнал.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Success,
+    BadPassword,
+    UnknownUser,
+    /// Вход во время блокировки; пароль при этом не проверяется.
+    Locked,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::BadPassword => "bad_password",
+            Outcome::UnknownUser => "unknown_user",
+            Outcome::Locked => "locked",
+        }
+    }
+}
+
+/// Сколько минут осталось до конца блокировки, с округлением вверх.
+pub fn remaining_minutes(until: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+    ((until - now).num_seconds() + 59).div_euclid(60).max(1)
+}
+
+/// Ошибка входа в закрытый аккаунт; клиент показывает ее текст на экране входа.
+pub fn locked_error(until: DateTime<Utc>, now: DateTime<Utc>) -> AppError {
+    AppError::new(
+        StatusCode::LOCKED,
+        &format!(
+            "Слишком много неверных паролей, вход временно закрыт. Попробуйте через {} мин.",
+            remaining_minutes(until, now)
+        ),
+    ).with_code("account_locked")
+}
+
+/// Проверяет, не закрыт ли вход. Вызывается до сверки пароля, чтобы подбор
+/// во время блокировки ничего не давал.
+pub fn ensure_unlocked(user: &User, now: DateTime<Utc>) -> Result<(), AppError> {
+    match user.locked_until {
+        Some(until) if until > now => Err(locked_error(until, now)),
+        _ => Ok(()),
+    }
+}
+
+/// Записывает попытку входа в журнал.
+pub async fn record(
+    user_id: Option<i32>,
+    nickname: &str,
+    ip: Option<IpAddr>,
+    outcome: Outcome,
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    sqlx::query("INSERT INTO login_attempts (user_id, nickname, ip, outcome) VALUES ($1, $2, $3, $4)")
+        .bind(user_id)
+        .bind(nickname)
+        .bind(ip.map(|ip| ip.to_string()))
+        .bind(outcome.as_str())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Засчитывает неверный пароль и закрывает вход, если неудач подряд набралось
+/// `LOGIN_LOCKOUT_THRESHOLD`. Считаются неудачи после последнего успешного входа
+/// и конца прошлой блокировки, и только за время одной блокировки: редкие
+/// опечатки не копятся неделями. Возвращает конец новой блокировки.
+pub async fn register_failure(
+    user: &User,
+    ip: Option<IpAddr>,
+    pool: &PgPool,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    record(Some(user.id), &user.nickname, ip, Outcome::BadPassword, pool).await?;
+    let Some(lockout) = config::login_lockout() else {
+        return Ok(None);
+    };
+
+    let failures: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM login_attempts
+         WHERE user_id = $1 AND outcome = 'bad_password'
+           AND attempted_at > NOW() - make_interval(secs => $2)
+           AND attempted_at > COALESCE($3, '-infinity')
+           AND id > COALESCE((SELECT MAX(id) FROM login_attempts WHERE user_id = $1 AND outcome = 'success'), 0)",
+    )
+        .bind(user.id)
+        .bind(lockout.duration.as_secs_f64())
+        .bind(user.locked_until)
+        .fetch_one(pool)
+        .await?;
+    if failures < lockout.max_failures {
+        return Ok(None);
+    }
+
+    let until: DateTime<Utc> = sqlx::query_scalar(
+        "UPDATE users SET locked_until = NOW() + make_interval(secs => $2) WHERE id = $1 RETURNING locked_until",
+    )
+        .bind(user.id)
+        .bind(lockout.duration.as_secs_f64())
+        .fetch_one(pool)
+        .await?;
+    tracing::warn!("Вход пользователя {} закрыт до {} после {} неверных паролей", user.id, until, failures);
+    Ok(Some(until))
+}
+
+/// Снимает блокировку входа (после сброса пароля или разблокировки админом).
+pub async fn unlock<'e, E>(user_id: i32, executor: E) -> Result<(), AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query("UPDATE users SET locked_until = NULL WHERE id = $1")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Журнал входов пользователя, новые первыми, по курсору.
+pub async fn attempts(user_id: i32, pagination: CursorPagination, pool: &PgPool) -> Result<CursorPage<LoginAttempt>, AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"));
+    }
+
+    let attempts = sqlx::query_as::<_, LoginAttempt>(
+        "SELECT id, nickname, ip, outcome, attempted_at FROM login_attempts
+         WHERE user_id = $1 AND ($2::int IS NULL OR id < $2)
+         ORDER BY id DESC
+         LIMIT $3",
+    )
+        .bind(user_id)
+        .bind(pagination.cursor)
+        .bind(pagination.fetch_limit())
+        .fetch_all(pool)
+        .await?;
+    Ok(CursorPage::new(attempts, pagination, |attempt| attempt.id))
+}
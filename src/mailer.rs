@@ -0,0 +1,152 @@
+use axum::async_trait;
+use axum::http::StatusCode;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::config;
+use crate::errors::AppError;
+
+/// Таймаут одного письма, включая подключение к SMTP-серверу.
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Письмо пользователю.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Способ отправки писем. Реализации выбираются по конфигурации при первой отправке.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn send(&self, email: &Email) -> Result<(), AppError>;
+}
+
+/// Пишет письма в лог вместо отправки: для разработки без SMTP.
+pub struct ConsoleMailer;
+
+#[async_trait]
+impl Mailer for ConsoleMailer {
+    fn name(&self) -> &'static str {
+        "console"
+    }
+
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        tracing::info!("Письмо для {}: {}\n{}", email.to, email.subject, email.body);
+        Ok(())
+    }
+}
+
+/// Отправляет письма через SMTP-релей (`SMTP_HOST`, `SMTP_PORT`) без шифрования
+/// и авторизации: релей должен принимать почту от сервера, например локальный Postfix.
+pub struct SmtpMailer {
+    address: String,
+    from: String,
+}
+
+/// Текст письма по протоколу SMTP: заголовки, тело с CRLF и экранированными точками.
+pub fn smtp_message(from: &str, email: &Email) -> String {
+    let body: String = email
+        .body
+        .lines()
+        .map(|line| if line.starts_with('.') { format!(".{}\r\n", line) } else { format!("{}\r\n", line) })
+        .collect();
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: =?UTF-8?B?{}?=\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}.\r\n",
+        from,
+        email.to,
+        STANDARD.encode(email.subject.as_bytes()),
+        body
+    )
+}
+
+type SmtpStream = BufReader<TcpStream>;
+
+/// Отправляет команду (если есть) и проверяет код ответа, пропуская многострочные ответы.
+async fn command(stream: &mut SmtpStream, line: Option<&str>, expected: &str) -> std::io::Result<Result<(), String>> {
+    if let Some(line) = line {
+        stream.get_mut().write_all(line.as_bytes()).await?;
+    }
+    let mut reply = String::new();
+    loop {
+        reply.clear();
+        if stream.read_line(&mut reply).await? == 0 {
+            return Ok(Err("соединение закрыто".to_string()));
+        }
+        // «250-...» — продолжение ответа, «250 ...» — последняя строка
+        if reply.as_bytes().get(3) != Some(&b'-') {
+            break;
+        }
+    }
+    if reply.starts_with(expected) { Ok(Ok(())) } else { Ok(Err(reply.trim().to_string())) }
+}
+
+impl SmtpMailer {
+    async fn deliver(&self, email: &Email) -> std::io::Result<Result<(), String>> {
+        let mut stream = BufReader::new(TcpStream::connect(&self.address).await?);
+        let steps = [
+            (None, "220"),
+            (Some("EHLO localhost\r\n".to_string()), "250"),
+            (Some(format!("MAIL FROM:<{}>\r\n", self.from)), "250"),
+            (Some(format!("RCPT TO:<{}>\r\n", email.to)), "250"),
+            (Some("DATA\r\n".to_string()), "354"),
+            (Some(smtp_message(&self.from, email)), "250"),
+        ];
+        for (line, expected) in steps {
+            if let Err(reply) = command(&mut stream, line.as_deref(), expected).await? {
+                return Ok(Err(reply));
+            }
+        }
+        let _ = command(&mut stream, Some("QUIT\r\n"), "221").await;
+        Ok(Ok(()))
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        let failed = |reason: String| {
+            AppError::new(StatusCode::BAD_GATEWAY, &format!("SMTP-сервер {} не принял письмо: {}", self.address, reason))
+        };
+        match tokio::time::timeout(SEND_TIMEOUT, self.deliver(email)).await {
+            Ok(Ok(Ok(()))) => Ok(()),
+            Ok(Ok(Err(reply))) => Err(failed(reply)),
+            Ok(Err(e)) => Err(failed(e.to_string())),
+            Err(_) => Err(failed("таймаут".to_string())),
+        }
+    }
+}
+
+// Отправитель выбирается один раз: SMTP, если он настроен, иначе лог.
+static MAILER: Lazy<Box<dyn Mailer>> = Lazy::new(|| match config::smtp_address() {
+    Some(address) => Box::new(SmtpMailer { address, from: config::smtp_from() }),
+    None => {
+        tracing::warn!("SMTP_HOST не задан, письма будут выводиться в лог");
+        Box::new(ConsoleMailer)
+    }
+});
+
+/// Отправляет письмо настроенным способом.
+pub async fn send(email: &Email) -> Result<(), AppError> {
+    MAILER.send(email).await
+}
+
+/// Отправляет письмо в фоне, чтобы не задерживать ответ; ошибка пишется в лог.
+pub fn send_later(email: Email) {
+    tokio::spawn(async move {
+        if let Err(e) = send(&email).await {
+            tracing::warn!("Письмо для {} не отправлено ({}): {}", email.to, MAILER.name(), e.message());
+        }
+    });
+}
@@ -6,6 +6,96 @@ mod models;
 mod handlers;
 mod auth;
 mod errors;
+mod jobs;
+mod achievements;
+mod media;
+mod config;
+mod classes;
+mod notifications;
+mod assignments;
+mod gradebook;
+mod shadowing;
+mod matching;
+mod progress;
+mod worksheets;
+mod hsk;
+mod plans;
+mod presence;
+mod unihan;
+mod dictionary;
+mod difficulty;
+mod decomposition;
+mod graph;
+mod contributions;
+mod bulk;
+mod announcements;
+mod export;
+mod settings;
+mod hygiene;
+mod srs;
+mod study_list;
+mod ext;
+mod content;
+mod lessons;
+mod decks;
+mod sync;
+mod search;
+mod public;
+mod cedict;
+mod doctor;
+mod hieroglyph_csv;
+mod blueprints;
+mod anki;
+mod compose;
+mod discussion;
+mod strokes;
+mod audio;
+mod startup;
+mod assist;
+mod test_builder;
+mod caching;
+mod events;
+mod quiz;
+mod hidden;
+mod distractors;
+mod classroom;
+mod streak;
+mod snapshots;
+mod gamification;
+mod friends;
+mod registration;
+mod mailer;
+mod password_reset;
+mod reader;
+mod email_verification;
+mod groups;
+mod rate_limit;
+mod review_load;
+mod lockout;
+mod importer;
+mod security_log;
+mod sessions;
+mod consent;
+mod password_policy;
+mod mining;
+mod typing;
+mod stats;
+mod jwt_keys;
+mod attempts;
+mod authz;
+mod http_cache;
+mod local_api;
+mod media_cache;
+mod offline;
+mod profiles;
+mod reader_player;
+mod paged_model;
+mod keybindings;
+mod app;
+
+// Server tests, compiled only for `cargo test`
+#[cfg(test)]
+mod tests;
 
 pub use models::AppState;
 
@@ -15,17 +105,21 @@ use axum::{
 };
 use dotenvy::dotenv;
 use rdev::display_size;
-use slint::{ComponentHandle, LogicalPosition, LogicalSize, SharedString};
+use slint::{ComponentHandle, LogicalPosition, LogicalSize, Model, ModelRc, SharedString};
 use sqlx::postgres::PgPoolOptions;
 use std::cell::RefCell;
 use reqwest::Client;
-use crate::models::{LoginPayload, RegisterPayload, AuthResponse}; // Assuming these are public
+use crate::models::{LoginPayload, RegisterPayload, AuthResponse, Announcement, OnboardingState, ClientConfig, Deck, ContentType, SearchResponse, HieroglyphStrokes, ReadinessStatus, StartupPhase, ReviewItem, ReviewAnswerPayload, UserSummary, UserRole, CreateHieroglyphPayload, Contribution, ContributionPayload, ContentAssist, ExistingContent, StreakSummary, PassageSummary, PassageTiming, CursorPage, Paginated, Hieroglyph, Notification, ResultHistoryItem, SessionInfo, TypingPrompt, TypingAnswer, TypingRunPayload, TypingRunResult}; // Assuming these are public
+use serde::de::DeserializeOwned;
 use serde_json::Value; // For parsing generic error messages
 use std::net::SocketAddr;
+use std::process::ExitCode;
 use std::rc::Rc;
+use paged_model::{Page, PagedModel};
+use keybindings::{ReviewAction, REVIEW_KEYS};
 
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Mutex;
 use bcrypt::{hash, verify, DEFAULT_COST};
 
@@ -35,6 +129,36 @@ static USERS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| {
     Mutex::new(HashMap::new())
 });
 
+// Access token of the active profile; empty in offline mode.
+static AUTH_TOKEN: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+// Remote configuration fetched from the server at startup.
+static CLIENT_CONFIG: Lazy<Mutex<Option<ClientConfig>>> = Lazy::new(|| Mutex::new(None));
+
+// Due cards of the flashcard screen; the front one is on screen.
+static FLASHCARD_QUEUE: Lazy<Mutex<VecDeque<ReviewItem>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+// When the card on screen was shown, to report the answer latency
+static FLASHCARD_SHOWN_AT: Lazy<Mutex<Option<std::time::Instant>>> = Lazy::new(|| Mutex::new(None));
+// Card rated last, put back on screen if the grade is undone
+static LAST_RATED: Lazy<Mutex<Option<ReviewItem>>> = Lazy::new(|| Mutex::new(None));
+
+// Typing drill in progress; the front prompt is on screen.
+static TYPING_DRILL: Lazy<Mutex<TypingDrill>> = Lazy::new(|| Mutex::new(TypingDrill::default()));
+
+// Passage open in the reader and its audio, kept for replaying.
+static READER_PASSAGE: Lazy<Mutex<Option<(PassageTiming, Vec<u8>)>>> = Lazy::new(|| Mutex::new(None));
+
+/// How many due cards one flashcard session fetches.
+const FLASHCARD_BATCH: u32 = 50;
+
+#[derive(Default)]
+struct TypingDrill {
+    prompts: VecDeque<TypingPrompt>,
+    answers: Vec<TypingAnswer>,
+    // When the prompt on screen appeared: the answer time includes IME composition
+    shown_at: Option<std::time::Instant>,
+}
+
 fn handle_signup(nickname: String, password: String) -> bool {
     // FUTURE: This function will make an HTTP POST request to a /signup endpoint.
     // For now, it simulates direct user creation.
@@ -88,53 +212,1994 @@ fn handle_signin(nickname: String, password: String) -> bool {
     }
 }
 
-fn main()
-{
-    let authenticationWindow = authentication::new().unwrap();
-    let mainAppWindowHandle: Rc<RefCell<Option<mainApp>>> = Rc::new(RefCell::new(None));
+/// Base URL of the API server: the active profile's server, otherwise `API_BASE_URL`.
+fn api_base_url() -> String {
+    profiles::active_server()
+        .unwrap_or_else(|| std::env::var("API_BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:3000".to_string()))
+}
 
-    // Weak reference for callbacks
-    let weakAuthentication = authenticationWindow.as_weak();
+/// Whether a server feature is available. Unknown features and a missing
+/// config (server unreachable) count as enabled, so the client keeps working offline.
+fn feature_enabled(name: &str) -> bool {
+    CLIENT_CONFIG
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|config| config.features.get(name).copied())
+        .unwrap_or(true)
+}
 
-    // Clone for on_authenticate
-    let mainAppWindowHandleClone = mainAppWindowHandle.clone();
-    let auth_weak_for_auth = weakAuthentication.clone(); // Clone weak ref
+/// Parses a `major.minor.patch` version; missing parts count as zero.
+fn parse_version(version: &str) -> (u32, u32, u32) {
+    let mut parts = version.trim().split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
 
-    authenticationWindow.on_authenticate(move |nickName, password| {
-        let nickName_str: String = nickName.into();
-        let password_str: String = password.into();
-        if handle_signin(nickName_str.clone(), password_str) {
-            if let Some(app_auth) = auth_weak_for_auth.upgrade() { // Use the cloned weak ref
-                app_auth.global::<status>().set_auth_status_message("".into());
+/// Fetches the remote client configuration and warns if this build is no longer supported.
+fn load_client_config(weakAuthentication: slint::Weak<authentication>) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let url = format!("{}/api/client-config", api_base_url());
+        let result = runtime.block_on(http_cache::get_json::<ClientConfig>(&Client::new(), &url, None));
+
+        match result {
+            Ok(Some(config)) => {
+                let outdated = parse_version(env!("CARGO_PKG_VERSION")) < parse_version(&config.min_client_version);
+                *CLIENT_CONFIG.lock().unwrap() = Some(config);
+
+                if outdated {
+                    let _ = slint::invoke_from_event_loop(move || {
+                        if let Some(app_auth) = weakAuthentication.upgrade() {
+                            app_auth.global::<status>().set_auth_status_message(
+                                "Версия приложения устарела. Обновите клиент, часть функций может не работать.".into(),
+                            );
+                        }
+                    });
+                }
+            }
+            Ok(None) => println!("Failed to load client config: not found"),
+            Err(e) => println!("Failed to load client config: {:?}", e),
+        }
+    });
+}
+
+/// How many times to poll `/readyz` while the server reports it is still starting.
+const READY_POLL_ATTEMPTS: u32 = 120;
+
+/// Waits in the background until the server is ready, showing its startup phase on the
+/// sign-in screen, then loads the client config. The window stays responsive meanwhile;
+/// an unreachable server means offline mode, so polling stops right away.
+fn wait_for_server(weakAuthentication: slint::Weak<authentication>) {
+    let show_message = |weak: &slint::Weak<authentication>, message: String| {
+        let weak = weak.clone();
+        let _ = slint::invoke_from_event_loop(move || {
+            if let Some(app_auth) = weak.upgrade() {
+                app_auth.global::<status>().set_auth_status_message(message.into());
+            }
+        });
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+        let client = Client::new();
+        let mut waited = false;
+
+        for _ in 0..READY_POLL_ATTEMPTS {
+            let result = runtime.block_on(async {
+                client
+                    .get(format!("{}/readyz", api_base_url()))
+                    .timeout(std::time::Duration::from_secs(3))
+                    .send()
+                    .await?
+                    .json::<ReadinessStatus>()
+                    .await
+            });
+
+            match result {
+                Ok(readiness) if readiness.ready => {
+                    if waited {
+                        show_message(&weakAuthentication, "".to_string());
+                    }
+                    load_client_config(weakAuthentication);
+                    return;
+                }
+                Ok(readiness) if readiness.phase == StartupPhase::Failed => {
+                    show_message(&weakAuthentication, "Сервер не смог запуститься, работа только офлайн".to_string());
+                    return;
+                }
+                Ok(readiness) => {
+                    waited = true;
+                    let message = match readiness.phase {
+                        StartupPhase::Migrating => "Сервер обновляет базу данных...",
+                        _ => "Сервер подключается к базе данных...",
+                    };
+                    show_message(&weakAuthentication, message.to_string());
+                }
+                Err(e) => {
+                    println!("Server is not reachable, working offline: {:?}", e);
+                    if waited {
+                        show_message(&weakAuthentication, "".to_string());
+                    }
+                    return;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+
+        show_message(&weakAuthentication, "Сервер долго не отвечает, работа только офлайн".to_string());
+    });
+}
+
+/// Fetches announcements in a background thread and shows them in the home view.
+fn load_announcements(weakMainApp: slint::Weak<mainApp>) {
+    if !feature_enabled("announcements") {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                println!("Failed to start runtime for announcements: {:?}", e);
+                return;
+            }
+        };
+
+        let result = runtime.block_on(async {
+            Client::new()
+                .get(format!("{}/api/announcements", api_base_url()))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<Announcement>>()
+                .await
+        });
+
+        match result {
+            Ok(announcements) => {
+                let items: Vec<AnnouncementItem> = announcements
+                    .into_iter()
+                    .map(|a| AnnouncementItem {
+                        title: a.title.into(),
+                        body: a.body.into(),
+                        date: a.created_at.format("%d.%m.%Y").to_string().into(),
+                        read: a.read,
+                    })
+                    .collect();
+
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(app_main) = weakMainApp.upgrade() {
+                        app_main.set_announcements(Rc::new(slint::VecModel::from(items)).into());
+                    }
+                });
+            }
+            Err(e) => println!("Failed to load announcements: {:?}", e),
+        }
+    });
+}
+
+/// Asks where to save the export, then downloads it in a background thread.
+fn export_user_data(weakMainApp: slint::Weak<mainApp>, format: String) {
+    let set_status = |weak: &slint::Weak<mainApp>, message: String| {
+        let weak = weak.clone();
+        let _ = slint::invoke_from_event_loop(move || {
+            if let Some(app_main) = weak.upgrade() {
+                app_main.set_exportStatus(message.into());
+            }
+        });
+    };
+
+    if !feature_enabled("export") {
+        set_status(&weakMainApp, "Экспорт данных временно недоступен".to_string());
+        return;
+    }
+
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        set_status(&weakMainApp, "Экспорт доступен после входа через сервер".to_string());
+        return;
+    };
+
+    let (file_name, extension) = match format.as_str() {
+        "csv" => ("mandarin-progress.csv", "csv"),
+        _ => ("mandarin-export.json", "json"),
+    };
+    let Some(path) = rfd::FileDialog::new()
+        .set_file_name(file_name)
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file()
+    else {
+        return;
+    };
+
+    set_status(&weakMainApp, "Экспорт...".to_string());
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                println!("Failed to start runtime for export: {:?}", e);
+                return;
+            }
+        };
+
+        let result = runtime.block_on(async {
+            Client::new()
+                .get(format!("{}/api/me/export", api_base_url()))
+                .query(&[("format", format.as_str())])
+                .bearer_auth(token)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        });
+
+        let message = match result {
+            Ok(bytes) => match std::fs::write(&path, &bytes) {
+                Ok(()) => format!("Данные сохранены в {}", path.display()),
+                Err(e) => format!("Не удалось сохранить файл: {}", e),
+            },
+            Err(e) => format!("Не удалось выгрузить данные: {}", e),
+        };
+        set_status(&weakMainApp, message);
+    });
+}
+
+/// Shows the current media cache size on the profile page.
+fn show_cache_usage(weakMainApp: slint::Weak<mainApp>) {
+    let usage = media_cache::format_usage(media_cache::usage());
+    let _ = slint::invoke_from_event_loop(move || {
+        if let Some(app_main) = weakMainApp.upgrade() {
+            app_main.set_cacheUsage(usage.into());
+        }
+    });
+}
+
+/// Prefetches media for the review queue so reviews work offline.
+fn prefetch_media(weakMainApp: slint::Weak<mainApp>) {
+    if !feature_enabled("media_prefetch") {
+        return;
+    }
 
-                let mainAppWindow = mainApp::new().unwrap();
-                mainAppWindow.set_nickName(nickName.into()); // Use original SharedString or new String
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
 
-                let weakMainApp = mainAppWindow.as_weak();
-                mainAppWindow.on_exit(move || {
+    media_cache::prefetch_review_media(token, move || show_cache_usage(weakMainApp));
+}
+
+/// Loads the deck list for the offline download panel.
+fn load_decks(weakMainApp: slint::Weak<mainApp>) {
+    if !feature_enabled("offline_decks") {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let url = format!("{}/api/decks", api_base_url());
+        let result = runtime.block_on(http_cache::get_json::<Vec<Deck>>(&Client::new(), &url, None));
+
+        match result {
+            Ok(decks) => {
+                let decks = decks.unwrap_or_default();
+                let items: Vec<OfflineDeckItem> = decks
+                    .into_iter()
+                    .map(|deck| {
+                        let downloaded = offline::load(deck.id).is_some();
+                        OfflineDeckItem {
+                            id: deck.id,
+                            name: deck.name.into(),
+                            itemCount: deck.item_count as i32,
+                            progress: if downloaded { 1.0 } else { 0.0 },
+                            status: if downloaded { "Скачана".into() } else { "".into() },
+                            downloading: false,
+                        }
+                    })
+                    .collect();
+
+                let _ = slint::invoke_from_event_loop(move || {
                     if let Some(app_main) = weakMainApp.upgrade() {
-                        app_main.hide().unwrap();
+                        app_main.set_offlineDecks(Rc::new(slint::VecModel::from(items)).into());
                     }
                 });
+            }
+            Err(e) => println!("Failed to load decks: {:?}", e),
+        }
+    });
+}
+
+/// Updates one row of the offline deck list from any thread.
+fn update_offline_deck(weakMainApp: &slint::Weak<mainApp>, deckId: i32, progress: f32, status: String, downloading: bool) {
+    let weakMainApp = weakMainApp.clone();
+    let _ = slint::invoke_from_event_loop(move || {
+        let Some(app_main) = weakMainApp.upgrade() else {
+            return;
+        };
+        let decks = app_main.get_offlineDecks();
+        for row in 0..decks.row_count() {
+            if let Some(mut deck) = decks.row_data(row).filter(|deck| deck.id == deckId) {
+                deck.progress = progress;
+                deck.status = status.into();
+                deck.downloading = downloading;
+                decks.set_row_data(row, deck);
+                break;
+            }
+        }
+    });
+}
 
-                let (screenWidth, screenHeight) = display_size().unwrap();
-                let (screenWidth_f32, screenHeight_f32) = (screenWidth as f32, screenHeight as f32);
-                let (width, height) = (1280.0, 720.0);
+/// Downloads a deck with its media for offline study, reporting progress in the deck list.
+fn download_offline_deck(weakMainApp: slint::Weak<mainApp>, deckId: i32) {
+    update_offline_deck(&weakMainApp, deckId, 0.0, "Подготовка...".to_string(), true);
 
-                mainAppWindow.window().set_size(LogicalSize::new(width, height));
-                mainAppWindow.window().set_position(LogicalPosition::new((screenWidth_f32 - width) / 2.0, (screenHeight_f32 - height) / 2.0));
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
 
-                mainAppWindow.show().unwrap();
-                app_auth.hide().unwrap(); // use app_auth here
-                *mainAppWindowHandleClone.borrow_mut() = Some(mainAppWindow);
+        let progressWeak = weakMainApp.clone();
+        let result = runtime.block_on(offline::download_deck(&Client::new(), deckId, |done, total| {
+            let progress = if total == 0 { 1.0 } else { done as f32 / total as f32 };
+            update_offline_deck(&progressWeak, deckId, progress, format!("{} из {}", done, total), true);
+        }));
+
+        match result {
+            Ok(deck) => {
+                let message = format!("Скачана, {} элементов", deck.items.len());
+                update_offline_deck(&weakMainApp, deckId, 1.0, message, false);
+            }
+            Err(e) => update_offline_deck(&weakMainApp, deckId, 0.0, format!("Ошибка: {}", e), false),
+        }
+        show_cache_usage(weakMainApp);
+    });
+}
+
+/// Finds a character on the server and shows it with its stroke-order animation.
+fn show_hieroglyph(weakMainApp: slint::Weak<mainApp>, query: String) {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return;
+    }
+    let token = AUTH_TOKEN.lock().unwrap().clone();
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result: Result<Option<(String, String, String, Option<HieroglyphStrokes>)>, String> =
+            runtime.block_on(async {
+                let client = Client::new();
+                let mut request = client
+                    .get(format!("{}/api/search", api_base_url()))
+                    .query(&[("q", query.as_str()), ("limit", "5")]);
+                if let Some(token) = &token {
+                    request = request.bearer_auth(token);
+                }
+                let search = async { request.send().await?.error_for_status()?.json::<SearchResponse>().await }
+                    .await
+                    .map_err(|e| e.to_string())?;
+
+                // Exact match first, otherwise the best-ranked character
+                let hits: Vec<_> = search
+                    .groups
+                    .into_iter()
+                    .filter(|group| group.content_type == ContentType::Hieroglyph)
+                    .flat_map(|group| group.items)
+                    .collect();
+                let Some(hit) = hits.iter().find(|hit| hit.text == query).or(hits.first()).cloned() else {
+                    return Ok(None);
+                };
+
+                // Stroke data rarely changes, so it is revalidated through the HTTP cache;
+                // 404 means there's no stroke data for this character yet
+                let url = format!("{}/api/hieroglyphs/{}/strokes", api_base_url(), hit.content_id);
+                let strokes = http_cache::get_json::<HieroglyphStrokes>(&client, &url, token.as_deref()).await?;
+                Ok(Some((hit.text, hit.pinyin, hit.translation, strokes)))
+            });
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(app_main) = weakMainApp.upgrade() else {
+                return;
+            };
+            match result {
+                Ok(Some((character, pinyin, translation, strokes))) => {
+                    let paths: Vec<SharedString> =
+                        strokes.map(|s| s.strokes.into_iter().map(SharedString::from).collect()).unwrap_or_default();
+                    app_main.set_detailStatus(if paths.is_empty() {
+                        "Для этого иероглифа пока нет данных о порядке черт".into()
+                    } else {
+                        "".into()
+                    });
+                    app_main.set_detailCharacter(character.into());
+                    app_main.set_detailPinyin(pinyin.into());
+                    app_main.set_detailTranslation(translation.into());
+                    app_main.set_detailPlaying(!paths.is_empty());
+                    app_main.set_detailShownStrokes(0);
+                    app_main.set_detailStrokes(Rc::new(slint::VecModel::from(paths)).into());
+                }
+                Ok(None) => app_main.set_detailStatus("Иероглиф не найден".into()),
+                Err(e) => app_main.set_detailStatus(format!("Не удалось загрузить иероглиф: {}", e).into()),
             }
+        });
+    });
+}
+
+/// Shows the card at the front of the queue, face up; an empty queue shows the "done" state.
+fn show_flashcard(app_main: &mainApp) {
+    let queue = FLASHCARD_QUEUE.lock().unwrap();
+    app_main.set_cardFlipped(false);
+    app_main.set_cardsRemaining(queue.len() as i32);
+    *FLASHCARD_SHOWN_AT.lock().unwrap() = queue.front().map(|_| std::time::Instant::now());
+    if let Some(card) = queue.front() {
+        app_main.set_cardFront(card.front.clone().into());
+        app_main.set_cardPinyin(card.pinyin.clone().into());
+        app_main.set_cardTranslation(card.translation.clone().into());
+    }
+}
+
+/// Fetches the due-review queue for the flashcard screen.
+fn load_flashcards(weakMainApp: slint::Weak<mainApp>) {
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        if let Some(app_main) = weakMainApp.upgrade() {
+            app_main.set_cardsStatus("Карточки доступны после входа на сервер".into());
+        }
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(async {
+            Client::new()
+                .get(format!("{}/api/review/queue", api_base_url()))
+                .query(&[("limit", FLASHCARD_BATCH)])
+                .bearer_auth(token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<ReviewItem>>()
+                .await
+        });
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(app_main) = weakMainApp.upgrade() else {
+                return;
+            };
+            match result {
+                Ok(items) => {
+                    app_main.set_cardsStatus(if items.is_empty() {
+                        "Повторять пока нечего, загляните позже".into()
+                    } else {
+                        "".into()
+                    });
+                    *FLASHCARD_QUEUE.lock().unwrap() = items.into();
+                    show_flashcard(&app_main);
+                }
+                Err(e) => app_main.set_cardsStatus(format!("Не удалось загрузить карточки: {}", e).into()),
+            }
+        });
+    });
+}
+
+/// Rates the card on screen (SM-2 quality 0-5), moves on to the next one
+/// right away and posts the answer in the background.
+fn rate_flashcard(weakMainApp: slint::Weak<mainApp>, quality: i32) {
+    let Some(card) = FLASHCARD_QUEUE.lock().unwrap().pop_front() else {
+        return;
+    };
+    let latency_ms = FLASHCARD_SHOWN_AT.lock().unwrap().map(|shown_at| shown_at.elapsed().as_millis() as u32);
+    *LAST_RATED.lock().unwrap() = Some(card.clone());
+    if let Some(app_main) = weakMainApp.upgrade() {
+        show_flashcard(&app_main);
+    }
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let payload = ReviewAnswerPayload {
+            content_type: card.content_type,
+            content_id: card.content_id,
+            quality: quality.clamp(0, 5) as u8,
+            latency_ms,
+        };
+        let result = runtime.block_on(async {
+            Client::new()
+                .post(format!("{}/api/review/answer", api_base_url()))
+                .bearer_auth(token)
+                .json(&payload)
+                .send()
+                .await?
+                .error_for_status()
+        });
+
+        match result {
+            // The answer may have extended today's streak
+            Ok(_) => load_streak(weakMainApp),
+            Err(e) => {
+                println!("Failed to save review answer: {:?}", e);
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(app_main) = weakMainApp.upgrade() {
+                        app_main.set_cardsStatus(format!("Ответ для «{}» не сохранился: {}", card.front, e).into());
+                    }
+                });
+            }
+        }
+    });
+}
+
+/// Takes back the last grade on the server and puts its card back on screen.
+fn undo_flashcard(weakMainApp: slint::Weak<mainApp>) {
+    let Some(card) = LAST_RATED.lock().unwrap().take() else {
+        return;
+    };
+    api_request(
+        weakMainApp,
+        |client| client.post(format!("{}/api/reviews/undo", api_base_url())),
+        move |app_main, _| {
+            FLASHCARD_QUEUE.lock().unwrap().push_front(card);
+            app_main.set_cardsStatus("".into());
+            show_flashcard(app_main);
+        },
+        |app_main, message| app_main.set_cardsStatus(format!("Не удалось отменить ответ: {}", message).into()),
+    );
+}
+
+/// Handles a key pressed on the flashcard screen; returns whether it was bound.
+/// Grades only apply once the card is flipped, like the rate buttons.
+fn flashcard_key(weakMainApp: slint::Weak<mainApp>, key: &str) -> bool {
+    let Some(action) = REVIEW_KEYS.action(key) else {
+        return false;
+    };
+    let Some(app_main) = weakMainApp.upgrade() else {
+        return false;
+    };
+    let has_card = !FLASHCARD_QUEUE.lock().unwrap().is_empty();
+    match action {
+        ReviewAction::Flip => {
+            if has_card {
+                app_main.set_cardFlipped(true);
+            }
+        }
+        ReviewAction::Undo => undo_flashcard(weakMainApp),
+        ReviewAction::Skip => {
+            {
+                let mut queue = FLASHCARD_QUEUE.lock().unwrap();
+                if let Some(card) = queue.pop_front() {
+                    queue.push_back(card);
+                }
+            }
+            show_flashcard(&app_main);
+        }
+        grade => {
+            if let Some(quality) = grade.quality() {
+                if has_card && app_main.get_cardFlipped() {
+                    rate_flashcard(weakMainApp, quality);
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Shows the prompt at the front of the typing drill and starts its timer.
+fn show_typing_prompt(app_main: &mainApp, drill: &mut TypingDrill) {
+    app_main.set_typingRemaining(drill.prompts.len() as i32);
+    app_main.set_typingInput("".into());
+    if let Some(prompt) = drill.prompts.front() {
+        app_main.set_typingPinyin(prompt.pinyin.clone().into());
+        app_main.set_typingTranslation(prompt.translation.clone().unwrap_or_default().into());
+        drill.shown_at = Some(std::time::Instant::now());
+    }
+}
+
+/// Fetches prompts for a new typing drill run.
+fn start_typing_drill(weakMainApp: slint::Weak<mainApp>) {
+    if AUTH_TOKEN.lock().unwrap().is_none() {
+        if let Some(app_main) = weakMainApp.upgrade() {
+            app_main.set_typingStatus("Тренажер доступен после входа на сервер".into());
+        }
+        return;
+    }
+
+    api_request(
+        weakMainApp,
+        |client| client.get(format!("{}/api/typing/drill", api_base_url())),
+        |app_main, body| {
+            let prompts: Vec<TypingPrompt> = serde_json::from_value(body).unwrap_or_default();
+            if prompts.is_empty() {
+                app_main.set_typingStatus("Нет слов для тренировки".into());
+                return;
+            }
+            app_main.set_typingStatus("".into());
+            app_main.set_typingFeedback("".into());
+            app_main.set_typingSummary("".into());
+            let mut drill = TYPING_DRILL.lock().unwrap();
+            *drill = TypingDrill { prompts: prompts.into(), ..Default::default() };
+            show_typing_prompt(app_main, &mut drill);
+        },
+        |app_main, message| app_main.set_typingStatus(format!("Не удалось начать заход: {}", message).into()),
+    );
+}
+
+/// Records the answer to the prompt on screen and moves on; after the last
+/// prompt the run is sent to the server, which checks it and compares it with
+/// the personal best.
+fn answer_typing(weakMainApp: slint::Weak<mainApp>, typed: String) {
+    let Some(app_main) = weakMainApp.upgrade() else {
+        return;
+    };
+    let mut drill = TYPING_DRILL.lock().unwrap();
+    let Some(prompt) = drill.prompts.pop_front() else {
+        return;
+    };
+
+    let elapsed = drill.shown_at.map(|shown| shown.elapsed().as_millis()).unwrap_or(0);
+    let correct = typing::is_correct(&typed, &prompt.text);
+    app_main.set_typingFeedbackCorrect(correct);
+    app_main.set_typingFeedback(if correct {
+        format!("Верно: {} — {}", prompt.text, prompt.pinyin).into()
+    } else {
+        format!("Нужно было: {} — {}", prompt.text, prompt.pinyin).into()
+    });
+    drill.answers.push(TypingAnswer {
+        content_type: prompt.content_type,
+        content_id: prompt.content_id,
+        typed,
+        elapsed_ms: elapsed.clamp(1, u32::MAX as u128) as u32,
+    });
+    show_typing_prompt(&app_main, &mut drill);
+    if !drill.prompts.is_empty() {
+        return;
+    }
+
+    let payload = TypingRunPayload { answers: std::mem::take(&mut drill.answers) };
+    app_main.set_typingStatus("Сохраняем результат…".into());
+    api_request(
+        weakMainApp,
+        move |client| client.post(format!("{}/api/typing/runs", api_base_url())).json(&payload),
+        |app_main, body| {
+            app_main.set_typingStatus("".into());
+            let Ok(result) = serde_json::from_value::<TypingRunResult>(body) else {
+                return;
+            };
+            let mut summary = format!(
+                "Скорость: {:.1} иероглифа в минуту, верных ответов {} из {} ({:.0}%).",
+                result.run.chars_per_minute, result.run.correct_count, result.run.item_count, result.run.accuracy
+            );
+            if result.new_best {
+                summary.push_str(" Новый личный рекорд!");
+            } else if let Some(best) = result.previous_best {
+                summary.push_str(&format!(" Личный рекорд: {:.1}.", best));
+            }
+            app_main.set_typingSummary(summary.into());
+        },
+        |app_main, message| app_main.set_typingStatus(format!("Результат не сохранился: {}", message).into()),
+    );
+}
+
+/// Fetches the passages that have aligned audio for the reader.
+fn load_passages(weakMainApp: slint::Weak<mainApp>) {
+    let token = AUTH_TOKEN.lock().unwrap().clone();
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(async {
+            let mut request = Client::new().get(format!("{}/api/passages", api_base_url()));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request.send().await?.error_for_status()?.json::<Vec<PassageSummary>>().await
+        });
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(app_main) = weakMainApp.upgrade() else {
+                return;
+            };
+            match result {
+                Ok(passages) => {
+                    let items: Vec<PassageItem> = passages
+                        .into_iter()
+                        .map(|passage| PassageItem { id: passage.id, title: passage.title.into() })
+                        .collect();
+                    app_main.set_readerPassages(Rc::new(slint::VecModel::from(items)).into());
+                }
+                Err(e) => app_main.set_readerStatus(format!("Не удалось загрузить тексты: {}", e).into()),
+            }
+        });
+    });
+}
+
+/// Loads the passage timing and audio, shows the two panes and starts playback.
+fn open_passage(weakMainApp: slint::Weak<mainApp>, passageId: i32) {
+    reader_player::stop();
+    let token = AUTH_TOKEN.lock().unwrap().clone();
+    if let Some(app_main) = weakMainApp.upgrade() {
+        app_main.set_readerPlaying(false);
+        app_main.set_readerStatus("Загрузка...".into());
+    }
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(async {
+            let client = Client::new();
+            let mut request = client.get(format!("{}/api/passages/{}/timing", api_base_url(), passageId));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let timing = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| e.to_string())?
+                .json::<PassageTiming>()
+                .await
+                .map_err(|e| e.to_string())?;
+            // Audio goes through the media cache, so a passage read once plays offline
+            let audio = media_cache::fetch(&client, &timing.audio_url).await?;
+            Ok::<_, String>((timing, audio))
+        });
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(app_main) = weakMainApp.upgrade() else {
+                return;
+            };
+            match result {
+                Ok((timing, audio)) => {
+                    let sentences: Vec<ReaderSentence> = timing
+                        .sentences
+                        .iter()
+                        .map(|sentence| ReaderSentence {
+                            text: sentence.text.clone().into(),
+                            pinyin: sentence.pinyin.clone().into(),
+                            translation: sentence.translation.clone().into(),
+                        })
+                        .collect();
+                    app_main.set_readerTitle(timing.title.clone().into());
+                    app_main.set_readerSentences(Rc::new(slint::VecModel::from(sentences)).into());
+                    app_main.set_readerCurrent(-1);
+                    app_main.set_readerStatus("".into());
+                    reader_player::play(audio.clone());
+                    app_main.set_readerPlaying(true);
+                    *READER_PASSAGE.lock().unwrap() = Some((timing, audio));
+                }
+                Err(e) => app_main.set_readerStatus(format!("Не удалось открыть текст: {}", e).into()),
+            }
+        });
+    });
+}
+
+/// Resumes playback, or starts over once the passage has been read to the end.
+fn reader_play(app_main: &mainApp) {
+    let passage = READER_PASSAGE.lock().unwrap();
+    let Some((timing, audio)) = passage.as_ref() else {
+        return;
+    };
+    let end = timing.sentences.last().map_or(0, |sentence| sentence.end_ms as i64);
+    if reader_player::position_ms() >= end {
+        reader_player::play(audio.clone());
+    } else {
+        reader_player::resume();
+    }
+    app_main.set_readerPlaying(true);
+}
+
+/// Moves the highlight to the sentence being spoken; stops after the last one.
+fn reader_tick(app_main: &mainApp) {
+    let passage = READER_PASSAGE.lock().unwrap();
+    let Some((timing, _)) = passage.as_ref() else {
+        return;
+    };
+    let position = reader_player::position_ms();
+    let current = reader::sentence_at(&timing.sentences, position).map_or(-1, |index| index as i32);
+    if app_main.get_readerCurrent() != current {
+        app_main.set_readerCurrent(current);
+    }
+    if timing.sentences.last().is_some_and(|last| position >= last.end_ms as i64) {
+        reader_player::pause();
+        app_main.set_readerPlaying(false);
+    }
+}
+
+/// Rows requested per page by the paged lists.
+const LIST_PAGE_SIZE: i64 = 50;
+
+/// Fetches one page of a cursor-paginated endpoint (`cursor`/`limit`, `CursorPage` response).
+async fn fetch_cursor_page<R: DeserializeOwned>(path: &str, cursor: Option<String>) -> Result<Page<R>, String> {
+    let token = AUTH_TOKEN.lock().unwrap().clone();
+    let mut request = Client::new()
+        .get(format!("{}{}", api_base_url(), path))
+        .query(&[("limit", LIST_PAGE_SIZE.to_string())]);
+    if let Some(cursor) = cursor {
+        request = request.query(&[("cursor", cursor)]);
+    }
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let page = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<CursorPage<R>>()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Page { rows: page.items, next_cursor: page.next_cursor })
+}
+
+/// Fetches one page of a page-numbered endpoint (`page`/`per_page`, `Paginated` response);
+/// the page number serves as the cursor.
+async fn fetch_numbered_page<R: DeserializeOwned>(path: &str, cursor: Option<String>) -> Result<Page<R>, String> {
+    let page_number: i64 = cursor.and_then(|cursor| cursor.parse().ok()).unwrap_or(1);
+    let token = AUTH_TOKEN.lock().unwrap().clone();
+    let mut request = Client::new()
+        .get(format!("{}{}", api_base_url(), path))
+        .query(&[("page", page_number.to_string()), ("per_page", LIST_PAGE_SIZE.to_string())]);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let page = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<Paginated<R>>()
+        .await
+        .map_err(|e| e.to_string())?;
+    let next_cursor = (page.page < page.total_pages).then(|| (page.page + 1).to_string());
+    Ok(Page { rows: page.items, next_cursor })
+}
+
+fn notification_row(notification: Notification) -> ListRow {
+    ListRow {
+        id: notification.id,
+        title: notification.title.into(),
+        subtitle: notification.body.into(),
+        date: notification.created_at.format("%d.%m.%Y %H:%M").to_string().into(),
+        highlight: notification.read_at.is_none(),
+    }
+}
+
+fn result_row(result: ResultHistoryItem) -> ListRow {
+    let score = if result.total_questions > 0 {
+        format!("{} из {}", result.score, result.total_questions)
+    } else {
+        format!("Баллы: {}", result.score)
+    };
+    ListRow {
+        id: result.result_id,
+        title: result.test_name.into(),
+        subtitle: score.into(),
+        date: result.submitted_at.format("%d.%m.%Y %H:%M").to_string().into(),
+        highlight: false,
+    }
+}
+
+fn hieroglyph_row(hieroglyph: Hieroglyph) -> ListRow {
+    ListRow {
+        id: hieroglyph.id,
+        title: hieroglyph.character.into(),
+        subtitle: format!("{} — {}", hieroglyph.pinyin, hieroglyph.translation).into(),
+        date: hieroglyph.hsk_level.map(|level| format!("HSK {}", level)).unwrap_or_default().into(),
+        highlight: false,
+    }
+}
+
+/// Header text for the study streak, e.g. "Серия: 5 дн. · заморозок: 1".
+fn describe_streak(streak: &StreakSummary) -> String {
+    let mut text = format!("Серия: {} дн.", streak.current_days);
+    if streak.current_days > 0 && !streak.studied_today {
+        text.push_str(" · позанимайтесь сегодня");
+    }
+    if streak.freezes_available > 0 {
+        text.push_str(&format!(" · заморозок: {}", streak.freezes_available));
+    }
+    text
+}
+
+/// Fetches the study streak in a background thread and shows it in the header.
+fn load_streak(weakMainApp: slint::Weak<mainApp>) {
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(async {
+            Client::new()
+                .get(format!("{}/api/streak/me", api_base_url()))
+                .bearer_auth(token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<StreakSummary>()
+                .await
+        });
+
+        match result {
+            Ok(streak) => {
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(app_main) = weakMainApp.upgrade() {
+                        app_main.set_streakText(describe_streak(&streak).into());
+                    }
+                });
+            }
+            Err(e) => println!("Failed to load streak: {:?}", e),
+        }
+    });
+}
+
+/// Error text from a JSON `{"error": ...}` body, or the HTTP status if there is none.
+async fn error_message(response: reqwest::Response) -> String {
+    api_error(response).await.message
+}
+
+/// Error code the server sends once the account is banned or disabled.
+const ACCOUNT_SUSPENDED: &str = "account_suspended";
+
+/// Error reply of the server: its message and the machine-readable `code`, if any.
+struct ApiError {
+    message: String,
+    code: Option<String>,
+}
+
+impl ApiError {
+    fn account_suspended(&self) -> bool {
+        self.code.as_deref() == Some(ACCOUNT_SUSPENDED)
+    }
+}
+
+async fn api_error(response: reqwest::Response) -> ApiError {
+    let status = response.status();
+    let body = response.json::<Value>().await.unwrap_or(Value::Null);
+    ApiError {
+        message: body["error"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Сервер ответил ошибкой {}", status)),
+        code: body["code"].as_str().map(str::to_string),
+    }
+}
+
+/// Role as the UI knows it: moderators get the contributions queue, admins all tools.
+fn ui_role(user_role: &UserRole) -> role {
+    match user_role {
+        UserRole::Admin => role::admin,
+        UserRole::Moderator => role::moderator,
+        UserRole::User => role::user,
+    }
+}
+
+/// Loads the signed-in user's role; admin tools are shown only to admins and
+/// the moderation queue to moderators.
+fn load_current_user(weakMainApp: slint::Weak<mainApp>) {
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result: Result<UserSummary, ApiError> = runtime.block_on(async {
+            let response = Client::new()
+                .get(format!("{}/api/me", api_base_url()))
+                .bearer_auth(token)
+                .send()
+                .await
+                .map_err(|e| ApiError { message: e.to_string(), code: None })?;
+            if !response.status().is_success() {
+                return Err(api_error(response).await);
+            }
+            response.json::<UserSummary>().await.map_err(|e| ApiError { message: e.to_string(), code: None })
+        });
+
+        match result {
+            Ok(user) => {
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(app_main) = weakMainApp.upgrade() {
+                        app_main.global::<status>().set_currentUserRole(ui_role(&user.role));
+                    }
+                });
+            }
+            // Signed in from a saved profile after the account was suspended
+            Err(e) if e.account_suspended() => {
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(app_main) = weakMainApp.upgrade() {
+                        app_main.invoke_accountSuspended();
+                    }
+                });
+            }
+            Err(e) => println!("Failed to load current user: {}", e.message),
+        }
+    });
+}
+
+/// Sends an admin request in the background. `on_done` gets the JSON body
+/// (`Null` for empty responses) on the UI thread; errors go to the admin panel status.
+fn admin_request<F, D>(weakMainApp: slint::Weak<mainApp>, request: F, on_done: D)
+where
+    F: FnOnce(&Client) -> reqwest::RequestBuilder + Send + 'static,
+    D: FnOnce(&mainApp, Value) + Send + 'static,
+{
+    api_request(weakMainApp, request, on_done, |app_main, message| app_main.set_adminStatus(message.into()));
+}
+
+/// Sends an authorized request in the background. `on_done` gets the JSON body
+/// (`Null` for empty responses) on the UI thread, `on_error` the error text.
+/// A suspended account is signed out instead of reporting the error.
+fn api_request<F, D, E>(weakMainApp: slint::Weak<mainApp>, request: F, on_done: D, on_error: E)
+where
+    F: FnOnce(&Client) -> reqwest::RequestBuilder + Send + 'static,
+    D: FnOnce(&mainApp, Value) + Send + 'static,
+    E: FnOnce(&mainApp, String) + Send + 'static,
+{
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result: Result<Value, ApiError> = runtime.block_on(async {
+            let response = request(&Client::new())
+                .bearer_auth(token)
+                .send()
+                .await
+                .map_err(|e| ApiError { message: e.to_string(), code: None })?;
+            if !response.status().is_success() {
+                return Err(api_error(response).await);
+            }
+            let bytes = response.bytes().await.map_err(|e| ApiError { message: e.to_string(), code: None })?;
+            Ok(serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+        });
+
+        let _ = slint::invoke_from_event_loop(move || {
+            if let Some(app_main) = weakMainApp.upgrade() {
+                match result {
+                    Ok(body) => on_done(&app_main, body),
+                    Err(e) if e.account_suspended() => app_main.invoke_accountSuspended(),
+                    Err(e) => on_error(&app_main, e.message),
+                }
+            }
+        });
+    });
+}
+
+/// Extensions accepted as content attachments, with their MIME types.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "ogg"];
+
+fn attachment_mime_type(path: &str) -> Option<&'static str> {
+    let extension = std::path::Path::new(path).extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "mp3" => Some("audio/mpeg"),
+        "ogg" => Some("audio/ogg"),
+        _ => None,
+    }
+}
+
+/// Hieroglyphs with the same character; other content types may share the text.
+fn describe_duplicates(duplicates: &[ExistingContent]) -> String {
+    duplicates
+        .iter()
+        .filter(|existing| existing.content_type == ContentType::Hieroglyph)
+        .map(|existing| format!("иероглиф #{} ({} · {})", existing.id, existing.pinyin, existing.translation))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn fetch_content_assist(client: &Client, token: &str, text: &str) -> Result<ContentAssist, String> {
+    let response = client
+        .get(format!("{}/api/admin/content-assist", api_base_url()))
+        .query(&[("text", text)])
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_message(response).await);
+    }
+    response.json::<ContentAssist>().await.map_err(|e| e.to_string())
+}
+
+/// Uploads a file from disk as a multipart field.
+async fn upload_attachment(client: &Client, token: &str, url: String, field: &'static str, path: &str) -> Result<(), String> {
+    let mime_type = attachment_mime_type(path).ok_or_else(|| format!("Неподдерживаемый формат файла: {}", path))?;
+    let bytes = std::fs::read(path).map_err(|e| format!("Не удалось прочитать {}: {}", path, e))?;
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let part = reqwest::multipart::Part::bytes(bytes)
+        .file_name(file_name)
+        .mime_str(mime_type)
+        .map_err(|e| e.to_string())?;
+
+    let response = client
+        .post(url)
+        .bearer_auth(token)
+        .multipart(reqwest::multipart::Form::new().part(field, part))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_message(response).await);
+    }
+    Ok(())
+}
+
+/// Fills the content form from the server dictionary and flags existing hieroglyphs
+/// with the same character. Only empty fields are filled, so manual edits are kept.
+fn assist_content(weakMainApp: slint::Weak<mainApp>) {
+    let Some(app_main) = weakMainApp.upgrade() else {
+        return;
+    };
+    let form = app_main.global::<editor>();
+    let text = form.get_character().trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        form.set_statusMessage("Редактор доступен после входа на сервер".into());
+        return;
+    };
+    form.set_busy(true);
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(fetch_content_assist(&Client::new(), &token, &text));
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(app_main) = weakMainApp.upgrade() else {
+                return;
+            };
+            let form = app_main.global::<editor>();
+            form.set_busy(false);
+            match result {
+                Ok(assist) => {
+                    if form.get_pinyin().trim().is_empty() {
+                        form.set_pinyin(assist.pinyin.clone().unwrap_or_default().into());
+                    }
+                    if form.get_translation().trim().is_empty() {
+                        form.set_translation(assist.translation.clone().unwrap_or_default().into());
+                    }
+                    // A single segment repeats the pinyin field, so only longer text is shown split
+                    let segments = if assist.segments.len() > 1 {
+                        assist
+                            .segments
+                            .iter()
+                            .map(|segment| format!("{} {}", segment.text, segment.pinyin).trim().to_string())
+                            .collect::<Vec<_>>()
+                            .join(" · ")
+                    } else {
+                        String::new()
+                    };
+                    form.set_segments(segments.into());
+                    form.set_duplicates(describe_duplicates(&assist.duplicates).into());
+                    form.set_statusMessage(if assist.pinyin.is_none() && assist.translation.is_none() {
+                        "В словаре ничего не нашлось".into()
+                    } else {
+                        "".into()
+                    });
+                }
+                Err(message) => form.set_statusMessage(message.into()),
+            }
+        });
+    });
+}
+
+/// Lets the admin choose an attachment file for the content form.
+fn pick_attachment(weakMainApp: slint::Weak<mainApp>, audio: bool) {
+    let (name, extensions) = if audio { ("Аудио", AUDIO_EXTENSIONS) } else { ("Изображения", IMAGE_EXTENSIONS) };
+    let Some(path) = rfd::FileDialog::new().add_filter(name, extensions).pick_file() else {
+        return;
+    };
+    if let Some(app_main) = weakMainApp.upgrade() {
+        let form = app_main.global::<editor>();
+        let path: SharedString = path.display().to_string().into();
+        if audio {
+            form.set_audioPath(path);
         } else {
-            if let Some(app_auth) = auth_weak_for_auth.upgrade() {
-                app_auth.global::<status>().set_auth_status_message("Login failed. Check nickname or password.".into());
+            form.set_imagePath(path);
+        }
+    }
+}
+
+/// Creates a hieroglyph from the content form, then uploads the chosen attachments.
+/// Duplicates are checked again right before creating: the form may have changed since the hint.
+fn submit_content(weakMainApp: slint::Weak<mainApp>) {
+    enum SubmitError {
+        Duplicate(String),
+        Failed(String),
+    }
+
+    let Some(app_main) = weakMainApp.upgrade() else {
+        return;
+    };
+    let form = app_main.global::<editor>();
+    let character = form.get_character().trim().to_string();
+    let pinyin = form.get_pinyin().trim().to_string();
+    let translation = form.get_translation().trim().to_string();
+    let example = form.get_example().trim().to_string();
+    let (imagePath, audioPath) = (form.get_imagePath().to_string(), form.get_audioPath().to_string());
+
+    if character.is_empty() || pinyin.is_empty() || translation.is_empty() {
+        form.set_statusMessage("Заполните иероглиф, пиньинь и перевод".into());
+        return;
+    }
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        form.set_statusMessage("Редактор доступен после входа на сервер".into());
+        return;
+    };
+    form.set_busy(true);
+    form.set_statusMessage("Сохранение...".into());
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result: Result<String, SubmitError> = runtime.block_on(async {
+            let client = Client::new();
+
+            let assist = fetch_content_assist(&client, &token, &character).await.map_err(SubmitError::Failed)?;
+            let duplicates = describe_duplicates(&assist.duplicates);
+            if !duplicates.is_empty() {
+                return Err(SubmitError::Duplicate(duplicates));
+            }
+
+            let payload = CreateHieroglyphPayload {
+                character: character.clone(),
+                pinyin,
+                translation,
+                example: Some(example).filter(|example| !example.is_empty()),
+            };
+            let response = client
+                .post(format!("{}/api/hieroglyphs", api_base_url()))
+                .bearer_auth(&token)
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|e| SubmitError::Failed(e.to_string()))?;
+            if !response.status().is_success() {
+                return Err(SubmitError::Failed(error_message(response).await));
+            }
+            let created: Value = response.json().await.map_err(|e| SubmitError::Failed(e.to_string()))?;
+            let id = created["id"].as_i64().unwrap_or_default();
+
+            // The hieroglyph already exists at this point, so a failed upload is reported, not rolled back
+            let mut failed = Vec::new();
+            if !imagePath.is_empty() {
+                let url = format!("{}/api/hieroglyphs/{}/image", api_base_url(), id);
+                if let Err(e) = upload_attachment(&client, &token, url, "image", &imagePath).await {
+                    failed.push(format!("изображение ({})", e));
+                }
+            }
+            if !audioPath.is_empty() {
+                let url = format!("{}/api/hieroglyphs/{}/audio", api_base_url(), id);
+                if let Err(e) = upload_attachment(&client, &token, url, "audio", &audioPath).await {
+                    failed.push(format!("озвучка ({})", e));
+                }
+            }
+
+            Ok(if failed.is_empty() {
+                format!("Иероглиф {} добавлен", character)
+            } else {
+                format!("Иероглиф {} добавлен, но не загрузились: {}", character, failed.join(", "))
+            })
+        });
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(app_main) = weakMainApp.upgrade() else {
+                return;
+            };
+            let form = app_main.global::<editor>();
+            form.set_busy(false);
+            match result {
+                Ok(message) => {
+                    form.set_character("".into());
+                    form.set_pinyin("".into());
+                    form.set_translation("".into());
+                    form.set_example("".into());
+                    form.set_imagePath("".into());
+                    form.set_audioPath("".into());
+                    form.set_segments("".into());
+                    form.set_statusMessage(message.into());
+                }
+                Err(SubmitError::Duplicate(duplicates)) => {
+                    form.set_duplicates(duplicates.into());
+                    form.set_statusMessage("Такой иероглиф уже есть".into());
+                }
+                Err(SubmitError::Failed(message)) => form.set_statusMessage(message.into()),
+            }
+        });
+    });
+}
+
+/// Loads pending contributions into the moderation queue.
+fn load_contributions(weakMainApp: slint::Weak<mainApp>) {
+    admin_request(
+        weakMainApp,
+        |client| client.get(format!("{}/api/admin/contributions", api_base_url())),
+        |app_main, body| {
+            let contributions: Vec<Contribution> = serde_json::from_value(body).unwrap_or_default();
+            let items: Vec<ContributionItem> = contributions
+                .into_iter()
+                .map(|contribution| {
+                    let summary = match serde_json::from_value::<ContributionPayload>(contribution.payload) {
+                        Ok(ContributionPayload::Word { simplified, pinyin, translation }) => {
+                            format!("{} · {} · {}", simplified, pinyin, translation)
+                        }
+                        Ok(ContributionPayload::Sentence { text, pinyin, translation }) => {
+                            format!("{} · {} · {}", text, pinyin, translation)
+                        }
+                        Err(_) => "Некорректное предложение".to_string(),
+                    };
+                    ContributionItem {
+                        id: contribution.id,
+                        kind: contribution.kind.into(),
+                        summary: summary.into(),
+                        date: contribution.created_at.format("%d.%m.%Y").to_string().into(),
+                    }
+                })
+                .collect();
+            app_main.set_adminContributions(Rc::new(slint::VecModel::from(items)).into());
+        },
+    );
+}
+
+/// Accepts or rejects a contribution and refreshes the queue.
+fn review_contribution(weakMainApp: slint::Weak<mainApp>, id: i32, accept: bool) {
+    let action = if accept { "accept" } else { "reject" };
+    admin_request(
+        weakMainApp,
+        move |client| client.post(format!("{}/api/admin/contributions/{}/{}", api_base_url(), id, action)),
+        move |app_main, _| {
+            app_main.set_adminStatus(if accept { "Предложение принято" } else { "Предложение отклонено" }.into());
+            load_contributions(app_main.as_weak());
+        },
+    );
+}
+
+/// Searches users by nickname for the admin panel.
+fn search_users(weakMainApp: slint::Weak<mainApp>, query: String) {
+    admin_request(
+        weakMainApp,
+        move |client| {
+            client
+                .get(format!("{}/api/admin/users", api_base_url()))
+                .query(&[("q", query.trim())])
+        },
+        |app_main, body| {
+            let users: Vec<UserSummary> = serde_json::from_value(body).unwrap_or_default();
+            let items: Vec<UserItem> = users
+                .into_iter()
+                .map(|user| UserItem {
+                    id: user.id,
+                    nickname: user.nickname.into(),
+                    role: ui_role(&user.role),
+                    banned: user.is_banned,
+                })
+                .collect();
+            app_main.set_adminUsers(Rc::new(slint::VecModel::from(items)).into());
+        },
+    );
+}
+
+/// Bans or unbans a user and updates the row in place.
+fn set_user_banned(weakMainApp: slint::Weak<mainApp>, id: i32, banned: bool) {
+    let action = if banned { "ban" } else { "unban" };
+    admin_request(
+        weakMainApp,
+        move |client| client.post(format!("{}/api/admin/users/{}/{}", api_base_url(), id, action)),
+        move |app_main, _| {
+            let users = app_main.get_adminUsers();
+            for row in 0..users.row_count() {
+                if let Some(mut user) = users.row_data(row).filter(|user| user.id == id) {
+                    user.banned = banned;
+                    users.set_row_data(row, user);
+                }
+            }
+            app_main.set_adminStatus(if banned { "Пользователь заблокирован" } else { "Пользователь разблокирован" }.into());
+        },
+    );
+}
+
+/// Loads the active sessions of the signed-in user for the profile page.
+fn load_sessions(weakMainApp: slint::Weak<mainApp>) {
+    api_request(
+        weakMainApp,
+        |client| client.get(format!("{}/api/sessions/me", api_base_url())),
+        |app_main, body| {
+            let sessions: Vec<SessionInfo> = serde_json::from_value(body).unwrap_or_default();
+            let items: Vec<SessionItem> = sessions.into_iter().map(session_item).collect();
+            app_main.set_sessions(Rc::new(slint::VecModel::from(items)).into());
+            app_main.set_sessionsStatus("".into());
+        },
+        |app_main, message| app_main.set_sessionsStatus(message.into()),
+    );
+}
+
+fn session_item(session: SessionInfo) -> SessionItem {
+    let mut details = format!(
+        "Вход {}, последняя активность {}",
+        session.created_at.format("%d.%m.%Y %H:%M"),
+        session.last_used_at.format("%d.%m.%Y %H:%M")
+    );
+    if let Some(ip) = &session.ip {
+        details.push_str(&format!(", IP {}", ip));
+    }
+    SessionItem {
+        id: session.id.into(),
+        device: session.user_agent.unwrap_or_else(|| "Неизвестное устройство".to_string()).into(),
+        details: details.into(),
+        current: session.current,
+        warning: session.device_mismatch_at.is_some(),
+    }
+}
+
+/// Ends a session on another device and drops it from the list.
+fn revoke_session(weakMainApp: slint::Weak<mainApp>, id: String) {
+    let path = format!("{}/api/sessions/{}", api_base_url(), id);
+    api_request(
+        weakMainApp,
+        move |client| client.delete(path),
+        move |app_main, _| {
+            let sessions = app_main.get_sessions();
+            let items: Vec<SessionItem> = sessions.iter().filter(|session| session.id.as_str() != id).collect();
+            app_main.set_sessions(Rc::new(slint::VecModel::from(items)).into());
+            app_main.set_sessionsStatus("Сеанс завершен".into());
+        },
+        |app_main, message| app_main.set_sessionsStatus(message.into()),
+    );
+}
+
+/// Signs out everywhere. This device's session ends too, so the client goes
+/// back to the account picker.
+fn revoke_all_sessions(weakMainApp: slint::Weak<mainApp>) {
+    api_request(
+        weakMainApp,
+        |client| client.delete(format!("{}/api/sessions", api_base_url())),
+        |app_main, _| app_main.invoke_switchAccount(),
+        |app_main, message| app_main.set_sessionsStatus(message.into()),
+    );
+}
+
+/// Opens the onboarding flow instead of the profile page if the user hasn't finished it yet.
+fn check_onboarding(weakMainApp: slint::Weak<mainApp>) {
+    if !feature_enabled("onboarding") {
+        return;
+    }
+
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(async {
+            Client::new()
+                .get(format!("{}/api/onboarding", api_base_url()))
+                .bearer_auth(token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<OnboardingState>()
+                .await
+        });
+
+        match result {
+            Ok(state) if state.required => {
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(app_main) = weakMainApp.upgrade() {
+                        app_main.set_language(state.settings.interface_language.into());
+                        app_main.set_script(state.settings.script.into());
+                        app_main.global::<status>().set_currentView(view::onboarding);
+                    }
+                });
+            }
+            Ok(_) => {}
+            Err(e) => println!("Failed to load onboarding state: {:?}", e),
+        }
+    });
+}
+
+/// Saves the choices made during onboarding and marks it as finished.
+fn finish_onboarding(language: String, script: String, reminderTime: String, skipped: bool) {
+    let Some(token) = AUTH_TOKEN.lock().unwrap().clone() else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result: Result<(), reqwest::Error> = runtime.block_on(async {
+            let client = Client::new();
+
+            if !skipped {
+                let mut settings = serde_json::json!({ "interface_language": language, "script": script });
+                if let Ok(time) = chrono::NaiveTime::parse_from_str(reminderTime.trim(), "%H:%M") {
+                    settings["reminder_time"] = serde_json::json!(time);
+                }
+                client
+                    .put(format!("{}/api/settings", api_base_url()))
+                    .bearer_auth(&token)
+                    .json(&settings)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+
+            client
+                .post(format!("{}/api/onboarding/finish", api_base_url()))
+                .bearer_auth(&token)
+                .json(&serde_json::json!({ "skipped": skipped }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            println!("Failed to save onboarding: {:?}", e);
+        }
+    });
+}
+
+thread_local! {
+    // Keeps the main window alive; replaced when another account signs in.
+    static MAIN_WINDOW: RefCell<Option<mainApp>> = RefCell::new(None);
+}
+
+/// Fills the account picker with the saved profiles.
+fn show_accounts(app_auth: &authentication) {
+    let items: Vec<AccountItem> = profiles::list()
+        .into_iter()
+        .map(|profile| AccountItem {
+            id: profile.id().into(),
+            nickname: profile.nickname.clone().into(),
+            server: profile.server_url.clone().into(),
+            hasSession: profile.has_session(),
+        })
+        .collect();
+    app_auth.set_savedAccounts(Rc::new(slint::VecModel::from(items)).into());
+}
+
+/// Opens the main window for a signed-in user and hides the sign-in screen.
+fn open_main_window(nickName: String, weakAuthentication: slint::Weak<authentication>) {
+    let Some(app_auth) = weakAuthentication.upgrade() else {
+        return;
+    };
+    app_auth.global::<status>().set_auth_status_message("".into());
+
+    let mainAppWindow = mainApp::new().unwrap();
+    mainAppWindow.set_nickName(nickName.into());
+
+    let weakMainApp = mainAppWindow.as_weak();
+    mainAppWindow.on_exit(move || {
+        if let Some(app_main) = weakMainApp.upgrade() {
+            app_main.hide().unwrap();
+        }
+    });
+
+    // Back to the account picker; the profile stays saved for switching back
+    // (the hidden window is dropped when the next one opens)
+    let weakAuthenticationSwitch = weakAuthentication.clone();
+    let weakMainAppSwitch = mainAppWindow.as_weak();
+    mainAppWindow.on_switchAccount(move || {
+        profiles::sign_out();
+        if let Some(app_auth) = weakAuthenticationSwitch.upgrade() {
+            show_accounts(&app_auth);
+            app_auth.global::<status>().set_currentView(view::accounts);
+            app_auth.show().unwrap();
+        }
+        if let Some(app_main) = weakMainAppSwitch.upgrade() {
+            app_main.hide().unwrap();
+        }
+    });
+
+    // The server suspended the account: nothing else will work, so sign out
+    // and explain why on the sign-in screen
+    let weakAuthenticationSuspended = weakAuthentication.clone();
+    let weakMainAppSuspended = mainAppWindow.as_weak();
+    mainAppWindow.on_accountSuspended(move || {
+        profiles::sign_out();
+        if let Some(app_auth) = weakAuthenticationSuspended.upgrade() {
+            show_accounts(&app_auth);
+            app_auth.global::<status>().set_auth_status_message(
+                "Аккаунт заблокирован. Обратитесь к администратору сервера".into(),
+            );
+            app_auth.global::<status>().set_currentView(view::accounts);
+            app_auth.show().unwrap();
+        }
+        if let Some(app_main) = weakMainAppSuspended.upgrade() {
+            app_main.hide().unwrap();
+        }
+    });
+
+    let (screenWidth, screenHeight) = display_size().unwrap();
+    let (screenWidth_f32, screenHeight_f32) = (screenWidth as f32, screenHeight as f32);
+    let (width, height) = (1280.0, 720.0);
+
+    mainAppWindow.window().set_size(LogicalSize::new(width, height));
+    mainAppWindow.window().set_position(LogicalPosition::new((screenWidth_f32 - width) / 2.0, (screenHeight_f32 - height) / 2.0));
+
+    mainAppWindow.global::<status>().set_currentView(view::profile);
+    load_current_user(mainAppWindow.as_weak());
+    load_streak(mainAppWindow.as_weak());
+    check_onboarding(mainAppWindow.as_weak());
+    load_announcements(mainAppWindow.as_weak());
+
+    // Long lists load page by page as they scroll; opening a view starts from the top
+    let notifications = PagedModel::new(|cursor| fetch_cursor_page::<Notification>("/api/notifications/me", cursor), notification_row);
+    mainAppWindow.set_notificationRows(ModelRc::from(notifications.clone()));
+    notifications.reload();
+    mainAppWindow.on_loadNotifications(move || notifications.reload());
+
+    let results = PagedModel::new(|cursor| fetch_cursor_page::<ResultHistoryItem>("/api/results/me", cursor), result_row);
+    mainAppWindow.set_resultRows(ModelRc::from(results.clone()));
+    mainAppWindow.on_loadResults(move || results.reload());
+
+    let hieroglyphs = PagedModel::new(|cursor| fetch_numbered_page::<Hieroglyph>("/api/hieroglyphs", cursor), hieroglyph_row);
+    mainAppWindow.set_hieroglyphRows(ModelRc::from(hieroglyphs.clone()));
+    mainAppWindow.on_loadHieroglyphList(move || hieroglyphs.reload());
+
+    mainAppWindow.on_onboardingFinished(|language, script, reminderTime, skipped| {
+        finish_onboarding(language.into(), script.into(), reminderTime.into(), skipped);
+    });
+
+    let weakMainAppExport = mainAppWindow.as_weak();
+    mainAppWindow.on_exportData(move |format| {
+        export_user_data(weakMainAppExport.clone(), format.into());
+    });
+
+    show_cache_usage(mainAppWindow.as_weak());
+    prefetch_media(mainAppWindow.as_weak());
+
+    load_decks(mainAppWindow.as_weak());
+    let weakMainAppDecks = mainAppWindow.as_weak();
+    mainAppWindow.on_downloadDeck(move |deckId| {
+        download_offline_deck(weakMainAppDecks.clone(), deckId);
+    });
+
+    load_sessions(mainAppWindow.as_weak());
+    let weakMainAppSessions = mainAppWindow.as_weak();
+    mainAppWindow.on_loadSessions(move || {
+        load_sessions(weakMainAppSessions.clone());
+    });
+    let weakMainAppSessions = mainAppWindow.as_weak();
+    mainAppWindow.on_revokeSession(move |id| {
+        revoke_session(weakMainAppSessions.clone(), id.into());
+    });
+    let weakMainAppSessions = mainAppWindow.as_weak();
+    mainAppWindow.on_revokeAllSessions(move || {
+        revoke_all_sessions(weakMainAppSessions.clone());
+    });
+
+    let weakMainAppDetail = mainAppWindow.as_weak();
+    mainAppWindow.on_lookupHieroglyph(move |character| {
+        show_hieroglyph(weakMainAppDetail.clone(), character.into());
+    });
+
+    let weakMainAppCards = mainAppWindow.as_weak();
+    mainAppWindow.on_loadFlashcards(move || {
+        load_flashcards(weakMainAppCards.clone());
+    });
+    let weakMainAppRate = mainAppWindow.as_weak();
+    mainAppWindow.on_rateFlashcard(move |quality| {
+        rate_flashcard(weakMainAppRate.clone(), quality);
+    });
+    let weakMainAppKeys = mainAppWindow.as_weak();
+    mainAppWindow.on_flashcardKey(move |key| flashcard_key(weakMainAppKeys.clone(), key.as_str()));
+
+    let weakMainAppTyping = mainAppWindow.as_weak();
+    mainAppWindow.on_startTypingDrill(move || {
+        start_typing_drill(weakMainAppTyping.clone());
+    });
+    let weakMainAppTyping = mainAppWindow.as_weak();
+    mainAppWindow.on_answerTyping(move |typed| {
+        answer_typing(weakMainAppTyping.clone(), typed.into());
+    });
+
+    let weakMainAppPassages = mainAppWindow.as_weak();
+    mainAppWindow.on_loadPassages(move || {
+        load_passages(weakMainAppPassages.clone());
+    });
+    let weakMainAppOpen = mainAppWindow.as_weak();
+    mainAppWindow.on_openPassage(move |passageId| {
+        open_passage(weakMainAppOpen.clone(), passageId);
+    });
+    let weakMainAppPlay = mainAppWindow.as_weak();
+    mainAppWindow.on_readerPlay(move || {
+        if let Some(app_main) = weakMainAppPlay.upgrade() {
+            reader_play(&app_main);
+        }
+    });
+    let weakMainAppPause = mainAppWindow.as_weak();
+    mainAppWindow.on_readerPause(move || {
+        reader_player::pause();
+        if let Some(app_main) = weakMainAppPause.upgrade() {
+            app_main.set_readerPlaying(false);
+        }
+    });
+    let weakMainAppSpeed = mainAppWindow.as_weak();
+    mainAppWindow.on_readerSetSpeed(move |speed| {
+        reader_player::set_speed(speed);
+        if let Some(app_main) = weakMainAppSpeed.upgrade() {
+            app_main.set_readerSpeed(speed.clamp(reader_player::MIN_SPEED, reader_player::MAX_SPEED));
+        }
+    });
+    let weakMainAppTick = mainAppWindow.as_weak();
+    mainAppWindow.on_readerTick(move || {
+        if let Some(app_main) = weakMainAppTick.upgrade() {
+            reader_tick(&app_main);
+        }
+    });
+
+    let contentForm = mainAppWindow.global::<editor>();
+    let weakMainAppEditor = mainAppWindow.as_weak();
+    contentForm.on_assist(move || {
+        assist_content(weakMainAppEditor.clone());
+    });
+    let weakMainAppEditor = mainAppWindow.as_weak();
+    contentForm.on_pickImage(move || {
+        pick_attachment(weakMainAppEditor.clone(), false);
+    });
+    let weakMainAppEditor = mainAppWindow.as_weak();
+    contentForm.on_pickAudio(move || {
+        pick_attachment(weakMainAppEditor.clone(), true);
+    });
+    let weakMainAppEditor = mainAppWindow.as_weak();
+    contentForm.on_submit(move || {
+        submit_content(weakMainAppEditor.clone());
+    });
+
+    let weakMainAppAdmin = mainAppWindow.as_weak();
+    mainAppWindow.on_loadContributions(move || {
+        load_contributions(weakMainAppAdmin.clone());
+    });
+    let weakMainAppAdmin = mainAppWindow.as_weak();
+    mainAppWindow.on_reviewContribution(move |id, accept| {
+        review_contribution(weakMainAppAdmin.clone(), id, accept);
+    });
+    let weakMainAppAdmin = mainAppWindow.as_weak();
+    mainAppWindow.on_searchUsers(move |query| {
+        search_users(weakMainAppAdmin.clone(), query.into());
+    });
+    let weakMainAppAdmin = mainAppWindow.as_weak();
+    mainAppWindow.on_setUserBanned(move |id, banned| {
+        set_user_banned(weakMainAppAdmin.clone(), id, banned);
+    });
+
+    let weakMainAppCache = mainAppWindow.as_weak();
+    mainAppWindow.on_clearCache(move || {
+        media_cache::clear();
+        http_cache::clear();
+        show_cache_usage(weakMainAppCache.clone());
+    });
+
+    mainAppWindow.show().unwrap();
+    app_auth.hide().unwrap();
+    MAIN_WINDOW.with(|window| *window.borrow_mut() = Some(mainAppWindow));
+}
+
+fn show_auth_message(weakAuthentication: &slint::Weak<authentication>, message: String) {
+    let weakAuthentication = weakAuthentication.clone();
+    let _ = slint::invoke_from_event_loop(move || {
+        if let Some(app_auth) = weakAuthentication.upgrade() {
+            app_auth.global::<status>().set_auth_status_message(message.into());
+        }
+    });
+}
+
+/// Signs in on the given server and saves the account as a profile. An unreachable
+/// server falls back to the local offline accounts.
+fn sign_in(weakAuthentication: slint::Weak<authentication>, server: String, nickName: String, password: String) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        match runtime.block_on(profiles::sign_in(&server, &nickName, &password)) {
+            Ok(profile) => {
+                let _ = slint::invoke_from_event_loop(move || {
+                    open_main_window(profile.nickname, weakAuthentication);
+                });
+            }
+            Err(profiles::ProfileError::Unreachable(e)) => {
+                println!("Server is not reachable, signing in offline: {:?}", e);
+                let _ = slint::invoke_from_event_loop(move || {
+                    if handle_signin(nickName.clone(), password) {
+                        open_main_window(nickName, weakAuthentication);
+                    } else if let Some(app_auth) = weakAuthentication.upgrade() {
+                        app_auth.global::<status>().set_auth_status_message("Сервер недоступен, а офлайн-аккаунт не найден".into());
+                    }
+                });
+            }
+            Err(e) => {
+                println!("Authentication failed for nickname: {}", nickName); // Keep console log
+                show_auth_message(&weakAuthentication, e.message());
             }
-            println!("Authentication failed for nickname: {}", nickName); // Keep console log
         }
     });
+}
+
+/// Switches to a saved profile without asking for the password. If its session
+/// expired, the sign-in form opens with the server and nickname filled in.
+fn switch_account(weakAuthentication: slint::Weak<authentication>, id: String) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let result = runtime.block_on(profiles::switch_to(&id));
+        let _ = slint::invoke_from_event_loop(move || {
+            match result {
+                Ok(profile) => open_main_window(profile.nickname, weakAuthentication),
+                Err(e) => {
+                    let Some(app_auth) = weakAuthentication.upgrade() else {
+                        return;
+                    };
+                    if matches!(e, profiles::ProfileError::Expired) {
+                        if let Some(profile) = profiles::list().into_iter().find(|p| p.id() == id) {
+                            app_auth.set_serverUrl(profile.server_url.into());
+                            app_auth.set_nickName(profile.nickname.into());
+                        }
+                        show_accounts(&app_auth);
+                        app_auth.global::<status>().set_currentView(view::authorization);
+                    }
+                    app_auth.global::<status>().set_auth_status_message(e.message().into());
+                }
+            }
+        });
+    });
+}
+
+/// Deletes a saved profile and ends its session on the server.
+fn remove_account(weakAuthentication: slint::Weak<authentication>, id: String) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        runtime.block_on(profiles::remove(&id));
+        let _ = slint::invoke_from_event_loop(move || {
+            if let Some(app_auth) = weakAuthentication.upgrade() {
+                show_accounts(&app_auth);
+                if profiles::list().is_empty() {
+                    app_auth.global::<status>().set_currentView(view::authorization);
+                }
+            }
+        });
+    });
+}
+
+/// Checks that the address points to a Mandarin instance and tells what it offers.
+fn check_server(weakAuthentication: slint::Weak<authentication>, server: String) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        let message = match runtime.block_on(profiles::discover(&server)) {
+            Ok(instance) => {
+                let registration = match instance.registration.as_str() {
+                    "open" => "регистрация открыта",
+                    "invite" => "регистрация по приглашениям",
+                    _ => "регистрация закрыта",
+                };
+                format!("Mandarin {} · {}", instance.version, registration)
+            }
+            Err(e) => e.message(),
+        };
+        show_auth_message(&weakAuthentication, message);
+    });
+}
+
+/// Runs a server-side command on a tokio runtime instead of opening the client.
+fn run_server_command(command: impl std::future::Future<Output = ExitCode>) -> ExitCode {
+    match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(command),
+        Err(e) => {
+            println!("Failed to start the async runtime: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode
+{
+    // `doctor` checks the server configuration and `server` runs the API;
+    // without arguments the desktop client opens
+    match std::env::args().nth(1).as_deref() {
+        Some("doctor") => return run_server_command(app::run_doctor()),
+        Some("server") => {
+            return run_server_command(async {
+                match app::run_server().await {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        println!("Server stopped: {}", e);
+                        ExitCode::FAILURE
+                    }
+                }
+            });
+        }
+        _ => {}
+    }
+
+    let authenticationWindow = authentication::new().unwrap();
+
+    // Weak reference for callbacks
+    let weakAuthentication = authenticationWindow.as_weak();
+
+    // Startup work runs in background threads so the window shows up immediately
+    wait_for_server(weakAuthentication.clone());
+    local_api::spawn_if_enabled();
+
+    // Saved accounts open straight into the picker
+    authenticationWindow.set_serverUrl(api_base_url().into());
+    show_accounts(&authenticationWindow);
+    if !profiles::list().is_empty() {
+        authenticationWindow.global::<status>().set_currentView(view::accounts);
+    }
+
+    let auth_weak_for_auth = weakAuthentication.clone(); // Clone weak ref
+    authenticationWindow.on_authenticate(move |server, nickName, password| {
+        sign_in(auth_weak_for_auth.clone(), server.into(), nickName.into(), password.into());
+    });
+
+    let auth_weak_for_switch = weakAuthentication.clone();
+    authenticationWindow.on_switchAccount(move |id| {
+        switch_account(auth_weak_for_switch.clone(), id.into());
+    });
+
+    let auth_weak_for_remove = weakAuthentication.clone();
+    authenticationWindow.on_removeAccount(move |id| {
+        remove_account(auth_weak_for_remove.clone(), id.into());
+    });
+
+    let auth_weak_for_check = weakAuthentication.clone();
+    authenticationWindow.on_checkServer(move |server| {
+        check_server(auth_weak_for_check.clone(), server.into());
+    });
 
     // Clone weak ref for on_register
     let auth_weak_for_register = weakAuthentication.clone();
@@ -175,4 +2240,5 @@ fn main()
     authenticationWindow.show().unwrap();
 
     slint::run_event_loop().unwrap();
-}
\ No newline at end of file
+    ExitCode::SUCCESS
+}
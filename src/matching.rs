@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Насколько ответ совпал с эталоном.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// Иероглифы совпали (с точностью до пробелов, пунктуации и ширины символов).
+    Exact,
+    /// Ответ дан пиньинем без иероглифов и совпал без учета тонов.
+    PinyinOnly,
+    Wrong,
+}
+
+impl MatchKind {
+    /// Доля балла за ответ.
+    pub fn credit(self) -> f32 {
+        match self {
+            MatchKind::Exact => 1.0,
+            MatchKind::PinyinOnly => 0.5,
+            MatchKind::Wrong => 0.0,
+        }
+    }
+}
+
+/// Китайская пунктуация, которая не учитывается при сравнении.
+const CJK_PUNCTUATION: &str = "，。！？、；：“”‘’（）《》【】…·";
+
+/// Приводит полноширинные ASCII-символы к обычным.
+fn to_half_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// Нормализует текст на иероглифах: убирает пробелы и пунктуацию, приводит ширину символов.
+pub fn normalize_hanzi(text: &str) -> String {
+    text.chars()
+        .map(to_half_width)
+        .filter(|c| !c.is_whitespace() && !c.is_ascii_punctuation() && !CJK_PUNCTUATION.contains(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Убирает с гласной знак тона.
+fn strip_tone_mark(c: char) -> char {
+    match c {
+        'ā' | 'á' | 'ǎ' | 'à' => 'a',
+        'ē' | 'é' | 'ě' | 'è' => 'e',
+        'ī' | 'í' | 'ǐ' | 'ì' => 'i',
+        'ō' | 'ó' | 'ǒ' | 'ò' => 'o',
+        'ū' | 'ú' | 'ǔ' | 'ù' => 'u',
+        'ü' | 'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' => 'v',
+        _ => c,
+    }
+}
+
+/// Нормализует пиньинь без учета тонов: `nǐ hǎo`, `ni3 hao3` и `nihao` дают `nihao`.
+pub fn normalize_pinyin(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(to_half_width)
+        .map(strip_tone_mark)
+        .filter(|c| c.is_ascii_alphabetic())
+        .collect()
+}
+
+/// Пишет ли пользователь латиницей (без иероглифов).
+fn is_latin_only(text: &str) -> bool {
+    text.chars().all(|c| !c.is_alphabetic() || strip_tone_mark(c).is_ascii_alphabetic())
+}
+
+/// Сравнивает ответ с эталоном, допуская мелкие различия в записи.
+pub fn grade(expected_hanzi: &str, expected_pinyin: &str, answer: &str) -> MatchKind {
+    let answer_hanzi = normalize_hanzi(answer);
+    if !answer_hanzi.is_empty() && answer_hanzi == normalize_hanzi(expected_hanzi) {
+        return MatchKind::Exact;
+    }
+
+    if is_latin_only(answer) {
+        let answer_pinyin = normalize_pinyin(answer);
+        if !answer_pinyin.is_empty() && answer_pinyin == normalize_pinyin(expected_pinyin) {
+            return MatchKind::PinyinOnly;
+        }
+    }
+
+    MatchKind::Wrong
+}
@@ -0,0 +1,140 @@
+use rand::RngCore;
+use sqlx::PgPool;
+use std::env;
+use std::future::Future;
+use std::path::PathBuf;
+
+use crate::authz::{Admin, RoleRequirement};
+use crate::classes::TEST_VISIBILITY_CONDITION;
+use crate::config;
+use crate::errors::AppError;
+use crate::models::{Claims, MediaFile};
+use axum::http::StatusCode;
+
+/// Допустимые MIME-типы изображений.
+const IMAGE_MIME_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+/// Допустимые MIME-типы аудио.
+const AUDIO_MIME_TYPES: &[&str] = &["audio/mpeg", "audio/ogg", "audio/wav"];
+
+/// Вид медиафайла.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaKind {
+    Image,
+    Audio,
+}
+
+impl MediaKind {
+    fn allows(self, mime_type: &str) -> bool {
+        match self {
+            MediaKind::Image => IMAGE_MIME_TYPES.contains(&mime_type),
+            MediaKind::Audio => AUDIO_MIME_TYPES.contains(&mime_type),
+        }
+    }
+}
+
+/// Каталог, в котором хранятся медиафайлы (`MEDIA_DIR`, по умолчанию `./media`).
+pub fn media_dir() -> PathBuf {
+    PathBuf::from(env::var("MEDIA_DIR").unwrap_or_else(|_| "media".to_string()))
+}
+
+/// Сохраняет файл на диск и регистрирует его в таблице `media`.
+pub async fn store(kind: MediaKind, mime_type: &str, bytes: &[u8], pool: &PgPool) -> Result<MediaFile, AppError> {
+    if !kind.allows(mime_type) {
+        return Err(AppError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Неподдерживаемый тип файла"));
+    }
+
+    // Имя файла случайное, чтобы не зависеть от имени, присланного клиентом
+    let mut name_bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut name_bytes);
+    let file_name = hex::encode(name_bytes);
+
+    let dir = media_dir();
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join(&file_name), bytes).await?;
+
+    let media = sqlx::query_as::<_, MediaFile>(
+        "INSERT INTO media (file_name, mime_type, size_bytes) VALUES ($1, $2, $3) RETURNING *",
+    )
+        .bind(&file_name)
+        .bind(mime_type)
+        .bind(bytes.len() as i64)
+        .fetch_one(pool)
+        .await;
+    if media.is_err() {
+        remove_file(&file_name).await;
+    }
+
+    Ok(media?)
+}
+
+/// Сохраняет файл и привязывает его к владельцу: `attach` получает id новой
+/// записи `media`. Если привязать не удалось (владельца нет или ошибка БД),
+/// файл удаляется вместе с записью, чтобы не копить сирот на диске.
+pub async fn store_attached<T, F, Fut>(
+    kind: MediaKind,
+    mime_type: &str,
+    bytes: &[u8],
+    pool: &PgPool,
+    attach: F,
+) -> Result<T, AppError>
+where
+    F: FnOnce(i32) -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let stored = store(kind, mime_type, bytes, pool).await?;
+    let attached = attach(stored.id).await;
+    if attached.is_err() {
+        if let Err(e) = sqlx::query("DELETE FROM media WHERE id = $1").bind(stored.id).execute(pool).await {
+            tracing::warn!("Не удалось удалить запись непривязанного файла {}: {:?}", stored.id, e);
+        }
+        remove_file(&stored.file_name).await;
+    }
+    attached
+}
+
+async fn remove_file(file_name: &str) {
+    if let Err(e) = tokio::fs::remove_file(media_dir().join(file_name)).await {
+        tracing::warn!("Не удалось удалить файл {}: {}", file_name, e);
+    }
+}
+
+/// Проверяет, что файл виден пользователю, по его владельцам: вложение вопроса —
+/// тем, кому виден тест, иллюстрация или запись учебного контента — как сам
+/// контент (без входа только при `PUBLIC_CONTENT`). Файл без владельца видят
+/// только администраторы. Возвращает, открыт ли файл всем.
+pub async fn ensure_visible(id: i32, claims: Option<&Claims>, pool: &PgPool) -> Result<bool, AppError> {
+    let (in_visible_test, in_content): (bool, bool) = sqlx::query_as(&format!(
+        "SELECT EXISTS (SELECT 1 FROM test_items i JOIN tests t ON t.id = i.test_id
+                        WHERE (i.image_media_id = $2 OR i.audio_media_id = $2) AND {}),
+                EXISTS (SELECT 1 FROM hieroglyphs WHERE image_media_id = $2)
+                    OR EXISTS (SELECT 1 FROM sentences WHERE audio_media_id = $2)
+                    OR EXISTS (SELECT 1 FROM passages WHERE audio_media_id = $2)",
+        TEST_VISIBILITY_CONDITION
+    ))
+        .bind(claims.map(|c| c.user_id))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+    let public = in_content && config::public_content();
+    let visible = public
+        || in_visible_test
+        || (in_content && claims.is_some())
+        || claims.is_some_and(|c| Admin::allows(&c.role));
+    if !visible {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Файл не найден"));
+    }
+    Ok(public)
+}
+
+/// Загружает описание и содержимое медиафайла.
+pub async fn load(id: i32, pool: &PgPool) -> Result<(MediaFile, Vec<u8>), AppError> {
+    let media = sqlx::query_as::<_, MediaFile>("SELECT * FROM media WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Файл не найден"))?;
+
+    let bytes = tokio::fs::read(media_dir().join(&media.file_name)).await?;
+    Ok((media, bytes))
+}
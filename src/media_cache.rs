@@ -0,0 +1,275 @@
+// media_cache.rs
+//
+// Disk cache for audio and other media the client plays during study.
+// Files live in `MEDIA_CACHE_DIR` (default `media-cache`) next to an index
+// that remembers when each file was last used; when the cache grows past
+// `MEDIA_CACHE_LIMIT_MB` (default 500) the least recently used files are evicted.
+// Media for the review queue is prefetched after sign-in, so reviews work offline.
+// Media of decks downloaded for offline study is pinned and never evicted.
+
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::env;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::ReviewItem;
+
+const INDEX_FILE: &str = "index.json";
+const DEFAULT_LIMIT_MB: u64 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    file: String,
+    size: u64,
+    /// Unix seconds of the last read or write.
+    last_used: u64,
+    /// Part of a deck downloaded for offline study; skipped by eviction.
+    #[serde(default)]
+    pinned: bool,
+}
+
+/// Cached files keyed by their absolute URL.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+}
+
+static INDEX: Lazy<Mutex<CacheIndex>> = Lazy::new(|| Mutex::new(load_index()));
+
+/// Current cache size, shown on the settings panel.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheUsage {
+    pub bytes: u64,
+    pub files: usize,
+    pub limit_bytes: u64,
+}
+
+fn cache_dir() -> PathBuf {
+    PathBuf::from(env::var("MEDIA_CACHE_DIR").unwrap_or_else(|_| "media-cache".to_string()))
+}
+
+fn limit_bytes() -> u64 {
+    env::var("MEDIA_CACHE_LIMIT_MB")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_LIMIT_MB)
+        * 1024
+        * 1024
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn load_index() -> CacheIndex {
+    std::fs::read(cache_dir().join(INDEX_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(index: &CacheIndex) {
+    let dir = cache_dir();
+    let result = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(dir.join(INDEX_FILE), serde_json::to_vec(index).unwrap_or_default()));
+    if let Err(e) = result {
+        println!("Failed to save media cache index: {:?}", e);
+    }
+}
+
+/// Turns a server-relative media path into an absolute URL using the client config.
+pub fn absolute_url(url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return url.to_string();
+    }
+    let base = crate::CLIENT_CONFIG
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|config| config.media_base_url.clone())
+        .filter(|base| !base.is_empty())
+        .unwrap_or_else(crate::api_base_url);
+    format!("{}{}", base.trim_end_matches('/'), url)
+}
+
+fn file_name_for(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Returns the cached file for `url` and marks it as recently used.
+/// The new timestamp is persisted with the next write to the index.
+pub fn cached(url: &str) -> Option<Vec<u8>> {
+    let url = absolute_url(url);
+    let mut index = INDEX.lock().unwrap();
+    let entry = index.entries.get_mut(&url)?;
+    match std::fs::read(cache_dir().join(&entry.file)) {
+        Ok(bytes) => {
+            entry.last_used = now();
+            Some(bytes)
+        }
+        Err(_) => {
+            // The file was removed behind our back: forget it
+            index.entries.remove(&url);
+            None
+        }
+    }
+}
+
+/// Size of the cached file for `url`, used to verify offline downloads.
+pub fn cached_size(url: &str) -> Option<u64> {
+    let url = absolute_url(url);
+    let index = INDEX.lock().unwrap();
+    let entry = index.entries.get(&url)?;
+    cache_dir().join(&entry.file).is_file().then_some(entry.size)
+}
+
+/// Stores a downloaded file and evicts the least recently used files above the limit.
+fn store(url: &str, bytes: &[u8], pinned: bool) -> std::io::Result<()> {
+    let dir = cache_dir();
+    std::fs::create_dir_all(&dir)?;
+    let file = file_name_for(url);
+    std::fs::write(dir.join(&file), bytes)?;
+
+    let mut index = INDEX.lock().unwrap();
+    index.entries.insert(url.to_string(), CacheEntry { file, size: bytes.len() as u64, last_used: now(), pinned });
+
+    let limit = limit_bytes();
+    let mut total: u64 = index.entries.values().map(|e| e.size).sum();
+    if total > limit {
+        let mut by_age: Vec<(String, u64, u64)> = index
+            .entries
+            .iter()
+            .filter(|(key, e)| key.as_str() != url && !e.pinned)
+            .map(|(key, e)| (key.clone(), e.last_used, e.size))
+            .collect();
+        by_age.sort_by_key(|(_, last_used, _)| *last_used);
+
+        for (key, _, size) in by_age {
+            if total <= limit {
+                break;
+            }
+            if let Some(entry) = index.entries.remove(&key) {
+                let _ = std::fs::remove_file(dir.join(entry.file));
+            }
+            total -= size;
+        }
+    }
+
+    save_index(&index);
+    Ok(())
+}
+
+/// Returns media from the cache, downloading and caching it on a miss.
+pub async fn fetch(client: &Client, url: &str) -> Result<Vec<u8>, String> {
+    fetch_with(client, url, false).await
+}
+
+/// Like `fetch`, but pins the file so eviction keeps it for offline study.
+pub async fn fetch_pinned(client: &Client, url: &str) -> Result<Vec<u8>, String> {
+    fetch_with(client, url, true).await
+}
+
+async fn fetch_with(client: &Client, url: &str, pinned: bool) -> Result<Vec<u8>, String> {
+    if let Some(bytes) = cached(url) {
+        if pinned {
+            pin(url);
+        }
+        return Ok(bytes);
+    }
+
+    let url = absolute_url(url);
+    let bytes = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .bytes()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Err(e) = store(&url, &bytes, pinned) {
+        println!("Failed to cache {}: {:?}", url, e);
+    }
+    Ok(bytes.to_vec())
+}
+
+fn pin(url: &str) {
+    let mut index = INDEX.lock().unwrap();
+    if let Some(entry) = index.entries.get_mut(&absolute_url(url)) {
+        if !entry.pinned {
+            entry.pinned = true;
+            save_index(&index);
+        }
+    }
+}
+
+pub fn usage() -> CacheUsage {
+    let index = INDEX.lock().unwrap();
+    CacheUsage {
+        bytes: index.entries.values().map(|e| e.size).sum(),
+        files: index.entries.len(),
+        limit_bytes: limit_bytes(),
+    }
+}
+
+/// Deletes every cached file.
+pub fn clear() {
+    let mut index = INDEX.lock().unwrap();
+    let dir = cache_dir();
+    for entry in index.entries.values() {
+        let _ = std::fs::remove_file(dir.join(&entry.file));
+    }
+    index.entries.clear();
+    save_index(&index);
+}
+
+/// Human-readable cache usage, e.g. "12.4 МБ из 500 МБ (37 файлов)".
+pub fn format_usage(usage: CacheUsage) -> String {
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    format!("{:.1} МБ из {:.0} МБ ({} файлов)", mb(usage.bytes), mb(usage.limit_bytes), usage.files)
+}
+
+/// Downloads audio for everything currently due for review in the background.
+/// `on_done` runs on the worker thread after the prefetch finishes.
+pub fn prefetch_review_media(token: String, on_done: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+
+        runtime.block_on(async {
+            let client = Client::new();
+            let queue = client
+                .get(format!("{}/api/review/queue", crate::api_base_url()))
+                .query(&[("limit", "100")])
+                .bearer_auth(&token)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let items = match queue {
+                Ok(response) => response.json::<Vec<ReviewItem>>().await.unwrap_or_default(),
+                Err(e) => {
+                    println!("Failed to load review queue for prefetch: {:?}", e);
+                    return;
+                }
+            };
+
+            for url in items.iter().filter_map(|item| item.audio_url.as_deref()) {
+                if let Err(e) = fetch(&client, url).await {
+                    println!("Failed to prefetch {}: {}", url, e);
+                }
+            }
+        });
+
+        on_done();
+    });
+}
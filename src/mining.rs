@@ -0,0 +1,267 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+
+use crate::difficulty::{self, estimate_hsk_level};
+use crate::errors::AppError;
+use crate::hsk::{self, MAX_LEVEL};
+use crate::matching::normalize_hanzi;
+use crate::models::{ContentType, CreateMinedDeckPayload, MinedDeck, MinedDeckDetails, MinedWord, MiningResult};
+
+/// Максимальная длина разбираемого текста в символах.
+pub const MAX_TEXT_LEN: usize = 5000;
+
+/// Максимальная длина названия колоды.
+const MAX_DECK_NAME_LEN: usize = 100;
+
+/// Чем заменяется слово в предложении-пропуске.
+pub const CLOZE_GAP: &str = "＿＿";
+
+// Знаки, которыми заканчивается предложение
+const SENTENCE_END: &[char] = &['。', '！', '？', '!', '?', '；', ';', '…', '\n'];
+
+/// Делит текст на предложения. Знак конца остается в предложении, пустые строки отбрасываются.
+pub fn sentences(text: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        current.push(c);
+        if SENTENCE_END.contains(&c) {
+            let sentence = current.trim();
+            if !sentence.is_empty() {
+                result.push(sentence.to_string());
+            }
+            current.clear();
+        }
+    }
+    let rest = current.trim();
+    if !rest.is_empty() {
+        result.push(rest.to_string());
+    }
+    result
+}
+
+/// Заменяет первое вхождение слова пропуском. Если в исходном виде слово не нашлось
+/// (например, внутри него пробел), пропуск ставится в нормализованное предложение.
+pub fn cloze(sentence: &str, word: &str) -> String {
+    if sentence.contains(word) {
+        sentence.replacen(word, CLOZE_GAP, 1)
+    } else {
+        normalize_hanzi(sentence).replacen(word, CLOZE_GAP, 1)
+    }
+}
+
+// Карточка слова для ответа
+#[derive(sqlx::FromRow)]
+struct WordInfo {
+    id: i32,
+    simplified: String,
+    pinyin: Option<String>,
+    translation: Option<String>,
+    hsk_level: Option<i16>,
+}
+
+/// Уровень HSK пользователя по выученным словам; `None`, если ничего не выучено.
+async fn user_level(user_id: i32, pool: &PgPool) -> Result<Option<i16>, AppError> {
+    let levels: Vec<Option<i16>> = sqlx::query_scalar(
+        "SELECT w.hsk_level FROM user_progress up
+         JOIN words w ON w.id = up.content_id
+         WHERE up.user_id = $1 AND up.content_type = $2 AND up.is_learned AND w.hsk_level IS NOT NULL",
+    )
+        .bind(user_id)
+        .bind(ContentType::Word)
+        .fetch_all(pool)
+        .await?;
+    Ok(estimate_hsk_level(&levels))
+}
+
+/// Находит в тексте незнакомые пользователю слова не выше `max_level`
+/// (по умолчанию — на один уровень выше уровня пользователя).
+/// Слова идут в порядке первого появления, к каждому — предложение-пропуск.
+pub async fn mine(user_id: i32, text: &str, max_level: Option<i16>, pool: &PgPool) -> Result<MiningResult, AppError> {
+    let length = text.chars().count();
+    if length == 0 || length > MAX_TEXT_LEN {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Текст должен содержать от 1 до {} символов", MAX_TEXT_LEN),
+        ));
+    }
+    let max_level = match max_level {
+        Some(level) => {
+            hsk::ensure_level(level)?;
+            level
+        }
+        None => user_level(user_id, pool).await?.map_or(1, |level| (level + 1).min(MAX_LEVEL)),
+    };
+
+    let chars: Vec<char> = normalize_hanzi(text).chars().collect();
+    let words = difficulty::dictionary_words(&chars, pool).await?;
+    let dictionary: HashSet<String> = words.keys().cloned().collect();
+    let known_words = difficulty::known_words(user_id, pool).await?;
+
+    let mut token_count = 0;
+    let mut known_count = 0;
+    let mut found: Vec<(i32, String, String)> = Vec::new();
+    let mut seen = HashSet::new();
+    for sentence in sentences(text) {
+        for token in difficulty::segment(&normalize_hanzi(&sentence), &dictionary) {
+            token_count += 1;
+            if known_words.contains(&token) {
+                known_count += 1;
+                continue;
+            }
+            let Some(word) = words.get(&token) else { continue };
+            if word.hsk_level.is_some_and(|level| level <= max_level) && seen.insert(word.id) {
+                found.push((word.id, sentence.clone(), cloze(&sentence, &token)));
+            }
+        }
+    }
+
+    let ids: Vec<i32> = found.iter().map(|(id, _, _)| *id).collect();
+    let details: HashMap<i32, WordInfo> = sqlx::query_as::<_, WordInfo>(
+        "SELECT id, simplified, pinyin, translation, hsk_level FROM words WHERE id = ANY($1)",
+    )
+        .bind(&ids)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|info| (info.id, info))
+        .collect();
+
+    let words = found
+        .into_iter()
+        .filter_map(|(word_id, sentence, cloze)| {
+            let info = details.get(&word_id)?;
+            Some(MinedWord {
+                word_id,
+                simplified: info.simplified.clone(),
+                pinyin: info.pinyin.clone(),
+                translation: info.translation.clone(),
+                hsk_level: info.hsk_level,
+                sentence,
+                cloze,
+            })
+        })
+        .collect();
+
+    Ok(MiningResult { max_level, token_count, known_count, words })
+}
+
+/// Создает личную колоду из незнакомых слов текста и добавляет их в список изучения.
+pub async fn create_deck(user_id: i32, payload: &CreateMinedDeckPayload, pool: &PgPool) -> Result<MinedDeck, AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_DECK_NAME_LEN {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Название колоды должно содержать от 1 до {} символов", MAX_DECK_NAME_LEN),
+        ));
+    }
+
+    let mut cards = mine(user_id, &payload.text, payload.max_level, pool).await?.words;
+    if let Some(word_ids) = &payload.word_ids {
+        let selected: HashSet<i32> = word_ids.iter().copied().collect();
+        cards.retain(|card| selected.contains(&card.word_id));
+    }
+    if cards.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "В тексте нет новых слов для колоды"));
+    }
+
+    let word_ids: Vec<i32> = cards.iter().map(|card| card.word_id).collect();
+    let sentences: Vec<&str> = cards.iter().map(|card| card.sentence.as_str()).collect();
+    let clozes: Vec<&str> = cards.iter().map(|card| card.cloze.as_str()).collect();
+
+    let mut tx = pool.begin().await?;
+    let deck = sqlx::query_as::<_, MinedDeck>(
+        "INSERT INTO mined_decks (user_id, name) VALUES ($1, $2)
+         ON CONFLICT (user_id, name) DO NOTHING
+         RETURNING id, name, created_at, 0::bigint AS card_count",
+    )
+        .bind(user_id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::CONFLICT, "Колода с таким названием уже есть"))?;
+
+    sqlx::query(
+        "INSERT INTO mined_cards (deck_id, word_id, position, sentence, cloze)
+         SELECT $1, word_id, position::int, sentence, cloze
+         FROM UNNEST($2::int[], $3::text[], $4::text[]) WITH ORDINALITY AS t(word_id, sentence, cloze, position)",
+    )
+        .bind(deck.id)
+        .bind(&word_ids)
+        .bind(&sentences)
+        .bind(&clozes)
+        .execute(&mut *tx)
+        .await?;
+
+    // Слова колоды попадают в список изучения, как при ручном добавлении
+    sqlx::query(
+        "INSERT INTO user_progress (user_id, content_type, content_id, is_learned)
+         SELECT $1, $2, UNNEST($3::int[]), FALSE
+         ON CONFLICT (user_id, content_type, content_id) DO NOTHING",
+    )
+        .bind(user_id)
+        .bind(ContentType::Word)
+        .bind(&word_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(MinedDeck { card_count: word_ids.len() as i64, ..deck })
+}
+
+/// Личные колоды пользователя, новые первыми.
+pub async fn list_decks(user_id: i32, pool: &PgPool) -> Result<Vec<MinedDeck>, AppError> {
+    let decks = sqlx::query_as::<_, MinedDeck>(
+        "SELECT d.id, d.name, d.created_at,
+                (SELECT COUNT(*) FROM mined_cards c WHERE c.deck_id = d.id) AS card_count
+         FROM mined_decks d
+         WHERE d.user_id = $1
+         ORDER BY d.created_at DESC, d.id DESC",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(decks)
+}
+
+/// Личная колода с карточками. Чужая колода выглядит как несуществующая.
+pub async fn deck(user_id: i32, deck_id: i32, pool: &PgPool) -> Result<MinedDeckDetails, AppError> {
+    let deck = sqlx::query_as::<_, MinedDeck>(
+        "SELECT d.id, d.name, d.created_at,
+                (SELECT COUNT(*) FROM mined_cards c WHERE c.deck_id = d.id) AS card_count
+         FROM mined_decks d
+         WHERE d.id = $1 AND d.user_id = $2",
+    )
+        .bind(deck_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Колода не найдена"))?;
+
+    let cards = sqlx::query_as::<_, MinedWord>(
+        "SELECT c.word_id, w.simplified, w.pinyin, w.translation, w.hsk_level, c.sentence, c.cloze
+         FROM mined_cards c
+         JOIN words w ON w.id = c.word_id
+         WHERE c.deck_id = $1
+         ORDER BY c.position",
+    )
+        .bind(deck_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(MinedDeckDetails { deck, cards })
+}
+
+/// Удаляет личную колоду. Слова остаются в списке изучения.
+pub async fn delete_deck(user_id: i32, deck_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let result = sqlx::query("DELETE FROM mined_decks WHERE id = $1 AND user_id = $2")
+        .bind(deck_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Колода не найдена"));
+    }
+    Ok(())
+}
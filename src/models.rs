@@ -1,19 +1,39 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fmt;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use crate::config::RegistrationMode;
+use crate::errors::AppError;
+use crate::matching::MatchKind;
+use crate::notifications::{NotificationChannel, NotificationEvent};
+use crate::shadowing::AlignedChar;
 
 // --- Модели для базы данных ---
 
 /// Rust-эквивалент для `content_type_enum` из PostgreSQL.
-#[derive(Debug, Clone, sqlx::Type, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, sqlx::Type, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[sqlx(type_name = "content_type_enum", rename_all = "snake_case")]
 pub enum ContentType {
+    // Псевдонимы принимают написание, как в БД: так тип задается в критериях достижений
+    #[serde(alias = "hieroglyph")]
     Hieroglyph,
+    #[serde(alias = "word")]
     Word,
+    #[serde(alias = "phrase")]
     Phrase,
+    #[serde(alias = "grammar_rule")]
     GrammarRule,
+    #[serde(alias = "lesson")]
     Lesson,
+    #[serde(alias = "sentence")]
+    Sentence,
 }
 
 /// Rust-эквивалент для `user_role_enum` из PostgreSQL.
@@ -21,27 +41,145 @@ pub enum ContentType {
 #[sqlx(type_name = "user_role_enum", rename_all = "lowercase")]
 pub enum UserRole {
     User,
+    /// Разбирает очередь предложений пользователей, но не управляет контентом и пользователями.
+    Moderator,
     Admin,
 }
 
+/// Rust-эквивалент для `question_skill_enum`: какой навык проверяет вопрос.
+#[derive(Debug, Clone, Copy, sqlx::Type, Serialize, Deserialize, PartialEq)]
+#[sqlx(type_name = "question_skill_enum", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum QuestionSkill {
+    Listening,
+    Reading,
+    Writing,
+    Vocabulary,
+    Grammar,
+}
+
 // Реализуем Display для удобного вывода роли в текстовом виде.
 impl fmt::Display for UserRole {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             UserRole::User => write!(f, "user"),
+            UserRole::Moderator => write!(f, "moderator"),
             UserRole::Admin => write!(f, "admin"),
         }
     }
 }
 
 
-#[derive(sqlx::FromRow, Debug, Serialize, Deserialize)]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: i32,
     pub nickname: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub role: UserRole,
+    pub is_banned: bool,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub presence_visible: bool,
+    /// Вход закрыт до этого времени после серии неверных паролей.
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Запись журнала входа (`GET /api/admin/users/:id/login-attempts`).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoginAttempt {
+    pub id: i32,
+    pub nickname: String,
+    pub ip: Option<String>,
+    /// `success`, `bad_password` или `locked` — попытка во время блокировки.
+    pub outcome: String,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// Событие журнала безопасности (`GET /api/admin/users/:id/security-events`).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SecurityEvent {
+    pub id: i32,
+    /// `device_mismatch` — сессию обновили с другого устройства,
+    /// `refresh_reuse` — повторно предъявлен замененный токен.
+    pub kind: String,
+    pub ip: Option<String>,
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Активная сессия пользователя (`GET /api/sessions/me`). Id не меняется при обновлении токенов.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_agent: Option<String>,
+    /// Адрес, с которого сессию последний раз обновляли.
+    pub ip: Option<String>,
+    /// Время входа.
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Когда сессию обновляли с другого устройства, если такое было.
+    pub device_mismatch_at: Option<DateTime<Utc>>,
+    /// Сессия, из которой сделан запрос.
+    pub current: bool,
+}
+
+/// Вид юридического документа.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegalDocumentKind {
+    /// Условия использования.
+    Terms,
+    /// Политика конфиденциальности.
+    Privacy,
+}
+
+/// Версия условий использования или политики конфиденциальности (`GET /api/legal`).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LegalDocument {
+    pub id: i32,
+    /// `terms` или `privacy`.
+    pub kind: String,
+    pub version: String,
+    pub content: String,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Публикация новой версии документа (`POST /api/admin/legal`).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PublishLegalDocumentPayload {
+    pub kind: LegalDocumentKind,
+    pub version: String,
+    pub content: String,
+}
+
+/// Принятие документов (`POST /api/consent`): id действующих версий.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConsentPayload {
+    pub document_ids: Vec<i32>,
+}
+
+/// Действующие документы и то, что из них пользователь еще не принял.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentStatus {
+    pub documents: Vec<LegalDocument>,
+    /// Id документов, без принятия которых API недоступен.
+    pub pending: Vec<i32>,
+}
+
+/// Пользователь без секретов: для `GET /api/me` и поиска в админке.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserSummary {
+    pub id: i32,
+    pub nickname: String,
+    pub role: UserRole,
+    pub is_banned: bool,
+    pub disabled_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Когда подтверждена текущая почта; `None` — не подтверждена или ее нет.
+    #[serde(default)]
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -51,6 +189,62 @@ pub struct Hieroglyph {
     pub pinyin: String,
     pub translation: String,
     pub example: Option<String>,
+    pub definition: Option<String>,
+    pub readings: Option<String>,
+    pub total_strokes: Option<i16>,
+    pub radical_index: Option<i16>,
+    pub tags: Vec<String>,
+    pub hsk_level: Option<i16>,
+    /// Иллюстрация; файл отдается по `GET /api/media/:id`.
+    pub image_media_id: Option<i32>,
+}
+
+/// Слово из одного или нескольких иероглифов.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Word {
+    pub id: i32,
+    pub simplified: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub hsk_level: Option<i16>,
+    pub hsk_version: Option<String>,
+    pub tags: Vec<String>,
+    pub contributor_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Устойчивое выражение или фраза.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Phrase {
+    pub id: i32,
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub usage_note: Option<String>,
+    pub hsk_level: Option<i16>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Пример употребления грамматической конструкции.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrammarExample {
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+}
+
+/// Правило грамматики.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GrammarRule {
+    pub id: i32,
+    pub title: String,
+    pub explanation: String,
+    pub pattern: String,
+    pub examples: sqlx::types::Json<Vec<GrammarExample>>,
+    pub hsk_level: Option<i16>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -61,9 +255,13 @@ pub struct UserProgress {
     pub content_id: i32,
     pub is_learned: bool,
     pub learned_at: Option<DateTime<Utc>>,
+    pub next_review_at: Option<DateTime<Utc>>,
+    pub ease_factor: f32,
+    pub interval_days: i32,
+    pub repetitions: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Achievement {
     pub id: i32,
     pub name: String,
@@ -87,6 +285,20 @@ pub struct Test {
     pub name: String,
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub class_id: Option<i32>,
+    /// Ограничение времени; такой тест проходится через попытку (`POST /api/tests/:id/attempts`).
+    pub time_limit_secs: Option<i32>,
+    /// Запас пауз каждой попытки, секунд.
+    pub pause_allowance_secs: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Class {
+    pub id: i32,
+    pub name: String,
+    pub teacher_id: i32,
+    pub restrict_content: bool,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -95,19 +307,363 @@ pub struct TestItem {
     pub test_id: i32,
     pub question: String,
     pub options: Option<Value>, // JSONB
+    pub image_url: Option<String>,
+    pub audio_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MediaFile {
+    pub id: i32,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Озвучка иероглифа или слова; файл отдается по `GET /api/audio/:id`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AudioFile {
+    pub id: i32,
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    /// Озвучка синтезирована TTS, а не записана диктором.
+    pub synthesized: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Sentence {
+    pub id: i32,
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub audio_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShadowingAttempt {
+    pub id: i32,
+    pub transcript: String,
+    pub score: f32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StudyPlan {
+    pub id: i32,
+    pub user_id: i32,
+    pub deck_id: i32,
+    pub start_date: NaiveDate,
+    pub target_date: NaiveDate,
+    pub initial_remaining: i32,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Contribution {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: String,
+    pub payload: Value, // JSONB
+    pub status: String,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub content_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Assignment {
+    pub id: i32,
+    pub class_id: i32,
+    pub test_id: i32,
+    pub title: String,
+    pub due_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Notification {
+    pub id: i32,
+    pub kind: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub desktop: bool,
 }
 
 // --- Структуры для request/response ---
 
+// --- Постраничный вывод ---
+
+/// Номер и размер страницы из query-параметров `page` и `per_page`.
+/// Используется как экстрактор в обработчиках списков.
+#[derive(Debug, Clone, Copy)]
+pub struct Pagination {
+    /// Номер страницы, начиная с 1.
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl Pagination {
+    pub const DEFAULT_PER_PAGE: i64 = 50;
+    pub const MAX_PER_PAGE: i64 = 200;
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PaginationQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Pagination
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PaginationQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Некорректные параметры страницы"))?;
+
+        Ok(Pagination {
+            page: query.page.unwrap_or(1).max(1),
+            per_page: query.per_page.unwrap_or(Self::DEFAULT_PER_PAGE).clamp(1, Self::MAX_PER_PAGE),
+        })
+    }
+}
+
+/// Страница списка вместе с общим числом записей.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total_count: i64,
+    pub total_pages: i64,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, pagination: Pagination, total_count: i64) -> Self {
+        Paginated {
+            items,
+            page: pagination.page,
+            per_page: pagination.per_page,
+            total_count,
+            total_pages: (total_count + pagination.per_page - 1) / pagination.per_page,
+        }
+    }
+}
+
+/// Курсор и размер страницы из query-параметров `cursor` и `limit` для лент,
+/// новые записи которых появляются сверху. Курсор — id последней полученной
+/// записи, поэтому новые записи не сдвигают следующие страницы.
+#[derive(Debug, Clone, Copy)]
+pub struct CursorPagination {
+    /// Нужны записи старше этой; без курсора лента начинается с самой новой.
+    pub cursor: Option<i32>,
+    pub limit: i64,
+}
+
+impl CursorPagination {
+    pub const DEFAULT_LIMIT: i64 = 50;
+    pub const MAX_LIMIT: i64 = 200;
+
+    /// Сколько строк выбирать: на одну больше страницы, чтобы узнать, есть ли следующая.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CursorQuery {
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CursorPagination
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<CursorQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Некорректные параметры страницы"))?;
+        // Для клиента курсор непрозрачен: он только возвращает то, что получил
+        let cursor = match query.cursor.as_deref().filter(|cursor| !cursor.is_empty()) {
+            Some(cursor) => Some(cursor.parse().map_err(|_| {
+                AppError::new(StatusCode::BAD_REQUEST, "Некорректный курсор").with_code("invalid_cursor")
+            })?),
+            None => None,
+        };
+
+        Ok(CursorPagination {
+            cursor,
+            limit: query.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT),
+        })
+    }
+}
+
+/// Страница ленты и курсор следующей; `None` — записей больше нет.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> CursorPage<T> {
+    /// Собирает страницу из строк, выбранных с `fetch_limit()`. `id` — ключ курсора.
+    pub fn new(mut items: Vec<T>, pagination: CursorPagination, id: impl Fn(&T) -> i32) -> Self {
+        let has_more = items.len() as i64 > pagination.limit;
+        items.truncate(pagination.limit as usize);
+        let next_cursor = if has_more { items.last().map(|item| id(item).to_string()) } else { None };
+        CursorPage { items, next_cursor }
+    }
+}
+
+/// Направление сортировки.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Поле сортировки списка иероглифов.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HieroglyphSort {
+    #[default]
+    Id,
+    Character,
+    Pinyin,
+    Strokes,
+    HskLevel,
+}
+
+/// Сортировка и фильтры списка иероглифов; страница задается через `Pagination`.
+#[derive(Debug, Default, Deserialize)]
+pub struct HieroglyphListQuery {
+    #[serde(default)]
+    pub sort_by: HieroglyphSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Начало чтения пиньинь, без учета регистра.
+    pub pinyin: Option<String>,
+    pub hsk_level: Option<i16>,
+    pub tag: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TestDetails {
     pub id: i32,
     pub name: String,
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub time_limit_secs: Option<i32>,
     pub questions: Vec<TestItem>,
 }
 
+/// Полезная нагрузка для создания и изменения теста.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TestPayload {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Новый вопрос теста. Без вариантов ответ вводится вручную.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TestItemPayload {
+    pub question: String,
+    pub options: Option<Vec<String>>,
+    pub correct_answer: String,
+    pub skill: Option<QuestionSkill>,
+    pub hsk_level: Option<i16>,
+    pub grammar_rule_id: Option<i32>,
+    /// Пояснение к ответу: показывается только в разборе после отправки теста.
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+/// Пакет вопросов, добавляемых в тест одной транзакцией.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TestItemsPayload {
+    pub items: Vec<TestItemPayload>,
+}
+
+/// Теги вопроса теста для сборки тестов по шаблонам. Пустое значение снимает тег.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagTestItemPayload {
+    pub skill: Option<QuestionSkill>,
+    pub hsk_level: Option<i16>,
+    pub grammar_rule_id: Option<i32>,
+}
+
+/// Раздел шаблона: сколько вопросов взять из банка и с какими тегами.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BlueprintSection {
+    pub question_count: i32,
+    pub skill: Option<QuestionSkill>,
+    pub hsk_level: Option<i16>,
+    pub grammar_rule_id: Option<i32>,
+}
+
+/// Шаблон теста.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestBlueprint {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sections: Vec<BlueprintSection>,
+}
+
+/// Полезная нагрузка для создания шаблона теста.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlueprintPayload {
+    pub name: String,
+    pub description: Option<String>,
+    pub sections: Vec<BlueprintSection>,
+}
+
+/// Вес типа контента в персональном тесте.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuizMixEntry {
+    pub content_type: ContentType,
+    pub weight: u32,
+}
+
+/// Запрос на персональный тест по выученному. Без `mix` вопросы делятся
+/// поровну между иероглифами, словами и фразами.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GenerateQuizPayload {
+    pub size: Option<u32>,
+    pub mix: Option<Vec<QuizMixEntry>>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AnswerPayload {
     pub question_id: i32,
@@ -121,62 +677,2073 @@ pub struct TestSubmissionPayload {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TestResultResponse {
+    /// ID результата для разбора ответов (`GET /api/tests/:id/results/:result_id`).
+    pub result_id: i32,
     pub score: usize,
     pub total_questions: usize,
+    /// Достижения, полученные за этот тест.
+    pub new_achievements: Vec<Achievement>,
 }
 
+/// Разбор одного вопроса после отправки теста.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuestionReview {
+    pub question_id: i32,
+    pub question: String,
+    pub options: Option<Value>,
+    /// Ответ пользователя; пусто, если вопрос пропущен.
+    pub answer: Option<String>,
+    pub correct_answer: String,
+    pub is_correct: bool,
+    pub explanation: Option<String>,
+}
 
-/// Полезная нагрузка для регистрации.
-#[derive(Debug, Deserialize, Serialize)]
-pub struct RegisterPayload {
-    pub nickname: String,
-    pub password: String,
+/// Результат теста в истории пользователя.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ResultHistoryItem {
+    pub result_id: i32,
+    pub test_id: i32,
+    pub test_name: String,
+    pub score: i32,
+    /// Число вопросов с сохраненными ответами; 0 у результатов до появления разбора.
+    pub total_questions: i64,
+    pub submitted_at: DateTime<Utc>,
 }
 
-/// Полезная нагрузка для логина.
+/// Подробный результат теста. У результатов, сохраненных до появления разбора,
+/// список вопросов пуст.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestResultDetails {
+    pub result_id: i32,
+    pub test_id: i32,
+    pub test_name: String,
+    pub score: i32,
+    pub total_questions: usize,
+    pub submitted_at: DateTime<Utc>,
+    pub questions: Vec<QuestionReview>,
+}
+
+
+/// Ссылка для публикации результата теста.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareLinkResponse {
+    pub token: String,
+    pub url: String,
+}
+
+/// Публичная карточка результата: только счет, название теста и дата.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SharedResult {
+    pub test_name: String,
+    pub score: i32,
+    pub total_questions: i64,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Полезная нагрузка для создания класса.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct LoginPayload {
-    pub nickname: String,
-    pub password: String,
+pub struct CreateClassPayload {
+    pub name: String,
+    pub teacher_id: i32,
 }
 
-/// Полезная нагрузка для обновления токена.
+/// Полезная нагрузка для добавления ученика в класс.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct RefreshPayload {
-    pub refresh_token: String,
+pub struct AddClassMemberPayload {
+    pub user_id: i32,
 }
 
-/// Полезная нагрузка для создания иероглифа
+/// Полезная нагрузка для настройки видимого классу контента.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct CreateHieroglyphPayload {
-    pub character: String,
-    pub pinyin: String,
-    pub translation: String,
-    pub example: Option<String>,
+pub struct ClassContentPayload {
+    pub restrict_content: bool,
+    pub test_ids: Vec<i32>,
+    #[serde(default)]
+    pub deck_ids: Vec<i32>,
+    #[serde(default)]
+    pub lesson_ids: Vec<i32>,
 }
 
-/// Полезная нагрузка для отметки контента как выученного.
+/// Полезная нагрузка для создания собственного теста класса.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct MarkLearnedPayload {
-    pub content_type: ContentType,
-    pub content_id: i32,
+pub struct CreateClassTestPayload {
+    pub name: String,
+    pub description: Option<String>,
+    /// Ограничение времени в секундах; без него тест не ограничен по времени.
+    pub time_limit_secs: Option<i32>,
+    /// Сколько секунд пауз из-за потери связи не идет в зачет времени.
+    #[serde(default)]
+    pub pause_allowance_secs: i32,
 }
 
+/// Попытка теста с ограничением времени.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TestAttempt {
+    pub id: i32,
+    pub user_id: i32,
+    pub test_id: i32,
+    pub time_limit_secs: i32,
+    pub pause_allowance_secs: i32,
+    /// Засчитанные паузы, секунд.
+    pub paused_secs: i32,
+    pub started_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub result_id: Option<i32>,
+}
 
-/// Ответ с токенами.
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuthResponse {
-    pub access_token: String,
-    pub refresh_token: String,
+/// Ответ, сохраненный в попытке.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AttemptAnswer {
+    pub question_id: i32,
+    pub answer: String,
+    pub answered_at: DateTime<Utc>,
+    /// Секунд с начала попытки без учета засчитанных пауз.
+    pub elapsed_secs: i32,
 }
 
-/// Структура "claims" для JWT.
+/// Попытка вместе с оставшимся временем и уже данными ответами.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    pub exp: usize,
-    pub iat: usize,
-    pub user_id: i32,
-    pub role: UserRole,
+pub struct TestAttemptState {
+    #[serde(flatten)]
+    pub attempt: TestAttempt,
+    pub remaining_secs: i64,
+    pub pause_remaining_secs: i64,
+    pub answers: Vec<AttemptAnswer>,
+}
+
+/// Продление запаса пауз попытки учителем.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GrantPauseAllowancePayload {
+    pub seconds: i32,
+}
+
+/// Событие журнала попытки.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AttemptEvent {
+    pub id: i32,
+    pub kind: String,
+    pub seconds: Option<i32>,
+    pub details: Option<String>,
+    pub actor_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Полезная нагрузка для создания задания.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateAssignmentPayload {
+    pub test_id: i32,
+    pub title: String,
+    pub due_at: DateTime<Utc>,
+}
+
+/// Полезная нагрузка для создания предложения.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateSentencePayload {
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+}
+
+/// Предложение текста для чтения и его место в записи.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PassageSentencePayload {
+    pub sentence_id: i32,
+    pub start_ms: i32,
+    pub end_ms: i32,
+}
+
+/// Полезная нагрузка для создания текста для чтения.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PassagePayload {
+    pub title: String,
+    /// Предложения по порядку; отрезки записи не должны пересекаться.
+    pub sentences: Vec<PassageSentencePayload>,
+}
+
+/// Текст для чтения в списке.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PassageSummary {
+    pub id: i32,
+    pub title: String,
+    pub sentence_count: i64,
+    pub has_audio: bool,
+}
+
+/// Предложение текста с отрезком записи.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TimedSentence {
+    pub sentence_id: i32,
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub start_ms: i32,
+    pub end_ms: i32,
+}
+
+/// Разметка текста для режима чтения: запись и время каждого предложения.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassageTiming {
+    pub id: i32,
+    pub title: String,
+    pub audio_url: String,
+    pub sentences: Vec<TimedSentence>,
+}
+
+/// Результат попытки shadowing: расшифровка, выравнивание с эталоном и оценка.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShadowingResult {
+    pub transcript: String,
+    pub alignment: Vec<AlignedChar>,
+    pub score: f32,
+    pub previous_best: Option<f32>,
+}
+
+/// Задание тренажера набора: по пиньиню и переводу нужно набрать иероглифы.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TypingPrompt {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    /// Ожидаемый ответ: клиент показывает его после ввода.
+    pub text: String,
+    pub pinyin: String,
+    pub translation: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TypingDrillQuery {
+    pub size: Option<u32>,
+}
+
+/// Ответ на одно задание тренажера набора.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypingAnswer {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub typed: String,
+    /// Время от показа задания до отправки ответа, мс.
+    pub elapsed_ms: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TypingRunPayload {
+    pub answers: Vec<TypingAnswer>,
+}
+
+/// Заход тренажера набора.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TypingRun {
+    pub id: i32,
+    pub item_count: i32,
+    pub correct_count: i32,
+    /// Иероглифов в верных ответах.
+    pub char_count: i32,
+    pub duration_ms: i32,
+    /// Верно набранных иероглифов в минуту — аналог WPM для китайского.
+    pub chars_per_minute: f32,
+    /// Доля верных ответов, %.
+    pub accuracy: f32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Итог захода и сравнение с личным рекордом.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TypingRunResult {
+    pub run: TypingRun,
+    pub previous_best: Option<f32>,
+    pub new_best: bool,
+}
+
+/// Личные рекорды и последние заходы тренажера набора.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TypingStats {
+    pub run_count: i64,
+    /// Лучшая скорость среди заходов с достаточной точностью.
+    pub best_chars_per_minute: Option<f32>,
+    pub best_accuracy: Option<f32>,
+    pub recent: Vec<TypingRun>,
+}
+
+/// Задание диктанта: только озвучка, без текста.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DictationPrompt {
+    pub sentence_id: i32,
+    pub audio_url: String,
+}
+
+/// Ответ пользователя в диктанте.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DictationAnswerPayload {
+    pub answer: String,
+}
+
+/// Результат проверки диктанта.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DictationResult {
+    pub result: MatchKind,
+    pub credit: f32,
+    pub correct_text: String,
+    pub correct_pinyin: String,
+    pub translation: String,
+}
+
+/// Задание на сборку слова: пиньинь и перевод, а иероглифы слова перемешаны с лишними.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompositionPrompt {
+    pub word_id: i32,
+    pub pinyin: String,
+    pub translation: String,
+    /// Сколько иероглифов в слове.
+    pub length: usize,
+    pub tiles: Vec<String>,
+}
+
+/// Собранное пользователем слово.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CompositionAnswerPayload {
+    pub answer: String,
+}
+
+/// Результат проверки собранного слова.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompositionResult {
+    pub correct: bool,
+    pub correct_text: String,
+    pub pinyin: String,
+    pub translation: String,
+}
+
+/// Параметры генерации прописей: `items` — id иероглифов через запятую.
+#[derive(Debug, Deserialize)]
+pub struct WorksheetQuery {
+    pub items: String,
+}
+
+/// Версия стандарта HSK.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum HskVersion {
+    #[serde(rename = "2.0")]
+    V2,
+    #[serde(rename = "3.0")]
+    V3,
+}
+
+/// Полезная нагрузка для разметки уровней HSK (только иероглифы и слова).
+#[derive(Debug, Deserialize)]
+pub struct SetHskLevelsPayload {
+    pub content_type: ContentType,
+    pub ids: Vec<i32>,
+    /// Пустое значение снимает уровень.
+    pub level: Option<i16>,
+}
+
+/// Прогресс пользователя по уровню HSK.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HskLevelProgress {
+    pub level: i16,
+    pub hieroglyphs_total: i64,
+    pub hieroglyphs_learned: i64,
+    pub words_total: i64,
+    pub words_learned: i64,
+    /// Доля выученного по иероглифам и словам вместе, от 0 до 100.
+    pub percent: f64,
+}
+
+impl HskLevelProgress {
+    pub fn new(level: i16, hieroglyphs_total: i64, hieroglyphs_learned: i64, words_total: i64, words_learned: i64) -> Self {
+        let total = hieroglyphs_total + words_total;
+        let learned = hieroglyphs_learned + words_learned;
+        let percent = if total == 0 { 0.0 } else { (learned as f64 * 1000.0 / total as f64).round() / 10.0 };
+        HskLevelProgress { level, hieroglyphs_total, hieroglyphs_learned, words_total, words_learned, percent }
+    }
+}
+
+/// Колода в списке колод.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Deck {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub item_count: i64,
+}
+
+/// Элемент колоды с подставленным содержимым.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DeckItem {
+    pub position: i32,
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub text: String,
+    pub pinyin: Option<String>,
+    pub translation: Option<String>,
+    pub audio_url: Option<String>,
+}
+
+/// Медиафайл, который нужно скачать для офлайн-режима.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MediaRef {
+    pub url: String,
+    pub size_bytes: i64,
+}
+
+/// Описание колоды для полной офлайн-загрузки.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckManifest {
+    pub deck_id: i32,
+    pub name: String,
+    pub item_count: i64,
+    pub media: Vec<MediaRef>,
+    /// SHA-256 содержимого всех элементов в порядке колоды.
+    pub checksum: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeckItemsQuery {
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Полезная нагрузка для создания учебного плана.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreatePlanPayload {
+    pub deck_id: i32,
+    pub target_date: NaiveDate,
+}
+
+/// Соответствие плану.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StudyPlanStatus {
+    OnSchedule,
+    Behind,
+    Overdue,
+    Completed,
+}
+
+/// Текущий прогресс по плану.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlanProgress {
+    pub learned: i64,
+    pub remaining: i64,
+    /// Сколько должно быть выучено к сегодняшнему дню при равномерном темпе.
+    pub expected_learned: i64,
+    /// Сколько новых элементов в день нужно учить, чтобы успеть к сроку.
+    pub daily_pace: i64,
+    pub days_left: i64,
+    pub status: StudyPlanStatus,
+}
+
+/// План вместе с прогрессом.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StudyPlanResponse {
+    #[serde(flatten)]
+    pub plan: StudyPlan,
+    pub progress: PlanProgress,
+}
+
+/// Вид учебной активности для учета времени.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityType {
+    Flashcards,
+    Review,
+    Test,
+    Dictation,
+    Shadowing,
+    Reading,
+    Other,
+}
+
+impl ActivityType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActivityType::Flashcards => "flashcards",
+            ActivityType::Review => "review",
+            ActivityType::Test => "test",
+            ActivityType::Dictation => "dictation",
+            ActivityType::Shadowing => "shadowing",
+            ActivityType::Reading => "reading",
+            ActivityType::Other => "other",
+        }
+    }
+}
+
+/// Heartbeat клиента: сколько секунд пользователь занимался с прошлого события.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StudyTimePayload {
+    pub activity: ActivityType,
+    pub seconds: i32,
+}
+
+/// Параметры статистики времени занятий.
+#[derive(Debug, Deserialize)]
+pub struct StudyTimeQuery {
+    pub days: Option<i32>,
+}
+
+/// Период истории статистики; по умолчанию — последние 30 дней.
+#[derive(Debug, Deserialize)]
+pub struct StatsHistoryQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Период разбивки статистики повторений в днях; по умолчанию — 90.
+#[derive(Debug, Deserialize)]
+pub struct StatsBreakdownQuery {
+    pub days: Option<i32>,
+}
+
+/// Показатели повторений группы элементов.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BreakdownStats {
+    /// Сколько разных элементов повторялось.
+    pub items: i64,
+    pub reviews: i64,
+    /// Доля успешных повторений (оценка 3 и выше), %.
+    pub accuracy: f32,
+    /// Доля элементов, последнее повторение которых успешно, %.
+    pub retention: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LevelBreakdown {
+    /// `None` — элементы вне списков HSK.
+    pub hsk_level: Option<i16>,
+    #[serde(flatten)]
+    pub stats: BreakdownStats,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagBreakdown {
+    pub tag: String,
+    #[serde(flatten)]
+    pub stats: BreakdownStats,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentTypeBreakdown {
+    pub content_type: ContentType,
+    #[serde(flatten)]
+    pub stats: BreakdownStats,
+}
+
+/// Сочетание уровня HSK и тега с низкой точностью, например «глаголы HSK 3».
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WeakSpot {
+    pub hsk_level: i16,
+    pub tag: String,
+    #[serde(flatten)]
+    pub stats: BreakdownStats,
+}
+
+/// Разбивка статистики повторений пользователя.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsBreakdown {
+    pub days: i32,
+    pub by_hsk_level: Vec<LevelBreakdown>,
+    pub by_tag: Vec<TagBreakdown>,
+    pub by_content_type: Vec<ContentTypeBreakdown>,
+    /// Самые слабые сочетания уровня и тега, худшие первыми.
+    pub weak_spots: Vec<WeakSpot>,
+    pub latency: LatencyStats,
+}
+
+/// Перцентили времени ответа на повторения, мс; `None`, если замеров нет.
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LatencyStats {
+    /// Сколько ответов с замеренным временем.
+    pub answers: i64,
+    pub p50_ms: Option<i32>,
+    pub p90_ms: Option<i32>,
+    pub p99_ms: Option<i32>,
+}
+
+/// Итоги пользователя на конец дня.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StatSnapshot {
+    pub day: NaiveDate,
+    pub learned_total: i32,
+    pub reviews_total: i32,
+    pub streak_days: i32,
+    pub xp: i64,
+}
+
+/// Время занятий за день по одному виду активности.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StudyTimeEntry {
+    pub day: NaiveDate,
+    pub activity: String,
+    pub minutes: f64,
+}
+
+/// Опыт и уровень пользователя для `GET /api/profile/me`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelProgress {
+    pub xp: i64,
+    pub level: i32,
+    /// Опыт, набранный на текущем уровне.
+    pub xp_into_level: i64,
+    /// Сколько опыта нужно набрать на текущем уровне для перехода на следующий.
+    pub xp_for_next_level: i64,
+    /// Доля пройденного уровня, от 0 до 1.
+    pub progress: f64,
+}
+
+/// Профиль текущего пользователя.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileResponse {
+    pub id: i32,
+    pub nickname: String,
+    #[serde(flatten)]
+    pub level: LevelProgress,
+}
+
+/// Серия дней занятий пользователя.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreakSummary {
+    /// Текущая серия; пропуски, закрытые заморозками, ее не прерывают, но и не удлиняют.
+    pub current_days: i64,
+    pub longest_days: i64,
+    pub studied_today: bool,
+    pub freezes_available: i32,
+    pub last_study_day: Option<NaiveDate>,
+}
+
+/// Полезная нагрузка для пробной проверки условия достижения.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CriteriaDryRunPayload {
+    pub criteria: Value,
+    pub user_id: i32,
+}
+
+/// Результат пробной проверки условия достижения.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CriteriaDryRunResponse {
+    pub satisfied: bool,
+}
+
+/// Настройка одного канала уведомлений для категории событий.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreference {
+    pub event: NotificationEvent,
+    pub channel: NotificationChannel,
+    pub enabled: bool,
+}
+
+/// Присутствие пользователя в сети.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceInfo {
+    pub user_id: i32,
+    pub nickname: String,
+    pub online: bool,
+    /// Чем пользователь занят сейчас («повторяет карточки»).
+    pub activity: Option<ActivityType>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Строка живой доски класса: присутствие ученика и что он сделал за сегодня.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardEntry {
+    #[serde(flatten)]
+    pub presence: PresenceInfo,
+    pub reviews_today: i64,
+    pub learned_today: i64,
+    pub tests_today: i64,
+    pub last_active_at: Option<DateTime<Utc>>,
+}
+
+/// Настройки приватности присутствия.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PresenceSettingsPayload {
+    pub visible: bool,
+}
+
+/// Полезная нагрузка для заявки в друзья.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FriendRequestPayload {
+    pub nickname: String,
+}
+
+/// Заявка в друзья.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FriendRequest {
+    pub id: i32,
+    pub from_user_id: i32,
+    pub from_nickname: String,
+    pub to_user_id: i32,
+    pub to_nickname: String,
+    /// `pending`, `accepted` или `declined`.
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// Ожидающие ответа заявки текущего пользователя.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FriendRequests {
+    pub incoming: Vec<FriendRequest>,
+    pub outgoing: Vec<FriendRequest>,
+}
+
+/// Друг в списке друзей.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Friend {
+    pub user_id: i32,
+    pub nickname: String,
+    pub since: DateTime<Utc>,
+}
+
+/// Профиль друга: уровень, серия и достижения.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FriendProfile {
+    pub user_id: i32,
+    pub nickname: String,
+    pub level: LevelProgress,
+    pub streak: StreakSummary,
+    pub achievements: Vec<UserAchievementDetails>,
+}
+
+/// Полезная нагрузка для создания и изменения учебной группы.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StudyGroupPayload {
+    pub name: String,
+    /// Цель группы на неделю в очках опыта.
+    pub weekly_goal: i32,
+}
+
+/// Полезная нагрузка для вступления в группу по коду.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JoinGroupPayload {
+    pub code: String,
+}
+
+/// Участник учебной группы и его вклад в текущую неделю.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GroupMember {
+    pub user_id: i32,
+    pub nickname: String,
+    pub weekly_xp: i64,
+    pub studied_today: bool,
+}
+
+/// Учебная группа: общая цель недели, прогресс участников и серия группы.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StudyGroup {
+    pub id: i32,
+    pub name: String,
+    pub owner_id: i32,
+    /// Код для вступления; его видят только участники.
+    pub join_code: String,
+    pub weekly_goal: i32,
+    /// Опыт всех участников с начала недели (с понедельника).
+    pub weekly_xp: i64,
+    pub goal_met: bool,
+    pub members: Vec<GroupMember>,
+    /// Серия дней, когда занимались все участники; заморозок у группы нет.
+    pub streak: StreakSummary,
+}
+
+/// Предложение нового контента от пользователя.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContributionPayload {
+    Word {
+        simplified: String,
+        pinyin: String,
+        translation: String,
+    },
+    Sentence {
+        text: String,
+        pinyin: String,
+        translation: String,
+    },
+}
+
+impl ContributionPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ContributionPayload::Word { .. } => "word",
+            ContributionPayload::Sentence { .. } => "sentence",
+        }
+    }
+}
+
+/// Параметры списка предложений контента.
+#[derive(Debug, Deserialize)]
+pub struct ContributionsQuery {
+    pub status: Option<String>,
+}
+
+/// Параметры поиска пользователей: часть никнейма без учета регистра.
+#[derive(Debug, Deserialize)]
+pub struct UserSearchQuery {
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Порядок черт в формате Make Me A Hanzi: контуры черт (SVG path) в порядке
+/// написания и осевые линии черт для анимации.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrokeDataPayload {
+    pub strokes: Vec<String>,
+    #[serde(default)]
+    pub medians: Vec<Vec<[i32; 2]>>,
+}
+
+/// Порядок черт иероглифа в экранных координатах: поле 1024×1024, ось y вниз.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HieroglyphStrokes {
+    pub hieroglyph_id: i32,
+    pub character: String,
+    pub strokes: Vec<String>,
+    pub medians: Vec<Vec<[i32; 2]>>,
+}
+
+/// Формат файла импорта (`format` в `POST /api/admin/import`).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// Словарь CC-CEDICT: однознаковые статьи — иероглифы, остальные — слова.
+    Cedict,
+    /// Файлы Unihan: дополняют существующие иероглифы.
+    Unihan,
+    /// Официальный список слов HSK в CSV `word,pinyin,translation,level`.
+    Hsk,
+    /// Таблица иероглифов `character,pinyin,translation,example`, как в выгрузке.
+    HieroglyphCsv,
+}
+
+/// Параметры запуска импорта.
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    pub format: ImportFormat,
+    /// Версия стандарта для списков HSK.
+    pub hsk_version: Option<HskVersion>,
+}
+
+/// Что импорт сделает с записью.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Unchanged,
+    /// Запись сознательно пропускается (повтор, имя собственное и т.п.).
+    Skip,
+    /// Строку не удалось разобрать или она не прошла проверку.
+    Error,
+}
+
+/// Одна запись предпросмотра импорта.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportChange {
+    /// Строка файла, если запись из нее.
+    pub line: Option<usize>,
+    /// Иероглиф или слово.
+    pub key: Option<String>,
+    pub action: ImportAction,
+    /// Что именно изменится, расхождения с базой или текст ошибки.
+    pub details: Option<String>,
+}
+
+/// Предпросмотр импорта: сколько записей будет создано и изменено и сами записи.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub format: ImportFormat,
+    pub create: usize,
+    pub update: usize,
+    pub unchanged: usize,
+    pub skip: usize,
+    pub error: usize,
+    /// Записи без неизмененных; их может быть меньше, чем в счетчиках.
+    pub changes: Vec<ImportChange>,
+    /// Записей больше, чем помещается в предпросмотр.
+    pub truncated: bool,
+}
+
+/// Итог применения импорта.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportResult {
+    pub created: usize,
+    pub updated: usize,
+    /// Дополнительные сведения, например собранные колоды.
+    pub notes: Vec<String>,
+}
+
+/// Состояние импорта: разбор, предпросмотр и, после подтверждения, применение.
+#[derive(Debug, Serialize)]
+pub struct ImportStatus {
+    /// Id импорта — id задачи разбора.
+    pub id: u64,
+    pub format: ImportFormat,
+    pub job: Option<JobStatus>,
+    /// Задача применения, если импорт подтвержден.
+    pub apply_job: Option<JobStatus>,
+    pub preview: Option<ImportPreview>,
+    pub result: Option<ImportResult>,
+}
+
+/// Знает ли пользователь слово (для внешних инструментов чтения).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KnownStatus {
+    pub text: String,
+    /// `None`, если слова нет в словаре.
+    pub content_type: Option<ContentType>,
+    pub content_id: Option<i32>,
+    pub known: bool,
+    pub in_study_list: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StudyTextQuery {
+    pub text: String,
+}
+
+/// Ключ API (без самого значения ключа).
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateApiKeyPayload {
+    pub name: String,
+}
+
+/// Только что созданный ключ. Значение `key` больше нигде не показывается.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    pub id: i32,
+    pub name: String,
+    pub key: String,
+}
+
+/// Компактный ответ для всплывающего окна расширения браузера.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtLookupResponse {
+    pub text: String,
+    pub pinyin: Option<String>,
+    pub translation: Option<String>,
+    pub known: bool,
+    pub saved: bool,
+}
+
+/// Состояние интервального повторения элемента (SM-2).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReviewState {
+    pub ease_factor: f32,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub next_review_at: Option<DateTime<Utc>>,
+}
+
+/// Элемент очереди повторения.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReviewItem {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    /// Иероглиф, слово или текст предложения.
+    pub front: String,
+    pub pinyin: String,
+    pub translation: String,
+    /// Озвучка, если есть; клиент заранее скачивает ее для офлайн-повторения.
+    pub audio_url: Option<String>,
+    pub ease_factor: f32,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub next_review_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQueueQuery {
+    pub limit: Option<i64>,
+}
+
+/// Новое расписание повторения и достижения, полученные за ответ.
+#[derive(Debug, Serialize)]
+pub struct ReviewAnswerResponse {
+    #[serde(flatten)]
+    pub state: ReviewState,
+    pub new_achievements: Vec<Achievement>,
+}
+
+/// Отмененный ответ: элемент и восстановленное расписание.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReviewUndoResponse {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    #[serde(flatten)]
+    pub state: ReviewState,
+}
+
+/// Достижения, полученные за действие (например, за отметку «выучено»).
+#[derive(Debug, Serialize)]
+pub struct AchievementUnlocks {
+    pub new_achievements: Vec<Achievement>,
+    /// Прогноз повторений, если он уже выше порога и новое лучше отложить.
+    pub review_load: Option<ReviewForecast>,
+}
+
+/// Что делать с новыми элементами, когда прогноз повторений достиг порога.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewLoadMode {
+    Off,
+    Warn,
+    Block,
+}
+
+/// Общий порог нагрузки повторений, который задает админ.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewLoadSettings {
+    /// Повторений в день, после которых новые элементы не добавляются.
+    pub daily_limit: i32,
+    pub mode: ReviewLoadMode,
+}
+
+/// Сколько повторений придется на день; просроченные считаются на сегодня.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::FromRow)]
+pub struct ForecastDay {
+    pub day: NaiveDate,
+    pub due: i64,
+}
+
+/// Прогноз повторений на ближайшие дни и его сравнение с порогом.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewForecast {
+    pub days: Vec<ForecastDay>,
+    /// Порог пользователя, а если он его не задал — общий.
+    pub daily_limit: i32,
+    pub mode: ReviewLoadMode,
+    /// Больше всего повторений в один день прогноза.
+    pub peak: i64,
+    /// Порог достигнут: новые элементы лучше не добавлять.
+    pub overloaded: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastQuery {
+    pub days: Option<i64>,
+}
+
+/// Ответ на повторение.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReviewAnswerPayload {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    /// Качество вспоминания от 0 до 5.
+    pub quality: u8,
+    /// Время от показа карточки до оценки, мс; медленное «хорошо» засчитывается как «трудно».
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
+}
+
+// --- Синхронизация между устройствами ---
+
+#[derive(Debug, Deserialize)]
+pub struct SyncPullQuery {
+    /// Курсор из предыдущего ответа; без него возвращается все.
+    pub since: Option<i64>,
+}
+
+/// Прогресс и состояние повторения элемента в том виде, в каком он синхронизируется.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncProgress {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub is_learned: bool,
+    pub learned_at: Option<DateTime<Utc>>,
+    pub ease_factor: f32,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub next_review_at: Option<DateTime<Utc>>,
+    pub version: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Заметка пользователя к элементу контента.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncNote {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub body: String,
+    pub deleted: bool,
+    pub version: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Синхронизируемая часть настроек.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncSettings {
+    pub interface_language: String,
+    pub script: String,
+    pub reminder_time: Option<NaiveTime>,
+    pub version: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Изменения на сервере после курсора клиента.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncPullResponse {
+    /// Передается в следующий запрос как `since`.
+    pub cursor: i64,
+    /// Изменений больше, чем поместилось в ответ: нужно запросить еще раз.
+    pub has_more: bool,
+    pub progress: Vec<SyncProgress>,
+    pub notes: Vec<SyncNote>,
+    pub settings: Option<SyncSettings>,
+}
+
+/// Локальное изменение прогресса. `base_version` — версия записи, от которой
+/// клиент отталкивался (`None` для новой), `changed_at` — время изменения на устройстве.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ProgressChange {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub is_learned: bool,
+    pub learned_at: Option<DateTime<Utc>>,
+    pub ease_factor: f32,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub next_review_at: Option<DateTime<Utc>>,
+    pub base_version: Option<i64>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NoteChange {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub body: String,
+    #[serde(default)]
+    pub deleted: bool,
+    pub base_version: Option<i64>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SettingsChange {
+    pub interface_language: InterfaceLanguage,
+    pub script: Script,
+    pub reminder_time: Option<NaiveTime>,
+    pub base_version: Option<i64>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Пакет локальных изменений с устройства.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SyncPushPayload {
+    #[serde(default)]
+    pub progress: Vec<ProgressChange>,
+    #[serde(default)]
+    pub notes: Vec<NoteChange>,
+    pub settings: Option<SettingsChange>,
+}
+
+/// Итог приема изменений. После него клиент делает pull, чтобы получить
+/// итоговое состояние записей, в том числе объединенных.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncPushResponse {
+    /// Применено поверх той версии, которую видел клиент.
+    pub applied: usize,
+    /// Запись успела измениться на другом устройстве, изменения объединены.
+    pub merged: usize,
+    /// Изменение устарело и отброшено в пользу серверной версии.
+    pub rejected: usize,
+}
+
+/// Ответ с текстовым сообщением — для операций, у которых нет другого результата.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+impl MessageResponse {
+    pub fn new(message: &str) -> Self {
+        MessageResponse { message: message.to_string() }
+    }
+}
+
+/// Метрики очистки устаревших записей с момента запуска сервера.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HygieneStats {
+    pub runs: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// Сколько строк удалено при последнем запуске, по таблицам.
+    pub last_removed: BTreeMap<String, u64>,
+    pub total_removed: BTreeMap<String, u64>,
+}
+
+/// Удаленная конфигурация десктопного клиента.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientConfig {
+    /// Минимальная поддерживаемая версия клиента; более старые должны предложить обновление.
+    pub min_client_version: String,
+    pub features: BTreeMap<String, bool>,
+    /// Базовый адрес для относительных ссылок на медиафайлы.
+    pub media_base_url: String,
+    pub tts_available: bool,
+}
+
+/// Количество учебного контента на сервере.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ContentCounts {
+    pub hieroglyphs: i64,
+    pub words: i64,
+    pub phrases: i64,
+    pub sentences: i64,
+    pub grammar_rules: i64,
+    pub lessons: i64,
+}
+
+/// Этап запуска сервера.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    Connecting,
+    Migrating,
+    Ready,
+    Failed,
+}
+
+/// Ответ `/readyz`: готов ли сервер обслуживать запросы.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessStatus {
+    pub ready: bool,
+    pub phase: StartupPhase,
+    /// Последняя ошибка подключения к базе или миграции.
+    pub error: Option<String>,
+}
+
+/// Состояние сервера для стартовой страницы.
+#[derive(Debug, Serialize)]
+pub struct InstanceStatus {
+    pub version: &'static str,
+    pub registration: RegistrationMode,
+    pub registration_url: Option<String>,
+    pub public_content: bool,
+    pub content: ContentCounts,
+}
+
+/// Язык интерфейса клиента.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceLanguage {
+    Ru,
+    En,
+}
+
+/// Набор иероглифов: упрощенные или традиционные.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Simplified,
+    Traditional,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserSettings {
+    pub interface_language: String,
+    pub script: String,
+    /// Время ежедневного напоминания (UTC).
+    pub reminder_time: Option<NaiveTime>,
+    pub onboarding_finished_at: Option<DateTime<Utc>>,
+    pub onboarding_skipped: bool,
+    /// Свой порог повторений в день вместо общего.
+    pub daily_review_limit: Option<i32>,
+}
+
+/// Частичное обновление настроек: незаданные поля не меняются.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateSettingsPayload {
+    pub interface_language: Option<InterfaceLanguage>,
+    pub script: Option<Script>,
+    pub reminder_time: Option<NaiveTime>,
+    /// Отключить ежедневное напоминание.
+    #[serde(default)]
+    pub clear_reminder: bool,
+    pub daily_review_limit: Option<i32>,
+    /// Вернуться к общему порогу повторений.
+    #[serde(default)]
+    pub clear_daily_review_limit: bool,
+}
+
+/// Завершение вводного сценария.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FinishOnboardingPayload {
+    /// Пользователь пропустил сценарий, не пройдя шаги.
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// Состояние вводного сценария для клиента.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnboardingState {
+    /// Нужно ли показывать сценарий.
+    pub required: bool,
+    pub settings: UserSettings,
+    /// Тест для определения уровня (шаг пропускается, если не настроен).
+    pub placement_test_id: Option<i32>,
+    pub placement_test_taken: bool,
+    pub has_study_plan: bool,
+}
+
+/// Формат выгрузки данных пользователя.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Какие иероглифы попадают в колоду Anki.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnkiSelection {
+    #[default]
+    All,
+    Learned,
+    Unlearned,
+    /// Выученные, которые пора повторить.
+    Due,
+}
+
+/// Параметры выгрузки в Anki: `deck` — `all` или уровень вида `hsk1`.
+#[derive(Debug, Deserialize)]
+pub struct AnkiExportQuery {
+    pub deck: Option<String>,
+    #[serde(default)]
+    pub select: AnkiSelection,
+}
+
+/// Состояние изучения одного элемента контента в выгрузке.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportProgressRow {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub is_learned: bool,
+    pub learned_at: Option<DateTime<Utc>>,
+    pub misses: i32,
+    pub last_missed_at: Option<DateTime<Utc>>,
+    pub ease_factor: Option<f32>,
+    pub interval_days: Option<i32>,
+    pub repetitions: Option<i32>,
+    pub next_review_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportTestResult {
+    pub test_id: i32,
+    pub test_name: String,
+    pub score: i32,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Все учебные данные пользователя.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserExport {
+    pub exported_at: DateTime<Utc>,
+    pub nickname: String,
+    pub progress: Vec<ExportProgressRow>,
+    pub test_results: Vec<ExportTestResult>,
+    pub study_time: Vec<StudyTimeEntry>,
+}
+
+/// Аудитория объявления.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementAudience {
+    All,
+    Class,
+    Admins,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Announcement {
+    pub id: i32,
+    pub author_id: Option<i32>,
+    pub title: String,
+    pub body: String,
+    pub audience: String,
+    pub class_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Прочитано ли текущим пользователем (для гостя всегда `false`).
+    pub read: bool,
+}
+
+/// Полезная нагрузка для создания объявления.
+#[derive(Debug, Deserialize)]
+pub struct CreateAnnouncementPayload {
+    pub title: String,
+    pub body: String,
+    pub audience: AnnouncementAudience,
+    /// Обязателен для аудитории `class`.
+    pub class_id: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Заметка учителя к уроку для класса.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LessonNote {
+    pub id: i32,
+    pub class_id: i32,
+    pub lesson_id: i32,
+    pub author_id: Option<i32>,
+    pub author_nickname: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LessonNotePayload {
+    pub body: String,
+}
+
+/// Сообщение в обсуждении урока: вопрос или ответ на него (`parent_id`).
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LessonPost {
+    pub id: i32,
+    pub class_id: i32,
+    pub lesson_id: i32,
+    pub parent_id: Option<i32>,
+    pub author_id: Option<i32>,
+    pub author_nickname: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LessonPostPayload {
+    pub body: String,
+    /// Вопрос, на который дается ответ; без него создается новый вопрос.
+    pub parent_id: Option<i32>,
+}
+
+/// Таблица контента для массовых операций.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkTarget {
+    Hieroglyphs,
+    Words,
+    Sentences,
+}
+
+/// Операция в составе массового запроса.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    /// Добавить и/или снять теги с набора элементов.
+    Retag {
+        target: BulkTarget,
+        ids: Vec<i32>,
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// Проставить уровень HSK выбранным словам.
+    SetHskLevel { ids: Vec<i32>, level: i16 },
+    /// Опубликовать (принять) набор предложенных пользователями материалов.
+    PublishContributions { ids: Vec<i32> },
+}
+
+/// Полезная нагрузка для массовых операций.
+#[derive(Debug, Deserialize)]
+pub struct BulkPayload {
+    /// Только показать изменения, не применяя их.
+    #[serde(default)]
+    pub dry_run: bool,
+    pub operations: Vec<BulkOperation>,
+}
+
+/// Изменение одного элемента в результате массовой операции.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkChange {
+    /// Номер операции в запросе.
+    pub operation: usize,
+    /// Ключ элемента вида `word:12`.
+    pub item: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// Отчет о массовых операциях.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkReport {
+    pub dry_run: bool,
+    pub changes: Vec<BulkChange>,
+}
+
+/// Тип связи в графе контента.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphRelation {
+    /// Иероглиф состоит из компонента.
+    Component,
+    /// Слово содержит иероглиф.
+    Contains,
+    /// Предложение служит примером употребления.
+    Example,
+}
+
+/// Узел графа контента.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// Уникальный ключ узла вида `word:12`.
+    pub key: String,
+    pub content_type: ContentType,
+    pub id: i32,
+    pub label: String,
+}
+
+/// Ребро графа контента, направленное от целого к части.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub relation: GraphRelation,
+}
+
+/// Граф связей вокруг элемента контента.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContentGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Урок в списке уроков с учетом прогресса текущего пользователя.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Lesson {
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub position: i32,
+    pub item_count: i64,
+    pub completed: bool,
+}
+
+/// Элемент урока с подставленным содержимым.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LessonItem {
+    pub position: i32,
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub text: String,
+    pub pinyin: Option<String>,
+    pub translation: Option<String>,
+}
+
+/// Урок вместе с содержимым в порядке изучения.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LessonDetails {
+    #[serde(flatten)]
+    pub lesson: Lesson,
+    pub items: Vec<LessonItem>,
+}
+
+/// Полезная нагрузка для создания и изменения урока.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LessonPayload {
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub position: i32,
+}
+
+/// Ссылка на элемент контента в составе урока.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LessonItemRef {
+    pub content_type: ContentType,
+    pub content_id: i32,
+}
+
+/// Полезная нагрузка для задания состава урока.
+#[derive(Debug, Deserialize)]
+pub struct SetLessonItemsPayload {
+    /// Элементы в порядке изучения.
+    pub items: Vec<LessonItemRef>,
+}
+
+/// Результат прохождения урока.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LessonCompletion {
+    pub lesson_id: i32,
+    /// Сколько элементов урока впервые отмечено выученными.
+    pub newly_learned: u64,
+}
+
+/// Полезная нагрузка для задания компонентов иероглифа.
+#[derive(Debug, Deserialize)]
+pub struct SetComponentsPayload {
+    /// Компоненты в порядке написания.
+    pub components: Vec<String>,
+}
+
+/// Компоненты, которые стоит выучить перед иероглифом.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrerequisitesResponse {
+    pub hieroglyph_id: i32,
+    /// Невыученные компоненты в порядке изучения.
+    pub prerequisites: Vec<Hieroglyph>,
+    /// Запрещено ли отмечать иероглиф выученным, пока компоненты не изучены.
+    pub enforced: bool,
+}
+
+/// Полезная нагрузка для оценки сложности предложения.
+#[derive(Debug, Deserialize)]
+pub struct GradeSentencePayload {
+    pub text: String,
+}
+
+/// Слово предложения с отметкой, знает ли его пользователь.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GradedToken {
+    pub text: String,
+    pub word_id: Option<i32>,
+    pub hsk_level: Option<i16>,
+    pub known: bool,
+}
+
+/// Оценка сложности предложения для пользователя.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SentenceGrade {
+    pub tokens: Vec<GradedToken>,
+    pub known_percent: f32,
+    /// Оценка уровня HSK; `None`, если слишком много слов вне списков HSK.
+    pub hsk_level: Option<i16>,
+}
+
+/// Полезная нагрузка для разбора текста на новые слова.
+#[derive(Debug, Deserialize)]
+pub struct MinePayload {
+    pub text: String,
+    /// Самый высокий уровень HSK для отбора слов; по умолчанию — на один выше уровня пользователя.
+    #[serde(default)]
+    pub max_level: Option<i16>,
+}
+
+/// Незнакомое слово из текста с предложением-пропуском.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MinedWord {
+    pub word_id: i32,
+    pub simplified: String,
+    pub pinyin: Option<String>,
+    pub translation: Option<String>,
+    pub hsk_level: Option<i16>,
+    /// Предложение текста, в котором слово встретилось первым.
+    pub sentence: String,
+    /// То же предложение, где слово заменено пропуском.
+    pub cloze: String,
+}
+
+/// Результат разбора текста.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MiningResult {
+    /// Уровень HSK, до которого отбирались слова.
+    pub max_level: i16,
+    pub token_count: usize,
+    pub known_count: usize,
+    pub words: Vec<MinedWord>,
+}
+
+/// Полезная нагрузка для создания колоды из текста.
+#[derive(Debug, Deserialize)]
+pub struct CreateMinedDeckPayload {
+    pub name: String,
+    pub text: String,
+    #[serde(default)]
+    pub max_level: Option<i16>,
+    /// Слова, которые нужно взять в колоду; по умолчанию — все найденные.
+    #[serde(default)]
+    pub word_ids: Option<Vec<i32>>,
+}
+
+/// Личная колода, собранная из текста.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MinedDeck {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub card_count: i64,
+}
+
+/// Личная колода с карточками.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MinedDeckDetails {
+    #[serde(flatten)]
+    pub deck: MinedDeck,
+    pub cards: Vec<MinedWord>,
+}
+
+/// Параметры поиска в словаре.
+#[derive(Debug, Deserialize)]
+pub struct LookupQuery {
+    pub q: String,
+}
+
+/// Query-параметры подсказок для формы создания контента.
+#[derive(Debug, Deserialize)]
+pub struct ContentAssistQuery {
+    pub text: String,
+}
+
+/// Уже существующий элемент контента с тем же текстом.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExistingContent {
+    pub content_type: ContentType,
+    pub id: i32,
+    pub pinyin: String,
+    pub translation: String,
+}
+
+/// Подсказки для формы создания контента.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentAssist {
+    pub text: String,
+    /// Пиньинь из словаря или склеенный из пиньиня слов.
+    pub pinyin: Option<String>,
+    pub translation: Option<String>,
+    /// Текст, разбитый на слова, с пиньинем и переводом каждого (пустые, если слова нет в словаре).
+    pub segments: Vec<LookupEntry>,
+    pub duplicates: Vec<ExistingContent>,
+}
+
+/// Откуда взят результат поиска в словаре.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupSource {
+    Local,
+    Cache,
+    External,
+}
+
+/// Статья словаря.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LookupEntry {
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+}
+
+/// Результат поиска в словаре.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LookupResponse {
+    pub query: String,
+    pub source: LookupSource,
+    pub entries: Vec<LookupEntry>,
+}
+
+/// Query-параметры поиска по словарю.
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    /// Максимум результатов каждого типа.
+    pub limit: Option<i64>,
+}
+
+/// Найденный элемент контента с рангом совпадения от 0 до 1.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SearchHit {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub rank: f32,
+}
+
+/// Результаты поиска одного типа контента, лучшие первыми.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchGroup {
+    pub content_type: ContentType,
+    pub items: Vec<SearchHit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub groups: Vec<SearchGroup>,
+}
+
+/// Полезная нагрузка для регистрации.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RegisterPayload {
+    pub nickname: String,
+    pub password: String,
+    /// Код приглашения, обязателен в режиме `REGISTRATION_MODE=invite`.
+    #[serde(default)]
+    pub invite_code: Option<String>,
+    /// Почта, обязательна, если заданы разрешенные домены (`REGISTRATION_EMAIL_DOMAINS`).
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Полезная нагрузка для создания кодов приглашений.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateInvitesPayload {
+    pub count: u32,
+    pub expires_in_days: Option<i64>,
+}
+
+/// Код приглашения.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct InviteCode {
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Строка CSV-списка учеников для массового создания аккаунтов.
+#[derive(Debug, Deserialize)]
+pub struct RosterRow {
+    pub nickname: String,
+    pub password: String,
+    pub role: Option<UserRole>,
+}
+
+/// Результат обработки одной строки CSV-списка.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RosterRowResult {
+    pub line: usize,
+    pub nickname: Option<String>,
+    pub created: bool,
+    pub error: Option<String>,
+}
+
+/// Полезная нагрузка для логина.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoginPayload {
+    pub nickname: String,
+    pub password: String,
+    /// Идентификатор устройства клиента, к которому привязывается сессия.
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
+
+/// Полезная нагрузка для обновления токена.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefreshPayload {
+    pub refresh_token: String,
+    /// Тот же идентификатор устройства, что и при входе.
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
+
+/// Полезная нагрузка для запроса сброса пароля.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ForgotPasswordPayload {
+    pub email: String,
+}
+
+/// Полезная нагрузка для смены пароля по коду из письма.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResetPasswordPayload {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// Полезная нагрузка для подтверждения почты кодом из письма.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VerifyEmailPayload {
+    pub token: String,
+}
+
+/// Полезная нагрузка для создания иероглифа
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateHieroglyphPayload {
+    pub character: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub example: Option<String>,
+}
+
+/// Полезная нагрузка для создания и изменения слова.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WordPayload {
+    pub simplified: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub hsk_level: Option<i16>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Полезная нагрузка для создания и изменения фразы.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PhrasePayload {
+    pub text: String,
+    pub pinyin: String,
+    pub translation: String,
+    pub usage_note: Option<String>,
+    pub hsk_level: Option<i16>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Полезная нагрузка для создания и изменения правила грамматики.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GrammarRulePayload {
+    pub title: String,
+    pub explanation: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub examples: Vec<GrammarExample>,
+    pub hsk_level: Option<i16>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Полезная нагрузка для отметки контента как выученного.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MarkLearnedPayload {
+    pub content_type: ContentType,
+    pub content_id: i32,
+}
+
+/// Элемент, который пользователь скрыл из занятий.
+#[derive(Debug, Deserialize)]
+pub struct HiddenItemPayload {
+    pub content_type: ContentType,
+    pub content_id: i32,
+}
+
+/// Скрытый элемент с лицевой стороной для списка в настройках.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct HiddenItem {
+    pub content_type: ContentType,
+    pub content_id: i32,
+    pub front: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+
+/// Ответ с токенами.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+/// Структура "claims" для JWT.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub exp: usize,
+    pub iat: usize,
+    pub user_id: i32,
+    pub role: UserRole,
+    /// Сессия, для которой выдан токен (`GET /api/sessions/me` отмечает текущую).
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// Вид пересчета производных данных.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecomputeKind {
+    Achievements,
+    /// Дни занятий по журналу повторений, выученному и тестам.
+    Streaks,
+    /// Опыт и уровни, по которым строятся рейтинги, из журнала начислений.
+    Leaderboards,
+    /// Отметки о прохождении уроков, все элементы которых выучены.
+    LessonCompletion,
+}
+
+/// Полезная нагрузка для запуска пересчета.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecomputePayload {
+    pub kind: RecomputeKind,
+}
+
+/// Состояние фоновой задачи.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Результат одной проверки самодиагностики.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfCheckStatus {
+    Ok,
+    /// Работает, но стоит исправить.
+    Warning,
+    Failed,
+    /// Проверка неприменима (например, почта не настроена).
+    Skipped,
+}
+
+/// Проверка самодиагностики с подсказкой, что исправить.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfCheck {
+    pub name: String,
+    pub status: SelfCheckStatus,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+/// Отчет самодиагностики сервера (`doctor`, `/api/admin/selfcheck`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelfCheckReport {
+    /// Нет ни одной проваленной проверки.
+    pub ok: bool,
+    pub checks: Vec<SelfCheck>,
+}
+
+impl SelfCheckReport {
+    pub fn new(checks: Vec<SelfCheck>) -> Self {
+        let ok = checks.iter().all(|c| c.status != SelfCheckStatus::Failed);
+        SelfCheckReport { ok, checks }
+    }
+}
+
+/// Статус фоновой задачи с прогрессом выполнения.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub kind: String,
+    pub state: JobState,
+    pub processed: usize,
+    pub total: usize,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Открытый ключ проверки JWT в формате JWK (RFC 7517).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    /// Модуль RSA, base64url без выравнивания.
+    pub n: String,
+    /// Экспонента RSA, base64url без выравнивания.
+    pub e: String,
+}
+
+/// Ответ `GET /.well-known/jwks.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
 }
 
 // --- Application State ---
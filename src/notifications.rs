@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::errors::AppError;
+use crate::mailer::{self, Email};
+use crate::models::NotificationPreference;
+
+/// Вид уведомления.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationKind {
+    AssignmentReminder,
+    AssignmentSummary,
+    StudyReminder,
+    LessonNote,
+    LessonQuestion,
+    LessonReply,
+    Achievement,
+    FriendRequest,
+    FriendAccepted,
+    GroupGoal,
+    GroupStreak,
+}
+
+impl NotificationKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::AssignmentReminder => "assignment_reminder",
+            NotificationKind::AssignmentSummary => "assignment_summary",
+            NotificationKind::StudyReminder => "study_reminder",
+            NotificationKind::LessonNote => "lesson_note",
+            NotificationKind::LessonQuestion => "lesson_question",
+            NotificationKind::LessonReply => "lesson_reply",
+            NotificationKind::Achievement => "achievement",
+            NotificationKind::FriendRequest => "friend_request",
+            NotificationKind::FriendAccepted => "friend_accepted",
+            NotificationKind::GroupGoal => "group_goal",
+            NotificationKind::GroupStreak => "group_streak",
+        }
+    }
+
+    /// Категория событий, по которой пользователь настраивает уведомления.
+    pub fn event(self) -> NotificationEvent {
+        match self {
+            NotificationKind::AssignmentReminder | NotificationKind::AssignmentSummary => NotificationEvent::Assignments,
+            NotificationKind::StudyReminder => NotificationEvent::Reminders,
+            NotificationKind::LessonNote => NotificationEvent::Assignments,
+            NotificationKind::LessonQuestion
+            | NotificationKind::LessonReply
+            | NotificationKind::FriendRequest
+            | NotificationKind::FriendAccepted
+            | NotificationKind::GroupGoal
+            | NotificationKind::GroupStreak => NotificationEvent::Social,
+            NotificationKind::Achievement => NotificationEvent::Achievements,
+        }
+    }
+}
+
+/// Категория событий в настройках уведомлений.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    Achievements,
+    Reminders,
+    Assignments,
+    Social,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 4] = [
+        NotificationEvent::Achievements,
+        NotificationEvent::Reminders,
+        NotificationEvent::Assignments,
+        NotificationEvent::Social,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationEvent::Achievements => "achievements",
+            NotificationEvent::Reminders => "reminders",
+            NotificationEvent::Assignments => "assignments",
+            NotificationEvent::Social => "social",
+        }
+    }
+}
+
+/// Канал доставки уведомлений.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    InApp,
+    Email,
+    Desktop,
+}
+
+impl NotificationChannel {
+    pub const ALL: [NotificationChannel; 3] = [
+        NotificationChannel::InApp,
+        NotificationChannel::Email,
+        NotificationChannel::Desktop,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationChannel::InApp => "in_app",
+            NotificationChannel::Email => "email",
+            NotificationChannel::Desktop => "desktop",
+        }
+    }
+}
+
+/// Возвращает полную матрицу настроек пользователя с учетом значений по умолчанию.
+pub async fn preferences(user_id: i32, pool: &PgPool) -> Result<Vec<NotificationPreference>, AppError> {
+    let stored: HashMap<(String, String), bool> = sqlx::query_as::<_, (String, String, bool)>(
+        "SELECT event, channel, enabled FROM notification_preferences WHERE user_id = $1",
+    )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(event, channel, enabled)| ((event, channel), enabled))
+        .collect();
+
+    let mut result = Vec::new();
+    for event in NotificationEvent::ALL {
+        for channel in NotificationChannel::ALL {
+            let enabled = stored
+                .get(&(event.as_str().to_string(), channel.as_str().to_string()))
+                .copied()
+                .unwrap_or(true);
+            result.push(NotificationPreference { event, channel, enabled });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Сохраняет настройки уведомлений пользователя.
+pub async fn save_preferences(
+    user_id: i32,
+    preferences: &[NotificationPreference],
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    for preference in preferences {
+        sqlx::query(
+            "INSERT INTO notification_preferences (user_id, event, channel, enabled)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (user_id, event, channel) DO UPDATE SET enabled = EXCLUDED.enabled",
+        )
+            .bind(user_id)
+            .bind(preference.event.as_str())
+            .bind(preference.channel.as_str())
+            .bind(preference.enabled)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn channel_enabled(
+    user_id: i32,
+    event: NotificationEvent,
+    channel: NotificationChannel,
+    pool: &PgPool,
+) -> Result<bool, AppError> {
+    let enabled: Option<bool> = sqlx::query_scalar(
+        "SELECT enabled FROM notification_preferences WHERE user_id = $1 AND event = $2 AND channel = $3",
+    )
+        .bind(user_id)
+        .bind(event.as_str())
+        .bind(channel.as_str())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(enabled.unwrap_or(true))
+}
+
+/// Отправляет пользователю уведомление.
+/// Все источники уведомлений должны проходить через эту функцию: здесь
+/// централизованно учитываются настройки каналов пользователя.
+pub async fn notify(
+    user_id: i32,
+    kind: NotificationKind,
+    title: &str,
+    body: &str,
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    let event = kind.event();
+    let in_app = channel_enabled(user_id, event, NotificationChannel::InApp, pool).await?;
+    let desktop = channel_enabled(user_id, event, NotificationChannel::Desktop, pool).await?;
+
+    // Уведомление на рабочем столе показывает клиент, забирая его из общего списка,
+    // поэтому запись нужна, если включен хотя бы один из этих каналов.
+    if in_app || desktop {
+        sqlx::query("INSERT INTO notifications (user_id, kind, title, body, desktop) VALUES ($1, $2, $3, $4, $5)")
+            .bind(user_id)
+            .bind(kind.as_str())
+            .bind(title)
+            .bind(body)
+            .bind(desktop)
+            .execute(pool)
+            .await?;
+    }
+
+    if channel_enabled(user_id, event, NotificationChannel::Email, pool).await? {
+        let email: Option<String> = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+        if let Some(email) = email {
+            mailer::send_later(Email { to: email, subject: title.to_string(), body: body.to_string() });
+        }
+    }
+
+    Ok(())
+}
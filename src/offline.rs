@@ -0,0 +1,104 @@
+// offline.rs
+//
+// "Download for offline": fetches a deck's items in batches, pins its media in
+// the media cache and saves the items to `OFFLINE_DIR` (default `offline`).
+// The download is verified against the server manifest before it is kept:
+// the items must hash to the manifest checksum and every media file must be
+// cached with the expected size.
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+
+use crate::media_cache;
+use crate::models::{DeckItem, DeckManifest};
+
+/// Items per request; the server caps batches at the same size.
+const BATCH_SIZE: usize = 200;
+
+/// A deck saved on disk for offline study.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OfflineDeck {
+    pub deck_id: i32,
+    pub name: String,
+    pub checksum: String,
+    pub items: Vec<DeckItem>,
+}
+
+fn offline_dir() -> PathBuf {
+    PathBuf::from(env::var("OFFLINE_DIR").unwrap_or_else(|_| "offline".to_string()))
+}
+
+fn deck_path(deck_id: i32) -> PathBuf {
+    offline_dir().join(format!("deck-{}.json", deck_id))
+}
+
+/// Loads a previously downloaded deck, if any.
+pub fn load(deck_id: i32) -> Option<OfflineDeck> {
+    let bytes = std::fs::read(deck_path(deck_id)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Downloads a deck with its media. `on_progress(done, total)` is called after
+/// every batch and media file; it runs on the calling (worker) thread.
+pub async fn download_deck(
+    client: &Client,
+    deck_id: i32,
+    on_progress: impl Fn(usize, usize),
+) -> Result<OfflineDeck, String> {
+    let base = crate::api_base_url();
+    let manifest: DeckManifest = client
+        .get(format!("{}/api/decks/{}/manifest", base, deck_id))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let item_count = manifest.item_count as usize;
+    let batches = item_count.div_ceil(BATCH_SIZE);
+    let total = batches + manifest.media.len();
+    on_progress(0, total);
+
+    let mut items: Vec<DeckItem> = Vec::with_capacity(item_count);
+    for batch in 0..batches {
+        let page: Vec<DeckItem> = client
+            .get(format!("{}/api/decks/{}/items", base, deck_id))
+            .query(&[("offset", batch * BATCH_SIZE), ("limit", BATCH_SIZE)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        items.extend(page);
+        on_progress(batch + 1, total);
+    }
+
+    for (i, media) in manifest.media.iter().enumerate() {
+        media_cache::fetch_pinned(client, &media.url).await?;
+        on_progress(batches + i + 1, total);
+    }
+
+    // Integrity check: the deck may have changed mid-download or a file may be truncated
+    if crate::decks::checksum(&items) != manifest.checksum {
+        return Err("Контрольная сумма колоды не совпадает, попробуйте скачать еще раз".to_string());
+    }
+    for media in &manifest.media {
+        if media_cache::cached_size(&media.url) != Some(media.size_bytes as u64) {
+            return Err(format!("Файл {} скачан не полностью", media.url));
+        }
+    }
+
+    let deck = OfflineDeck { deck_id, name: manifest.name, checksum: manifest.checksum, items };
+    let dir = offline_dir();
+    std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(deck_path(deck_id), serde_json::to_vec(&deck).unwrap_or_default()))
+        .map_err(|e| e.to_string())?;
+
+    Ok(deck)
+}
@@ -0,0 +1,173 @@
+// Incremental list model for paginated endpoints.
+//
+// The model starts with the first page and asks for the next one when the view
+// shows a row close to the end, so long lists (notifications, result history,
+// the hieroglyph browser) never load everything up front. Pages are fetched on a
+// background thread like the rest of the client's network calls; results come
+// back to the UI thread through `slint::invoke_from_event_loop`.
+
+use slint::{Model, ModelNotify, ModelTracker};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The next page is requested once a row this close to the end is shown.
+const PREFETCH_ROWS: usize = 10;
+
+/// One page of rows and the cursor of the next one; `None` when the list is complete.
+pub struct Page<R> {
+    pub rows: Vec<R>,
+    pub next_cursor: Option<String>,
+}
+
+type PageFuture<R> = Pin<Box<dyn Future<Output = Result<Page<R>, String>>>>;
+type Fetch<R> = Arc<dyn Fn(Option<String>) -> PageFuture<R> + Send + Sync>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // Live models by id. The fetch thread only knows the id: the model itself is
+    // an `Rc` and never leaves the UI thread.
+    static MODELS: RefCell<HashMap<u64, Weak<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// List model that loads rows `R` from the server page by page and shows them as `T`.
+pub struct PagedModel<R, T> {
+    id: u64,
+    fetch: Fetch<R>,
+    map: fn(R) -> T,
+    rows: RefCell<Vec<T>>,
+    next_cursor: RefCell<Option<String>>,
+    complete: Cell<bool>,
+    loading: Cell<bool>,
+    // A failed page is not retried on every repaint, only after `reload`
+    failed: Cell<bool>,
+    // Bumped by `reload`, so a page requested before it is dropped
+    generation: Cell<u64>,
+    notify: ModelNotify,
+}
+
+impl<R: Send + 'static, T: Clone + 'static> PagedModel<R, T> {
+    /// Creates an empty model; the first page is requested by `reload`, usually
+    /// when its view is opened. `fetch` gets the cursor returned with the previous
+    /// page (`None` for the first one).
+    pub fn new<F, Fut>(fetch: F, map: fn(R) -> T) -> Rc<Self>
+    where
+        F: Fn(Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Page<R>, String>> + 'static,
+    {
+        let model = Rc::new(PagedModel {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            fetch: Arc::new(move |cursor| Box::pin(fetch(cursor)) as PageFuture<R>),
+            map,
+            rows: RefCell::new(Vec::new()),
+            next_cursor: RefCell::new(None),
+            complete: Cell::new(false),
+            loading: Cell::new(false),
+            failed: Cell::new(false),
+            generation: Cell::new(0),
+            notify: ModelNotify::default(),
+        });
+        let weak: Weak<dyn Any> = Rc::downgrade(&model) as Weak<dyn Any>;
+        MODELS.with(|models| models.borrow_mut().insert(model.id, weak));
+        model
+    }
+
+    /// Drops the loaded rows and starts again from the first page.
+    pub fn reload(&self) {
+        self.generation.set(self.generation.get() + 1);
+        self.rows.borrow_mut().clear();
+        *self.next_cursor.borrow_mut() = None;
+        self.complete.set(false);
+        self.loading.set(false);
+        self.failed.set(false);
+        self.notify.reset();
+        self.load_more();
+    }
+
+    fn load_more(&self) {
+        if self.loading.get() || self.complete.get() || self.failed.get() {
+            return;
+        }
+        self.loading.set(true);
+
+        let id = self.id;
+        let generation = self.generation.get();
+        let cursor = self.next_cursor.borrow().clone();
+        let fetch = self.fetch.clone();
+        std::thread::spawn(move || {
+            let result = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime.block_on(fetch(cursor)),
+                Err(e) => Err(format!("Failed to start runtime: {}", e)),
+            };
+            let _ = slint::invoke_from_event_loop(move || deliver::<R, T>(id, generation, result));
+        });
+    }
+
+    fn append(&self, generation: u64, result: Result<Page<R>, String>) {
+        if generation != self.generation.get() {
+            return;
+        }
+        self.loading.set(false);
+
+        match result {
+            Ok(page) => {
+                let start = self.rows.borrow().len();
+                let count = page.rows.len();
+                self.rows.borrow_mut().extend(page.rows.into_iter().map(self.map));
+                // An empty page with a cursor would otherwise be requested forever
+                self.complete.set(page.next_cursor.is_none() || count == 0);
+                *self.next_cursor.borrow_mut() = page.next_cursor;
+                if count > 0 {
+                    self.notify.row_added(start, count);
+                }
+            }
+            Err(e) => {
+                println!("Failed to load list page: {}", e);
+                self.failed.set(true);
+            }
+        }
+    }
+}
+
+fn deliver<R: Send + 'static, T: Clone + 'static>(id: u64, generation: u64, result: Result<Page<R>, String>) {
+    let model = MODELS.with(|models| models.borrow().get(&id).and_then(Weak::upgrade));
+    if let Some(model) = model.and_then(|model| model.downcast::<PagedModel<R, T>>().ok()) {
+        model.append(generation, result);
+    }
+}
+
+impl<R, T> Drop for PagedModel<R, T> {
+    fn drop(&mut self) {
+        let _ = MODELS.try_with(|models| models.borrow_mut().remove(&self.id));
+    }
+}
+
+impl<R: Send + 'static, T: Clone + 'static> Model for PagedModel<R, T> {
+    type Data = T;
+
+    fn row_count(&self) -> usize {
+        self.rows.borrow().len()
+    }
+
+    fn row_data(&self, row: usize) -> Option<T> {
+        let data = self.rows.borrow().get(row).cloned();
+        if row + PREFETCH_ROWS >= self.row_count() {
+            self.load_more();
+        }
+        data
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        &self.notify
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,74 @@
+use axum::http::StatusCode;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+
+use crate::config::{self, PasswordPolicy};
+use crate::errors::{AppError, FieldError};
+
+/// Длиннее пароль не принимается: хеширование очень длинных строк — легкий способ нагрузить сервер.
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+// Встроенный список распространенных паролей, строки с `#` — комментарии
+static COMMON_PASSWORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    include_str!("../resources/common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+});
+
+/// Сколько классов символов есть в пароле: строчные, заглавные, цифры, прочие.
+pub fn character_classes(password: &str) -> usize {
+    let lower = password.chars().any(char::is_lowercase);
+    let upper = password.chars().any(char::is_uppercase);
+    let digit = password.chars().any(|c| c.is_ascii_digit());
+    let other = password.chars().any(|c| !c.is_alphanumeric());
+    [lower, upper, digit, other].iter().filter(|present| **present).count()
+}
+
+/// Проверяет пароль по политике и возвращает все нарушения сразу, чтобы
+/// пользователь исправил пароль за один раз. Пустой список — пароль подходит.
+pub fn check(password: &str, nickname: Option<&str>, policy: &PasswordPolicy) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut push = |code, message: String| errors.push(FieldError { field: "password", code, message });
+
+    let length = password.chars().count();
+    if length < policy.min_length {
+        push("too_short", format!("Пароль должен быть не короче {} символов", policy.min_length));
+    }
+    if length > MAX_PASSWORD_LENGTH {
+        push("too_long", format!("Пароль должен быть не длиннее {} символов", MAX_PASSWORD_LENGTH));
+    }
+    if character_classes(password) < policy.min_classes {
+        push(
+            "too_simple",
+            format!(
+                "Пароль должен содержать символы хотя бы {} видов из четырех: строчные и заглавные буквы, цифры, другие символы",
+                policy.min_classes
+            ),
+        );
+    }
+
+    let lowered = password.to_lowercase();
+    if policy.reject_common && COMMON_PASSWORDS.contains(lowered.as_str()) {
+        push("common", "Этот пароль слишком распространен, его легко подобрать".to_string());
+    }
+    if let Some(nickname) = nickname.map(str::trim).filter(|nickname| !nickname.is_empty()) {
+        if lowered.contains(&nickname.to_lowercase()) {
+            push("contains_nickname", "Пароль не должен содержать никнейм".to_string());
+        }
+    }
+    errors
+}
+
+/// Проверяет пароль по политике сервера. Ошибка `weak_password` перечисляет
+/// нарушения в `fields`, чтобы клиент показал их у поля пароля.
+pub fn ensure_valid(password: &str, nickname: Option<&str>) -> Result<(), AppError> {
+    let errors = check(password, nickname, &config::password_policy());
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::new(StatusCode::BAD_REQUEST, "Пароль не соответствует требованиям")
+        .with_code("weak_password")
+        .with_fields(errors))
+}
@@ -0,0 +1,106 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::auth;
+use crate::errors::AppError;
+use crate::lockout;
+use crate::mailer::{self, Email};
+use crate::password_policy;
+
+/// Сколько действует токен сброса пароля.
+pub const TOKEN_TTL_MINUTES: i64 = 60;
+/// Не чаще одного письма за столько секунд на пользователя.
+const RESEND_INTERVAL_SECS: i64 = 60;
+
+/// В базе лежит только хеш: утечка таблицы не дает сбросить чужой пароль.
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Выдает токен сброса и отправляет его на почту. Ответ не зависит от того,
+/// есть ли такая почта, чтобы по нему нельзя было проверять чужие адреса.
+pub async fn request(email: &str, pool: &PgPool) -> Result<(), AppError> {
+    let user: Option<(i32, String, String)> = sqlx::query_as(
+        "SELECT id, nickname, email FROM users
+         WHERE lower(email) = lower($1) AND NOT is_banned AND disabled_at IS NULL",
+    )
+        .bind(email.trim())
+        .fetch_optional(pool)
+        .await?;
+    let Some((user_id, nickname, email)) = user else {
+        return Ok(());
+    };
+
+    let recent: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM password_reset_tokens
+                        WHERE user_id = $1 AND created_at > NOW() - make_interval(secs => $2))",
+    )
+        .bind(user_id)
+        .bind(RESEND_INTERVAL_SECS as f64)
+        .fetch_one(pool)
+        .await?;
+    if recent {
+        return Ok(());
+    }
+
+    // Новый токен отменяет неиспользованные старые
+    let token = auth::random_token();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO password_reset_tokens (token_hash, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(token_hash(&token))
+        .bind(user_id)
+        .bind(Utc::now() + Duration::minutes(TOKEN_TTL_MINUTES))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    mailer::send_later(Email {
+        to: email,
+        subject: "Сброс пароля".to_string(),
+        body: format!(
+            "Здравствуйте, {}!\n\nКод для сброса пароля:\n\n{}\n\nКод действует {} минут и подходит один раз. \
+             Если вы не запрашивали сброс, просто проигнорируйте это письмо.",
+            nickname, token, TOKEN_TTL_MINUTES
+        ),
+    });
+    Ok(())
+}
+
+/// Меняет пароль по токену и завершает все сессии пользователя. Новый пароль
+/// проверяется политикой с учетом никнейма владельца токена; отклоненный
+/// пароль токен не гасит.
+pub async fn reset(token: &str, new_password: &str, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    // Токен гасится тем же запросом, который его проверяет: дважды его не использовать
+    let (user_id, nickname): (i32, String) = sqlx::query_as(
+        "UPDATE password_reset_tokens t SET used_at = NOW()
+         FROM users u
+         WHERE t.token_hash = $1 AND t.used_at IS NULL AND t.expires_at > NOW() AND u.id = t.user_id
+         RETURNING t.user_id, u.nickname",
+    )
+        .bind(token_hash(token.trim()))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::BAD_REQUEST, "Код сброса недействителен или устарел").with_code("invalid_reset_token")
+        })?;
+
+    password_policy::ensure_valid(new_password, Some(&nickname))?;
+    let password_hash = auth::hash_password(new_password)?;
+
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+        .bind(&password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    auth::revoke_all_sessions(user_id, &mut *tx).await?;
+    lockout::unlock(user_id, &mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::hidden;
+use crate::models::{PlanProgress, StudyPlan, StudyPlanStatus};
+
+/// Считает, сколько элементов колоды пользователь еще не выучил.
+/// Скрытые элементы он учить не будет, поэтому они не считаются.
+pub async fn remaining_in_deck(user_id: i32, deck_id: i32, pool: &PgPool) -> Result<i64, AppError> {
+    let remaining = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM deck_items di
+         WHERE di.deck_id = $2 AND NOT EXISTS (
+             SELECT 1 FROM user_progress up
+             WHERE up.user_id = $1 AND up.content_type = di.content_type
+               AND up.content_id = di.content_id AND up.is_learned
+         ) AND {}",
+        hidden::not_hidden("$1", "di.content_type", "di.content_id")
+    ))
+        .bind(user_id)
+        .bind(deck_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(remaining)
+}
+
+/// Рассчитывает состояние плана на дату `today`.
+///
+/// Темп пересчитывается от оставшегося объема и оставшихся дней, поэтому
+/// пропущенные дни автоматически распределяются на оставшийся срок.
+pub fn compute_progress(
+    initial_remaining: i64,
+    remaining: i64,
+    start_date: NaiveDate,
+    target_date: NaiveDate,
+    today: NaiveDate,
+) -> PlanProgress {
+    let total_days = (target_date - start_date).num_days().max(1);
+    let elapsed_days = (today - start_date).num_days().clamp(0, total_days);
+    // Сегодняшний день тоже считается доступным для занятий
+    let days_left = (target_date - today).num_days().max(0) + 1;
+
+    let learned = (initial_remaining - remaining).max(0);
+    let expected_learned = initial_remaining * elapsed_days / total_days;
+    let daily_pace = if remaining == 0 {
+        0
+    } else {
+        (remaining + days_left - 1) / days_left
+    };
+
+    let status = if remaining == 0 {
+        StudyPlanStatus::Completed
+    } else if today > target_date {
+        StudyPlanStatus::Overdue
+    } else if learned >= expected_learned {
+        StudyPlanStatus::OnSchedule
+    } else {
+        StudyPlanStatus::Behind
+    };
+
+    PlanProgress {
+        learned,
+        remaining,
+        expected_learned,
+        daily_pace,
+        days_left,
+        status,
+    }
+}
+
+/// Возвращает план вместе с текущим прогрессом.
+pub async fn with_progress(plan: StudyPlan, pool: &PgPool) -> Result<(StudyPlan, PlanProgress), AppError> {
+    let remaining = remaining_in_deck(plan.user_id, plan.deck_id, pool).await?;
+    let today = chrono::Utc::now().date_naive();
+    let progress = compute_progress(
+        plan.initial_remaining as i64,
+        remaining,
+        plan.start_date,
+        plan.target_date,
+        today,
+    );
+
+    Ok((plan, progress))
+}
@@ -0,0 +1,93 @@
+use axum::extract::ws::{Message, WebSocket};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::models::{ActivityType, PresenceInfo};
+
+/// Состояние подключенного пользователя.
+#[derive(Debug, Clone)]
+struct Presence {
+    /// Сколько открыто соединений (клиент может быть запущен на нескольких устройствах).
+    connections: usize,
+    activity: Option<ActivityType>,
+    since: DateTime<Utc>,
+}
+
+// Реестр WebSocket-подключений. Присутствие — эфемерное состояние,
+// поэтому оно хранится только в памяти процесса.
+static REGISTRY: Lazy<Mutex<HashMap<i32, Presence>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Сообщение клиента о смене активности.
+#[derive(Debug, Deserialize)]
+struct ActivityMessage {
+    activity: Option<ActivityType>,
+}
+
+fn connect(user_id: i32) {
+    let mut registry = REGISTRY.lock().unwrap();
+    let presence = registry.entry(user_id).or_insert(Presence {
+        connections: 0,
+        activity: None,
+        since: Utc::now(),
+    });
+    presence.connections += 1;
+}
+
+fn disconnect(user_id: i32) {
+    let mut registry = REGISTRY.lock().unwrap();
+    if let Some(presence) = registry.get_mut(&user_id) {
+        presence.connections -= 1;
+        if presence.connections == 0 {
+            registry.remove(&user_id);
+        }
+    }
+}
+
+fn set_activity(user_id: i32, activity: Option<ActivityType>) {
+    if let Some(presence) = REGISTRY.lock().unwrap().get_mut(&user_id) {
+        if presence.activity != activity {
+            presence.activity = activity;
+            presence.since = Utc::now();
+        }
+    }
+}
+
+/// Обслуживает WebSocket-соединение пользователя, пока клиент его не закроет.
+pub async fn handle_socket(mut socket: WebSocket, user_id: i32) {
+    connect(user_id);
+
+    while let Some(Ok(message)) = socket.recv().await {
+        match message {
+            Message::Text(text) => match serde_json::from_str::<ActivityMessage>(&text) {
+                Ok(message) => set_activity(user_id, message.activity),
+                Err(e) => tracing::debug!("Некорректное сообщение присутствия от {}: {}", user_id, e),
+            },
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    disconnect(user_id);
+}
+
+/// Возвращает присутствие указанных пользователей (`nickname` и видимость берутся из БД).
+pub fn snapshot(users: Vec<(i32, String, bool)>) -> Vec<PresenceInfo> {
+    let registry = REGISTRY.lock().unwrap();
+    users
+        .into_iter()
+        .map(|(user_id, nickname, visible)| {
+            // Скрывшие присутствие пользователи всегда показываются не в сети
+            let presence = registry.get(&user_id).filter(|_| visible);
+            PresenceInfo {
+                user_id,
+                nickname,
+                online: presence.is_some(),
+                activity: presence.and_then(|p| p.activity),
+                since: presence.map(|p| p.since),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,318 @@
+// profiles.rs
+//
+// Saved server + account profiles, so one client can be used with several
+// instances (a school server, a personal self-hosted one) without typing the
+// password again. Profiles live in `PROFILES_FILE` (default `profiles.json`).
+// Only the refresh token is kept, never the password: switching to a profile
+// exchanges it for a fresh access token, and the rotated refresh token is
+// written back right away. A profile whose session expired stays in the list
+// and just asks for the password again. Sessions are bound on the server to a
+// random device id kept in the same file, so a copied refresh token doesn't
+// work from another machine.
+
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::models::{AuthResponse, LoginPayload, RefreshPayload};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub server_url: String,
+    pub nickname: String,
+    /// Missing once the session expired or was revoked on the server.
+    #[serde(default)]
+    refresh_token: Option<String>,
+    /// Unix seconds of the last sign-in or switch.
+    last_used: u64,
+}
+
+impl Profile {
+    /// Stable key of the profile: the same nickname may exist on several servers.
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.nickname, self.server_url)
+    }
+
+    /// Whether switching to this profile works without the password.
+    pub fn has_session(&self) -> bool {
+        self.refresh_token.is_some()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfileStore {
+    profiles: Vec<Profile>,
+    /// Generated on the first sign-in and sent with every login and refresh.
+    #[serde(default)]
+    device_id: Option<String>,
+}
+
+static STORE: Lazy<Mutex<ProfileStore>> = Lazy::new(|| Mutex::new(load_store()));
+
+/// Server of the profile currently signed in; `None` falls back to `API_BASE_URL`.
+static ACTIVE_SERVER: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// What `/api/status` tells about an instance before signing in.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstanceInfo {
+    pub version: String,
+    pub registration: String,
+    pub public_content: bool,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The address doesn't look like a server URL.
+    InvalidServer,
+    /// The server couldn't be reached; the caller may fall back to offline mode.
+    Unreachable(reqwest::Error),
+    /// The server refused the request, with its own message.
+    Rejected(String),
+    /// The saved session is gone; the password has to be entered again.
+    Expired,
+    UnknownProfile,
+}
+
+impl ProfileError {
+    /// Message for the sign-in screen.
+    pub fn message(&self) -> String {
+        match self {
+            ProfileError::InvalidServer => "Некорректный адрес сервера".to_string(),
+            ProfileError::Unreachable(_) => "Сервер недоступен".to_string(),
+            ProfileError::Rejected(message) => message.clone(),
+            ProfileError::Expired => "Сессия истекла, введите пароль еще раз".to_string(),
+            ProfileError::UnknownProfile => "Профиль не найден".to_string(),
+        }
+    }
+}
+
+fn profiles_file() -> PathBuf {
+    PathBuf::from(env::var("PROFILES_FILE").unwrap_or_else(|_| "profiles.json".to_string()))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn load_store() -> ProfileStore {
+    std::fs::read(profiles_file())
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn save_store(store: &ProfileStore) {
+    let path = profiles_file();
+    let result = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_vec_pretty(store).unwrap_or_default()));
+
+    // The file holds refresh tokens: keep it private to the current user
+    #[cfg(unix)]
+    let result = result.and_then(|_| {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+    });
+
+    if let Err(e) = result {
+        println!("Failed to save profiles: {:?}", e);
+    }
+}
+
+/// Shown in the server's list of active sessions.
+fn user_agent() -> String {
+    format!("Mandarin Heroes {} ({})", env!("CARGO_PKG_VERSION"), env::consts::OS)
+}
+
+/// Id of this installation, created on first use.
+fn device_id() -> String {
+    let mut store = STORE.lock().unwrap();
+    if let Some(id) = &store.device_id {
+        return id.clone();
+    }
+    let mut bytes = [0u8; 16];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut bytes);
+    let id = hex::encode(bytes);
+    store.device_id = Some(id.clone());
+    save_store(&store);
+    id
+}
+
+/// Normalizes a server address typed by the user: trims it, drops trailing
+/// slashes and adds a scheme (plain http only for local addresses).
+pub fn normalize_server_url(input: &str) -> Result<String, ProfileError> {
+    let input = input.trim().trim_end_matches('/');
+    if input.is_empty() || input.contains(char::is_whitespace) {
+        return Err(ProfileError::InvalidServer);
+    }
+    if input.starts_with("http://") || input.starts_with("https://") {
+        return Ok(input.to_string());
+    }
+    let local = ["localhost", "127.", "[::1]"].iter().any(|prefix| input.starts_with(prefix));
+    Ok(format!("{}://{}", if local { "http" } else { "https" }, input))
+}
+
+/// Server the API calls go to: the active profile's, or `API_BASE_URL`.
+pub fn active_server() -> Option<String> {
+    ACTIVE_SERVER.lock().unwrap().clone()
+}
+
+/// Saved profiles, most recently used first.
+pub fn list() -> Vec<Profile> {
+    let mut profiles = STORE.lock().unwrap().profiles.clone();
+    profiles.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    profiles
+}
+
+/// Remembers the tokens for a profile and makes it the active one.
+fn activate(server_url: &str, nickname: &str, tokens: AuthResponse) {
+    let mut store = STORE.lock().unwrap();
+    match store.profiles.iter_mut().find(|p| p.server_url == server_url && p.nickname == nickname) {
+        Some(profile) => {
+            profile.refresh_token = Some(tokens.refresh_token);
+            profile.last_used = now();
+        }
+        None => store.profiles.push(Profile {
+            server_url: server_url.to_string(),
+            nickname: nickname.to_string(),
+            refresh_token: Some(tokens.refresh_token),
+            last_used: now(),
+        }),
+    }
+    save_store(&store);
+
+    *ACTIVE_SERVER.lock().unwrap() = Some(server_url.to_string());
+    *crate::AUTH_TOKEN.lock().unwrap() = Some(tokens.access_token);
+}
+
+/// Turns an error response into `Rejected` with the server's own message.
+async fn rejection(response: reqwest::Response) -> ProfileError {
+    let status = response.status();
+    let message = response
+        .json::<Value>()
+        .await
+        .ok()
+        .and_then(|body| body["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("Сервер ответил ошибкой {}", status));
+    ProfileError::Rejected(message)
+}
+
+/// Asks an instance about itself, so the user can check an address before signing in.
+pub async fn discover(server_url: &str) -> Result<InstanceInfo, ProfileError> {
+    let server_url = normalize_server_url(server_url)?;
+    let response = Client::new()
+        .get(format!("{}/api/status", server_url))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(ProfileError::Unreachable)?;
+    if !response.status().is_success() {
+        return Err(rejection(response).await);
+    }
+    // Anything else than a Mandarin instance won't parse
+    response.json::<InstanceInfo>().await.map_err(|_| ProfileError::InvalidServer)
+}
+
+/// Signs in with a password and saves the account as a profile.
+pub async fn sign_in(server_url: &str, nickname: &str, password: &str) -> Result<Profile, ProfileError> {
+    let server_url = normalize_server_url(server_url)?;
+    let response = Client::new()
+        .post(format!("{}/api/login", server_url))
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::USER_AGENT, user_agent())
+        .json(&LoginPayload {
+            nickname: nickname.to_string(),
+            password: password.to_string(),
+            device_id: Some(device_id()),
+        })
+        .send()
+        .await
+        .map_err(ProfileError::Unreachable)?;
+    if !response.status().is_success() {
+        return Err(rejection(response).await);
+    }
+    let tokens = response.json::<AuthResponse>().await.map_err(ProfileError::Unreachable)?;
+
+    activate(&server_url, nickname, tokens);
+    list()
+        .into_iter()
+        .find(|p| p.server_url == server_url && p.nickname == nickname)
+        .ok_or(ProfileError::UnknownProfile)
+}
+
+/// Switches to a saved profile using its refresh token.
+pub async fn switch_to(id: &str) -> Result<Profile, ProfileError> {
+    let profile = list().into_iter().find(|p| p.id() == id).ok_or(ProfileError::UnknownProfile)?;
+    let refresh_token = profile.refresh_token.clone().ok_or(ProfileError::Expired)?;
+
+    let response = Client::new()
+        .post(format!("{}/api/refresh", profile.server_url))
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::USER_AGENT, user_agent())
+        .json(&RefreshPayload { refresh_token, device_id: Some(device_id()) })
+        .send()
+        .await
+        .map_err(ProfileError::Unreachable)?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        forget_session(id);
+        return Err(ProfileError::Expired);
+    }
+    if !response.status().is_success() {
+        return Err(rejection(response).await);
+    }
+    let tokens = response.json::<AuthResponse>().await.map_err(ProfileError::Unreachable)?;
+
+    activate(&profile.server_url, &profile.nickname, tokens);
+    Ok(profile)
+}
+
+/// Drops the refresh token of a profile but keeps the profile itself.
+fn forget_session(id: &str) {
+    let mut store = STORE.lock().unwrap();
+    if let Some(profile) = store.profiles.iter_mut().find(|p| p.id() == id) {
+        profile.refresh_token = None;
+        save_store(&store);
+    }
+}
+
+/// Leaves the active profile; it stays saved for switching back later.
+pub fn sign_out() {
+    *ACTIVE_SERVER.lock().unwrap() = None;
+    *crate::AUTH_TOKEN.lock().unwrap() = None;
+}
+
+/// Deletes a profile and ends its session on the server. The session is
+/// revoked on a best-effort basis: an unreachable server doesn't keep the profile around.
+pub async fn remove(id: &str) {
+    let removed = {
+        let mut store = STORE.lock().unwrap();
+        let Some(position) = store.profiles.iter().position(|p| p.id() == id) else {
+            return;
+        };
+        let removed = store.profiles.remove(position);
+        save_store(&store);
+        removed
+    };
+
+    if let Some(refresh_token) = removed.refresh_token {
+        let result = Client::new()
+            .post(format!("{}/api/logout", removed.server_url))
+            .timeout(REQUEST_TIMEOUT)
+            .json(&RefreshPayload { refresh_token, device_id: Some(device_id()) })
+            .send()
+            .await;
+        if let Err(e) = result {
+            println!("Failed to end the session of {}: {:?}", id, e);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::ContentType;
+
+/// Отмечает ошибку пользователя в элементе контента: такие элементы
+/// считаются слабыми местами и предлагаются к повторению чаще.
+pub async fn record_miss(user_id: i32, content_type: ContentType, content_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO weak_items (user_id, content_type, content_id)
+         VALUES ($1, $2, $3)
+         ON CONFLICT (user_id, content_type, content_id) DO UPDATE
+         SET misses = weak_items.misses + 1, last_missed_at = NOW()",
+    )
+        .bind(user_id)
+        .bind(content_type)
+        .bind(content_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::config;
+
+/// Middleware для публичных роутов контента: разрешает кешировать успешные
+/// ответы браузерам и CDN на `PUBLIC_CACHE_MAX_AGE` секунд.
+pub async fn cache_headers(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let immutable = response
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("immutable"));
+    // Неизменяемые файлы кешируются дольше, их заголовок не трогаем
+    if response.status().is_success() && !immutable {
+        let value = format!(
+            "public, max-age={}, stale-while-revalidate={}",
+            config::public_cache_max_age(),
+            config::public_cache_max_age() * 10
+        );
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(header::CACHE_CONTROL, value);
+        }
+    }
+    response
+}
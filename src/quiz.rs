@@ -0,0 +1,185 @@
+use axum::http::StatusCode;
+use rand::seq::SliceRandom;
+use sqlx::PgPool;
+
+use crate::distractors;
+use crate::errors::AppError;
+use crate::hidden;
+use crate::models::{ContentType, GenerateQuizPayload, QuestionSkill, QuizMixEntry};
+
+/// Размер теста, если клиент его не указал.
+pub const DEFAULT_SIZE: u32 = 10;
+/// Максимум вопросов в персональном тесте.
+pub const MAX_SIZE: u32 = 50;
+/// Лишних вариантов ответа к каждому вопросу.
+const DISTRACTOR_COUNT: usize = 3;
+/// Элементы, повторение которых наступит в ближайшие дни, считаются «вот-вот забудет».
+const FORGET_HORIZON_DAYS: i32 = 2;
+
+/// Таблица и столбец с лицевой стороной для типов, по которым строятся вопросы.
+fn source_for(content_type: &ContentType) -> Option<(&'static str, &'static str, QuestionSkill)> {
+    match content_type {
+        ContentType::Hieroglyph => Some(("hieroglyphs", "character", QuestionSkill::Vocabulary)),
+        ContentType::Word => Some(("words", "simplified", QuestionSkill::Vocabulary)),
+        ContentType::Phrase => Some(("phrases", "text", QuestionSkill::Vocabulary)),
+        ContentType::Sentence => Some(("sentences", "text", QuestionSkill::Reading)),
+        ContentType::GrammarRule | ContentType::Lesson => None,
+    }
+}
+
+fn default_mix() -> Vec<QuizMixEntry> {
+    [ContentType::Hieroglyph, ContentType::Word, ContentType::Phrase]
+        .into_iter()
+        .map(|content_type| QuizMixEntry { content_type, weight: 1 })
+        .collect()
+}
+
+/// Проверяет размер и состав теста и подставляет значения по умолчанию.
+pub fn validate(payload: &GenerateQuizPayload) -> Result<(u32, Vec<QuizMixEntry>), AppError> {
+    let size = payload.size.unwrap_or(DEFAULT_SIZE);
+    if size == 0 || size > MAX_SIZE {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Размер теста должен быть от 1 до {}", MAX_SIZE),
+        ));
+    }
+
+    let mix = payload.mix.clone().unwrap_or_else(default_mix);
+    for (i, entry) in mix.iter().enumerate() {
+        if source_for(&entry.content_type).is_none() {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "По этому типу контента тест не строится"));
+        }
+        if mix[..i].iter().any(|other| other.content_type == entry.content_type) {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Тип контента указан в составе дважды"));
+        }
+    }
+    if mix.iter().all(|entry| entry.weight == 0) {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "В составе теста нет ни одного типа контента"));
+    }
+    Ok((size, mix))
+}
+
+/// Делит `size` вопросов пропорционально весам методом наибольшего остатка:
+/// сумма долей всегда равна `size`.
+pub fn split(size: u32, mix: &[QuizMixEntry]) -> Vec<u32> {
+    let total: u64 = mix.iter().map(|entry| entry.weight as u64).sum();
+    if total == 0 {
+        return vec![0; mix.len()];
+    }
+
+    let exact: Vec<u64> = mix.iter().map(|entry| size as u64 * entry.weight as u64).collect();
+    let mut counts: Vec<u32> = exact.iter().map(|value| (value / total) as u32).collect();
+    let mut order: Vec<usize> = (0..mix.len()).collect();
+    // При равных остатках выигрывает тип, указанный раньше
+    order.sort_by_key(|&i| std::cmp::Reverse(exact[i] % total));
+    let missing = size - counts.iter().sum::<u32>();
+    for &i in order.iter().take(missing as usize) {
+        counts[i] += 1;
+    }
+    counts
+}
+
+#[derive(sqlx::FromRow)]
+struct QuizSource {
+    id: i32,
+    front: String,
+    translation: String,
+}
+
+struct QuizQuestion {
+    question: String,
+    options: Option<Vec<String>>,
+    correct_answer: String,
+    skill: QuestionSkill,
+}
+
+/// Собирает персональный тест из выученного пользователем и возвращает его id.
+///
+/// Первыми берутся элементы, повторение которых уже наступило или наступит
+/// в ближайшие `FORGET_HORIZON_DAYS` дня, затем — с наименьшим коэффициентом
+/// легкости; скрытые пользователем элементы не берутся. Тест принадлежит
+/// пользователю и не виден в общем списке.
+/// Если выученного одного типа меньше его доли, тест получается короче.
+pub async fn generate(user_id: i32, payload: &GenerateQuizPayload, pool: &PgPool) -> Result<i32, AppError> {
+    let (size, mix) = validate(payload)?;
+    let counts = split(size, &mix);
+
+    let mut questions: Vec<QuizQuestion> = Vec::new();
+    for (entry, count) in mix.iter().zip(counts) {
+        if count == 0 {
+            continue;
+        }
+        let Some((table, front, skill)) = source_for(&entry.content_type) else {
+            continue;
+        };
+
+        let sources = sqlx::query_as::<_, QuizSource>(&format!(
+            "SELECT c.id, c.{front} AS front, c.translation
+             FROM user_progress up
+             JOIN {table} c ON c.id = up.content_id
+             WHERE up.user_id = $1 AND up.content_type = $2 AND up.is_learned
+               AND {not_hidden}
+             ORDER BY up.next_review_at <= NOW() + make_interval(days => $3) DESC,
+                      up.ease_factor, up.next_review_at, random()
+             LIMIT $4",
+            not_hidden = hidden::not_hidden("up.user_id", "up.content_type", "up.content_id")
+        ))
+            .bind(user_id)
+            .bind(&entry.content_type)
+            .bind(FORGET_HORIZON_DAYS)
+            .bind(count as i64)
+            .fetch_all(pool)
+            .await?;
+
+        for source in sources {
+            let distractors = distractors::translations(&entry.content_type, source.id, DISTRACTOR_COUNT, pool).await?;
+
+            // Без лишних вариантов вопрос остается с ответом в свободной форме
+            let options = (!distractors.is_empty()).then(|| {
+                let mut options = distractors;
+                options.push(source.translation.clone());
+                options.shuffle(&mut rand::thread_rng());
+                options
+            });
+            questions.push(QuizQuestion {
+                question: format!("Что означает «{}»?", source.front),
+                options,
+                correct_answer: source.translation,
+                skill,
+            });
+        }
+    }
+
+    if questions.is_empty() {
+        return Err(AppError::new(StatusCode::CONFLICT, "Нет выученных элементов для теста")
+            .with_code("nothing_learned"));
+    }
+    questions.shuffle(&mut rand::thread_rng());
+
+    let mut tx = pool.begin().await?;
+    let test_id: i32 = sqlx::query_scalar(
+        "INSERT INTO tests (name, description, generated_for) VALUES ($1, $2, $3) RETURNING id",
+    )
+        .bind("Персональный тест")
+        .bind(format!("Повторение выученного: вопросов — {}", questions.len()))
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    for question in &questions {
+        sqlx::query(
+            "INSERT INTO test_items (test_id, question, options, correct_answer, skill)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+            .bind(test_id)
+            .bind(&question.question)
+            .bind(question.options.as_ref().map(|options| serde_json::json!(options)))
+            .bind(&question.correct_answer)
+            .bind(question.skill)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(test_id)
+}
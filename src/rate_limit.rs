@@ -0,0 +1,133 @@
+use axum::async_trait;
+use axum::body::{to_bytes, Body};
+use axum::extract::{ConnectInfo, Request};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{self, AuthRateLimits, RateLimit};
+use crate::errors::AppError;
+use crate::registration;
+
+/// Тела запросов входа крошечные; больше для поиска никнейма не читаем.
+const MAX_AUTH_BODY: usize = 64 * 1024;
+/// Сколько ключей хранить, прежде чем вычищать устаревшие.
+const MAX_MEMORY_KEYS: usize = 10_000;
+
+/// Хранилище счетчиков лимита. Сейчас оно в памяти процесса; общее хранилище
+/// (например, Redis) понадобится, когда серверов станет несколько.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Засчитывает запрос сразу по всем ключам. Если хоть один лимит исчерпан,
+    /// запрос не засчитывается ни по одному ключу, а возвращается время до
+    /// освобождения места во всех окнах.
+    async fn hit_all(&self, keys: &[(String, RateLimit)]) -> Option<Duration>;
+}
+
+/// Скользящее окно в памяти: время каждого засчитанного запроса по ключу.
+#[derive(Default)]
+pub struct MemoryStore {
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl MemoryStore {
+    pub fn hit_all_at(&self, keys: &[(String, RateLimit)], now: Instant) -> Option<Duration> {
+        // Проверка и запись идут под одной блокировкой: между ними никто не вклинится
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() + keys.len() > MAX_MEMORY_KEYS {
+            // Окна у лимитов разные, но ключ без запросов за час точно не нужен
+            hits.retain(|_, times| times.back().is_some_and(|last| now.duration_since(*last) < Duration::from_secs(3600)));
+        }
+
+        let mut retry_after: Option<Duration> = None;
+        for (key, limit) in keys {
+            let Some(times) = hits.get_mut(key) else {
+                continue;
+            };
+            while times.front().is_some_and(|first| now.duration_since(*first) >= limit.window) {
+                times.pop_front();
+            }
+            if times.len() >= limit.max as usize {
+                if let Some(oldest) = times.front() {
+                    let wait = limit.window - now.duration_since(*oldest);
+                    retry_after = retry_after.max(Some(wait));
+                }
+            }
+        }
+        if retry_after.is_some() {
+            return retry_after;
+        }
+
+        for (key, _) in keys {
+            hits.entry(key.clone()).or_default().push_back(now);
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn hit_all(&self, keys: &[(String, RateLimit)]) -> Option<Duration> {
+        self.hit_all_at(keys, Instant::now())
+    }
+}
+
+static STORE: Lazy<Box<dyn RateLimitStore>> = Lazy::new(|| Box::new(MemoryStore::default()));
+static LIMITS: Lazy<AuthRateLimits> = Lazy::new(config::auth_rate_limits);
+
+/// Ответ 429 с `Retry-After` в целых секундах, округленных вверх.
+pub fn too_many_requests(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let mut response = AppError::new(StatusCode::TOO_MANY_REQUESTS, "Слишком много попыток, попробуйте позже")
+        .with_code("rate_limited")
+        .into_response();
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds.max(1)));
+    response
+}
+
+/// Middleware для входа, регистрации и обновления токена: ограничивает запросы
+/// с одного IP и на один никнейм из тела запроса, чтобы пароль нельзя было подбирать
+/// ни с одного адреса, ни распределенно. Лимиты у каждого адреса свои.
+pub async fn limit_auth(request: Request, next: Next) -> Response {
+    let limits = *LIMITS;
+    let path = request.uri().path().to_string();
+    let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| *addr);
+    let ip = registration::client_ip(request.headers(), peer);
+
+    let mut keys: Vec<(String, RateLimit)> = Vec::new();
+    if let (Some(ip), Some(limit)) = (ip, limits.ip) {
+        keys.push((format!("{}:ip:{}", path, ip), limit));
+    }
+
+    let request = match limits.nickname {
+        Some(limit) => {
+            let (parts, body) = request.into_parts();
+            let bytes = match to_bytes(body, MAX_AUTH_BODY).await {
+                Ok(bytes) => bytes,
+                Err(_) => return AppError::new(StatusCode::PAYLOAD_TOO_LARGE, "Слишком большой запрос").into_response(),
+            };
+            let nickname = serde_json::from_slice::<serde_json::Value>(&bytes)
+                .ok()
+                .and_then(|body| body.get("nickname")?.as_str().map(|nickname| nickname.trim().to_lowercase()));
+            if let Some(nickname) = nickname.filter(|nickname| !nickname.is_empty()) {
+                keys.push((format!("{}:nickname:{}", path, nickname), limit));
+            }
+            Request::from_parts(parts, Body::from(bytes))
+        }
+        None => request,
+    };
+
+    // Отклоненный по одному ключу запрос не расходует лимит остальных: иначе
+    // перебор чужого никнейма съедал бы лимит адреса и наоборот
+    if let Some(retry_after) = STORE.hit_all(&keys).await {
+        let keys: Vec<&str> = keys.iter().map(|(key, _)| key.as_str()).collect();
+        tracing::warn!("Лимит запросов превышен: {}", keys.join(", "));
+        return too_many_requests(retry_after);
+    }
+    next.run(request).await
+}
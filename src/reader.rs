@@ -0,0 +1,133 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::{PassagePayload, PassageSentencePayload, PassageSummary, PassageTiming, TimedSentence};
+
+const SUMMARY_COLUMNS: &str = "p.id, p.title,
+    (SELECT COUNT(*) FROM passage_sentences ps WHERE ps.passage_id = p.id) AS sentence_count,
+    p.audio_media_id IS NOT NULL AS has_audio";
+
+/// Проверяет разметку: отрезки идут по порядку и не пересекаются.
+pub fn validate(sentences: &[PassageSentencePayload]) -> Result<(), AppError> {
+    if sentences.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "В тексте нет предложений"));
+    }
+    let mut previous_end = 0;
+    for (i, sentence) in sentences.iter().enumerate() {
+        if sentence.start_ms < previous_end || sentence.end_ms <= sentence.start_ms {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Отрезок предложения №{} пересекается с предыдущим или пуст", i + 1),
+            )
+                .with_code("invalid_timing"));
+        }
+        previous_end = sentence.end_ms;
+    }
+    Ok(())
+}
+
+/// Предложение, которое нужно подсветить в момент `position_ms` записи. В паузе
+/// между предложениями подсвечено прошедшее, до начала первого — ничего.
+pub fn sentence_at(sentences: &[TimedSentence], position_ms: i64) -> Option<usize> {
+    sentences.partition_point(|sentence| sentence.start_ms as i64 <= position_ms).checked_sub(1)
+}
+
+/// Создает текст для чтения; запись загружается отдельно.
+pub async fn create(payload: &PassagePayload, pool: &PgPool) -> Result<PassageSummary, AppError> {
+    let title = payload.title.trim();
+    if title.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название текста не может быть пустым"));
+    }
+    validate(&payload.sentences)?;
+
+    let ids: Vec<i32> = payload.sentences.iter().map(|sentence| sentence.sentence_id).collect();
+    let found: i64 = sqlx::query_scalar("SELECT COUNT(DISTINCT id) FROM sentences WHERE id = ANY($1)")
+        .bind(&ids)
+        .fetch_one(pool)
+        .await?;
+    let mut unique = ids.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    if found != unique.len() as i64 {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Некоторые предложения не найдены"));
+    }
+
+    let mut tx = pool.begin().await?;
+    let id: i32 = sqlx::query_scalar("INSERT INTO passages (title) VALUES ($1) RETURNING id")
+        .bind(title)
+        .fetch_one(&mut *tx)
+        .await?;
+    for (position, sentence) in payload.sentences.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO passage_sentences (passage_id, position, sentence_id, start_ms, end_ms)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+            .bind(id)
+            .bind(position as i32)
+            .bind(sentence.sentence_id)
+            .bind(sentence.start_ms)
+            .bind(sentence.end_ms)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    summary(id, pool).await
+}
+
+async fn summary(id: i32, pool: &PgPool) -> Result<PassageSummary, AppError> {
+    sqlx::query_as::<_, PassageSummary>(&format!("SELECT {} FROM passages p WHERE p.id = $1", SUMMARY_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Текст не найден"))
+}
+
+/// Привязывает к тексту запись диктора.
+pub async fn set_audio(id: i32, media_id: i32, pool: &PgPool) -> Result<PassageSummary, AppError> {
+    let result = sqlx::query("UPDATE passages SET audio_media_id = $1 WHERE id = $2")
+        .bind(media_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Текст не найден"));
+    }
+    summary(id, pool).await
+}
+
+/// Тексты с записью, которые можно открыть в режиме чтения.
+pub async fn list(pool: &PgPool) -> Result<Vec<PassageSummary>, AppError> {
+    let passages = sqlx::query_as::<_, PassageSummary>(&format!(
+        "SELECT {} FROM passages p WHERE p.audio_media_id IS NOT NULL ORDER BY p.title",
+        SUMMARY_COLUMNS
+    ))
+        .fetch_all(pool)
+        .await?;
+    Ok(passages)
+}
+
+/// Разметка текста для режима чтения. Без записи синхронизировать нечего, поэтому 404.
+pub async fn timing(id: i32, pool: &PgPool) -> Result<PassageTiming, AppError> {
+    let (title, audio_media_id): (String, Option<i32>) =
+        sqlx::query_as("SELECT title, audio_media_id FROM passages WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Текст не найден"))?;
+    let audio_media_id = audio_media_id
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "У текста нет записи").with_code("no_audio"))?;
+
+    let sentences = sqlx::query_as::<_, TimedSentence>(
+        "SELECT s.id AS sentence_id, s.text, s.pinyin, s.translation, ps.start_ms, ps.end_ms
+         FROM passage_sentences ps JOIN sentences s ON s.id = ps.sentence_id
+         WHERE ps.passage_id = $1
+         ORDER BY ps.position",
+    )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(PassageTiming { id, title, audio_url: format!("/api/media/{}", audio_media_id), sentences })
+}
@@ -0,0 +1,166 @@
+// reader_player.rs
+//
+// Audio playback for the immersive reader. rodio's output stream can't leave
+// the thread that opened it, so playback runs on a dedicated thread driven
+// through a channel. The reader highlights sentences by the clock kept here
+// (wall time scaled by the playback speed) rather than by asking the decoder,
+// so highlighting keeps working even when no audio device is available.
+// Without the `audio` feature nothing is played at all and the reader runs on
+// the clock alone.
+
+use once_cell::sync::Lazy;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Slowest and fastest playback the reader offers.
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 1.5;
+
+// Without audio the commands are only drained, so their payloads go unread
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+enum Command {
+    Play(Vec<u8>, f32),
+    Pause,
+    Resume,
+    Speed(f32),
+    Stop,
+}
+
+/// Playback position, advancing only while playing.
+struct Clock {
+    /// Position reached before the current run, in milliseconds of audio.
+    base_ms: f64,
+    /// When the current run started; `None` while paused.
+    running_since: Option<Instant>,
+    speed: f32,
+}
+
+impl Clock {
+    fn position_ms(&self) -> f64 {
+        let running = self.running_since.map_or(0.0, |since| since.elapsed().as_secs_f64() * 1000.0);
+        self.base_ms + running * self.speed as f64
+    }
+
+    /// Folds the current run into `base_ms`, so speed changes don't move the past.
+    fn settle(&mut self) {
+        self.base_ms = self.position_ms();
+        if self.running_since.is_some() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+}
+
+static CLOCK: Lazy<Mutex<Clock>> =
+    Lazy::new(|| Mutex::new(Clock { base_ms: 0.0, running_since: None, speed: 1.0 }));
+
+static PLAYER: Lazy<Mutex<Sender<Command>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<Command>();
+    std::thread::spawn(move || run_output(receiver));
+    Mutex::new(sender)
+});
+
+#[cfg(feature = "audio")]
+fn run_output(receiver: Receiver<Command>) {
+    use std::io::Cursor;
+
+    let output = rodio::OutputStream::try_default();
+    if let Err(e) = &output {
+        println!("No audio output, the reader will play silently: {:?}", e);
+    }
+    let mut sink: Option<rodio::Sink> = None;
+
+    for command in receiver {
+        let Ok((_, handle)) = &output else {
+            continue;
+        };
+        match command {
+            Command::Play(bytes, speed) => {
+                // A fresh sink per passage: a stopped one can't be reused reliably
+                sink = rodio::Sink::try_new(handle).ok();
+                match (&sink, rodio::Decoder::new(Cursor::new(bytes))) {
+                    (Some(sink), Ok(source)) => {
+                        sink.set_speed(speed);
+                        sink.append(source);
+                    }
+                    (_, Err(e)) => println!("Failed to decode passage audio: {:?}", e),
+                    (None, _) => println!("Failed to open an audio sink"),
+                }
+            }
+            Command::Pause => {
+                if let Some(sink) = &sink {
+                    sink.pause();
+                }
+            }
+            Command::Resume => {
+                if let Some(sink) = &sink {
+                    sink.play();
+                }
+            }
+            Command::Speed(speed) => {
+                if let Some(sink) = &sink {
+                    sink.set_speed(speed);
+                }
+            }
+            Command::Stop => {
+                if let Some(sink) = sink.take() {
+                    sink.stop();
+                }
+            }
+        }
+    }
+}
+
+/// Built without audio: commands are drained and only the clock moves.
+#[cfg(not(feature = "audio"))]
+fn run_output(receiver: Receiver<Command>) {
+    for _ in receiver {}
+}
+
+fn send(command: Command) {
+    let _ = PLAYER.lock().unwrap().send(command);
+}
+
+/// Starts the passage audio from the beginning at the current speed.
+pub fn play(bytes: Vec<u8>) {
+    let mut clock = CLOCK.lock().unwrap();
+    clock.base_ms = 0.0;
+    clock.running_since = Some(Instant::now());
+    send(Command::Play(bytes, clock.speed));
+}
+
+pub fn pause() {
+    let mut clock = CLOCK.lock().unwrap();
+    clock.settle();
+    clock.running_since = None;
+    send(Command::Pause);
+}
+
+pub fn resume() {
+    let mut clock = CLOCK.lock().unwrap();
+    if clock.running_since.is_none() {
+        clock.running_since = Some(Instant::now());
+    }
+    send(Command::Resume);
+}
+
+pub fn stop() {
+    let mut clock = CLOCK.lock().unwrap();
+    clock.base_ms = 0.0;
+    clock.running_since = None;
+    send(Command::Stop);
+}
+
+/// Changes the playback speed; rodio resamples, so the pitch shifts along with it.
+pub fn set_speed(speed: f32) {
+    let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    let mut clock = CLOCK.lock().unwrap();
+    clock.settle();
+    clock.speed = speed;
+    send(Command::Speed(speed));
+}
+
+/// Current position in the passage audio, in milliseconds.
+pub fn position_ms() -> i64 {
+    CLOCK.lock().unwrap().position_ms() as i64
+}
@@ -0,0 +1,97 @@
+use axum::http::{HeaderMap, StatusCode};
+use sqlx::{Postgres, Transaction};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::config::{self, EmailDomainRules};
+use crate::errors::AppError;
+
+fn domain_matches(domain: &str, rule: &str) -> bool {
+    domain == rule || domain.strip_suffix(rule).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Проверяет почту по правилам доменов и возвращает ее в нижнем регистре.
+/// Без разрешенных доменов почта необязательна.
+pub fn check_email(email: Option<&str>, rules: &EmailDomainRules) -> Result<Option<String>, AppError> {
+    let Some(email) = email.map(str::trim).filter(|email| !email.is_empty()) else {
+        if rules.allow.is_empty() {
+            return Ok(None);
+        }
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Для регистрации нужна почта").with_code("email_required"));
+    };
+
+    let email = email.to_lowercase();
+    let domain = match email.split_once('@') {
+        Some((local, domain))
+            if !local.is_empty() && domain.contains('.') && !domain.contains('@') && !email.contains(char::is_whitespace) =>
+        {
+            domain
+        }
+        _ => return Err(AppError::new(StatusCode::BAD_REQUEST, "Некорректный адрес почты").with_code("invalid_email")),
+    };
+
+    let denied = rules.deny.iter().any(|rule| domain_matches(domain, rule));
+    let allowed = rules.allow.is_empty() || rules.allow.iter().any(|rule| domain_matches(domain, rule));
+    if denied || !allowed {
+        return Err(AppError::new(StatusCode::FORBIDDEN, "Регистрация с этим почтовым доменом запрещена")
+            .with_code("email_domain_not_allowed"));
+    }
+    Ok(Some(email))
+}
+
+/// Адрес клиента: из `X-Forwarded-For`, если серверу велено ему доверять, иначе — адрес соединения.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+    let hops = config::trusted_proxy_hops();
+    if hops > 0 {
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+        if let Some(ip) = forwarded_client(&forwarded, hops) {
+            return Some(ip);
+        }
+    }
+    peer.map(|peer| peer.ip())
+}
+
+/// Адрес клиента из цепочки `X-Forwarded-For` за `hops` доверенными прокси.
+/// Каждый прокси дописывает справа адрес, от которого получил запрос, поэтому
+/// верить можно только `hops` правым записям: левее стоит то, что прислал сам
+/// клиент. Если записей меньше, чем прокси, цепочка не сходится и адреса нет.
+pub fn forwarded_client(forwarded: &str, hops: usize) -> Option<IpAddr> {
+    let entries: Vec<&str> = forwarded.split(',').map(str::trim).filter(|entry| !entry.is_empty()).collect();
+    let index = entries.len().checked_sub(hops)?;
+    entries[index].parse().ok()
+}
+
+/// Проверяет суточный лимит регистраций с IP и засчитывает новую.
+/// Вызывается в транзакции регистрации: при откате регистрация не считается.
+pub async fn count_ip(ip: Option<IpAddr>, tx: &mut Transaction<'_, Postgres>) -> Result<(), AppError> {
+    let (Some(ip), Some(limit)) = (ip, config::registration_daily_ip_limit()) else {
+        return Ok(());
+    };
+    let ip = ip.to_string();
+
+    // Блокировка по адресу не дает параллельным регистрациям проскочить лимит
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('registration:' || $1))")
+        .bind(&ip)
+        .execute(&mut **tx)
+        .await?;
+    let today: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM registrations_by_ip WHERE ip = $1 AND registered_at > NOW() - INTERVAL '1 day'",
+    )
+        .bind(&ip)
+        .fetch_one(&mut **tx)
+        .await?;
+    if today >= limit {
+        return Err(AppError::new(StatusCode::TOO_MANY_REQUESTS, "Слишком много регистраций с этого адреса, попробуйте завтра")
+            .with_code("registration_limit"));
+    }
+
+    sqlx::query("INSERT INTO registrations_by_ip (ip) VALUES ($1)")
+        .bind(&ip)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use axum::http::StatusCode;
+use chrono::{Duration, NaiveDate};
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::hidden;
+use crate::models::{ContentType, ForecastDay, ReviewForecast, ReviewLoadMode, ReviewLoadSettings};
+
+/// Дней прогноза по умолчанию: новый элемент возвращается на повторение в течение недели.
+pub const DEFAULT_FORECAST_DAYS: i64 = 7;
+/// Максимальный период одного запроса прогноза.
+pub const MAX_FORECAST_DAYS: i64 = 90;
+/// Порог выше этого не имеет смысла и, скорее всего, опечатка.
+pub const MAX_DAILY_LIMIT: i32 = 10_000;
+
+impl ReviewLoadMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReviewLoadMode::Off => "off",
+            ReviewLoadMode::Warn => "warn",
+            ReviewLoadMode::Block => "block",
+        }
+    }
+
+    fn parse(value: &str) -> ReviewLoadMode {
+        match value {
+            "off" => ReviewLoadMode::Off,
+            "block" => ReviewLoadMode::Block,
+            _ => ReviewLoadMode::Warn,
+        }
+    }
+}
+
+/// Проверяет порог повторений в день.
+pub fn validate_limit(limit: i32) -> Result<(), AppError> {
+    if limit <= 0 || limit > MAX_DAILY_LIMIT {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Порог повторений должен быть от 1 до {}", MAX_DAILY_LIMIT),
+        ));
+    }
+    Ok(())
+}
+
+/// Раскладывает число повторений по дням с `today` на `days` дней вперед;
+/// дни без повторений получают ноль, просроченное уже лежит на `today`.
+pub fn fill_days(today: NaiveDate, days: i64, due: &[ForecastDay]) -> Vec<ForecastDay> {
+    (0..days)
+        .map(|offset| {
+            let day = today + Duration::days(offset);
+            let due = due.iter().filter(|entry| entry.day == day).map(|entry| entry.due).sum();
+            ForecastDay { day, due }
+        })
+        .collect()
+}
+
+/// Сравнивает прогноз с порогом. Порог считается достигнутым, когда хотя бы
+/// в один день повторений не меньше него: еще один элемент его превысит.
+pub fn assess(days: Vec<ForecastDay>, daily_limit: i32, mode: ReviewLoadMode) -> ReviewForecast {
+    let peak = days.iter().map(|day| day.due).max().unwrap_or(0);
+    ReviewForecast {
+        overloaded: mode != ReviewLoadMode::Off && peak >= daily_limit as i64,
+        days,
+        daily_limit,
+        mode,
+        peak,
+    }
+}
+
+/// Общие настройки порога.
+pub async fn settings(pool: &PgPool) -> Result<ReviewLoadSettings, AppError> {
+    let (daily_limit, mode): (i32, String) =
+        sqlx::query_as("SELECT daily_limit, mode FROM review_load_settings")
+            .fetch_one(pool)
+            .await?;
+    Ok(ReviewLoadSettings { daily_limit, mode: ReviewLoadMode::parse(&mode) })
+}
+
+/// Меняет общие настройки порога. Пользователи со своим порогом сохраняют его.
+pub async fn update_settings(payload: &ReviewLoadSettings, pool: &PgPool) -> Result<ReviewLoadSettings, AppError> {
+    validate_limit(payload.daily_limit)?;
+    sqlx::query("UPDATE review_load_settings SET daily_limit = $1, mode = $2")
+        .bind(payload.daily_limit)
+        .bind(payload.mode.as_str())
+        .execute(pool)
+        .await?;
+    settings(pool).await
+}
+
+/// Прогноз повторений пользователя на `days` дней, начиная с сегодня. Скрытые элементы не считаются.
+pub async fn forecast(user_id: i32, days: i64, pool: &PgPool) -> Result<ReviewForecast, AppError> {
+    let settings = settings(pool).await?;
+    let own_limit: Option<i32> =
+        sqlx::query_scalar("SELECT daily_review_limit FROM user_settings WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+
+    let today: NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE")
+        .fetch_one(pool)
+        .await?;
+    let due = sqlx::query_as::<_, ForecastDay>(&format!(
+        "SELECT GREATEST(up.next_review_at::date, $3) AS day, COUNT(*) AS due
+         FROM user_progress up
+         WHERE up.user_id = $1 AND up.is_learned AND up.next_review_at < $3 + $2::int
+           AND {}
+         GROUP BY 1",
+        hidden::not_hidden("up.user_id", "up.content_type", "up.content_id")
+    ))
+        .bind(user_id)
+        .bind(days as i32)
+        .bind(today)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(assess(fill_days(today, days, &due), own_limit.unwrap_or(settings.daily_limit), settings.mode))
+}
+
+/// Проверяет, можно ли начать учить новый элемент. Повторная отметка уже
+/// выученного не проверяется. При достигнутом пороге в режиме `block` —
+/// ошибка `review_overload`, в режиме `warn` — прогноз для предупреждения.
+pub async fn check_new_item(
+    user_id: i32,
+    content_type: &ContentType,
+    content_id: i32,
+    pool: &PgPool,
+) -> Result<Option<ReviewForecast>, AppError> {
+    let learned: Option<bool> = sqlx::query_scalar(
+        "SELECT is_learned FROM user_progress WHERE user_id = $1 AND content_type = $2 AND content_id = $3",
+    )
+        .bind(user_id)
+        .bind(content_type)
+        .bind(content_id)
+        .fetch_optional(pool)
+        .await?;
+    if learned == Some(true) {
+        return Ok(None);
+    }
+
+    let forecast = forecast(user_id, DEFAULT_FORECAST_DAYS, pool).await?;
+    if !forecast.overloaded {
+        return Ok(None);
+    }
+    if forecast.mode == ReviewLoadMode::Block {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!(
+                "На ближайшие дни запланировано до {} повторений при пороге {}: сначала повторите выученное",
+                forecast.peak, forecast.daily_limit
+            ),
+        ).with_code("review_overload"));
+    }
+    Ok(Some(forecast))
+}
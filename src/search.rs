@@ -0,0 +1,98 @@
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::matching::normalize_pinyin;
+use crate::models::{SearchGroup, SearchHit, SearchResponse};
+
+/// Максимум результатов одного типа.
+pub const MAX_RESULTS_PER_TYPE: i64 = 50;
+
+/// Таблицы, по которым идет поиск: тип контента, таблица, колонка с иероглифами.
+const SEARCH_TABLES: [(&str, &str, &str); 3] = [
+    ("hieroglyph", "hieroglyphs", "character"),
+    ("word", "words", "simplified"),
+    ("phrase", "phrases", "text"),
+];
+
+/// Запрос к одной таблице. $1 — запрос как есть, $2 — пиньинь без тонов
+/// (NULL, если в запросе нет латиницы), $3 — лимит.
+///
+/// Ранг — лучшая из оценок: совпадение иероглифов, пиньинь и перевода.
+/// Точное совпадение ставится выше частичного, частичные упорядочиваются
+/// по триграммному сходству и рангу полнотекстового поиска.
+fn table_query(content_type: &str, table: &str, text_column: &str) -> String {
+    format!(
+        "(SELECT '{content_type}'::content_type_enum AS content_type, id AS content_id,
+                {text_column} AS text, pinyin, translation,
+                GREATEST(
+                    CASE WHEN {text_column} = $1 THEN 1.0
+                         WHEN strpos({text_column}, $1) > 0 THEN 0.5 + 0.4 * similarity({text_column}, $1)
+                         ELSE 0 END,
+                    CASE WHEN $2::text IS NULL THEN 0
+                         WHEN pinyin_plain = $2 THEN 0.95
+                         WHEN pinyin_plain LIKE $2 || '%' THEN 0.6 + 0.3 * similarity(pinyin_plain, $2)
+                         ELSE 0.8 * similarity(pinyin_plain, $2) END,
+                    CASE WHEN lower(translation) = lower($1) THEN 0.9
+                         ELSE 0.5 * ts_rank(to_tsvector('simple', translation), plainto_tsquery('simple', $1))
+                              + 0.4 * word_similarity($1, translation) END
+                )::real AS rank
+         FROM {table}
+         WHERE strpos({text_column}, $1) > 0
+            OR ($2::text IS NOT NULL AND (pinyin_plain LIKE $2 || '%' OR pinyin_plain % $2))
+            OR to_tsvector('simple', translation) @@ plainto_tsquery('simple', $1)
+            OR $1 <% translation
+         ORDER BY rank DESC, id
+         LIMIT $3)"
+    )
+}
+
+/// Шаблон `LIKE` для поиска подстроки: в отличие от `strpos`, его ускоряют триграммные индексы.
+pub fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Ищет иероглифы, слова и фразы по иероглифам, пиньинь (с тонами,
+/// цифрами тонов или без них) и переводу. Результаты сгруппированы по типу;
+/// группы идут в порядке лучшего результата в них.
+pub async fn search(query: &str, limit: i64, pool: &PgPool) -> Result<SearchResponse, AppError> {
+    let pinyin = normalize_pinyin(query);
+    let pinyin = (!pinyin.is_empty()).then_some(pinyin);
+
+    let sql = SEARCH_TABLES
+        .iter()
+        .map(|(content_type, table, text_column)| table_query(content_type, table, text_column))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+
+    let hits = sqlx::query_as::<_, SearchHit>(&sql)
+        .bind(query)
+        .bind(pinyin)
+        .bind(limit.clamp(1, MAX_RESULTS_PER_TYPE))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(SearchResponse { query: query.to_string(), groups: group_hits(hits) })
+}
+
+/// Раскладывает результаты по типам контента, сохраняя порядок по рангу.
+pub fn group_hits(mut hits: Vec<SearchHit>) -> Vec<SearchGroup> {
+    hits.sort_by(|a, b| b.rank.total_cmp(&a.rank));
+
+    let mut groups: Vec<SearchGroup> = Vec::new();
+    for hit in hits {
+        match groups.iter_mut().find(|g| g.content_type == hit.content_type) {
+            Some(group) => group.items.push(hit),
+            None => groups.push(SearchGroup { content_type: hit.content_type.clone(), items: vec![hit] }),
+        }
+    }
+    groups
+}
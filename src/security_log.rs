@@ -0,0 +1,67 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::errors::AppError;
+use crate::models::{CursorPage, CursorPagination, SecurityEvent};
+
+/// Вид события журнала безопасности.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    /// Refresh-токен предъявлен с другого устройства.
+    DeviceMismatch,
+    /// Предъявлен уже замененный refresh-токен; семейство сессий отозвано.
+    RefreshReuse,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::DeviceMismatch => "device_mismatch",
+            EventKind::RefreshReuse => "refresh_reuse",
+        }
+    }
+}
+
+/// Записывает событие в журнал и дублирует его в лог сервера.
+pub async fn record(
+    user_id: i32,
+    kind: EventKind,
+    ip: Option<IpAddr>,
+    details: &str,
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    tracing::warn!("Событие безопасности {} у пользователя {}: {}", kind.as_str(), user_id, details);
+    sqlx::query("INSERT INTO security_events (user_id, kind, ip, details) VALUES ($1, $2, $3, $4)")
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(ip.map(|ip| ip.to_string()))
+        .bind(details)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Журнал безопасности пользователя, новые первыми, по курсору.
+pub async fn events(user_id: i32, pagination: CursorPagination, pool: &PgPool) -> Result<CursorPage<SecurityEvent>, AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Пользователь не найден"));
+    }
+
+    let events = sqlx::query_as::<_, SecurityEvent>(
+        "SELECT id, kind, ip, details, created_at FROM security_events
+         WHERE user_id = $1 AND ($2::int IS NULL OR id < $2)
+         ORDER BY id DESC
+         LIMIT $3",
+    )
+        .bind(user_id)
+        .bind(pagination.cursor)
+        .bind(pagination.fetch_limit())
+        .fetch_all(pool)
+        .await?;
+    Ok(CursorPage::new(events, pagination, |event| event.id))
+}
@@ -0,0 +1,43 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::auth;
+use crate::errors::AppError;
+use crate::models::SessionInfo;
+
+/// Активные сессии пользователя, недавно использованные первыми. Сессия — один
+/// вход на устройстве; замененные при ротации токены в список не попадают.
+/// `current_id` — сессия текущего запроса, она отмечается в списке.
+pub async fn list(user_id: i32, current_id: Option<&str>, pool: &PgPool) -> Result<Vec<SessionInfo>, AppError> {
+    let sessions = sqlx::query_as::<_, SessionInfo>(
+        "SELECT family_id AS id, user_agent, ip, created_at, last_used_at, expires_at, device_mismatch_at,
+                COALESCE(family_id = $2, FALSE) AS current
+         FROM refresh_sessions
+         WHERE user_id = $1 AND rotated_at IS NULL AND expires_at > NOW()
+         ORDER BY last_used_at DESC",
+    )
+        .bind(user_id)
+        .bind(current_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(sessions)
+}
+
+/// Завершает сессию пользователя на одном устройстве. Уже выданный access token
+/// действует до истечения, но обновить его больше нельзя.
+pub async fn revoke(user_id: i32, id: &str, pool: &PgPool) -> Result<(), AppError> {
+    let result = sqlx::query("DELETE FROM refresh_sessions WHERE user_id = $1 AND family_id = $2")
+        .bind(user_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Сессия не найдена"));
+    }
+    Ok(())
+}
+
+/// Завершает все сессии пользователя, включая текущую.
+pub async fn revoke_all(user_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    auth::revoke_all_sessions(user_id, pool).await
+}
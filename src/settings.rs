@@ -0,0 +1,115 @@
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::errors::AppError;
+use crate::models::{InterfaceLanguage, Script, UpdateSettingsPayload, UserSettings};
+use crate::notifications::{self, NotificationKind};
+use crate::review_load;
+
+/// Как часто проверять, кому пора напомнить о занятиях.
+const STUDY_REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+impl InterfaceLanguage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InterfaceLanguage::Ru => "ru",
+            InterfaceLanguage::En => "en",
+        }
+    }
+}
+
+impl Script {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Script::Simplified => "simplified",
+            Script::Traditional => "traditional",
+        }
+    }
+}
+
+/// Настройки пользователя; если он их еще не менял — значения по умолчанию.
+pub async fn load(user_id: i32, pool: &PgPool) -> Result<UserSettings, AppError> {
+    let settings = sqlx::query_as::<_, UserSettings>(
+        "INSERT INTO user_settings (user_id) VALUES ($1)
+         ON CONFLICT (user_id) DO UPDATE SET user_id = EXCLUDED.user_id
+         RETURNING interface_language, script, reminder_time, onboarding_finished_at, onboarding_skipped,
+                   daily_review_limit",
+    )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(settings)
+}
+
+/// Обновляет переданные поля настроек, остальные не трогает.
+pub async fn update(user_id: i32, payload: &UpdateSettingsPayload, pool: &PgPool) -> Result<UserSettings, AppError> {
+    if let Some(limit) = payload.daily_review_limit {
+        review_load::validate_limit(limit)?;
+    }
+
+    sqlx::query("INSERT INTO user_settings (user_id) VALUES ($1) ON CONFLICT (user_id) DO NOTHING")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    let settings = sqlx::query_as::<_, UserSettings>(
+        "UPDATE user_settings SET
+             interface_language = COALESCE($2, interface_language),
+             script = COALESCE($3, script),
+             reminder_time = CASE WHEN $4 THEN NULL ELSE COALESCE($5, reminder_time) END,
+             daily_review_limit = CASE WHEN $6 THEN NULL ELSE COALESCE($7, daily_review_limit) END
+         WHERE user_id = $1
+         RETURNING interface_language, script, reminder_time, onboarding_finished_at, onboarding_skipped,
+                   daily_review_limit",
+    )
+        .bind(user_id)
+        .bind(payload.interface_language.map(|l| l.as_str()))
+        .bind(payload.script.map(|s| s.as_str()))
+        .bind(payload.clear_reminder)
+        .bind(payload.reminder_time)
+        .bind(payload.clear_daily_review_limit)
+        .bind(payload.daily_review_limit)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(settings)
+}
+
+/// Запускает ежедневные напоминания о занятиях в выбранное пользователем время.
+pub fn spawn_study_reminder_loop(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(STUDY_REMINDER_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = send_study_reminders(&pool).await {
+                tracing::error!("Ошибка рассылки напоминаний о занятиях: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Отправляет напоминание тем, у кого наступило время напоминания и кому сегодня еще не напоминали.
+pub async fn send_study_reminders(pool: &PgPool) -> Result<(), AppError> {
+    let due: Vec<i32> = sqlx::query_scalar(
+        "UPDATE user_settings SET last_reminded_on = (NOW() AT TIME ZONE 'UTC')::date
+         WHERE reminder_time IS NOT NULL
+           AND reminder_time <= (NOW() AT TIME ZONE 'UTC')::time
+           AND (last_reminded_on IS NULL OR last_reminded_on < (NOW() AT TIME ZONE 'UTC')::date)
+         RETURNING user_id",
+    )
+        .fetch_all(pool)
+        .await?;
+
+    for user_id in due {
+        notifications::notify(
+            user_id,
+            NotificationKind::StudyReminder,
+            "Время заниматься",
+            "Несколько минут повторения сегодня помогут не забыть выученное.",
+            pool,
+        ).await?;
+    }
+
+    Ok(())
+}
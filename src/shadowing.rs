@@ -0,0 +1,128 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::errors::AppError;
+use axum::http::StatusCode;
+
+/// Статус символа эталонного предложения после сравнения с распознанной речью.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlignmentStatus {
+    Matched,
+    Missed,
+    Extra,
+}
+
+/// Один символ выравнивания.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AlignedChar {
+    pub character: char,
+    pub status: AlignmentStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecognitionResponse {
+    transcript: String,
+}
+
+/// Отправляет запись во внешний сервис распознавания речи (`ASR_URL`).
+/// Сервис принимает аудио в теле запроса и возвращает `{"transcript": "..."}`.
+pub async fn recognize(audio: Vec<u8>, mime_type: &str) -> Result<String, AppError> {
+    let url = env::var("ASR_URL").map_err(|_| {
+        AppError::new(StatusCode::SERVICE_UNAVAILABLE, "Распознавание речи не настроено")
+    })?;
+
+    let response = Client::new()
+        .post(url)
+        .header("Content-Type", mime_type)
+        .query(&[("lang", "zh")])
+        .body(audio)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            tracing::error!("Ошибка сервиса распознавания речи: {:?}", e);
+            AppError::new(StatusCode::BAD_GATEWAY, "Сервис распознавания речи недоступен")
+        })?;
+
+    let recognition: RecognitionResponse = response.json().await.map_err(|e| {
+        tracing::error!("Некорректный ответ сервиса распознавания речи: {:?}", e);
+        AppError::new(StatusCode::BAD_GATEWAY, "Некорректный ответ сервиса распознавания речи")
+    })?;
+
+    Ok(recognition.transcript)
+}
+
+/// Символы, которые учитываются при сравнении (без пробелов и пунктуации).
+fn significant_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_ascii_punctuation() && !"，。！？、；：“”‘’（）《》".contains(*c))
+        .collect()
+}
+
+/// Выравнивает эталонный текст и распознанную речь по расстоянию Левенштейна.
+pub fn align(expected: &str, transcript: &str) -> Vec<AlignedChar> {
+    let expected = significant_chars(expected);
+    let actual = significant_chars(transcript);
+    let (n, m) = (expected.len(), actual.len());
+
+    // dp[i][j] — расстояние между префиксами длины i и j
+    let mut dp = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..=m {
+        dp[0][j] = j;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let substitution = dp[i - 1][j - 1] + usize::from(expected[i - 1] != actual[j - 1]);
+            dp[i][j] = substitution.min(dp[i - 1][j] + 1).min(dp[i][j - 1] + 1);
+        }
+    }
+
+    // Восстанавливаем путь с конца
+    let mut result = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && expected[i - 1] == actual[j - 1] && dp[i][j] == dp[i - 1][j - 1] {
+            result.push(AlignedChar { character: expected[i - 1], status: AlignmentStatus::Matched });
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && dp[i][j] == dp[i - 1][j - 1] + 1 {
+            // Замена: эталонный символ пропущен, вместо него сказано другое
+            result.push(AlignedChar { character: actual[j - 1], status: AlignmentStatus::Extra });
+            result.push(AlignedChar { character: expected[i - 1], status: AlignmentStatus::Missed });
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && dp[i][j] == dp[i - 1][j] + 1 {
+            result.push(AlignedChar { character: expected[i - 1], status: AlignmentStatus::Missed });
+            i -= 1;
+        } else {
+            result.push(AlignedChar { character: actual[j - 1], status: AlignmentStatus::Extra });
+            j -= 1;
+        }
+    }
+
+    result.reverse();
+    result
+}
+
+/// Оценка беглости от 0 до 100: доля верно произнесенных символов
+/// с штрафом за лишние слоги.
+pub fn fluency_score(alignment: &[AlignedChar]) -> f32 {
+    let count = |status| alignment.iter().filter(|c| c.status == status).count() as f32;
+    let (matched, missed, extra) = (
+        count(AlignmentStatus::Matched),
+        count(AlignmentStatus::Missed),
+        count(AlignmentStatus::Extra),
+    );
+
+    let expected = matched + missed;
+    if expected == 0.0 {
+        return 0.0;
+    }
+
+    (100.0 * (matched - 0.5 * extra).max(0.0) / expected).min(100.0)
+}
@@ -0,0 +1,120 @@
+use axum::http::StatusCode;
+use chrono::{Duration, NaiveDate};
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::models::StatSnapshot;
+
+/// Как часто проверять, не пора ли снять итоги за прошедшие сутки.
+const SNAPSHOT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// Период истории по умолчанию.
+pub const DEFAULT_HISTORY_DAYS: i64 = 30;
+/// Максимальный период одного запроса истории.
+pub const MAX_HISTORY_DAYS: i64 = 366;
+
+/// Запускает фоновую задачу снимков. Снимок дня делается один раз, после его окончания;
+/// если сервер был выключен, пропущенные дни досчитываются при следующей проверке.
+pub fn spawn_snapshot_loop(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SNAPSHOT_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = materialize(&pool).await {
+                tracing::error!("Ошибка снимка статистики: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Снимает итоги за все еще не обработанные завершившиеся дни и возвращает число новых строк.
+pub async fn materialize(pool: &PgPool) -> Result<u64, AppError> {
+    let (mut day, yesterday): (NaiveDate, NaiveDate) = sqlx::query_as(
+        "SELECT COALESCE(MAX(day) + 1, CURRENT_DATE - 1), CURRENT_DATE - 1 FROM stat_snapshots",
+    )
+        .fetch_one(pool)
+        .await?;
+
+    let mut inserted = 0;
+    while day <= yesterday {
+        inserted += snapshot_day(day, pool).await?;
+        day += Duration::days(1);
+    }
+    if inserted > 0 {
+        tracing::info!("Снимки статистики: {} строк до {}", inserted, yesterday);
+    }
+    Ok(inserted)
+}
+
+/// Итоги всех пользователей на конец дня `day`.
+///
+/// Число повторений прибавляется к вчерашнему снимку, чтобы не сканировать весь
+/// журнал повторений; без вчерашнего снимка журнал считается целиком. Серия —
+/// длина непрерывного отрезка дней занятий, который заканчивается в `day`
+/// (дни, закрытые заморозкой, не прерывают его, но и не считаются).
+/// Опыт — сумма начислений до конца дня.
+async fn snapshot_day(day: NaiveDate, pool: &PgPool) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        "INSERT INTO stat_snapshots (user_id, day, learned_total, reviews_total, streak_days, xp)
+         SELECT u.id, $1,
+                (SELECT COUNT(*) FROM user_progress up
+                 WHERE up.user_id = u.id AND up.is_learned
+                   AND (up.learned_at IS NULL OR up.learned_at < $1 + 1))::int,
+                CASE WHEN prev.user_id IS NULL
+                     THEN (SELECT COUNT(*) FROM review_log r WHERE r.user_id = u.id AND r.reviewed_at < $1 + 1)::int
+                     ELSE prev.reviews_total + (SELECT COUNT(*) FROM review_log r
+                                                WHERE r.user_id = u.id
+                                                  AND r.reviewed_at >= $1 AND r.reviewed_at < $1 + 1)::int
+                END,
+                (SELECT COUNT(*) FILTER (WHERE NOT i.frozen) FROM (
+                     SELECT s.frozen, s.day - (ROW_NUMBER() OVER (ORDER BY s.day))::int AS grp
+                     FROM study_days s WHERE s.user_id = u.id AND s.day <= $1
+                 ) i
+                 WHERE i.grp = $1 - (SELECT COUNT(*) FROM study_days s WHERE s.user_id = u.id AND s.day <= $1)::int
+                )::int,
+                (SELECT COALESCE(SUM(x.amount), 0) FROM xp_awards x
+                 WHERE x.user_id = u.id AND x.awarded_at < $1 + 1)::bigint
+         FROM users u
+         LEFT JOIN stat_snapshots prev ON prev.user_id = u.id AND prev.day = $1 - 1
+         ON CONFLICT (user_id, day) DO NOTHING",
+    )
+        .bind(day)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Проверяет период истории и подставляет значения по умолчанию
+/// (`to` — вчера, последний день со снимком).
+pub fn history_range(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    today: NaiveDate,
+) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let to = to.unwrap_or(today - Duration::days(1));
+    let from = from.unwrap_or(to - Duration::days(DEFAULT_HISTORY_DAYS - 1));
+    if from > to {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Начало периода позже его конца"));
+    }
+    if (to - from).num_days() >= MAX_HISTORY_DAYS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Период не может быть длиннее {} дней", MAX_HISTORY_DAYS),
+        ));
+    }
+    Ok((from, to))
+}
+
+/// Снимки пользователя за период, по возрастанию дат.
+pub async fn history(user_id: i32, from: NaiveDate, to: NaiveDate, pool: &PgPool) -> Result<Vec<StatSnapshot>, AppError> {
+    let snapshots = sqlx::query_as::<_, StatSnapshot>(
+        "SELECT day, learned_total, reviews_total, streak_days, xp FROM stat_snapshots
+         WHERE user_id = $1 AND day BETWEEN $2 AND $3
+         ORDER BY day",
+    )
+        .bind(user_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+    Ok(snapshots)
+}
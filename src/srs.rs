@@ -0,0 +1,222 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::hidden;
+use crate::models::{ContentType, ReviewItem, ReviewState, ReviewUndoResponse};
+
+/// Минимальный коэффициент легкости в SM-2.
+const MIN_EASE_FACTOR: f32 = 1.3;
+
+/// Максимальный размер очереди повторения за один запрос.
+pub const MAX_QUEUE_SIZE: i64 = 100;
+
+/// Ответ «хорошо» дольше этого времени засчитывается как «трудно».
+pub const SLOW_ANSWER_MS: u32 = 15_000;
+
+/// Более долгий ответ не записывается: пользователь, скорее всего, отвлекся.
+pub const MAX_LATENCY_MS: u32 = 300_000;
+
+/// Сколько минут после ответа его можно отменить.
+pub const UNDO_WINDOW_MINUTES: i64 = 10;
+
+/// Оценка «хорошо» в SM-2.
+const GOOD_QUALITY: u8 = 4;
+
+/// Оценка с учетом времени ответа: «хорошо», вспомненное слишком долго, становится «трудно».
+pub fn effective_quality(quality: u8, latency_ms: Option<u32>) -> u8 {
+    match latency_ms {
+        Some(latency) if quality == GOOD_QUALITY && latency > SLOW_ANSWER_MS => GOOD_QUALITY - 1,
+        _ => quality,
+    }
+}
+
+/// Пересчитывает расписание по алгоритму SM-2.
+/// `quality` — оценка вспоминания от 0 (полностью забыл) до 5 (идеально).
+pub fn schedule(state: &ReviewState, quality: u8, now: DateTime<Utc>) -> ReviewState {
+    let q = quality.min(5) as f32;
+
+    let (repetitions, interval_days) = if quality < 3 {
+        // Забытый элемент повторяется заново с первого интервала
+        (0, 1)
+    } else {
+        let repetitions = state.repetitions + 1;
+        let interval = match repetitions {
+            1 => 1,
+            2 => 6,
+            _ => (state.interval_days as f32 * state.ease_factor).round() as i32,
+        };
+        (repetitions, interval)
+    };
+
+    let ease_factor = (state.ease_factor + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02))).max(MIN_EASE_FACTOR);
+
+    ReviewState {
+        ease_factor,
+        interval_days,
+        repetitions,
+        next_review_at: Some(now + Duration::days(interval_days as i64)),
+    }
+}
+
+/// Элементы, которые пора повторить, начиная с самых просроченных. Скрытые пропускаются.
+pub async fn due_queue(user_id: i32, limit: i64, pool: &PgPool) -> Result<Vec<ReviewItem>, AppError> {
+    let items = sqlx::query_as::<_, ReviewItem>(&format!(
+        "SELECT up.content_type, up.content_id,
+                COALESCE(h.character, w.simplified, p.text, s.text, g.pattern) AS front,
+                COALESCE(h.pinyin, w.pinyin, p.pinyin, s.pinyin, '') AS pinyin,
+                COALESCE(h.translation, w.translation, p.translation, s.translation, g.title) AS translation,
+                CASE WHEN s.audio_media_id IS NULL THEN NULL ELSE '/api/media/' || s.audio_media_id END AS audio_url,
+                up.ease_factor, up.interval_days, up.repetitions, up.next_review_at
+         FROM user_progress up
+         LEFT JOIN hieroglyphs h ON up.content_type = 'hieroglyph' AND h.id = up.content_id
+         LEFT JOIN words w ON up.content_type = 'word' AND w.id = up.content_id
+         LEFT JOIN phrases p ON up.content_type = 'phrase' AND p.id = up.content_id
+         LEFT JOIN sentences s ON up.content_type = 'sentence' AND s.id = up.content_id
+         LEFT JOIN grammar_rules g ON up.content_type = 'grammar_rule' AND g.id = up.content_id
+         WHERE up.user_id = $1 AND up.is_learned AND up.next_review_at <= NOW()
+           AND COALESCE(h.id, w.id, p.id, s.id, g.id) IS NOT NULL
+           AND {}
+         ORDER BY up.next_review_at
+         LIMIT $2",
+        hidden::not_hidden("up.user_id", "up.content_type", "up.content_id")
+    ))
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(items)
+}
+
+/// Записывает ответ на повторение и переносит следующее повторение.
+/// `latency_ms` — время от показа карточки до оценки, если клиент его измерил.
+/// Возвращает новое состояние и засчитанную оценку (медленное «хорошо» — «трудно»).
+pub async fn answer(
+    user_id: i32,
+    content_type: ContentType,
+    content_id: i32,
+    quality: u8,
+    latency_ms: Option<u32>,
+    pool: &PgPool,
+) -> Result<(ReviewState, u8), AppError> {
+    let latency_ms = latency_ms.filter(|latency| *latency <= MAX_LATENCY_MS);
+    let quality = effective_quality(quality, latency_ms);
+    let mut tx = pool.begin().await?;
+
+    let current = sqlx::query_as::<_, ReviewState>(
+        "SELECT ease_factor, interval_days, repetitions, next_review_at FROM user_progress
+         WHERE user_id = $1 AND content_type = $2 AND content_id = $3 AND is_learned
+         FOR UPDATE",
+    )
+        .bind(user_id)
+        .bind(&content_type)
+        .bind(content_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::new(axum::http::StatusCode::NOT_FOUND, "Элемент не выучен"))?;
+
+    let next = schedule(&current, quality, Utc::now());
+
+    sqlx::query(
+        "UPDATE user_progress SET ease_factor = $4, interval_days = $5, repetitions = $6, next_review_at = $7
+         WHERE user_id = $1 AND content_type = $2 AND content_id = $3",
+    )
+        .bind(user_id)
+        .bind(&content_type)
+        .bind(content_id)
+        .bind(next.ease_factor)
+        .bind(next.interval_days)
+        .bind(next.repetitions)
+        .bind(next.next_review_at)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO review_log (user_id, content_type, content_id, quality, ease_factor, interval_days, latency_ms,
+                                 prev_ease_factor, prev_interval_days, prev_repetitions, prev_next_review_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+    )
+        .bind(user_id)
+        .bind(&content_type)
+        .bind(content_id)
+        .bind(quality as i16)
+        .bind(next.ease_factor)
+        .bind(next.interval_days)
+        .bind(latency_ms.map(|latency| latency as i32))
+        .bind(current.ease_factor)
+        .bind(current.interval_days)
+        .bind(current.repetitions)
+        .bind(current.next_review_at)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok((next, quality))
+}
+
+// Последний ответ пользователя с расписанием до него
+#[derive(sqlx::FromRow)]
+struct LastReview {
+    id: i32,
+    content_type: ContentType,
+    content_id: i32,
+    reviewed_at: DateTime<Utc>,
+    prev_ease_factor: Option<f32>,
+    prev_interval_days: Option<i32>,
+    prev_repetitions: Option<i32>,
+    prev_next_review_at: Option<DateTime<Utc>>,
+}
+
+/// Отменяет последний ответ пользователя, если с него прошло не больше
+/// `UNDO_WINDOW_MINUTES`: возвращает расписание элемента и удаляет запись журнала.
+/// Повторный вызов отменяет предыдущий ответ.
+pub async fn undo_last(user_id: i32, pool: &PgPool) -> Result<ReviewUndoResponse, AppError> {
+    let nothing_to_undo = || AppError::new(axum::http::StatusCode::NOT_FOUND, "Нет ответа, который можно отменить");
+    let mut tx = pool.begin().await?;
+
+    let last = sqlx::query_as::<_, LastReview>(
+        "SELECT id, content_type, content_id, reviewed_at,
+                prev_ease_factor, prev_interval_days, prev_repetitions, prev_next_review_at
+         FROM review_log WHERE user_id = $1
+         ORDER BY id DESC
+         LIMIT 1
+         FOR UPDATE",
+    )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(nothing_to_undo)?;
+    if last.reviewed_at < Utc::now() - Duration::minutes(UNDO_WINDOW_MINUTES) {
+        return Err(nothing_to_undo());
+    }
+    // Записи, сделанные до появления отмены, не хранят прежнего расписания
+    let (Some(ease_factor), Some(interval_days), Some(repetitions)) =
+        (last.prev_ease_factor, last.prev_interval_days, last.prev_repetitions)
+    else {
+        return Err(nothing_to_undo());
+    };
+    let state = ReviewState { ease_factor, interval_days, repetitions, next_review_at: last.prev_next_review_at };
+
+    sqlx::query(
+        "UPDATE user_progress SET ease_factor = $4, interval_days = $5, repetitions = $6, next_review_at = $7
+         WHERE user_id = $1 AND content_type = $2 AND content_id = $3",
+    )
+        .bind(user_id)
+        .bind(&last.content_type)
+        .bind(last.content_id)
+        .bind(state.ease_factor)
+        .bind(state.interval_days)
+        .bind(state.repetitions)
+        .bind(state.next_review_at)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM review_log WHERE id = $1")
+        .bind(last.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(ReviewUndoResponse { content_type: last.content_type, content_id: last.content_id, state })
+}
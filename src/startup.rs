@@ -0,0 +1,87 @@
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::AppError;
+use crate::models::{ReadinessStatus, StartupPhase};
+
+/// Через сколько секунд клиенту стоит повторить запрос, пока сервер запускается.
+const RETRY_AFTER_SECS: u64 = 2;
+/// Максимальная пауза между попытками подключиться к базе.
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+// Пока запуск не начат (например, в тестах роутера), сервер считается готовым.
+static STATUS: Lazy<Mutex<ReadinessStatus>> =
+    Lazy::new(|| Mutex::new(ReadinessStatus { ready: true, phase: StartupPhase::Ready, error: None }));
+
+fn set_phase(phase: StartupPhase, error: Option<String>) {
+    *STATUS.lock().unwrap() = ReadinessStatus { ready: phase == StartupPhase::Ready, phase, error };
+}
+
+/// Текущее состояние запуска.
+pub fn status() -> ReadinessStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+/// Пул без подключения: соединения открываются при первом запросе,
+/// так что сервер может начать слушать порт, не дожидаясь базы.
+pub fn lazy_pool(database_url: &str) -> Result<PgPool, sqlx::Error> {
+    set_phase(StartupPhase::Connecting, None);
+    PgPoolOptions::new()
+        .max_connections(10)
+        .acquire_timeout(Duration::from_secs(5))
+        .connect_lazy(database_url)
+}
+
+/// Дожидается базы и применяет миграции. Подключение повторяется с растущей
+/// паузой, пока база не станет доступна; ошибка миграции окончательная.
+/// Возвращает `true`, если сервер готов к работе.
+pub async fn prepare(pool: &PgPool) -> bool {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match pool.acquire().await {
+            Ok(_) => break,
+            Err(e) => {
+                tracing::warn!("База данных недоступна, повтор через {} с: {}", backoff.as_secs(), e);
+                set_phase(StartupPhase::Connecting, Some(e.to_string()));
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+            }
+        }
+    }
+
+    set_phase(StartupPhase::Migrating, None);
+    if let Err(e) = sqlx::migrate!("./migrations").run(pool).await {
+        tracing::error!("Не удалось применить миграции: {}", e);
+        set_phase(StartupPhase::Failed, Some(e.to_string()));
+        return false;
+    }
+
+    set_phase(StartupPhase::Ready, None);
+    tracing::info!("Сервер готов к работе");
+    true
+}
+
+/// Пока сервер не готов, запросы к `/api` получают 503 с `Retry-After`,
+/// а не зависают в ожидании соединения с базой. Статическая страница и
+/// проверки `/healthz` и `/readyz` доступны всегда.
+pub async fn require_ready(request: Request, next: Next) -> Response {
+    if !request.uri().path().starts_with("/api/") || STATUS.lock().unwrap().ready {
+        return next.run(request).await;
+    }
+
+    let mut response =
+        AppError::new(StatusCode::SERVICE_UNAVAILABLE, "Сервер запускается, повторите запрос позже")
+            .with_code("starting")
+            .into_response();
+    response.headers_mut().insert(header::RETRY_AFTER, RETRY_AFTER_SECS.into());
+    response
+}
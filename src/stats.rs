@@ -0,0 +1,172 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::errors::AppError;
+use crate::models::{
+    BreakdownStats, ContentType, ContentTypeBreakdown, LatencyStats, LevelBreakdown, StatsBreakdown, TagBreakdown,
+    WeakSpot,
+};
+
+/// Период разбивки по умолчанию, дней.
+pub const DEFAULT_BREAKDOWN_DAYS: i32 = 90;
+/// Максимальный период разбивки, дней.
+pub const MAX_BREAKDOWN_DAYS: i32 = 365;
+/// Сколько тегов с наибольшим числом повторений показывать.
+const MAX_TAGS: usize = 50;
+/// Меньше повторений — слишком мало, чтобы считать сочетание слабым местом.
+pub const MIN_WEAK_SPOT_REVIEWS: i64 = 20;
+/// Сколько слабых мест показывать.
+const MAX_WEAK_SPOTS: usize = 5;
+/// Оценка, начиная с которой повторение считается успешным (как в SM-2).
+const PASSING_QUALITY: i16 = 3;
+
+/// Повторения одного элемента за период.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ItemReviews {
+    pub content_type: ContentType,
+    pub hsk_level: Option<i16>,
+    pub tags: Vec<String>,
+    pub reviews: i64,
+    pub passed: i64,
+    /// Успешно ли последнее повторение.
+    pub retained: bool,
+}
+
+#[derive(Default)]
+struct Totals {
+    items: i64,
+    reviews: i64,
+    passed: i64,
+    retained: i64,
+}
+
+impl Totals {
+    fn add(&mut self, item: &ItemReviews) {
+        self.items += 1;
+        self.reviews += item.reviews;
+        self.passed += item.passed;
+        self.retained += i64::from(item.retained);
+    }
+
+    fn stats(&self) -> BreakdownStats {
+        let percent = |part: i64, total: i64| if total == 0 { 0.0 } else { part as f32 / total as f32 * 100.0 };
+        BreakdownStats {
+            items: self.items,
+            reviews: self.reviews,
+            accuracy: percent(self.passed, self.reviews),
+            retention: percent(self.retained, self.items),
+        }
+    }
+}
+
+/// Группирует элементы по ключам и считает показатели каждой группы.
+/// Элемент с несколькими ключами (например, тегами) попадает в каждую из групп.
+pub fn group<K, F>(items: &[ItemReviews], keys: F) -> Vec<(K, BreakdownStats)>
+where
+    K: Eq + Hash,
+    F: Fn(&ItemReviews) -> Vec<K>,
+{
+    let mut totals: HashMap<K, Totals> = HashMap::new();
+    for item in items {
+        for key in keys(item) {
+            totals.entry(key).or_default().add(item);
+        }
+    }
+    totals.into_iter().map(|(key, totals)| (key, totals.stats())).collect()
+}
+
+/// Сочетания уровня и тега с достаточным числом повторений, от худшей точности к лучшей.
+pub fn weak_spots(items: &[ItemReviews]) -> Vec<WeakSpot> {
+    let mut spots: Vec<WeakSpot> = group(items, |item| match item.hsk_level {
+        Some(level) => item.tags.iter().map(|tag| (level, tag.clone())).collect(),
+        None => Vec::new(),
+    })
+        .into_iter()
+        .filter(|(_, stats)| stats.reviews >= MIN_WEAK_SPOT_REVIEWS)
+        .map(|((hsk_level, tag), stats)| WeakSpot { hsk_level, tag, stats })
+        .collect();
+    spots.sort_by(|a, b| {
+        a.stats.accuracy.total_cmp(&b.stats.accuracy).then(b.stats.reviews.cmp(&a.stats.reviews))
+    });
+    spots.truncate(MAX_WEAK_SPOTS);
+    spots
+}
+
+/// Разбивка по уровню HSK, тегу и типу карточки из журнала повторений за `days` дней.
+pub async fn breakdown(user_id: i32, days: Option<i32>, pool: &PgPool) -> Result<StatsBreakdown, AppError> {
+    let days = days.unwrap_or(DEFAULT_BREAKDOWN_DAYS);
+    if !(1..=MAX_BREAKDOWN_DAYS).contains(&days) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Период должен быть от 1 до {} дней", MAX_BREAKDOWN_DAYS),
+        ));
+    }
+
+    let since = Utc::now() - Duration::days(days as i64);
+    let items = sqlx::query_as::<_, ItemReviews>(
+        "SELECT r.content_type,
+                COALESCE(h.hsk_level, w.hsk_level, p.hsk_level, g.hsk_level) AS hsk_level,
+                COALESCE(h.tags, w.tags, p.tags, s.tags, g.tags, '{}') AS tags,
+                COUNT(*) AS reviews,
+                COUNT(*) FILTER (WHERE r.quality >= $3) AS passed,
+                (ARRAY_AGG(r.quality ORDER BY r.reviewed_at DESC, r.id DESC))[1] >= $3 AS retained
+         FROM review_log r
+         LEFT JOIN hieroglyphs h ON r.content_type = 'hieroglyph' AND h.id = r.content_id
+         LEFT JOIN words w ON r.content_type = 'word' AND w.id = r.content_id
+         LEFT JOIN phrases p ON r.content_type = 'phrase' AND p.id = r.content_id
+         LEFT JOIN sentences s ON r.content_type = 'sentence' AND s.id = r.content_id
+         LEFT JOIN grammar_rules g ON r.content_type = 'grammar_rule' AND g.id = r.content_id
+         WHERE r.user_id = $1 AND r.reviewed_at >= $2
+         GROUP BY r.content_type, r.content_id, h.id, w.id, p.id, s.id, g.id",
+    )
+        .bind(user_id)
+        .bind(since)
+        .bind(PASSING_QUALITY)
+        .fetch_all(pool)
+        .await?;
+
+    let mut by_hsk_level: Vec<LevelBreakdown> = group(&items, |item| vec![item.hsk_level])
+        .into_iter()
+        .map(|(hsk_level, stats)| LevelBreakdown { hsk_level, stats })
+        .collect();
+    // Элементы без уровня — в конце
+    by_hsk_level.sort_by_key(|group| (group.hsk_level.is_none(), group.hsk_level));
+
+    let mut by_tag: Vec<TagBreakdown> = group(&items, |item| item.tags.clone())
+        .into_iter()
+        .map(|(tag, stats)| TagBreakdown { tag, stats })
+        .collect();
+    by_tag.sort_by(|a, b| b.stats.reviews.cmp(&a.stats.reviews).then_with(|| a.tag.cmp(&b.tag)));
+    by_tag.truncate(MAX_TAGS);
+
+    let mut by_content_type: Vec<ContentTypeBreakdown> = group(&items, |item| vec![item.content_type.clone()])
+        .into_iter()
+        .map(|(content_type, stats)| ContentTypeBreakdown { content_type, stats })
+        .collect();
+    by_content_type.sort_by(|a, b| b.stats.reviews.cmp(&a.stats.reviews));
+
+    let latency = sqlx::query_as::<_, LatencyStats>(
+        "SELECT COUNT(latency_ms) AS answers,
+                percentile_disc(0.5) WITHIN GROUP (ORDER BY latency_ms) AS p50_ms,
+                percentile_disc(0.9) WITHIN GROUP (ORDER BY latency_ms) AS p90_ms,
+                percentile_disc(0.99) WITHIN GROUP (ORDER BY latency_ms) AS p99_ms
+         FROM review_log
+         WHERE user_id = $1 AND reviewed_at >= $2 AND latency_ms IS NOT NULL",
+    )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(StatsBreakdown {
+        days,
+        by_hsk_level,
+        by_tag,
+        by_content_type,
+        weak_spots: weak_spots(&items),
+        latency,
+    })
+}
@@ -0,0 +1,165 @@
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::jobs;
+use crate::models::StreakSummary;
+
+/// Больше заморозок не копится.
+pub const MAX_FREEZES: i32 = 2;
+/// Заморозка выдается за каждые столько дней серии.
+pub const FREEZE_EVERY_DAYS: i64 = 7;
+
+/// День занятий; `frozen` — пропуск, закрытый заморозкой.
+#[derive(Debug, Clone, Copy, sqlx::FromRow)]
+pub struct StudyDay {
+    pub day: NaiveDate,
+    pub frozen: bool,
+}
+
+/// Считает серию по дням занятий, отсортированным по возрастанию.
+///
+/// Серия жива, если последний день занятий — сегодня или вчера, либо если
+/// пропущенных с тех пор дней не больше, чем заморозок в запасе: при следующем
+/// занятии они закроют пропуск.
+pub fn summarize(days: &[StudyDay], today: NaiveDate, freezes: i32) -> StreakSummary {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        if previous.is_none_or(|previous| (day.day - previous).num_days() != 1) {
+            run = 0;
+        }
+        if !day.frozen {
+            run += 1;
+        }
+        longest = longest.max(run);
+        previous = Some(day.day);
+    }
+
+    let last = days.last();
+    let current = match last {
+        Some(last) => {
+            let missed = (today - last.day).num_days() - 1;
+            if missed <= freezes as i64 { run } else { 0 }
+        }
+        None => 0,
+    };
+
+    StreakSummary {
+        current_days: current,
+        longest_days: longest,
+        studied_today: last.is_some_and(|last| last.day == today && !last.frozen),
+        freezes_available: freezes,
+        last_study_day: days.iter().rev().find(|day| !day.frozen).map(|day| day.day),
+    }
+}
+
+async fn load_days<'e, E>(user_id: i32, executor: E) -> Result<Vec<StudyDay>, AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let days = sqlx::query_as::<_, StudyDay>("SELECT day, frozen FROM study_days WHERE user_id = $1 ORDER BY day")
+        .bind(user_id)
+        .fetch_all(executor)
+        .await?;
+    Ok(days)
+}
+
+/// Серия пользователя на сегодня.
+pub async fn summary(user_id: i32, pool: &PgPool) -> Result<StreakSummary, AppError> {
+    let (today, freezes): (NaiveDate, i32) =
+        sqlx::query_as("SELECT CURRENT_DATE, streak_freezes FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+    let days = load_days(user_id, pool).await?;
+    Ok(summarize(&days, today, freezes))
+}
+
+/// Засчитывает сегодняшний день занятий. Пропуск с прошлого занятия закрывается
+/// заморозками, если их хватает; за каждые `FREEZE_EVERY_DAYS` дней серии
+/// выдается новая заморозка.
+pub async fn record(user_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Блокировка строки пользователя не дает двум событиям потратить одну заморозку
+    let (today, mut freezes): (NaiveDate, i32) =
+        sqlx::query_as("SELECT CURRENT_DATE, streak_freezes FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    let last: Option<NaiveDate> = sqlx::query_scalar("SELECT MAX(day) FROM study_days WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if last == Some(today) {
+        return Ok(());
+    }
+
+    if let Some(last) = last {
+        let missed = (today - last).num_days() - 1;
+        if missed > 0 && missed <= freezes as i64 {
+            sqlx::query(
+                "INSERT INTO study_days (user_id, day, frozen)
+                 SELECT $1, d::date, TRUE FROM generate_series($2::date + 1, $3::date - 1, INTERVAL '1 day') d",
+            )
+                .bind(user_id)
+                .bind(last)
+                .bind(today)
+                .execute(&mut *tx)
+                .await?;
+            freezes -= missed as i32;
+        }
+    }
+
+    sqlx::query("INSERT INTO study_days (user_id, day) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(today)
+        .execute(&mut *tx)
+        .await?;
+
+    let current = summarize(&load_days(user_id, &mut *tx).await?, today, freezes).current_days;
+    if current % FREEZE_EVERY_DAYS == 0 && freezes < MAX_FREEZES {
+        freezes += 1;
+    }
+    sqlx::query("UPDATE users SET streak_freezes = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(freezes)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Восстанавливает дни занятий всех пользователей по выученному, журналу
+/// повторений и сданным тестам — например, после импорта истории. День,
+/// закрытый заморозкой, в который на деле были занятия, становится обычным.
+/// Заморозки не пересчитываются: их расход зависел от порядка занятий.
+pub async fn recompute_all(pool: PgPool, job_id: u64) -> Result<(), AppError> {
+    let user_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM users ORDER BY id")
+        .fetch_all(&pool)
+        .await?;
+
+    jobs::set_total(job_id, user_ids.len());
+    for user_id in user_ids {
+        sqlx::query(
+            "INSERT INTO study_days (user_id, day)
+             SELECT $1, day FROM (
+                 SELECT learned_at::date AS day FROM user_progress
+                 WHERE user_id = $1 AND is_learned AND learned_at IS NOT NULL
+                 UNION SELECT reviewed_at::date FROM review_log WHERE user_id = $1
+                 UNION SELECT submitted_at::date FROM test_results WHERE user_id = $1
+             ) activity
+             ON CONFLICT (user_id, day) DO UPDATE SET frozen = FALSE WHERE study_days.frozen",
+        )
+            .bind(user_id)
+            .execute(&pool)
+            .await?;
+        jobs::advance(job_id);
+    }
+
+    Ok(())
+}
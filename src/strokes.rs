@@ -0,0 +1,163 @@
+use axum::http::StatusCode;
+use serde::Deserialize;
+use sqlx::PgPool;
+use sqlx::types::Json;
+use std::collections::HashMap;
+
+use crate::errors::AppError;
+use crate::jobs;
+use crate::models::{HieroglyphStrokes, StrokeDataPayload};
+
+/// Максимум черт у одного иероглифа (самые сложные иероглифы — около 60 черт).
+pub const MAX_STROKES: usize = 64;
+
+/// В Make Me A Hanzi ось y направлена вверх и смещена: экранная координата — `900 - y`.
+const BASELINE: f64 = 900.0;
+
+/// Строка `graphics.txt` из Make Me A Hanzi.
+#[derive(Debug, Deserialize)]
+struct GraphicsLine {
+    character: String,
+    strokes: Vec<String>,
+    #[serde(default)]
+    medians: Vec<Vec<[i32; 2]>>,
+}
+
+/// Переводит контур черты в экранные координаты (поле 1024×1024, ось y вниз),
+/// чтобы клиентам не нужно было применять преобразование из Make Me A Hanzi.
+/// Команды в данных абсолютные и состоят из пар координат, поэтому
+/// отражается каждое второе число после буквы команды.
+pub fn to_screen(path: &str) -> String {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut number = String::new();
+    // Номер числа после последней команды: нечетные — координаты y
+    let mut index = 0;
+
+    for c in path.chars() {
+        if c.is_ascii_alphabetic() {
+            flush_number(&mut number, &mut index, &mut tokens);
+            tokens.push(c.to_string());
+            index = 0;
+        } else if c.is_ascii_digit() || c == '.' || (c == '-' && number.is_empty()) {
+            number.push(c);
+        } else if c == '-' {
+            flush_number(&mut number, &mut index, &mut tokens);
+            number.push(c);
+        } else {
+            flush_number(&mut number, &mut index, &mut tokens);
+        }
+    }
+    flush_number(&mut number, &mut index, &mut tokens);
+    tokens.join(" ")
+}
+
+fn flush_number(number: &mut String, index: &mut usize, tokens: &mut Vec<String>) {
+    if number.is_empty() {
+        return;
+    }
+    let token = match number.parse::<f64>() {
+        Ok(value) if *index % 2 == 1 => format_number(BASELINE - value),
+        _ => number.clone(),
+    };
+    tokens.push(token);
+    number.clear();
+    *index += 1;
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 { format!("{}", value as i64) } else { format!("{}", value) }
+}
+
+/// Проверяет данные порядка черт.
+pub fn validate(payload: &StrokeDataPayload) -> Result<(), AppError> {
+    if payload.strokes.is_empty() || payload.strokes.len() > MAX_STROKES {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Число черт должно быть от 1 до {}", MAX_STROKES),
+        ));
+    }
+    if !payload.medians.is_empty() && payload.medians.len() != payload.strokes.len() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Число осевых линий не совпадает с числом черт"));
+    }
+    Ok(())
+}
+
+/// Порядок черт иероглифа в экранных координатах.
+pub async fn get(hieroglyph_id: i32, pool: &PgPool) -> Result<HieroglyphStrokes, AppError> {
+    let row = sqlx::query_as::<_, (String, Option<Json<Vec<String>>>, Option<Json<Vec<Vec<[i32; 2]>>>>)>(
+        "SELECT h.character, s.strokes, s.medians
+         FROM hieroglyphs h LEFT JOIN hieroglyph_strokes s ON s.hieroglyph_id = h.id
+         WHERE h.id = $1",
+    )
+        .bind(hieroglyph_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Иероглиф не найден"))?;
+
+    let (character, Some(Json(strokes)), Some(Json(medians))) = row else {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Для иероглифа нет данных о порядке черт")
+            .with_code("no_stroke_data"));
+    };
+
+    Ok(HieroglyphStrokes {
+        hieroglyph_id,
+        character,
+        strokes: strokes.iter().map(|path| to_screen(path)).collect(),
+        medians: medians
+            .into_iter()
+            .map(|median| median.into_iter().map(|[x, y]| [x, BASELINE as i32 - y]).collect())
+            .collect(),
+    })
+}
+
+/// Сохраняет порядок черт иероглифа (в исходных координатах Make Me A Hanzi).
+pub async fn save(hieroglyph_id: i32, payload: &StrokeDataPayload, pool: &PgPool) -> Result<(), AppError> {
+    validate(payload)?;
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM hieroglyphs WHERE id = $1)")
+        .bind(hieroglyph_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Иероглиф не найден"));
+    }
+
+    sqlx::query(
+        "INSERT INTO hieroglyph_strokes (hieroglyph_id, strokes, medians) VALUES ($1, $2, $3)
+         ON CONFLICT (hieroglyph_id) DO UPDATE
+         SET strokes = EXCLUDED.strokes, medians = EXCLUDED.medians, updated_at = NOW()",
+    )
+        .bind(hieroglyph_id)
+        .bind(Json(&payload.strokes))
+        .bind(Json(&payload.medians))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Фоновая задача: импорт `graphics.txt` из Make Me A Hanzi (по JSON-объекту в строке).
+/// Данные сохраняются только для иероглифов, которые уже есть в базе.
+pub async fn import(data: String, pool: PgPool, job_id: u64) -> Result<(), AppError> {
+    let ids: HashMap<String, i32> = sqlx::query_as::<_, (String, i32)>("SELECT character, id FROM hieroglyphs")
+        .fetch_all(&pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let lines: Vec<&str> = data.lines().filter(|line| !line.trim().is_empty()).collect();
+    jobs::set_total(job_id, lines.len());
+
+    for line in lines {
+        jobs::advance(job_id);
+        let Ok(entry) = serde_json::from_str::<GraphicsLine>(line) else {
+            continue;
+        };
+        let Some(&hieroglyph_id) = ids.get(&entry.character) else {
+            continue;
+        };
+        let payload = StrokeDataPayload { strokes: entry.strokes, medians: entry.medians };
+        if validate(&payload).is_ok() {
+            save(hieroglyph_id, &payload, &pool).await?;
+        }
+    }
+    Ok(())
+}
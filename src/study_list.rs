@@ -0,0 +1,69 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::matching::normalize_hanzi;
+use crate::models::{ContentType, KnownStatus};
+
+/// Находит слово или иероглиф по тексту: сначала среди слов, затем среди иероглифов.
+async fn resolve(text: &str, pool: &PgPool) -> Result<Option<(ContentType, i32)>, AppError> {
+    let word_id: Option<i32> = sqlx::query_scalar("SELECT id FROM words WHERE simplified = $1")
+        .bind(text)
+        .fetch_optional(pool)
+        .await?;
+    if let Some(id) = word_id {
+        return Ok(Some((ContentType::Word, id)));
+    }
+
+    let hieroglyph_id: Option<i32> = sqlx::query_scalar("SELECT id FROM hieroglyphs WHERE character = $1")
+        .bind(text)
+        .fetch_optional(pool)
+        .await?;
+    Ok(hieroglyph_id.map(|id| (ContentType::Hieroglyph, id)))
+}
+
+/// Знает ли пользователь слово и есть ли оно в его списке изучения.
+pub async fn status(user_id: i32, text: &str, pool: &PgPool) -> Result<KnownStatus, AppError> {
+    let text = normalize_hanzi(text);
+    let Some((content_type, content_id)) = resolve(&text, pool).await? else {
+        return Ok(KnownStatus { text, content_type: None, content_id: None, known: false, in_study_list: false });
+    };
+
+    let is_learned: Option<bool> = sqlx::query_scalar(
+        "SELECT is_learned FROM user_progress WHERE user_id = $1 AND content_type = $2 AND content_id = $3",
+    )
+        .bind(user_id)
+        .bind(&content_type)
+        .bind(content_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(KnownStatus {
+        text,
+        content_type: Some(content_type),
+        content_id: Some(content_id),
+        known: is_learned == Some(true),
+        in_study_list: is_learned.is_some(),
+    })
+}
+
+/// Добавляет слово в список изучения (запись прогресса без отметки о выучивании).
+pub async fn add(user_id: i32, text: &str, pool: &PgPool) -> Result<KnownStatus, AppError> {
+    let normalized = normalize_hanzi(text);
+    let (content_type, content_id) = resolve(&normalized, pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Слово не найдено в словаре"))?;
+
+    sqlx::query(
+        "INSERT INTO user_progress (user_id, content_type, content_id, is_learned)
+         VALUES ($1, $2, $3, FALSE)
+         ON CONFLICT (user_id, content_type, content_id) DO NOTHING",
+    )
+        .bind(user_id)
+        .bind(&content_type)
+        .bind(content_id)
+        .execute(pool)
+        .await?;
+
+    status(user_id, &normalized, pool).await
+}
@@ -0,0 +1,289 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use crate::content;
+use crate::errors::AppError;
+use crate::models::{
+    NoteChange, ProgressChange, SettingsChange, SyncNote, SyncProgress, SyncPullResponse, SyncPushPayload,
+    SyncPushResponse, SyncSettings,
+};
+
+/// Максимум записей одного типа в ответе pull.
+pub const MAX_PULL_ROWS: i64 = 500;
+/// Максимум изменений в одном push.
+pub const MAX_PUSH_CHANGES: usize = 1000;
+
+/// Что делать с изменением, пришедшим с устройства.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution<T> {
+    /// Клиент менял ту же версию, что лежит на сервере: применяем как есть.
+    Apply(T),
+    /// Запись успела измениться на другом устройстве: сохраняем объединенное состояние.
+    Merge(T),
+    /// Серверная версия новее и поглощает изменение.
+    Keep,
+}
+
+impl SyncPushResponse {
+    fn record<T>(&mut self, resolution: &Resolution<T>) {
+        match resolution {
+            Resolution::Apply(_) => self.applied += 1,
+            Resolution::Merge(_) => self.merged += 1,
+            Resolution::Keep => self.rejected += 1,
+        }
+    }
+}
+
+/// Объединяет изменение прогресса с серверной записью.
+///
+/// При конфликте отметка о выучивании не теряется ни с одного устройства
+/// (логическое ИЛИ, дата — самая ранняя), а состояние повторения берется
+/// у того, кто менял запись позже.
+pub fn resolve_progress(server: Option<&SyncProgress>, change: &ProgressChange) -> Resolution<ProgressChange> {
+    let Some(server) = server else {
+        return Resolution::Apply(change.clone());
+    };
+    if change.base_version == Some(server.version) {
+        return Resolution::Apply(change.clone());
+    }
+
+    let mut merged = if change.changed_at > server.updated_at {
+        change.clone()
+    } else {
+        ProgressChange {
+            ease_factor: server.ease_factor,
+            interval_days: server.interval_days,
+            repetitions: server.repetitions,
+            next_review_at: server.next_review_at,
+            ..change.clone()
+        }
+    };
+    merged.is_learned = server.is_learned || change.is_learned;
+    merged.learned_at = match (server.learned_at, change.learned_at) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    let unchanged = merged.is_learned == server.is_learned
+        && merged.learned_at == server.learned_at
+        && merged.ease_factor == server.ease_factor
+        && merged.interval_days == server.interval_days
+        && merged.repetitions == server.repetitions
+        && merged.next_review_at == server.next_review_at;
+    if unchanged { Resolution::Keep } else { Resolution::Merge(merged) }
+}
+
+/// Для заметок и настроек при конфликте побеждает более позднее изменение.
+pub fn resolve_last_writer<T: Clone>(
+    server: Option<(i64, DateTime<Utc>)>,
+    base_version: Option<i64>,
+    changed_at: DateTime<Utc>,
+    change: &T,
+) -> Resolution<T> {
+    match server {
+        None => Resolution::Apply(change.clone()),
+        Some((version, _)) if base_version == Some(version) => Resolution::Apply(change.clone()),
+        Some((_, updated_at)) if changed_at > updated_at => Resolution::Merge(change.clone()),
+        Some(_) => Resolution::Keep,
+    }
+}
+
+/// Изменения пользователя с версией больше `since`.
+pub async fn pull(user_id: i32, since: i64, pool: &PgPool) -> Result<SyncPullResponse, AppError> {
+    let progress = sqlx::query_as::<_, SyncProgress>(
+        "SELECT content_type, content_id, is_learned, learned_at, ease_factor, interval_days,
+                repetitions, next_review_at, version, updated_at
+         FROM user_progress WHERE user_id = $1 AND version > $2
+         ORDER BY version LIMIT $3",
+    )
+        .bind(user_id)
+        .bind(since)
+        .bind(MAX_PULL_ROWS)
+        .fetch_all(pool)
+        .await?;
+
+    let notes = sqlx::query_as::<_, SyncNote>(
+        "SELECT content_type, content_id, body, deleted, version, updated_at
+         FROM user_notes WHERE user_id = $1 AND version > $2
+         ORDER BY version LIMIT $3",
+    )
+        .bind(user_id)
+        .bind(since)
+        .bind(MAX_PULL_ROWS)
+        .fetch_all(pool)
+        .await?;
+
+    let settings = sqlx::query_as::<_, SyncSettings>(
+        "SELECT interface_language, script, reminder_time, version, updated_at
+         FROM user_settings WHERE user_id = $1 AND version > $2",
+    )
+        .bind(user_id)
+        .bind(since)
+        .fetch_optional(pool)
+        .await?;
+
+    // Если один из списков обрезан, курсор не должен уйти дальше его последней
+    // записи. Записи других типов с большей версией придут повторно — клиент
+    // применяет их по ключу, так что повтор безопасен.
+    let mut truncated = Vec::new();
+    if progress.len() as i64 == MAX_PULL_ROWS {
+        truncated.extend(progress.last().map(|p| p.version));
+    }
+    if notes.len() as i64 == MAX_PULL_ROWS {
+        truncated.extend(notes.last().map(|n| n.version));
+    }
+    let has_more = !truncated.is_empty();
+    let cursor = match truncated.iter().min() {
+        Some(&cursor) => cursor,
+        None => progress
+            .iter()
+            .map(|p| p.version)
+            .chain(notes.iter().map(|n| n.version))
+            .chain(settings.iter().map(|s| s.version))
+            .max()
+            .unwrap_or(since),
+    };
+
+    Ok(SyncPullResponse { cursor, has_more, progress, notes, settings })
+}
+
+async fn push_progress(
+    user_id: i32,
+    change: &ProgressChange,
+    conn: &mut PgConnection,
+) -> Result<Resolution<ProgressChange>, AppError> {
+    let server = sqlx::query_as::<_, SyncProgress>(
+        "SELECT content_type, content_id, is_learned, learned_at, ease_factor, interval_days,
+                repetitions, next_review_at, version, updated_at
+         FROM user_progress WHERE user_id = $1 AND content_type = $2 AND content_id = $3
+         FOR UPDATE",
+    )
+        .bind(user_id)
+        .bind(&change.content_type)
+        .bind(change.content_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let resolution = resolve_progress(server.as_ref(), change);
+    if let Resolution::Apply(state) | Resolution::Merge(state) = &resolution {
+        sqlx::query(
+            "INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at,
+                                        ease_factor, interval_days, repetitions, next_review_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             ON CONFLICT (user_id, content_type, content_id) DO UPDATE SET
+                 is_learned = EXCLUDED.is_learned, learned_at = EXCLUDED.learned_at,
+                 ease_factor = EXCLUDED.ease_factor, interval_days = EXCLUDED.interval_days,
+                 repetitions = EXCLUDED.repetitions, next_review_at = EXCLUDED.next_review_at",
+        )
+            .bind(user_id)
+            .bind(&state.content_type)
+            .bind(state.content_id)
+            .bind(state.is_learned)
+            .bind(state.learned_at)
+            .bind(state.ease_factor)
+            .bind(state.interval_days)
+            .bind(state.repetitions)
+            .bind(state.next_review_at)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(resolution)
+}
+
+async fn push_note(user_id: i32, change: &NoteChange, conn: &mut PgConnection) -> Result<Resolution<NoteChange>, AppError> {
+    let server: Option<(i64, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT version, updated_at FROM user_notes
+         WHERE user_id = $1 AND content_type = $2 AND content_id = $3 FOR UPDATE",
+    )
+        .bind(user_id)
+        .bind(&change.content_type)
+        .bind(change.content_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let resolution = resolve_last_writer(server, change.base_version, change.changed_at, change);
+    if let Resolution::Apply(note) | Resolution::Merge(note) = &resolution {
+        sqlx::query(
+            "INSERT INTO user_notes (user_id, content_type, content_id, body, deleted)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (user_id, content_type, content_id) DO UPDATE SET
+                 body = EXCLUDED.body, deleted = EXCLUDED.deleted",
+        )
+            .bind(user_id)
+            .bind(&note.content_type)
+            .bind(note.content_id)
+            .bind(if note.deleted { "" } else { note.body.as_str() })
+            .bind(note.deleted)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(resolution)
+}
+
+async fn push_settings(
+    user_id: i32,
+    change: &SettingsChange,
+    conn: &mut PgConnection,
+) -> Result<Resolution<SettingsChange>, AppError> {
+    let server: Option<(i64, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT version, updated_at FROM user_settings WHERE user_id = $1 FOR UPDATE",
+    )
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let resolution = resolve_last_writer(server, change.base_version, change.changed_at, change);
+    if let Resolution::Apply(settings) | Resolution::Merge(settings) = &resolution {
+        sqlx::query(
+            "INSERT INTO user_settings (user_id, interface_language, script, reminder_time)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (user_id) DO UPDATE SET
+                 interface_language = EXCLUDED.interface_language, script = EXCLUDED.script,
+                 reminder_time = EXCLUDED.reminder_time",
+        )
+            .bind(user_id)
+            .bind(settings.interface_language.as_str())
+            .bind(settings.script.as_str())
+            .bind(settings.reminder_time)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(resolution)
+}
+
+/// Принимает пакет изменений с устройства одной транзакцией.
+pub async fn push(user_id: i32, payload: &SyncPushPayload, pool: &PgPool) -> Result<SyncPushResponse, AppError> {
+    if payload.progress.len() + payload.notes.len() > MAX_PUSH_CHANGES {
+        return Err(AppError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            &format!("Не больше {} изменений за один запрос", MAX_PUSH_CHANGES),
+        ));
+    }
+    for (content_type, content_id) in payload
+        .progress
+        .iter()
+        .map(|p| (&p.content_type, p.content_id))
+        .chain(payload.notes.iter().map(|n| (&n.content_type, n.content_id)))
+    {
+        content::ensure_exists(content_type, content_id, pool).await?;
+    }
+
+    let mut report = SyncPushResponse::default();
+    let mut tx = pool.begin().await?;
+    for change in &payload.progress {
+        let resolution = push_progress(user_id, change, &mut tx).await?;
+        report.record(&resolution);
+    }
+    for change in &payload.notes {
+        let resolution = push_note(user_id, change, &mut tx).await?;
+        report.record(&resolution);
+    }
+    if let Some(change) = &payload.settings {
+        let resolution = push_settings(user_id, change, &mut tx).await?;
+        report.record(&resolution);
+    }
+    tx.commit().await?;
+
+    Ok(report)
+}
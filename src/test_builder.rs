@@ -0,0 +1,159 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+use crate::hsk;
+use crate::models::{Test, TestItemPayload, TestPayload};
+
+/// Максимум вопросов в одном пакете.
+pub const MAX_BATCH_ITEMS: usize = 100;
+/// Минимум вариантов ответа у вопроса с выбором.
+pub const MIN_OPTIONS: usize = 2;
+
+/// Проверяет название теста.
+pub fn validate_test(payload: &TestPayload) -> Result<(), AppError> {
+    if payload.name.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Название теста не может быть пустым"));
+    }
+    Ok(())
+}
+
+/// Проверяет вопрос: текст, ответ и то, что среди вариантов есть правильный.
+pub fn validate_item(item: &TestItemPayload) -> Result<(), AppError> {
+    if item.question.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Текст вопроса не может быть пустым"));
+    }
+    if item.correct_answer.trim().is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Правильный ответ не может быть пустым"));
+    }
+    if let Some(options) = &item.options {
+        if options.len() < MIN_OPTIONS {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("У вопроса должно быть не меньше {} вариантов ответа", MIN_OPTIONS),
+            ));
+        }
+        if options.iter().any(|option| option.trim().is_empty()) {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Варианты ответа не могут быть пустыми"));
+        }
+        for (i, option) in options.iter().enumerate() {
+            if options[..i].contains(option) {
+                return Err(AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Вариант «{}» повторяется", option),
+                ));
+            }
+        }
+        // Ответы сравниваются с правильным дословно, поэтому и здесь без нормализации
+        if !options.contains(&item.correct_answer) {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Среди вариантов нет правильного ответа"));
+        }
+    }
+    if let Some(level) = item.hsk_level {
+        hsk::ensure_level(level)?;
+    }
+    Ok(())
+}
+
+/// Проверяет пакет целиком; ошибка указывает номер вопроса, начиная с единицы.
+pub fn validate_items(items: &[TestItemPayload]) -> Result<(), AppError> {
+    if items.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Нет вопросов для добавления"));
+    }
+    if items.len() > MAX_BATCH_ITEMS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("За раз можно добавить не больше {} вопросов", MAX_BATCH_ITEMS),
+        ));
+    }
+    for (i, item) in items.iter().enumerate() {
+        validate_item(item)
+            .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, &format!("Вопрос {}: {}", i + 1, e.message())))?;
+    }
+    Ok(())
+}
+
+/// Создает общий тест.
+pub async fn create(payload: &TestPayload, pool: &PgPool) -> Result<Test, AppError> {
+    validate_test(payload)?;
+    let test = sqlx::query_as::<_, Test>(
+        "INSERT INTO tests (name, description) VALUES ($1, $2) RETURNING *",
+    )
+        .bind(payload.name.trim())
+        .bind(&payload.description)
+        .fetch_one(pool)
+        .await?;
+    Ok(test)
+}
+
+/// Меняет название и описание теста.
+pub async fn update(test_id: i32, payload: &TestPayload, pool: &PgPool) -> Result<Test, AppError> {
+    validate_test(payload)?;
+    let test = sqlx::query_as::<_, Test>(
+        "UPDATE tests SET name = $2, description = $3 WHERE id = $1 RETURNING *",
+    )
+        .bind(test_id)
+        .bind(payload.name.trim())
+        .bind(&payload.description)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Тест не найден"))?;
+    Ok(test)
+}
+
+/// Удаляет тест вместе с вопросами.
+pub async fn delete(test_id: i32, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM test_items WHERE test_id = $1")
+        .bind(test_id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM tests WHERE id = $1")
+        .bind(test_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Тест не найден"));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Добавляет вопросы в тест: либо все, либо ни одного.
+pub async fn add_items(test_id: i32, items: &[TestItemPayload], pool: &PgPool) -> Result<(), AppError> {
+    validate_items(items)?;
+
+    let generated: Option<bool> = sqlx::query_scalar("SELECT generated_for IS NOT NULL FROM tests WHERE id = $1")
+        .bind(test_id)
+        .fetch_optional(pool)
+        .await?;
+    match generated {
+        None => return Err(AppError::new(StatusCode::NOT_FOUND, "Тест не найден")),
+        // Такие тесты собираются из банка и принадлежат одному пользователю
+        Some(true) => {
+            return Err(AppError::new(StatusCode::CONFLICT, "В тест, собранный по шаблону, нельзя добавлять вопросы"));
+        }
+        Some(false) => {}
+    }
+
+    let mut tx = pool.begin().await?;
+    for item in items {
+        sqlx::query(
+            "INSERT INTO test_items (test_id, question, options, correct_answer, skill, hsk_level, grammar_rule_id,
+                                     explanation)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+            .bind(test_id)
+            .bind(item.question.trim())
+            .bind(item.options.as_ref().map(|options| serde_json::json!(options)))
+            .bind(&item.correct_answer)
+            .bind(item.skill)
+            .bind(item.hsk_level)
+            .bind(item.grammar_rule_id)
+            .bind(item.explanation.as_deref().map(str::trim).filter(|explanation| !explanation.is_empty()))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
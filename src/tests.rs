@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use crate::app;
+    use crate::app::app;
     use crate::auth;
     use crate::models::{RegisterPayload, LoginPayload, AuthResponse, CreateHieroglyphPayload};
     use crate::AppState;
@@ -23,6 +23,49 @@ mod tests {
             .expect("Не удалось подключиться к тестовой базе данных")
     }
 
+    /// Создает пользователя с ролью `role` и паролем `password` и возвращает его access-токен.
+    async fn create_user_and_login(app: &axum::Router, pool: &PgPool, nickname: &str, role: &str) -> String {
+        sqlx::query("INSERT INTO users (nickname, password_hash, role) VALUES ($1, $2, $3::user_role_enum)")
+            .bind(nickname)
+            .bind(auth::hash_password("password").unwrap())
+            .bind(role)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let tokens: AuthResponse = serde_json::from_slice(
+            &app.clone().oneshot(Request::builder()
+                .method(Method::POST)
+                .uri("/api/login")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&LoginPayload { nickname: nickname.to_string(), password: "password".to_string(), device_id: None }).unwrap()))
+                .unwrap()
+            ).await.unwrap().into_body().collect().await.unwrap().to_bytes()
+        ).unwrap();
+        tokens.access_token
+    }
+
+    /// Отправляет запрос с токеном и JSON-телом и возвращает статус и JSON ответа (`null`, если тела нет).
+    async fn request_json(
+        app: &axum::Router,
+        method: Method,
+        uri: &str,
+        token: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+    }
+
     #[tokio::test]
     async fn test_register_and_login() {
         let pool = setup_test_pool().await;
@@ -33,7 +76,9 @@ mod tests {
         // 1. Тест успешной регистрации
         let register_payload = RegisterPayload {
             nickname: nickname.clone(),
-            password: "testpassword".to_string(),
+            password: "test-password-42".to_string(),
+            invite_code: None,
+            email: None,
         };
 
         let request = Request::builder()
@@ -45,6 +90,10 @@ mod tests {
 
         let response = app.clone().oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let message: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(message["message"].is_string());
 
         // 2. Тест регистрации с существующим никнеймом
         let request = Request::builder()
@@ -61,7 +110,8 @@ mod tests {
         // 3. Тест успешного логина
         let login_payload = LoginPayload {
             nickname: nickname.clone(),
-            password: "testpassword".to_string(),
+            password: "test-password-42".to_string(),
+            device_id: None,
         };
 
         let request = Request::builder()
@@ -102,6 +152,7 @@ mod tests {
         let login_payload = LoginPayload {
             nickname: nickname.clone(),
             password: "password".to_string(),
+            device_id: None,
         };
         let request = Request::builder()
             .method(Method::POST)
@@ -163,7 +214,7 @@ mod tests {
                 .method(Method::POST)
                 .uri("/api/login")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&LoginPayload { nickname: admin_nick.clone(), password: "password".to_string() }).unwrap()))
+                .body(Body::from(serde_json::to_string(&LoginPayload { nickname: admin_nick.clone(), password: "password".to_string(), device_id: None }).unwrap()))
                 .unwrap()
             ).await.unwrap().into_body().collect().await.unwrap().to_bytes()
         ).unwrap();
@@ -174,7 +225,7 @@ mod tests {
                 .method(Method::POST)
                 .uri("/api/login")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&LoginPayload { nickname: user_nick.clone(), password: "password".to_string() }).unwrap()))
+                .body(Body::from(serde_json::to_string(&LoginPayload { nickname: user_nick.clone(), password: "password".to_string(), device_id: None }).unwrap()))
                 .unwrap()
             ).await.unwrap().into_body().collect().await.unwrap().to_bytes()
         ).unwrap();
@@ -217,4 +268,2268 @@ mod tests {
             .execute(&pool).await.unwrap();
         sqlx::query("DELETE FROM hieroglyphs WHERE character = '测'").execute(&pool).await.unwrap();
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_banned_user_is_rejected() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let nickname = "test_banned_user".to_string();
+
+        sqlx::query("INSERT INTO users (nickname, password_hash, role) VALUES ($1, $2, 'user')")
+            .bind(nickname.clone())
+            .bind(auth::hash_password("password").unwrap())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let tokens: AuthResponse = serde_json::from_slice(
+            &app.clone().oneshot(Request::builder()
+                .method(Method::POST)
+                .uri("/api/login")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&LoginPayload { nickname: nickname.clone(), password: "password".to_string(), device_id: None }).unwrap()))
+                .unwrap()
+            ).await.unwrap().into_body().collect().await.unwrap().to_bytes()
+        ).unwrap();
+
+        // Блокируем пользователя напрямую в БД
+        sqlx::query("UPDATE users SET is_banned = TRUE, disabled_at = NOW() WHERE nickname = $1")
+            .bind(nickname.clone())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 1. Еще действующий access токен больше не принимается
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/protected")
+            .header("Authorization", format!("Bearer {}", tokens.access_token))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["code"], "account_suspended");
+
+        // 2. Повторный логин тоже запрещен
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/login")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&LoginPayload { nickname: nickname.clone(), password: "password".to_string(), device_id: None }).unwrap()))
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Очистка
+        sqlx::query("DELETE FROM users WHERE nickname = $1").bind(nickname).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_shadowing_alignment() {
+        use crate::shadowing::{align, fluency_score, AlignmentStatus};
+
+        // Полное совпадение, пунктуация не учитывается
+        let alignment = align("你好。", "你好");
+        assert!(alignment.iter().all(|c| c.status == AlignmentStatus::Matched));
+        assert_eq!(fluency_score(&alignment), 100.0);
+
+        // Пропущенный символ
+        let alignment = align("我很好", "我好");
+        assert_eq!(alignment.iter().filter(|c| c.status == AlignmentStatus::Missed).count(), 1);
+        assert!(fluency_score(&alignment) < 100.0);
+
+        // Пустая расшифровка
+        assert_eq!(fluency_score(&align("你好", "")), 0.0);
+    }
+
+    #[test]
+    fn test_study_plan_pace() {
+        use crate::models::StudyPlanStatus;
+        use crate::plans::compute_progress;
+        use chrono::NaiveDate;
+
+        let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let target = NaiveDate::from_ymd_opt(2026, 1, 11).unwrap();
+
+        // Идем по графику: за 5 дней выучено 50 из 100
+        let progress = compute_progress(100, 50, start, target, NaiveDate::from_ymd_opt(2026, 1, 6).unwrap());
+        assert_eq!(progress.status, StudyPlanStatus::OnSchedule);
+        assert_eq!(progress.daily_pace, 9);
+
+        // Пропущенные дни увеличивают темп на оставшийся срок
+        let progress = compute_progress(100, 100, start, target, NaiveDate::from_ymd_opt(2026, 1, 6).unwrap());
+        assert_eq!(progress.status, StudyPlanStatus::Behind);
+        assert_eq!(progress.daily_pace, 17);
+
+        let progress = compute_progress(100, 0, start, target, target);
+        assert_eq!(progress.status, StudyPlanStatus::Completed);
+    }
+
+    #[test]
+    fn test_sentence_segmentation_and_hsk_estimate() {
+        use crate::difficulty::{estimate_hsk_level, segment};
+        use std::collections::HashSet;
+
+        let dictionary: HashSet<String> = ["我们", "学习", "中文"].iter().map(|w| w.to_string()).collect();
+        assert_eq!(segment("我们学习中文吧", &dictionary), vec!["我们", "学习", "中文", "吧"]);
+
+        // Одно редкое слово из десяти не поднимает уровень
+        let mut levels = vec![Some(1); 9];
+        levels.push(Some(6));
+        assert_eq!(estimate_hsk_level(&levels), Some(1));
+
+        // Слишком много слов вне HSK
+        assert_eq!(estimate_hsk_level(&[Some(1), None]), None);
+        assert_eq!(estimate_hsk_level(&[]), None);
+    }
+
+    #[test]
+    fn test_sm2_schedule() {
+        use crate::models::ReviewState;
+        use crate::srs::schedule;
+        use chrono::Utc;
+
+        let now = Utc::now();
+        let new = ReviewState { ease_factor: 2.5, interval_days: 0, repetitions: 0, next_review_at: None };
+
+        // Первые успешные повторения: 1 день, затем 6 дней, затем интервал * коэффициент
+        let first = schedule(&new, 5, now);
+        assert_eq!(first.interval_days, 1);
+        let second = schedule(&first, 5, now);
+        assert_eq!(second.interval_days, 6);
+        let third = schedule(&second, 4, now);
+        assert_eq!(third.interval_days, (6.0 * second.ease_factor).round() as i32);
+
+        // Провал сбрасывает повторения, коэффициент не опускается ниже 1.3
+        let failed = schedule(&third, 0, now);
+        assert_eq!(failed.repetitions, 0);
+        assert_eq!(failed.interval_days, 1);
+        let mut state = failed;
+        for _ in 0..10 {
+            state = schedule(&state, 0, now);
+        }
+        assert!(state.ease_factor >= 1.3);
+    }
+
+    #[test]
+    fn test_sync_progress_merge() {
+        use crate::models::{ContentType, ProgressChange, SyncProgress};
+        use crate::sync::{resolve_progress, Resolution};
+        use chrono::{Duration, Utc};
+
+        let now = Utc::now();
+        let server = SyncProgress {
+            content_type: ContentType::Word,
+            content_id: 1,
+            is_learned: true,
+            learned_at: Some(now - Duration::days(3)),
+            ease_factor: 2.6,
+            interval_days: 6,
+            repetitions: 2,
+            next_review_at: Some(now + Duration::days(6)),
+            version: 10,
+            updated_at: now - Duration::hours(1),
+        };
+        let change = ProgressChange {
+            content_type: ContentType::Word,
+            content_id: 1,
+            is_learned: false,
+            learned_at: None,
+            ease_factor: 2.5,
+            interval_days: 1,
+            repetitions: 1,
+            next_review_at: Some(now + Duration::days(1)),
+            base_version: Some(10),
+            changed_at: now,
+        };
+
+        // Клиент видел актуальную версию: изменение применяется как есть
+        assert_eq!(resolve_progress(Some(&server), &change), Resolution::Apply(change.clone()));
+
+        // Конфликт, клиент новее: расписание клиента, но отметка о выучивании сохраняется
+        let stale = ProgressChange { base_version: Some(7), ..change.clone() };
+        match resolve_progress(Some(&server), &stale) {
+            Resolution::Merge(merged) => {
+                assert!(merged.is_learned);
+                assert_eq!(merged.learned_at, server.learned_at);
+                assert_eq!(merged.interval_days, 1);
+            }
+            other => panic!("ожидалось объединение, получено {:?}", other),
+        }
+
+        // Конфликт, сервер новее и уже содержит все: изменение отбрасывается
+        let old = ProgressChange { changed_at: now - Duration::days(1), ..stale };
+        assert_eq!(resolve_progress(Some(&server), &old), Resolution::Keep);
+    }
+
+    #[test]
+    fn test_paginated_envelope() {
+        use crate::models::{Paginated, Pagination};
+
+        let pagination = Pagination { page: 3, per_page: 20 };
+        assert_eq!(pagination.offset(), 40);
+
+        let page = Paginated::new(vec![1, 2, 3], pagination, 43);
+        assert_eq!(page.total_pages, 3);
+        assert_eq!(page.total_count, 43);
+
+        let empty = Paginated::<i32>::new(Vec::new(), Pagination { page: 1, per_page: 50 }, 0);
+        assert_eq!(empty.total_pages, 0);
+    }
+
+    #[test]
+    fn test_search_groups_by_best_rank() {
+        use crate::models::{ContentType, SearchHit};
+        use crate::search::group_hits;
+
+        let hit = |content_type: ContentType, content_id: i32, rank: f32| SearchHit {
+            content_type,
+            content_id,
+            text: String::new(),
+            pinyin: String::new(),
+            translation: String::new(),
+            rank,
+        };
+        let groups = group_hits(vec![
+            hit(ContentType::Hieroglyph, 1, 0.4),
+            hit(ContentType::Word, 2, 0.95),
+            hit(ContentType::Hieroglyph, 3, 0.7),
+            hit(ContentType::Word, 4, 0.5),
+        ]);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].content_type, ContentType::Word);
+        assert_eq!(groups[0].items.iter().map(|h| h.content_id).collect::<Vec<_>>(), vec![2, 4]);
+        assert_eq!(groups[1].items.iter().map(|h| h.content_id).collect::<Vec<_>>(), vec![3, 1]);
+    }
+
+    #[test]
+    fn test_hsk_level_progress_percent() {
+        use crate::models::HskLevelProgress;
+
+        let progress = HskLevelProgress::new(1, 150, 50, 150, 49);
+        assert_eq!(progress.percent, 33.0);
+
+        let empty = HskLevelProgress::new(7, 0, 0, 0, 0);
+        assert_eq!(empty.percent, 0.0);
+    }
+
+    #[test]
+    fn test_cedict_parse_and_tone_marks() {
+        use crate::cedict::{parse_line, to_tone_marks};
+
+        let entry = parse_line("綠茶 绿茶 [lu:4 cha2] /green tea/").unwrap();
+        assert_eq!(entry.simplified, "绿茶");
+        assert_eq!(entry.glosses, vec!["green tea".to_string()]);
+        assert_eq!(to_tone_marks(&entry.pinyin), "lǜ chá");
+
+        assert_eq!(to_tone_marks("xiao3 gou3 zi5"), "xiǎo gǒu zi");
+        assert_eq!(to_tone_marks("liu2 gui4 you3"), "liú guì yǒu");
+        assert!(parse_line("# CC-CEDICT").is_none());
+        assert!(parse_line("broken line").is_none());
+    }
+
+    #[test]
+    fn test_selfcheck_jwt_secret_and_clock_skew() {
+        use crate::doctor::{check_clock_skew, check_jwt_secret};
+        use crate::models::SelfCheckStatus;
+        use chrono::{Duration, Utc};
+
+        assert_eq!(check_jwt_secret(None).status, SelfCheckStatus::Failed);
+        assert_eq!(check_jwt_secret(Some("changeme")).status, SelfCheckStatus::Failed);
+        assert_eq!(check_jwt_secret(Some("short-but-random")).status, SelfCheckStatus::Warning);
+        assert_eq!(check_jwt_secret(Some("3f9c1a7e5b2d48c6a0e1f7b9d3c5a8e2")).status, SelfCheckStatus::Ok);
+
+        let now = Utc::now();
+        assert_eq!(check_clock_skew(now, now - Duration::seconds(1)).status, SelfCheckStatus::Ok);
+        assert_eq!(check_clock_skew(now, now + Duration::seconds(10)).status, SelfCheckStatus::Warning);
+        assert_eq!(check_clock_skew(now, now - Duration::minutes(5)).status, SelfCheckStatus::Failed);
+    }
+
+    #[test]
+    fn test_is_hanzi() {
+        use crate::hieroglyph_csv::is_hanzi;
+
+        assert!(is_hanzi('好'));
+        assert!(is_hanzi('㐀'));
+        assert!(!is_hanzi('a'));
+        assert!(!is_hanzi('。'));
+    }
+
+    #[test]
+    fn test_blueprint_validation() {
+        use crate::blueprints::validate;
+        use crate::models::{BlueprintPayload, BlueprintSection, QuestionSkill};
+
+        let section = |count, level| BlueprintSection {
+            question_count: count,
+            skill: Some(QuestionSkill::Listening),
+            hsk_level: level,
+            grammar_rule_id: None,
+        };
+        let payload = |sections| BlueprintPayload { name: "HSK 2".to_string(), description: None, sections };
+
+        assert!(validate(&payload(vec![section(10, Some(2)), section(10, Some(2))])).is_ok());
+        assert!(validate(&payload(vec![])).is_err());
+        assert!(validate(&payload(vec![section(0, None)])).is_err());
+        assert!(validate(&payload(vec![section(5, Some(12))])).is_err());
+        assert!(validate(&payload(vec![section(60, None), section(60, None)])).is_err());
+    }
+
+    #[test]
+    fn test_anki_deck_and_tsv() {
+        use crate::anki::{deck_name, parse_deck, to_tsv, AnkiCard};
+
+        assert_eq!(parse_deck(None).unwrap(), None);
+        assert_eq!(parse_deck(Some("all")).unwrap(), None);
+        assert_eq!(parse_deck(Some("HSK2")).unwrap(), Some(2));
+        assert!(parse_deck(Some("hsk10")).is_err());
+        assert!(parse_deck(Some("level1")).is_err());
+
+        let cards = vec![AnkiCard {
+            character: "好".to_string(),
+            pinyin: "hǎo".to_string(),
+            translation: "хороший\tдобрый".to_string(),
+            example: Some("你好 <привет>".to_string()),
+            hsk_level: Some(1),
+            is_learned: true,
+        }];
+        let tsv = to_tsv(&deck_name(Some(1)), &cards);
+        assert!(tsv.starts_with("#separator:tab\n"));
+        assert!(tsv.contains("#deck:Mandarin::HSK 1\n"));
+        assert!(tsv.contains("好\thǎo<br>хороший добрый<br><br>你好 &lt;привет&gt;\tmandarin hsk1 learned\n"));
+    }
+
+    #[test]
+    fn test_composition_answer() {
+        use crate::compose::is_correct;
+
+        assert!(is_correct("你好", "你好"));
+        assert!(is_correct("你好", "你 好"));
+        assert!(!is_correct("你好", "好你"));
+        assert!(!is_correct("你好", "你"));
+    }
+
+    #[test]
+    fn test_discussion_body_validation() {
+        use crate::discussion::{validate_body, MAX_BODY_LEN};
+
+        assert_eq!(validate_body("  Почему 了 здесь?  ").unwrap(), "Почему 了 здесь?");
+        assert!(validate_body("   ").is_err());
+        assert!(validate_body(&"字".repeat(MAX_BODY_LEN)).is_ok());
+        assert!(validate_body(&"字".repeat(MAX_BODY_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_strokes_to_screen_coordinates() {
+        use crate::strokes::to_screen;
+
+        assert_eq!(to_screen("M 100 800 L 200 850 Z"), "M 100 100 L 200 50 Z");
+        assert_eq!(to_screen("M 10,900 Q 20 880 30 -100"), "M 10 0 Q 20 20 30 1000");
+        assert_eq!(to_screen("M 1.5 899.5"), "M 1.5 0.5");
+    }
+
+    #[test]
+    fn test_audio_range_parsing() {
+        use crate::audio::{parse_range, ByteRange};
+
+        assert_eq!(parse_range(None, 1000), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-99"), 1000), ByteRange::Partial(0, 99));
+        assert_eq!(parse_range(Some("bytes=900-"), 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range(Some("bytes=-100"), 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range(Some("bytes=500-5000"), 1000), ByteRange::Partial(500, 999));
+        assert_eq!(parse_range(Some("bytes=1000-"), 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=20-10"), 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        use crate::audio::hmac_sha256;
+
+        // RFC 4231, тестовый случай 2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_content_assist_pinyin() {
+        use crate::assist::combine_pinyin;
+        use crate::models::LookupEntry;
+
+        let entry = |text: &str, pinyin: &str| LookupEntry {
+            text: text.to_string(),
+            pinyin: pinyin.to_string(),
+            translation: String::new(),
+        };
+
+        assert_eq!(
+            combine_pinyin(&[entry("我们", "wǒmen"), entry("学习", " xuéxí ")]),
+            Some("wǒmen xuéxí".to_string())
+        );
+        // Слово без пиньиня: склейка была бы неполной
+        assert_eq!(combine_pinyin(&[entry("我们", "wǒmen"), entry("龘", "")]), None);
+        assert_eq!(combine_pinyin(&[]), None);
+    }
+
+    #[test]
+    fn test_builder_item_validation() {
+        use crate::models::TestItemPayload;
+        use crate::test_builder::{validate_item, validate_items, MAX_BATCH_ITEMS};
+
+        let item = |options: Option<Vec<&str>>, correct: &str| TestItemPayload {
+            question: "Как читается 好?".to_string(),
+            options: options.map(|o| o.into_iter().map(String::from).collect()),
+            correct_answer: correct.to_string(),
+            skill: None,
+            hsk_level: None,
+            grammar_rule_id: None,
+            explanation: None,
+        };
+
+        assert!(validate_item(&item(Some(vec!["hǎo", "hào", "háo"]), "hǎo")).is_ok());
+        assert!(validate_item(&item(None, "hǎo")).is_ok());
+        assert!(validate_item(&item(Some(vec!["hào", "háo"]), "hǎo")).is_err());
+        assert!(validate_item(&item(Some(vec!["hǎo"]), "hǎo")).is_err());
+        assert!(validate_item(&item(Some(vec!["hǎo", "hǎo"]), "hǎo")).is_err());
+        assert!(validate_item(&item(Some(vec!["hǎo", " "]), "hǎo")).is_err());
+        assert!(validate_item(&item(None, "  ")).is_err());
+
+        // Ошибка в пакете указывает номер вопроса
+        let batch = vec![item(None, "hǎo"), item(Some(vec!["a", "b"]), "c")];
+        assert!(validate_items(&batch).unwrap_err().message().starts_with("Вопрос 2:"));
+        assert!(validate_items(&[]).is_err());
+        assert!(validate_items(&vec![item(None, "hǎo"); MAX_BATCH_ITEMS + 1]).is_err());
+    }
+
+    #[test]
+    fn test_caching_validators() {
+        use crate::caching::{etag, http_date, not_modified_since};
+        use axum::http::{header, HeaderMap, HeaderValue};
+        use chrono::{TimeZone, Utc};
+
+        let modified = Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap();
+        assert_eq!(http_date(modified), "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        let mut headers = HeaderMap::new();
+        assert!(!not_modified_since(&headers, modified));
+        headers.insert(header::IF_MODIFIED_SINCE, HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(not_modified_since(&headers, modified));
+        assert!(!not_modified_since(&headers, modified + chrono::Duration::seconds(1)));
+        headers.insert(header::IF_MODIFIED_SINCE, HeaderValue::from_static("вчера"));
+        assert!(!not_modified_since(&headers, modified));
+
+        assert_eq!(etag(b"{}"), etag(b"{}"));
+        assert_ne!(etag(b"{}"), etag(b"[]"));
+        assert!(etag(b"{}").starts_with('"') && etag(b"{}").ends_with('"'));
+    }
+
+    #[test]
+    fn test_seq_scan_guardrail() {
+        use crate::doctor::{seq_scans_dominate, ScanStats, SEQ_SCAN_MIN_ROWS};
+
+        let stats = |seq_scan, idx_scan, live_rows| ScanStats {
+            table_name: "user_progress".to_string(),
+            seq_scan,
+            idx_scan,
+            live_rows,
+        };
+
+        assert!(seq_scans_dominate(&stats(900, 100, SEQ_SCAN_MIN_ROWS)));
+        assert!(!seq_scans_dominate(&stats(100, 900, SEQ_SCAN_MIN_ROWS)));
+        // Маленькие таблицы выгоднее читать целиком
+        assert!(!seq_scans_dominate(&stats(900, 100, SEQ_SCAN_MIN_ROWS - 1)));
+        assert!(!seq_scans_dominate(&stats(0, 0, SEQ_SCAN_MIN_ROWS)));
+    }
+
+    #[test]
+    fn test_contains_pattern_escaping() {
+        use crate::search::contains_pattern;
+
+        assert_eq!(contains_pattern("好"), "%好%");
+        assert_eq!(contains_pattern("50%_a\\b"), "%50\\%\\_a\\\\b%");
+        assert_eq!(contains_pattern(""), "%%");
+    }
+
+    #[test]
+    fn test_event_sink_and_envelope() {
+        use crate::config::{parse_event_sink, EventSink};
+        use crate::events::{Envelope, Event};
+        use crate::models::ContentType;
+        use chrono::{TimeZone, Utc};
+        use std::path::PathBuf;
+
+        assert_eq!(
+            parse_event_sink("file:/var/log/events.jsonl"),
+            Some(EventSink::File(PathBuf::from("/var/log/events.jsonl")))
+        );
+        assert_eq!(
+            parse_event_sink(" https://example.com/hook "),
+            Some(EventSink::Webhook("https://example.com/hook".to_string()))
+        );
+        assert_eq!(parse_event_sink("file:"), None);
+        assert_eq!(parse_event_sink("syslog"), None);
+
+        // Во внешний приемник уходит плоский объект с типом события
+        let envelope = Envelope {
+            at: Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap(),
+            event: Event::Learned { user_id: 7, content_type: ContentType::Word, content_id: 42 },
+        };
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["type"], "learned");
+        assert_eq!(json["user_id"], 7);
+        assert_eq!(json["content_id"], 42);
+        assert_eq!(json["at"], "2026-10-17T12:00:00Z");
+    }
+
+    #[test]
+    fn test_quiz_mix_split() {
+        use crate::models::{ContentType, GenerateQuizPayload, QuizMixEntry};
+        use crate::quiz::{split, validate, DEFAULT_SIZE, MAX_SIZE};
+
+        let entry = |content_type, weight| QuizMixEntry { content_type, weight };
+
+        // Доли всегда складываются в размер теста
+        let mix = vec![entry(ContentType::Hieroglyph, 1), entry(ContentType::Word, 1), entry(ContentType::Phrase, 1)];
+        assert_eq!(split(10, &mix), vec![4, 3, 3]);
+        let mix = vec![entry(ContentType::Word, 3), entry(ContentType::Sentence, 1)];
+        assert_eq!(split(10, &mix), vec![8, 2]);
+        let mix = vec![entry(ContentType::Word, 0), entry(ContentType::Phrase, 2)];
+        assert_eq!(split(5, &mix), vec![0, 5]);
+
+        let (size, mix) = validate(&GenerateQuizPayload { size: None, mix: None }).unwrap();
+        assert_eq!(size, DEFAULT_SIZE);
+        assert_eq!(mix.len(), 3);
+
+        assert!(validate(&GenerateQuizPayload { size: Some(0), mix: None }).is_err());
+        assert!(validate(&GenerateQuizPayload { size: Some(MAX_SIZE + 1), mix: None }).is_err());
+        // По грамматике и урокам вопросы на перевод не строятся
+        let grammar = GenerateQuizPayload { size: Some(5), mix: Some(vec![entry(ContentType::GrammarRule, 1)]) };
+        assert!(validate(&grammar).is_err());
+        let twice = GenerateQuizPayload {
+            size: Some(5),
+            mix: Some(vec![entry(ContentType::Word, 1), entry(ContentType::Word, 2)]),
+        };
+        assert!(validate(&twice).is_err());
+        let empty = GenerateQuizPayload { size: Some(5), mix: Some(vec![entry(ContentType::Word, 0)]) };
+        assert!(validate(&empty).is_err());
+    }
+
+    #[test]
+    fn test_not_hidden_condition() {
+        use crate::hidden::not_hidden;
+
+        let condition = not_hidden("$1", "'word'", "w.id");
+        assert!(condition.starts_with("NOT EXISTS"));
+        assert!(condition.contains("hi.user_id = $1"));
+        assert!(condition.contains("hi.content_type = 'word'"));
+        assert!(condition.contains("hi.content_id = w.id"));
+    }
+
+    #[test]
+    fn test_achievement_criteria_parsing() {
+        use crate::achievements::{Criteria, TimeWindow};
+        use crate::models::ContentType;
+
+        let criteria: Criteria = serde_json::from_value(serde_json::json!(
+            {"type": "learned_count", "content_type": "hieroglyph", "count": 50}
+        )).unwrap();
+        assert!(matches!(
+            criteria,
+            Criteria::LearnedCount { content_type: Some(ContentType::Hieroglyph), count: 50, window: None }
+        ));
+
+        let criteria: Criteria = serde_json::from_value(serde_json::json!({
+            "type": "any",
+            "conditions": [
+                {"type": "reviews_count", "count": 100},
+                {"type": "learned_count", "count": 10, "window": {"kind": "last_days", "days": 7}}
+            ]
+        })).unwrap();
+        let Criteria::Any { conditions } = criteria else { panic!("ожидалось условие any") };
+        assert!(matches!(conditions[0], Criteria::ReviewsCount { content_type: None, count: 100 }));
+        assert!(matches!(
+            conditions[1],
+            Criteria::LearnedCount { window: Some(TimeWindow::LastDays { days: 7 }), .. }
+        ));
+
+        assert!(serde_json::from_value::<Criteria>(serde_json::json!({"type": "unknown"})).is_err());
+    }
+
+    #[test]
+    fn test_distractor_ranking() {
+        use crate::distractors::{pick, pinyin_similarity, plausibility, Candidate};
+
+        let candidate = |text: &str, pinyin: &str, radical: Option<i16>, level: Option<i16>| Candidate {
+            text: text.to_string(),
+            pinyin_plain: pinyin.to_string(),
+            radical_index: radical,
+            hsk_level: level,
+        };
+
+        assert_eq!(pinyin_similarity("ma", "ma"), 3);
+        assert_eq!(pinyin_similarity("ma", "mao"), 2);
+        assert_eq!(pinyin_similarity("zhong", "zhuang"), 1);
+        assert_eq!(pinyin_similarity("ma", "ni"), 0);
+        assert_eq!(pinyin_similarity("", ""), 0);
+
+        // 妈: ключ 38 (女), HSK 1
+        let target = candidate("мама", "ma", Some(38), Some(1));
+        let same_radical = candidate("сестра", "jie", Some(38), Some(3));
+        let homophone = candidate("лошадь", "ma", Some(187), Some(3));
+        let same_level = candidate("ты", "ni", Some(9), Some(1));
+        let unrelated = candidate("дракон", "long", Some(212), Some(5));
+        assert!(plausibility(&target, &same_radical) > plausibility(&target, &unrelated));
+        assert!(plausibility(&target, &homophone) > plausibility(&target, &same_level));
+        assert_eq!(plausibility(&target, &unrelated), 0);
+
+        let picked = pick(
+            &target,
+            vec![unrelated.clone(), same_level.clone(), homophone.clone(), same_radical.clone()],
+            2,
+        );
+        assert_eq!(picked.len(), 2);
+        assert!(picked.contains(&"сестра".to_string()));
+        assert!(picked.contains(&"лошадь".to_string()));
+
+        // Правильный ответ и повторы не попадают в варианты
+        let picked = pick(&target, vec![target.clone(), homophone.clone(), homophone.clone(), unrelated.clone()], 3);
+        assert_eq!(picked, vec!["лошадь".to_string(), "дракон".to_string()]);
+    }
+
+    #[test]
+    fn test_board_message_shape() {
+        use crate::classroom::BoardMessage;
+        use crate::events::Event;
+        use crate::models::{BoardEntry, PresenceInfo};
+
+        let student = BoardEntry {
+            presence: PresenceInfo { user_id: 3, nickname: "li".to_string(), online: true, activity: None, since: None },
+            reviews_today: 12,
+            learned_today: 4,
+            tests_today: 1,
+            last_active_at: None,
+        };
+        let message = BoardMessage::Activity {
+            student,
+            event: Event::TestSubmitted { user_id: 3, test_id: 8, result_id: 21, score: 9, total_questions: 10 },
+        };
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "activity");
+        // Присутствие встраивается в строку доски без вложенного объекта
+        assert_eq!(json["student"]["nickname"], "li");
+        assert_eq!(json["student"]["reviews_today"], 12);
+        assert_eq!(json["event"]["type"], "test_submitted");
+        assert_eq!(json["event"]["score"], 9);
+
+        let json = serde_json::to_value(BoardMessage::Snapshot { students: Vec::new() }).unwrap();
+        assert_eq!(json, serde_json::json!({"type": "snapshot", "students": []}));
+    }
+
+    #[test]
+    fn test_streak_summary() {
+        use crate::streak::{summarize, StudyDay};
+        use chrono::NaiveDate;
+
+        let date = |day: u32| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+        let studied = |day: u32| StudyDay { day: date(day), frozen: false };
+        let frozen = |day: u32| StudyDay { day: date(day), frozen: true };
+
+        let empty = summarize(&[], date(17), 0);
+        assert_eq!((empty.current_days, empty.longest_days, empty.studied_today), (0, 0, false));
+
+        // 1–4 подряд, затем 10–12 с заморозкой 11-го: заморозка не удлиняет серию
+        let days = [studied(1), studied(2), studied(3), studied(4), studied(10), frozen(11), studied(12)];
+        let summary = summarize(&days, date(12), 1);
+        assert_eq!(summary.current_days, 2);
+        assert_eq!(summary.longest_days, 4);
+        assert!(summary.studied_today);
+        assert_eq!(summary.last_study_day, Some(date(12)));
+
+        // Вчерашнее занятие держит серию, сегодня еще не занимался
+        let summary = summarize(&days, date(13), 0);
+        assert_eq!(summary.current_days, 2);
+        assert!(!summary.studied_today);
+
+        // Два пропущенных дня: серия жива, только если заморозок хватает
+        assert_eq!(summarize(&days, date(15), 2).current_days, 2);
+        assert_eq!(summarize(&days, date(15), 1).current_days, 0);
+        assert_eq!(summarize(&days, date(15), 1).longest_days, 4);
+    }
+
+    #[test]
+    fn test_stats_history_range() {
+        use crate::snapshots::{history_range, DEFAULT_HISTORY_DAYS, MAX_HISTORY_DAYS};
+        use chrono::{Duration, NaiveDate};
+
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+
+        // По умолчанию — последние 30 дней со снимками, включая вчера
+        let (from, to) = history_range(None, None, today).unwrap();
+        assert_eq!(to, yesterday);
+        assert_eq!((to - from).num_days(), DEFAULT_HISTORY_DAYS - 1);
+
+        let day = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert_eq!(history_range(Some(day), Some(day), today).unwrap(), (day, day));
+        assert!(history_range(Some(yesterday), Some(day), today).is_err());
+        assert!(history_range(Some(day), Some(day + Duration::days(MAX_HISTORY_DAYS)), today).is_err());
+        assert!(history_range(Some(day), Some(day + Duration::days(MAX_HISTORY_DAYS - 1)), today).is_ok());
+    }
+
+    #[test]
+    fn test_level_curve() {
+        use crate::config::LevelCurve;
+        use crate::gamification::{level_cost, level_progress};
+
+        let curve = LevelCurve { base: 100, growth: 1.5 };
+        assert_eq!(level_cost(curve, 1), 100);
+        assert_eq!(level_cost(curve, 2), 150);
+        assert_eq!(level_cost(curve, 3), 225);
+
+        let start = level_progress(0, curve);
+        assert_eq!((start.level, start.xp_into_level, start.xp_for_next_level), (1, 0, 100));
+        assert_eq!(start.progress, 0.0);
+
+        // Ровно на границе — уже следующий уровень
+        assert_eq!(level_progress(99, curve).level, 1);
+        assert_eq!(level_progress(100, curve).level, 2);
+
+        let middle = level_progress(325, curve);
+        assert_eq!((middle.level, middle.xp_into_level, middle.xp_for_next_level), (3, 75, 225));
+        assert!((middle.progress - 1.0 / 3.0).abs() < 1e-9);
+
+        // Без роста все уровни стоят одинаково
+        let flat = LevelCurve { base: 50, growth: 1.0 };
+        assert_eq!(level_progress(1000, flat).level, 21);
+    }
+
+    #[test]
+    fn test_friend_notifications() {
+        use crate::notifications::{NotificationEvent, NotificationKind};
+
+        // Заявки в друзья отключаются вместе с остальными социальными уведомлениями
+        assert_eq!(NotificationKind::FriendRequest.event(), NotificationEvent::Social);
+        assert_eq!(NotificationKind::FriendAccepted.event(), NotificationEvent::Social);
+        assert_eq!(NotificationKind::FriendRequest.as_str(), "friend_request");
+        assert_eq!(NotificationKind::FriendAccepted.as_str(), "friend_accepted");
+    }
+
+    #[test]
+    fn test_email_domain_rules() {
+        use crate::config::EmailDomainRules;
+        use crate::registration::check_email;
+
+        let open = EmailDomainRules::default();
+        assert_eq!(check_email(None, &open).unwrap(), None);
+        assert_eq!(check_email(Some(" Student@Mail.RU "), &open).unwrap().as_deref(), Some("student@mail.ru"));
+        assert!(check_email(Some("student"), &open).is_err());
+        assert!(check_email(Some("student@localhost"), &open).is_err());
+
+        let school = EmailDomainRules {
+            allow: vec!["school.edu".to_string()],
+            deny: vec!["guests.school.edu".to_string()],
+        };
+        assert_eq!(check_email(None, &school).unwrap_err().code(), Some("email_required"));
+        assert!(check_email(Some("pupil@school.edu"), &school).is_ok());
+        assert!(check_email(Some("pupil@class5.school.edu"), &school).is_ok());
+        // Совпадение по концу строки без точки — другой домен
+        assert!(check_email(Some("pupil@myschool.edu"), &school).is_err());
+        assert_eq!(
+            check_email(Some("visitor@guests.school.edu"), &school).unwrap_err().code(),
+            Some("email_domain_not_allowed")
+        );
+    }
+
+    #[test]
+    fn test_smtp_message() {
+        use crate::mailer::{smtp_message, Email};
+
+        let email = Email {
+            to: "student@school.edu".to_string(),
+            subject: "Сброс".to_string(),
+            body: "Код:\n.abc".to_string(),
+        };
+        let message = smtp_message("noreply@school.edu", &email);
+        assert!(message.contains("Subject: =?UTF-8?B?0KHQsdGA0L7RgQ==?=\r\n"));
+        // Строка, начинающаяся с точки, удваивает ее, а письмо заканчивается одиночной точкой
+        assert!(message.ends_with("\r\n\r\nКод:\r\n..abc\r\n.\r\n"));
+    }
+
+    #[test]
+    fn test_reader_timing() {
+        use crate::models::{PassageSentencePayload, TimedSentence};
+        use crate::reader::{sentence_at, validate};
+
+        let segment = |start_ms, end_ms| PassageSentencePayload { sentence_id: 1, start_ms, end_ms };
+        assert!(validate(&[segment(0, 1000), segment(1000, 2500), segment(3000, 4000)]).is_ok());
+        assert!(validate(&[]).is_err());
+        assert_eq!(
+            validate(&[segment(0, 1500), segment(1000, 2000)]).unwrap_err().code(),
+            Some("invalid_timing")
+        );
+        assert!(validate(&[segment(0, 1000), segment(1000, 1000)]).is_err());
+
+        let timed = |start_ms, end_ms| TimedSentence {
+            sentence_id: 1,
+            text: String::new(),
+            pinyin: String::new(),
+            translation: String::new(),
+            start_ms,
+            end_ms,
+        };
+        let sentences = [timed(500, 1000), timed(1000, 2500), timed(3000, 4000)];
+        assert_eq!(sentence_at(&sentences, 0), None);
+        assert_eq!(sentence_at(&sentences, 500), Some(0));
+        assert_eq!(sentence_at(&sentences, 1000), Some(1));
+        // В паузе между предложениями подсвечено прошедшее
+        assert_eq!(sentence_at(&sentences, 2700), Some(1));
+        assert_eq!(sentence_at(&sentences, 9000), Some(2));
+        assert_eq!(sentence_at(&[], 100), None);
+    }
+
+    #[test]
+    fn test_group_days() {
+        use crate::groups::group_days;
+        use crate::streak::{summarize, StudyDay};
+        use chrono::NaiveDate;
+
+        let d = |day| NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+        let studied = |days: &[u32]| days.iter().map(|&day| StudyDay { day: d(day), frozen: false }).collect::<Vec<_>>();
+
+        // Второй участник пропустил 3-е число, третий вступил только 4-го
+        let mut second = studied(&[1, 2, 4, 5]);
+        second.push(StudyDay { day: d(6), frozen: true });
+        second.sort_by_key(|day| day.day);
+        let members = vec![
+            (d(1), studied(&[1, 2, 3, 4, 5, 6])),
+            (d(1), second),
+            (d(4), studied(&[3, 4, 5, 6])),
+        ];
+        let days = group_days(&members);
+        let listed: Vec<(NaiveDate, bool)> = days.iter().map(|day| (day.day, day.frozen)).collect();
+        assert_eq!(listed, vec![(d(1), false), (d(2), false), (d(4), false), (d(5), false), (d(6), true)]);
+
+        // Заморозка одного участника не прерывает серию группы, но и не удлиняет ее
+        let summary = summarize(&days, d(7), 0);
+        assert_eq!(summary.current_days, 2);
+        assert_eq!(summary.longest_days, 2);
+        assert!(!summary.studied_today);
+        assert_eq!(summarize(&days, d(8), 0).current_days, 0);
+    }
+
+    #[test]
+    fn test_cursor_page() {
+        use crate::models::{CursorPage, CursorPagination};
+
+        let pagination = CursorPagination { cursor: None, limit: 3 };
+        assert_eq!(pagination.fetch_limit(), 4);
+
+        // Лишняя строка означает, что есть следующая страница; курсор — последний id страницы
+        let page = CursorPage::new(vec![10, 9, 7, 4], pagination, |id| *id);
+        assert_eq!(page.items, vec![10, 9, 7]);
+        assert_eq!(page.next_cursor.as_deref(), Some("7"));
+
+        let last = CursorPage::new(vec![3, 1], pagination, |id| *id);
+        assert_eq!(last.items, vec![3, 1]);
+        assert_eq!(last.next_cursor, None);
+
+        let exact = CursorPage::new(vec![6, 5, 2], pagination, |id| *id);
+        assert_eq!(exact.next_cursor, None);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        use crate::config::{parse_rate_limit, RateLimit};
+        use crate::rate_limit::MemoryStore;
+        use std::time::{Duration, Instant};
+
+        assert_eq!(parse_rate_limit("5/60"), Some(RateLimit { max: 5, window: Duration::from_secs(60) }));
+        assert_eq!(parse_rate_limit(" 10 / 1 "), Some(RateLimit { max: 10, window: Duration::from_secs(1) }));
+        assert_eq!(parse_rate_limit("off"), None);
+        assert_eq!(parse_rate_limit("0/60"), None);
+        assert_eq!(parse_rate_limit("5"), None);
+
+        let limit = RateLimit { max: 2, window: Duration::from_secs(60) };
+        let keys = |names: &[&str]| names.iter().map(|name| (name.to_string(), limit)).collect::<Vec<_>>();
+        let store = MemoryStore::default();
+        let start = Instant::now();
+        assert_eq!(store.hit_all_at(&keys(&["login:ip:1"]), start), None);
+        assert_eq!(store.hit_all_at(&keys(&["login:ip:1"]), start + Duration::from_secs(10)), None);
+        // Третья попытка ждет, пока из окна не выйдет первая
+        assert_eq!(
+            store.hit_all_at(&keys(&["login:ip:1"]), start + Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(store.hit_all_at(&keys(&["login:ip:2"]), start + Duration::from_secs(20)), None);
+        // Отклоненная попытка не продлевает блокировку
+        assert_eq!(store.hit_all_at(&keys(&["login:ip:1"]), start + Duration::from_secs(60)), None);
+        assert!(store.hit_all_at(&keys(&["login:ip:1"]), start + Duration::from_secs(61)).is_some());
+
+        // Запрос, отклоненный по никнейму, не расходует лимит адреса
+        let store = MemoryStore::default();
+        let login = |ip: &str| keys(&[ip, "login:nickname:victim"]);
+        assert_eq!(store.hit_all_at(&login("login:ip:a"), start), None);
+        assert_eq!(store.hit_all_at(&login("login:ip:b"), start), None);
+        assert!(store.hit_all_at(&login("login:ip:a"), start).is_some());
+        assert!(store.hit_all_at(&login("login:ip:a"), start).is_some());
+        assert_eq!(store.hit_all_at(&keys(&["login:ip:a", "login:nickname:other"]), start), None);
+    }
+
+    #[test]
+    fn test_review_load() {
+        use crate::models::{ForecastDay, ReviewLoadMode};
+        use crate::review_load::{assess, fill_days, validate_limit};
+        use chrono::NaiveDate;
+
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        let days = fill_days(today, 3, &[
+            ForecastDay { day: day(1), due: 40 },
+            ForecastDay { day: day(3), due: 120 },
+            // За пределами прогноза
+            ForecastDay { day: day(5), due: 500 },
+        ]);
+        assert_eq!(days.iter().map(|d| d.due).collect::<Vec<_>>(), vec![40, 0, 120]);
+        assert_eq!(days[1].day, day(2));
+
+        let forecast = assess(days.clone(), 120, ReviewLoadMode::Warn);
+        assert_eq!(forecast.peak, 120);
+        assert!(forecast.overloaded);
+        assert!(!assess(days.clone(), 121, ReviewLoadMode::Block).overloaded);
+        assert!(!assess(days, 10, ReviewLoadMode::Off).overloaded);
+        assert_eq!(assess(Vec::new(), 1, ReviewLoadMode::Warn).peak, 0);
+
+        assert!(validate_limit(1).is_ok());
+        assert!(validate_limit(0).is_err());
+        assert!(validate_limit(crate::review_load::MAX_DAILY_LIMIT + 1).is_err());
+    }
+
+    #[test]
+    fn test_login_lockout() {
+        use crate::lockout::{ensure_unlocked, remaining_minutes};
+        use crate::models::{User, UserRole};
+        use chrono::{Duration, TimeZone, Utc};
+
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        assert_eq!(remaining_minutes(now + Duration::minutes(15), now), 15);
+        assert_eq!(remaining_minutes(now + Duration::seconds(61), now), 2);
+        assert_eq!(remaining_minutes(now + Duration::seconds(5), now), 1);
+
+        let mut user = User {
+            id: 1,
+            nickname: "learner".to_string(),
+            password_hash: String::new(),
+            role: UserRole::User,
+            is_banned: false,
+            disabled_at: None,
+            presence_visible: true,
+            locked_until: None,
+        };
+        assert!(ensure_unlocked(&user, now).is_ok());
+        user.locked_until = Some(now - Duration::minutes(1));
+        assert!(ensure_unlocked(&user, now).is_ok());
+
+        user.locked_until = Some(now + Duration::minutes(10));
+        let error = ensure_unlocked(&user, now).unwrap_err();
+        assert_eq!(error.status_code(), axum::http::StatusCode::LOCKED);
+        assert_eq!(error.code(), Some("account_locked"));
+        assert!(error.message().contains("10 мин"));
+    }
+
+    #[test]
+    fn test_import_preview() {
+        use crate::importer::MAX_PREVIEW_CHANGES;
+        use crate::models::{ImportAction, ImportFormat, ImportPreview};
+
+        let mut preview = ImportPreview::new(ImportFormat::Cedict);
+        preview.push(Some(2), Some("好"), ImportAction::Create, Some("hǎo — good".to_string()));
+        preview.push(Some(3), Some("你"), ImportAction::Unchanged, None);
+        preview.push(Some(4), None, ImportAction::Error, Some("Строка не разобрана".to_string()));
+        assert_eq!((preview.create, preview.unchanged, preview.error), (1, 1, 1));
+        // Неизмененные записи только считаются
+        assert_eq!(preview.changes.len(), 2);
+        assert_eq!(preview.changes[0].key.as_deref(), Some("好"));
+
+        for line in 0..MAX_PREVIEW_CHANGES {
+            preview.push(Some(line), None, ImportAction::Skip, None);
+        }
+        assert_eq!(preview.skip, MAX_PREVIEW_CHANGES);
+        assert_eq!(preview.changes.len(), MAX_PREVIEW_CHANGES);
+        assert!(preview.truncated);
+    }
+
+    #[test]
+    fn test_device_id() {
+        use crate::auth::device_id;
+        use axum::http::{header, HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(device_id(Some("  laptop-1 "), &headers).as_deref(), Some("laptop-1"));
+        assert_eq!(device_id(None, &headers), None);
+
+        // Без идентификатора от клиента сессия привязывается к User-Agent
+        headers.insert(header::USER_AGENT, HeaderValue::from_static("Mozilla/5.0"));
+        let fingerprint = device_id(Some(""), &headers).unwrap();
+        assert!(fingerprint.starts_with("ua:"));
+        assert_eq!(device_id(None, &headers), Some(fingerprint.clone()));
+        assert_eq!(device_id(Some(&"x".repeat(200)), &headers), Some(fingerprint.clone()));
+
+        headers.insert(header::USER_AGENT, HeaderValue::from_static("curl/8.0"));
+        assert_ne!(device_id(None, &headers), Some(fingerprint));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_reuse_revokes_family() {
+        let pool = setup_test_pool().await;
+        let nickname = "test_refresh_reuse".to_string();
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (nickname, password_hash, role) VALUES ($1, $2, 'user') RETURNING id",
+        )
+            .bind(&nickname)
+            .bind(auth::hash_password("password").unwrap())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let first = auth::generate_tokens(&user_id, &auth::NewSession::default(), &pool).await.unwrap();
+        let second = auth::refresh_access_token(&first.refresh_token, auth::ClientInfo::default(), &pool).await.unwrap();
+
+        // Замененный токен предъявлен еще раз: отзывается и тот, что выдан взамен
+        let error = auth::refresh_access_token(&first.refresh_token, auth::ClientInfo::default(), &pool).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::UNAUTHORIZED);
+        assert_eq!(error.code(), Some("refresh_reused"));
+        assert!(auth::refresh_access_token(&second.refresh_token, auth::ClientInfo::default(), &pool).await.is_err());
+
+        let logged: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM security_events WHERE user_id = $1 AND kind = 'refresh_reuse'",
+        )
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, 1);
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sessions_list_and_revoke() {
+        use crate::auth::{ClientInfo, NewSession};
+        use crate::sessions;
+
+        let pool = setup_test_pool().await;
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (nickname, password_hash, role) VALUES ($1, $2, 'user') RETURNING id",
+        )
+            .bind("test_sessions_user")
+            .bind(auth::hash_password("password").unwrap())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let laptop = NewSession {
+            client: ClientInfo { user_agent: Some("laptop".to_string()), ..Default::default() },
+            ..Default::default()
+        };
+        let tokens = auth::generate_tokens(&user_id, &laptop, &pool).await.unwrap();
+        auth::generate_tokens(&user_id, &NewSession::default(), &pool).await.unwrap();
+        // Ротация не добавляет сессий: замененный токен в списке не виден
+        auth::refresh_access_token(&tokens.refresh_token, ClientInfo::default(), &pool).await.unwrap();
+
+        let listed = sessions::list(user_id, None, &pool).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        let laptop_session = listed.iter().find(|s| s.user_agent.as_deref() == Some("laptop")).unwrap();
+        let current = sessions::list(user_id, Some(&laptop_session.id), &pool).await.unwrap();
+        assert_eq!(current.iter().filter(|s| s.current).count(), 1);
+
+        sessions::revoke(user_id, &laptop_session.id, &pool).await.unwrap();
+        assert_eq!(sessions::list(user_id, None, &pool).await.unwrap().len(), 1);
+        let error = sessions::revoke(user_id, &laptop_session.id, &pool).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+
+        sessions::revoke_all(user_id, &pool).await.unwrap();
+        assert!(sessions::list(user_id, None, &pool).await.unwrap().is_empty());
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_consent_exempt_paths() {
+        use crate::consent::is_exempt;
+
+        assert!(is_exempt("/api/consent"));
+        assert!(is_exempt("/api/consent/me"));
+        assert!(is_exempt("/api/me/export"));
+        assert!(is_exempt("/api/password/reset"));
+        assert!(is_exempt("/api/sessions/abc"));
+        // Совпадение только по целому сегменту пути
+        assert!(!is_exempt("/api/messages"));
+        assert!(!is_exempt("/api/review/queue"));
+        assert!(!is_exempt("/api/legalese"));
+    }
+
+    #[test]
+    fn test_password_policy() {
+        use crate::config::PasswordPolicy;
+        use crate::password_policy::{check, character_classes, ensure_valid};
+
+        let policy = PasswordPolicy { min_length: 8, min_classes: 2, reject_common: true };
+        let codes = |password: &str, nickname: Option<&str>| -> Vec<&'static str> {
+            check(password, nickname, &policy).into_iter().map(|error| error.code).collect()
+        };
+
+        assert_eq!(character_classes("abc"), 1);
+        assert_eq!(character_classes("Пароль-1"), 4);
+        assert!(codes("correct-horse-7", Some("li")).is_empty());
+        // Все нарушения возвращаются сразу
+        assert_eq!(codes("abc", None), vec!["too_short", "too_simple"]);
+        assert_eq!(codes("Password1", None), vec!["common"]);
+        assert_eq!(codes("xiaoming-2024", Some("XiaoMing")), vec!["contains_nickname"]);
+        assert_eq!(codes(&"a1".repeat(100), None), vec!["too_long"]);
+
+        let error = ensure_valid("123", None).unwrap_err();
+        assert_eq!(error.code(), Some("weak_password"));
+        assert!(error.fields().iter().all(|field| field.field == "password"));
+    }
+
+    #[test]
+    fn test_mining_sentences_and_cloze() {
+        use crate::mining::{cloze, sentences};
+
+        assert_eq!(
+            sentences("我喜欢喝茶。你呢？\n\n 他不喜欢！还没说完"),
+            vec!["我喜欢喝茶。", "你呢？", "他不喜欢！", "还没说完"]
+        );
+        assert!(sentences("  \n").is_empty());
+
+        assert_eq!(cloze("我喜欢喝茶。", "喜欢"), "我＿＿喝茶。");
+        // Пропуск ставится только вместо первого вхождения
+        assert_eq!(cloze("喜欢就是喜欢", "喜欢"), "＿＿就是喜欢");
+        // Слово, разорванное пробелом, ищется в нормализованном предложении
+        assert_eq!(cloze("我喜 欢茶。", "喜欢"), "我＿＿茶");
+    }
+
+    #[test]
+    fn test_password_hashing_argon2_and_bcrypt() {
+        use crate::config::PasswordHashing;
+        use crate::doctor::check_password_hashing;
+        use crate::models::SelfCheckStatus;
+
+        let hash = auth::hash_password("test-password-42").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(auth::verify_password("test-password-42", &hash).unwrap());
+        assert!(!auth::verify_password("wrong-password", &hash).unwrap());
+        assert!(!auth::needs_rehash(&hash));
+
+        // Старые хеши bcrypt по-прежнему принимаются, но подлежат пересчету
+        let legacy = bcrypt::hash("test-password-42", 4).unwrap();
+        assert!(auth::verify_password("test-password-42", &legacy).unwrap());
+        assert!(!auth::verify_password("wrong-password", &legacy).unwrap());
+        assert!(auth::needs_rehash(&legacy));
+
+        let weak = PasswordHashing { memory_kib: 4096, iterations: 1, parallelism: 1 };
+        assert_eq!(check_password_hashing(weak).status, SelfCheckStatus::Warning);
+        let invalid = PasswordHashing { memory_kib: 1, iterations: 0, parallelism: 1 };
+        assert_eq!(check_password_hashing(invalid).status, SelfCheckStatus::Failed);
+    }
+
+    #[test]
+    fn test_typing_metrics() {
+        use crate::typing::{chars_per_minute, is_correct};
+
+        assert!(is_correct("你好", "你好"));
+        // Пробелы и пунктуация, которые оставляет IME, не считаются ошибкой
+        assert!(is_correct(" 你好。", "你好"));
+        assert!(!is_correct("你", "你好"));
+        assert!(!is_correct("", ""));
+
+        assert_eq!(chars_per_minute(30, 60_000), 30.0);
+        assert_eq!(chars_per_minute(10, 30_000), 20.0);
+        assert_eq!(chars_per_minute(10, 0), 0.0);
+    }
+
+    #[test]
+    fn test_stats_breakdown_grouping() {
+        use crate::models::ContentType;
+        use crate::stats::{group, weak_spots, ItemReviews};
+
+        let item = |level: Option<i16>, tags: &[&str], reviews: i64, passed: i64, retained: bool| ItemReviews {
+            content_type: ContentType::Word,
+            hsk_level: level,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            reviews,
+            passed,
+            retained,
+        };
+        let items = vec![
+            item(Some(3), &["verb"], 20, 8, false),
+            item(Some(3), &["verb", "food"], 10, 6, true),
+            item(Some(3), &["noun"], 30, 27, true),
+            item(Some(1), &["verb"], 25, 25, true),
+            item(None, &[], 4, 4, true),
+        ];
+
+        let by_level = group(&items, |i| vec![i.hsk_level]);
+        let level3 = &by_level.iter().find(|(level, _)| *level == Some(3)).unwrap().1;
+        assert_eq!(level3.items, 3);
+        assert_eq!(level3.reviews, 60);
+        assert_eq!(level3.accuracy, 41.0 / 60.0 * 100.0);
+        assert_eq!(level3.retention, 2.0 / 3.0 * 100.0);
+
+        // Элемент с двумя тегами считается в обеих группах
+        let by_tag = group(&items, |i| i.tags.clone());
+        assert_eq!(by_tag.iter().find(|(tag, _)| tag == "verb").unwrap().1.items, 3);
+        assert_eq!(by_tag.iter().find(|(tag, _)| tag == "food").unwrap().1.items, 1);
+
+        // «food» повторялся слишком мало, чтобы считаться слабым местом
+        let spots = weak_spots(&items);
+        assert_eq!(spots.len(), 3);
+        assert_eq!((spots[0].hsk_level, spots[0].tag.as_str()), (3, "verb"));
+        assert_eq!(spots[0].stats.reviews, 30);
+        assert!(spots.iter().all(|spot| spot.tag != "food"));
+    }
+
+    #[test]
+    fn test_slow_good_review_counts_as_hard() {
+        use crate::srs::{effective_quality, SLOW_ANSWER_MS};
+
+        assert_eq!(effective_quality(4, Some(SLOW_ANSWER_MS)), 4);
+        assert_eq!(effective_quality(4, Some(SLOW_ANSWER_MS + 1)), 3);
+        // Без замера и для других оценок время ответа ни на что не влияет
+        assert_eq!(effective_quality(4, None), 4);
+        assert_eq!(effective_quality(5, Some(SLOW_ANSWER_MS * 2)), 5);
+        assert_eq!(effective_quality(1, Some(SLOW_ANSWER_MS * 2)), 1);
+    }
+
+    #[tokio::test]
+    async fn test_undo_last_review() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let nickname = "test_undo_review";
+        let token = create_user_and_login(&app, &pool, nickname, "user").await;
+
+        let hieroglyph_id: i32 = sqlx::query_scalar(
+            "INSERT INTO hieroglyphs (character, pinyin, translation) VALUES ('撤', 'chè', 'отменять') RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO user_progress (user_id, content_type, content_id, is_learned, next_review_at)
+             SELECT id, 'hieroglyph', $2, TRUE, NOW() FROM users WHERE nickname = $1",
+        )
+            .bind(nickname)
+            .bind(hieroglyph_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let post = |uri: &str, body: serde_json::Value| Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        // Ошибочно поставленное «легко» сдвигает повторение
+        let answer = serde_json::json!({ "content_type": "hieroglyph", "content_id": hieroglyph_id, "quality": 5 });
+        let response = app.clone().oneshot(post("/api/review/answer", answer)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Отмена возвращает расписание до ответа и удаляет запись журнала
+        let response = app.clone().oneshot(post("/api/reviews/undo", serde_json::json!({}))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let undone: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(undone["content_id"], hieroglyph_id);
+        assert_eq!(undone["repetitions"], 0);
+        assert_eq!(undone["interval_days"], 0);
+
+        let (repetitions, logged): (i32, i64) = sqlx::query_as(
+            "SELECT up.repetitions, (SELECT COUNT(*) FROM review_log r WHERE r.user_id = up.user_id)
+             FROM user_progress up JOIN users u ON u.id = up.user_id
+             WHERE u.nickname = $1 AND up.content_id = $2",
+        )
+            .bind(nickname)
+            .bind(hieroglyph_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((repetitions, logged), (0, 0));
+
+        // Больше отменять нечего
+        let response = app.clone().oneshot(post("/api/reviews/undo", serde_json::json!({}))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Очистка
+        sqlx::query("DELETE FROM users WHERE nickname = $1").bind(nickname).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM hieroglyphs WHERE id = $1").bind(hieroglyph_id).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_jwt_key_rotation_and_rs256() {
+        use crate::jwt_keys::{JwtKey, KeySet};
+        use jsonwebtoken::{encode, EncodingKey, Header};
+
+        let claims = serde_json::json!({ "user_id": 7, "exp": chrono::Utc::now().timestamp() + 600 });
+
+        // Токен, подписанный старым ключом, проверяется и после ротации
+        let old = KeySet::new(vec![JwtKey::hs256("2025-01", "old-secret")], None).unwrap();
+        let token = old.sign(&claims).unwrap();
+        let rotated = KeySet::new(
+            vec![JwtKey::hs256("2025-01", "old-secret"), JwtKey::hs256("2026-01", "new-secret")],
+            None,
+        ).unwrap();
+        assert_eq!(rotated.active().kid, "2026-01");
+        let verified: serde_json::Value = rotated.verify(&token).unwrap();
+        assert_eq!(verified["user_id"], 7);
+
+        // Ключ с неизвестным kid отклоняется
+        let retired = KeySet::new(vec![JwtKey::hs256("2026-01", "new-secret")], None).unwrap();
+        assert!(retired.verify::<serde_json::Value>(&token).is_err());
+
+        // Токены без kid, выданные до ротации, проверяются ключом JWT_SECRET
+        let legacy = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"legacy")).unwrap();
+        let with_legacy = KeySet::new(
+            vec![JwtKey::hs256("default", "legacy"), JwtKey::hs256("2026-01", "new-secret")],
+            Some("2026-01"),
+        ).unwrap();
+        assert!(with_legacy.verify::<serde_json::Value>(&legacy).is_ok());
+
+        let public_pem = include_str!("../resources/test_keys/rs256.pub.pem");
+        let private_pem = include_str!("../resources/test_keys/rs256.key.pem");
+        let rsa = KeySet::new(vec![JwtKey::rs256("rsa-1", public_pem, Some(private_pem)).unwrap()], None).unwrap();
+        let token = rsa.sign(&claims).unwrap();
+        assert!(rsa.verify::<serde_json::Value>(&token).is_ok());
+
+        // Другие сервисы проверяют токены только по открытому ключу
+        let verify_only = KeySet::new(
+            vec![JwtKey::rs256("rsa-1", public_pem, None).unwrap(), JwtKey::hs256("2026-01", "new-secret")],
+            None,
+        ).unwrap();
+        assert!(verify_only.verify::<serde_json::Value>(&token).is_ok());
+        let jwks = verify_only.jwks();
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0].kid, "rsa-1");
+        assert_eq!(jwks.keys[0].e, "AQAB");
+
+        // Открытый ключ RS256 нельзя использовать как секрет HS256
+        let mut header = Header::default();
+        header.kid = Some("rsa-1".to_string());
+        let forged = encode(&header, &claims, &EncodingKey::from_secret(public_pem.as_bytes())).unwrap();
+        assert!(rsa.verify::<serde_json::Value>(&forged).is_err());
+
+        // Закрытый ключ без открытого или от другой пары не загружается
+        assert!(JwtKey::rs256("broken", "not a key", Some(private_pem)).is_err());
+        assert!(KeySet::new(vec![JwtKey::hs256("a", "x"), JwtKey::hs256("a", "y")], None).is_err());
+    }
+
+    #[test]
+    fn test_attempt_idle_pause_accounting() {
+        use crate::attempts::{idle_pause, remaining_secs};
+        use crate::models::TestAttempt;
+        use chrono::{Duration, TimeZone, Utc};
+
+        let start = Utc.with_ymd_and_hms(2026, 5, 1, 9, 0, 0).unwrap();
+        let at = |secs: i64| start + Duration::seconds(secs);
+
+        // Затишье короче порога — время на раздумье, не пауза
+        assert_eq!(idle_pause(at(0), at(60), 60, 300), None);
+
+        // Связь пропала на 5 минут: первые 60 секунд идут в зачет, остальное — пауза
+        let pause = idle_pause(at(0), at(360), 60, 600).unwrap();
+        assert_eq!(pause.from, at(60));
+        assert_eq!(pause.idle_secs, 300);
+        assert_eq!(pause.credited_secs, 300);
+
+        // Засчитывается не больше оставшегося запаса, а без запаса — ничего
+        assert_eq!(idle_pause(at(0), at(360), 60, 120).unwrap().credited_secs, 120);
+        assert_eq!(idle_pause(at(0), at(360), 60, -5).unwrap().credited_secs, 0);
+
+        let attempt = TestAttempt {
+            id: 1,
+            user_id: 1,
+            test_id: 1,
+            time_limit_secs: 600,
+            pause_allowance_secs: 300,
+            paused_secs: 120,
+            started_at: start,
+            last_seen_at: at(400),
+            finished_at: None,
+            result_id: None,
+        };
+        assert_eq!(remaining_secs(&attempt, at(400)), 320);
+        assert_eq!(remaining_secs(&attempt, at(720)), 0);
+        assert!(remaining_secs(&attempt, at(800)) < 0);
+    }
+
+    #[test]
+    fn test_role_requirements() {
+        use crate::authz::{Admin, Moderator, Permission, RoleRequirement};
+        use crate::models::UserRole;
+
+        assert!(Admin::allows(&UserRole::Admin));
+        assert!(!Admin::allows(&UserRole::Moderator));
+        assert!(!Admin::allows(&UserRole::User));
+
+        assert!(Moderator::allows(&UserRole::Moderator));
+        assert!(Moderator::allows(&UserRole::Admin));
+        assert!(!Moderator::allows(&UserRole::User));
+
+        // Модератор разбирает предложения, но не управляет контентом и пользователями
+        assert!(!UserRole::Moderator.has_permission(Permission::ManageContent));
+        assert!(!UserRole::Moderator.has_permission(Permission::ManageUsers));
+        assert!(!UserRole::Moderator.has_permission(Permission::ManageSystem));
+        assert_eq!(UserRole::Moderator.to_string(), "moderator");
+
+        // Экстрактор и группы роутов смотрят в одну таблицу прав
+        for role in [UserRole::Admin, UserRole::Moderator, UserRole::User] {
+            assert_eq!(Admin::allows(&role), role.has_permission(Permission::ManageSystem));
+            assert_eq!(Moderator::allows(&role), role.has_permission(Permission::ModerateContributions));
+        }
+    }
+
+    #[test]
+    fn test_forwarded_client_ignores_forged_entries() {
+        use crate::registration::forwarded_client;
+        use std::net::IpAddr;
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        // Клиент прислал свой X-Forwarded-For, прокси дописал реальный адрес справа
+        assert_eq!(forwarded_client("1.2.3.4, 203.0.113.7", 1), Some(ip("203.0.113.7")));
+        assert_eq!(forwarded_client("203.0.113.7", 1), Some(ip("203.0.113.7")));
+        // За двумя прокси правая запись — адрес внешнего прокси, клиент левее
+        assert_eq!(forwarded_client("1.2.3.4, 203.0.113.7, 10.0.0.2", 2), Some(ip("203.0.113.7")));
+        // Записей меньше, чем прокси: цепочке верить нельзя
+        assert_eq!(forwarded_client("203.0.113.7", 2), None);
+        assert_eq!(forwarded_client("", 1), None);
+        assert_eq!(forwarded_client("1.2.3.4, garbage", 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_recompute_lesson_completion() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let nickname = "test_recompute_lessons";
+        create_user_and_login(&app, &pool, nickname, "user").await;
+        let user_id: i32 = sqlx::query_scalar("SELECT id FROM users WHERE nickname = $1")
+            .bind(nickname)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let hieroglyph_id: i32 = sqlx::query_scalar(
+            "INSERT INTO hieroglyphs (character, pinyin, translation) VALUES ('课', 'kè', 'урок') RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let lesson_id: i32 = sqlx::query_scalar("INSERT INTO lessons (title) VALUES ('Пересчет') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO lesson_items (lesson_id, content_type, content_id, position) VALUES ($1, 'hieroglyph', $2, 0)")
+            .bind(lesson_id)
+            .bind(hieroglyph_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Элемент выучен по отдельности, сам урок не отмечен
+        sqlx::query("INSERT INTO user_progress (user_id, content_type, content_id, is_learned) VALUES ($1, 'hieroglyph', $2, TRUE)")
+            .bind(user_id)
+            .bind(hieroglyph_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        crate::lessons::recompute_completions(pool.clone(), 0).await.unwrap();
+
+        let completed: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM user_progress WHERE user_id = $1 AND content_type = 'lesson' AND content_id = $2)",
+        )
+            .bind(user_id)
+            .bind(lesson_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(completed);
+        let xp: i64 = sqlx::query_scalar("SELECT xp FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(xp, crate::gamification::XP_PER_LESSON as i64);
+
+        // Очистка
+        sqlx::query("DELETE FROM lessons WHERE id = $1").bind(lesson_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM hieroglyphs WHERE id = $1").bind(hieroglyph_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_rsa_jwk_components_rejects_malformed_keys() {
+        use crate::jwt_keys::{rsa_jwk_components, JwtKey};
+
+        let public_pem = include_str!("../resources/test_keys/rs256.pub.pem");
+        let (n, e) = rsa_jwk_components(public_pem).unwrap();
+        assert_eq!(e, "AQAB");
+        // base64url без выравнивания и без ведущего нулевого байта модуля
+        assert!(!n.contains(['=', '+', '/']));
+        assert!(!n.starts_with("AA"));
+
+        let armored = |body: &str| format!("-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n", body);
+        let body: String = public_pem.lines().filter(|line| !line.starts_with("-----")).collect();
+
+        // Испорченный PEM: нет заголовков или тело не base64
+        assert!(rsa_jwk_components(&body).is_err());
+        assert!(rsa_jwk_components(&armored("не base64!")).is_err());
+        // Корректный PEM с испорченным DER: мусор и обрезанный ключ
+        assert!(rsa_jwk_components(&armored("AAECAwQFBgcI")).is_err());
+        assert!(rsa_jwk_components(&armored(&body[..body.len() / 8 * 4])).is_err());
+        assert!(JwtKey::rs256("truncated", &armored(&body[..body.len() / 8 * 4]), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_route_groups_enforce_permissions() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let user_token = create_user_and_login(&app, &pool, "test_perm_user", "user").await;
+        let moderator_token = create_user_and_login(&app, &pool, "test_perm_moderator", "moderator").await;
+        let admin_token = create_user_and_login(&app, &pool, "test_perm_admin", "admin").await;
+
+        // Управление пользователями и управление контентом
+        let routes = ["/api/admin/users?q=test_perm", "/api/hieroglyphs/export"];
+        let status = |uri: &'static str, token: Option<String>| {
+            let app = app.clone();
+            async move {
+                let mut request = Request::builder().method(Method::GET).uri(uri);
+                if let Some(token) = token {
+                    request = request.header("Authorization", format!("Bearer {}", token));
+                }
+                app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+            }
+        };
+
+        for uri in routes {
+            assert_eq!(status(uri, None).await, StatusCode::UNAUTHORIZED, "{}", uri);
+            assert_eq!(status(uri, Some(user_token.clone())).await, StatusCode::FORBIDDEN, "{}", uri);
+            assert_eq!(status(uri, Some(moderator_token.clone())).await, StatusCode::FORBIDDEN, "{}", uri);
+            assert_eq!(status(uri, Some(admin_token.clone())).await, StatusCode::OK, "{}", uri);
+        }
+
+        // Очистка
+        sqlx::query("DELETE FROM users WHERE nickname LIKE 'test_perm_%'").execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_email_verification_tokens() {
+        use crate::email_verification;
+        use crate::password_reset::token_hash;
+
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let nickname = "test_verify_email";
+        let token = create_user_and_login(&app, &pool, nickname, "user").await;
+        let user_id: i32 = sqlx::query_scalar("UPDATE users SET email = 'pupil@school.edu' WHERE nickname = $1 RETURNING id")
+            .bind(nickname)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        // Повторная отправка в течение минуты не плодит коды
+        email_verification::send(user_id, &pool).await.unwrap();
+        let response = app.clone().oneshot(Request::builder()
+            .method(Method::POST)
+            .uri("/api/verify-email/resend")
+            .header("Authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let codes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM email_verification_tokens WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(codes, 1);
+
+        // Код из письма известен только пользователю, поэтому подставляем свой
+        let issue = |code: &'static str, email: &'static str, expires_in_hours: i32| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query(
+                    "INSERT INTO email_verification_tokens (token_hash, user_id, email, expires_at)
+                     VALUES ($1, $2, $3, NOW() + make_interval(hours => $4::int))",
+                )
+                    .bind(token_hash(code))
+                    .bind(user_id)
+                    .bind(email)
+                    .bind(expires_in_hours)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+        };
+        let verify = |code: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(Request::builder()
+                    .method(Method::POST)
+                    .uri("/api/verify-email")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::json!({ "token": code }).to_string()))
+                    .unwrap()
+                ).await.unwrap();
+                let status = response.status();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                (status, body["code"].as_str().map(str::to_string))
+            }
+        };
+        let verified = || {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, bool>("SELECT email_verified_at IS NOT NULL FROM users WHERE id = $1")
+                    .bind(user_id)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            }
+        };
+        let invalid = (StatusCode::BAD_REQUEST, Some("invalid_verification_token".to_string()));
+
+        // Просроченный код и код на почту, которую уже сменили, не подходят
+        issue("expired-code", "pupil@school.edu", -1).await;
+        assert_eq!(verify("expired-code").await, invalid);
+        issue("old-address-code", "old@school.edu", 1).await;
+        assert_eq!(verify("old-address-code").await, invalid);
+        assert!(!verified().await);
+
+        // Верный код подтверждает почту (регистр адреса не важен) и больше не действует
+        issue("valid-code", "Pupil@School.edu", 1).await;
+        assert_eq!(verify(" valid-code ").await.0, StatusCode::OK);
+        assert!(verified().await);
+        assert_eq!(verify("valid-code").await, invalid);
+
+        // Очистка
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_orphan_media_is_hidden() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let token = create_user_and_login(&app, &pool, "test_media_user", "user").await;
+
+        // Файл, который ни к чему не приложен (например, владелец удален)
+        let media_id: i32 = sqlx::query_scalar(
+            "INSERT INTO media (file_name, mime_type, size_bytes) VALUES ('test_orphan', 'image/png', 0) RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        for token in [None, Some(token)] {
+            let mut request = Request::builder().method(Method::GET).uri(format!("/api/media/{}", media_id));
+            if let Some(token) = token {
+                request = request.header("Authorization", format!("Bearer {}", token));
+            }
+            let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        // Очистка
+        sqlx::query("DELETE FROM media WHERE id = $1").bind(media_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE nickname = 'test_media_user'").execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_rejects_nickname_in_password() {
+        use crate::password_reset::{reset, token_hash};
+
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let nickname = "resetnick";
+        create_user_and_login(&app, &pool, nickname, "user").await;
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (token_hash, user_id, expires_at)
+             SELECT $1, id, NOW() + INTERVAL '10 minutes' FROM users WHERE nickname = $2",
+        )
+            .bind(token_hash("reset-code"))
+            .bind(nickname)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Никнейм владельца токена в пароле не допускается, а токен остается действующим
+        let err = reset("reset-code", "My-ResetNick-2026!", &pool).await.unwrap_err();
+        assert_eq!(err.code(), Some("weak_password"));
+        reset("reset-code", "Correct-Horse-Battery-9", &pool).await.unwrap();
+        assert!(reset("reset-code", "Correct-Horse-Battery-9", &pool).await.is_err());
+
+        // Очистка
+        sqlx::query("DELETE FROM users WHERE nickname = $1").bind(nickname).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stale_attempt_expires_on_restart() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let token = create_user_and_login(&app, &pool, "test_attempt_stale", "user").await;
+        let test_id: i32 = sqlx::query_scalar(
+            "INSERT INTO tests (name, description, time_limit_secs, pause_allowance_secs) VALUES ('Брошенная попытка', '', 600, 60) RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let start = || {
+            let app = app.clone();
+            let token = token.clone();
+            async move {
+                let response = app.oneshot(Request::builder()
+                    .method(Method::POST)
+                    .uri(format!("/api/tests/{}/attempts", test_id))
+                    .header("Authorization", format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap()
+                ).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let first = start().await;
+        let first_id = first["id"].as_i64().unwrap() as i32;
+        // Пока время не вышло, повторный старт продолжает ту же попытку
+        assert_eq!(start().await["id"], first["id"]);
+
+        // Ученик ушел на час: время и запас пауз (600 + 60 с) давно исчерпаны
+        sqlx::query("UPDATE test_attempts SET started_at = NOW() - INTERVAL '1 hour', last_seen_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+            .bind(first_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let second = start().await;
+        assert_ne!(second["id"], first["id"]);
+        assert_eq!(second["remaining_secs"], 600);
+
+        let finished: bool = sqlx::query_scalar("SELECT finished_at IS NOT NULL FROM test_attempts WHERE id = $1")
+            .bind(first_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(finished);
+        let kinds: Vec<String> = sqlx::query_scalar("SELECT kind FROM test_attempt_events WHERE attempt_id = $1 ORDER BY id")
+            .bind(first_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(kinds, ["started", "expired"]);
+
+        // Очистка
+        sqlx::query("DELETE FROM tests WHERE id = $1").bind(test_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE nickname = 'test_attempt_stale'").execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grant_allowance_requires_teacher() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let student_token = create_user_and_login(&app, &pool, "test_allowance_student", "user").await;
+        let other_token = create_user_and_login(&app, &pool, "test_allowance_other", "user").await;
+        let test_id: i32 = sqlx::query_scalar(
+            "INSERT INTO tests (name, description, time_limit_secs, pause_allowance_secs) VALUES ('Продление', '', 600, 0) RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let response = app.clone().oneshot(Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/tests/{}/attempts", test_id))
+            .header("Authorization", format!("Bearer {}", student_token))
+            .body(Body::empty())
+            .unwrap()
+        ).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let attempt_id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"].as_i64().unwrap();
+
+        // Ни сам ученик, ни посторонний пользователь не учителя: продлевать запас им нельзя
+        for token in [&student_token, &other_token] {
+            let response = app.clone().oneshot(Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/test-attempts/{}/pause-allowance", attempt_id))
+                .header("Authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "seconds": 300 }).to_string()))
+                .unwrap()
+            ).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let allowance: i32 = sqlx::query_scalar("SELECT pause_allowance_secs FROM test_attempts WHERE id = $1")
+            .bind(attempt_id as i32)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(allowance, 0);
+
+        // Очистка
+        sqlx::query("DELETE FROM tests WHERE id = $1").bind(test_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE nickname LIKE 'test_allowance_%'").execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_keybinding_overrides() {
+        use crate::keybindings::{BindingsFile, KeyBindings, ReviewAction, TestAction};
+        use std::collections::HashMap;
+
+        // Переназначенное действие теряет клавиши по умолчанию, остальные их сохраняют
+        let overrides = HashMap::from([(ReviewAction::Good, vec!["g".to_string()])]);
+        let keys = KeyBindings::with_overrides(overrides);
+        assert_eq!(keys.action("g"), Some(ReviewAction::Good));
+        assert_eq!(keys.action("3"), None);
+        assert_eq!(keys.action("k"), None);
+        assert_eq!(keys.action("1"), Some(ReviewAction::Again));
+        assert_eq!(keys.action("\n"), Some(ReviewAction::Flip));
+
+        // Клавиша другого действия переходит к переназначенному
+        let overrides = HashMap::from([(ReviewAction::Skip, vec!["u".to_string()])]);
+        let keys = KeyBindings::with_overrides(overrides);
+        assert_eq!(keys.action("u"), Some(ReviewAction::Skip));
+        assert_eq!(keys.action("s"), None);
+
+        // Разделы файла относятся каждый к своему экрану
+        let file = BindingsFile::parse(br#"{"review": {"undo": ["z"]}, "test": {"submit": ["\n"]}}"#);
+        let review = KeyBindings::with_overrides(file.review);
+        let test = KeyBindings::with_overrides(file.test);
+        assert_eq!(review.action("z"), Some(ReviewAction::Undo));
+        assert_eq!(test.action("\n"), Some(TestAction::Submit));
+        assert_eq!(test.action("n"), Some(TestAction::Next));
+        assert_eq!(test.action("2"), Some(TestAction::Choice2));
+    }
+
+    #[test]
+    fn test_malformed_keybindings_fall_back_to_defaults() {
+        use crate::keybindings::{BindingsFile, KeyBindings, ReviewAction, TestAction};
+
+        // Битый JSON, неизвестное действие и неверный тип значения
+        let files: [&[u8]; 4] = [b"{\"review\": ", br#"{"review": {"jump": ["j"]}}"#, br#"{"test": {"skip": "s"}}"#, b""];
+        for bytes in files {
+            let file = BindingsFile::parse(bytes);
+            assert!(file.review.is_empty() && file.test.is_empty());
+            let review = KeyBindings::with_overrides(file.review);
+            let test = KeyBindings::with_overrides(file.test);
+            assert_eq!(review.action("j"), Some(ReviewAction::Hard));
+            assert_eq!(review.action(" "), Some(ReviewAction::Flip));
+            assert_eq!(test.action("s"), Some(TestAction::Skip));
+            assert_eq!(test.action("f"), Some(TestAction::Submit));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restricted_class_sees_allowed_decks_and_lessons() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let teacher_token = create_user_and_login(&app, &pool, "test_restrict_teacher", "user").await;
+        let student_token = create_user_and_login(&app, &pool, "test_restrict_student", "user").await;
+        let outsider_token = create_user_and_login(&app, &pool, "test_restrict_outsider", "user").await;
+
+        let class_id: i32 = sqlx::query_scalar(
+            "INSERT INTO classes (name, teacher_id) SELECT 'Ограниченный', id FROM users WHERE nickname = 'test_restrict_teacher' RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO class_members (class_id, user_id) SELECT $1, id FROM users WHERE nickname = 'test_restrict_student'")
+            .bind(class_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let deck_ids: Vec<i32> = sqlx::query_scalar(
+            "INSERT INTO decks (name) VALUES ('test_restrict_allowed'), ('test_restrict_hidden') RETURNING id",
+        )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let lesson_ids: Vec<i32> = sqlx::query_scalar(
+            "INSERT INTO lessons (title) VALUES ('test_restrict_allowed'), ('test_restrict_hidden') RETURNING id",
+        )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        // Учитель разрешает классу по одной колоде и одному уроку
+        let response = app.clone().oneshot(Request::builder()
+            .method(Method::PUT)
+            .uri(format!("/api/classes/{}/content", class_id))
+            .header("Authorization", format!("Bearer {}", teacher_token))
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({
+                "restrict_content": true,
+                "test_ids": [],
+                "deck_ids": [deck_ids[0]],
+                "lesson_ids": [lesson_ids[0]],
+            }).to_string()))
+            .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let names = |uri: &'static str, field: &'static str, token: String| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(Request::builder()
+                    .method(Method::GET)
+                    .uri(uri)
+                    .header("Authorization", format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap()
+                ).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let items: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+                let mut names: Vec<String> = items.iter().filter_map(|item| item[field].as_str().map(str::to_string)).collect();
+                names.retain(|name| name.starts_with("test_restrict_"));
+                names
+            }
+        };
+
+        // Ученик видит только разрешенное, пользователь вне класса — все
+        for (uri, field) in [("/api/decks", "name"), ("/api/lessons", "title")] {
+            assert_eq!(names(uri, field, student_token.clone()).await, ["test_restrict_allowed"], "{}", uri);
+            assert_eq!(names(uri, field, outsider_token.clone()).await.len(), 2, "{}", uri);
+        }
+
+        // Очистка
+        sqlx::query("DELETE FROM decks WHERE id = ANY($1)").bind(&deck_ids).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM lessons WHERE id = ANY($1)").bind(&lesson_ids).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM classes WHERE id = $1").bind(class_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE nickname LIKE 'test_restrict_%'").execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_dictation_matching() {
+        use crate::matching::{grade, normalize_hanzi, normalize_pinyin, MatchKind};
+
+        // Пробелы, пунктуация и полноширинные символы не важны
+        assert_eq!(grade("你好！", "nǐ hǎo", "你 好"), MatchKind::Exact);
+        assert_eq!(grade("你好", "nǐ hǎo", "你好。"), MatchKind::Exact);
+        assert_eq!(normalize_hanzi("ＡＢＣ，１２３"), "abc123");
+
+        // Пиньинь без иероглифов засчитывается наполовину, тоны не учитываются
+        assert_eq!(normalize_pinyin("Nǐ hǎo"), "nihao");
+        assert_eq!(normalize_pinyin("ni3 hao3"), "nihao");
+        assert_eq!(normalize_pinyin("lǜ"), normalize_pinyin("lü"));
+        assert_eq!(grade("你好", "nǐ hǎo", "ni3hao3"), MatchKind::PinyinOnly);
+        assert_eq!(MatchKind::PinyinOnly.credit(), 0.5);
+
+        // Смесь иероглифов и латиницы не сравнивается как пиньинь
+        assert_eq!(grade("你好", "nǐ hǎo", "你hao"), MatchKind::Wrong);
+        assert_eq!(grade("你好", "nǐ hǎo", "您好"), MatchKind::Wrong);
+
+        // Пустой ответ или ответ из одной пунктуации не совпадает даже с пустым эталоном
+        assert_eq!(grade("你好", "nǐ hǎo", ""), MatchKind::Wrong);
+        assert_eq!(grade("", "", "  ，。"), MatchKind::Wrong);
+        assert_eq!(grade("。", "", "！"), MatchKind::Wrong);
+        assert_eq!(MatchKind::Wrong.credit(), 0.0);
+    }
+
+    #[test]
+    fn test_achievement_criteria_parsing() {
+        use crate::achievements::{Criteria, TimeWindow};
+
+        let parse = |json: serde_json::Value| serde_json::from_value::<Criteria>(json);
+
+        let criteria = parse(serde_json::json!({
+            "type": "all",
+            "conditions": [
+                {"type": "learned_count", "count": 100, "window": {"kind": "single_day"}},
+                {"type": "any", "conditions": [
+                    {"type": "study_streak", "days": 7},
+                    {"type": "tests_passed", "count": 3}
+                ]}
+            ]
+        })).unwrap();
+        let Criteria::All { conditions } = criteria else { panic!("ожидалось all") };
+        assert!(matches!(
+            conditions[0],
+            Criteria::LearnedCount { content_type: None, count: 100, window: Some(TimeWindow::SingleDay) }
+        ));
+        let Criteria::Any { conditions: nested } = &conditions[1] else { panic!("ожидалось any") };
+        assert!(matches!(nested[0], Criteria::StudyStreak { days: 7 }));
+        // Порог результата по умолчанию — любой результат
+        assert!(matches!(nested[1], Criteria::TestsPassed { count: 3, min_score_percent } if min_score_percent == 0.0));
+
+        // Неизвестные виды условий и окон, а также условия без обязательных полей отклоняются
+        assert!(parse(serde_json::json!({"type": "xp_total", "count": 10})).is_err());
+        assert!(parse(serde_json::json!({"type": "learned_count", "count": 5, "window": {"kind": "weekly"}})).is_err());
+        assert!(parse(serde_json::json!({"type": "all", "conditions": [{"type": "unknown"}]})).is_err());
+        assert!(parse(serde_json::json!({"type": "study_streak"})).is_err());
+        assert!(parse(serde_json::json!({"count": 5})).is_err());
+        assert!(parse(serde_json::json!({})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_achievement_criteria_evaluation() {
+        use crate::achievements::{is_satisfied, Criteria};
+
+        let pool = setup_test_pool().await;
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (nickname, password_hash, role) VALUES ('test_criteria_user', '', 'user') RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        // Три иероглифа выучены сегодня, занятия три дня подряд
+        sqlx::query(
+            "INSERT INTO user_progress (user_id, content_type, content_id, is_learned, learned_at)
+             SELECT $1, 'hieroglyph', id, TRUE, NOW() FROM generate_series(1, 3) AS id",
+        )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO study_time (user_id, day, activity, seconds)
+             SELECT $1, CURRENT_DATE - offset_days, 'review', 60 FROM generate_series(0, 2) AS offset_days",
+        )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let check = |json: serde_json::Value| {
+            let pool = pool.clone();
+            async move {
+                let criteria: Criteria = serde_json::from_value(json).unwrap();
+                is_satisfied(user_id, &criteria, &pool).await.unwrap()
+            }
+        };
+        let learned = |count: i64| serde_json::json!({"type": "learned_count", "count": count});
+        let streak = |days: i64| serde_json::json!({"type": "study_streak", "days": days});
+
+        assert!(check(learned(3)).await);
+        assert!(!check(learned(4)).await);
+        assert!(check(serde_json::json!({"type": "learned_count", "count": 3, "window": {"kind": "single_day"}})).await);
+        assert!(check(serde_json::json!({"type": "learned_count", "count": 3, "window": {"kind": "last_days", "days": 7}})).await);
+        assert!(!check(serde_json::json!({"type": "learned_count", "content_type": "word", "count": 1})).await);
+        assert!(check(streak(3)).await);
+        assert!(!check(streak(4)).await);
+
+        assert!(check(serde_json::json!({"type": "all", "conditions": [learned(3), streak(3)]})).await);
+        assert!(!check(serde_json::json!({"type": "all", "conditions": [learned(3), streak(4)]})).await);
+        assert!(check(serde_json::json!({"type": "any", "conditions": [learned(4), streak(3)]})).await);
+        assert!(!check(serde_json::json!({"type": "any", "conditions": [learned(4), streak(4)]})).await);
+        // Пустое «все» выполнено, пустое «хотя бы одно» — нет
+        assert!(check(serde_json::json!({"type": "all", "conditions": []})).await);
+        assert!(!check(serde_json::json!({"type": "any", "conditions": []})).await);
+
+        // Очистка
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bulk_dry_run_and_commit() {
+        use crate::bulk::execute;
+        use crate::models::BulkPayload;
+
+        let pool = setup_test_pool().await;
+        let word_id: i32 = sqlx::query_scalar(
+            "INSERT INTO words (simplified, pinyin, translation) VALUES ('测试批量', 'cèshì pīliàng', 'тест') RETURNING id",
+        )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let payload = |dry_run: bool, level: i16| -> BulkPayload {
+            serde_json::from_value(serde_json::json!({
+                "dry_run": dry_run,
+                "operations": [
+                    {"op": "retag", "target": "words", "ids": [word_id], "add": ["verb"]},
+                    {"op": "set_hsk_level", "ids": [word_id], "level": level}
+                ]
+            })).unwrap()
+        };
+        let row = || {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_as::<_, (Option<i16>, Vec<String>)>("SELECT hsk_level, tags FROM words WHERE id = $1")
+                    .bind(word_id)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // Пробный прогон показывает изменения, но не вносит их
+        let (report, _) = execute(payload(true, 3), 0, &pool).await.unwrap();
+        assert!(report.dry_run);
+        let items: Vec<(usize, &str)> = report.changes.iter().map(|c| (c.operation, c.item.as_str())).collect();
+        let key = format!("word:{}", word_id);
+        assert_eq!(items, [(0, key.as_str()), (1, key.as_str())]);
+        assert_eq!(report.changes[0].after, serde_json::json!(["verb"]));
+        assert_eq!(report.changes[1].before, serde_json::Value::Null);
+        assert_eq!(row().await, (None, vec![]));
+
+        // Тот же пакет без dry_run дает тот же отчет и применяется
+        let (report, _) = execute(payload(false, 3), 0, &pool).await.unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(row().await, (Some(3), vec!["verb".to_string()]));
+
+        // Повторный пакет ничего не меняет, и в отчете пусто
+        let (report, _) = execute(payload(false, 3), 0, &pool).await.unwrap();
+        assert!(report.changes.is_empty());
+
+        // Ошибка в любой операции откатывает весь пакет
+        let failing: BulkPayload = serde_json::from_value(serde_json::json!({
+            "operations": [
+                {"op": "retag", "target": "words", "ids": [word_id], "remove": ["verb"]},
+                {"op": "set_hsk_level", "ids": [word_id], "level": 10}
+            ]
+        })).unwrap();
+        assert!(execute(failing, 0, &pool).await.is_err());
+        assert_eq!(row().await, (Some(3), vec!["verb".to_string()]));
+
+        // Пустой пакет отклоняется
+        let empty: BulkPayload = serde_json::from_value(serde_json::json!({"dry_run": true, "operations": []})).unwrap();
+        assert_eq!(execute(empty, 0, &pool).await.unwrap_err().status_code(), StatusCode::BAD_REQUEST);
+
+        // Очистка
+        sqlx::query("DELETE FROM words WHERE id = $1").bind(word_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_result_review() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let admin_token = create_user_and_login(&app, &pool, "test_review_admin", "admin").await;
+        let student_token = create_user_and_login(&app, &pool, "test_review_student", "user").await;
+        let other_token = create_user_and_login(&app, &pool, "test_review_other", "user").await;
+
+        let (status, test) = request_json(&app, Method::POST, "/api/tests", &admin_token, Some(serde_json::json!({
+            "name": "Разбор результата"
+        }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let test_id = test["id"].as_i64().unwrap();
+        let (status, details) = request_json(&app, Method::POST, &format!("/api/tests/{}/items", test_id), &admin_token, Some(serde_json::json!({
+            "items": [
+                {"question": "Как будет «хорошо»?", "options": ["好", "大"], "correct_answer": "好"},
+                {"question": "Как будет «большой»?", "correct_answer": "大"},
+                {"question": "Как будет «три»?", "correct_answer": "三"}
+            ]
+        }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let ids: Vec<i64> = details["questions"].as_array().unwrap().iter().map(|q| q["id"].as_i64().unwrap()).collect();
+
+        // Один верный ответ, один неверный, третий вопрос пропущен
+        let (status, result) = request_json(&app, Method::POST, &format!("/api/tests/{}/submit", test_id), &student_token, Some(serde_json::json!({
+            "answers": [{"question_id": ids[0], "answer": "好"}, {"question_id": ids[1], "answer": "小"}]
+        }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((result["score"].as_i64(), result["total_questions"].as_i64()), (Some(1), Some(3)));
+        let review_uri = format!("/api/tests/{}/results/{}", test_id, result["result_id"]);
+
+        let (status, review) = request_json(&app, Method::GET, &review_uri, &student_token, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(review["total_questions"], 3);
+        let questions = review["questions"].as_array().unwrap();
+        let answers: Vec<(&serde_json::Value, bool, &str)> = questions
+            .iter()
+            .map(|q| (&q["answer"], q["is_correct"].as_bool().unwrap(), q["correct_answer"].as_str().unwrap()))
+            .collect();
+        assert_eq!(answers, [
+            (&serde_json::json!("好"), true, "好"),
+            (&serde_json::json!("小"), false, "大"),
+            (&serde_json::Value::Null, false, "三"),
+        ]);
+
+        // Чужой результат доступен только администратору, результат другого теста не найден
+        assert_eq!(request_json(&app, Method::GET, &review_uri, &other_token, None).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(request_json(&app, Method::GET, &review_uri, &admin_token, None).await.0, StatusCode::OK);
+        let wrong_test_uri = format!("/api/tests/{}/results/{}", test_id + 1_000_000, result["result_id"]);
+        assert_eq!(request_json(&app, Method::GET, &wrong_test_uri, &student_token, None).await.0, StatusCode::NOT_FOUND);
+
+        // Пустая отправка: все вопросы в разборе без ответа
+        let (_, empty) = request_json(&app, Method::POST, &format!("/api/tests/{}/submit", test_id), &student_token, Some(serde_json::json!({
+            "answers": []
+        }))).await;
+        assert_eq!(empty["score"], 0);
+        let review_uri = format!("/api/tests/{}/results/{}", test_id, empty["result_id"]);
+        let (_, review) = request_json(&app, Method::GET, &review_uri, &student_token, None).await;
+        let questions = review["questions"].as_array().unwrap();
+        assert_eq!(questions.len(), 3);
+        assert!(questions.iter().all(|q| q["answer"].is_null() && q["is_correct"] == false));
+
+        // Очистка
+        sqlx::query("DELETE FROM test_results WHERE test_id = $1").bind(test_id as i32).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM tests WHERE id = $1").bind(test_id as i32).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE nickname LIKE 'test_review_%'").execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_explanation_hidden_until_submitted() {
+        let pool = setup_test_pool().await;
+        let app_state = AppState { db_pool: pool.clone() };
+        let app = app(app_state);
+        let admin_token = create_user_and_login(&app, &pool, "test_explain_admin", "admin").await;
+        let student_token = create_user_and_login(&app, &pool, "test_explain_student", "user").await;
+
+        let (_, test) = request_json(&app, Method::POST, "/api/tests", &admin_token, Some(serde_json::json!({
+            "name": "Пояснения"
+        }))).await;
+        let test_id = test["id"].as_i64().unwrap();
+        let (status, details) = request_json(&app, Method::POST, &format!("/api/tests/{}/items", test_id), &admin_token, Some(serde_json::json!({
+            "items": [
+                {"question": "Как будет «хорошо»?", "correct_answer": "好", "explanation": "  女 + 子: женщина с ребёнком  "},
+                {"question": "Как будет «большой»?", "correct_answer": "大", "explanation": "   "},
+                {"question": "Как будет «три»?", "correct_answer": "三"}
+            ]
+        }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let ids: Vec<i64> = details["questions"].as_array().unwrap().iter().map(|q| q["id"].as_i64().unwrap()).collect();
+
+        // До отправки пояснений нет ни в одном вопросе
+        let (status, test) = request_json(&app, Method::GET, &format!("/api/tests/{}", test_id), &student_token, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(test["questions"].as_array().unwrap().iter().all(|q| q.get("explanation").is_none()));
+
+        // После отправки пояснение обрезано, пустое и отсутствующее хранятся как NULL
+        let (_, result) = request_json(&app, Method::POST, &format!("/api/tests/{}/submit", test_id), &student_token, Some(serde_json::json!({
+            "answers": [{"question_id": ids[0], "answer": "好"}]
+        }))).await;
+        let review_uri = format!("/api/tests/{}/results/{}", test_id, result["result_id"]);
+        let (status, review) = request_json(&app, Method::GET, &review_uri, &student_token, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let explanations: Vec<&serde_json::Value> = review["questions"].as_array().unwrap().iter().map(|q| &q["explanation"]).collect();
+        assert_eq!(explanations, [
+            &serde_json::json!("女 + 子: женщина с ребёнком"),
+            &serde_json::Value::Null,
+            &serde_json::Value::Null,
+        ]);
+
+        // Очистка
+        sqlx::query("DELETE FROM test_results WHERE test_id = $1").bind(test_id as i32).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM tests WHERE id = $1").bind(test_id as i32).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE nickname LIKE 'test_explain_%'").execute(&pool).await.unwrap();
+    }
+}
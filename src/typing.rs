@@ -0,0 +1,176 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::errors::AppError;
+use crate::matching::normalize_hanzi;
+use crate::models::{ContentType, TypingPrompt, TypingRun, TypingRunPayload, TypingRunResult, TypingStats};
+
+/// Размер захода, если клиент его не указал.
+pub const DEFAULT_DRILL_SIZE: u32 = 20;
+/// Максимум заданий в одном заходе.
+pub const MAX_DRILL_SIZE: u32 = 50;
+/// Более долгая пауза на одном задании засчитывается как эта: отошедший
+/// от компьютера пользователь не должен обнулять скорость всего захода.
+pub const MAX_ANSWER_MS: u32 = 60_000;
+/// Заход с меньшей точностью не претендует на личный рекорд скорости.
+pub const MIN_BEST_ACCURACY: f32 = 90.0;
+/// Сколько последних заходов показывать в статистике.
+const RECENT_RUNS: i64 = 10;
+
+/// Верно ли набрано задание: сравнение без пробелов и пунктуации.
+pub fn is_correct(typed: &str, expected: &str) -> bool {
+    let typed = normalize_hanzi(typed);
+    !typed.is_empty() && typed == normalize_hanzi(expected)
+}
+
+/// Скорость в иероглифах в минуту.
+pub fn chars_per_minute(chars: usize, duration_ms: u64) -> f32 {
+    if duration_ms == 0 {
+        return 0.0;
+    }
+    chars as f32 * 60_000.0 / duration_ms as f32
+}
+
+/// Задания для захода: сначала выученные слова и иероглифы, которые пользователь
+/// должен уметь набрать, затем, если их не хватает, слова HSK 1.
+pub async fn drill(user_id: i32, size: Option<u32>, pool: &PgPool) -> Result<Vec<TypingPrompt>, AppError> {
+    let size = size.unwrap_or(DEFAULT_DRILL_SIZE);
+    if size == 0 || size > MAX_DRILL_SIZE {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Размер захода должен быть от 1 до {}", MAX_DRILL_SIZE),
+        ));
+    }
+
+    let prompts = sqlx::query_as::<_, TypingPrompt>(
+        "SELECT content_type, content_id, text, pinyin, translation FROM (
+             SELECT up.content_type, w.id AS content_id, w.simplified AS text, w.pinyin, w.translation, 0 AS priority
+             FROM user_progress up
+             JOIN words w ON up.content_type = 'word' AND w.id = up.content_id
+             WHERE up.user_id = $1 AND up.is_learned
+             UNION ALL
+             SELECT up.content_type, h.id, h.character, h.pinyin, h.translation, 0
+             FROM user_progress up
+             JOIN hieroglyphs h ON up.content_type = 'hieroglyph' AND h.id = up.content_id
+             WHERE up.user_id = $1 AND up.is_learned
+             UNION ALL
+             SELECT 'word'::content_type_enum, w.id, w.simplified, w.pinyin, w.translation, 1
+             FROM words w
+             WHERE w.hsk_level = 1 AND NOT EXISTS (
+                 SELECT 1 FROM user_progress up
+                 WHERE up.user_id = $1 AND up.content_type = 'word' AND up.content_id = w.id AND up.is_learned
+             )
+         ) items
+         WHERE pinyin IS NOT NULL AND pinyin <> ''
+         ORDER BY priority, random()
+         LIMIT $2",
+    )
+        .bind(user_id)
+        .bind(size as i64)
+        .fetch_all(pool)
+        .await?;
+    Ok(prompts)
+}
+
+/// Ожидаемые ответы для заданий захода.
+async fn expected_texts(payload: &TypingRunPayload, pool: &PgPool) -> Result<HashMap<(ContentType, i32), String>, AppError> {
+    let ids_of = |content_type: ContentType| -> Vec<i32> {
+        payload.answers.iter().filter(|a| a.content_type == content_type).map(|a| a.content_id).collect()
+    };
+
+    let rows: Vec<(ContentType, i32, String)> = sqlx::query_as(
+        "SELECT 'word'::content_type_enum, id, simplified FROM words WHERE id = ANY($1)
+         UNION ALL
+         SELECT 'hieroglyph'::content_type_enum, id, character FROM hieroglyphs WHERE id = ANY($2)",
+    )
+        .bind(ids_of(ContentType::Word))
+        .bind(ids_of(ContentType::Hieroglyph))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(content_type, id, text)| ((content_type, id), text)).collect())
+}
+
+/// Проверяет ответы захода, сохраняет его и сравнивает с личным рекордом.
+/// Правильность определяет сервер, а не клиент.
+pub async fn submit(user_id: i32, payload: &TypingRunPayload, pool: &PgPool) -> Result<TypingRunResult, AppError> {
+    if payload.answers.is_empty() || payload.answers.len() > MAX_DRILL_SIZE as usize {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("В заходе должно быть от 1 до {} ответов", MAX_DRILL_SIZE),
+        ));
+    }
+
+    let expected = expected_texts(payload, pool).await?;
+    let mut correct_count = 0;
+    let mut char_count = 0;
+    let mut duration_ms: u64 = 0;
+    for answer in &payload.answers {
+        let text = expected
+            .get(&(answer.content_type.clone(), answer.content_id))
+            .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Задание тренажера не найдено"))?;
+        if answer.elapsed_ms == 0 {
+            return Err(AppError::new(StatusCode::BAD_REQUEST, "Время ответа должно быть положительным"));
+        }
+        duration_ms += answer.elapsed_ms.min(MAX_ANSWER_MS) as u64;
+        if is_correct(&answer.typed, text) {
+            correct_count += 1;
+            char_count += text.chars().count();
+        }
+    }
+
+    let item_count = payload.answers.len();
+    let speed = chars_per_minute(char_count, duration_ms);
+    let accuracy = correct_count as f32 / item_count as f32 * 100.0;
+
+    let previous_best: Option<f32> = sqlx::query_scalar(
+        "SELECT MAX(chars_per_minute) FROM typing_runs WHERE user_id = $1 AND accuracy >= $2",
+    )
+        .bind(user_id)
+        .bind(MIN_BEST_ACCURACY)
+        .fetch_one(pool)
+        .await?;
+
+    let run = sqlx::query_as::<_, TypingRun>(
+        "INSERT INTO typing_runs (user_id, item_count, correct_count, char_count, duration_ms, chars_per_minute, accuracy)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING id, item_count, correct_count, char_count, duration_ms, chars_per_minute, accuracy, created_at",
+    )
+        .bind(user_id)
+        .bind(item_count as i32)
+        .bind(correct_count)
+        .bind(char_count as i32)
+        .bind(duration_ms as i32)
+        .bind(speed)
+        .bind(accuracy)
+        .fetch_one(pool)
+        .await?;
+
+    let new_best = accuracy >= MIN_BEST_ACCURACY && previous_best.is_none_or(|best| speed > best);
+    Ok(TypingRunResult { run, previous_best, new_best })
+}
+
+/// Личные рекорды и последние заходы пользователя.
+pub async fn stats(user_id: i32, pool: &PgPool) -> Result<TypingStats, AppError> {
+    let (run_count, best_chars_per_minute, best_accuracy): (i64, Option<f32>, Option<f32>) = sqlx::query_as(
+        "SELECT COUNT(*), MAX(chars_per_minute) FILTER (WHERE accuracy >= $2), MAX(accuracy)
+         FROM typing_runs WHERE user_id = $1",
+    )
+        .bind(user_id)
+        .bind(MIN_BEST_ACCURACY)
+        .fetch_one(pool)
+        .await?;
+
+    let recent = sqlx::query_as::<_, TypingRun>(
+        "SELECT id, item_count, correct_count, char_count, duration_ms, chars_per_minute, accuracy, created_at
+         FROM typing_runs WHERE user_id = $1
+         ORDER BY created_at DESC, id DESC
+         LIMIT $2",
+    )
+        .bind(user_id)
+        .bind(RECENT_RUNS)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(TypingStats { run_count, best_chars_per_minute, best_accuracy, recent })
+}
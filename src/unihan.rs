@@ -0,0 +1,170 @@
+use axum::async_trait;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::errors::AppError;
+use crate::importer::{ImportPlan, Importer, Prepared};
+use crate::jobs;
+use crate::models::{Hieroglyph, ImportAction, ImportFormat, ImportPreview, ImportResult};
+
+/// Поля Unihan, которые переносятся в иероглифы.
+#[derive(Debug, Default, Clone)]
+struct UnihanEntry {
+    definition: Option<String>,
+    readings: Option<String>,
+    total_strokes: Option<i16>,
+    radical_index: Option<i16>,
+}
+
+/// Изменения одного иероглифа по данным Unihan (`None` — поле не меняется).
+#[derive(Debug, Clone)]
+struct UnihanChange {
+    hieroglyph_id: i32,
+    definition: Option<String>,
+    readings: Option<String>,
+    total_strokes: Option<i16>,
+    radical_index: Option<i16>,
+}
+
+impl UnihanChange {
+    /// Измененные поля для предпросмотра; пусто, если менять нечего.
+    fn describe(&self) -> Vec<String> {
+        let mut fields = Vec::new();
+        if let Some(definition) = &self.definition {
+            fields.push(format!("определение: {}", definition));
+        }
+        if let Some(readings) = &self.readings {
+            fields.push(format!("чтения: {}", readings));
+        }
+        if let Some(total_strokes) = self.total_strokes {
+            fields.push(format!("черт: {}", total_strokes));
+        }
+        if let Some(radical_index) = self.radical_index {
+            fields.push(format!("ключ: {}", radical_index));
+        }
+        fields
+    }
+}
+
+/// Разбирает файлы Unihan (`U+4E00<TAB>kDefinition<TAB>...`), оставляя только нужные поля.
+fn parse(data: &str) -> HashMap<String, UnihanEntry> {
+    let mut entries: HashMap<String, UnihanEntry> = HashMap::new();
+
+    for line in data.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(3, '\t');
+        let (Some(code), Some(field), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let Some(character) = code
+            .strip_prefix("U+")
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+        else {
+            continue;
+        };
+
+        let entry = entries.entry(character.to_string()).or_default();
+        match field {
+            "kDefinition" => entry.definition = Some(value.to_string()),
+            "kMandarin" => entry.readings = Some(value.to_string()),
+            // Может содержать несколько значений через пробел, берем первое
+            "kTotalStrokes" => entry.total_strokes = value.split_whitespace().next().and_then(|v| v.parse().ok()),
+            // Формат «радикал.остаток», у упрощенных радикалов после номера стоит апостроф
+            "kRSUnicode" => {
+                entry.radical_index = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|rs| rs.split('.').next())
+                    .and_then(|radical| radical.trim_end_matches('\'').parse().ok())
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+/// Импорт Unihan: сопоставляет данные с существующими иероглифами и дополняет их.
+/// Новые иероглифы не создаются.
+pub struct UnihanImporter;
+
+#[async_trait]
+impl Importer for UnihanImporter {
+    async fn prepare(&self, data: &str, pool: &PgPool, job_id: u64) -> Result<Prepared, AppError> {
+        let entries = parse(data);
+        let hieroglyphs = sqlx::query_as::<_, Hieroglyph>("SELECT * FROM hieroglyphs ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+
+        jobs::set_total(job_id, hieroglyphs.len());
+        let mut preview = ImportPreview::new(ImportFormat::Unihan);
+        let mut changes = Vec::new();
+
+        for hieroglyph in hieroglyphs {
+            if let Some(entry) = entries.get(&hieroglyph.character) {
+                let change = UnihanChange {
+                    hieroglyph_id: hieroglyph.id,
+                    definition: diff(&hieroglyph.definition, &entry.definition),
+                    readings: diff(&hieroglyph.readings, &entry.readings),
+                    total_strokes: diff(&hieroglyph.total_strokes, &entry.total_strokes),
+                    radical_index: diff(&hieroglyph.radical_index, &entry.radical_index),
+                };
+                let fields = change.describe();
+                if fields.is_empty() {
+                    preview.push(None, Some(&hieroglyph.character), ImportAction::Unchanged, None);
+                } else {
+                    preview.push(None, Some(&hieroglyph.character), ImportAction::Update, Some(fields.join("; ")));
+                    changes.push(change);
+                }
+            }
+            jobs::advance(job_id);
+        }
+
+        Ok(Prepared { preview, plan: Box::new(UnihanPlan { changes }) })
+    }
+}
+
+/// Возвращает новое значение, только если оно есть и отличается от текущего.
+fn diff<T: Clone + PartialEq>(current: &Option<T>, new: &Option<T>) -> Option<T> {
+    match new {
+        Some(value) if current.as_ref() != Some(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+struct UnihanPlan {
+    changes: Vec<UnihanChange>,
+}
+
+#[async_trait]
+impl ImportPlan for UnihanPlan {
+    /// Применяет изменения одной транзакцией.
+    async fn apply(self: Box<Self>, pool: &PgPool, job_id: u64) -> Result<ImportResult, AppError> {
+        jobs::set_total(job_id, self.changes.len());
+        let mut tx = pool.begin().await?;
+        for change in &self.changes {
+            sqlx::query(
+                "UPDATE hieroglyphs SET
+                     definition = COALESCE($1, definition),
+                     readings = COALESCE($2, readings),
+                     total_strokes = COALESCE($3, total_strokes),
+                     radical_index = COALESCE($4, radical_index)
+                 WHERE id = $5",
+            )
+                .bind(&change.definition)
+                .bind(&change.readings)
+                .bind(change.total_strokes)
+                .bind(change.radical_index)
+                .bind(change.hieroglyph_id)
+                .execute(&mut *tx)
+                .await?;
+            jobs::advance(job_id);
+        }
+        tx.commit().await?;
+
+        Ok(ImportResult { updated: self.changes.len(), ..ImportResult::default() })
+    }
+}
@@ -0,0 +1,164 @@
+use once_cell::sync::Lazy;
+use printpdf::{Color, Greyscale, IndirectFontRef, Line, LineDashPattern, Mm, PdfDocument, PdfLayerReference, Point};
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+
+use crate::errors::AppError;
+use crate::models::Hieroglyph;
+use axum::http::StatusCode;
+
+// --- Разметка листа A4 (в миллиметрах) ---
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const CELL: f32 = 18.0;
+const COLUMNS: usize = 10;
+const LABEL_HEIGHT: f32 = 6.0;
+const ROW_HEIGHT: f32 = CELL + LABEL_HEIGHT;
+/// Сколько клеток после образца заполнено бледным иероглифом для обводки.
+const TRACING_CELLS: usize = 4;
+const GLYPH_FONT_SIZE: f32 = 40.0;
+const LABEL_FONT_SIZE: f32 = 9.0;
+
+/// Максимум прописей в кеше; при переполнении кеш очищается целиком.
+const CACHE_CAPACITY: usize = 32;
+
+static CACHE: Lazy<Mutex<HashMap<String, Arc<Vec<u8>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Ключ кеша строится из самого содержимого, чтобы правка иероглифа не отдавала устаревший PDF.
+fn cache_key(hieroglyphs: &[Hieroglyph]) -> String {
+    hieroglyphs
+        .iter()
+        .map(|h| format!("{}|{}|{}", h.character, h.pinyin, h.translation))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Возвращает PDF с прописями для выбранных иероглифов (из кеша или генерирует заново).
+pub async fn render(hieroglyphs: Vec<Hieroglyph>) -> Result<Arc<Vec<u8>>, AppError> {
+    let key = cache_key(&hieroglyphs);
+    if let Some(pdf) = CACHE.lock().unwrap().get(&key) {
+        return Ok(pdf.clone());
+    }
+
+    let font_path = env::var("WORKSHEET_FONT_PATH").map_err(|_| {
+        AppError::new(StatusCode::SERVICE_UNAVAILABLE, "Шрифт для прописей не настроен")
+    })?;
+
+    // printpdf работает синхронно, поэтому генерация вынесена из async-контекста
+    let pdf = tokio::task::spawn_blocking(move || build_pdf(&hieroglyphs, &font_path))
+        .await
+        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Не удалось сформировать PDF"))??;
+    let pdf = Arc::new(pdf);
+
+    let mut cache = CACHE.lock().unwrap();
+    if cache.len() >= CACHE_CAPACITY {
+        cache.clear();
+    }
+    cache.insert(key, pdf.clone());
+
+    Ok(pdf)
+}
+
+fn pdf_error(err: printpdf::Error) -> AppError {
+    tracing::error!("Ошибка генерации PDF: {:?}", err);
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Не удалось сформировать PDF")
+}
+
+fn build_pdf(hieroglyphs: &[Hieroglyph], font_path: &str) -> Result<Vec<u8>, AppError> {
+    let (doc, first_page, first_layer) =
+        PdfDocument::new("Прописи", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Прописи");
+    let font = doc
+        .add_external_font(BufReader::new(File::open(font_path)?))
+        .map_err(pdf_error)?;
+
+    let rows_per_page = ((PAGE_HEIGHT - 2.0 * MARGIN) / ROW_HEIGHT) as usize;
+    let mut layer = doc.get_page(first_page).get_layer(first_layer);
+
+    for (index, hieroglyph) in hieroglyphs.iter().enumerate() {
+        let row = index % rows_per_page;
+        if row == 0 && index > 0 {
+            let (page, page_layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Прописи");
+            layer = doc.get_page(page).get_layer(page_layer);
+        }
+
+        let top = PAGE_HEIGHT - MARGIN - row as f32 * ROW_HEIGHT;
+        draw_row(&layer, &font, hieroglyph, top);
+    }
+
+    doc.save_to_bytes().map_err(pdf_error)
+}
+
+/// Рисует строку прописи: подпись, образец и клетки для обводки и самостоятельного письма.
+fn draw_row(layer: &PdfLayerReference, font: &IndirectFontRef, hieroglyph: &Hieroglyph, top: f32) {
+    let label = format!("{}  {}", hieroglyph.pinyin, hieroglyph.translation);
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+    layer.use_text(label, LABEL_FONT_SIZE, Mm(MARGIN), Mm(top - LABEL_HEIGHT + 1.5), font);
+
+    // TODO: миниатюры порядка черт в подписи, когда появятся данные о чертах
+    let cell_top = top - LABEL_HEIGHT;
+    let cell_bottom = cell_top - CELL;
+    // Кегль в пунктах переводим в миллиметры, чтобы отцентрировать знак в клетке
+    let glyph_size = GLYPH_FONT_SIZE * 25.4 / 72.0;
+
+    for column in 0..COLUMNS {
+        let left = MARGIN + column as f32 * CELL;
+        draw_cell(layer, left, cell_bottom);
+
+        let shade = match column {
+            0 => Some(0.0),
+            c if c <= TRACING_CELLS => Some(0.8),
+            _ => None,
+        };
+        if let Some(shade) = shade {
+            layer.set_fill_color(Color::Greyscale(Greyscale::new(shade, None)));
+            layer.use_text(
+                hieroglyph.character.clone(),
+                GLYPH_FONT_SIZE,
+                Mm(left + (CELL - glyph_size) / 2.0),
+                Mm(cell_bottom + (CELL - glyph_size) / 2.0 + glyph_size * 0.12),
+                font,
+            );
+        }
+    }
+}
+
+/// Рисует клетку с пунктирным крестом-направляющей (田字格).
+fn draw_cell(layer: &PdfLayerReference, left: f32, bottom: f32) {
+    let right = left + CELL;
+    let top = bottom + CELL;
+    let middle_x = left + CELL / 2.0;
+    let middle_y = bottom + CELL / 2.0;
+
+    layer.set_outline_color(Color::Greyscale(Greyscale::new(0.5, None)));
+    layer.set_outline_thickness(0.6);
+    layer.set_line_dash_pattern(LineDashPattern::default());
+    layer.add_line(Line {
+        points: vec![
+            (Point::new(Mm(left), Mm(bottom)), false),
+            (Point::new(Mm(right), Mm(bottom)), false),
+            (Point::new(Mm(right), Mm(top)), false),
+            (Point::new(Mm(left), Mm(top)), false),
+        ],
+        is_closed: true,
+    });
+
+    layer.set_outline_color(Color::Greyscale(Greyscale::new(0.75, None)));
+    layer.set_outline_thickness(0.3);
+    layer.set_line_dash_pattern(LineDashPattern { dash_1: Some(2), ..Default::default() });
+    for (from, to) in [
+        ((middle_x, bottom), (middle_x, top)),
+        ((left, middle_y), (right, middle_y)),
+    ] {
+        layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(from.0), Mm(from.1)), false),
+                (Point::new(Mm(to.0), Mm(to.1)), false),
+            ],
+            is_closed: false,
+        });
+    }
+}